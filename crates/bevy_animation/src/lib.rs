@@ -9,6 +9,7 @@
 
 mod animatable;
 mod graph;
+mod property;
 mod transition;
 mod util;
 
@@ -24,7 +25,7 @@ use bevy_core::Name;
 use bevy_ecs::entity::MapEntities;
 use bevy_ecs::prelude::*;
 use bevy_ecs::reflect::ReflectMapEntities;
-use bevy_math::{FloatExt, Quat, Vec3};
+use bevy_math::{FloatExt, Quat, Vec3, Vec4};
 use bevy_reflect::Reflect;
 use bevy_render::mesh::morph::MorphWeights;
 use bevy_time::Time;
@@ -46,11 +47,13 @@ use uuid::Uuid;
 pub mod prelude {
     #[doc(hidden)]
     pub use crate::{
-        animatable::*, graph::*, transition::*, AnimationClip, AnimationPlayer, AnimationPlugin,
-        Interpolation, Keyframes, VariableCurve,
+        animatable::*, graph::*, property::*, transition::*, AnimationClip, AnimationPlayer,
+        AnimationPlugin, Interpolation, Keyframes, VariableCurve,
     };
 }
 
+pub use property::{AnimatableProperty, AnimatedProperties};
+
 use crate::transition::{advance_transitions, expire_completed_transitions};
 
 /// The [UUID namespace] of animation targets (e.g. bones).
@@ -58,7 +61,8 @@ use crate::transition::{advance_transitions, expire_completed_transitions};
 /// [UUID namespace]: https://en.wikipedia.org/wiki/Universally_unique_identifier#Versions_3_and_5_(namespace_name-based)
 pub static ANIMATION_TARGET_NAMESPACE: Uuid = Uuid::from_u128(0x3179f519d9274ff2b5966fd077023911);
 
-/// List of keyframes for one of the attribute of a [`Transform`].
+/// List of keyframes for one of the attribute of a [`Transform`], for morph
+/// weights, or for an [`AnimatableProperty`].
 #[derive(Reflect, Clone, Debug)]
 pub enum Keyframes {
     /// Keyframes for rotation.
@@ -76,6 +80,9 @@ pub enum Keyframes {
     ///
     /// [glTF design]: https://registry.khronos.org/glTF/specs/2.0/glTF-2.0.html#animations
     Weights(Vec<f32>),
+    /// Keyframes for an [`AnimatableProperty`], written to the
+    /// [`AnimatedProperties`] of the target.
+    Property(AnimatableProperty, Vec<Vec4>),
 }
 
 impl Keyframes {
@@ -85,6 +92,7 @@ impl Keyframes {
             Keyframes::Weights(vec) => vec.len(),
             Keyframes::Translation(vec) | Keyframes::Scale(vec) => vec.len(),
             Keyframes::Rotation(vec) => vec.len(),
+            Keyframes::Property(_, vec) => vec.len(),
         }
     }
 
@@ -94,7 +102,8 @@ impl Keyframes {
     }
 }
 
-/// Describes how an attribute of a [`Transform`] or [`MorphWeights`] should be animated.
+/// Describes how an attribute of a [`Transform`], [`MorphWeights`] or
+/// [`AnimatedProperties`] should be animated.
 ///
 /// `keyframe_timestamps` and `keyframes` should have the same length.
 #[derive(Reflect, Clone, Debug)]
@@ -523,6 +532,7 @@ struct AnimationTargetContext<'a> {
     name: Option<&'a Name>,
     transform: Option<Mut<'a, Transform>>,
     morph_weights: Option<Mut<'a, MorphWeights>>,
+    properties: Option<Mut<'a, AnimatedProperties>>,
 }
 
 /// Information needed during the traversal of the animation graph in
@@ -771,7 +781,7 @@ pub fn animate_targets(
         Entity,
         &AnimationTarget,
         Option<&Name>,
-        AnyOf<(&mut Transform, &mut MorphWeights)>,
+        AnyOf<(&mut Transform, &mut MorphWeights, &mut AnimatedProperties)>,
     )>,
 ) {
     // We use two queries here: one read-only query for animation players and
@@ -780,9 +790,8 @@ pub fn animate_targets(
     // animation targets, which are evaluated in parallel.
 
     // Iterate over all animation targets in parallel.
-    targets
-        .par_iter_mut()
-        .for_each(|(id, target, name, (transform, morph_weights))| {
+    targets.par_iter_mut().for_each(
+        |(id, target, name, (transform, morph_weights, properties))| {
             let Ok((animation_player, animation_graph_handle)) = players.get(target.player) else {
                 trace!(
                     "Either an animation player {:?} or a graph was missing for the target \
@@ -805,6 +814,7 @@ pub fn animate_targets(
                 name,
                 transform,
                 morph_weights,
+                properties,
             };
 
             // Apply the animations one after another. The way we accumulate
@@ -848,7 +858,8 @@ pub fn animate_targets(
 
                 target_context.apply(curves, weight / total_weight, active_animation.seek_time);
             }
-        });
+        },
+    );
 }
 
 impl AnimationTargetContext<'_> {
@@ -918,6 +929,10 @@ impl AnimationTargetContext<'_> {
                     weight,
                 );
             }
+
+            Keyframes::Property(property, keyframes) => {
+                self.apply_property(property, keyframes[0], weight);
+            }
         }
     }
 
@@ -1101,8 +1116,46 @@ impl AnimationTargetContext<'_> {
                     );
                 lerp_morph_weights(morphs.weights_mut(), result, weight);
             }
+
+            (Interpolation::Step, Keyframes::Property(property, keyframes)) => {
+                self.apply_property(property, keyframes[step_start], weight);
+            }
+
+            (Interpolation::Linear, Keyframes::Property(property, keyframes)) => {
+                let result = keyframes[step_start].lerp(keyframes[step_start + 1], lerp);
+                self.apply_property(property, result, weight);
+            }
+
+            (Interpolation::CubicSpline, Keyframes::Property(property, keyframes)) => {
+                let value_start = keyframes[step_start * 3 + 1];
+                let tangent_out_start = keyframes[step_start * 3 + 2];
+                let tangent_in_end = keyframes[(step_start + 1) * 3];
+                let value_end = keyframes[(step_start + 1) * 3 + 1];
+                let result = cubic_spline_interpolation(
+                    value_start,
+                    tangent_out_start,
+                    tangent_in_end,
+                    value_end,
+                    lerp,
+                    duration,
+                );
+                self.apply_property(property, result, weight);
+            }
         }
     }
+
+    fn apply_property(&mut self, property: &AnimatableProperty, value: Vec4, weight: f32) {
+        let Some(ref mut properties) = self.properties else {
+            error!(
+                "Tried to animate {:?} on {:?} ({:?}), but no `AnimatedProperties` was found",
+                property.name(),
+                self.entity,
+                self.name,
+            );
+            return;
+        };
+        properties.blend(property, value, weight);
+    }
 }
 
 /// Update `weights` based on weights in `keyframe` with a linear interpolation
@@ -1160,6 +1213,7 @@ impl Plugin for AnimationPlugin {
             .register_asset_reflect::<AnimationGraph>()
             .register_type::<AnimationPlayer>()
             .register_type::<AnimationTarget>()
+            .register_type::<AnimatedProperties>()
             .register_type::<AnimationTransitions>()
             .register_type::<NodeIndex>()
             .add_systems(
@@ -1225,8 +1279,13 @@ impl AnimationGraphEvaluator {
 
 #[cfg(test)]
 mod tests {
-    use crate::VariableCurve;
-    use bevy_math::Vec3;
+    use crate::{
+        AnimatableProperty, AnimatedProperties, AnimationTarget, AnimationTargetContext,
+        AnimationTargetId, Interpolation, Keyframes, VariableCurve,
+    };
+    use bevy_ecs::world::World;
+    use bevy_math::{Vec3, Vec4};
+    use uuid::Uuid;
 
     fn test_variable_curve() -> VariableCurve {
         let keyframe_timestamps = vec![1.0, 2.0, 3.0, 4.0];
@@ -1370,4 +1429,43 @@ mod tests {
             assert!(exact_keyframe == inexact_keyframe);
         }
     }
+
+    #[test]
+    fn property_curves_are_written_to_animated_properties() {
+        let curve = VariableCurve {
+            keyframe_timestamps: vec![0.0, 1.0],
+            keyframes: Keyframes::Property(
+                AnimatableProperty::new("base_color"),
+                vec![Vec4::ZERO, Vec4::ONE],
+            ),
+            interpolation: Interpolation::Linear,
+        };
+
+        let mut world = World::new();
+        let entity = world.spawn(AnimatedProperties::default()).id();
+        let target = AnimationTarget {
+            id: AnimationTargetId(Uuid::nil()),
+            player: entity,
+        };
+        let mut context = AnimationTargetContext {
+            entity,
+            target: &target,
+            name: None,
+            transform: None,
+            morph_weights: None,
+            properties: world.get_mut::<AnimatedProperties>(entity),
+        };
+
+        // The first clip sets the value, the next ones blend into it.
+        context.apply(std::slice::from_ref(&curve), 1.0, 0.5);
+        assert_eq!(
+            context.properties.as_ref().unwrap().get("base_color"),
+            Some(Vec4::splat(0.5))
+        );
+        context.apply(std::slice::from_ref(&curve), 0.5, 0.0);
+        assert_eq!(
+            context.properties.as_ref().unwrap().get("base_color"),
+            Some(Vec4::splat(0.25))
+        );
+    }
 }
@@ -0,0 +1,74 @@
+//! Animation of properties other than transforms and morph weights.
+
+use std::{borrow::Borrow, borrow::Cow};
+
+use bevy_ecs::{component::Component, reflect::ReflectComponent};
+use bevy_math::Vec4;
+use bevy_reflect::{std_traits::ReflectDefault, Reflect};
+use bevy_utils::HashMap;
+
+/// The name of a value of an animation target, other than its [`Transform`]
+/// and [`MorphWeights`], animated by [`Keyframes::Property`] curves.
+///
+/// Animated properties are written to the [`AnimatedProperties`] of the target,
+/// where the plugins owning them read them. For example, `bevy_pbr` writes the
+/// properties named after fields of the material table data of a material,
+/// such as `base_color`, to the material of the target.
+///
+/// [`Transform`]: bevy_transform::components::Transform
+/// [`MorphWeights`]: bevy_render::mesh::morph::MorphWeights
+/// [`Keyframes::Property`]: crate::Keyframes::Property
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Reflect)]
+pub struct AnimatableProperty(pub Cow<'static, str>);
+
+impl AnimatableProperty {
+    /// Creates a property from its name.
+    pub const fn new(name: &'static str) -> Self {
+        Self(Cow::Borrowed(name))
+    }
+
+    /// Returns the name of the property.
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for AnimatableProperty {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+/// The current value of the [`AnimatableProperty`]s of an animation target.
+///
+/// Add this component to the targets of clips with [`Keyframes::Property`]
+/// curves. Values have up to four components: a scalar is stored in `x`, and a
+/// color in linear RGBA.
+///
+/// [`Keyframes::Property`]: crate::Keyframes::Property
+#[derive(Component, Reflect, Clone, Debug, Default)]
+#[reflect(Component, Default)]
+pub struct AnimatedProperties(HashMap<AnimatableProperty, Vec4>);
+
+impl AnimatedProperties {
+    /// Returns the value of the property named `name`, if it was animated.
+    pub fn get(&self, name: &str) -> Option<Vec4> {
+        self.0.get(name).copied()
+    }
+
+    /// Iterates over the animated properties and their value.
+    pub fn iter(&self) -> impl Iterator<Item = (&AnimatableProperty, Vec4)> {
+        self.0.iter().map(|(property, value)| (property, *value))
+    }
+
+    /// Blends the value of `property` toward `value` by `weight`, or sets it to
+    /// `value` if it wasn't animated yet.
+    pub(crate) fn blend(&mut self, property: &AnimatableProperty, value: Vec4, weight: f32) {
+        match self.0.get_mut(property) {
+            Some(current) => *current = current.lerp(value, weight),
+            None => {
+                self.0.insert(property.clone(), value);
+            }
+        }
+    }
+}
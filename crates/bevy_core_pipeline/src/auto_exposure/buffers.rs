@@ -2,6 +2,7 @@ use bevy_ecs::prelude::*;
 use bevy_render::{
     render_resource::{StorageBuffer, UniformBuffer},
     renderer::{RenderDevice, RenderQueue},
+    view::ViewHistoryTextures,
     Extract,
 };
 use bevy_utils::{Entry, HashMap};
@@ -17,6 +18,9 @@ pub(super) struct AutoExposureBuffers {
 pub(super) struct AutoExposureBuffer {
     pub(super) state: StorageBuffer<f32>,
     pub(super) settings: UniformBuffer<AutoExposureSettingsUniform>,
+    /// The state the exposure starts adapting from, and is reset to along with
+    /// the history of the view.
+    initial_state: f32,
 }
 
 #[derive(Resource)]
@@ -44,6 +48,7 @@ pub(super) fn prepare_buffers(
     queue: Res<RenderQueue>,
     mut extracted: ResMut<ExtractedStateBuffers>,
     mut buffers: ResMut<AutoExposureBuffers>,
+    view_histories: Res<ViewHistoryTextures>,
 ) {
     for (entity, settings) in extracted.changed.drain(..) {
         let (min_log_lum, max_log_lum) = settings.range.into_inner();
@@ -66,6 +71,7 @@ pub(super) fn prepare_buffers(
                 // Update the settings buffer, but skip updating the state buffer.
                 // The state buffer is skipped so that the animation stays continuous.
                 let value = entry.get_mut();
+                value.initial_state = initial_state;
                 value.settings.set(settings);
                 value.settings.write_buffer(&device, &queue);
             }
@@ -73,6 +79,7 @@ pub(super) fn prepare_buffers(
                 let value = entry.insert(AutoExposureBuffer {
                     state: StorageBuffer::from(initial_state),
                     settings: UniformBuffer::from(settings),
+                    initial_state,
                 });
 
                 value.state.write_buffer(&device, &queue);
@@ -84,4 +91,13 @@ pub(super) fn prepare_buffers(
     for entity in extracted.removed.drain(..) {
         buffers.buffers.remove(&entity);
     }
+
+    // Camera cuts snap the exposure back instead of animating from the exposure
+    // of the previous shot.
+    for (entity, value) in &mut buffers.buffers {
+        if view_histories.is_reset(*entity) {
+            value.state.set(value.initial_state);
+            value.state.write_buffer(&device, &queue);
+        }
+    }
 }
@@ -100,4 +100,9 @@ impl SpecializedRenderPipeline for BlitPipeline {
             push_constant_ranges: Vec::new(),
         }
     }
+
+    fn priority(&self, _key: &Self::Key) -> PipelinePriority {
+        // Nothing reaches the window until this pipeline is compiled.
+        PipelinePriority::High
+    }
 }
@@ -81,7 +81,7 @@ impl Plugin for BloomPlugin {
             .add_render_graph_node::<ViewNodeRunner<BloomNode>>(Core2d, Node2d::Bloom)
             .add_render_graph_edges(
                 Core2d,
-                (Node2d::MainPass, Node2d::Bloom, Node2d::Tonemapping),
+                (Node2d::PartialRedraw, Node2d::Bloom, Node2d::Tonemapping),
             );
     }
 
@@ -360,12 +360,13 @@ fn prepare_bloom_textures(
                 not(target_arch = "wasm32"),
                 feature = "webgpu"
             ))]
-            let texture = texture_cache.get(&render_device, texture_descriptor);
+            let texture = texture_cache.get_for_view(&render_device, entity, texture_descriptor);
             #[cfg(all(feature = "webgl", target_arch = "wasm32", not(feature = "webgpu")))]
             let texture: Vec<CachedTexture> = (0..mip_count)
                 .map(|mip| {
-                    texture_cache.get(
+                    texture_cache.get_for_view(
                         &render_device,
+                        entity,
                         TextureDescriptor {
                             size: Extent3d {
                                 width: (texture_descriptor.size.width >> mip).max(1),
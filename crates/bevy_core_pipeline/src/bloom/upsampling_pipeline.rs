@@ -14,7 +14,7 @@ use bevy_render::{
         *,
     },
     renderer::RenderDevice,
-    view::ViewTarget,
+    view::{ExtractedView, HdrFormat},
 };
 
 #[derive(Component)]
@@ -32,6 +32,7 @@ pub struct BloomUpsamplingPipeline {
 pub struct BloomUpsamplingPipelineKeys {
     composite_mode: BloomCompositeMode,
     final_pipeline: bool,
+    hdr_format: HdrFormat,
 }
 
 impl FromWorld for BloomUpsamplingPipeline {
@@ -62,7 +63,7 @@ impl SpecializedRenderPipeline for BloomUpsamplingPipeline {
 
     fn specialize(&self, key: Self::Key) -> RenderPipelineDescriptor {
         let texture_format = if key.final_pipeline {
-            ViewTarget::TEXTURE_FORMAT_HDR
+            key.hdr_format.texture_format()
         } else {
             BLOOM_TEXTURE_FORMAT
         };
@@ -133,15 +134,16 @@ pub fn prepare_upsampling_pipeline(
     pipeline_cache: Res<PipelineCache>,
     mut pipelines: ResMut<SpecializedRenderPipelines<BloomUpsamplingPipeline>>,
     pipeline: Res<BloomUpsamplingPipeline>,
-    views: Query<(Entity, &BloomSettings)>,
+    views: Query<(Entity, &ExtractedView, &BloomSettings)>,
 ) {
-    for (entity, settings) in &views {
+    for (entity, view, settings) in &views {
         let pipeline_id = pipelines.specialize(
             &pipeline_cache,
             &pipeline,
             BloomUpsamplingPipelineKeys {
                 composite_mode: settings.composite_mode,
                 final_pipeline: false,
+                hdr_format: view.hdr_format,
             },
         );
 
@@ -151,6 +153,7 @@ pub fn prepare_upsampling_pipeline(
             BloomUpsamplingPipelineKeys {
                 composite_mode: settings.composite_mode,
                 final_pipeline: true,
+                hdr_format: view.hdr_format,
             },
         );
 
@@ -0,0 +1,398 @@
+//! Camera stacking: overlay cameras that render on top of a base camera, into
+//! the same render target.
+//!
+//! Add [`StackedCamera`] to an overlay camera, such as a first person weapon
+//! camera or a 3D HUD, pointing at the base camera of the scene. The overlay
+//! follows the render target, viewport and HDR setting of its base, is ordered
+//! after it, and loads the output of the base instead of clearing it. Whether
+//! the overlay also inherits the depth, exposure and tonemapping of its base is
+//! controlled per overlay.
+//!
+//! With [`Camera::hdr`], tonemapping is a post-processing pass over the whole
+//! main texture shared by the stack, so it must only run once: either the
+//! overlay inherits the tonemapping of its base and applies it to the whole
+//! stack, or its own tonemapping is disabled.
+
+use std::iter;
+
+use bevy_app::{App, Plugin, PostUpdate};
+use bevy_ecs::prelude::*;
+use bevy_reflect::Reflect;
+use bevy_render::{
+    camera::{Camera, CameraUpdateSystem, ClearColorConfig, Exposure},
+    Extract, ExtractSchedule, RenderApp,
+};
+use bevy_utils::HashMap;
+
+use crate::{
+    core_3d::{Camera3d, Camera3dDepthLoadOp},
+    tonemapping::{DebandDither, Tonemapping},
+};
+
+/// Keeps [`StackedCamera`]s in sync with their base camera.
+pub struct CameraStackPlugin;
+
+impl Plugin for CameraStackPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<StackedCamera>()
+            .add_systems(PostUpdate, sync_stacked_cameras.before(CameraUpdateSystem));
+
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app.add_systems(ExtractSchedule, extract_stacked_cameras);
+    }
+}
+
+/// Makes a camera an overlay of another camera, its base, that it renders on
+/// top of. See the [module documentation](self).
+///
+/// Every frame, the overlay gets the [`Camera::target`], [`Camera::viewport`]
+/// and [`Camera::hdr`] of its base, so that they share their main texture. Its
+/// [`Camera::order`] is raised above the base if needed, its
+/// [`Camera::clear_color`] is set to [`ClearColorConfig::None`] and
+/// [`Camera::msaa_writeback`] is enabled, so that every pass of the overlay
+/// loads what the base rendered.
+#[derive(Component, Reflect, Clone, Copy, Debug, PartialEq, Eq)]
+#[reflect(Component, PartialEq)]
+pub struct StackedCamera {
+    /// The camera this camera renders on top of.
+    pub base: Entity,
+    /// Whether the overlay is depth tested against what the base rendered.
+    ///
+    /// When enabled, the [`Camera3d::depth_load_op`] of the overlay is set to
+    /// [`Camera3dDepthLoadOp::Load`], so it's hidden behind the geometry of the
+    /// base, like a 3D gizmo in the scene, and its [`Camera3d::stencil`] is set
+    /// to the one of the base, so that they share their depth texture. When
+    /// disabled, it's set to clear the depth, so the overlay is always drawn on
+    /// top, like a first person weapon. This only affects 3D cameras.
+    pub inherit_depth: bool,
+    /// Whether the overlay uses the [`Exposure`] of the base.
+    pub inherit_exposure: bool,
+    /// Whether the overlay uses the [`Tonemapping`] and [`DebandDither`] of the
+    /// base.
+    ///
+    /// With [`Camera::hdr`], the base then skips its tonemapping pass, and the
+    /// last overlay inheriting it tonemaps the whole stack once. When disabled,
+    /// the tonemapping of the overlay is set to [`Tonemapping::None`] and its
+    /// [`DebandDither`] is disabled instead, so that the image of the base
+    /// isn't tonemapped twice, and the overlay is drawn with its colors as is.
+    ///
+    /// Without [`Camera::hdr`], tonemapping happens while rendering each
+    /// camera, and a non inheriting overlay keeps its own settings.
+    pub inherit_tonemapping: bool,
+}
+
+impl StackedCamera {
+    /// Creates an overlay of `base` that is always drawn on top of it, with
+    /// the exposure of the base and its own colors.
+    pub fn new(base: Entity) -> Self {
+        Self {
+            base,
+            inherit_depth: false,
+            inherit_exposure: true,
+            inherit_tonemapping: false,
+        }
+    }
+
+    /// Sets [`Self::inherit_depth`].
+    pub fn with_inherit_depth(mut self, inherit_depth: bool) -> Self {
+        self.inherit_depth = inherit_depth;
+        self
+    }
+
+    /// Sets [`Self::inherit_exposure`].
+    pub fn with_inherit_exposure(mut self, inherit_exposure: bool) -> Self {
+        self.inherit_exposure = inherit_exposure;
+        self
+    }
+
+    /// Sets [`Self::inherit_tonemapping`].
+    pub fn with_inherit_tonemapping(mut self, inherit_tonemapping: bool) -> Self {
+        self.inherit_tonemapping = inherit_tonemapping;
+        self
+    }
+}
+
+/// The settings a [`StackedCamera`] can inherit from its base.
+type InheritedSettings = (
+    Option<&'static Exposure>,
+    Option<&'static Tonemapping>,
+    Option<&'static DebandDither>,
+);
+
+/// Copies the settings of the base of every [`StackedCamera`] to the overlay.
+///
+/// Settings are only written when they differ, so that cameras aren't marked as
+/// changed every frame.
+pub fn sync_stacked_cameras(
+    mut commands: Commands,
+    stacked_cameras: Query<(Entity, &StackedCamera)>,
+    mut cameras: Query<(&mut Camera, Option<&mut Camera3d>)>,
+    settings: Query<InheritedSettings, With<Camera>>,
+) {
+    for (entity, stacked_camera) in &stacked_cameras {
+        if entity == stacked_camera.base {
+            continue;
+        }
+        let Ok([(base, base_3d), (mut overlay, overlay_3d)]) =
+            cameras.get_many_mut([stacked_camera.base, entity])
+        else {
+            continue;
+        };
+
+        if overlay.target.normalize(None) != base.target.normalize(None) {
+            overlay.target = base.target.clone();
+        }
+        if overlay.viewport != base.viewport {
+            overlay.viewport.clone_from(&base.viewport);
+        }
+        if overlay.hdr != base.hdr {
+            overlay.hdr = base.hdr;
+        }
+        if overlay.hdr_format != base.hdr_format {
+            overlay.hdr_format = base.hdr_format;
+        }
+        if overlay.order <= base.order {
+            overlay.order = base.order + 1;
+        }
+        if !matches!(overlay.clear_color, ClearColorConfig::None) {
+            overlay.clear_color = ClearColorConfig::None;
+        }
+        if !overlay.msaa_writeback {
+            overlay.msaa_writeback = true;
+        }
+
+        if let Some(mut overlay_3d) = overlay_3d {
+            match (stacked_camera.inherit_depth, &overlay_3d.depth_load_op) {
+                (true, Camera3dDepthLoadOp::Load) | (false, Camera3dDepthLoadOp::Clear(_)) => {}
+                (true, _) => overlay_3d.depth_load_op = Camera3dDepthLoadOp::Load,
+                (false, _) => overlay_3d.depth_load_op = Camera3dDepthLoadOp::default(),
+            }
+            // The depth textures are shared by format, so the overlay must use
+            // the format of the base to load its depth.
+            let base_stencil = base_3d.is_some_and(|base_3d| base_3d.stencil);
+            if stacked_camera.inherit_depth && overlay_3d.stencil != base_stencil {
+                overlay_3d.stencil = base_stencil;
+            }
+        }
+
+        let (Ok(base_settings), Ok(overlay_settings)) =
+            (settings.get(stacked_camera.base), settings.get(entity))
+        else {
+            continue;
+        };
+        let (base_exposure, base_tonemapping, base_deband_dither) = base_settings;
+        let (overlay_exposure, overlay_tonemapping, overlay_deband_dither) = overlay_settings;
+        let mut overlay_commands = commands.entity(entity);
+
+        if stacked_camera.inherit_exposure
+            && base_exposure.map(|exposure| exposure.ev100)
+                != overlay_exposure.map(|exposure| exposure.ev100)
+        {
+            match base_exposure {
+                Some(exposure) => overlay_commands.insert(*exposure),
+                None => overlay_commands.remove::<Exposure>(),
+            };
+        }
+        if stacked_camera.inherit_tonemapping {
+            if base_tonemapping != overlay_tonemapping {
+                match base_tonemapping {
+                    Some(tonemapping) => overlay_commands.insert(*tonemapping),
+                    None => overlay_commands.remove::<Tonemapping>(),
+                };
+            }
+            if base_deband_dither != overlay_deband_dither {
+                match base_deband_dither {
+                    Some(deband_dither) => overlay_commands.insert(*deband_dither),
+                    None => overlay_commands.remove::<DebandDither>(),
+                };
+            }
+        } else if base.hdr {
+            if overlay_tonemapping != Some(&Tonemapping::None) {
+                overlay_commands.insert(Tonemapping::None);
+            }
+            if overlay_deband_dither != Some(&DebandDither::Disabled) {
+                overlay_commands.insert(DebandDither::Disabled);
+            }
+        }
+    }
+}
+
+/// Marks the view of a camera that skips its tonemapping pass, because a
+/// [`StackedCamera`] rendered after it inherits its tonemapping and applies it
+/// to the whole stack.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct TonemappedByOverlay;
+
+/// Adds [`TonemappedByOverlay`] to the views that render before the last HDR
+/// overlay inheriting the tonemapping of their stack.
+pub fn extract_stacked_cameras(
+    mut commands: Commands,
+    stacked_cameras: Extract<Query<(Entity, &StackedCamera, &Camera)>>,
+    cameras: Extract<Query<&Camera>>,
+) {
+    let overlays = stacked_cameras
+        .iter()
+        .filter(|(entity, stacked_camera, overlay)| {
+            *entity != stacked_camera.base
+                && stacked_camera.inherit_tonemapping
+                && overlay.is_active
+                && overlay.hdr
+                && cameras.contains(stacked_camera.base)
+        })
+        .map(|(entity, stacked_camera, overlay)| (stacked_camera.base, entity, overlay.order));
+
+    for entity in views_tonemapped_by_overlay(overlays) {
+        commands.get_or_spawn(entity).insert(TonemappedByOverlay);
+    }
+}
+
+/// Returns the views that skip their tonemapping, given the base, entity and
+/// [`Camera::order`] of every overlay inheriting the tonemapping of its base:
+/// the bases, and all of their overlays but the last one.
+fn views_tonemapped_by_overlay(
+    overlays: impl IntoIterator<Item = (Entity, Entity, isize)>,
+) -> Vec<Entity> {
+    let mut stacks: HashMap<Entity, Vec<(isize, Entity)>> = HashMap::default();
+    for (base, overlay, order) in overlays {
+        stacks.entry(base).or_default().push((order, overlay));
+    }
+
+    stacks
+        .into_iter()
+        .flat_map(|(base, mut overlays)| {
+            overlays.sort_unstable();
+            overlays.pop();
+            iter::once(base).chain(overlays.into_iter().map(|(_, overlay)| overlay))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs::{prelude::*, system::RunSystemOnce};
+    use bevy_render::camera::{Camera, ClearColorConfig};
+
+    use super::{sync_stacked_cameras, views_tonemapped_by_overlay, StackedCamera};
+    use crate::{
+        core_3d::{Camera3d, Camera3dDepthLoadOp},
+        tonemapping::{DebandDither, Tonemapping},
+    };
+
+    fn spawn_stack(
+        world: &mut World,
+        hdr: bool,
+        stacked_camera: impl Fn(Entity) -> StackedCamera,
+    ) -> (Entity, Entity) {
+        let base = world
+            .spawn((
+                Camera {
+                    hdr,
+                    order: 2,
+                    ..Default::default()
+                },
+                Camera3d {
+                    stencil: true,
+                    ..Default::default()
+                },
+                Tonemapping::AcesFitted,
+                DebandDither::Enabled,
+            ))
+            .id();
+        let overlay = world
+            .spawn((
+                Camera::default(),
+                Camera3d::default(),
+                Tonemapping::TonyMcMapface,
+                DebandDither::Enabled,
+                stacked_camera(base),
+            ))
+            .id();
+        world.run_system_once(sync_stacked_cameras);
+        (base, overlay)
+    }
+
+    #[test]
+    fn overlay_loads_the_output_of_its_base() {
+        let mut world = World::new();
+        let (_, overlay) = spawn_stack(&mut world, true, StackedCamera::new);
+
+        let camera = world.get::<Camera>(overlay).unwrap();
+        assert!(camera.hdr);
+        assert_eq!(camera.order, 3);
+        assert!(matches!(camera.clear_color, ClearColorConfig::None));
+        assert!(camera.msaa_writeback);
+
+        let camera_3d = world.get::<Camera3d>(overlay).unwrap();
+        assert!(matches!(
+            camera_3d.depth_load_op,
+            Camera3dDepthLoadOp::Clear(_)
+        ));
+        assert!(!camera_3d.stencil);
+    }
+
+    #[test]
+    fn inherited_depth_shares_the_depth_texture_of_the_base() {
+        let mut world = World::new();
+        let (_, overlay) = spawn_stack(&mut world, true, |base| {
+            StackedCamera::new(base).with_inherit_depth(true)
+        });
+
+        let camera_3d = world.get::<Camera3d>(overlay).unwrap();
+        assert!(matches!(camera_3d.depth_load_op, Camera3dDepthLoadOp::Load));
+        assert!(camera_3d.stencil);
+    }
+
+    #[test]
+    fn hdr_overlay_is_not_tonemapped_twice() {
+        let mut world = World::new();
+        let (_, overlay) = spawn_stack(&mut world, true, StackedCamera::new);
+        assert_eq!(world.get::<Tonemapping>(overlay), Some(&Tonemapping::None));
+        assert_eq!(
+            world.get::<DebandDither>(overlay),
+            Some(&DebandDither::Disabled)
+        );
+
+        let mut world = World::new();
+        let (_, overlay) = spawn_stack(&mut world, false, StackedCamera::new);
+        assert_eq!(
+            world.get::<Tonemapping>(overlay),
+            Some(&Tonemapping::TonyMcMapface)
+        );
+    }
+
+    #[test]
+    fn inherited_tonemapping_is_copied_from_the_base() {
+        let mut world = World::new();
+        let (_, overlay) = spawn_stack(&mut world, true, |base| {
+            StackedCamera::new(base).with_inherit_tonemapping(true)
+        });
+        assert_eq!(
+            world.get::<Tonemapping>(overlay),
+            Some(&Tonemapping::AcesFitted)
+        );
+        assert_eq!(
+            world.get::<DebandDither>(overlay),
+            Some(&DebandDither::Enabled)
+        );
+    }
+
+    #[test]
+    fn only_the_last_inheriting_overlay_tonemaps() {
+        let mut world = World::new();
+        let [base, other_base, first, second, other] = [(); 5].map(|_| world.spawn_empty().id());
+
+        let mut skipped = views_tonemapped_by_overlay([
+            (base, second, 2),
+            (base, first, 1),
+            (other_base, other, 1),
+        ]);
+        skipped.sort_unstable();
+        let mut expected = vec![base, first, other_base];
+        expected.sort_unstable();
+        assert_eq!(skipped, expected);
+
+        assert!(views_tonemapped_by_overlay([]).is_empty());
+    }
+}
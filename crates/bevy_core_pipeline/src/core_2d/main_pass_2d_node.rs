@@ -1,4 +1,4 @@
-use crate::core_2d::Transparent2d;
+use crate::{core_2d::Transparent2d, partial_redraw::ViewPartialRedraw};
 use bevy_ecs::prelude::*;
 use bevy_render::{
     camera::ExtractedCamera,
@@ -18,6 +18,7 @@ pub struct MainPass2dNode {
             &'static ExtractedCamera,
             &'static SortedRenderPhase<Transparent2d>,
             &'static ViewTarget,
+            Option<&'static ViewPartialRedraw>,
         ),
         With<ExtractedView>,
     >,
@@ -43,7 +44,8 @@ impl Node for MainPass2dNode {
         world: &World,
     ) -> Result<(), NodeRunError> {
         let view_entity = graph.view_entity();
-        let Ok((camera, transparent_phase, target)) = self.query.get_manual(world, view_entity)
+        let Ok((camera, transparent_phase, target, partial_redraw)) =
+            self.query.get_manual(world, view_entity)
         else {
             // no target
             return Ok(());
@@ -68,6 +70,9 @@ impl Node for MainPass2dNode {
             if let Some(viewport) = camera.viewport.as_ref() {
                 render_pass.set_camera_viewport(viewport);
             }
+            if let Some(partial_redraw) = partial_redraw {
+                partial_redraw.set_scissor_rect(&mut render_pass);
+            }
 
             transparent_phase.render(&mut render_pass, world, view_entity);
 
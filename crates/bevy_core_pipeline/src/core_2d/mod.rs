@@ -15,6 +15,7 @@ pub mod graph {
     pub enum Node2d {
         MsaaWriteback,
         MainPass,
+        PartialRedraw,
         Bloom,
         Tonemapping,
         Fxaa,
@@ -44,7 +45,9 @@ use bevy_render::{
     Extract, ExtractSchedule, Render, RenderApp, RenderSet,
 };
 
-use crate::{tonemapping::TonemappingNode, upscaling::UpscalingNode};
+use crate::{
+    partial_redraw::PartialRedrawNode, tonemapping::TonemappingNode, upscaling::UpscalingNode,
+};
 
 use self::graph::{Core2d, Node2d};
 
@@ -69,6 +72,10 @@ impl Plugin for Core2dPlugin {
         render_app
             .add_render_sub_graph(Core2d)
             .add_render_graph_node::<MainPass2dNode>(Core2d, Node2d::MainPass)
+            .add_render_graph_node::<ViewNodeRunner<PartialRedrawNode>>(
+                Core2d,
+                Node2d::PartialRedraw,
+            )
             .add_render_graph_node::<ViewNodeRunner<TonemappingNode>>(Core2d, Node2d::Tonemapping)
             .add_render_graph_node::<EmptyNode>(Core2d, Node2d::EndMainPassPostProcessing)
             .add_render_graph_node::<ViewNodeRunner<UpscalingNode>>(Core2d, Node2d::Upscaling)
@@ -76,6 +83,7 @@ impl Plugin for Core2dPlugin {
                 Core2d,
                 (
                     Node2d::MainPass,
+                    Node2d::PartialRedraw,
                     Node2d::Tonemapping,
                     Node2d::EndMainPassPostProcessing,
                     Node2d::Upscaling,
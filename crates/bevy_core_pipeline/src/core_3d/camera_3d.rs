@@ -1,24 +1,25 @@
 use crate::{
-    core_3d::graph::Core3d,
+    core_3d::{graph::Core3d, CORE_3D_DEPTH_FORMAT, CORE_3D_DEPTH_STENCIL_FORMAT},
+    prepass::{DeferredPrepass, DepthPrepass},
     tonemapping::{DebandDither, Tonemapping},
 };
-use bevy_ecs::prelude::*;
+use bevy_ecs::{prelude::*, query::QueryItem};
 use bevy_reflect::{Reflect, ReflectDeserialize, ReflectSerialize};
 use bevy_render::{
     camera::{Camera, CameraMainTextureUsages, CameraRenderGraph, Exposure, Projection},
     extract_component::ExtractComponent,
     primitives::Frustum,
-    render_resource::{LoadOp, TextureUsages},
+    render_resource::{LoadOp, TextureFormat, TextureUsages},
     view::{ColorGrading, VisibleEntities},
 };
 use bevy_transform::prelude::{GlobalTransform, Transform};
+use bevy_utils::warn_once;
 use serde::{Deserialize, Serialize};
 
 /// Configuration for the "main 3d render graph".
 /// The camera coordinate space is right-handed x-right, y-up, z-back.
 /// This means "forward" is -Z.
-#[derive(Component, Reflect, Clone, ExtractComponent)]
-#[extract_component_filter(With<Camera>)]
+#[derive(Component, Reflect, Clone)]
 #[reflect(Component)]
 pub struct Camera3d {
     /// The depth clear operation to perform for the main 3d pass.
@@ -50,6 +51,17 @@ pub struct Camera3d {
     ///
     /// **Note:** You can get better-looking results at any quality level by enabling TAA. See: [`TemporalAntiAliasPlugin`](crate::experimental::taa::TemporalAntiAliasPlugin).
     pub screen_space_specular_transmission_quality: ScreenSpaceTransmissionQuality,
+    /// Whether the depth texture of the main 3d passes has a stencil, which materials can test
+    /// and write to, for effects like portals, masked mirrors and x-ray. Defaults to `false`.
+    ///
+    /// The stencil is cleared to `0` along with the depth, according to [`Self::depth_load_op`].
+    /// Enabling it switches the depth texture to [`CORE_3D_DEPTH_STENCIL_FORMAT`], which has less
+    /// depth precision than the default [`CORE_3D_DEPTH_FORMAT`].
+    ///
+    /// The stencil isn't supported together with a [`DepthPrepass`] or [`DeferredPrepass`], whose
+    /// depth is copied from the main depth texture into a [`CORE_3D_DEPTH_FORMAT`] texture, and is
+    /// ignored with a warning on such cameras.
+    pub stencil: bool,
 }
 
 impl Default for Camera3d {
@@ -59,6 +71,38 @@ impl Default for Camera3d {
             depth_texture_usages: TextureUsages::RENDER_ATTACHMENT.into(),
             screen_space_specular_transmission_steps: 1,
             screen_space_specular_transmission_quality: Default::default(),
+            stencil: false,
+        }
+    }
+}
+
+impl ExtractComponent for Camera3d {
+    type QueryData = (&'static Self, Has<DepthPrepass>, Has<DeferredPrepass>);
+    type QueryFilter = With<Camera>;
+    type Out = Self;
+
+    fn extract_component(
+        (camera_3d, depth_prepass, deferred_prepass): QueryItem<'_, Self::QueryData>,
+    ) -> Option<Self::Out> {
+        let mut camera_3d = camera_3d.clone();
+        if camera_3d.stencil && (depth_prepass || deferred_prepass) {
+            warn_once!(
+                "Camera3d::stencil isn't supported with a DepthPrepass or DeferredPrepass, and is \
+                ignored on those cameras."
+            );
+            camera_3d.stencil = false;
+        }
+        Some(camera_3d)
+    }
+}
+
+impl Camera3d {
+    /// The format of the depth texture of the main 3d passes.
+    pub fn depth_format(&self) -> TextureFormat {
+        if self.stencil {
+            CORE_3D_DEPTH_STENCIL_FORMAT
+        } else {
+            CORE_3D_DEPTH_FORMAT
         }
     }
 }
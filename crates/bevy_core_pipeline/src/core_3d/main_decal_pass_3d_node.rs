@@ -0,0 +1,69 @@
+use crate::{core_3d::Decal3d, partial_redraw::ViewPartialRedraw};
+use bevy_ecs::{prelude::*, query::QueryItem};
+use bevy_render::{
+    camera::ExtractedCamera,
+    diagnostic::RecordDiagnostics,
+    render_graph::{NodeRunError, RenderGraphContext, ViewNode},
+    render_phase::SortedRenderPhase,
+    render_resource::{RenderPassDescriptor, StoreOp},
+    renderer::RenderContext,
+    view::{ViewDepthTexture, ViewTarget},
+};
+#[cfg(feature = "trace")]
+use bevy_utils::tracing::info_span;
+
+/// A [`bevy_render::render_graph::Node`] that runs the [`Decal3d`]
+/// [`SortedRenderPhase`], after the opaque pass.
+#[derive(Default)]
+pub struct MainDecalPass3dNode;
+
+impl ViewNode for MainDecalPass3dNode {
+    type ViewQuery = (
+        &'static ExtractedCamera,
+        &'static SortedRenderPhase<Decal3d>,
+        &'static ViewTarget,
+        &'static ViewDepthTexture,
+        Option<&'static ViewPartialRedraw>,
+    );
+    fn run(
+        &self,
+        graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        (camera, decal_phase, target, depth, partial_redraw): QueryItem<Self::ViewQuery>,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        if decal_phase.items.is_empty() {
+            return Ok(());
+        }
+
+        #[cfg(feature = "trace")]
+        let _main_decal_pass_3d_span = info_span!("main_decal_pass_3d").entered();
+
+        let diagnostics = render_context.diagnostic_recorder();
+
+        let mut render_pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
+            label: Some("main_decal_pass_3d"),
+            color_attachments: &[Some(target.get_color_attachment())],
+            // NOTE: Decals are tested against the depth of the opaque meshes, but don't write to
+            // it. Projected decals read the depth of the scene from the depth prepass instead.
+            depth_stencil_attachment: Some(depth.get_attachment(StoreOp::Store)),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+
+        let pass_span = diagnostics.pass_span(&mut render_pass, "main_decal_pass_3d");
+
+        if let Some(viewport) = camera.viewport.as_ref() {
+            render_pass.set_camera_viewport(viewport);
+        }
+        if let Some(partial_redraw) = partial_redraw {
+            partial_redraw.set_scissor_rect(&mut render_pass);
+        }
+
+        decal_phase.render(&mut render_pass, world, graph.view_entity());
+
+        pass_span.end(&mut render_pass);
+
+        Ok(())
+    }
+}
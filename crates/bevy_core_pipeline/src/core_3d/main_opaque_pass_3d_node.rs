@@ -1,5 +1,6 @@
 use crate::{
     core_3d::Opaque3d,
+    partial_redraw::ViewPartialRedraw,
     skybox::{SkyboxBindGroup, SkyboxPipelineId},
 };
 use bevy_ecs::{prelude::World, query::QueryItem};
@@ -32,6 +33,7 @@ impl ViewNode for MainOpaquePass3dNode {
         Option<&'static SkyboxPipelineId>,
         Option<&'static SkyboxBindGroup>,
         &'static ViewUniformOffset,
+        Option<&'static ViewPartialRedraw>,
     );
 
     fn run<'w>(
@@ -47,6 +49,7 @@ impl ViewNode for MainOpaquePass3dNode {
             skybox_pipeline,
             skybox_bind_group,
             view_uniform_offset,
+            partial_redraw,
         ): QueryItem<'w, Self::ViewQuery>,
         world: &'w World,
     ) -> Result<(), NodeRunError> {
@@ -80,6 +83,9 @@ impl ViewNode for MainOpaquePass3dNode {
             if let Some(viewport) = camera.viewport.as_ref() {
                 render_pass.set_camera_viewport(viewport);
             }
+            if let Some(partial_redraw) = partial_redraw {
+                partial_redraw.set_scissor_rect(&mut render_pass);
+            }
 
             // Opaque draws
             if !opaque_phase.is_empty() {
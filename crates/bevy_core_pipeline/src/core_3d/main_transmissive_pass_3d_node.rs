@@ -1,5 +1,5 @@
 use super::{Camera3d, ViewTransmissionTexture};
-use crate::core_3d::Transmissive3d;
+use crate::{core_3d::Transmissive3d, partial_redraw::ViewPartialRedraw};
 use bevy_ecs::{prelude::*, query::QueryItem};
 use bevy_render::{
     camera::ExtractedCamera,
@@ -26,13 +26,14 @@ impl ViewNode for MainTransmissivePass3dNode {
         &'static ViewTarget,
         Option<&'static ViewTransmissionTexture>,
         &'static ViewDepthTexture,
+        Option<&'static ViewPartialRedraw>,
     );
 
     fn run(
         &self,
         graph: &mut RenderGraphContext,
         render_context: &mut RenderContext,
-        (camera, camera_3d, transmissive_phase, target, transmission, depth): QueryItem<
+        (camera, camera_3d, transmissive_phase, target, transmission, depth, partial_redraw): QueryItem<
             Self::ViewQuery,
         >,
         world: &World,
@@ -89,6 +90,9 @@ impl ViewNode for MainTransmissivePass3dNode {
                     if let Some(viewport) = camera.viewport.as_ref() {
                         render_pass.set_camera_viewport(viewport);
                     }
+                    if let Some(partial_redraw) = partial_redraw {
+                        partial_redraw.set_scissor_rect(&mut render_pass);
+                    }
 
                     // render items in range
                     transmissive_phase.render_range(&mut render_pass, world, view_entity, range);
@@ -100,6 +104,9 @@ impl ViewNode for MainTransmissivePass3dNode {
                 if let Some(viewport) = camera.viewport.as_ref() {
                     render_pass.set_camera_viewport(viewport);
                 }
+                if let Some(partial_redraw) = partial_redraw {
+                    partial_redraw.set_scissor_rect(&mut render_pass);
+                }
 
                 transmissive_phase.render(&mut render_pass, world, view_entity);
             }
@@ -1,4 +1,4 @@
-use crate::core_3d::Transparent3d;
+use crate::{core_3d::Transparent3d, partial_redraw::ViewPartialRedraw};
 use bevy_ecs::{prelude::*, query::QueryItem};
 use bevy_render::{
     camera::ExtractedCamera,
@@ -23,12 +23,13 @@ impl ViewNode for MainTransparentPass3dNode {
         &'static SortedRenderPhase<Transparent3d>,
         &'static ViewTarget,
         &'static ViewDepthTexture,
+        Option<&'static ViewPartialRedraw>,
     );
     fn run(
         &self,
         graph: &mut RenderGraphContext,
         render_context: &mut RenderContext,
-        (camera, transparent_phase, target, depth): QueryItem<Self::ViewQuery>,
+        (camera, transparent_phase, target, depth, partial_redraw): QueryItem<Self::ViewQuery>,
         world: &World,
     ) -> Result<(), NodeRunError> {
         let view_entity = graph.view_entity();
@@ -60,6 +61,9 @@ impl ViewNode for MainTransparentPass3dNode {
             if let Some(viewport) = camera.viewport.as_ref() {
                 render_pass.set_camera_viewport(viewport);
             }
+            if let Some(partial_redraw) = partial_redraw {
+                partial_redraw.set_scissor_rect(&mut render_pass);
+            }
 
             transparent_phase.render(&mut render_pass, world, view_entity);
 
@@ -1,4 +1,5 @@
 mod camera_3d;
+mod main_decal_pass_3d_node;
 mod main_opaque_pass_3d_node;
 mod main_transmissive_pass_3d_node;
 mod main_transparent_pass_3d_node;
@@ -22,11 +23,15 @@ pub mod graph {
         EndPrepasses,
         StartMainPass,
         MainOpaquePass,
+        MainDecalPass,
         MainTransmissivePass,
         MainTransparentPass,
+        PartialRedraw,
         EndMainPass,
+        DepthReadback,
         Taa,
         MotionBlur,
+        SubFrameAccumulation,
         Bloom,
         AutoExposure,
         Tonemapping,
@@ -39,12 +44,15 @@ pub mod graph {
 
 // PERF: vulkan docs recommend using 24 bit depth for better performance
 pub const CORE_3D_DEPTH_FORMAT: TextureFormat = TextureFormat::Depth32Float;
+/// The depth format of the main 3d passes of cameras with [`Camera3d::stencil`] enabled.
+pub const CORE_3D_DEPTH_STENCIL_FORMAT: TextureFormat = TextureFormat::Depth24PlusStencil8;
 
 use std::ops::Range;
 
 use bevy_asset::AssetId;
 use bevy_color::LinearRgba;
 pub use camera_3d::*;
+pub use main_decal_pass_3d_node::*;
 pub use main_opaque_pass_3d_node::*;
 pub use main_transparent_pass_3d_node::*;
 
@@ -67,8 +75,8 @@ use bevy_render::{
         Texture, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages, TextureView,
     },
     renderer::RenderDevice,
-    texture::{BevyDefault, ColorAttachment, Image, TextureCache},
-    view::{ExtractedView, ViewDepthTexture, ViewTarget},
+    texture::{ColorAttachment, Image, TextureCache},
+    view::{ExtractedView, ViewDepthTexture},
     Extract, ExtractSchedule, Render, RenderApp, RenderSet,
 };
 use bevy_utils::{tracing::warn, HashMap};
@@ -77,13 +85,16 @@ use crate::{
     core_3d::main_transmissive_pass_3d_node::MainTransmissivePass3dNode,
     deferred::{
         copy_lighting_id::CopyDeferredLightingIdNode, node::DeferredGBufferPrepassNode,
-        AlphaMask3dDeferred, Opaque3dDeferred, DEFERRED_LIGHTING_PASS_ID_FORMAT,
-        DEFERRED_PREPASS_FORMAT,
+        AlphaMask3dDeferred, Opaque3dDeferred, DEFERRED_GBUFFER_EXTENSION_FORMAT,
+        DEFERRED_LIGHTING_PASS_ID_FORMAT, DEFERRED_PREPASS_FORMAT,
     },
+    depth_readback::DepthReadback,
+    partial_redraw::PartialRedrawNode,
     prepass::{
-        node::PrepassNode, AlphaMask3dPrepass, DeferredPrepass, DepthPrepass, MotionVectorPrepass,
-        NormalPrepass, Opaque3dPrepass, OpaqueNoLightmap3dBinKey, ViewPrepassTextures,
-        MOTION_VECTOR_PREPASS_FORMAT, NORMAL_PREPASS_FORMAT,
+        node::PrepassNode, AlphaMask3dPrepass, DeferredGBufferExtensionPrepass, DeferredPrepass,
+        DepthPrepass, MotionVectorPrepass, NormalPrepass, ObjectIdPrepass, Opaque3dPrepass,
+        OpaqueNoLightmap3dBinKey, ViewPrepassTextures, MOTION_VECTOR_PREPASS_FORMAT,
+        NORMAL_PREPASS_FORMAT, OBJECT_ID_PREPASS_FORMAT,
     },
     skybox::SkyboxPlugin,
     tonemapping::TonemappingNode,
@@ -107,6 +118,7 @@ impl Plugin for Core3dPlugin {
         render_app
             .init_resource::<DrawFunctions<Opaque3d>>()
             .init_resource::<DrawFunctions<AlphaMask3d>>()
+            .init_resource::<DrawFunctions<Decal3d>>()
             .init_resource::<DrawFunctions<Transmissive3d>>()
             .init_resource::<DrawFunctions<Transparent3d>>()
             .init_resource::<DrawFunctions<Opaque3dPrepass>>()
@@ -118,6 +130,7 @@ impl Plugin for Core3dPlugin {
             .add_systems(
                 Render,
                 (
+                    sort_phase_system::<Decal3d>.in_set(RenderSet::PhaseSort),
                     sort_phase_system::<Transmissive3d>.in_set(RenderSet::PhaseSort),
                     sort_phase_system::<Transparent3d>.in_set(RenderSet::PhaseSort),
                     prepare_core_3d_depth_textures.in_set(RenderSet::PrepareResources),
@@ -143,6 +156,10 @@ impl Plugin for Core3dPlugin {
                 Core3d,
                 Node3d::MainOpaquePass,
             )
+            .add_render_graph_node::<ViewNodeRunner<MainDecalPass3dNode>>(
+                Core3d,
+                Node3d::MainDecalPass,
+            )
             .add_render_graph_node::<ViewNodeRunner<MainTransmissivePass3dNode>>(
                 Core3d,
                 Node3d::MainTransmissivePass,
@@ -151,6 +168,10 @@ impl Plugin for Core3dPlugin {
                 Core3d,
                 Node3d::MainTransparentPass,
             )
+            .add_render_graph_node::<ViewNodeRunner<PartialRedrawNode>>(
+                Core3d,
+                Node3d::PartialRedraw,
+            )
             .add_render_graph_node::<EmptyNode>(Core3d, Node3d::EndMainPass)
             .add_render_graph_node::<ViewNodeRunner<TonemappingNode>>(Core3d, Node3d::Tonemapping)
             .add_render_graph_node::<EmptyNode>(Core3d, Node3d::EndMainPassPostProcessing)
@@ -164,8 +185,10 @@ impl Plugin for Core3dPlugin {
                     Node3d::EndPrepasses,
                     Node3d::StartMainPass,
                     Node3d::MainOpaquePass,
+                    Node3d::MainDecalPass,
                     Node3d::MainTransmissivePass,
                     Node3d::MainTransparentPass,
+                    Node3d::PartialRedraw,
                     Node3d::EndMainPass,
                     Node3d::Tonemapping,
                     Node3d::EndMainPassPostProcessing,
@@ -405,6 +428,74 @@ impl CachedRenderPipelinePhaseItem for Transmissive3d {
     }
 }
 
+/// Decal 3D [`SortedPhaseItem`]s, drawn after the opaque meshes they're applied to.
+///
+/// Only cameras with a [`DepthPrepass`] have this phase, so that decals projected onto the
+/// scene can read its depth.
+pub struct Decal3d {
+    pub distance: f32,
+    pub pipeline: CachedRenderPipelineId,
+    pub entity: Entity,
+    pub draw_function: DrawFunctionId,
+    pub batch_range: Range<u32>,
+    pub extra_index: PhaseItemExtraIndex,
+}
+
+impl PhaseItem for Decal3d {
+    #[inline]
+    fn entity(&self) -> Entity {
+        self.entity
+    }
+
+    #[inline]
+    fn draw_function(&self) -> DrawFunctionId {
+        self.draw_function
+    }
+
+    #[inline]
+    fn batch_range(&self) -> &Range<u32> {
+        &self.batch_range
+    }
+
+    #[inline]
+    fn batch_range_mut(&mut self) -> &mut Range<u32> {
+        &mut self.batch_range
+    }
+
+    #[inline]
+    fn extra_index(&self) -> PhaseItemExtraIndex {
+        self.extra_index
+    }
+
+    #[inline]
+    fn batch_range_and_extra_index_mut(&mut self) -> (&mut Range<u32>, &mut PhaseItemExtraIndex) {
+        (&mut self.batch_range, &mut self.extra_index)
+    }
+}
+
+impl SortedPhaseItem for Decal3d {
+    // NOTE: Values increase towards the camera. Decals are blended back-to-front, so that the
+    // nearest decal ends up on top.
+    type SortKey = FloatOrd;
+
+    #[inline]
+    fn sort_key(&self) -> Self::SortKey {
+        FloatOrd(self.distance)
+    }
+
+    #[inline]
+    fn sort(items: &mut [Self]) {
+        radsort::sort_by_key(items, |item| item.distance);
+    }
+}
+
+impl CachedRenderPipelinePhaseItem for Decal3d {
+    #[inline]
+    fn cached_pipeline(&self) -> CachedRenderPipelineId {
+        self.pipeline
+    }
+}
+
 pub struct Transparent3d {
     pub distance: f32,
     pub pipeline: CachedRenderPipelineId,
@@ -459,6 +550,14 @@ impl SortedPhaseItem for Transparent3d {
     fn sort(items: &mut [Self]) {
         radsort::sort_by_key(items, |item| item.distance);
     }
+
+    #[inline]
+    fn sort_for_gpu_sorting(items: &mut [Self]) -> bool {
+        // The stable sort detects runs, so the common case of items that are
+        // already grouped, like the instances of a particle system, is linear.
+        items.sort_by_key(|item| (item.pipeline, item.draw_function));
+        true
+    }
 }
 
 impl CachedRenderPipelinePhaseItem for Transparent3d {
@@ -495,19 +594,29 @@ pub fn extract_camera_prepass_phase(
                 Has<DepthPrepass>,
                 Has<NormalPrepass>,
                 Has<MotionVectorPrepass>,
+                Has<ObjectIdPrepass>,
                 Has<DeferredPrepass>,
+                Has<DeferredGBufferExtensionPrepass>,
             ),
             With<Camera3d>,
         >,
     >,
 ) {
-    for (entity, camera, depth_prepass, normal_prepass, motion_vector_prepass, deferred_prepass) in
-        cameras_3d.iter()
+    for (
+        entity,
+        camera,
+        depth_prepass,
+        normal_prepass,
+        motion_vector_prepass,
+        object_id_prepass,
+        deferred_prepass,
+        deferred_gbuffer_extension_prepass,
+    ) in cameras_3d.iter()
     {
         if camera.is_active {
             let mut entity = commands.get_or_spawn(entity);
 
-            if depth_prepass || normal_prepass || motion_vector_prepass {
+            if depth_prepass || normal_prepass || motion_vector_prepass || object_id_prepass {
                 entity.insert((
                     BinnedRenderPhase::<Opaque3dPrepass>::default(),
                     BinnedRenderPhase::<AlphaMask3dPrepass>::default(),
@@ -522,7 +631,7 @@ pub fn extract_camera_prepass_phase(
             }
 
             if depth_prepass {
-                entity.insert(DepthPrepass);
+                entity.insert((DepthPrepass, SortedRenderPhase::<Decal3d>::default()));
             }
             if normal_prepass {
                 entity.insert(NormalPrepass);
@@ -530,8 +639,14 @@ pub fn extract_camera_prepass_phase(
             if motion_vector_prepass {
                 entity.insert(MotionVectorPrepass);
             }
+            if object_id_prepass {
+                entity.insert(ObjectIdPrepass);
+            }
             if deferred_prepass {
                 entity.insert(DeferredPrepass);
+                if deferred_gbuffer_extension_prepass {
+                    entity.insert(DeferredGBufferExtensionPrepass);
+                }
             }
         }
     }
@@ -543,7 +658,13 @@ pub fn prepare_core_3d_depth_textures(
     msaa: Res<Msaa>,
     render_device: Res<RenderDevice>,
     views_3d: Query<
-        (Entity, &ExtractedCamera, Option<&DepthPrepass>, &Camera3d),
+        (
+            Entity,
+            &ExtractedCamera,
+            Option<&DepthPrepass>,
+            &Camera3d,
+            Has<DepthReadback>,
+        ),
         (
             With<BinnedRenderPhase<Opaque3d>>,
             With<BinnedRenderPhase<AlphaMask3d>>,
@@ -553,13 +674,17 @@ pub fn prepare_core_3d_depth_textures(
     >,
 ) {
     let mut render_target_usage = HashMap::default();
-    for (_, camera, depth_prepass, camera_3d) in &views_3d {
+    for (_, camera, depth_prepass, camera_3d, depth_readback) in &views_3d {
         // Default usage required to write to the depth texture
         let mut usage: TextureUsages = camera_3d.depth_texture_usages.into();
         if depth_prepass.is_some() {
             // Required to read the output of the prepass
             usage |= TextureUsages::COPY_SRC;
         }
+        if depth_readback {
+            // Required to copy the depth into the readback texture
+            usage |= TextureUsages::TEXTURE_BINDING;
+        }
         render_target_usage
             .entry(camera.target.clone())
             .and_modify(|u| *u |= usage)
@@ -567,13 +692,14 @@ pub fn prepare_core_3d_depth_textures(
     }
 
     let mut textures = HashMap::default();
-    for (entity, camera, _, camera_3d) in &views_3d {
+    for (entity, camera, _, camera_3d, _) in &views_3d {
         let Some(physical_target_size) = camera.physical_target_size else {
             continue;
         };
 
+        let format = camera_3d.depth_format();
         let cached_texture = textures
-            .entry(camera.target.clone())
+            .entry((camera.target.clone(), format))
             .or_insert_with(|| {
                 // The size of the depth texture
                 let size = Extent3d {
@@ -592,12 +718,12 @@ pub fn prepare_core_3d_depth_textures(
                     mip_level_count: 1,
                     sample_count: msaa.samples(),
                     dimension: TextureDimension::D2,
-                    format: CORE_3D_DEPTH_FORMAT,
+                    format,
                     usage,
                     view_formats: &[],
                 };
 
-                texture_cache.get(&render_device, descriptor)
+                texture_cache.get_for_view(&render_device, entity, descriptor)
             })
             .clone();
 
@@ -665,11 +791,7 @@ pub fn prepare_core_3d_transmission_textures(
                     height: physical_target_size.y,
                 };
 
-                let format = if view.hdr {
-                    ViewTarget::TEXTURE_FORMAT_HDR
-                } else {
-                    TextureFormat::bevy_default()
-                };
+                let format = view.main_texture_format();
 
                 let descriptor = TextureDescriptor {
                     label: Some("view_transmission_texture"),
@@ -682,7 +804,7 @@ pub fn prepare_core_3d_transmission_textures(
                     view_formats: &[],
                 };
 
-                texture_cache.get(&render_device, descriptor)
+                texture_cache.get_for_view(&render_device, entity, descriptor)
             })
             .clone();
 
@@ -730,7 +852,9 @@ pub fn prepare_prepass_textures(
             Has<DepthPrepass>,
             Has<NormalPrepass>,
             Has<MotionVectorPrepass>,
+            Has<ObjectIdPrepass>,
             Has<DeferredPrepass>,
+            Has<DeferredGBufferExtensionPrepass>,
         ),
         Or<(
             With<BinnedRenderPhase<Opaque3dPrepass>>,
@@ -745,8 +869,18 @@ pub fn prepare_prepass_textures(
     let mut deferred_textures = HashMap::default();
     let mut deferred_lighting_id_textures = HashMap::default();
     let mut motion_vectors_textures = HashMap::default();
-    for (entity, camera, depth_prepass, normal_prepass, motion_vector_prepass, deferred_prepass) in
-        &views_3d
+    let mut object_id_textures = HashMap::default();
+    let mut deferred_gbuffer_extension_textures = HashMap::default();
+    for (
+        entity,
+        camera,
+        depth_prepass,
+        normal_prepass,
+        motion_vector_prepass,
+        object_id_prepass,
+        deferred_prepass,
+        deferred_gbuffer_extension_prepass,
+    ) in &views_3d
     {
         let Some(physical_target_size) = camera.physical_target_size else {
             continue;
@@ -774,7 +908,7 @@ pub fn prepare_prepass_textures(
                             | TextureUsages::TEXTURE_BINDING,
                         view_formats: &[],
                     };
-                    texture_cache.get(&render_device, descriptor)
+                    texture_cache.get_for_view(&render_device, entity, descriptor)
                 })
                 .clone()
         });
@@ -783,8 +917,9 @@ pub fn prepare_prepass_textures(
             normal_textures
                 .entry(camera.target.clone())
                 .or_insert_with(|| {
-                    texture_cache.get(
+                    texture_cache.get_for_view(
                         &render_device,
+                        entity,
                         TextureDescriptor {
                             label: Some("prepass_normal_texture"),
                             size,
@@ -805,8 +940,9 @@ pub fn prepare_prepass_textures(
             motion_vectors_textures
                 .entry(camera.target.clone())
                 .or_insert_with(|| {
-                    texture_cache.get(
+                    texture_cache.get_for_view(
                         &render_device,
+                        entity,
                         TextureDescriptor {
                             label: Some("prepass_motion_vectors_textures"),
                             size,
@@ -823,12 +959,37 @@ pub fn prepare_prepass_textures(
                 .clone()
         });
 
+        let cached_object_id_texture = object_id_prepass.then(|| {
+            object_id_textures
+                .entry(camera.target.clone())
+                .or_insert_with(|| {
+                    texture_cache.get_for_view(
+                        &render_device,
+                        entity,
+                        TextureDescriptor {
+                            label: Some("prepass_object_id_texture"),
+                            size,
+                            mip_level_count: 1,
+                            sample_count: msaa.samples(),
+                            dimension: TextureDimension::D2,
+                            format: OBJECT_ID_PREPASS_FORMAT,
+                            usage: TextureUsages::RENDER_ATTACHMENT
+                                | TextureUsages::TEXTURE_BINDING
+                                | TextureUsages::COPY_SRC,
+                            view_formats: &[],
+                        },
+                    )
+                })
+                .clone()
+        });
+
         let cached_deferred_texture = deferred_prepass.then(|| {
             deferred_textures
                 .entry(camera.target.clone())
                 .or_insert_with(|| {
-                    texture_cache.get(
+                    texture_cache.get_for_view(
                         &render_device,
+                        entity,
                         TextureDescriptor {
                             label: Some("prepass_deferred_texture"),
                             size,
@@ -849,8 +1010,9 @@ pub fn prepare_prepass_textures(
             deferred_lighting_id_textures
                 .entry(camera.target.clone())
                 .or_insert_with(|| {
-                    texture_cache.get(
+                    texture_cache.get_for_view(
                         &render_device,
+                        entity,
                         TextureDescriptor {
                             label: Some("deferred_lighting_pass_id_texture"),
                             size,
@@ -867,6 +1029,30 @@ pub fn prepare_prepass_textures(
                 .clone()
         });
 
+        let cached_deferred_gbuffer_extension_texture =
+            (deferred_prepass && deferred_gbuffer_extension_prepass).then(|| {
+                deferred_gbuffer_extension_textures
+                    .entry(camera.target.clone())
+                    .or_insert_with(|| {
+                        texture_cache.get_for_view(
+                            &render_device,
+                            entity,
+                            TextureDescriptor {
+                                label: Some("prepass_deferred_gbuffer_extension_texture"),
+                                size,
+                                mip_level_count: 1,
+                                sample_count: 1,
+                                dimension: TextureDimension::D2,
+                                format: DEFERRED_GBUFFER_EXTENSION_FORMAT,
+                                usage: TextureUsages::RENDER_ATTACHMENT
+                                    | TextureUsages::TEXTURE_BINDING,
+                                view_formats: &[],
+                            },
+                        )
+                    })
+                    .clone()
+            });
+
         commands.entity(entity).insert(ViewPrepassTextures {
             depth: cached_depth_texture
                 .map(|t| ColorAttachment::new(t, None, Some(LinearRgba::BLACK))),
@@ -877,10 +1063,16 @@ pub fn prepare_prepass_textures(
             // https://gpuopen.com/performance/#clears
             motion_vectors: cached_motion_vectors_texture
                 .map(|t| ColorAttachment::new(t, None, Some(LinearRgba::BLACK))),
+            // Zero means no entity
+            object_id: cached_object_id_texture
+                .map(|t| ColorAttachment::new(t, None, Some(LinearRgba::BLACK))),
             deferred: cached_deferred_texture
                 .map(|t| ColorAttachment::new(t, None, Some(LinearRgba::BLACK))),
             deferred_lighting_pass_id: cached_deferred_lighting_pass_id_texture
                 .map(|t| ColorAttachment::new(t, None, Some(LinearRgba::BLACK))),
+            // Materials that don't write to it leave zeros
+            deferred_gbuffer_extension: cached_deferred_gbuffer_extension_texture
+                .map(|t| ColorAttachment::new(t, None, Some(LinearRgba::BLACK))),
             size,
         });
     }
@@ -200,7 +200,7 @@ fn prepare_deferred_lighting_id_textures(
                 usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
                 view_formats: &[],
             };
-            let texture = texture_cache.get(&render_device, texture_descriptor);
+            let texture = texture_cache.get_for_view(&render_device, entity, texture_descriptor);
             commands
                 .entity(entity)
                 .insert(DeferredLightingIdDepthTexture { texture });
@@ -105,6 +105,20 @@ impl ViewNode for DeferredGBufferPrepassNode {
                 .map(|deferred_lighting_pass_id| deferred_lighting_pass_id.get_attachment()),
         );
 
+        color_attachments.push(
+            view_prepass_textures
+                .object_id
+                .as_ref()
+                .map(|object_id_texture| object_id_texture.get_attachment()),
+        );
+
+        color_attachments.push(
+            view_prepass_textures
+                .deferred_gbuffer_extension
+                .as_ref()
+                .map(|gbuffer_extension| gbuffer_extension.get_attachment()),
+        );
+
         // If all color attachments are none: clear the color attachment list so that no fragment shader is required
         if color_attachments.iter().all(Option::is_none) {
             color_attachments.clear();
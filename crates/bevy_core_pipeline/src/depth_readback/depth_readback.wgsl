@@ -0,0 +1,15 @@
+#import bevy_core_pipeline::fullscreen_vertex_shader::FullscreenVertexOutput
+
+#ifdef MULTISAMPLED
+@group(0) @binding(0) var depth_texture: texture_depth_multisampled_2d;
+#else
+@group(0) @binding(0) var depth_texture: texture_depth_2d;
+#endif
+
+// Copies the depth of the view into a color target, which unlike depth
+// attachments can be copied to a buffer on every platform. Multisampled depth
+// is read from its first sample.
+@fragment
+fn fragment(in: FullscreenVertexOutput) -> @location(0) f32 {
+    return textureLoad(depth_texture, vec2<i32>(in.position.xy), 0);
+}
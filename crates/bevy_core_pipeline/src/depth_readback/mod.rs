@@ -0,0 +1,561 @@
+//! Asynchronous readback of the depth buffer of 3D cameras.
+//!
+//! Add the [`DepthReadback`] component to a 3D camera, then use the
+//! [`DepthReadbackFrames`] resource to find the depth, or the world space
+//! position, of the closest surface under a given pixel. This is useful for
+//! gameplay features that would otherwise need a CPU raycast against the
+//! scene, such as decal placement, foot IK probing or camera collision.
+//!
+//! Reading data back from the GPU takes a few frames, so the results always
+//! describe a frame that was rendered slightly in the past. The readback never
+//! blocks waiting on the GPU: if every buffer of a camera is still in flight,
+//! that frame is simply not read back.
+
+mod node;
+
+pub use node::*;
+
+use bevy_app::{App, Plugin, PreUpdate};
+use bevy_asset::{load_internal_asset, Handle};
+use bevy_ecs::{entity::EntityHashMap, prelude::*};
+use bevy_math::{Mat4, URect, UVec2, Vec3, Vec4};
+use bevy_reflect::{std_traits::ReflectDefault, Reflect};
+use bevy_render::{
+    camera::{Camera, ExtractedCamera},
+    extract_component::{ExtractComponent, ExtractComponentPlugin},
+    render_graph::{RenderGraphApp, ViewNodeRunner},
+    render_resource::{
+        binding_types::{texture_depth_2d, texture_depth_2d_multisampled},
+        *,
+    },
+    renderer::RenderDevice,
+    texture::{CachedTexture, TextureCache},
+    view::{ExtractedView, Msaa},
+    Render, RenderApp, RenderSet,
+};
+use bevy_utils::{default, tracing::error};
+use crossbeam_channel::{Receiver, Sender};
+
+use crate::{
+    core_3d::{
+        graph::{Core3d, Node3d},
+        Camera3d,
+    },
+    fullscreen_vertex_shader::fullscreen_shader_vertex_state,
+};
+
+pub const DEPTH_READBACK_SHADER_HANDLE: Handle<Shader> =
+    Handle::weak_from_u128(9071749741545670261);
+
+/// The format of the texture that the depth of a view is copied into before
+/// being read back.
+///
+/// Depth attachments can't be copied to buffers on every platform, and
+/// multisampled textures can't be copied at all, so a fullscreen pass copies
+/// depth to a color target instead.
+pub const DEPTH_READBACK_FORMAT: TextureFormat = TextureFormat::R32Float;
+
+/// The number of readback buffers each camera cycles through.
+///
+/// While a buffer is waiting to be mapped, the next readback copies into
+/// another one. If all of them are in flight, that frame is simply not read
+/// back.
+pub const DEPTH_READBACK_BUFFER_COUNT: usize = 2;
+
+/// Adds depth readback support for 3D cameras with the [`DepthReadback`]
+/// component.
+pub struct DepthReadbackPlugin;
+
+impl Plugin for DepthReadbackPlugin {
+    fn build(&self, app: &mut App) {
+        load_internal_asset!(
+            app,
+            DEPTH_READBACK_SHADER_HANDLE,
+            "depth_readback.wgsl",
+            Shader::from_wgsl
+        );
+
+        let (sender, receiver) = crossbeam_channel::unbounded();
+
+        app.register_type::<DepthReadback>()
+            .insert_resource(DepthReadbackFrames {
+                receiver,
+                frames: default(),
+            })
+            .add_systems(PreUpdate, receive_depth_readback_frames)
+            .add_plugins(ExtractComponentPlugin::<DepthReadback>::default());
+
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+
+        render_app
+            .insert_resource(DepthReadbackSender(sender))
+            .init_resource::<SpecializedRenderPipelines<DepthReadbackPipeline>>()
+            .init_resource::<DepthReadbacks>()
+            .add_systems(
+                Render,
+                (
+                    prepare_depth_readbacks.in_set(RenderSet::PrepareResources),
+                    map_depth_readbacks.in_set(RenderSet::Cleanup),
+                ),
+            )
+            .add_render_graph_node::<ViewNodeRunner<DepthReadbackNode>>(
+                Core3d,
+                Node3d::DepthReadback,
+            )
+            .add_render_graph_edges(
+                Core3d,
+                (
+                    Node3d::EndMainPass,
+                    Node3d::DepthReadback,
+                    Node3d::EndMainPassPostProcessing,
+                ),
+            );
+    }
+
+    fn finish(&self, app: &mut App) {
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+
+        render_app.init_resource::<DepthReadbackPipeline>();
+    }
+}
+
+/// Reads back the depth buffer of a 3D camera to the CPU, where it can be
+/// queried with [`DepthReadbackFrames`].
+///
+/// The depth is copied after the main passes, so it contains every opaque and
+/// alpha masked surface the camera rendered.
+#[derive(Component, ExtractComponent, Reflect, Clone, Copy, Debug)]
+#[reflect(Component, Default)]
+#[extract_component_filter(With<Camera3d>)]
+pub struct DepthReadback {
+    /// The region to read back, in physical pixels of the camera's render
+    /// target. Reading back a small region around the points of interest is
+    /// much cheaper than reading back the whole view.
+    ///
+    /// The region is clamped to the render target. Defaults to `None`, which
+    /// reads back the camera's whole viewport.
+    pub rect: Option<URect>,
+    /// How often the depth is read back, in frames. An interval of 1 reads it
+    /// back every frame, 4 every fourth frame.
+    ///
+    /// Defaults to 1.
+    pub interval: u32,
+}
+
+impl Default for DepthReadback {
+    fn default() -> Self {
+        Self {
+            rect: None,
+            interval: 1,
+        }
+    }
+}
+
+/// A region of the depth buffer of a camera that was read back from the GPU.
+#[derive(Clone, Debug)]
+pub struct DepthReadbackFrame {
+    /// The camera that rendered this frame.
+    pub camera: Entity,
+    /// The region that was read back, in physical pixels of the camera's
+    /// render target.
+    pub rect: URect,
+    /// One normalized device coordinate depth value per pixel of
+    /// [`DepthReadbackFrame::rect`], in row-major order.
+    ///
+    /// Bevy uses a reversed depth buffer: 1 is the near plane, and 0 is the far
+    /// plane, or infinitely far away for perspective projections.
+    pub depth: Vec<f32>,
+    /// The viewport of the camera, in physical pixels of the render target.
+    pub viewport: URect,
+    /// The camera's view-to-world matrix at the time it was rendered.
+    pub world_from_view: Mat4,
+    /// The inverse of the camera's projection at the time it was rendered.
+    pub view_from_clip: Mat4,
+}
+
+impl DepthReadbackFrame {
+    /// Returns the normalized device coordinate depth at the given pixel, in
+    /// physical pixels of the camera's render target, or `None` if the pixel
+    /// wasn't read back.
+    pub fn depth(&self, position: UVec2) -> Option<f32> {
+        let URect { min, max } = self.rect;
+        if position.x < min.x || position.y < min.y || position.x >= max.x || position.y >= max.y {
+            return None;
+        }
+        let local = position - min;
+        self.depth
+            .get((local.y * self.rect.width() + local.x) as usize)
+            .copied()
+    }
+
+    /// Returns the view space position of the closest surface at the given
+    /// pixel, or `None` if the pixel wasn't read back or nothing was drawn
+    /// there.
+    pub fn view_position(&self, position: UVec2) -> Option<Vec3> {
+        let depth = self.depth(position)?;
+        if depth <= 0.0 {
+            return None;
+        }
+
+        // Reconstruct the position from the center of the pixel.
+        let viewport_size = self.viewport.size().as_vec2();
+        let uv = (position.as_vec2() - self.viewport.min.as_vec2() + 0.5) / viewport_size;
+        let ndc = Vec4::new(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, depth, 1.0);
+        let view_position = self.view_from_clip * ndc;
+        Some(view_position.truncate() / view_position.w)
+    }
+
+    /// Returns the world space position of the closest surface at the given
+    /// pixel, or `None` if the pixel wasn't read back or nothing was drawn
+    /// there.
+    pub fn world_position(&self, position: UVec2) -> Option<Vec3> {
+        Some(
+            self.world_from_view
+                .transform_point3(self.view_position(position)?),
+        )
+    }
+
+    /// Returns the linear depth of the closest surface at the given pixel: its
+    /// distance from the camera along the camera's forward axis, in world
+    /// units. Returns `None` if the pixel wasn't read back or nothing was drawn
+    /// there.
+    pub fn linear_depth(&self, position: UVec2) -> Option<f32> {
+        Some(-self.view_position(position)?.z)
+    }
+}
+
+/// Holds the most recent [`DepthReadbackFrame`] of every camera with the
+/// [`DepthReadback`] component.
+///
+/// This is filled by [`receive_depth_readback_frames`] at the start of every
+/// frame.
+#[derive(Resource)]
+pub struct DepthReadbackFrames {
+    receiver: Receiver<DepthReadbackFrame>,
+    frames: EntityHashMap<DepthReadbackFrame>,
+}
+
+impl DepthReadbackFrames {
+    /// Returns the most recent frame read back for the given camera.
+    pub fn get(&self, camera: Entity) -> Option<&DepthReadbackFrame> {
+        self.frames.get(&camera)
+    }
+
+    /// Iterates over the most recent frame of every camera.
+    pub fn iter(&self) -> impl Iterator<Item = &DepthReadbackFrame> {
+        self.frames.values()
+    }
+}
+
+/// Collects the depth readback frames sent by the render world, keeping only
+/// the latest one for each camera.
+pub fn receive_depth_readback_frames(
+    mut depth_readback_frames: ResMut<DepthReadbackFrames>,
+    cameras: Query<(), (With<Camera>, With<DepthReadback>)>,
+) {
+    let DepthReadbackFrames { receiver, frames } = &mut *depth_readback_frames;
+    for frame in receiver.try_iter() {
+        frames.insert(frame.camera, frame);
+    }
+    frames.retain(|camera, _| cameras.contains(*camera));
+}
+
+/// The depth readback of a view this frame. Only present on views whose
+/// depth is read back this frame.
+#[derive(Component)]
+pub struct ViewDepthReadback {
+    /// The texture the depth of the view is copied into.
+    pub texture: CachedTexture,
+    /// The region that is read back, in physical pixels of the render target.
+    pub rect: URect,
+    /// The index of the readback buffer the region is copied into.
+    pub buffer_index: usize,
+    /// The pipeline that copies the depth of the view into
+    /// [`ViewDepthReadback::texture`].
+    pub pipeline_id: CachedRenderPipelineId,
+    /// Whether the depth texture of the view is multisampled.
+    pub multisampled: bool,
+}
+
+#[derive(Resource)]
+pub struct DepthReadbackPipeline {
+    pub layout: BindGroupLayout,
+    pub layout_msaa: BindGroupLayout,
+}
+
+impl FromWorld for DepthReadbackPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+
+        let layout = render_device.create_bind_group_layout(
+            "depth_readback_bind_group_layout",
+            &BindGroupLayoutEntries::single(ShaderStages::FRAGMENT, texture_depth_2d()),
+        );
+        let layout_msaa = render_device.create_bind_group_layout(
+            "depth_readback_bind_group_layout_msaa",
+            &BindGroupLayoutEntries::single(
+                ShaderStages::FRAGMENT,
+                texture_depth_2d_multisampled(),
+            ),
+        );
+
+        Self {
+            layout,
+            layout_msaa,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct DepthReadbackPipelineKey {
+    pub multisampled: bool,
+}
+
+impl SpecializedRenderPipeline for DepthReadbackPipeline {
+    type Key = DepthReadbackPipelineKey;
+
+    fn specialize(&self, key: Self::Key) -> RenderPipelineDescriptor {
+        let (layout, shader_defs) = if key.multisampled {
+            (self.layout_msaa.clone(), vec!["MULTISAMPLED".into()])
+        } else {
+            (self.layout.clone(), vec![])
+        };
+
+        RenderPipelineDescriptor {
+            label: Some("depth_readback_pipeline".into()),
+            layout: vec![layout],
+            vertex: fullscreen_shader_vertex_state(),
+            fragment: Some(FragmentState {
+                shader: DEPTH_READBACK_SHADER_HANDLE,
+                shader_defs,
+                entry_point: "fragment".into(),
+                targets: vec![Some(ColorTargetState {
+                    format: DEPTH_READBACK_FORMAT,
+                    blend: None,
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            push_constant_ranges: vec![],
+        }
+    }
+}
+
+/// Sends finished [`DepthReadbackFrame`]s from the render world to the main
+/// world.
+#[derive(Resource)]
+struct DepthReadbackSender(Sender<DepthReadbackFrame>);
+
+/// A buffer that a region of the depth of a view is copied into so that it can
+/// be read back.
+pub struct DepthReadbackBuffer {
+    /// The buffer itself.
+    pub buffer: ReadbackBuffer,
+    /// The size of the region the buffer has room for, in pixels.
+    pub size: UVec2,
+    /// The number of bytes per row, including padding.
+    pub padded_bytes_per_row: u32,
+    rect: URect,
+    viewport: URect,
+    world_from_view: Mat4,
+    view_from_clip: Mat4,
+}
+
+impl DepthReadbackBuffer {
+    fn new(render_device: &RenderDevice, size: UVec2) -> Self {
+        let padded_bytes_per_row =
+            RenderDevice::align_copy_bytes_per_row(size.x as usize * 4) as u32;
+        let buffer = ReadbackBuffer::new(
+            render_device,
+            "depth_readback_buffer",
+            padded_bytes_per_row as u64 * size.y as u64,
+        );
+        Self {
+            buffer,
+            size,
+            padded_bytes_per_row,
+            rect: URect::default(),
+            viewport: URect::default(),
+            world_from_view: Mat4::IDENTITY,
+            view_from_clip: Mat4::IDENTITY,
+        }
+    }
+
+    /// Copies the mapped contents of the buffer to a [`DepthReadbackFrame`],
+    /// stripping the row padding, and unmaps the buffer.
+    fn read(&self, camera: Entity) -> DepthReadbackFrame {
+        let row_bytes = self.size.x as usize * std::mem::size_of::<f32>();
+        let mut depth = Vec::with_capacity(self.size.x as usize * self.size.y as usize);
+        self.buffer.read(|data| {
+            for row in data
+                .chunks(self.padded_bytes_per_row as usize)
+                .take(self.size.y as usize)
+            {
+                depth.extend_from_slice(bytemuck::cast_slice::<u8, f32>(&row[..row_bytes]));
+            }
+        });
+
+        DepthReadbackFrame {
+            camera,
+            rect: self.rect,
+            depth,
+            viewport: self.viewport,
+            world_from_view: self.world_from_view,
+            view_from_clip: self.view_from_clip,
+        }
+    }
+}
+
+/// The readback buffers of a view with the [`DepthReadback`] component.
+#[derive(Default)]
+struct DepthReadbackBuffers {
+    buffers: Vec<DepthReadbackBuffer>,
+    /// The number of frames rendered since the view was first seen, used to
+    /// honor [`DepthReadback::interval`].
+    frame_count: u32,
+}
+
+/// The readback buffers of every view with the [`DepthReadback`] component.
+#[derive(Resource, Default)]
+pub struct DepthReadbacks {
+    views: EntityHashMap<DepthReadbackBuffers>,
+}
+
+impl DepthReadbacks {
+    /// Returns the readback buffer with the given index for a view.
+    pub fn get(&self, view: Entity, index: usize) -> Option<&DepthReadbackBuffer> {
+        self.views.get(&view)?.buffers.get(index)
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn prepare_depth_readbacks(
+    mut commands: Commands,
+    render_device: Res<RenderDevice>,
+    mut texture_cache: ResMut<TextureCache>,
+    pipeline_cache: Res<PipelineCache>,
+    pipeline: Res<DepthReadbackPipeline>,
+    mut pipelines: ResMut<SpecializedRenderPipelines<DepthReadbackPipeline>>,
+    msaa: Res<Msaa>,
+    mut readbacks: ResMut<DepthReadbacks>,
+    views: Query<(Entity, &ExtractedCamera, &ExtractedView, &DepthReadback)>,
+) {
+    readbacks.views.retain(|view, _| views.contains(*view));
+
+    for (entity, camera, view, depth_readback) in &views {
+        let Some(target_size) = camera.physical_target_size else {
+            continue;
+        };
+
+        let view_buffers = readbacks.views.entry(entity).or_default();
+        let frame_count = view_buffers.frame_count;
+        view_buffers.frame_count = frame_count.wrapping_add(1);
+        if frame_count % depth_readback.interval.max(1) != 0 {
+            continue;
+        }
+
+        let origin = UVec2::new(view.viewport.x, view.viewport.y);
+        let viewport = URect::from_corners(
+            origin,
+            origin + UVec2::new(view.viewport.z, view.viewport.w),
+        );
+        let rect = depth_readback
+            .rect
+            .unwrap_or(viewport)
+            .intersect(URect::from_corners(UVec2::ZERO, target_size));
+        if rect.is_empty() {
+            continue;
+        }
+
+        let buffers = &mut view_buffers.buffers;
+        let index = match buffers
+            .iter()
+            .position(|readback| readback.buffer.is_free())
+        {
+            Some(index) => {
+                if buffers[index].size != rect.size() {
+                    buffers[index] = DepthReadbackBuffer::new(&render_device, rect.size());
+                }
+                index
+            }
+            None if buffers.len() < DEPTH_READBACK_BUFFER_COUNT => {
+                buffers.push(DepthReadbackBuffer::new(&render_device, rect.size()));
+                buffers.len() - 1
+            }
+            // Every buffer is still waiting on the GPU, skip this frame.
+            None => continue,
+        };
+
+        let readback = &mut buffers[index];
+        readback.buffer.start_copy();
+        readback.rect = rect;
+        readback.viewport = viewport;
+        readback.world_from_view = view.transform.compute_matrix();
+        readback.view_from_clip = view.projection.inverse();
+
+        // The whole texture is allocated so that the copy pass can address it
+        // with the same pixel coordinates as the depth texture, but only the
+        // region that is read back is drawn to.
+        let texture = texture_cache.get_for_view(
+            &render_device,
+            entity,
+            TextureDescriptor {
+                label: Some("depth_readback_texture"),
+                size: Extent3d {
+                    width: target_size.x,
+                    height: target_size.y,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: DEPTH_READBACK_FORMAT,
+                usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+                view_formats: &[],
+            },
+        );
+        let multisampled = msaa.samples() > 1;
+        let pipeline_id = pipelines.specialize(
+            &pipeline_cache,
+            &pipeline,
+            DepthReadbackPipelineKey { multisampled },
+        );
+
+        commands.entity(entity).insert(ViewDepthReadback {
+            texture,
+            rect,
+            buffer_index: index,
+            pipeline_id,
+            multisampled,
+        });
+    }
+}
+
+/// Starts mapping the buffers that were copied into this frame, and sends the
+/// contents of the buffers that finished mapping to the main world.
+fn map_depth_readbacks(
+    render_device: Res<RenderDevice>,
+    mut readbacks: ResMut<DepthReadbacks>,
+    sender: Res<DepthReadbackSender>,
+) {
+    render_device.poll(Maintain::Poll);
+
+    for (&camera, view_buffers) in &mut readbacks.views {
+        for readback in &mut view_buffers.buffers {
+            match readback.buffer.poll() {
+                None => {}
+                Some(Err(err)) => error!("Failed to map depth readback buffer: {err}"),
+                Some(Ok(())) => {
+                    // The main world may have been dropped during shutdown.
+                    let _ = sender.0.send(readback.read(camera));
+                }
+            }
+        }
+    }
+}
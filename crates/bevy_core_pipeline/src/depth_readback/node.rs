@@ -0,0 +1,117 @@
+use bevy_ecs::{prelude::*, query::QueryItem};
+use bevy_render::{
+    diagnostic::RecordDiagnostics,
+    render_graph::{NodeRunError, RenderGraphContext, ViewNode},
+    render_resource::{
+        BindGroupEntries, Extent3d, ImageCopyBuffer, ImageCopyTexture, ImageDataLayout, LoadOp,
+        Operations, Origin3d, PipelineCache, RenderPassColorAttachment, RenderPassDescriptor,
+        StoreOp, TextureAspect,
+    },
+    renderer::RenderContext,
+    view::ViewDepthTexture,
+};
+#[cfg(feature = "trace")]
+use bevy_utils::tracing::info_span;
+
+use super::{DepthReadbackPipeline, DepthReadbacks, ViewDepthReadback};
+
+/// Render node that copies the depth of a view with the
+/// [`DepthReadback`](super::DepthReadback) component into its current readback
+/// buffer.
+#[derive(Default)]
+pub struct DepthReadbackNode;
+
+impl ViewNode for DepthReadbackNode {
+    type ViewQuery = (&'static ViewDepthTexture, &'static ViewDepthReadback);
+
+    fn run(
+        &self,
+        graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        (depth, depth_readback): QueryItem<Self::ViewQuery>,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let (Some(pipeline), Some(readback)) = (
+            world
+                .resource::<PipelineCache>()
+                .get_render_pipeline(depth_readback.pipeline_id),
+            world
+                .resource::<DepthReadbacks>()
+                .get(graph.view_entity(), depth_readback.buffer_index),
+        ) else {
+            return Ok(());
+        };
+
+        #[cfg(feature = "trace")]
+        let _depth_readback_span = info_span!("depth_readback").entered();
+
+        let depth_readback_pipeline = world.resource::<DepthReadbackPipeline>();
+        let layout = if depth_readback.multisampled {
+            &depth_readback_pipeline.layout_msaa
+        } else {
+            &depth_readback_pipeline.layout
+        };
+        let bind_group = render_context.render_device().create_bind_group(
+            "depth_readback_bind_group",
+            layout,
+            &BindGroupEntries::single(depth.depth_view()),
+        );
+
+        let diagnostics = render_context.diagnostic_recorder();
+
+        {
+            let mut render_pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
+                label: Some("depth_readback"),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: &depth_readback.texture.default_view,
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Clear(Default::default()),
+                        store: StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            let pass_span = diagnostics.pass_span(&mut render_pass, "depth_readback");
+
+            // Only the region that is read back needs to be copied.
+            let rect = depth_readback.rect;
+            render_pass.set_scissor_rect(rect.min.x, rect.min.y, rect.width(), rect.height());
+            render_pass.set_render_pipeline(pipeline);
+            render_pass.set_bind_group(0, &bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+
+            pass_span.end(&mut render_pass);
+        }
+
+        render_context.command_encoder().copy_texture_to_buffer(
+            ImageCopyTexture {
+                texture: &depth_readback.texture.texture,
+                mip_level: 0,
+                origin: Origin3d {
+                    x: depth_readback.rect.min.x,
+                    y: depth_readback.rect.min.y,
+                    z: 0,
+                },
+                aspect: TextureAspect::All,
+            },
+            ImageCopyBuffer {
+                buffer: &readback.buffer,
+                layout: ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(readback.padded_bytes_per_row),
+                    rows_per_image: None,
+                },
+            },
+            Extent3d {
+                width: readback.size.x,
+                height: readback.size.y,
+                depth_or_array_layers: 1,
+            },
+        );
+
+        Ok(())
+    }
+}
@@ -10,14 +10,18 @@
 pub mod auto_exposure;
 pub mod blit;
 pub mod bloom;
+pub mod camera_stack;
 pub mod contrast_adaptive_sharpening;
 pub mod core_2d;
 pub mod core_3d;
 pub mod deferred;
+pub mod depth_readback;
 pub mod fullscreen_vertex_shader;
 pub mod fxaa;
 pub mod motion_blur;
 pub mod msaa_writeback;
+pub mod offline_render;
+pub mod partial_redraw;
 pub mod prepass;
 mod skybox;
 mod taa;
@@ -49,19 +53,27 @@ pub mod prelude {
 use crate::{
     blit::BlitPlugin,
     bloom::BloomPlugin,
+    camera_stack::CameraStackPlugin,
     contrast_adaptive_sharpening::CASPlugin,
     core_2d::Core2dPlugin,
     core_3d::Core3dPlugin,
     deferred::copy_lighting_id::CopyDeferredLightingIdPlugin,
+    depth_readback::DepthReadbackPlugin,
     fullscreen_vertex_shader::FULLSCREEN_SHADER_HANDLE,
     fxaa::FxaaPlugin,
     motion_blur::MotionBlurPlugin,
     msaa_writeback::MsaaWritebackPlugin,
-    prepass::{DeferredPrepass, DepthPrepass, MotionVectorPrepass, NormalPrepass},
+    partial_redraw::PartialRedrawPlugin,
+    prepass::{
+        bindings::ViewPrepassBindGroupPlugin,
+        outputs::{check_prepass_requirements, sync_prepass_outputs, PrepassOutputs},
+        DeferredGBufferExtensionPrepass, DeferredPrepass, DepthPrepass, MotionVectorPrepass,
+        NormalPrepass, ObjectIdPrepass,
+    },
     tonemapping::TonemappingPlugin,
     upscaling::UpscalingPlugin,
 };
-use bevy_app::{App, Plugin};
+use bevy_app::{App, Plugin, PostUpdate};
 use bevy_asset::load_internal_asset;
 use bevy_render::prelude::Shader;
 
@@ -81,18 +93,34 @@ impl Plugin for CorePipelinePlugin {
             .register_type::<NormalPrepass>()
             .register_type::<MotionVectorPrepass>()
             .register_type::<DeferredPrepass>()
+            .register_type::<ObjectIdPrepass>()
+            .register_type::<DeferredGBufferExtensionPrepass>()
+            .register_type::<PrepassOutputs>()
+            .add_systems(
+                PostUpdate,
+                (
+                    sync_prepass_outputs,
+                    check_prepass_requirements::<DeferredGBufferExtensionPrepass>(
+                        PrepassOutputs::NONE.with_deferred(true),
+                    ),
+                ),
+            )
             .add_plugins((
                 Core2dPlugin,
                 Core3dPlugin,
                 CopyDeferredLightingIdPlugin,
                 BlitPlugin,
                 MsaaWritebackPlugin,
+                PartialRedrawPlugin,
                 TonemappingPlugin,
                 UpscalingPlugin,
                 BloomPlugin,
                 FxaaPlugin,
                 CASPlugin,
                 MotionBlurPlugin,
+                DepthReadbackPlugin,
+                ViewPrepassBindGroupPlugin,
+                CameraStackPlugin,
             ));
     }
 }
@@ -5,9 +5,12 @@
 
 use crate::{
     core_3d::graph::{Core3d, Node3d},
-    prepass::{DepthPrepass, MotionVectorPrepass},
+    prepass::{
+        outputs::{check_prepass_requirements, PrepassOutputs},
+        DepthPrepass, MotionVectorPrepass,
+    },
 };
-use bevy_app::{App, Plugin};
+use bevy_app::{App, Plugin, PostUpdate};
 use bevy_asset::{load_internal_asset, Handle};
 use bevy_ecs::{
     bundle::Bundle, component::Component, query::With, reflect::ReflectComponent,
@@ -130,7 +133,13 @@ impl Plugin for MotionBlurPlugin {
         app.add_plugins((
             ExtractComponentPlugin::<MotionBlur>::default(),
             UniformComponentPlugin::<MotionBlur>::default(),
-        ));
+        ))
+        .add_systems(
+            PostUpdate,
+            check_prepass_requirements::<MotionBlur>(
+                PrepassOutputs::DEPTH.with_motion_vectors(true),
+            ),
+        );
 
         let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
             return;
@@ -1,5 +1,9 @@
-use bevy_ecs::{query::QueryItem, world::World};
+use bevy_ecs::{
+    query::{Has, QueryItem},
+    world::World,
+};
 use bevy_render::{
+    camera::CameraCut,
     extract_component::ComponentUniforms,
     globals::GlobalsBuffer,
     render_graph::{NodeRunError, RenderGraphContext, ViewNode},
@@ -27,17 +31,23 @@ impl ViewNode for MotionBlurNode {
         &'static MotionBlurPipelineId,
         &'static ViewPrepassTextures,
         &'static MotionBlur,
+        Has<CameraCut>,
     );
     fn run(
         &self,
         _graph: &mut RenderGraphContext,
         render_context: &mut RenderContext,
-        (view_target, pipeline_id, prepass_textures, settings): QueryItem<Self::ViewQuery>,
+        (view_target, pipeline_id, prepass_textures, settings, camera_cut): QueryItem<
+            Self::ViewQuery,
+        >,
         world: &World,
     ) -> Result<(), NodeRunError> {
         if settings.samples == 0 || settings.shutter_angle <= 0.0 {
             return Ok(()); // We can skip running motion blur in these cases.
         }
+        if camera_cut {
+            return Ok(()); // Don't smear the previous shot into the new one.
+        }
 
         let motion_blur_pipeline = world.resource::<MotionBlurPipeline>();
         let pipeline_cache = world.resource::<PipelineCache>();
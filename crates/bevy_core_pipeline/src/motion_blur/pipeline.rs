@@ -16,11 +16,10 @@ use bevy_render::{
         ColorWrites, FragmentState, MultisampleState, PipelineCache, PrimitiveState,
         RenderPipelineDescriptor, Sampler, SamplerBindingType, SamplerDescriptor, ShaderDefVal,
         ShaderStages, ShaderType, SpecializedRenderPipeline, SpecializedRenderPipelines,
-        TextureFormat, TextureSampleType,
+        TextureSampleType,
     },
     renderer::RenderDevice,
-    texture::BevyDefault,
-    view::{ExtractedView, Msaa, ViewTarget},
+    view::{ExtractedView, HdrFormat, Msaa, ViewTarget},
 };
 
 use crate::fullscreen_vertex_shader::fullscreen_shader_vertex_state;
@@ -95,6 +94,7 @@ impl FromWorld for MotionBlurPipeline {
 #[derive(PartialEq, Eq, Hash, Clone, Copy)]
 pub struct MotionBlurPipelineKey {
     hdr: bool,
+    hdr_format: HdrFormat,
     samples: u32,
 }
 
@@ -128,11 +128,7 @@ impl SpecializedRenderPipeline for MotionBlurPipeline {
                 shader_defs,
                 entry_point: "fragment".into(),
                 targets: vec![Some(ColorTargetState {
-                    format: if key.hdr {
-                        ViewTarget::TEXTURE_FORMAT_HDR
-                    } else {
-                        TextureFormat::bevy_default()
-                    },
+                    format: ViewTarget::main_texture_format_for(key.hdr, key.hdr_format),
                     blend: None,
                     write_mask: ColorWrites::ALL,
                 })],
@@ -162,6 +158,7 @@ pub(crate) fn prepare_motion_blur_pipelines(
             &pipeline,
             MotionBlurPipelineKey {
                 hdr: view.hdr,
+                hdr_format: view.hdr_format,
                 samples: msaa.samples(),
             },
         );
@@ -0,0 +1,642 @@
+//! Offline rendering of cinematics, at a quality beyond real-time.
+//!
+//! Insert the [`OfflineRender`] resource to step the app at a fixed frame rate, accumulate several
+//! sub-frames into each output frame, and optionally export every output frame to disk. See
+//! [`OfflineRender`] for more documentation.
+
+use std::{path::PathBuf, time::Duration};
+
+use crate::{
+    core_3d::{
+        graph::{Core3d, Node3d},
+        Camera3d,
+    },
+    fullscreen_vertex_shader::fullscreen_shader_vertex_state,
+};
+use bevy_app::{App, First, Last, Plugin};
+use bevy_asset::{load_internal_asset, Handle};
+use bevy_ecs::{prelude::*, query::QueryItem};
+use bevy_math::vec2;
+use bevy_render::{
+    camera::{ExtractedCamera, TemporalJitter},
+    extract_resource::{ExtractResource, ExtractResourcePlugin},
+    render_graph::{NodeRunError, RenderGraphApp, RenderGraphContext, ViewNode, ViewNodeRunner},
+    render_resource::{
+        binding_types::{texture_2d, uniform_buffer},
+        BindGroupEntries, BindGroupLayout, BindGroupLayoutEntries, CachedRenderPipelineId,
+        ColorTargetState, ColorWrites, DynamicUniformBuffer, Extent3d, FragmentState,
+        MultisampleState, Operations, PipelineCache, PrimitiveState, RenderPassColorAttachment,
+        RenderPassDescriptor, RenderPipelineDescriptor, Shader, ShaderStages, ShaderType,
+        SpecializedRenderPipeline, SpecializedRenderPipelines, TextureDescriptor, TextureDimension,
+        TextureSampleType, TextureUsages,
+    },
+    renderer::{RenderContext, RenderDevice, RenderQueue},
+    texture::CachedTexture,
+    view::{
+        screenshot::ScreenshotManager, ExtractedView, HdrFormat, ViewHistoryTextures, ViewTarget,
+    },
+    Render, RenderApp, RenderSet,
+};
+use bevy_time::{TimeSystem, TimeUpdateStrategy};
+use bevy_utils::tracing::warn;
+
+const SUB_FRAME_ACCUMULATION_SHADER_HANDLE: Handle<Shader> =
+    Handle::weak_from_u128(3981720386519284671);
+
+/// Adds support for offline rendering to the app. See [`OfflineRender`] for details.
+///
+/// Requires the `TimePlugin`, whose time is stepped while [`OfflineRender`] is present.
+pub struct OfflineRenderPlugin;
+
+impl Plugin for OfflineRenderPlugin {
+    fn build(&self, app: &mut App) {
+        load_internal_asset!(
+            app,
+            SUB_FRAME_ACCUMULATION_SHADER_HANDLE,
+            "sub_frame_accumulation.wgsl",
+            Shader::from_wgsl
+        );
+
+        app.add_plugins(ExtractResourcePlugin::<OfflineRender>::default())
+            .add_systems(First, step_offline_render.before(TimeSystem))
+            .add_systems(Last, export_offline_render_frames);
+
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app
+            .init_resource::<SpecializedRenderPipelines<SubFrameAccumulationPipeline>>()
+            .init_resource::<SubFrameAccumulationUniforms>()
+            .add_systems(
+                Render,
+                (
+                    prepare_sub_frame_jitter.in_set(RenderSet::ManageViews),
+                    prepare_sub_frame_accumulation_textures.in_set(RenderSet::PrepareResources),
+                    prepare_sub_frame_accumulation_pipelines.in_set(RenderSet::Prepare),
+                ),
+            )
+            .add_render_graph_node::<ViewNodeRunner<SubFrameAccumulationNode>>(
+                Core3d,
+                Node3d::SubFrameAccumulation,
+            )
+            .add_render_graph_edges(
+                Core3d,
+                (
+                    Node3d::MotionBlur,
+                    // Accumulate after the temporal effects, and before bloom and tonemapping, so
+                    // that they only see the converged image.
+                    Node3d::SubFrameAccumulation,
+                    Node3d::Bloom,
+                ),
+            );
+    }
+
+    fn finish(&self, app: &mut App) {
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+
+        render_app.init_resource::<SubFrameAccumulationPipeline>();
+    }
+}
+
+/// Where the frames of an [`OfflineRender`] are saved.
+#[derive(Clone, Debug)]
+pub struct OfflineRenderExport {
+    /// The window whose content is saved.
+    pub window: Entity,
+    /// The directory the frames are saved to, as `frame_00000.<extension>`, numbered from
+    /// [`OfflineRender::frame`].
+    pub directory: PathBuf,
+    /// The extension of the frames, which selects the image format, such as `"png"`.
+    pub extension: String,
+}
+
+/// Resource that switches the app to offline rendering while it's present.
+///
+/// Offline rendering trades speed for determinism and quality, to render cinematics from within
+/// the engine:
+/// - Time is stepped by a fixed duration every update, instead of following the wall clock, so the
+///   same app renders the same frames however long each of them takes. Time is restored to follow
+///   the wall clock once the resource is removed.
+/// - Each output frame is made of [`Self::sub_frames`] sub-frames, one per update, that are
+///   averaged together before bloom and tonemapping. The sub-frames are spread over the duration
+///   of the output frame, which gives physically accurate motion blur, and the projection of 3D
+///   cameras without temporal anti-aliasing is jittered for each of them, which anti-aliases the
+///   image.
+/// - With [`Self::export`], the last sub-frame of each output frame, which holds their average, is
+///   saved to disk.
+///
+/// Accumulation only applies to 3D cameras. Motion blur and temporal anti-aliasing still work on
+/// each sub-frame, but aren't needed with enough sub-frames, and the ghosting of temporal
+/// anti-aliasing may be more visible than in real-time. Send a
+/// [`ResetViewHistory`](bevy_render::view::ResetViewHistory) event or add a
+/// [`CameraCut`](bevy_render::camera::CameraCut) on camera cuts, so that the sub-frames of the
+/// previous shot aren't blended into the new one.
+///
+/// Requires the [`OfflineRenderPlugin`].
+///
+/// ```
+/// # use bevy_core_pipeline::offline_render::OfflineRender;
+/// # use bevy_ecs::prelude::*;
+/// # fn test(mut commands: Commands) {
+/// // 24 frames per second, each accumulated from 16 sub-frames.
+/// commands.insert_resource(OfflineRender::new(24.0, 16));
+/// # }
+/// ```
+#[derive(Resource, ExtractResource, Clone, Debug)]
+pub struct OfflineRender {
+    /// The number of output frames per second.
+    pub frame_rate: f64,
+    /// The number of sub-frames accumulated into each output frame. `1` disables accumulation.
+    pub sub_frames: u32,
+    /// Where to save the output frames, if anywhere.
+    pub export: Option<OfflineRenderExport>,
+    /// The number of sub-frames rendered so far, including the current one.
+    sub_frame_count: u64,
+}
+
+impl OfflineRender {
+    pub fn new(frame_rate: f64, sub_frames: u32) -> Self {
+        Self {
+            frame_rate,
+            sub_frames,
+            export: None,
+            sub_frame_count: 0,
+        }
+    }
+
+    /// Saves the output frames of `window` to `directory`, in the format given by `extension`.
+    pub fn with_export(
+        mut self,
+        window: Entity,
+        directory: impl Into<PathBuf>,
+        extension: impl Into<String>,
+    ) -> Self {
+        self.export = Some(OfflineRenderExport {
+            window,
+            directory: directory.into(),
+            extension: extension.into(),
+        });
+        self
+    }
+
+    /// The duration time is stepped by on each update, a sub-frame.
+    pub fn timestep(&self) -> Duration {
+        Duration::from_secs_f64(1.0 / (self.frame_rate * self.sub_frames.max(1) as f64))
+    }
+
+    /// The index of the output frame being rendered.
+    pub fn frame(&self) -> u64 {
+        self.sub_frame_count.saturating_sub(1) / self.sub_frames.max(1) as u64
+    }
+
+    /// The index of the sub-frame being rendered in the current output frame.
+    pub fn sub_frame(&self) -> u32 {
+        (self.sub_frame_count.saturating_sub(1) % self.sub_frames.max(1) as u64) as u32
+    }
+
+    /// Whether the current sub-frame is the last one of its output frame, which holds the
+    /// accumulated output frame once rendered.
+    pub fn is_last_sub_frame(&self) -> bool {
+        self.sub_frame() + 1 == self.sub_frames.max(1)
+    }
+
+    /// How much of the current sub-frame is blended into the sub-frames accumulated so far.
+    ///
+    /// This is a running average: the n-th sub-frame contributes 1/n of the accumulated image. A
+    /// reset history restarts the average from the current sub-frame.
+    fn accumulation_weight(&self, history_reset: bool) -> f32 {
+        if history_reset {
+            1.0
+        } else {
+            1.0 / (self.sub_frame() + 1) as f32
+        }
+    }
+}
+
+/// Steps time by the timestep of the [`OfflineRender`], and restores the [`TimeUpdateStrategy`]
+/// once it's removed.
+fn step_offline_render(
+    offline_render: Option<ResMut<OfflineRender>>,
+    mut time_update_strategy: ResMut<TimeUpdateStrategy>,
+    mut was_rendering: Local<bool>,
+) {
+    match offline_render {
+        Some(mut offline_render) => {
+            offline_render.sub_frame_count += 1;
+            *time_update_strategy = TimeUpdateStrategy::ManualDuration(offline_render.timestep());
+            *was_rendering = true;
+        }
+        None if *was_rendering => {
+            *time_update_strategy = TimeUpdateStrategy::Automatic;
+            *was_rendering = false;
+        }
+        None => {}
+    }
+}
+
+/// Saves the output frame to disk on the last sub-frame of each output frame.
+fn export_offline_render_frames(
+    offline_render: Option<Res<OfflineRender>>,
+    mut screenshot_manager: ResMut<ScreenshotManager>,
+) {
+    let Some(offline_render) = offline_render else {
+        return;
+    };
+    let Some(export) = &offline_render.export else {
+        return;
+    };
+    if !offline_render.is_last_sub_frame() {
+        return;
+    }
+
+    let path = export.directory.join(format!(
+        "frame_{:05}.{}",
+        offline_render.frame(),
+        export.extension
+    ));
+    if let Err(err) = screenshot_manager.save_screenshot_to_disk(export.window, &path) {
+        warn!("Couldn't export offline render frame {path:?}: {err}");
+    }
+}
+
+/// Jitters the projection of the 3D views that aren't already jittered, differently on each
+/// sub-frame.
+fn prepare_sub_frame_jitter(
+    mut commands: Commands,
+    offline_render: Option<Res<OfflineRender>>,
+    views: Query<Entity, (With<Camera3d>, With<ExtractedView>, Without<TemporalJitter>)>,
+) {
+    let Some(offline_render) = offline_render else {
+        return;
+    };
+    if offline_render.sub_frames <= 1 {
+        return;
+    }
+
+    // Halton sequence (2, 3) - 0.5, skipping i = 0
+    let halton_sequence = [
+        vec2(0.0, -0.16666666),
+        vec2(-0.25, 0.16666669),
+        vec2(0.25, -0.3888889),
+        vec2(-0.375, -0.055555552),
+        vec2(0.125, 0.2777778),
+        vec2(-0.125, -0.2777778),
+        vec2(0.375, 0.055555582),
+        vec2(-0.4375, 0.3888889),
+    ];
+
+    let offset = halton_sequence[offline_render.sub_frame() as usize % halton_sequence.len()];
+
+    for entity in &views {
+        commands.entity(entity).insert(TemporalJitter { offset });
+    }
+}
+
+#[derive(Clone, Copy, ShaderType)]
+struct SubFrameAccumulationUniform {
+    /// How much of the current sub-frame is blended into the accumulated ones.
+    weight: f32,
+    #[cfg(all(feature = "webgl", target_arch = "wasm32"))]
+    // WebGL2 structs must be 16 byte aligned.
+    _webgl2_padding: bevy_math::Vec3,
+}
+
+#[derive(Resource, Default)]
+struct SubFrameAccumulationUniforms {
+    uniforms: DynamicUniformBuffer<SubFrameAccumulationUniform>,
+}
+
+#[derive(Component)]
+pub struct SubFrameAccumulationTextures {
+    write: CachedTexture,
+    read: CachedTexture,
+    uniform_offset: u32,
+}
+
+fn prepare_sub_frame_accumulation_textures(
+    mut commands: Commands,
+    offline_render: Option<Res<OfflineRender>>,
+    mut view_histories: ResMut<ViewHistoryTextures>,
+    mut accumulation_uniforms: ResMut<SubFrameAccumulationUniforms>,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    views: Query<(Entity, &ExtractedCamera, &ExtractedView), With<Camera3d>>,
+) {
+    let Some(offline_render) = offline_render else {
+        return;
+    };
+    if offline_render.sub_frames <= 1 {
+        return;
+    }
+
+    let Some(mut writer) = accumulation_uniforms.uniforms.get_writer(
+        views.iter().len(),
+        &render_device,
+        &render_queue,
+    ) else {
+        return;
+    };
+
+    for (entity, camera, view) in &views {
+        let Some(physical_target_size) = camera.physical_target_size else {
+            continue;
+        };
+
+        let history = view_histories.get::<OfflineRender>(
+            &render_device,
+            entity,
+            TextureDescriptor {
+                label: Some("sub_frame_accumulation_texture"),
+                size: Extent3d {
+                    depth_or_array_layers: 1,
+                    width: physical_target_size.x,
+                    height: physical_target_size.y,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: view.main_texture_format(),
+                usage: TextureUsages::TEXTURE_BINDING | TextureUsages::RENDER_ATTACHMENT,
+                view_formats: &[],
+            },
+        );
+
+        let uniform_offset = writer.write(&SubFrameAccumulationUniform {
+            weight: offline_render.accumulation_weight(history.reset),
+            #[cfg(all(feature = "webgl", target_arch = "wasm32"))]
+            _webgl2_padding: bevy_math::Vec3::default(),
+        });
+
+        commands
+            .entity(entity)
+            .insert(SubFrameAccumulationTextures {
+                write: history.write,
+                read: history.read,
+                uniform_offset,
+            });
+    }
+}
+
+#[derive(Component)]
+pub struct SubFrameAccumulationPipelineId(CachedRenderPipelineId);
+
+fn prepare_sub_frame_accumulation_pipelines(
+    mut commands: Commands,
+    pipeline_cache: Res<PipelineCache>,
+    mut pipelines: ResMut<SpecializedRenderPipelines<SubFrameAccumulationPipeline>>,
+    pipeline: Res<SubFrameAccumulationPipeline>,
+    views: Query<(Entity, &ExtractedView), With<SubFrameAccumulationTextures>>,
+) {
+    for (entity, view) in &views {
+        let pipeline_id = pipelines.specialize(
+            &pipeline_cache,
+            &pipeline,
+            SubFrameAccumulationPipelineKey {
+                hdr: view.hdr,
+                hdr_format: view.hdr_format,
+            },
+        );
+
+        commands
+            .entity(entity)
+            .insert(SubFrameAccumulationPipelineId(pipeline_id));
+    }
+}
+
+/// Render graph node for sub-frame accumulation.
+#[derive(Default)]
+pub struct SubFrameAccumulationNode;
+
+impl ViewNode for SubFrameAccumulationNode {
+    type ViewQuery = (
+        &'static ExtractedCamera,
+        &'static ViewTarget,
+        &'static SubFrameAccumulationTextures,
+        &'static SubFrameAccumulationPipelineId,
+    );
+
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        (camera, view_target, accumulation_textures, pipeline_id): QueryItem<Self::ViewQuery>,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let accumulation_pipeline = world.resource::<SubFrameAccumulationPipeline>();
+        let accumulation_uniforms = world.resource::<SubFrameAccumulationUniforms>();
+        let pipeline_cache = world.resource::<PipelineCache>();
+        let (Some(pipeline), Some(uniforms_binding)) = (
+            pipeline_cache.get_render_pipeline(pipeline_id.0),
+            accumulation_uniforms.uniforms.binding(),
+        ) else {
+            return Ok(());
+        };
+        let view_target = view_target.post_process_write();
+
+        let bind_group = render_context.render_device().create_bind_group(
+            "sub_frame_accumulation_bind_group",
+            &accumulation_pipeline.layout,
+            &BindGroupEntries::sequential((
+                view_target.source,
+                &accumulation_textures.read.default_view,
+                uniforms_binding,
+            )),
+        );
+
+        let mut accumulation_pass =
+            render_context.begin_tracked_render_pass(RenderPassDescriptor {
+                label: Some("sub_frame_accumulation_pass"),
+                color_attachments: &[
+                    Some(RenderPassColorAttachment {
+                        view: view_target.destination,
+                        resolve_target: None,
+                        ops: Operations::default(),
+                    }),
+                    Some(RenderPassColorAttachment {
+                        view: &accumulation_textures.write.default_view,
+                        resolve_target: None,
+                        ops: Operations::default(),
+                    }),
+                ],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+        accumulation_pass.set_render_pipeline(pipeline);
+        accumulation_pass.set_bind_group(0, &bind_group, &[accumulation_textures.uniform_offset]);
+        if let Some(viewport) = camera.viewport.as_ref() {
+            accumulation_pass.set_camera_viewport(viewport);
+        }
+        accumulation_pass.draw(0..3, 0..1);
+
+        Ok(())
+    }
+}
+
+#[derive(Resource)]
+struct SubFrameAccumulationPipeline {
+    layout: BindGroupLayout,
+}
+
+impl FromWorld for SubFrameAccumulationPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+
+        let layout = render_device.create_bind_group_layout(
+            "sub_frame_accumulation_bind_group_layout",
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::FRAGMENT,
+                (
+                    // View target (read)
+                    texture_2d(TextureSampleType::Float { filterable: false }),
+                    // Accumulated sub-frames (read)
+                    texture_2d(TextureSampleType::Float { filterable: false }),
+                    uniform_buffer::<SubFrameAccumulationUniform>(true),
+                ),
+            ),
+        );
+
+        SubFrameAccumulationPipeline { layout }
+    }
+}
+
+#[derive(PartialEq, Eq, Hash, Clone)]
+struct SubFrameAccumulationPipelineKey {
+    hdr: bool,
+    hdr_format: HdrFormat,
+}
+
+impl SpecializedRenderPipeline for SubFrameAccumulationPipeline {
+    type Key = SubFrameAccumulationPipelineKey;
+
+    fn specialize(&self, key: Self::Key) -> RenderPipelineDescriptor {
+        #[allow(unused_mut)]
+        let mut shader_defs = vec![];
+
+        #[cfg(all(feature = "webgl", target_arch = "wasm32"))]
+        shader_defs.push("SIXTEEN_BYTE_ALIGNMENT".into());
+
+        let format = ViewTarget::main_texture_format_for(key.hdr, key.hdr_format);
+
+        RenderPipelineDescriptor {
+            label: Some("sub_frame_accumulation_pipeline".into()),
+            layout: vec![self.layout.clone()],
+            vertex: fullscreen_shader_vertex_state(),
+            fragment: Some(FragmentState {
+                shader: SUB_FRAME_ACCUMULATION_SHADER_HANDLE,
+                shader_defs,
+                entry_point: "accumulate".into(),
+                targets: vec![
+                    Some(ColorTargetState {
+                        format,
+                        blend: None,
+                        write_mask: ColorWrites::ALL,
+                    }),
+                    Some(ColorTargetState {
+                        format,
+                        blend: None,
+                        write_mask: ColorWrites::ALL,
+                    }),
+                ],
+            }),
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            push_constant_ranges: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::OfflineRender;
+
+    #[test]
+    fn sub_frame_progress() {
+        let mut offline_render = OfflineRender::new(24.0, 4);
+        let mut progress = vec![];
+        for _ in 0..6 {
+            offline_render.sub_frame_count += 1;
+            progress.push((
+                offline_render.frame(),
+                offline_render.sub_frame(),
+                offline_render.is_last_sub_frame(),
+            ));
+        }
+        assert_eq!(
+            progress,
+            [
+                (0, 0, false),
+                (0, 1, false),
+                (0, 2, false),
+                (0, 3, true),
+                (1, 0, false),
+                (1, 1, false),
+            ]
+        );
+        assert_eq!(
+            offline_render.timestep(),
+            std::time::Duration::from_secs_f64(1.0 / 96.0)
+        );
+    }
+
+    #[test]
+    fn single_sub_frame_progress() {
+        for sub_frames in [0, 1] {
+            let mut offline_render = OfflineRender::new(30.0, sub_frames);
+            for frame in 0..3 {
+                offline_render.sub_frame_count += 1;
+                assert_eq!(offline_render.frame(), frame);
+                assert_eq!(offline_render.sub_frame(), 0);
+                assert!(offline_render.is_last_sub_frame());
+                assert_eq!(offline_render.accumulation_weight(false), 1.0);
+            }
+            assert_eq!(
+                offline_render.timestep(),
+                std::time::Duration::from_secs_f64(1.0 / 30.0)
+            );
+        }
+    }
+
+    /// Blends sub-frames the way the accumulation shader does.
+    fn accumulate(offline_render: &mut OfflineRender, values: &[f32], resets: &[bool]) -> f32 {
+        let mut accumulated = 0.0;
+        for (&value, &reset) in values.iter().zip(resets) {
+            offline_render.sub_frame_count += 1;
+            let weight = offline_render.accumulation_weight(reset);
+            accumulated += (value - accumulated) * weight;
+        }
+        accumulated
+    }
+
+    #[test]
+    fn sub_frames_are_averaged() {
+        let mut offline_render = OfflineRender::new(24.0, 4);
+        let accumulated = accumulate(
+            &mut offline_render,
+            &[1.0, 2.0, 3.0, 6.0],
+            &[false, false, false, false],
+        );
+        assert!(offline_render.is_last_sub_frame());
+        assert!((accumulated - 3.0).abs() < 1e-6);
+
+        // The next output frame starts a new average.
+        let accumulated = accumulate(&mut offline_render, &[8.0, 4.0], &[false, false]);
+        assert_eq!(offline_render.frame(), 1);
+        assert!((accumulated - 6.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn reset_history_discards_accumulated_sub_frames() {
+        let mut offline_render = OfflineRender::new(24.0, 4);
+        // The first sub-frames belong to the previous shot, and are discarded on the reset.
+        let accumulated = accumulate(
+            &mut offline_render,
+            &[100.0, 100.0, 2.0],
+            &[false, false, true],
+        );
+        assert_eq!(accumulated, 2.0);
+    }
+}
@@ -0,0 +1,35 @@
+// Averages the sub-frames of an offline render output frame, see `OfflineRender`.
+
+#import bevy_core_pipeline::fullscreen_vertex_shader::FullscreenVertexOutput
+
+struct SubFrameAccumulation {
+    weight: f32,
+#ifdef SIXTEEN_BYTE_ALIGNMENT
+    // WebGL2 structs must be 16 byte aligned.
+    _webgl2_padding: vec3<f32>
+#endif
+}
+
+@group(0) @binding(0) var view_target: texture_2d<f32>;
+@group(0) @binding(1) var accumulated: texture_2d<f32>;
+@group(0) @binding(2) var<uniform> settings: SubFrameAccumulation;
+
+struct Output {
+    @location(0) view_target: vec4<f32>,
+    @location(1) accumulated: vec4<f32>,
+};
+
+@fragment
+fn accumulate(in: FullscreenVertexOutput) -> Output {
+    let coords = vec2<i32>(in.position.xy);
+    let current = textureLoad(view_target, coords, 0);
+    let accumulated = textureLoad(accumulated, coords, 0);
+
+    // Running average of the sub-frames: a weight of 1/n for the n-th sub-frame.
+    let color = mix(accumulated, current, settings.weight);
+
+    var out: Output;
+    out.view_target = color;
+    out.accumulated = color;
+    return out;
+}
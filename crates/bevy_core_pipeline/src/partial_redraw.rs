@@ -0,0 +1,356 @@
+use crate::blit::{BlitPipeline, BlitPipelineKey};
+use bevy_app::{App, First, Plugin};
+use bevy_color::LinearRgba;
+use bevy_ecs::{entity::EntityHashMap, prelude::*, query::QueryItem};
+use bevy_math::{Mat4, URect, UVec4};
+use bevy_reflect::{std_traits::ReflectDefault, Reflect};
+use bevy_render::{
+    camera::{Camera, ExtractedCamera, TemporalJitter},
+    extract_component::{ExtractComponent, ExtractComponentPlugin},
+    render_graph::{NodeRunError, RenderGraphContext, ViewNode},
+    render_phase::TrackedRenderPass,
+    render_resource::*,
+    renderer::{RenderContext, RenderDevice},
+    view::{ExtractedView, ViewHistoryTexture, ViewHistoryTextures, ViewTarget},
+    Render, RenderApp, RenderSet,
+};
+use bevy_transform::components::GlobalTransform;
+
+/// Adds support for [`PartialRedraw`] to the `core_2d` and `core_3d` pipelines.
+pub struct PartialRedrawPlugin;
+
+impl Plugin for PartialRedrawPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<PartialRedraw>()
+            .add_plugins(ExtractComponentPlugin::<PartialRedraw>::default())
+            .add_systems(First, clear_partial_redraws);
+
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app.add_systems(
+            Render,
+            prepare_partial_redraws.in_set(RenderSet::PrepareResources),
+        );
+    }
+}
+
+/// Add this to a camera to only redraw the regions of its view that changed, keeping the rest of
+/// the previous frame. This saves GPU time in tools and editors whose views are mostly static.
+///
+/// Mark the regions that changed each frame with [`PartialRedraw::mark_dirty`]. They're cleared
+/// at the start of the next frame, and a frame with no dirty region redraws nothing.
+///
+/// The main passes are restricted to a scissor rect, the bounding rect of the dirty regions, and
+/// the rest of the viewport is copied from the previous frame before post-processing.
+/// Post-processing, such as bloom and tonemapping, and the UI still run on the whole view.
+/// Effects that sample the main texture during the main passes, like screen space specular
+/// transmission, only see the regions that are redrawn.
+///
+/// The whole view is redrawn when the transform, projection or viewport of the camera changed,
+/// when its history is reset, such as by a camera cut or a resize, and on every frame for cameras
+/// with a [`TemporalJitter`], since it moves every pixel.
+#[derive(Component, ExtractComponent, Reflect, Clone, Debug, Default)]
+#[extract_component_filter(With<Camera>)]
+#[reflect(Component, Default)]
+pub struct PartialRedraw {
+    dirty_regions: Vec<URect>,
+    all_dirty: bool,
+}
+
+impl PartialRedraw {
+    /// Marks `region` as changed this frame, in physical pixels relative to the top left corner
+    /// of the viewport.
+    pub fn mark_dirty(&mut self, region: URect) {
+        if !region.is_empty() {
+            self.dirty_regions.push(region);
+        }
+    }
+
+    /// Marks the whole view as changed this frame.
+    pub fn mark_all_dirty(&mut self) {
+        self.all_dirty = true;
+    }
+
+    /// The regions marked as changed this frame.
+    pub fn dirty_regions(&self) -> &[URect] {
+        &self.dirty_regions
+    }
+
+    /// Whether the whole view was marked as changed this frame.
+    pub fn is_all_dirty(&self) -> bool {
+        self.all_dirty
+    }
+}
+
+fn clear_partial_redraws(mut partial_redraws: Query<&mut PartialRedraw>) {
+    for mut partial_redraw in &mut partial_redraws {
+        if partial_redraw.all_dirty || !partial_redraw.dirty_regions.is_empty() {
+            partial_redraw.dirty_regions.clear();
+            partial_redraw.all_dirty = false;
+        }
+    }
+}
+
+/// The partial redraw of a view this frame, prepared from its [`PartialRedraw`].
+#[derive(Component)]
+pub struct ViewPartialRedraw {
+    /// The region of the target the main passes are restricted to, or `None` if the whole view
+    /// is redrawn.
+    pub scissor: Option<URect>,
+    /// The viewport of the view, in physical pixels of the target.
+    pub viewport: URect,
+    history: ViewHistoryTexture,
+    pipeline: CachedRenderPipelineId,
+}
+
+impl ViewPartialRedraw {
+    /// Restricts `render_pass` to the [`ViewPartialRedraw::scissor`], if any.
+    pub fn set_scissor_rect(&self, render_pass: &mut TrackedRenderPass) {
+        if let Some(scissor) = self.scissor {
+            render_pass.set_scissor_rect(
+                scissor.min.x,
+                scissor.min.y,
+                scissor.width(),
+                scissor.height(),
+            );
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn prepare_partial_redraws(
+    mut commands: Commands,
+    render_device: Res<RenderDevice>,
+    pipeline_cache: Res<PipelineCache>,
+    mut pipelines: ResMut<SpecializedRenderPipelines<BlitPipeline>>,
+    blit_pipeline: Res<BlitPipeline>,
+    mut view_histories: ResMut<ViewHistoryTextures>,
+    mut previous_cameras: Local<EntityHashMap<(GlobalTransform, Mat4, UVec4)>>,
+    views: Query<(
+        Entity,
+        &ExtractedCamera,
+        &ExtractedView,
+        &ViewTarget,
+        &PartialRedraw,
+        Has<TemporalJitter>,
+    )>,
+) {
+    let mut cameras = EntityHashMap::default();
+    for (entity, camera, view, view_target, partial_redraw, temporal_jitter) in &views {
+        let Some(physical_target_size) = camera.physical_target_size else {
+            continue;
+        };
+
+        let history = view_histories.get::<PartialRedraw>(
+            &render_device,
+            entity,
+            TextureDescriptor {
+                label: Some("partial_redraw_history_texture"),
+                size: Extent3d {
+                    width: physical_target_size.x,
+                    height: physical_target_size.y,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: view_target.main_texture_format(),
+                usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            },
+        );
+
+        let pipeline = pipelines.specialize(
+            &pipeline_cache,
+            &blit_pipeline,
+            BlitPipelineKey {
+                texture_format: view_target.main_texture_format(),
+                samples: 1,
+                blend_state: None,
+            },
+        );
+
+        let camera_state = (view.transform, view.projection, view.viewport);
+        let camera_changed = previous_cameras.get(&entity) != Some(&camera_state);
+        cameras.insert(entity, camera_state);
+
+        let viewport = URect::new(
+            view.viewport.x,
+            view.viewport.y,
+            view.viewport.x + view.viewport.z,
+            view.viewport.y + view.viewport.w,
+        );
+        let scissor =
+            if camera_changed || history.reset || temporal_jitter || partial_redraw.all_dirty {
+                None
+            } else {
+                let dirty_bounds = partial_redraw
+                    .dirty_regions
+                    .iter()
+                    .copied()
+                    .reduce(|bounds, region| bounds.union(region));
+                Some(match dirty_bounds {
+                    Some(bounds) => URect::from_corners(
+                        viewport.min.saturating_add(bounds.min),
+                        viewport.min.saturating_add(bounds.max),
+                    )
+                    .intersect(viewport),
+                    None => URect::from_corners(viewport.min, viewport.min),
+                })
+            };
+
+        commands.entity(entity).insert(ViewPartialRedraw {
+            scissor,
+            viewport,
+            history,
+            pipeline,
+        });
+    }
+    *previous_cameras = cameras;
+}
+
+/// Copies the regions of the viewport that weren't redrawn from the previous frame, then keeps
+/// the main texture for the next frame.
+#[derive(Default)]
+pub struct PartialRedrawNode;
+
+impl ViewNode for PartialRedrawNode {
+    type ViewQuery = (&'static ViewTarget, &'static ViewPartialRedraw);
+
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        (target, partial_redraw): QueryItem<Self::ViewQuery>,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let blit_pipeline = world.resource::<BlitPipeline>();
+        let pipeline_cache = world.resource::<PipelineCache>();
+        let Some(pipeline) = pipeline_cache.get_render_pipeline(partial_redraw.pipeline) else {
+            return Ok(());
+        };
+
+        if let Some(scissor) = partial_redraw.scissor {
+            let bind_group = render_context.render_device().create_bind_group(
+                None,
+                &blit_pipeline.texture_bind_group,
+                &BindGroupEntries::sequential((
+                    &partial_redraw.history.read.default_view,
+                    &blit_pipeline.sampler,
+                )),
+            );
+
+            let mut render_pass =
+                render_context
+                    .command_encoder()
+                    .begin_render_pass(&RenderPassDescriptor {
+                        label: Some("partial_redraw_restore"),
+                        color_attachments: &[Some(RenderPassColorAttachment {
+                            view: target.main_texture_view(),
+                            resolve_target: None,
+                            ops: Operations {
+                                load: LoadOp::Load,
+                                store: StoreOp::Store,
+                            },
+                        })],
+                        depth_stencil_attachment: None,
+                        timestamp_writes: None,
+                        occlusion_query_set: None,
+                    });
+
+            render_pass.set_pipeline(pipeline);
+            render_pass.set_bind_group(0, &bind_group, &[]);
+            for region in regions_outside(partial_redraw.viewport, scissor) {
+                render_pass.set_scissor_rect(
+                    region.min.x,
+                    region.min.y,
+                    region.width(),
+                    region.height(),
+                );
+                render_pass.draw(0..3, 0..1);
+            }
+        }
+
+        let bind_group = render_context.render_device().create_bind_group(
+            None,
+            &blit_pipeline.texture_bind_group,
+            &BindGroupEntries::sequential((target.main_texture_view(), &blit_pipeline.sampler)),
+        );
+
+        let mut render_pass =
+            render_context
+                .command_encoder()
+                .begin_render_pass(&RenderPassDescriptor {
+                    label: Some("partial_redraw_history"),
+                    color_attachments: &[Some(RenderPassColorAttachment {
+                        view: &partial_redraw.history.write.default_view,
+                        resolve_target: None,
+                        ops: Operations {
+                            load: LoadOp::Clear(LinearRgba::BLACK.into()),
+                            store: StoreOp::Store,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                    timestamp_writes: None,
+                    occlusion_query_set: None,
+                });
+
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_group(0, &bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+
+        Ok(())
+    }
+}
+
+/// Splits the part of `viewport` outside of `scissor` into up to four non-empty rects.
+fn regions_outside(viewport: URect, scissor: URect) -> impl Iterator<Item = URect> {
+    [
+        URect::new(
+            viewport.min.x,
+            viewport.min.y,
+            viewport.max.x,
+            scissor.min.y,
+        ),
+        URect::new(
+            viewport.min.x,
+            scissor.max.y,
+            viewport.max.x,
+            viewport.max.y,
+        ),
+        URect::new(viewport.min.x, scissor.min.y, scissor.min.x, scissor.max.y),
+        URect::new(scissor.max.x, scissor.min.y, viewport.max.x, scissor.max.y),
+    ]
+    .into_iter()
+    .filter(|region| !region.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::regions_outside;
+    use bevy_math::URect;
+
+    #[test]
+    fn regions_outside_cover_the_viewport() {
+        let viewport = URect::new(10, 20, 110, 220);
+        let scissor = URect::new(30, 40, 60, 90);
+        let regions: Vec<_> = regions_outside(viewport, scissor).collect();
+        assert_eq!(regions.len(), 4);
+
+        let area = |rect: URect| rect.width() * rect.height();
+        assert_eq!(
+            regions.iter().copied().map(area).sum::<u32>() + area(scissor),
+            area(viewport)
+        );
+        assert!(regions
+            .iter()
+            .all(|region| region.intersect(scissor).is_empty()));
+
+        let empty = URect::from_corners(viewport.min, viewport.min);
+        assert_eq!(
+            regions_outside(viewport, empty).collect::<Vec<_>>(),
+            vec![viewport]
+        );
+    }
+}
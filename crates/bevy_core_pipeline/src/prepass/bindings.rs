@@ -0,0 +1,227 @@
+//! A per-view bind group exposing the prepass textures to post-processing
+//! effects and custom render graph nodes.
+//!
+//! Every view with a [`ViewPrepassTextures`] component gets a
+//! [`ViewPrepassBindGroup`] with the same layout, whichever prepasses are
+//! enabled: missing textures are replaced by fallback textures. Effects only
+//! need to pick the layout matching the view's MSAA sample count, and declare
+//! the following bindings in their shader:
+//!
+//! ```wgsl
+//! // With MSAA, use `texture_depth_multisampled_2d` and
+//! // `texture_multisampled_2d` for every binding but the deferred G-buffer.
+//! @group(1) @binding(0) var prepass_depth: texture_depth_2d;
+//! @group(1) @binding(1) var prepass_normal: texture_2d<f32>;
+//! @group(1) @binding(2) var prepass_motion_vectors: texture_2d<f32>;
+//! @group(1) @binding(3) var deferred_gbuffer: texture_2d<u32>;
+//! @group(1) @binding(4) var prepass_object_id: texture_2d<u32>;
+//! ```
+//!
+//! The group index is up to the effect. The normal texture holds world space
+//! normals remapped to `0..1`. Roughness and the other material properties are
+//! only available with the [`DeferredPrepass`](super::DeferredPrepass), packed
+//! into the deferred G-buffer: they can be unpacked with
+//! `bevy_pbr::pbr_deferred_functions::pbr_input_from_deferred_gbuffer`.
+
+use bevy_app::{App, Plugin};
+use bevy_ecs::prelude::*;
+use bevy_render::{
+    render_resource::{
+        binding_types::{
+            texture_2d, texture_2d_multisampled, texture_depth_2d, texture_depth_2d_multisampled,
+        },
+        BindGroup, BindGroupEntries, BindGroupLayout, BindGroupLayoutEntries, ShaderStages,
+        TextureAspect, TextureSampleType, TextureViewDescriptor,
+    },
+    renderer::RenderDevice,
+    texture::FallbackImageMsaa,
+    view::Msaa,
+    Render, RenderApp, RenderSet,
+};
+use bevy_utils::default;
+
+use crate::{
+    core_3d::CORE_3D_DEPTH_FORMAT,
+    deferred::DEFERRED_PREPASS_FORMAT,
+    prepass::{
+        ViewPrepassTextures, MOTION_VECTOR_PREPASS_FORMAT, NORMAL_PREPASS_FORMAT,
+        OBJECT_ID_PREPASS_FORMAT,
+    },
+};
+
+/// The binding of the prepass depth texture in [`ViewPrepassBindGroup`].
+pub const VIEW_PREPASS_DEPTH_BINDING: u32 = 0;
+/// The binding of the prepass normal texture in [`ViewPrepassBindGroup`].
+pub const VIEW_PREPASS_NORMAL_BINDING: u32 = 1;
+/// The binding of the prepass motion vectors texture in [`ViewPrepassBindGroup`].
+pub const VIEW_PREPASS_MOTION_VECTORS_BINDING: u32 = 2;
+/// The binding of the deferred G-buffer texture in [`ViewPrepassBindGroup`].
+pub const VIEW_PREPASS_DEFERRED_BINDING: u32 = 3;
+/// The binding of the prepass object IDs texture in [`ViewPrepassBindGroup`].
+pub const VIEW_PREPASS_OBJECT_ID_BINDING: u32 = 4;
+
+/// Prepares a [`ViewPrepassBindGroup`] for every view with prepass textures.
+pub struct ViewPrepassBindGroupPlugin;
+
+impl Plugin for ViewPrepassBindGroupPlugin {
+    fn build(&self, app: &mut App) {
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+
+        render_app.add_systems(
+            Render,
+            prepare_view_prepass_bind_groups.in_set(RenderSet::PrepareBindGroups),
+        );
+    }
+
+    fn finish(&self, app: &mut App) {
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+
+        render_app.init_resource::<ViewPrepassBindGroupLayouts>();
+    }
+}
+
+/// The layouts of [`ViewPrepassBindGroup`], for views with and without MSAA.
+///
+/// The deferred G-buffer is never multisampled, as the deferred prepass
+/// doesn't support MSAA.
+#[derive(Resource)]
+pub struct ViewPrepassBindGroupLayouts {
+    pub layout: BindGroupLayout,
+    pub layout_msaa: BindGroupLayout,
+}
+
+impl ViewPrepassBindGroupLayouts {
+    /// Returns the layout for views with or without MSAA.
+    pub fn get(&self, multisampled: bool) -> &BindGroupLayout {
+        if multisampled {
+            &self.layout_msaa
+        } else {
+            &self.layout
+        }
+    }
+}
+
+impl FromWorld for ViewPrepassBindGroupLayouts {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+
+        let layout = render_device.create_bind_group_layout(
+            "view_prepass_bind_group_layout",
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::FRAGMENT | ShaderStages::COMPUTE,
+                (
+                    texture_depth_2d(),
+                    texture_2d(TextureSampleType::Float { filterable: false }),
+                    texture_2d(TextureSampleType::Float { filterable: false }),
+                    texture_2d(TextureSampleType::Uint),
+                    texture_2d(TextureSampleType::Uint),
+                ),
+            ),
+        );
+        let layout_msaa = render_device.create_bind_group_layout(
+            "view_prepass_bind_group_layout_msaa",
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::FRAGMENT | ShaderStages::COMPUTE,
+                (
+                    texture_depth_2d_multisampled(),
+                    texture_2d_multisampled(TextureSampleType::Float { filterable: false }),
+                    texture_2d_multisampled(TextureSampleType::Float { filterable: false }),
+                    texture_2d(TextureSampleType::Uint),
+                    texture_2d_multisampled(TextureSampleType::Uint),
+                ),
+            ),
+        );
+
+        Self {
+            layout,
+            layout_msaa,
+        }
+    }
+}
+
+/// The prepass textures of a view, bound with a stable layout. See the
+/// [module documentation](self) for the bindings.
+#[derive(Component)]
+pub struct ViewPrepassBindGroup {
+    /// The bind group, using the layout from [`ViewPrepassBindGroupLayouts`]
+    /// matching [`ViewPrepassBindGroup::multisampled`].
+    pub bind_group: BindGroup,
+    /// Whether the depth, normal, motion vectors and object IDs textures are
+    /// multisampled.
+    pub multisampled: bool,
+}
+
+fn prepare_view_prepass_bind_groups(
+    mut commands: Commands,
+    render_device: Res<RenderDevice>,
+    layouts: Res<ViewPrepassBindGroupLayouts>,
+    mut fallback_images: FallbackImageMsaa,
+    msaa: Res<Msaa>,
+    views: Query<(Entity, &ViewPrepassTextures)>,
+) {
+    let samples = msaa.samples();
+    let multisampled = samples > 1;
+
+    for (entity, prepass_textures) in &views {
+        let depth = match &prepass_textures.depth {
+            Some(depth) => depth.texture.texture.create_view(&TextureViewDescriptor {
+                label: Some("view_prepass_depth"),
+                aspect: TextureAspect::DepthOnly,
+                ..default()
+            }),
+            None => fallback_images
+                .image_for_samplecount(samples, CORE_3D_DEPTH_FORMAT)
+                .texture_view
+                .clone(),
+        };
+        let normal = match prepass_textures.normal_view() {
+            Some(normal) => normal.clone(),
+            None => fallback_images
+                .image_for_samplecount(samples, NORMAL_PREPASS_FORMAT)
+                .texture_view
+                .clone(),
+        };
+        let motion_vectors = match prepass_textures.motion_vectors_view() {
+            Some(motion_vectors) => motion_vectors.clone(),
+            None => fallback_images
+                .image_for_samplecount(samples, MOTION_VECTOR_PREPASS_FORMAT)
+                .texture_view
+                .clone(),
+        };
+        let deferred = match prepass_textures.deferred_view() {
+            Some(deferred) => deferred.clone(),
+            None => fallback_images
+                .image_for_samplecount(1, DEFERRED_PREPASS_FORMAT)
+                .texture_view
+                .clone(),
+        };
+        let object_id = match prepass_textures.object_id_view() {
+            Some(object_id) => object_id.clone(),
+            None => fallback_images
+                .image_for_samplecount(samples, OBJECT_ID_PREPASS_FORMAT)
+                .texture_view
+                .clone(),
+        };
+
+        let bind_group = render_device.create_bind_group(
+            "view_prepass_bind_group",
+            layouts.get(multisampled),
+            &BindGroupEntries::sequential((
+                &depth,
+                &normal,
+                &motion_vectors,
+                &deferred,
+                &object_id,
+            )),
+        );
+
+        commands.entity(entity).insert(ViewPrepassBindGroup {
+            bind_group,
+            multisampled,
+        });
+    }
+}
@@ -8,9 +8,17 @@
 //! [`DepthPrepass`]
 //! [`NormalPrepass`]
 //! [`MotionVectorPrepass`]
+//! [`ObjectIdPrepass`]
+//!
+//! Alternatively, [`PrepassOutputs`](outputs::PrepassOutputs) enables exactly the given prepasses with a single
+//! component. Effects that read the prepass textures report cameras missing the prepasses they need, see
+//! [`check_prepass_requirements`](outputs::check_prepass_requirements).
 //!
 //! The textures are automatically added to the default mesh view bindings. You can also get the raw textures
 //! by querying the [`ViewPrepassTextures`] component on any camera with a prepass component.
+//! Post-processing effects and custom render graph nodes can bind all of them at once with the
+//! [`ViewPrepassBindGroup`](bindings::ViewPrepassBindGroup), whose layout doesn't depend on which
+//! prepasses are enabled.
 //!
 //! The depth prepass will always run and generate the depth buffer as a side effect, but it won't copy it
 //! to a separate texture unless the [`DepthPrepass`] is activated. This means that if any prepass component is present
@@ -25,7 +33,9 @@
 //!
 //! Currently only works for 3D.
 
+pub mod bindings;
 pub mod node;
+pub mod outputs;
 
 use std::ops::Range;
 
@@ -44,6 +54,7 @@ use bevy_render::{
 
 pub const NORMAL_PREPASS_FORMAT: TextureFormat = TextureFormat::Rgb10a2Unorm;
 pub const MOTION_VECTOR_PREPASS_FORMAT: TextureFormat = TextureFormat::Rg16Float;
+pub const OBJECT_ID_PREPASS_FORMAT: TextureFormat = TextureFormat::R32Uint;
 
 /// If added to a [`crate::prelude::Camera3d`] then depth values will be copied to a separate texture available to the main pass.
 #[derive(Component, Default, Reflect, Clone)]
@@ -58,11 +69,27 @@ pub struct NormalPrepass;
 #[derive(Component, Default, Reflect, Clone)]
 pub struct MotionVectorPrepass;
 
+/// If added to a [`crate::prelude::Camera3d`] then the index of the entity each pixel belongs to will be copied to a
+/// separate texture, plus one so that zero means no entity.
+///
+/// Paired with [`MotionVectorPrepass`], this tells which object each motion vector belongs to, for custom reprojection
+/// effects or for exporting per-object motion to external tools. Meshlet meshes don't write object IDs.
+#[derive(Component, Default, Reflect, Clone)]
+pub struct ObjectIdPrepass;
+
 /// If added to a [`crate::prelude::Camera3d`] then deferred materials will be rendered to the deferred gbuffer texture and will be available to subsequent passes.
 /// Note the default deferred lighting plugin also requires `DepthPrepass` to work correctly.
 #[derive(Component, Default, Reflect)]
 pub struct DeferredPrepass;
 
+/// If added to a [`crate::prelude::Camera3d`] with [`DeferredPrepass`], deferred materials can write four `u32` channels
+/// of their own to a G-buffer extension texture, such as a custom ID or an anisotropy direction, for a custom deferred
+/// lighting shader to read. See [`DEFERRED_GBUFFER_EXTENSION_FORMAT`].
+///
+/// Materials that don't write to it leave it cleared to zero.
+#[derive(Component, Default, Reflect, Clone)]
+pub struct DeferredGBufferExtensionPrepass;
+
 /// Textures that are written to by the prepass.
 ///
 /// This component will only be present if any of the relevant prepass components are also present.
@@ -77,12 +104,18 @@ pub struct ViewPrepassTextures {
     /// The motion vectors texture generated by the prepass.
     /// Exists only if [`MotionVectorPrepass`] is added to the `ViewTarget`
     pub motion_vectors: Option<ColorAttachment>,
+    /// The object IDs texture generated by the prepass. See [`OBJECT_ID_PREPASS_FORMAT`].
+    /// Exists only if [`ObjectIdPrepass`] is added to the `ViewTarget`
+    pub object_id: Option<ColorAttachment>,
     /// The deferred gbuffer generated by the deferred pass.
     /// Exists only if [`DeferredPrepass`] is added to the `ViewTarget`
     pub deferred: Option<ColorAttachment>,
     /// A texture that specifies the deferred lighting pass id for a material.
     /// Exists only if [`DeferredPrepass`] is added to the `ViewTarget`
     pub deferred_lighting_pass_id: Option<ColorAttachment>,
+    /// The G-buffer extension texture written by the deferred pass. See [`DEFERRED_GBUFFER_EXTENSION_FORMAT`].
+    /// Exists only if [`DeferredPrepass`] and [`DeferredGBufferExtensionPrepass`] are added to the `ViewTarget`
+    pub deferred_gbuffer_extension: Option<ColorAttachment>,
     /// The size of the textures.
     pub size: Extent3d,
 }
@@ -102,9 +135,19 @@ impl ViewPrepassTextures {
             .map(|t| &t.texture.default_view)
     }
 
+    pub fn object_id_view(&self) -> Option<&TextureView> {
+        self.object_id.as_ref().map(|t| &t.texture.default_view)
+    }
+
     pub fn deferred_view(&self) -> Option<&TextureView> {
         self.deferred.as_ref().map(|t| &t.texture.default_view)
     }
+
+    pub fn deferred_gbuffer_extension_view(&self) -> Option<&TextureView> {
+        self.deferred_gbuffer_extension
+            .as_ref()
+            .map(|t| &t.texture.default_view)
+    }
 }
 
 /// Opaque phase of the 3D prepass.
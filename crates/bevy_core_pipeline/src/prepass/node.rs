@@ -58,6 +58,10 @@ impl ViewNode for PrepassNode {
             // Use None in place of deferred attachments
             None,
             None,
+            view_prepass_textures
+                .object_id
+                .as_ref()
+                .map(|object_id_texture| object_id_texture.get_attachment()),
         ];
 
         // If all color attachments are none: clear the color attachment list so that no fragment shader is required
@@ -0,0 +1,230 @@
+//! Choose the prepass outputs of a camera with a single component, and check that the effects of
+//! a camera get the prepass outputs they read.
+
+use bevy_ecs::{prelude::*, system::EntityCommands};
+use bevy_reflect::{std_traits::ReflectDefault, Reflect};
+use bevy_utils::{get_short_name, tracing::error};
+
+use super::{DeferredPrepass, DepthPrepass, MotionVectorPrepass, NormalPrepass, ObjectIdPrepass};
+
+/// Enables exactly the given prepass outputs on a [`Camera3d`](crate::prelude::Camera3d).
+///
+/// This is an alternative to adding the [`DepthPrepass`], [`NormalPrepass`],
+/// [`MotionVectorPrepass`], [`DeferredPrepass`] and [`ObjectIdPrepass`] components one by one:
+/// whenever it changes, the prepass components of the camera are inserted or removed to match it,
+/// replacing any that were added by hand.
+#[derive(Component, Reflect, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[reflect(Component, Default, PartialEq)]
+pub struct PrepassOutputs {
+    /// Copies the depth to a texture, see [`DepthPrepass`].
+    pub depth: bool,
+    /// Writes world normals to a texture, see [`NormalPrepass`].
+    pub normal: bool,
+    /// Writes screen space motion vectors to a texture, see [`MotionVectorPrepass`].
+    pub motion_vectors: bool,
+    /// Renders deferred materials to the G-buffer, see [`DeferredPrepass`].
+    pub deferred: bool,
+    /// Writes the index of the entity of each pixel to a texture, see [`ObjectIdPrepass`].
+    pub object_id: bool,
+}
+
+impl PrepassOutputs {
+    /// No prepass output.
+    pub const NONE: Self = Self {
+        depth: false,
+        normal: false,
+        motion_vectors: false,
+        deferred: false,
+        object_id: false,
+    };
+
+    /// Only the depth.
+    pub const DEPTH: Self = Self {
+        depth: true,
+        ..Self::NONE
+    };
+
+    /// The depth and normals, as read by screen space ambient occlusion.
+    pub const DEPTH_NORMAL: Self = Self {
+        normal: true,
+        ..Self::DEPTH
+    };
+
+    /// The depth, normals and motion vectors.
+    pub const DEPTH_NORMAL_MOTION_VECTORS: Self = Self {
+        motion_vectors: true,
+        ..Self::DEPTH_NORMAL
+    };
+
+    /// The depth, motion vectors and G-buffer of deferred rendering.
+    pub const DEFERRED: Self = Self {
+        depth: true,
+        motion_vectors: true,
+        deferred: true,
+        ..Self::NONE
+    };
+
+    /// Sets [`Self::depth`].
+    pub const fn with_depth(mut self, depth: bool) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    /// Sets [`Self::normal`].
+    pub const fn with_normal(mut self, normal: bool) -> Self {
+        self.normal = normal;
+        self
+    }
+
+    /// Sets [`Self::motion_vectors`].
+    pub const fn with_motion_vectors(mut self, motion_vectors: bool) -> Self {
+        self.motion_vectors = motion_vectors;
+        self
+    }
+
+    /// Sets [`Self::deferred`].
+    pub const fn with_deferred(mut self, deferred: bool) -> Self {
+        self.deferred = deferred;
+        self
+    }
+
+    /// Sets [`Self::object_id`].
+    pub const fn with_object_id(mut self, object_id: bool) -> Self {
+        self.object_id = object_id;
+        self
+    }
+
+    /// Returns the outputs of `required` that aren't enabled in `self`.
+    pub const fn missing(&self, required: &PrepassOutputs) -> PrepassOutputs {
+        Self {
+            depth: required.depth && !self.depth,
+            normal: required.normal && !self.normal,
+            motion_vectors: required.motion_vectors && !self.motion_vectors,
+            deferred: required.deferred && !self.deferred,
+            object_id: required.object_id && !self.object_id,
+        }
+    }
+
+    /// Returns the names of the prepass components matching the enabled outputs.
+    pub fn component_names(&self) -> Vec<&'static str> {
+        [
+            (self.depth, "DepthPrepass"),
+            (self.normal, "NormalPrepass"),
+            (self.motion_vectors, "MotionVectorPrepass"),
+            (self.deferred, "DeferredPrepass"),
+            (self.object_id, "ObjectIdPrepass"),
+        ]
+        .into_iter()
+        .filter_map(|(enabled, name)| enabled.then_some(name))
+        .collect()
+    }
+}
+
+/// Inserts and removes the prepass components of the cameras whose [`PrepassOutputs`] changed.
+pub fn sync_prepass_outputs(
+    mut commands: Commands,
+    cameras: Query<(Entity, &PrepassOutputs), Changed<PrepassOutputs>>,
+) {
+    fn set<C: Component + Default>(commands: &mut EntityCommands, enabled: bool) {
+        if enabled {
+            commands.insert(C::default());
+        } else {
+            commands.remove::<C>();
+        }
+    }
+
+    for (entity, outputs) in &cameras {
+        let mut commands = commands.entity(entity);
+        set::<DepthPrepass>(&mut commands, outputs.depth);
+        set::<NormalPrepass>(&mut commands, outputs.normal);
+        set::<MotionVectorPrepass>(&mut commands, outputs.motion_vectors);
+        set::<DeferredPrepass>(&mut commands, outputs.deferred);
+        set::<ObjectIdPrepass>(&mut commands, outputs.object_id);
+    }
+}
+
+/// Returns a system that logs an error for each camera with the component `C` that lacks one of
+/// the `required` prepass outputs, because `C` can't render without them.
+///
+/// The cameras are checked when `C` is added to them and when their [`PrepassOutputs`] change.
+/// Effects that read prepass textures add this system to [`PostUpdate`](bevy_app::PostUpdate),
+/// so that a missing prepass is reported with the fix instead of showing up as a black screen.
+pub fn check_prepass_requirements<C: Component>(
+    required: PrepassOutputs,
+) -> impl FnMut(
+    Query<
+        (
+            Entity,
+            Option<&PrepassOutputs>,
+            Has<DepthPrepass>,
+            Has<NormalPrepass>,
+            Has<MotionVectorPrepass>,
+            Has<DeferredPrepass>,
+            Has<ObjectIdPrepass>,
+        ),
+        (With<C>, Or<(Added<C>, Changed<PrepassOutputs>)>),
+    >,
+) {
+    move |cameras| {
+        for (entity, outputs, depth, normal, motion_vectors, deferred, object_id) in &cameras {
+            let enabled = outputs.copied().unwrap_or(PrepassOutputs {
+                depth,
+                normal,
+                motion_vectors,
+                deferred,
+                object_id,
+            });
+            let missing = enabled.missing(&required);
+            if missing == PrepassOutputs::NONE {
+                continue;
+            }
+            error!(
+                "Camera {entity:?} has {}, which needs the {} prepasses and won't render correctly \
+                without them. Add {} to the camera, or enable them in its PrepassOutputs.",
+                get_short_name(std::any::type_name::<C>()),
+                required.component_names().join(", "),
+                missing.component_names().join(", "),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs::prelude::*;
+
+    use super::{sync_prepass_outputs, PrepassOutputs};
+    use crate::prepass::{DeferredPrepass, DepthPrepass, MotionVectorPrepass, NormalPrepass};
+
+    #[test]
+    fn missing_outputs() {
+        assert_eq!(
+            PrepassOutputs::DEPTH_NORMAL.missing(&PrepassOutputs::DEPTH.with_motion_vectors(true)),
+            PrepassOutputs::NONE.with_motion_vectors(true)
+        );
+        assert_eq!(
+            PrepassOutputs::DEFERRED.missing(&PrepassOutputs::DEPTH),
+            PrepassOutputs::NONE
+        );
+        assert_eq!(
+            PrepassOutputs::DEPTH_NORMAL.component_names(),
+            ["DepthPrepass", "NormalPrepass"]
+        );
+    }
+
+    #[test]
+    fn sync_replaces_prepass_components() {
+        let mut world = World::new();
+        let camera = world.spawn((NormalPrepass, PrepassOutputs::DEFERRED)).id();
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(sync_prepass_outputs);
+        schedule.run(&mut world);
+
+        let camera = world.entity(camera);
+        assert!(camera.contains::<DepthPrepass>());
+        assert!(!camera.contains::<NormalPrepass>());
+        assert!(camera.contains::<MotionVectorPrepass>());
+        assert!(camera.contains::<DeferredPrepass>());
+    }
+}
@@ -18,12 +18,12 @@ use bevy_render::{
         *,
     },
     renderer::RenderDevice,
-    texture::{BevyDefault, GpuImage, Image},
-    view::{ExtractedView, Msaa, ViewTarget, ViewUniform, ViewUniforms},
+    texture::{GpuImage, Image},
+    view::{ExtractedView, HdrFormat, Msaa, ViewTarget, ViewUniform, ViewUniforms},
     Render, RenderApp, RenderSet,
 };
 
-use crate::core_3d::CORE_3D_DEPTH_FORMAT;
+use crate::core_3d::{Camera3d, CORE_3D_DEPTH_FORMAT};
 
 const SKYBOX_SHADER_HANDLE: Handle<Shader> = Handle::weak_from_u128(55594763423201);
 
@@ -141,6 +141,7 @@ impl SkyboxPipeline {
 #[derive(PartialEq, Eq, Hash, Clone, Copy)]
 struct SkyboxPipelineKey {
     hdr: bool,
+    hdr_format: HdrFormat,
     samples: u32,
     depth_format: TextureFormat,
 }
@@ -186,11 +187,7 @@ impl SpecializedRenderPipeline for SkyboxPipeline {
                 shader_defs: Vec::new(),
                 entry_point: "skybox_fragment".into(),
                 targets: vec![Some(ColorTargetState {
-                    format: if key.hdr {
-                        ViewTarget::TEXTURE_FORMAT_HDR
-                    } else {
-                        TextureFormat::bevy_default()
-                    },
+                    format: ViewTarget::main_texture_format_for(key.hdr, key.hdr_format),
                     // BlendState::REPLACE is not needed here, and None will be potentially much faster in some cases.
                     blend: None,
                     write_mask: ColorWrites::ALL,
@@ -209,16 +206,17 @@ fn prepare_skybox_pipelines(
     mut pipelines: ResMut<SpecializedRenderPipelines<SkyboxPipeline>>,
     pipeline: Res<SkyboxPipeline>,
     msaa: Res<Msaa>,
-    views: Query<(Entity, &ExtractedView), With<Skybox>>,
+    views: Query<(Entity, &ExtractedView, Option<&Camera3d>), With<Skybox>>,
 ) {
-    for (entity, view) in &views {
+    for (entity, view, camera_3d) in &views {
         let pipeline_id = pipelines.specialize(
             &pipeline_cache,
             &pipeline,
             SkyboxPipelineKey {
                 hdr: view.hdr,
+                hdr_format: view.hdr_format,
                 samples: msaa.samples(),
-                depth_format: CORE_3D_DEPTH_FORMAT,
+                depth_format: camera_3d.map_or(CORE_3D_DEPTH_FORMAT, Camera3d::depth_format),
             },
         );
 
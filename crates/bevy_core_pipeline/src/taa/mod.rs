@@ -2,9 +2,12 @@ use crate::{
     core_3d::graph::{Core3d, Node3d},
     fullscreen_vertex_shader::fullscreen_shader_vertex_state,
     prelude::Camera3d,
-    prepass::{DepthPrepass, MotionVectorPrepass, ViewPrepassTextures},
+    prepass::{
+        outputs::{check_prepass_requirements, PrepassOutputs},
+        DepthPrepass, MotionVectorPrepass, ViewPrepassTextures,
+    },
 };
-use bevy_app::{App, Plugin};
+use bevy_app::{App, Plugin, PostUpdate};
 use bevy_asset::{load_internal_asset, Handle};
 use bevy_core::FrameCount;
 use bevy_ecs::{
@@ -27,11 +30,11 @@ use bevy_render::{
         Operations, PipelineCache, PrimitiveState, RenderPassColorAttachment, RenderPassDescriptor,
         RenderPipelineDescriptor, Sampler, SamplerBindingType, SamplerDescriptor, Shader,
         ShaderStages, SpecializedRenderPipeline, SpecializedRenderPipelines, TextureDescriptor,
-        TextureDimension, TextureFormat, TextureSampleType, TextureUsages,
+        TextureDimension, TextureSampleType, TextureUsages,
     },
     renderer::{RenderContext, RenderDevice},
-    texture::{BevyDefault, CachedTexture, TextureCache},
-    view::{ExtractedView, Msaa, ViewTarget},
+    texture::CachedTexture,
+    view::{ExtractedView, HdrFormat, Msaa, ViewHistoryTextures, ViewTarget},
     ExtractSchedule, MainWorld, Render, RenderApp, RenderSet,
 };
 
@@ -47,7 +50,13 @@ impl Plugin for TemporalAntiAliasPlugin {
         load_internal_asset!(app, TAA_SHADER_HANDLE, "taa.wgsl", Shader::from_wgsl);
 
         app.insert_resource(Msaa::Off)
-            .register_type::<TemporalAntiAliasSettings>();
+            .register_type::<TemporalAntiAliasSettings>()
+            .add_systems(
+                PostUpdate,
+                check_prepass_requirements::<TemporalAntiAliasSettings>(
+                    PrepassOutputs::DEPTH.with_motion_vectors(true),
+                ),
+            );
 
         let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
             return;
@@ -59,8 +68,10 @@ impl Plugin for TemporalAntiAliasPlugin {
                 Render,
                 (
                     prepare_taa_jitter_and_mip_bias.in_set(RenderSet::ManageViews),
-                    prepare_taa_pipelines.in_set(RenderSet::Prepare),
                     prepare_taa_history_textures.in_set(RenderSet::PrepareResources),
+                    prepare_taa_pipelines
+                        .in_set(RenderSet::Prepare)
+                        .after(prepare_taa_history_textures),
                 ),
             )
             .add_render_graph_node::<ViewNodeRunner<TemporalAntiAliasNode>>(Core3d, Node3d::Taa)
@@ -141,6 +152,9 @@ pub struct TemporalAntiAliasSettings {
     ///
     /// Useful for preventing ghosting when the history is no longer
     /// representative of the current frame, such as in sudden camera cuts.
+    /// Sending a [`ResetViewHistory`](bevy_render::view::ResetViewHistory) event
+    /// for the camera does the same, for every temporal effect of the camera.
+    /// The history is also reset when the viewport is resized or HDR is toggled.
     ///
     /// After setting this to true, it will automatically be toggled
     /// back to false at the end of the frame.
@@ -290,6 +304,7 @@ impl FromWorld for TaaPipeline {
 #[derive(PartialEq, Eq, Hash, Clone)]
 struct TaaPipelineKey {
     hdr: bool,
+    hdr_format: HdrFormat,
     reset: bool,
 }
 
@@ -299,12 +314,10 @@ impl SpecializedRenderPipeline for TaaPipeline {
     fn specialize(&self, key: Self::Key) -> RenderPipelineDescriptor {
         let mut shader_defs = vec![];
 
-        let format = if key.hdr {
+        if key.hdr {
             shader_defs.push("TONEMAP".into());
-            ViewTarget::TEXTURE_FORMAT_HDR
-        } else {
-            TextureFormat::bevy_default()
-        };
+        }
+        let format = ViewTarget::main_texture_format_for(key.hdr, key.hdr_format);
 
         if key.reset {
             shader_defs.push("RESET".into());
@@ -394,55 +407,52 @@ fn prepare_taa_jitter_and_mip_bias(
 pub struct TemporalAntiAliasHistoryTextures {
     write: CachedTexture,
     read: CachedTexture,
+    reset: bool,
 }
 
 fn prepare_taa_history_textures(
     mut commands: Commands,
-    mut texture_cache: ResMut<TextureCache>,
+    mut view_histories: ResMut<ViewHistoryTextures>,
     render_device: Res<RenderDevice>,
-    frame_count: Res<FrameCount>,
-    views: Query<(Entity, &ExtractedCamera, &ExtractedView), With<TemporalAntiAliasSettings>>,
+    views: Query<(
+        Entity,
+        &ExtractedCamera,
+        &ExtractedView,
+        &TemporalAntiAliasSettings,
+    )>,
 ) {
-    for (entity, camera, view) in &views {
+    for (entity, camera, view, taa_settings) in &views {
         if let Some(physical_viewport_size) = camera.physical_viewport_size {
-            let mut texture_descriptor = TextureDescriptor {
-                label: None,
-                size: Extent3d {
-                    depth_or_array_layers: 1,
-                    width: physical_viewport_size.x,
-                    height: physical_viewport_size.y,
-                },
-                mip_level_count: 1,
-                sample_count: 1,
-                dimension: TextureDimension::D2,
-                format: if view.hdr {
-                    ViewTarget::TEXTURE_FORMAT_HDR
-                } else {
-                    TextureFormat::bevy_default()
+            if taa_settings.reset {
+                view_histories.reset_history(entity);
+            }
+
+            let history = view_histories.get::<TemporalAntiAliasSettings>(
+                &render_device,
+                entity,
+                TextureDescriptor {
+                    label: Some("taa_history_texture"),
+                    size: Extent3d {
+                        depth_or_array_layers: 1,
+                        width: physical_viewport_size.x,
+                        height: physical_viewport_size.y,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: TextureDimension::D2,
+                    format: view.main_texture_format(),
+                    usage: TextureUsages::TEXTURE_BINDING | TextureUsages::RENDER_ATTACHMENT,
+                    view_formats: &[],
                 },
-                usage: TextureUsages::TEXTURE_BINDING | TextureUsages::RENDER_ATTACHMENT,
-                view_formats: &[],
-            };
-
-            texture_descriptor.label = Some("taa_history_1_texture");
-            let history_1_texture = texture_cache.get(&render_device, texture_descriptor.clone());
-
-            texture_descriptor.label = Some("taa_history_2_texture");
-            let history_2_texture = texture_cache.get(&render_device, texture_descriptor);
-
-            let textures = if frame_count.0 % 2 == 0 {
-                TemporalAntiAliasHistoryTextures {
-                    write: history_1_texture,
-                    read: history_2_texture,
-                }
-            } else {
-                TemporalAntiAliasHistoryTextures {
-                    write: history_2_texture,
-                    read: history_1_texture,
-                }
-            };
-
-            commands.entity(entity).insert(textures);
+            );
+
+            commands
+                .entity(entity)
+                .insert(TemporalAntiAliasHistoryTextures {
+                    write: history.write,
+                    read: history.read,
+                    reset: history.reset,
+                });
         }
     }
 }
@@ -455,12 +465,13 @@ fn prepare_taa_pipelines(
     pipeline_cache: Res<PipelineCache>,
     mut pipelines: ResMut<SpecializedRenderPipelines<TaaPipeline>>,
     pipeline: Res<TaaPipeline>,
-    views: Query<(Entity, &ExtractedView, &TemporalAntiAliasSettings)>,
+    views: Query<(Entity, &ExtractedView, &TemporalAntiAliasHistoryTextures)>,
 ) {
-    for (entity, view, taa_settings) in &views {
+    for (entity, view, taa_history_textures) in &views {
         let mut pipeline_key = TaaPipelineKey {
             hdr: view.hdr,
-            reset: taa_settings.reset,
+            hdr_format: view.hdr_format,
+            reset: taa_history_textures.reset,
         };
         let pipeline_id = pipelines.specialize(&pipeline_cache, &pipeline, pipeline_key.clone());
 
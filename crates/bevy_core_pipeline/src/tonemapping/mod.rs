@@ -11,7 +11,7 @@ use bevy_render::render_resource::binding_types::{
 };
 use bevy_render::renderer::RenderDevice;
 use bevy_render::texture::{CompressedImageFormats, GpuImage, Image, ImageSampler, ImageType};
-use bevy_render::view::{ExtractedView, ViewTarget, ViewUniform};
+use bevy_render::view::{ExtractedView, HdrFormat, ViewTarget, ViewUniform};
 use bevy_render::{camera::Camera, texture::FallbackImage};
 use bevy_render::{render_resource::*, Render, RenderApp, RenderSet};
 #[cfg(not(feature = "tonemapping_luts"))]
@@ -198,6 +198,7 @@ bitflags! {
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct TonemappingPipelineKey {
+    hdr_format: HdrFormat,
     deband_dither: DebandDither,
     tonemapping: Tonemapping,
     flags: TonemappingPipelineKeyFlags,
@@ -276,7 +277,7 @@ impl SpecializedRenderPipeline for TonemappingPipeline {
                 shader_defs,
                 entry_point: "fragment".into(),
                 targets: vec![Some(ColorTargetState {
-                    format: ViewTarget::TEXTURE_FORMAT_HDR,
+                    format: key.hdr_format.texture_format(),
                     blend: None,
                     write_mask: ColorWrites::ALL,
                 })],
@@ -287,6 +288,11 @@ impl SpecializedRenderPipeline for TonemappingPipeline {
             push_constant_ranges: Vec::new(),
         }
     }
+
+    fn priority(&self, _key: &Self::Key) -> PipelinePriority {
+        // Nothing reaches the window until this pipeline is compiled.
+        PipelinePriority::High
+    }
 }
 
 impl FromWorld for TonemappingPipeline {
@@ -303,8 +309,14 @@ impl FromWorld for TonemappingPipeline {
             ),
         );
         let lut_layout_entries = get_lut_bind_group_layout_entries();
-        entries =
-            entries.extend_with_indices(((3, lut_layout_entries[0]), (4, lut_layout_entries[1])));
+        entries = entries.extend_with_indices((
+            (3, lut_layout_entries[0]),
+            (4, lut_layout_entries[1]),
+            (
+                5,
+                texture_2d(TextureSampleType::Float { filterable: false }),
+            ),
+        ));
 
         let render_device = render_world.resource::<RenderDevice>();
         let tonemap_texture_bind_group = render_device
@@ -356,6 +368,7 @@ pub fn prepare_view_tonemapping_pipelines(
         );
 
         let key = TonemappingPipelineKey {
+            hdr_format: view.hdr_format,
             deband_dither: *dither.unwrap_or(&DebandDither::Disabled),
             tonemapping: *tonemapping.unwrap_or(&Tonemapping::None),
             flags,
@@ -368,6 +381,12 @@ pub fn prepare_view_tonemapping_pipelines(
     }
 }
 /// Enables a debanding shader that applies dithering to mitigate color banding in the final image for a given [`Camera`] entity.
+///
+/// The image is dithered wherever the camera writes it to an 8-bit texture: in the tonemapping
+/// pass, or directly in the shaders of meshes and sprites for cameras without HDR. The pattern and
+/// strength of the dithering are configured with
+/// [`DitherSettings`](bevy_render::view::DitherSettings), and materials can opt out with
+/// `Material::deband_dither`.
 #[derive(
     Component, Debug, Hash, Clone, Copy, Reflect, Default, ExtractComponent, PartialEq, Eq,
 )]
@@ -1,6 +1,9 @@
 use std::sync::Mutex;
 
-use crate::tonemapping::{TonemappingLuts, TonemappingPipeline, ViewTonemappingPipeline};
+use crate::{
+    camera_stack::TonemappedByOverlay,
+    tonemapping::{TonemappingLuts, TonemappingPipeline, ViewTonemappingPipeline},
+};
 
 use bevy_ecs::{prelude::*, query::QueryItem};
 use bevy_render::{
@@ -11,7 +14,7 @@ use bevy_render::{
         RenderPassColorAttachment, RenderPassDescriptor, StoreOp, TextureViewId,
     },
     renderer::RenderContext,
-    texture::{FallbackImage, GpuImage},
+    texture::{BlueNoise, FallbackImage, GpuImage},
     view::{ViewTarget, ViewUniformOffset, ViewUniforms},
 };
 
@@ -29,15 +32,20 @@ impl ViewNode for TonemappingNode {
         &'static ViewTarget,
         &'static ViewTonemappingPipeline,
         &'static Tonemapping,
+        Has<TonemappedByOverlay>,
     );
 
     fn run(
         &self,
         _graph: &mut RenderGraphContext,
         render_context: &mut RenderContext,
-        (view_uniform_offset, target, view_tonemapping_pipeline, tonemapping): QueryItem<
-            Self::ViewQuery,
-        >,
+        (
+            view_uniform_offset,
+            target,
+            view_tonemapping_pipeline,
+            tonemapping,
+            tonemapped_by_overlay,
+        ): QueryItem<Self::ViewQuery>,
         world: &World,
     ) -> Result<(), NodeRunError> {
         let pipeline_cache = world.resource::<PipelineCache>();
@@ -48,7 +56,8 @@ impl ViewNode for TonemappingNode {
         let view_uniforms = &view_uniforms_resource.uniforms;
         let view_uniforms_id = view_uniforms.buffer().unwrap().id();
 
-        if !target.is_hdr() {
+        // A `StackedCamera` rendered later tonemaps the whole stack.
+        if !target.is_hdr() || tonemapped_by_overlay {
             return Ok(());
         }
 
@@ -96,6 +105,7 @@ impl ViewNode for TonemappingNode {
                         &tonemapping_pipeline.sampler,
                         lut_bindings.0,
                         lut_bindings.1,
+                        &world.resource::<BlueNoise>().texture_view,
                     )),
                 );
 
@@ -1,9 +1,12 @@
 #define TONEMAPPING_PASS
 
-#import bevy_render::view::View
+#import bevy_render::{
+    blue_noise::blue_noise_load,
+    view::View,
+}
 #import bevy_core_pipeline::{
     fullscreen_vertex_shader::FullscreenVertexOutput,
-    tonemapping::{tone_mapping, powsafe, screen_space_dither},
+    tonemapping::{tone_mapping, dither_srgb},
 }
 
 @group(0) @binding(0) var<uniform> view: View;
@@ -12,6 +15,7 @@
 @group(0) @binding(2) var hdr_sampler: sampler;
 @group(0) @binding(3) var dt_lut_texture: texture_3d<f32>;
 @group(0) @binding(4) var dt_lut_sampler: sampler;
+@group(0) @binding(5) var blue_noise_texture: texture_2d<f32>;
 
 @fragment
 fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
@@ -20,11 +24,13 @@ fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
     var output_rgb = tone_mapping(hdr_color, view.color_grading).rgb;
 
 #ifdef DEBAND_DITHER
-    output_rgb = powsafe(output_rgb.rgb, 1.0 / 2.2);
-    output_rgb = output_rgb + screen_space_dither(in.position.xy);
-    // This conversion back to linear space is required because our output texture format is
-    // SRGB; the GPU will assume our output is linear and will apply an SRGB conversion.
-    output_rgb = powsafe(output_rgb.rgb, 2.2);
+    output_rgb = dither_srgb(
+        output_rgb,
+        in.position.xy,
+        blue_noise_load(blue_noise_texture, vec2<u32>(in.position.xy)),
+        view.dither_pattern,
+        view.dither_strength,
+    );
 #endif
 
     return vec4<f32>(output_rgb, hdr_color.a);
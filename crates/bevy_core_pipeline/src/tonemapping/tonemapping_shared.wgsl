@@ -1,7 +1,7 @@
 #define_import_path bevy_core_pipeline::tonemapping
 
 #import bevy_render::{
-    view::ColorGrading,
+    view::{ColorGrading, DITHER_PATTERN_ORDERED, DITHER_PATTERN_SCREEN_SPACE_HASH},
     color_operations::{hsv_to_rgb, rgb_to_hsv},
     maths::PI_2
 }
@@ -286,6 +286,56 @@ fn screen_space_dither(frag_coord: vec2<f32>) -> vec3<f32> {
     return (dither - 0.5) / 255.0;
 }
 
+// Returns a dither offset with a triangular distribution in [-1, 1] / 255,
+// from two blue noise values in [0, 1).
+//
+// Unlike `screen_space_dither`, the error is evenly spread and has no visible
+// pattern.
+fn blue_noise_dither(noise: vec2<f32>) -> vec3<f32> {
+    return vec3(noise.x + noise.y - 1.0) / 255.0;
+}
+
+// Returns a dither offset in [-0.5, 0.5] / 255 from the 4x4 Bayer matrix.
+fn ordered_dither(frag_coord: vec2<f32>) -> vec3<f32> {
+    let xy = vec2<u32>(frag_coord) & vec2(3u);
+    let x_xor_y = xy.x ^ xy.y;
+    let index = ((x_xor_y & 1u) << 3u) | ((xy.y & 1u) << 2u) | (x_xor_y & 2u) | ((xy.y & 2u) >> 1u);
+    return vec3((f32(index) + 0.5) / 16.0 - 0.5) / 255.0;
+}
+
+// Returns the dither offset of `pattern`, one of the `DITHER_PATTERN_*`
+// constants of `bevy_render::view`, scaled by `strength`.
+//
+// `blue_noise` holds two blue noise values of the pixel, and is only used by
+// `DITHER_PATTERN_BLUE_NOISE`.
+fn dither(frag_coord: vec2<f32>, blue_noise: vec2<f32>, pattern: u32, strength: f32) -> vec3<f32> {
+    var offset: vec3<f32>;
+    if (pattern == DITHER_PATTERN_ORDERED) {
+        offset = ordered_dither(frag_coord);
+    } else if (pattern == DITHER_PATTERN_SCREEN_SPACE_HASH) {
+        offset = screen_space_dither(frag_coord);
+    } else {
+        offset = blue_noise_dither(blue_noise);
+    }
+    return offset * strength;
+}
+
+// Dithers a linear color that's about to be written to an 8-bit sRGB target,
+// to hide the banding of smooth gradients. See `dither` for the parameters.
+fn dither_srgb(
+    color: vec3<f32>,
+    frag_coord: vec2<f32>,
+    blue_noise: vec2<f32>,
+    pattern: u32,
+    strength: f32,
+) -> vec3<f32> {
+    var output_rgb = powsafe(color, 1.0 / 2.2);
+    output_rgb += dither(frag_coord, blue_noise, pattern, strength);
+    // This conversion back to linear space is required because our output texture format is
+    // SRGB; the GPU will assume our output is linear and will apply an SRGB conversion.
+    return powsafe(output_rgb, 2.2);
+}
+
 // Performs the "sectional" color grading: i.e. the color grading that applies
 // individually to shadows, midtones, and highlights.
 fn sectional_color_grading(
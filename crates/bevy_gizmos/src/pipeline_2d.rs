@@ -98,11 +98,7 @@ impl SpecializedRenderPipeline for LineGizmoPipeline {
     type Key = LineGizmoPipelineKey;
 
     fn specialize(&self, key: Self::Key) -> RenderPipelineDescriptor {
-        let format = if key.mesh_key.contains(Mesh2dPipelineKey::HDR) {
-            ViewTarget::TEXTURE_FORMAT_HDR
-        } else {
-            TextureFormat::bevy_default()
-        };
+        let format = key.mesh_key.view_target_format();
 
         let shader_defs = vec![
             #[cfg(feature = "webgl")]
@@ -178,11 +174,7 @@ impl SpecializedRenderPipeline for LineJointGizmoPipeline {
     type Key = LineJointGizmoPipelineKey;
 
     fn specialize(&self, key: Self::Key) -> RenderPipelineDescriptor {
-        let format = if key.mesh_key.contains(Mesh2dPipelineKey::HDR) {
-            ViewTarget::TEXTURE_FORMAT_HDR
-        } else {
-            TextureFormat::bevy_default()
-        };
+        let format = key.mesh_key.view_target_format();
 
         let shader_defs = vec![
             #[cfg(feature = "webgl")]
@@ -267,7 +259,8 @@ fn queue_line_gizmos_2d(
 
     for (view, mut transparent_phase, render_layers) in &mut views {
         let mesh_key = Mesh2dPipelineKey::from_msaa_samples(msaa.samples())
-            | Mesh2dPipelineKey::from_hdr(view.hdr);
+            | Mesh2dPipelineKey::from_hdr(view.hdr)
+            | Mesh2dPipelineKey::from_hdr_format(view.hdr_format);
 
         for (entity, handle, config) in &line_gizmos {
             let render_layers = render_layers.copied().unwrap_or_default();
@@ -323,7 +316,8 @@ fn queue_line_joint_gizmos_2d(
 
     for (view, mut transparent_phase, render_layers) in &mut views {
         let mesh_key = Mesh2dPipelineKey::from_msaa_samples(msaa.samples())
-            | Mesh2dPipelineKey::from_hdr(view.hdr);
+            | Mesh2dPipelineKey::from_hdr(view.hdr)
+            | Mesh2dPipelineKey::from_hdr_format(view.hdr_format);
 
         for (entity, handle, config) in &line_gizmos {
             let render_layers = render_layers.copied().unwrap_or_default();
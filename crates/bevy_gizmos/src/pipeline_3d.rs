@@ -8,7 +8,7 @@ use crate::{
 use bevy_app::{App, Plugin};
 use bevy_asset::Handle;
 use bevy_core_pipeline::{
-    core_3d::{Transparent3d, CORE_3D_DEPTH_FORMAT},
+    core_3d::{Camera3d, Transparent3d},
     prepass::{DeferredPrepass, DepthPrepass, MotionVectorPrepass, NormalPrepass},
 };
 
@@ -19,7 +19,7 @@ use bevy_ecs::{
     system::{Query, Res, ResMut, Resource},
     world::{FromWorld, World},
 };
-use bevy_pbr::{MeshPipeline, MeshPipelineKey, SetMeshViewBindGroup};
+use bevy_pbr::{MeshPipeline, MeshPipelineKey, MeshViewExtraBindings, SetMeshViewBindGroup};
 use bevy_render::{
     render_asset::{prepare_assets, RenderAssets},
     render_phase::{
@@ -107,11 +107,7 @@ impl SpecializedRenderPipeline for LineGizmoPipeline {
             shader_defs.push("PERSPECTIVE".into());
         }
 
-        let format = if key.view_key.contains(MeshPipelineKey::HDR) {
-            ViewTarget::TEXTURE_FORMAT_HDR
-        } else {
-            TextureFormat::bevy_default()
-        };
+        let format = key.view_key.view_target_format();
 
         let view_layout = self
             .mesh_pipeline
@@ -145,7 +141,7 @@ impl SpecializedRenderPipeline for LineGizmoPipeline {
             layout,
             primitive: PrimitiveState::default(),
             depth_stencil: Some(DepthStencilState {
-                format: CORE_3D_DEPTH_FORMAT,
+                format: key.view_key.depth_format(),
                 depth_write_enabled: true,
                 depth_compare: CompareFunction::Greater,
                 stencil: StencilState::default(),
@@ -200,11 +196,7 @@ impl SpecializedRenderPipeline for LineJointGizmoPipeline {
             shader_defs.push("PERSPECTIVE".into());
         }
 
-        let format = if key.view_key.contains(MeshPipelineKey::HDR) {
-            ViewTarget::TEXTURE_FORMAT_HDR
-        } else {
-            TextureFormat::bevy_default()
-        };
+        let format = key.view_key.view_target_format();
 
         let view_layout = self
             .mesh_pipeline
@@ -243,7 +235,7 @@ impl SpecializedRenderPipeline for LineJointGizmoPipeline {
             layout,
             primitive: PrimitiveState::default(),
             depth_stencil: Some(DepthStencilState {
-                format: CORE_3D_DEPTH_FORMAT,
+                format: key.view_key.depth_format(),
                 depth_write_enabled: true,
                 depth_compare: CompareFunction::Greater,
                 stencil: StencilState::default(),
@@ -282,7 +274,9 @@ fn queue_line_gizmos_3d(
     msaa: Res<Msaa>,
     line_gizmos: Query<(Entity, &Handle<LineGizmo>, &GizmoMeshConfig)>,
     line_gizmo_assets: Res<RenderAssets<GpuLineGizmo>>,
+    mesh_view_extra_bindings: Res<MeshViewExtraBindings>,
     mut views: Query<(
+        Entity,
         &ExtractedView,
         &mut SortedRenderPhase<Transparent3d>,
         Option<&RenderLayers>,
@@ -292,21 +286,26 @@ fn queue_line_gizmos_3d(
             Has<MotionVectorPrepass>,
             Has<DeferredPrepass>,
         ),
+        Option<&Camera3d>,
     )>,
 ) {
     let draw_function = draw_functions.read().get_id::<DrawLineGizmo3d>().unwrap();
 
     for (
+        view_entity,
         view,
         mut transparent_phase,
         render_layers,
         (normal_prepass, depth_prepass, motion_vector_prepass, deferred_prepass),
+        camera_3d,
     ) in &mut views
     {
         let render_layers = render_layers.copied().unwrap_or_default();
 
         let mut view_key = MeshPipelineKey::from_msaa_samples(msaa.samples())
-            | MeshPipelineKey::from_hdr(view.hdr);
+            | MeshPipelineKey::from_hdr(view.hdr)
+            | MeshPipelineKey::from_hdr_format(view.hdr_format)
+            | mesh_view_extra_bindings.view_key(view_entity);
 
         if normal_prepass {
             view_key |= MeshPipelineKey::NORMAL_PREPASS;
@@ -324,6 +323,10 @@ fn queue_line_gizmos_3d(
             view_key |= MeshPipelineKey::DEFERRED_PREPASS;
         }
 
+        if camera_3d.is_some_and(|camera_3d| camera_3d.stencil) {
+            view_key |= MeshPipelineKey::DEPTH_STENCIL;
+        }
+
         for (entity, handle, config) in &line_gizmos {
             if !config.render_layers.intersects(&render_layers) {
                 continue;
@@ -365,7 +368,9 @@ fn queue_line_joint_gizmos_3d(
     msaa: Res<Msaa>,
     line_gizmos: Query<(Entity, &Handle<LineGizmo>, &GizmoMeshConfig)>,
     line_gizmo_assets: Res<RenderAssets<GpuLineGizmo>>,
+    mesh_view_extra_bindings: Res<MeshViewExtraBindings>,
     mut views: Query<(
+        Entity,
         &ExtractedView,
         &mut SortedRenderPhase<Transparent3d>,
         Option<&RenderLayers>,
@@ -375,6 +380,7 @@ fn queue_line_joint_gizmos_3d(
             Has<MotionVectorPrepass>,
             Has<DeferredPrepass>,
         ),
+        Option<&Camera3d>,
     )>,
 ) {
     let draw_function = draw_functions
@@ -383,16 +389,20 @@ fn queue_line_joint_gizmos_3d(
         .unwrap();
 
     for (
+        view_entity,
         view,
         mut transparent_phase,
         render_layers,
         (normal_prepass, depth_prepass, motion_vector_prepass, deferred_prepass),
+        camera_3d,
     ) in &mut views
     {
         let render_layers = render_layers.copied().unwrap_or_default();
 
         let mut view_key = MeshPipelineKey::from_msaa_samples(msaa.samples())
-            | MeshPipelineKey::from_hdr(view.hdr);
+            | MeshPipelineKey::from_hdr(view.hdr)
+            | MeshPipelineKey::from_hdr_format(view.hdr_format)
+            | mesh_view_extra_bindings.view_key(view_entity);
 
         if normal_prepass {
             view_key |= MeshPipelineKey::NORMAL_PREPASS;
@@ -410,6 +420,10 @@ fn queue_line_joint_gizmos_3d(
             view_key |= MeshPipelineKey::DEFERRED_PREPASS;
         }
 
+        if camera_3d.is_some_and(|camera_3d| camera_3d.stencil) {
+            view_key |= MeshPipelineKey::DEPTH_STENCIL;
+        }
+
         for (entity, handle, config) in &line_gizmos {
             if !config.render_layers.intersects(&render_layers) {
                 continue;
@@ -6,6 +6,8 @@
     pbr_deferred_types::unpack_unorm3x4_plus_unorm_20_,
     lighting,
     mesh_view_bindings::deferred_prepass_texture,
+    deferred_lighting_bindings::{FullscreenVertexOutput, depth_id},
+    wetness::apply_wetness,
 }
 
 #ifdef SCREEN_SPACE_AMBIENT_OCCLUSION
@@ -13,25 +15,6 @@
 #import bevy_pbr::gtao_utils::gtao_multibounce
 #endif
 
-struct FullscreenVertexOutput {
-    @builtin(position)
-    position: vec4<f32>,
-    @location(0)
-    uv: vec2<f32>,
-};
-
-struct PbrDeferredLightingDepthId {
-    depth_id: u32, // limited to u8
-#ifdef SIXTEEN_BYTE_ALIGNMENT
-    // WebGL2 structs must be 16 byte aligned.
-    _webgl2_padding_0: f32,
-    _webgl2_padding_1: f32,
-    _webgl2_padding_2: f32,
-#endif
-}
-@group(1) @binding(0)
-var<uniform> depth_id: PbrDeferredLightingDepthId;
-
 @vertex
 fn vertex(@builtin(vertex_index) vertex_index: u32) -> FullscreenVertexOutput {
     // See the full screen vertex shader for explanation above for how this works.
@@ -61,6 +44,7 @@ fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
 
     // NOTE: Unlit bit not set means == 0 is true, so the true case is if lit
     if ((pbr_input.material.flags & STANDARD_MATERIAL_FLAGS_UNLIT_BIT) == 0u) {
+        pbr_input = apply_wetness(pbr_input);
 
 #ifdef SCREEN_SPACE_AMBIENT_OCCLUSION
         let ssao = textureLoad(screen_space_ambient_occlusion_texture, vec2<i32>(in.position.xy), 0i).r;
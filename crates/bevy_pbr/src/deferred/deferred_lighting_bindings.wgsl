@@ -0,0 +1,27 @@
+#define_import_path bevy_pbr::deferred_lighting_bindings
+
+struct FullscreenVertexOutput {
+    @builtin(position)
+    position: vec4<f32>,
+    @location(0)
+    uv: vec2<f32>,
+};
+
+struct PbrDeferredLightingDepthId {
+    depth_id: u32, // limited to u8
+#ifdef SIXTEEN_BYTE_ALIGNMENT
+    // WebGL2 structs must be 16 byte aligned.
+    _webgl2_padding_0: f32,
+    _webgl2_padding_1: f32,
+    _webgl2_padding_2: f32,
+#endif
+}
+@group(1) @binding(0)
+var<uniform> depth_id: PbrDeferredLightingDepthId;
+
+#ifdef DEFERRED_GBUFFER_EXTENSION
+// The channels written by the materials to `FragmentOutput::deferred_gbuffer_extension`, or
+// zeros for the materials that don't write them.
+@group(1) @binding(1)
+var deferred_gbuffer_extension_texture: texture_2d<u32>;
+#endif
@@ -1,7 +1,7 @@
 use crate::{
     graph::NodePbr, irradiance_volume::IrradianceVolume, prelude::EnvironmentMapLight,
-    MeshPipeline, MeshViewBindGroup, RenderViewLightProbes, ScreenSpaceAmbientOcclusionSettings,
-    ViewLightProbesUniformOffset,
+    MeshPipeline, MeshViewBindGroup, MeshViewExtraBindings, RenderViewLightProbes,
+    ScreenSpaceAmbientOcclusionSettings, ViewLightProbesUniformOffset,
 };
 use bevy_app::prelude::*;
 use bevy_asset::{load_internal_asset, Handle};
@@ -10,7 +10,11 @@ use bevy_core_pipeline::{
     deferred::{
         copy_lighting_id::DeferredLightingIdDepthTexture, DEFERRED_LIGHTING_PASS_ID_DEPTH_FORMAT,
     },
-    prepass::{DeferredPrepass, DepthPrepass, MotionVectorPrepass, NormalPrepass},
+    prepass::{
+        outputs::{check_prepass_requirements, PrepassOutputs},
+        DeferredGBufferExtensionPrepass, DeferredPrepass, DepthPrepass, MotionVectorPrepass,
+        NormalPrepass, ViewPrepassTextures,
+    },
     tonemapping::{DebandDither, Tonemapping},
 };
 use bevy_ecs::{prelude::*, query::QueryItem};
@@ -19,10 +23,9 @@ use bevy_render::{
         ComponentUniforms, ExtractComponent, ExtractComponentPlugin, UniformComponentPlugin,
     },
     render_graph::{NodeRunError, RenderGraphApp, RenderGraphContext, ViewNode, ViewNodeRunner},
-    render_resource::binding_types::uniform_buffer,
+    render_resource::binding_types::{texture_2d, uniform_buffer},
     render_resource::*,
     renderer::{RenderContext, RenderDevice},
-    texture::BevyDefault,
     view::{ExtractedView, ViewTarget, ViewUniformOffset},
     Render, RenderApp, RenderSet,
 };
@@ -36,8 +39,21 @@ pub struct DeferredPbrLightingPlugin;
 pub const DEFERRED_LIGHTING_SHADER_HANDLE: Handle<Shader> =
     Handle::weak_from_u128(2708011359337029741);
 
+pub const DEFERRED_LIGHTING_BINDINGS_SHADER_HANDLE: Handle<Shader> =
+    Handle::weak_from_u128(5493417920561835092);
+
 pub const DEFAULT_PBR_DEFERRED_LIGHTING_PASS_ID: u8 = 1;
 
+/// Replaces the fragment shader of the PBR deferred lighting pass of a camera with a [`DeferredPrepass`].
+///
+/// The shader has a `fragment` entry point taking a `bevy_pbr::deferred_lighting_bindings::FullscreenVertexOutput`,
+/// and is compiled with the same shader defs and bindings as the default `deferred_lighting.wgsl`, which it can
+/// start from. With a [`DeferredGBufferExtensionPrepass`], it also reads the channels the materials wrote with
+/// [`Material::deferred_gbuffer_extension`](crate::Material::deferred_gbuffer_extension) from
+/// `bevy_pbr::deferred_lighting_bindings::deferred_gbuffer_extension_texture`.
+#[derive(Component, Clone, ExtractComponent)]
+pub struct DeferredLightingShader(pub Handle<Shader>);
+
 /// Component with a `depth_id` for specifying which corresponding materials should be rendered by this specific PBR deferred lighting pass.
 /// Will be automatically added to entities with the [`DeferredPrepass`] component that don't already have a [`PbrDeferredLightingDepthId`].
 #[derive(Component, Clone, Copy, ExtractComponent, ShaderType)]
@@ -95,9 +111,23 @@ impl Plugin for DeferredPbrLightingPlugin {
         app.add_plugins((
             ExtractComponentPlugin::<PbrDeferredLightingDepthId>::default(),
             UniformComponentPlugin::<PbrDeferredLightingDepthId>::default(),
+            ExtractComponentPlugin::<DeferredLightingShader>::default(),
         ))
-        .add_systems(PostUpdate, insert_deferred_lighting_pass_id_component);
+        .add_systems(
+            PostUpdate,
+            (
+                insert_deferred_lighting_pass_id_component,
+                // The deferred lighting reads the depth prepass.
+                check_prepass_requirements::<DeferredPrepass>(PrepassOutputs::DEPTH),
+            ),
+        );
 
+        load_internal_asset!(
+            app,
+            DEFERRED_LIGHTING_BINDINGS_SHADER_HANDLE,
+            "deferred_lighting_bindings.wgsl",
+            Shader::from_wgsl
+        );
         load_internal_asset!(
             app,
             DEFERRED_LIGHTING_SHADER_HANDLE,
@@ -151,6 +181,7 @@ impl ViewNode for DeferredOpaquePass3dPbrLightingNode {
         &'static ViewTarget,
         &'static DeferredLightingIdDepthTexture,
         &'static DeferredLightingPipeline,
+        &'static ViewPrepassTextures,
     );
 
     fn run(
@@ -166,6 +197,7 @@ impl ViewNode for DeferredOpaquePass3dPbrLightingNode {
             target,
             deferred_lighting_id_depth_texture,
             deferred_lighting_pipeline,
+            view_prepass_textures,
         ): QueryItem<Self::ViewQuery>,
         world: &World,
     ) -> Result<(), NodeRunError> {
@@ -186,11 +218,21 @@ impl ViewNode for DeferredOpaquePass3dPbrLightingNode {
             return Ok(());
         };
 
-        let bind_group_1 = render_context.render_device().create_bind_group(
-            "deferred_lighting_layout_group_1",
-            &deferred_lighting_layout.bind_group_layout_1,
-            &BindGroupEntries::single(deferred_lighting_pass_id_binding),
-        );
+        let bind_group_1 = match view_prepass_textures.deferred_gbuffer_extension_view() {
+            Some(gbuffer_extension) => render_context.render_device().create_bind_group(
+                "deferred_lighting_layout_group_1",
+                &deferred_lighting_layout.bind_group_layout_1_gbuffer_extension,
+                &BindGroupEntries::sequential((
+                    deferred_lighting_pass_id_binding,
+                    gbuffer_extension,
+                )),
+            ),
+            None => render_context.render_device().create_bind_group(
+                "deferred_lighting_layout_group_1",
+                &deferred_lighting_layout.bind_group_layout_1,
+                &BindGroupEntries::single(deferred_lighting_pass_id_binding),
+            ),
+        };
 
         let mut render_pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
             label: Some("deferred_lighting_pass"),
@@ -229,6 +271,7 @@ impl ViewNode for DeferredOpaquePass3dPbrLightingNode {
 pub struct DeferredLightingLayout {
     mesh_pipeline: MeshPipeline,
     bind_group_layout_1: BindGroupLayout,
+    bind_group_layout_1_gbuffer_extension: BindGroupLayout,
 }
 
 #[derive(Component)]
@@ -236,10 +279,21 @@ pub struct DeferredLightingPipeline {
     pub pipeline_id: CachedRenderPipelineId,
 }
 
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct DeferredLightingPipelineKey {
+    pub mesh_key: MeshPipelineKey,
+    /// The [`DeferredLightingShader`] of the view, if any.
+    pub fragment_shader: Option<Handle<Shader>>,
+}
+
 impl SpecializedRenderPipeline for DeferredLightingLayout {
-    type Key = MeshPipelineKey;
+    type Key = DeferredLightingPipelineKey;
+
+    fn specialize(&self, pipeline_key: Self::Key) -> RenderPipelineDescriptor {
+        let key = self
+            .mesh_pipeline
+            .fit_view_texture_budget(pipeline_key.mesh_key);
 
-    fn specialize(&self, key: Self::Key) -> RenderPipelineDescriptor {
         let mut shader_defs = Vec::new();
 
         // Let the shader code know that it's running in a deferred pipeline.
@@ -304,6 +358,15 @@ impl SpecializedRenderPipeline for DeferredLightingLayout {
         // Always true, since we're in the deferred lighting pipeline
         shader_defs.push("DEFERRED_PREPASS".into());
 
+        let bind_group_layout_1 = if key.contains(MeshPipelineKey::DEFERRED_GBUFFER_EXTENSION) {
+            shader_defs.push("DEFERRED_GBUFFER_EXTENSION".into());
+            self.bind_group_layout_1_gbuffer_extension.clone()
+        } else {
+            self.bind_group_layout_1.clone()
+        };
+
+        shader_defs.extend_from_slice(self.mesh_pipeline.get_view_layout_shader_defs(key.into()));
+
         let shadow_filter_method =
             key.intersection(MeshPipelineKey::SHADOW_FILTER_METHOD_RESERVED_BITS);
         if shadow_filter_method == MeshPipelineKey::SHADOW_FILTER_METHOD_HARDWARE_2X2 {
@@ -321,7 +384,7 @@ impl SpecializedRenderPipeline for DeferredLightingLayout {
             label: Some("deferred_lighting_pipeline".into()),
             layout: vec![
                 self.mesh_pipeline.get_view_layout(key.into()).clone(),
-                self.bind_group_layout_1.clone(),
+                bind_group_layout_1,
             ],
             vertex: VertexState {
                 shader: DEFERRED_LIGHTING_SHADER_HANDLE,
@@ -330,15 +393,13 @@ impl SpecializedRenderPipeline for DeferredLightingLayout {
                 buffers: Vec::new(),
             },
             fragment: Some(FragmentState {
-                shader: DEFERRED_LIGHTING_SHADER_HANDLE,
+                shader: pipeline_key
+                    .fragment_shader
+                    .unwrap_or(DEFERRED_LIGHTING_SHADER_HANDLE),
                 shader_defs,
                 entry_point: "fragment".into(),
                 targets: vec![Some(ColorTargetState {
-                    format: if key.contains(MeshPipelineKey::HDR) {
-                        ViewTarget::TEXTURE_FORMAT_HDR
-                    } else {
-                        TextureFormat::bevy_default()
-                    },
+                    format: key.view_target_format(),
                     blend: None,
                     write_mask: ColorWrites::ALL,
                 })],
@@ -376,9 +437,20 @@ impl FromWorld for DeferredLightingLayout {
                 uniform_buffer::<PbrDeferredLightingDepthId>(false),
             ),
         );
+        let gbuffer_extension_layout = render_device.create_bind_group_layout(
+            "deferred_lighting_gbuffer_extension_layout",
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::VERTEX_FRAGMENT,
+                (
+                    uniform_buffer::<PbrDeferredLightingDepthId>(false),
+                    texture_2d(TextureSampleType::Uint),
+                ),
+            ),
+        );
         Self {
             mesh_pipeline: world.resource::<MeshPipeline>().clone(),
             bind_group_layout_1: layout,
+            bind_group_layout_1_gbuffer_extension: gbuffer_extension_layout,
         }
     }
 }
@@ -399,6 +471,7 @@ pub fn prepare_deferred_lighting_pipelines(
     pipeline_cache: Res<PipelineCache>,
     mut pipelines: ResMut<SpecializedRenderPipelines<DeferredLightingLayout>>,
     deferred_lighting_layout: Res<DeferredLightingLayout>,
+    mesh_view_extra_bindings: Res<MeshViewExtraBindings>,
     views: Query<
         (
             Entity,
@@ -414,6 +487,8 @@ pub fn prepare_deferred_lighting_pipelines(
             ),
             Has<RenderViewLightProbes<EnvironmentMapLight>>,
             Has<RenderViewLightProbes<IrradianceVolume>>,
+            Has<DeferredGBufferExtensionPrepass>,
+            Option<&DeferredLightingShader>,
         ),
         With<DeferredPrepass>,
     >,
@@ -428,9 +503,13 @@ pub fn prepare_deferred_lighting_pipelines(
         (normal_prepass, depth_prepass, motion_vector_prepass),
         has_environment_maps,
         has_irradiance_volumes,
+        deferred_gbuffer_extension,
+        deferred_lighting_shader,
     ) in &views
     {
-        let mut view_key = MeshPipelineKey::from_hdr(view.hdr);
+        let mut view_key = MeshPipelineKey::from_hdr(view.hdr)
+            | MeshPipelineKey::from_hdr_format(view.hdr_format)
+            | mesh_view_extra_bindings.view_key(entity);
 
         if normal_prepass {
             view_key |= MeshPipelineKey::NORMAL_PREPASS;
@@ -447,6 +526,10 @@ pub fn prepare_deferred_lighting_pipelines(
         // Always true, since we're in the deferred lighting pipeline
         view_key |= MeshPipelineKey::DEFERRED_PREPASS;
 
+        if deferred_gbuffer_extension {
+            view_key |= MeshPipelineKey::DEFERRED_GBUFFER_EXTENSION;
+        }
+
         if !view.hdr {
             if let Some(tonemapping) = tonemapping {
                 view_key |= MeshPipelineKey::TONEMAP_IN_SHADER;
@@ -497,8 +580,14 @@ pub fn prepare_deferred_lighting_pipelines(
             }
         }
 
-        let pipeline_id =
-            pipelines.specialize(&pipeline_cache, &deferred_lighting_layout, view_key);
+        let pipeline_id = pipelines.specialize(
+            &pipeline_cache,
+            &deferred_lighting_layout,
+            DeferredLightingPipelineKey {
+                mesh_key: view_key,
+                fragment_shader: deferred_lighting_shader.map(|shader| shader.0.clone()),
+            },
+        );
 
         commands
             .entity(entity)
@@ -21,6 +21,10 @@
     #import bevy_pbr::pbr_prepass_functions::calculate_motion_vector
 #endif
 
+#ifdef OBJECT_ID_PREPASS
+    #import bevy_pbr::mesh_bindings::mesh
+#endif
+
 // Creates the deferred gbuffer from a PbrInput.
 fn deferred_gbuffer_from_pbr_input(in: PbrInput) -> vec4<u32> {
      // Only monochrome occlusion supported. May not be worth including at all.
@@ -127,6 +131,12 @@ fn deferred_output(in: VertexOutput, pbr_input: PbrInput) -> FragmentOutput {
 #else
     out.motion_vector = calculate_motion_vector(in.world_position, in.previous_world_position);
 #endif
+#endif
+    // object id if required, 0 is left for the background
+#ifdef OBJECT_ID_PREPASS
+#ifndef MESHLET_MESH_MATERIAL_PASS
+    out.object_id = mesh[in.instance_index].entity_index + 1u;
+#endif
 #endif
 
     return out;
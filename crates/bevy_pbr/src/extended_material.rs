@@ -4,14 +4,18 @@ use bevy_render::{
     mesh::MeshVertexBufferLayoutRef,
     render_asset::RenderAssets,
     render_resource::{
-        AsBindGroup, AsBindGroupError, BindGroupLayout, RenderPipelineDescriptor, Shader,
-        ShaderRef, SpecializedMeshPipelineError, UnpreparedBindGroup,
+        AsBindGroup, AsBindGroupError, BindGroupLayout, BlendState, DepthBiasState, Face,
+        RenderPipelineDescriptor, Shader, ShaderDefVal, ShaderRef, SpecializedMeshPipelineError,
+        UnpreparedBindGroup,
     },
     renderer::RenderDevice,
     texture::{FallbackImage, GpuImage},
 };
 
-use crate::{Material, MaterialPipeline, MaterialPipelineKey, MeshPipeline, MeshPipelineKey};
+use crate::{
+    Material, MaterialPass, MaterialPipeline, MaterialPipelineKey, MaterialStencil, MaterialView,
+    MeshPipeline, MeshPipelineKey, VertexColorMode,
+};
 
 pub struct MaterialExtensionPipeline {
     pub mesh_pipeline: MeshPipeline,
@@ -23,6 +27,10 @@ pub struct MaterialExtensionPipeline {
 pub struct MaterialExtensionKey<E: MaterialExtension> {
     pub mesh_key: MeshPipelineKey,
     pub bind_group_data: E::Data,
+    /// See [`MaterialPipelineKey::pass_index`].
+    pub pass_index: u32,
+    /// The [`Material::view_key`] of the base material, see [`MaterialPipelineKey::view_key`].
+    pub view_key: u32,
 }
 
 /// A subset of the `Material` trait for defining extensions to a base `Material`, such as the builtin `StandardMaterial`.
@@ -54,6 +62,18 @@ pub trait MaterialExtension: Asset + AsBindGroup + Clone + Sized {
         ShaderRef::Default
     }
 
+    /// Returns this material's shadow vertex shader. If [`ShaderRef::Default`] is returned, the base material shadow vertex shader
+    /// will be used.
+    fn shadow_vertex_shader() -> ShaderRef {
+        ShaderRef::Default
+    }
+
+    /// Returns this material's shadow fragment shader. If [`ShaderRef::Default`] is returned, the base material shadow fragment shader
+    /// will be used.
+    fn shadow_fragment_shader() -> ShaderRef {
+        ShaderRef::Default
+    }
+
     /// Returns this material's deferred vertex shader. If [`ShaderRef::Default`] is returned, the base material deferred vertex shader
     /// will be used.
     fn deferred_vertex_shader() -> ShaderRef {
@@ -212,6 +232,10 @@ impl<B: Material, E: MaterialExtension> Material for ExtendedMaterial<B, E> {
         B::opaque_render_method(&self.base)
     }
 
+    fn deferred_unsupported_features(&self) -> Vec<&'static str> {
+        B::deferred_unsupported_features(&self.base)
+    }
+
     fn depth_bias(&self) -> f32 {
         B::depth_bias(&self.base)
     }
@@ -220,6 +244,58 @@ impl<B: Material, E: MaterialExtension> Material for ExtendedMaterial<B, E> {
         B::reads_view_transmission_texture(&self.base)
     }
 
+    fn additional_passes(&self) -> Vec<MaterialPass> {
+        B::additional_passes(&self.base)
+    }
+
+    fn stencil(&self) -> Option<MaterialStencil> {
+        B::stencil(&self.base)
+    }
+
+    fn blend_state(&self) -> Option<BlendState> {
+        B::blend_state(&self.base)
+    }
+
+    fn deband_dither(&self) -> bool {
+        B::deband_dither(&self.base)
+    }
+
+    fn shader_defs(&self) -> Vec<ShaderDefVal> {
+        B::shader_defs(&self.base)
+    }
+
+    fn cull_mode(&self) -> Option<Face> {
+        B::cull_mode(&self.base)
+    }
+
+    fn double_sided_normals(&self) -> bool {
+        B::double_sided_normals(&self.base)
+    }
+
+    fn rasterizer_depth_bias(&self) -> DepthBiasState {
+        B::rasterizer_depth_bias(&self.base)
+    }
+
+    fn vertex_color_mode(&self) -> VertexColorMode {
+        B::vertex_color_mode(&self.base)
+    }
+
+    fn decal(&self) -> bool {
+        B::decal(&self.base)
+    }
+
+    fn deferred_gbuffer_extension(&self) -> bool {
+        B::deferred_gbuffer_extension(&self.base)
+    }
+
+    fn timed_draws(&self) -> bool {
+        B::timed_draws(&self.base)
+    }
+
+    fn view_key(view: &MaterialView) -> u32 {
+        B::view_key(view)
+    }
+
     fn prepass_vertex_shader() -> ShaderRef {
         match E::prepass_vertex_shader() {
             ShaderRef::Default => B::prepass_vertex_shader(),
@@ -234,6 +310,20 @@ impl<B: Material, E: MaterialExtension> Material for ExtendedMaterial<B, E> {
         }
     }
 
+    fn shadow_vertex_shader() -> ShaderRef {
+        match E::shadow_vertex_shader() {
+            ShaderRef::Default => B::shadow_vertex_shader(),
+            specified => specified,
+        }
+    }
+
+    fn shadow_fragment_shader() -> ShaderRef {
+        match E::shadow_fragment_shader() {
+            ShaderRef::Default => B::shadow_fragment_shader(),
+            specified => specified,
+        }
+    }
+
     fn deferred_vertex_shader() -> ShaderRef {
         match E::deferred_vertex_shader() {
             ShaderRef::Default => B::deferred_vertex_shader(),
@@ -296,6 +386,15 @@ impl<B: Material, E: MaterialExtension> Material for ExtendedMaterial<B, E> {
         let base_key = MaterialPipelineKey::<B> {
             mesh_key: key.mesh_key,
             bind_group_data: key.bind_group_data.0,
+            pass_index: key.pass_index,
+            stencil: key.stencil.clone(),
+            blend_state: key.blend_state,
+            lod: key.lod.clone(),
+            shader_defs: key.shader_defs.clone(),
+            cull_mode: key.cull_mode,
+            double_sided_normals: key.double_sided_normals,
+            rasterizer_depth_bias: key.rasterizer_depth_bias,
+            view_key: key.view_key,
         };
         B::specialize(&base_pipeline, descriptor, layout, base_key)?;
 
@@ -320,6 +419,8 @@ impl<B: Material, E: MaterialExtension> Material for ExtendedMaterial<B, E> {
             MaterialExtensionKey {
                 mesh_key: key.mesh_key,
                 bind_group_data: key.bind_group_data.1,
+                pass_index: key.pass_index,
+                view_key: key.view_key,
             },
         )
     }
@@ -0,0 +1,203 @@
+use bevy_asset::{
+    AssetEvent, AssetId, AssetLoadFailedEvent, AssetPath, Assets, Handle, UntypedAssetId,
+};
+use bevy_color::Color;
+use bevy_ecs::prelude::*;
+use bevy_math::{Affine2, Vec2};
+use bevy_render::{
+    render_asset::RenderAssetUsages,
+    render_resource::{Extent3d, TextureDimension, TextureFormat},
+    texture::{Image, ImageAddressMode, ImageSampler, ImageSamplerDescriptor},
+    view::ViewVisibility,
+    Extract,
+};
+use bevy_utils::HashMap;
+
+use crate::{Material, RenderMaterialInstances, StandardMaterial};
+
+/// The handle of the default [`FallbackMaterial`], a magenta checkerboard.
+pub const FALLBACK_MATERIAL_HANDLE: Handle<StandardMaterial> =
+    Handle::weak_from_u128(175924375082394768206417253961508713940);
+
+/// The handle of the checkerboard texture of the default [`FallbackMaterial`].
+const FALLBACK_MATERIAL_TEXTURE_HANDLE: Handle<Image> =
+    Handle::weak_from_u128(36280193571045902810476294611875239026);
+
+/// The material entities whose material failed to load are drawn with, see [`MissingMaterial`].
+///
+/// Defaults to a bright magenta checkerboard, so that content errors are visible instead of the
+/// meshes silently vanishing. Set it to `None` to not draw these entities.
+#[derive(Resource, Clone, Debug)]
+pub struct FallbackMaterial(pub Option<Handle<StandardMaterial>>);
+
+impl Default for FallbackMaterial {
+    fn default() -> Self {
+        Self(Some(FALLBACK_MATERIAL_HANDLE))
+    }
+}
+
+/// Marks an entity whose material failed to load, which is drawn with the [`FallbackMaterial`]
+/// instead.
+///
+/// This is inserted and removed by the [`MaterialPlugin`](crate::MaterialPlugin) of the
+/// material, which also sends a [`MissingMaterialEvent`].
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct MissingMaterial;
+
+/// Sent when an entity starts being drawn with the [`FallbackMaterial`] because its material
+/// failed to load.
+#[derive(Event, Clone, Debug)]
+pub struct MissingMaterialEvent {
+    /// The entity using the material.
+    pub entity: Entity,
+    /// The material that failed to load.
+    pub material: UntypedAssetId,
+    /// The path of the material that failed to load.
+    pub path: AssetPath<'static>,
+}
+
+/// Inserts the default [`FallbackMaterial`] and its texture.
+pub(crate) fn insert_fallback_material(world: &mut World) {
+    world.init_resource::<FallbackMaterial>();
+
+    let Some(mut images) = world.get_resource_mut::<Assets<Image>>() else {
+        return;
+    };
+    const SIZE: u32 = 8;
+    let data = (0..SIZE * SIZE)
+        .flat_map(|texel| {
+            if (texel % SIZE + texel / SIZE) % 2 == 0 {
+                [255, 255, 255, 255]
+            } else {
+                [32, 32, 32, 255]
+            }
+        })
+        .collect();
+    let mut image = Image::new(
+        Extent3d {
+            width: SIZE,
+            height: SIZE,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::RENDER_WORLD,
+    );
+    image.sampler = ImageSampler::Descriptor(ImageSamplerDescriptor {
+        address_mode_u: ImageAddressMode::Repeat,
+        address_mode_v: ImageAddressMode::Repeat,
+        ..ImageSamplerDescriptor::nearest()
+    });
+    images.insert(&FALLBACK_MATERIAL_TEXTURE_HANDLE, image);
+
+    world.resource_mut::<Assets<StandardMaterial>>().insert(
+        &FALLBACK_MATERIAL_HANDLE,
+        StandardMaterial {
+            // Meshes without UVs are plain magenta.
+            base_color: Color::srgb(1.0, 0.0, 1.0),
+            base_color_texture: Some(FALLBACK_MATERIAL_TEXTURE_HANDLE),
+            unlit: true,
+            // The checks of the texture are repeated 4 times over the UVs.
+            uv_transform: Affine2::from_scale(Vec2::splat(4.0)),
+            ..Default::default()
+        },
+    );
+}
+
+/// Inserts [`MissingMaterial`] on the entities whose material `M` failed to load, and removes
+/// it once they use a material that loaded.
+pub fn detect_missing_materials<M: Material>(
+    mut commands: Commands,
+    mut failed_materials: Local<HashMap<AssetId<M>, AssetPath<'static>>>,
+    mut load_failed_events: EventReader<AssetLoadFailedEvent<M>>,
+    mut asset_events: EventReader<AssetEvent<M>>,
+    materials: Query<(Entity, Ref<Handle<M>>, Has<MissingMaterial>)>,
+    mut missing_material_events: EventWriter<MissingMaterialEvent>,
+) {
+    let mut failed_materials_changed = false;
+    for event in load_failed_events.read() {
+        failed_materials.insert(event.id, event.path.clone());
+        failed_materials_changed = true;
+    }
+    for event in asset_events.read() {
+        // The material may load after it failed to, e.g. when the file is fixed and reloaded.
+        if let AssetEvent::Added { id } | AssetEvent::LoadedWithDependencies { id } = event {
+            failed_materials_changed |= failed_materials.remove(id).is_some();
+        }
+    }
+    if failed_materials.is_empty() && !failed_materials_changed {
+        return;
+    }
+
+    for (entity, handle, missing) in &materials {
+        if !failed_materials_changed && !handle.is_changed() {
+            continue;
+        }
+        match (failed_materials.get(&handle.id()), missing) {
+            (Some(path), false) => {
+                commands.entity(entity).insert(MissingMaterial);
+                missing_material_events.send(MissingMaterialEvent {
+                    entity,
+                    material: handle.id().untyped(),
+                    path: path.clone(),
+                });
+            }
+            (None, true) => {
+                commands.entity(entity).remove::<MissingMaterial>();
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Draws the visible entities with a [`MissingMaterial`] with the [`FallbackMaterial`].
+pub fn extract_missing_material_fallbacks(
+    mut render_material_instances: ResMut<RenderMaterialInstances<StandardMaterial>>,
+    fallback_material: Extract<Res<FallbackMaterial>>,
+    missing_materials: Extract<Query<(Entity, &ViewVisibility), With<MissingMaterial>>>,
+) {
+    let Some(fallback_material) = &fallback_material.0 else {
+        return;
+    };
+    for (entity, view_visibility) in &missing_materials {
+        if view_visibility.get() {
+            render_material_instances.insert(entity, fallback_material.id());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_asset::{AssetEvent, AssetLoadError, AssetLoadFailedEvent, Handle};
+    use bevy_ecs::prelude::*;
+
+    use super::{detect_missing_materials, MissingMaterial, MissingMaterialEvent};
+    use crate::StandardMaterial;
+
+    #[test]
+    fn missing_material_is_marked_until_it_loads() {
+        let mut world = World::new();
+        world.init_resource::<Events<AssetLoadFailedEvent<StandardMaterial>>>();
+        world.init_resource::<Events<AssetEvent<StandardMaterial>>>();
+        world.init_resource::<Events<MissingMaterialEvent>>();
+        let mut schedule = Schedule::default();
+        schedule.add_systems(detect_missing_materials::<StandardMaterial>);
+
+        let handle = Handle::<StandardMaterial>::weak_from_u128(1);
+        let entity = world.spawn(handle.clone()).id();
+        world.send_event(AssetLoadFailedEvent {
+            id: handle.id(),
+            path: "missing.material".into(),
+            error: AssetLoadError::AssetMetaReadError,
+        });
+        schedule.run(&mut world);
+        assert!(world.entity(entity).contains::<MissingMaterial>());
+        assert_eq!(world.resource::<Events<MissingMaterialEvent>>().len(), 1);
+
+        world
+            .send_event(AssetEvent::<StandardMaterial>::LoadedWithDependencies { id: handle.id() });
+        schedule.run(&mut world);
+        assert!(!world.entity(entity).contains::<MissingMaterial>());
+    }
+}
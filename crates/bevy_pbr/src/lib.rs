@@ -14,42 +14,71 @@ pub mod wireframe;
 /// Experimental features that are not yet finished. Please report any issues you encounter!
 ///
 /// Expect bugs, missing features, compatibility issues, low performance, and/or future breaking changes.
-#[cfg(feature = "meshlet")]
 pub mod experimental {
+    #[cfg(feature = "meshlet")]
     pub mod meshlet {
         pub use crate::meshlet::*;
     }
+    pub mod visibility_buffer {
+        pub use crate::visibility_buffer::*;
+    }
 }
 
 mod bundle;
 pub mod deferred;
 mod extended_material;
+mod fallback_material;
 mod fog;
 mod light;
 mod light_probe;
 mod lightmap;
 mod material;
+mod material_lod;
+mod material_overrides;
+mod material_pipeline_stats;
+mod material_report;
+mod material_table;
 mod parallax;
 mod pbr_material;
+pub mod picking;
+mod pipeline_permutations;
+mod pipeline_prewarm;
 mod prepass;
 mod render;
 mod ssao;
+mod static_batching;
+mod uv_transform;
+mod vertex_occlusion;
+mod visibility_buffer;
+mod wetness;
 
 use bevy_color::{Color, LinearRgba};
 use std::marker::PhantomData;
 
 pub use bundle::*;
 pub use extended_material::*;
+pub use fallback_material::*;
 pub use fog::*;
 pub use light::*;
 pub use light_probe::*;
 pub use lightmap::*;
 pub use material::*;
+pub use material_lod::*;
+pub use material_overrides::*;
+pub use material_pipeline_stats::*;
+pub use material_report::*;
+pub use material_table::*;
 pub use parallax::*;
 pub use pbr_material::*;
+pub use pipeline_permutations::*;
+pub use pipeline_prewarm::*;
 pub use prepass::*;
 pub use render::*;
 pub use ssao::*;
+pub use static_batching::*;
+pub use uv_transform::*;
+pub use vertex_occlusion::*;
+pub use wetness::*;
 
 pub mod prelude {
     #[doc(hidden)]
@@ -61,12 +90,13 @@ pub mod prelude {
         fog::{FogFalloff, FogSettings},
         light::{light_consts, AmbientLight, DirectionalLight, PointLight, SpotLight},
         light_probe::{
-            environment_map::{EnvironmentMapLight, ReflectionProbeBundle},
+            environment_map::{EnvironmentMapLight, ParallaxCorrection, ReflectionProbeBundle},
             LightProbe,
         },
         material::{Material, MaterialPlugin},
         parallax::ParallaxMappingMethod,
         pbr_material::StandardMaterial,
+        picking::{GpuPicking, GpuPickingResults, PickingLayers, PickingVisibility},
         ssao::ScreenSpaceAmbientOcclusionPlugin,
     };
 }
@@ -83,12 +113,20 @@ pub mod graph {
         DeferredLightingPass,
         /// Label for the compute shader instance data building pass.
         GpuPreprocess,
+        /// Label for the GPU picking render node.
+        GpuPicking,
+        /// Label for the node that copies GPU picking results into a readback buffer.
+        EntityIndexBufferCopy,
+        /// Label for the node that counts the pixels covered by each entity for GPU picking.
+        GpuPickingCoverage,
+        /// Label for the experimental visibility buffer rasterization and resolve node.
+        VisibilityBuffer,
     }
 }
 
-use crate::{deferred::DeferredPbrLightingPlugin, graph::NodePbr};
+use crate::{deferred::DeferredPbrLightingPlugin, graph::NodePbr, picking::GpuPickingPlugin};
 use bevy_app::prelude::*;
-use bevy_asset::{load_internal_asset, AssetApp, Assets, Handle};
+use bevy_asset::{load_internal_asset, AssetApp, AssetId, Assets, Handle};
 use bevy_core_pipeline::core_3d::graph::{Core3d, Node3d};
 use bevy_ecs::prelude::*;
 use bevy_render::{
@@ -98,10 +136,11 @@ use bevy_render::{
         Projection,
     },
     extract_component::ExtractComponentPlugin,
+    extract_instances::extract_visible,
     extract_resource::ExtractResourcePlugin,
     render_asset::prepare_assets,
     render_graph::RenderGraph,
-    render_resource::Shader,
+    render_resource::{Shader, ShaderConstants},
     texture::{GpuImage, Image},
     view::{check_visibility, VisibilitySystems},
     ExtractSchedule, Render, RenderApp, RenderSet,
@@ -130,6 +169,8 @@ pub const PBR_PREPASS_FUNCTIONS_SHADER_HANDLE: Handle<Shader> =
 pub const PBR_DEFERRED_TYPES_HANDLE: Handle<Shader> = Handle::weak_from_u128(3221241127431430599);
 pub const PBR_DEFERRED_FUNCTIONS_HANDLE: Handle<Shader> = Handle::weak_from_u128(72019026415438599);
 pub const RGB9E5_FUNCTIONS_HANDLE: Handle<Shader> = Handle::weak_from_u128(2659010996143919192);
+pub const WETNESS_SHADER_HANDLE: Handle<Shader> = Handle::weak_from_u128(9183740216453098217);
+pub const SHADER_CONSTANTS_HANDLE: Handle<Shader> = Handle::weak_from_u128(4410978521739053296);
 const MESHLET_VISIBILITY_BUFFER_RESOLVE_SHADER_HANDLE: Handle<Shader> =
     Handle::weak_from_u128(2325134235233421);
 
@@ -145,6 +186,29 @@ pub struct PbrPlugin {
     /// This requires compute shader support and so will be forcibly disabled if
     /// the platform doesn't support those.
     pub use_gpu_instance_buffer_builder: bool,
+    /// Controls if meshes drawn with the default vertex shaders read their
+    /// vertices from storage buffers, see [`UseVertexPulling`].
+    ///
+    /// This requires storage buffer support and so will be forcibly disabled if
+    /// the platform doesn't support those.
+    pub use_vertex_pulling: bool,
+    /// Replaces the shaders of the [`StandardMaterial`], to shade it entirely with your own
+    /// shaders while keeping the glTF import, batching, lightmaps and shadows working.
+    ///
+    /// See [`MaterialShaders`] for what the replacement shaders must provide.
+    ///
+    /// ```
+    /// # use bevy_pbr::{MaterialShaders, PbrPlugin};
+    /// let pbr_plugin = PbrPlugin {
+    ///     standard_material_shaders: MaterialShaders {
+    ///         fragment: Some("shaders/my_pbr.wgsl".into()),
+    ///         deferred_fragment: Some("shaders/my_pbr_deferred.wgsl".into()),
+    ///         ..Default::default()
+    ///     },
+    ///     ..Default::default()
+    /// };
+    /// ```
+    pub standard_material_shaders: MaterialShaders,
 }
 
 impl Default for PbrPlugin {
@@ -153,10 +217,29 @@ impl Default for PbrPlugin {
             prepass_enabled: true,
             add_default_deferred_lighting_plugin: true,
             use_gpu_instance_buffer_builder: true,
+            use_vertex_pulling: false,
+            standard_material_shaders: MaterialShaders::default(),
         }
     }
 }
 
+/// Generates the `bevy_pbr::shader_constants` module, holding the constants the shaders share
+/// with the Rust side so that they can't drift apart.
+fn shader_constants() -> Shader {
+    ShaderConstants::new("bevy_pbr::shader_constants")
+        .with_u32("CLUSTER_COUNT_SIZE", CLUSTER_COUNT_SIZE)
+        .with_f32("POINT_LIGHT_NEAR_Z", POINT_LIGHT_NEAR_Z)
+        .with_u32(
+            "MATERIAL_BINDINGS_INDEX_BITS",
+            (1 << MaterialBindingsIndex::INDEX_BITS) - 1,
+        )
+        .with_u32(
+            "MATERIAL_BINDINGS_GENERATION_SHIFT",
+            MaterialBindingsIndex::INDEX_BITS,
+        )
+        .build()
+}
+
 impl Plugin for PbrPlugin {
     fn build(&self, app: &mut App) {
         load_internal_asset!(
@@ -172,6 +255,9 @@ impl Plugin for PbrPlugin {
             Shader::from_wgsl
         );
         load_internal_asset!(app, UTILS_HANDLE, "render/utils.wgsl", Shader::from_wgsl);
+        app.world_mut()
+            .resource_mut::<Assets<Shader>>()
+            .insert(&SHADER_CONSTANTS_HANDLE, shader_constants());
         load_internal_asset!(
             app,
             CLUSTERED_FORWARD_HANDLE,
@@ -239,6 +325,12 @@ impl Plugin for PbrPlugin {
             Shader::from_wgsl
         );
         load_internal_asset!(app, PBR_SHADER_HANDLE, "render/pbr.wgsl", Shader::from_wgsl);
+        load_internal_asset!(
+            app,
+            WETNESS_SHADER_HANDLE,
+            "render/wetness.wgsl",
+            Shader::from_wgsl
+        );
         load_internal_asset!(
             app,
             PBR_PREPASS_FUNCTIONS_SHADER_HANDLE,
@@ -273,6 +365,11 @@ impl Plugin for PbrPlugin {
 
         app.register_asset_reflect::<StandardMaterial>()
             .register_type::<AmbientLight>()
+            .register_type::<MaterialLod>()
+            .register_type::<MaterialOverrides>()
+            .register_type::<UvTransform>()
+            .register_type::<VertexColorMode>()
+            .register_type::<CascadeBlendMode>()
             .register_type::<CascadeShadowConfig>()
             .register_type::<Cascades>()
             .register_type::<CascadesVisibleEntities>()
@@ -284,6 +381,8 @@ impl Plugin for PbrPlugin {
             .register_type::<NotShadowReceiver>()
             .register_type::<PointLight>()
             .register_type::<PointLightShadowMap>()
+            .register_type::<ShadowBiasMode>()
+            .register_type::<DefaultShadowBiasMode>()
             .register_type::<SpotLight>()
             .register_type::<FogSettings>()
             .register_type::<ShadowFilteringMethod>()
@@ -291,14 +390,17 @@ impl Plugin for PbrPlugin {
             .init_resource::<GlobalVisiblePointLights>()
             .init_resource::<DirectionalLightShadowMap>()
             .init_resource::<PointLightShadowMap>()
+            .init_resource::<DefaultShadowBiasMode>()
             .register_type::<DefaultOpaqueRendererMethod>()
             .init_resource::<DefaultOpaqueRendererMethod>()
             .add_plugins((
                 MeshRenderPlugin {
                     use_gpu_instance_buffer_builder: self.use_gpu_instance_buffer_builder,
+                    use_vertex_pulling: self.use_vertex_pulling,
                 },
                 MaterialPlugin::<StandardMaterial> {
                     prepass_enabled: self.prepass_enabled,
+                    shaders: self.standard_material_shaders.clone(),
                     ..Default::default()
                 },
                 ScreenSpaceAmbientOcclusionPlugin,
@@ -314,6 +416,7 @@ impl Plugin for PbrPlugin {
                 GpuMeshPreprocessPlugin {
                     use_gpu_instance_buffer_builder: self.use_gpu_instance_buffer_builder,
                 },
+                GpuPickingPlugin,
             ))
             .configure_sets(
                 PostUpdate,
@@ -381,6 +484,7 @@ impl Plugin for PbrPlugin {
                     ..Default::default()
                 },
             );
+        insert_fallback_material(app.world_mut());
 
         let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
             return;
@@ -388,7 +492,17 @@ impl Plugin for PbrPlugin {
 
         // Extract the required data from the main world
         render_app
-            .add_systems(ExtractSchedule, (extract_clusters, extract_lights))
+            .init_resource::<RenderMaterialLods>()
+            .add_systems(
+                ExtractSchedule,
+                (
+                    extract_clusters,
+                    extract_lights,
+                    extract_material_lods,
+                    extract_missing_material_fallbacks
+                        .after(extract_visible::<AssetId<StandardMaterial>>),
+                ),
+            )
             .add_systems(
                 Render,
                 (
@@ -0,0 +1,135 @@
+use bevy_app::{App, Plugin};
+use bevy_ecs::{prelude::*, query::QueryItem};
+use bevy_reflect::prelude::*;
+use bevy_render::{
+    extract_component::{ExtractComponent, ExtractComponentPlugin},
+    render_resource::{binding_types::*, *},
+    renderer::RenderDevice,
+    RenderApp,
+};
+
+use crate::{MeshViewLayoutExtension, MeshViewLayoutExtensionPlugin};
+
+/// The format of the colored shadow maps: the light transmittance in rgb, and the depth of the
+/// translucent caster closest to the light in alpha.
+pub const COLORED_SHADOW_MAP_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+
+/// Adds the colored shadow maps of the cameras with [`ColoredShadows`].
+///
+/// This adds a [`MeshViewLayoutExtension`], so it must be added before the
+/// [`PbrPlugin`](crate::PbrPlugin) is finished.
+pub struct ColoredShadowsPlugin;
+
+impl Plugin for ColoredShadowsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<ColoredShadows>().add_plugins((
+            ExtractComponentPlugin::<ColoredShadows>::default(),
+            MeshViewLayoutExtensionPlugin::<ViewColoredShadowMaps>::default(),
+        ));
+    }
+
+    fn finish(&self, app: &mut App) {
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app.init_resource::<ColoredShadowMapSampler>();
+    }
+}
+
+/// Add this component to a 3D camera so that the lights it sees cast colored shadows through
+/// the meshes whose [`Material::colored_shadow`](crate::Material::colored_shadow) is `true`,
+/// like stained glass or foliage, when the [`ColoredShadowsPlugin`] is added.
+///
+/// Each shadow map of the lights gets a colored shadow map, where those meshes write how much
+/// light they let through instead of occluding it. Only the translucent caster closest to the
+/// light is tracked, so all the receivers behind it are tinted by every caster in front of them,
+/// whichever is in between.
+#[derive(Component, Clone, Copy, Debug, Default, Reflect)]
+#[reflect(Component, Default)]
+pub struct ColoredShadows;
+
+impl ExtractComponent for ColoredShadows {
+    type QueryData = ();
+    type QueryFilter = With<ColoredShadows>;
+    type Out = ViewColoredShadowMaps;
+
+    fn extract_component(_: QueryItem<'_, Self::QueryData>) -> Option<Self::Out> {
+        Some(ViewColoredShadowMaps::default())
+    }
+}
+
+/// The colored shadow maps of a view with [`ColoredShadows`], prepared by
+/// [`prepare_lights`](crate::prepare_lights).
+#[derive(Component, Default)]
+pub struct ViewColoredShadowMaps {
+    pub point_light_texture_view: Option<TextureView>,
+    pub directional_light_texture_view: Option<TextureView>,
+    pub sampler: Option<Sampler>,
+}
+
+impl MeshViewLayoutExtension for ViewColoredShadowMaps {
+    const SHADER_DEF: &'static str = "COLORED_SHADOWS";
+
+    const BINDING_SHADER_DEFS: &'static [&'static str] = &[
+        "COLORED_SHADOWS_POINT_TEXTURES_BINDING",
+        "COLORED_SHADOWS_DIRECTIONAL_TEXTURES_BINDING",
+        "COLORED_SHADOWS_SAMPLER_BINDING",
+    ];
+
+    fn layout_entries(_render_device: &RenderDevice) -> Vec<BindGroupLayoutEntryBuilder> {
+        let sample_type = TextureSampleType::Float { filterable: true };
+        vec![
+            #[cfg(all(
+                not(feature = "ios_simulator"),
+                any(
+                    not(feature = "webgl"),
+                    not(target_arch = "wasm32"),
+                    feature = "webgpu"
+                )
+            ))]
+            texture_cube_array(sample_type),
+            #[cfg(any(
+                feature = "ios_simulator",
+                all(feature = "webgl", target_arch = "wasm32", not(feature = "webgpu"))
+            ))]
+            texture_cube(sample_type),
+            #[cfg(any(
+                not(feature = "webgl"),
+                not(target_arch = "wasm32"),
+                feature = "webgpu"
+            ))]
+            texture_2d_array(sample_type),
+            #[cfg(all(feature = "webgl", target_arch = "wasm32", not(feature = "webgpu")))]
+            texture_2d(sample_type),
+            sampler(SamplerBindingType::Filtering),
+        ]
+    }
+
+    fn bindings(&self) -> Option<Vec<OwnedBindingResource>> {
+        Some(vec![
+            OwnedBindingResource::TextureView(self.point_light_texture_view.clone()?),
+            OwnedBindingResource::TextureView(self.directional_light_texture_view.clone()?),
+            OwnedBindingResource::Sampler(self.sampler.clone()?),
+        ])
+    }
+}
+
+/// The sampler of the colored shadow maps.
+#[derive(Resource)]
+pub struct ColoredShadowMapSampler(pub Sampler);
+
+impl FromWorld for ColoredShadowMapSampler {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+        Self(render_device.create_sampler(&SamplerDescriptor {
+            label: Some("colored_shadow_map_sampler"),
+            address_mode_u: AddressMode::ClampToEdge,
+            address_mode_v: AddressMode::ClampToEdge,
+            address_mode_w: AddressMode::ClampToEdge,
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            mipmap_filter: FilterMode::Nearest,
+            ..Default::default()
+        }))
+    }
+}
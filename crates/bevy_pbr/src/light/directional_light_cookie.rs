@@ -0,0 +1,236 @@
+use bevy_app::{App, Plugin};
+use bevy_asset::{AssetId, Assets, Handle};
+use bevy_core_pipeline::core_3d::Camera3d;
+use bevy_ecs::prelude::*;
+use bevy_math::{Vec2, Vec4};
+use bevy_reflect::prelude::*;
+use bevy_render::{
+    camera::Camera,
+    render_asset::RenderAssets,
+    render_resource::{binding_types::*, *},
+    renderer::RenderDevice,
+    texture::{FallbackImage, GpuImage, Image},
+    view::ViewVisibility,
+    Extract, ExtractSchedule, Render, RenderApp, RenderSet,
+};
+use bevy_time::Time;
+use bevy_utils::warn_once;
+
+use crate::{DirectionalLight, MeshViewLayoutExtension, MeshViewLayoutExtensionPlugin};
+
+/// Adds the [`DirectionalLightCookie`]s of the directional lights.
+///
+/// This adds a [`MeshViewLayoutExtension`], so it must be added before the
+/// [`PbrPlugin`](crate::PbrPlugin) is finished.
+pub struct DirectionalLightCookiePlugin;
+
+impl Plugin for DirectionalLightCookiePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<DirectionalLightCookie>()
+            .add_plugins(MeshViewLayoutExtensionPlugin::<ViewDirectionalLightCookie>::default());
+
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app
+            .add_systems(ExtractSchedule, extract_directional_light_cookies)
+            .add_systems(
+                Render,
+                prepare_directional_light_cookies.in_set(RenderSet::PrepareResources),
+            );
+    }
+
+    fn finish(&self, app: &mut App) {
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app.init_resource::<DirectionalLightCookieSampler>();
+    }
+}
+
+/// Add this component to a [`DirectionalLight`] to multiply its light by a tiling texture
+/// projected along the light onto a horizontal plane, when the [`DirectionalLightCookiePlugin`]
+/// is added.
+///
+/// The texture scrolls across the plane with [`DirectionalLightCookie::scroll_speed`], which is
+/// the usual way to get cheap animated cloud shadows over a terrain. Its rgb channels are
+/// multiplied into the light, so a colored texture tints it.
+///
+/// All the cameras bind a single cookie texture, so only the lights using the same
+/// [`DirectionalLightCookie::image`] as the first one get a cookie.
+#[derive(Component, Clone, Debug, Reflect)]
+#[reflect(Component, Default)]
+pub struct DirectionalLightCookie {
+    /// The tiling texture. It is sampled with a repeating sampler, so it should tile seamlessly.
+    pub image: Handle<Image>,
+    /// The size of one tile of the texture on the plane, in world units.
+    pub tile_size: Vec2,
+    /// How fast the texture moves across the plane along the world x and z axes, in world units
+    /// per second.
+    pub scroll_speed: Vec2,
+    /// The world y coordinate of the plane the texture is projected from, such as the altitude of
+    /// the cloud layer.
+    ///
+    /// The texture is projected along the light direction, so a plane above the scene makes its
+    /// shadows slide across slopes as the light gets lower.
+    pub height: f32,
+    /// How much the texture darkens the light, from 0.0 (not at all) to 1.0 (fully).
+    pub strength: f32,
+}
+
+impl Default for DirectionalLightCookie {
+    fn default() -> Self {
+        Self {
+            image: Handle::default(),
+            tile_size: Vec2::splat(100.0),
+            scroll_speed: Vec2::ZERO,
+            height: 0.0,
+            strength: 1.0,
+        }
+    }
+}
+
+/// The [`DirectionalLightCookie`] of a directional light in the render world, read by
+/// [`prepare_lights`](crate::prepare_lights).
+#[derive(Component, Clone, Copy, Debug)]
+pub struct ExtractedDirectionalLightCookie {
+    /// The scale from the plane coordinates to the texture coordinates in xy, and the scroll
+    /// offset of the texture coordinates this frame in zw.
+    pub uv_transform: Vec4,
+    pub height: f32,
+    /// Zero when the light uses another texture than the one bound to the views.
+    pub strength: f32,
+}
+
+/// The cookie texture bound to a view while a [`DirectionalLightCookie`] is in use.
+#[derive(Component)]
+pub struct ViewDirectionalLightCookie {
+    pub image: AssetId<Image>,
+    pub texture_view: Option<TextureView>,
+    pub sampler: Option<Sampler>,
+}
+
+impl MeshViewLayoutExtension for ViewDirectionalLightCookie {
+    const SHADER_DEF: &'static str = "DIRECTIONAL_LIGHT_COOKIE";
+
+    const BINDING_SHADER_DEFS: &'static [&'static str] = &[
+        "DIRECTIONAL_LIGHT_COOKIE_TEXTURE_BINDING",
+        "DIRECTIONAL_LIGHT_COOKIE_SAMPLER_BINDING",
+    ];
+
+    fn layout_entries(_render_device: &RenderDevice) -> Vec<BindGroupLayoutEntryBuilder> {
+        vec![
+            texture_2d(TextureSampleType::Float { filterable: true }),
+            sampler(SamplerBindingType::Filtering),
+        ]
+    }
+
+    fn bindings(&self) -> Option<Vec<OwnedBindingResource>> {
+        Some(vec![
+            OwnedBindingResource::TextureView(self.texture_view.clone()?),
+            OwnedBindingResource::Sampler(self.sampler.clone()?),
+        ])
+    }
+}
+
+/// The repeating sampler of the directional light cookies.
+#[derive(Resource)]
+pub struct DirectionalLightCookieSampler(pub Sampler);
+
+impl FromWorld for DirectionalLightCookieSampler {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+        Self(render_device.create_sampler(&SamplerDescriptor {
+            label: Some("directional_light_cookie_sampler"),
+            address_mode_u: AddressMode::Repeat,
+            address_mode_v: AddressMode::Repeat,
+            address_mode_w: AddressMode::Repeat,
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            mipmap_filter: FilterMode::Linear,
+            ..Default::default()
+        }))
+    }
+}
+
+/// Extracts the cookies of the visible directional lights, and adds a
+/// [`ViewDirectionalLightCookie`] to the 3D cameras if any light has one.
+pub fn extract_directional_light_cookies(
+    mut commands: Commands,
+    time: Extract<Res<Time>>,
+    images: Extract<Res<Assets<Image>>>,
+    lights: Extract<
+        Query<(Entity, &DirectionalLightCookie, &ViewVisibility), With<DirectionalLight>>,
+    >,
+    cameras: Extract<Query<(Entity, &Camera), With<Camera3d>>>,
+) {
+    let mut lights: Vec<_> = lights
+        .iter()
+        .filter(|(_, cookie, view_visibility)| {
+            view_visibility.get() && images.contains(&cookie.image)
+        })
+        .collect();
+    // Pick the same texture every frame, whatever the query order
+    lights.sort_by_key(|(entity, ..)| *entity);
+    let Some(image) = lights.first().map(|(_, cookie, _)| cookie.image.id()) else {
+        return;
+    };
+
+    let elapsed = time.elapsed_seconds_f64();
+    for (entity, cookie, _) in lights {
+        let strength = if cookie.image.id() == image {
+            cookie.strength.clamp(0.0, 1.0)
+        } else {
+            warn_once!(
+                "Directional lights use different cookie textures, only the lights using the \
+                first one get a cookie"
+            );
+            0.0
+        };
+        let tile_size = cookie.tile_size.max(Vec2::splat(f32::EPSILON));
+        // Wrapped in f64 so that the offset keeps its precision however long the app runs
+        let offset = Vec2::new(
+            (cookie.scroll_speed.x as f64 * elapsed / tile_size.x as f64).rem_euclid(1.0) as f32,
+            (cookie.scroll_speed.y as f64 * elapsed / tile_size.y as f64).rem_euclid(1.0) as f32,
+        );
+        commands
+            .get_or_spawn(entity)
+            .insert(ExtractedDirectionalLightCookie {
+                uv_transform: tile_size.recip().extend(offset.x).extend(offset.y),
+                height: cookie.height,
+                strength,
+            });
+    }
+
+    for (entity, camera) in &cameras {
+        if !camera.is_active {
+            continue;
+        }
+        commands
+            .get_or_spawn(entity)
+            .insert(ViewDirectionalLightCookie {
+                image,
+                texture_view: None,
+                sampler: None,
+            });
+    }
+}
+
+/// Binds the cookie texture to the views, or a white texture until it is uploaded.
+pub fn prepare_directional_light_cookies(
+    mut views: Query<&mut ViewDirectionalLightCookie>,
+    images: Res<RenderAssets<GpuImage>>,
+    fallback_image: Res<FallbackImage>,
+    sampler: Option<Res<DirectionalLightCookieSampler>>,
+) {
+    let Some(sampler) = sampler else {
+        return;
+    };
+    for mut view_cookie in &mut views {
+        let texture_view = images
+            .get(view_cookie.image)
+            .map_or(&fallback_image.d2.texture_view, |image| &image.texture_view);
+        view_cookie.texture_view = Some(texture_view.clone());
+        view_cookie.sampler = Some(sampler.0.clone());
+    }
+}
@@ -26,12 +26,23 @@ use crate::*;
 
 mod ambient_light;
 pub use ambient_light::AmbientLight;
+mod colored_shadows;
+pub use colored_shadows::{
+    ColoredShadowMapSampler, ColoredShadows, ColoredShadowsPlugin, ViewColoredShadowMaps,
+    COLORED_SHADOW_MAP_FORMAT,
+};
 mod point_light;
 pub use point_light::PointLight;
 mod spot_light;
 pub use spot_light::SpotLight;
 mod directional_light;
 pub use directional_light::DirectionalLight;
+mod directional_light_cookie;
+pub use directional_light_cookie::{
+    extract_directional_light_cookies, prepare_directional_light_cookies, DirectionalLightCookie,
+    DirectionalLightCookiePlugin, DirectionalLightCookieSampler, ExtractedDirectionalLightCookie,
+    ViewDirectionalLightCookie,
+};
 
 /// Constants for operating with the light units: lumens, and lux.
 pub mod light_consts {
@@ -119,6 +130,52 @@ impl Default for DirectionalLightShadowMap {
     }
 }
 
+/// How the shadow depth and normal biases of a light are chosen.
+///
+/// Add this component to a [`PointLight`], [`SpotLight`] or [`DirectionalLight`] to override the
+/// [`DefaultShadowBiasMode`] for that light, e.g. to keep hand-tuned biases on one light while the
+/// others are tuned automatically.
+#[derive(Component, Reflect, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[reflect(Component, Default, PartialEq)]
+pub enum ShadowBiasMode {
+    /// Uses the `shadow_depth_bias` and `shadow_normal_bias` of the light.
+    ///
+    /// The depth bias is a distance in world units, so it has to be tuned to the scale of the
+    /// scene.
+    #[default]
+    Manual,
+    /// Derives both biases from the world space size of a shadow map texel, ignoring the biases of
+    /// the light.
+    ///
+    /// The texel size is computed per cascade for directional lights, and at the distance of the
+    /// shaded fragment for point and spot lights, so the biases follow the resolution of the
+    /// shadow map at any scene scale.
+    Auto,
+}
+
+impl ShadowBiasMode {
+    /// The depth bias used by [`ShadowBiasMode::Auto`], in shadow map texels.
+    pub const AUTO_DEPTH_BIAS_TEXELS: f32 = 1.5;
+    /// The normal bias used by [`ShadowBiasMode::Auto`], in shadow map texels.
+    pub const AUTO_NORMAL_BIAS_TEXELS: f32 = 1.0;
+
+    /// Returns the depth and normal biases of a light, in the units its `shadow_depth_bias` and
+    /// `shadow_normal_bias` fields use in this mode.
+    pub fn biases(&self, shadow_depth_bias: f32, shadow_normal_bias: f32) -> (f32, f32) {
+        match self {
+            ShadowBiasMode::Manual => (shadow_depth_bias, shadow_normal_bias),
+            ShadowBiasMode::Auto => (Self::AUTO_DEPTH_BIAS_TEXELS, Self::AUTO_NORMAL_BIAS_TEXELS),
+        }
+    }
+}
+
+/// The [`ShadowBiasMode`] of the lights that don't have one.
+///
+/// Defaults to [`ShadowBiasMode::Manual`].
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq, Reflect)]
+#[reflect(Resource, Default)]
+pub struct DefaultShadowBiasMode(pub ShadowBiasMode);
+
 /// Controls how cascaded shadow mapping works.
 /// Prefer using [`CascadeShadowConfigBuilder`] to construct an instance.
 ///
@@ -141,6 +198,25 @@ pub struct CascadeShadowConfig {
     pub overlap_proportion: f32,
     /// The (positive) distance to the near boundary of the first cascade.
     pub minimum_distance: f32,
+    /// How the shadows of two cascades are blended where they overlap.
+    pub blend_mode: CascadeBlendMode,
+}
+
+/// How the shadows of neighbouring cascades are blended in the
+/// [`overlap_proportion`](CascadeShadowConfig::overlap_proportion) between them, to hide the line
+/// where one cascade switches to the next.
+#[derive(Reflect, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[reflect(Default, PartialEq)]
+pub enum CascadeBlendMode {
+    /// No blending: each fragment samples a single cascade, and the switch is a hard line.
+    None,
+    /// Mixes the shadows of both cascades, which samples two shadow maps in the overlap.
+    #[default]
+    Smooth,
+    /// Picks one of the two cascades per pixel with a noise dither weighted by the blend factor,
+    /// which samples a single shadow map. The dither changes every frame, so this looks best with
+    /// temporal anti-aliasing.
+    Dithered,
 }
 
 impl Default for CascadeShadowConfig {
@@ -198,6 +274,8 @@ pub struct CascadeShadowConfigBuilder {
     /// The overlap is used to make the transition from one cascade's shadow map to the next
     /// less abrupt by blending between both shadow maps.
     pub overlap_proportion: f32,
+    /// Sets how both shadow maps are blended in the overlap between cascades.
+    pub blend_mode: CascadeBlendMode,
 }
 
 impl CascadeShadowConfigBuilder {
@@ -236,6 +314,7 @@ impl CascadeShadowConfigBuilder {
             ),
             overlap_proportion: self.overlap_proportion,
             minimum_distance: self.minimum_distance,
+            blend_mode: self.blend_mode,
         }
     }
 }
@@ -254,6 +333,7 @@ impl Default for CascadeShadowConfigBuilder {
                 maximum_distance: 100.0,
                 first_cascade_far_bound: 5.0,
                 overlap_proportion: 0.2,
+                blend_mode: CascadeBlendMode::Smooth,
             }
         } else {
             Self {
@@ -262,6 +342,7 @@ impl Default for CascadeShadowConfigBuilder {
                 maximum_distance: 1000.0,
                 first_cascade_far_bound: 5.0,
                 overlap_proportion: 0.2,
+                blend_mode: CascadeBlendMode::Smooth,
             }
         }
     }
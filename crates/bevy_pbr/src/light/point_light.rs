@@ -26,6 +26,9 @@ pub struct PointLight {
     pub range: f32,
     pub radius: f32,
     pub shadows_enabled: bool,
+    /// A bias applied along the direction from the fragment to the light, in world units. Ignored
+    /// in favor of biases derived from the shadow map texel size when the light uses
+    /// [`ShadowBiasMode::Auto`](crate::ShadowBiasMode::Auto).
     pub shadow_depth_bias: f32,
     /// A bias applied along the direction of the fragment's surface normal. It is scaled to the
     /// shadow map's texel size so that it can be small close to the camera and gets larger further
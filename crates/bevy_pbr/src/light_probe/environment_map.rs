@@ -32,6 +32,11 @@
 //! The Khronos Group has [several pre-filtered environment maps] available for
 //! you to use.
 //!
+//! By default, a reflection probe treats its surroundings as infinitely far
+//! away, so reflections don't line up with the walls of a room it's placed in.
+//! Adding a [`ParallaxCorrection`] projects the reflections onto a box or a
+//! sphere fitted to the room instead.
+//!
 //! Currently, reflection probes (i.e. environment maps attached to light
 //! probes) use binding arrays (also known as bindless textures) and
 //! consequently aren't supported on WebGL2 or WebGPU. Reflection probes are
@@ -48,9 +53,10 @@
 
 use bevy_asset::{AssetId, Handle};
 use bevy_ecs::{
-    bundle::Bundle, component::Component, query::QueryItem, system::lifetimeless::Read,
+    bundle::Bundle, component::Component, query::QueryItem, reflect::ReflectComponent,
+    system::lifetimeless::Read,
 };
-use bevy_reflect::Reflect;
+use bevy_reflect::{std_traits::ReflectDefault, Reflect};
 use bevy_render::{
     extract_instances::ExtractInstance,
     prelude::SpatialBundle,
@@ -110,6 +116,61 @@ pub struct EnvironmentMapIds {
     pub(crate) specular: AssetId<Image>,
 }
 
+/// How a reflection probe corrects the direction its cubemaps are sampled in,
+/// so that the reflections line up with the geometry they were captured from.
+///
+/// The cubemaps of a reflection probe are captured from its center. Without
+/// correction, they're sampled as if the surroundings were infinitely far away,
+/// which is fine for outdoor scenes but makes the reflections of a room slide
+/// over its walls as the camera moves. With correction, the reflected ray is
+/// intersected with a proxy volume approximating the room, and the cubemaps are
+/// sampled in the direction of that intersection from the center of the probe.
+///
+/// The proxy volume is the bounding region of the [`LightProbe`] itself, so the
+/// transform of the probe should match the room. This only affects reflection
+/// probes, and only their specular light.
+#[derive(Clone, Copy, Component, Debug, Default, Reflect, PartialEq)]
+#[reflect(Component, Default)]
+pub struct ParallaxCorrection {
+    /// The shape of the proxy volume the reflections are projected onto.
+    pub proxy: ParallaxCorrectionProxy,
+
+    /// The fraction of the half extents of the light probe, from 0 to 1, over
+    /// which its reflections fade into the view environment map near the
+    /// edges of the probe.
+    ///
+    /// This hides the seams between the inside and the outside of the probe.
+    /// The default of 0 disables the falloff.
+    pub influence_falloff: f32,
+}
+
+/// The shape of the proxy volume of a [`ParallaxCorrection`].
+#[derive(Clone, Copy, Debug, Default, Reflect, PartialEq, Eq)]
+#[reflect(Default)]
+pub enum ParallaxCorrectionProxy {
+    /// The surroundings are infinitely far away, which disables the
+    /// correction.
+    #[default]
+    None,
+    /// The surroundings are the faces of the light probe cuboid, which suits
+    /// rooms and corridors.
+    Box,
+    /// The surroundings are the ellipsoid inscribed in the light probe cuboid,
+    /// which suits rounded areas.
+    Sphere,
+}
+
+impl ParallaxCorrectionProxy {
+    /// The value the shader uses for this proxy.
+    pub(crate) fn as_u32(self) -> u32 {
+        match self {
+            ParallaxCorrectionProxy::None => 0,
+            ParallaxCorrectionProxy::Box => 1,
+            ParallaxCorrectionProxy::Sphere => 2,
+        }
+    }
+}
+
 /// A bundle that contains everything needed to make an entity a reflection
 /// probe.
 ///
@@ -124,6 +185,8 @@ pub struct ReflectionProbeBundle {
     pub light_probe: LightProbe,
     /// The cubemaps that make up this environment map.
     pub environment_map: EnvironmentMapLight,
+    /// How the reflections are projected onto the surroundings of the probe.
+    pub parallax_correction: ParallaxCorrection,
 }
 
 /// All the bind group entries necessary for PBR shaders to access the
@@ -1,6 +1,6 @@
 #define_import_path bevy_pbr::environment_map
 
-#import bevy_pbr::light_probe::query_light_probe
+#import bevy_pbr::light_probe::{query_light_probe, LightProbeQueryResult}
 #import bevy_pbr::mesh_view_bindings as bindings
 #import bevy_pbr::mesh_view_bindings::light_probes
 
@@ -20,48 +20,132 @@ struct EnvironmentMapRadiances {
 
 #ifdef MULTIPLE_LIGHT_PROBES_IN_ARRAY
 
-fn compute_radiances(
+const PARALLAX_CORRECTION_PROXY_BOX: u32 = 1u;
+const PARALLAX_CORRECTION_PROXY_SPHERE: u32 = 2u;
+
+// Intersects the reflected ray with the proxy volume of the reflection probe,
+// and returns the direction from the center of the probe to the intersection.
+//
+// The proxy is the unit cube of the light probe, or the sphere inscribed in it,
+// so the intersection is computed in light probe model space.
+fn parallax_correct_reflection(
+    query_result: LightProbeQueryResult,
+    world_position: vec3<f32>,
+    R: vec3<f32>,
+) -> vec3<f32> {
+    let probe_space_pos = (query_result.inverse_transform * vec4(world_position, 1.0)).xyz;
+    let probe_space_dir = (query_result.inverse_transform * vec4(R, 0.0)).xyz;
+
+    var distance: f32;
+    if (query_result.parallax_correction_proxy == PARALLAX_CORRECTION_PROXY_BOX) {
+        // The fragment is inside the box, so the ray leaves it through the
+        // nearest of the planes it's heading towards.
+        let first_planes = (vec3(0.5) - probe_space_pos) / probe_space_dir;
+        let second_planes = (vec3(-0.5) - probe_space_pos) / probe_space_dir;
+        let furthest_planes = max(first_planes, second_planes);
+        distance = min(furthest_planes.x, min(furthest_planes.y, furthest_planes.z));
+    } else if (query_result.parallax_correction_proxy == PARALLAX_CORRECTION_PROXY_SPHERE) {
+        // Solve |pos + distance * dir|² = 0.5², taking the positive root since
+        // the fragment is inside the sphere.
+        let a = dot(probe_space_dir, probe_space_dir);
+        let b = dot(probe_space_pos, probe_space_dir);
+        let c = dot(probe_space_pos, probe_space_pos) - 0.25;
+        distance = (-b + sqrt(max(b * b - a * c, 0.0))) / a;
+    } else {
+        return R;
+    }
+
+    return world_position + R * distance - query_result.center;
+}
+
+// Returns how much the reflection probe contributes at the fragment, fading out
+// near the faces of the light probe over its influence falloff.
+fn reflection_probe_influence(
+    query_result: LightProbeQueryResult,
+    world_position: vec3<f32>,
+) -> f32 {
+    if (query_result.influence_falloff <= 0.0) {
+        return 1.0;
+    }
+
+    let probe_space_pos = (query_result.inverse_transform * vec4(world_position, 1.0)).xyz;
+    let distance_to_face = 0.5 - max(abs(probe_space_pos.x),
+        max(abs(probe_space_pos.y), abs(probe_space_pos.z)));
+    return saturate(distance_to_face / (0.5 * query_result.influence_falloff));
+}
+
+// Samples the diffuse and specular cubemaps at the given index of the binding
+// arrays.
+fn sample_environment_maps(
+    texture_index: i32,
+    intensity: f32,
     perceptual_roughness: f32,
     N: vec3<f32>,
     R: vec3<f32>,
-    world_position: vec3<f32>,
     found_diffuse_indirect: bool,
 ) -> EnvironmentMapRadiances {
     var radiances: EnvironmentMapRadiances;
 
-    // Search for a reflection probe that contains the fragment.
-    var query_result = query_light_probe(world_position, /*is_irradiance_volume=*/ false);
-
-    // If we didn't find a reflection probe, use the view environment map if applicable.
-    if (query_result.texture_index < 0) {
-        query_result.texture_index = light_probes.view_cubemap_index;
-        query_result.intensity = light_probes.intensity_for_view;
-    }
-
-    // If there's no cubemap, bail out.
-    if (query_result.texture_index < 0) {
-        radiances.irradiance = vec3(0.0);
-        radiances.radiance = vec3(0.0);
-        return radiances;
-    }
-
     // Split-sum approximation for image based lighting: https://cdn2.unrealengine.com/Resources/files/2013SiggraphPresentationsNotes-26915738.pdf
     let radiance_level = perceptual_roughness * f32(textureNumLevels(
-        bindings::specular_environment_maps[query_result.texture_index]) - 1u);
+        bindings::specular_environment_maps[texture_index]) - 1u);
 
     if (!found_diffuse_indirect) {
         radiances.irradiance = textureSampleLevel(
-            bindings::diffuse_environment_maps[query_result.texture_index],
+            bindings::diffuse_environment_maps[texture_index],
             bindings::environment_map_sampler,
             vec3(N.xy, -N.z),
-            0.0).rgb * query_result.intensity;
+            0.0).rgb * intensity;
     }
 
     radiances.radiance = textureSampleLevel(
-        bindings::specular_environment_maps[query_result.texture_index],
+        bindings::specular_environment_maps[texture_index],
         bindings::environment_map_sampler,
         vec3(R.xy, -R.z),
-        radiance_level).rgb * query_result.intensity;
+        radiance_level).rgb * intensity;
+
+    return radiances;
+}
+
+fn compute_radiances(
+    perceptual_roughness: f32,
+    N: vec3<f32>,
+    R: vec3<f32>,
+    world_position: vec3<f32>,
+    found_diffuse_indirect: bool,
+) -> EnvironmentMapRadiances {
+    var radiances: EnvironmentMapRadiances;
+    radiances.irradiance = vec3(0.0);
+    radiances.radiance = vec3(0.0);
+
+    // Search for a reflection probe that contains the fragment.
+    let query_result = query_light_probe(world_position, /*is_irradiance_volume=*/ false);
+
+    var influence = 0.0;
+    if (query_result.texture_index >= 0) {
+        influence = reflection_probe_influence(query_result, world_position);
+        radiances = sample_environment_maps(
+            query_result.texture_index,
+            query_result.intensity * influence,
+            perceptual_roughness,
+            N,
+            parallax_correct_reflection(query_result, world_position, R),
+            found_diffuse_indirect);
+    }
+
+    // Use the view environment map if applicable, for the fragments outside of
+    // reflection probes or near the edges of their influence.
+    if (influence < 1.0 && light_probes.view_cubemap_index >= 0) {
+        let view_radiances = sample_environment_maps(
+            light_probes.view_cubemap_index,
+            light_probes.intensity_for_view * (1.0 - influence),
+            perceptual_roughness,
+            N,
+            R,
+            found_diffuse_indirect);
+        radiances.irradiance += view_radiances.irradiance;
+        radiances.radiance += view_radiances.radiance;
+    }
 
     return radiances;
 }
@@ -14,6 +14,12 @@ struct LightProbeQueryResult {
     // Transform from world space to the light probe model space. In light probe
     // model space, the light probe is a 1×1×1 cube centered on the origin.
     inverse_transform: mat4x4<f32>,
+    // How the reflections of a reflection probe are parallax corrected, see
+    // `LightProbe`.
+    parallax_correction_proxy: u32,
+    influence_falloff: f32,
+    // The center of the light probe in world space.
+    center: vec3<f32>,
 };
 
 fn transpose_affine_matrix(matrix: mat3x4<f32>) -> mat4x4<f32> {
@@ -63,6 +69,9 @@ fn query_light_probe(
             result.texture_index = light_probe.cubemap_index;
             result.intensity = light_probe.intensity;
             result.inverse_transform = inverse_transform;
+            result.parallax_correction_proxy = light_probe.parallax_correction_proxy;
+            result.influence_falloff = light_probe.influence_falloff;
+            result.center = light_probe.center;
 
             // TODO: Workaround for ICE in DXC https://github.com/microsoft/DirectXShaderCompiler/issues/6183
             // We can't use `break` here because of the ICE.
@@ -12,7 +12,7 @@ use bevy_ecs::{
     schedule::IntoSystemConfigs,
     system::{Commands, Local, Query, Res, ResMut, Resource},
 };
-use bevy_math::{Affine3A, FloatOrd, Mat4, Vec3A, Vec4};
+use bevy_math::{Affine3A, FloatOrd, Mat4, Vec3, Vec3A, Vec4};
 use bevy_reflect::{std_traits::ReflectDefault, Reflect};
 use bevy_render::{
     extract_instances::ExtractInstancesPlugin,
@@ -34,7 +34,8 @@ use std::ops::Deref;
 use crate::{
     irradiance_volume::IRRADIANCE_VOLUME_SHADER_HANDLE,
     light_probe::environment_map::{
-        EnvironmentMapIds, EnvironmentMapLight, ENVIRONMENT_MAP_SHADER_HANDLE,
+        EnvironmentMapIds, EnvironmentMapLight, ParallaxCorrection, ParallaxCorrectionProxy,
+        ENVIRONMENT_MAP_SHADER_HANDLE,
     },
 };
 
@@ -124,6 +125,16 @@ struct RenderLightProbe {
     ///
     /// See the comment in [`EnvironmentMapLight`] for details.
     intensity: f32,
+
+    /// The [`ParallaxCorrectionProxy`] of a reflection probe, as a number.
+    parallax_correction_proxy: u32,
+
+    /// See [`ParallaxCorrection::influence_falloff`].
+    influence_falloff: f32,
+
+    /// The center of the light probe in world space, which the cubemaps were
+    /// captured from.
+    center: Vec3,
 }
 
 /// A per-view shader uniform that specifies all the light probes that the view
@@ -190,6 +201,10 @@ where
     // See the comment in [`EnvironmentMapLight`] for details.
     intensity: f32,
 
+    // How the reflections of a reflection probe are projected onto its
+    // surroundings.
+    parallax_correction: ParallaxCorrection,
+
     // The IDs of all assets associated with this light probe.
     //
     // Because each type of light probe component may reference different types
@@ -319,6 +334,8 @@ impl Plugin for LightProbePlugin {
 
         app.register_type::<LightProbe>()
             .register_type::<EnvironmentMapLight>()
+            .register_type::<ParallaxCorrection>()
+            .register_type::<ParallaxCorrectionProxy>()
             .register_type::<IrradianceVolume>();
     }
 
@@ -343,7 +360,9 @@ impl Plugin for LightProbePlugin {
 /// to views, performing frustum culling and distance sorting in the process.
 fn gather_light_probes<C>(
     image_assets: Res<RenderAssets<GpuImage>>,
-    light_probe_query: Extract<Query<(&GlobalTransform, &C), With<LightProbe>>>,
+    light_probe_query: Extract<
+        Query<(&GlobalTransform, &C, Option<&ParallaxCorrection>), With<LightProbe>>,
+    >,
     view_query: Extract<Query<(Entity, &GlobalTransform, &Frustum, Option<&C>), With<Camera3d>>>,
     mut reflection_probes: Local<Vec<LightProbeInfo<C>>>,
     mut view_reflection_probes: Local<Vec<LightProbeInfo<C>>>,
@@ -504,7 +523,11 @@ where
     /// [`LightProbeInfo`]. This is done for every light probe in the scene
     /// every frame.
     fn new(
-        (light_probe_transform, environment_map): (&GlobalTransform, &C),
+        (light_probe_transform, environment_map, parallax_correction): (
+            &GlobalTransform,
+            &C,
+            Option<&ParallaxCorrection>,
+        ),
         image_assets: &RenderAssets<GpuImage>,
     ) -> Option<LightProbeInfo<C>> {
         environment_map.id(image_assets).map(|id| LightProbeInfo {
@@ -512,6 +535,7 @@ where
             inverse_transform: light_probe_transform.compute_matrix().inverse(),
             asset_id: id,
             intensity: environment_map.intensity(),
+            parallax_correction: parallax_correction.copied().unwrap_or_default(),
         })
     }
 
@@ -609,6 +633,9 @@ where
                 ],
                 texture_index: cubemap_index as i32,
                 intensity: light_probe.intensity,
+                parallax_correction_proxy: light_probe.parallax_correction.proxy.as_u32(),
+                influence_falloff: light_probe.parallax_correction.influence_falloff,
+                center: light_probe.affine_transform.translation.into(),
             });
         }
     }
@@ -623,6 +650,7 @@ where
             inverse_transform: self.inverse_transform,
             affine_transform: self.affine_transform,
             intensity: self.intensity,
+            parallax_correction: self.parallax_correction,
             asset_id: self.asset_id.clone(),
         }
     }
@@ -7,7 +7,7 @@ use crate::*;
 use bevy_asset::{Asset, AssetId, AssetServer};
 use bevy_core_pipeline::{
     core_3d::{
-        AlphaMask3d, Camera3d, Opaque3d, Opaque3dBinKey, ScreenSpaceTransmissionQuality,
+        AlphaMask3d, Camera3d, Decal3d, Opaque3d, Opaque3dBinKey, ScreenSpaceTransmissionQuality,
         Transmissive3d, Transparent3d,
     },
     prepass::{
@@ -18,24 +18,37 @@ use bevy_core_pipeline::{
 use bevy_derive::{Deref, DerefMut};
 use bevy_ecs::{
     prelude::*,
-    system::{lifetimeless::SRes, SystemParamItem},
+    query::ROQueryItem,
+    system::{
+        lifetimeless::{SRes, SResMut},
+        SystemParamItem,
+    },
 };
 use bevy_reflect::Reflect;
 use bevy_render::{
-    camera::TemporalJitter,
-    extract_instances::{ExtractInstancesPlugin, ExtractedInstances},
+    camera::{Exposure, ExtractedCamera, Projection, TemporalJitter},
+    diagnostic::{DrawTimer, TimedDraws},
+    extract_instances::{extract_visible, ExtractInstancesPlugin, ExtractedInstances},
     extract_resource::ExtractResource,
     mesh::{GpuMesh, MeshVertexBufferLayoutRef},
-    render_asset::{PrepareAssetError, RenderAsset, RenderAssetPlugin, RenderAssets},
+    render_asset::{
+        PrepareAssetError, RenderAsset, RenderAssetPlugin, RenderAssets, VisibleRenderAssets,
+    },
     render_phase::*,
     render_resource::*,
     renderer::RenderDevice,
     texture::FallbackImage,
     view::{ExtractedView, Msaa, RenderVisibilityRanges, VisibleEntities, WithMesh},
 };
-use bevy_utils::tracing::error;
+use bevy_utils::{
+    get_short_name, info_once,
+    tracing::{error, warn},
+};
 use std::marker::PhantomData;
-use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc,
+};
 use std::{hash::Hash, num::NonZeroU32};
 
 use self::{irradiance_volume::IrradianceVolume, prelude::EnvironmentMapLight};
@@ -127,6 +140,18 @@ pub trait Material: Asset + AsBindGroup + Clone + Sized {
         OpaqueRendererMethod::Forward
     }
 
+    /// Returns the features of this material that the deferred renderer can't shade, because the
+    /// G-buffer doesn't store them.
+    ///
+    /// When the [`DefaultOpaqueRendererMethod`] is deferred, a material with
+    /// [`OpaqueRendererMethod::Auto`] that returns any feature is drawn with the forward renderer
+    /// instead of being shaded without them, and the features are kept in
+    /// [`MaterialProperties::deferred_fallback`]. Materials that select
+    /// [`OpaqueRendererMethod::Deferred`] explicitly are drawn deferred anyway.
+    fn deferred_unsupported_features(&self) -> Vec<&'static str> {
+        Vec::new()
+    }
+
     #[inline]
     /// Add a bias to the view depth of the mesh which can be used to force a specific render order.
     /// for meshes with similar depth, to avoid z-fighting.
@@ -144,6 +169,175 @@ pub trait Material: Asset + AsBindGroup + Clone + Sized {
         false
     }
 
+    /// Returns the passes this material is drawn in, in addition to its main pass.
+    ///
+    /// Each pass queues the mesh once more, in the phase selected by the [`AlphaMode`] of the
+    /// pass, and with the draw function registered for [`DrawMaterial`] in that phase. This allows,
+    /// for example, an opaque material to add an additive transparent "glow" pass.
+    ///
+    /// The pipeline of each pass is specialized separately, with [`MaterialPipelineKey::pass_index`]
+    /// set to the index of the pass, starting from `1`, and with the `MATERIAL_PASS_INDEX` shader
+    /// def set to the same value, so that [`Material::specialize`] and the shaders can tell the
+    /// passes apart. The main pass has a pass index of `0`.
+    ///
+    /// Additional passes are always forward rendered, and don't take part in the prepasses and
+    /// shadows, which only draw the main pass.
+    fn additional_passes(&self) -> Vec<MaterialPass> {
+        Vec::new()
+    }
+
+    /// Returns the stencil test and operations of this material, or `None` to leave the stencil
+    /// untouched.
+    ///
+    /// The stencil is only available to cameras with [`Camera3d::stencil`] enabled, and is
+    /// ignored by the other cameras. It applies to the main pass and the
+    /// [`Material::additional_passes`] of the material, but not to the prepasses, which don't
+    /// read or write the stencil.
+    fn stencil(&self) -> Option<MaterialStencil> {
+        None
+    }
+
+    /// Returns a [`BlendState`] that replaces the blending selected by the [`AlphaMode`] of this
+    /// material, or `None` to keep it.
+    ///
+    /// This allows blend operations that have no [`AlphaMode`], such as
+    /// [`BlendOperation::Min`], [`BlendOperation::Max`] and
+    /// [`BlendOperation::ReverseSubtract`]. The [`AlphaMode`] still selects the phase the mesh is
+    /// drawn in, so it should be a transparent one for the mesh to be sorted and drawn after the
+    /// opaque meshes. The blend state only applies to the main pass of the material.
+    ///
+    /// Dual-source blend factors, such as [`BlendFactor::Src1`], require a custom fragment shader
+    /// with a second `@blend_src(1)` output, and [`WgpuFeatures::DUAL_SOURCE_BLENDING`]. If the
+    /// feature isn't supported, the blend state is ignored.
+    fn blend_state(&self) -> Option<BlendState> {
+        None
+    }
+
+    /// Whether meshes with this material are dithered by cameras with
+    /// [`DebandDither::Enabled`] that draw them to an 8-bit texture.
+    ///
+    /// Return `false` for UI-like surfaces, such as flat colors and text drawn in the world, where
+    /// the grain of the dithering is more noticeable than banding. Materials drawn with the
+    /// deferred renderer are dithered by the lighting pass of the whole view, which ignores this.
+    fn deband_dither(&self) -> bool {
+        true
+    }
+
+    /// Returns shader defs that are added to the vertex and fragment shaders of the pipelines of
+    /// this material, including its prepass and shadow pipelines.
+    ///
+    /// This toggles code paths of the shaders per material instance, such as a `USE_DETAIL_MAP`
+    /// def, without a custom [`Material::specialize`] and bind group data. Each distinct list of
+    /// defs specializes its own pipelines, so prefer few combinations.
+    fn shader_defs(&self) -> Vec<ShaderDefVal> {
+        Vec::new()
+    }
+
+    /// Returns the faces culled by the pipelines of this material, or `None` to draw both faces.
+    ///
+    /// This applies to the main pass, the prepasses and the shadows of the material, before
+    /// [`Material::specialize`], which can still override it.
+    fn cull_mode(&self) -> Option<Face> {
+        Some(Face::Back)
+    }
+
+    /// Whether the normals of the back faces of meshes with this material are flipped to face
+    /// the camera, for materials that draw both faces.
+    ///
+    /// This defines `MATERIAL_DOUBLE_SIDED_NORMALS` in the shaders of the pipelines of this
+    /// material, for custom shaders to pass to `pbr_input_from_vertex_output` or
+    /// `prepare_world_normal`.
+    fn double_sided_normals(&self) -> bool {
+        false
+    }
+
+    /// Returns the depth bias the rasterizer adds to the depth of the fragments of this material.
+    ///
+    /// Unlike [`Material::depth_bias`], which only changes the order the meshes are sorted in,
+    /// this changes the depth the fragments are tested and written with. The
+    /// [`DepthBiasState::constant`] bias is in units of the smallest depth difference of the depth
+    /// format, and the [`DepthBiasState::slope_scale`] bias is scaled by the depth slope of the
+    /// triangle, so that decal-like materials drawn over other surfaces avoid z-fighting whatever
+    /// the depth precision and viewing angle. With the reverse-Z depth used by Bevy, positive
+    /// values move the fragments towards the camera.
+    ///
+    /// This applies to the main pass, the additional passes and the prepasses of the material,
+    /// but not to its shadows, before [`Material::specialize`], which can still override it.
+    /// Depth bias is ignored for meshes with a line or point topology.
+    fn rasterizer_depth_bias(&self) -> DepthBiasState {
+        DepthBiasState::default()
+    }
+
+    /// How the [`Mesh::ATTRIBUTE_COLOR`] of meshes with this material is used.
+    ///
+    /// This is applied by the mesh pipeline specialization, which only reads the attribute and
+    /// defines `VERTEX_COLORS` for meshes that have it unless this is
+    /// [`VertexColorMode::Ignore`], and additionally defines `VERTEX_COLORS_REPLACE` for
+    /// [`VertexColorMode::Replace`] and `VERTEX_COLORS_OCCLUSION` for
+    /// [`VertexColorMode::Occlusion`].
+    ///
+    /// [`Mesh::ATTRIBUTE_COLOR`]: bevy_render::mesh::Mesh::ATTRIBUTE_COLOR
+    fn vertex_color_mode(&self) -> VertexColorMode {
+        VertexColorMode::Multiply
+    }
+
+    /// Whether meshes with this material are decals, drawn over the opaque meshes in the
+    /// [`Decal3d`] phase instead of the phase of their [`AlphaMode`].
+    ///
+    /// Decals are drawn after the opaque pass, are depth tested against the opaque meshes
+    /// without writing depth, and aren't drawn in the prepasses or the shadow maps. This supports:
+    /// - mesh decals, whose geometry lies on the surface they're applied to, usually with a
+    ///   [`Material::depth_bias`] or a depth bias in [`Material::specialize`] to avoid
+    ///   z-fighting;
+    /// - screen-space decals, whose mesh is a volume that the fragment shader projects the decal
+    ///   through: it reads the depth of the scene with `bevy_pbr::prepass_utils::prepass_depth`
+    ///   and discards the fragments whose surface is outside of the volume.
+    ///
+    /// Shaders of decals are compiled with the `DECAL` shader def. Only cameras with a
+    /// [`DepthPrepass`] draw decals.
+    fn decal(&self) -> bool {
+        false
+    }
+
+    /// Whether this material writes to the G-buffer extension texture of cameras with a
+    /// [`DeferredGBufferExtensionPrepass`], when it's drawn with [`OpaqueRendererMethod::Deferred`].
+    ///
+    /// The deferred fragment shader of the material is then compiled with the
+    /// `DEFERRED_GBUFFER_EXTENSION` shader def, and writes the four `u32` channels of its own,
+    /// such as a custom ID or an anisotropy direction, to the `deferred_gbuffer_extension` field of
+    /// `bevy_pbr::prepass_io::FragmentOutput`. A [`DeferredLightingShader`] of the camera reads
+    /// them from `bevy_pbr::deferred_lighting_bindings::deferred_gbuffer_extension_texture`.
+    /// Materials that don't write to it leave zeros.
+    ///
+    /// [`DeferredGBufferExtensionPrepass`]: bevy_core_pipeline::prepass::DeferredGBufferExtensionPrepass
+    /// [`DeferredLightingShader`]: crate::deferred::DeferredLightingShader
+    fn deferred_gbuffer_extension(&self) -> bool {
+        false
+    }
+
+    /// Whether the draws of meshes with this material are timed on the GPU, as if the meshes had
+    /// [`TimedDraws`], when the [`DrawTimingsPlugin`] is added.
+    ///
+    /// The elapsed GPU time of the main pass draws of each material type is summed under the name
+    /// of the type, to find the material whose shader is the most expensive. Only a few draws can
+    /// be timed in a frame, so prefer [`TimedDraws`] on a few meshes for common materials.
+    ///
+    /// [`DrawTimingsPlugin`]: bevy_render::diagnostic::DrawTimingsPlugin
+    fn timed_draws(&self) -> bool {
+        false
+    }
+
+    /// Whether meshes with this material cast colored shadows instead of opaque ones, in the
+    /// views with [`ColoredShadows`].
+    ///
+    /// Their prepass fragment shader is then run in a separate shadow pass with the
+    /// `TRANSLUCENT_SHADOW` shader def, and writes the light transmittance of the surface to the
+    /// `transmittance` output, with the depth of the fragment in alpha. Transmittances are
+    /// multiplied together, so the shadow of overlapping casters is the product of their tints.
+    fn colored_shadow(&self) -> bool {
+        false
+    }
+
     /// Returns this material's prepass vertex shader. If [`ShaderRef::Default`] is returned, the default prepass vertex shader
     /// will be used.
     ///
@@ -163,6 +357,27 @@ pub trait Material: Asset + AsBindGroup + Clone + Sized {
         ShaderRef::Default
     }
 
+    /// Returns this material's shadow vertex shader. If [`ShaderRef::Default`] is returned, the
+    /// [prepass vertex shader](Material::prepass_vertex_shader) will be used.
+    ///
+    /// This is used when generating the depth maps required for shadow mapping, for directional,
+    /// point and spot lights alike. Materials that animate their vertices, such as foliage swaying
+    /// in the wind, return the same vertex shader here so that their shadows move with them,
+    /// without having to run the animation in the prepasses. The shadow views are specialized
+    /// with [`MeshPipelineKey::SHADOW_PASS`], and [`PreparedMaterial::report`] lists the shadow
+    /// shaders of the material.
+    fn shadow_vertex_shader() -> ShaderRef {
+        ShaderRef::Default
+    }
+
+    /// Returns this material's shadow fragment shader. If [`ShaderRef::Default`] is returned, the
+    /// [prepass fragment shader](Material::prepass_fragment_shader) will be used.
+    ///
+    /// This is used when generating the depth maps required for shadow mapping.
+    fn shadow_fragment_shader() -> ShaderRef {
+        ShaderRef::Default
+    }
+
     /// Returns this material's deferred vertex shader. If [`ShaderRef::Default`] is returned, the default deferred vertex shader
     /// will be used.
     fn deferred_vertex_shader() -> ShaderRef {
@@ -206,6 +421,17 @@ pub trait Material: Asset + AsBindGroup + Clone + Sized {
         ShaderRef::Default
     }
 
+    /// Returns the [`MaterialPipelineKey::view_key`] of the pipelines drawing this material from
+    /// `view`, so that [`Material::specialize`] can specialize them per view, e.g. to disable
+    /// features in orthographic views, or in the low dynamic range views capturing reflections.
+    ///
+    /// Each distinct key specializes new pipelines, so it should only keep the view data the
+    /// material uses, e.g. whether the exposure is above a threshold rather than the exposure.
+    #[allow(unused_variables)]
+    fn view_key(view: &MaterialView) -> u32 {
+        0
+    }
+
     /// Customizes the default [`RenderPipelineDescriptor`] for a specific entity using the entity's
     /// [`MaterialPipelineKey`] and [`MeshVertexBufferLayoutRef`] as input.
     #[allow(unused_variables)]
@@ -231,14 +457,101 @@ pub struct MaterialPlugin<M: Material> {
     pub prepass_enabled: bool,
     /// Controls if shadows are enabled for the Material.
     pub shadows_enabled: bool,
+    /// Replaces the shaders of the Material, keeping everything else about how it's rendered.
+    pub shaders: MaterialShaders,
     pub _marker: PhantomData<M>,
 }
 
+/// Shaders replacing those returned by the shader methods of a [`Material`], such as
+/// [`Material::fragment_shader`], set in [`MaterialPlugin::shaders`].
+///
+/// Each shader left to `None` is still picked by the [`Material`]. A [`ShaderRef::Default`]
+/// replacement uses the default mesh or prepass shader.
+///
+/// This is the way to bring your own shading to an existing material, e.g. an ubershader for the
+/// [`StandardMaterial`](crate::StandardMaterial) set with
+/// [`PbrPlugin::standard_material_shaders`](crate::PbrPlugin::standard_material_shaders).
+/// Everything else about the material is kept: glTF scenes still spawn it, its meshes are still
+/// batched and culled the same way, and lightmaps, shadows and the prepasses still apply to it.
+///
+/// # Writing a replacement shader
+///
+/// The pipelines and bind groups are unchanged, so a replacement shader must use the same
+/// layout as the shader it replaces:
+///
+/// - Group 0 is the mesh view bind group, imported from `bevy_pbr::mesh_view_bindings`, or
+///   `bevy_pbr::prepass_bindings` in the prepass and shadow shaders.
+/// - Group 1 is the mesh bind group, read through `bevy_pbr::mesh_functions` with the instance
+///   index of the vertex, which is what keeps batching working. The lightmap of a mesh is read
+///   with `bevy_pbr::lightmap` when `LIGHTMAP` is defined.
+/// - Group 2 is the bind group of the material, `bevy_pbr::pbr_bindings` for the
+///   [`StandardMaterial`](crate::StandardMaterial).
+/// - Group 3 holds the vertex buffers when `VERTEX_PULLING` is defined.
+/// - The entry points are named `vertex` and `fragment`.
+///
+/// The shaders are specialized with the shader defs of the [`MeshPipelineKey`] bits of each
+/// pipeline, which a replacement must honour rather than pick itself:
+///
+/// - `VERTEX_*` from the mesh vertex attributes, `SKINNED` and `MORPH_TARGETS`.
+/// - `LIGHTMAP` from [`MeshPipelineKey::LIGHTMAPPED`].
+/// - The prepass defs `DEPTH_PREPASS`, `NORMAL_PREPASS`, `MOTION_VECTOR_PREPASS` and
+///   `DEFERRED_PREPASS`, and `SHADOW_PASS` from [`MeshPipelineKey::SHADOW_PASS`] in the shadow
+///   views.
+/// - `MAY_DISCARD` and `ALPHA_HASHED`, and the `BLEND_*` and `PREMULTIPLY_ALPHA` defs of the
+///   blend bits, from the [`AlphaMode`] of the material.
+/// - `TONEMAP_IN_SHADER` with the `TONEMAP_METHOD_*` defs, and `DEBAND_DITHER`.
+/// - `SHADOW_FILTER_METHOD_*`, `ENVIRONMENT_MAP`, `IRRADIANCE_VOLUME`,
+///   `SCREEN_SPACE_AMBIENT_OCCLUSION` and `VISIBILITY_RANGE_DITHER` from the view.
+/// - The shader defs of the [`MeshViewLayoutExtension`](crate::MeshViewLayoutExtension)s and
+///   [`MeshViewBinding`](crate::MeshViewBinding)s in use, which add bindings to group 0.
+///
+/// The material also adds its own defs in [`Material::specialize`], such as
+/// `STANDARD_MATERIAL_NORMAL_MAP` for the [`StandardMaterial`](crate::StandardMaterial).
+///
+/// The meshlet shaders aren't replaced.
+#[derive(Clone, Debug, Default)]
+pub struct MaterialShaders {
+    /// Replaces [`Material::vertex_shader`].
+    pub vertex: Option<ShaderRef>,
+    /// Replaces [`Material::fragment_shader`].
+    pub fragment: Option<ShaderRef>,
+    /// Replaces [`Material::prepass_vertex_shader`].
+    pub prepass_vertex: Option<ShaderRef>,
+    /// Replaces [`Material::prepass_fragment_shader`].
+    pub prepass_fragment: Option<ShaderRef>,
+    /// Replaces [`Material::shadow_vertex_shader`].
+    pub shadow_vertex: Option<ShaderRef>,
+    /// Replaces [`Material::shadow_fragment_shader`].
+    pub shadow_fragment: Option<ShaderRef>,
+    /// Replaces [`Material::deferred_vertex_shader`].
+    pub deferred_vertex: Option<ShaderRef>,
+    /// Replaces [`Material::deferred_fragment_shader`].
+    pub deferred_fragment: Option<ShaderRef>,
+}
+
+/// The [`MaterialPlugin::shaders`] of `M`, in the render world.
+#[derive(Resource)]
+pub(crate) struct MaterialShaderReplacements<M: Material> {
+    pub(crate) shaders: MaterialShaders,
+    marker: PhantomData<M>,
+}
+
+impl<M: Material> MaterialShaderReplacements<M> {
+    /// The replacement shaders of `M`, if any.
+    pub(crate) fn get(world: &World) -> MaterialShaders {
+        world
+            .get_resource::<Self>()
+            .map(|replacements| replacements.shaders.clone())
+            .unwrap_or_default()
+    }
+}
+
 impl<M: Material> Default for MaterialPlugin<M> {
     fn default() -> Self {
         Self {
             prepass_enabled: true,
             shadows_enabled: true,
+            shaders: MaterialShaders::default(),
             _marker: Default::default(),
         }
     }
@@ -249,25 +562,49 @@ where
     M::Data: PartialEq + Eq + Hash + Clone,
 {
     fn build(&self, app: &mut App) {
-        app.init_asset::<M>().add_plugins((
-            ExtractInstancesPlugin::<AssetId<M>>::extract_visible(),
-            RenderAssetPlugin::<PreparedMaterial<M>>::default(),
-        ));
+        app.init_asset::<M>()
+            .add_event::<MissingMaterialEvent>()
+            .add_systems(PostUpdate, detect_missing_materials::<M>)
+            .add_plugins((
+                ExtractInstancesPlugin::<AssetId<M>>::extract_visible(),
+                RenderAssetPlugin::<PreparedMaterial<M>>::default(),
+            ));
 
         if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
             render_app
+                .insert_resource(MaterialShaderReplacements::<M> {
+                    shaders: self.shaders.clone(),
+                    marker: PhantomData,
+                })
+                .add_systems(
+                    ExtractSchedule,
+                    extract_visible_materials::<M>.after(extract_visible::<AssetId<M>>),
+                )
                 .init_resource::<DrawFunctions<Shadow>>()
                 .add_render_command::<Shadow, DrawPrepass<M>>()
+                .init_resource::<DrawFunctions<TranslucentShadow>>()
+                .add_render_command::<TranslucentShadow, DrawPrepass<M>>()
                 .add_render_command::<Transmissive3d, DrawMaterial<M>>()
                 .add_render_command::<Transparent3d, DrawMaterial<M>>()
                 .add_render_command::<Opaque3d, DrawMaterial<M>>()
                 .add_render_command::<AlphaMask3d, DrawMaterial<M>>()
+                .add_render_command::<Decal3d, DrawMaterial<M>>()
                 .init_resource::<SpecializedMeshPipelines<MaterialPipeline<M>>>()
                 .add_systems(
                     Render,
-                    queue_material_meshes::<M>
-                        .in_set(RenderSet::QueueMeshes)
-                        .after(prepare_assets::<PreparedMaterial<M>>),
+                    (
+                        queue_material_meshes::<M>
+                            .in_set(RenderSet::QueueMeshes)
+                            .after(prepare_assets::<PreparedMaterial<M>>),
+                        specialize_prewarm_pipelines::<M>
+                            .in_set(RenderSet::QueueMeshes)
+                            .after(prepare_assets::<PreparedMaterial<M>>),
+                        update_material_pipeline_stats::<M>.in_set(RenderSet::PrepareResources),
+                        update_pipeline_permutations::<MaterialPipeline<M>>
+                            .in_set(RenderSet::PrepareResources),
+                        evict_unused_mesh_pipelines::<MaterialPipeline<M>>
+                            .in_set(RenderSet::Cleanup),
+                    ),
                 );
 
             if self.shadows_enabled {
@@ -313,6 +650,26 @@ where
 pub struct MaterialPipelineKey<M: Material> {
     pub mesh_key: MeshPipelineKey,
     pub bind_group_data: M::Data,
+    /// The pass of the material the pipeline draws: `0` for the main pass, and `n` for the n-th
+    /// of the [`Material::additional_passes`].
+    pub pass_index: u32,
+    /// The stencil state of the pipeline, from the [`Material::stencil`] of the material, if the
+    /// view has a stencil.
+    pub stencil: Option<StencilState>,
+    /// The blend state of the pipeline, from the [`Material::blend_state`] of the material.
+    pub blend_state: Option<BlendState>,
+    /// The level of the [`MaterialLod`] of the mesh the pipeline draws, if any.
+    pub lod: Option<MaterialLodKey>,
+    /// The [`MaterialProperties::shader_defs`] of the material.
+    pub shader_defs: Arc<[ShaderDefVal]>,
+    /// The [`MaterialProperties::cull_mode`] of the material.
+    pub cull_mode: Option<Face>,
+    /// The [`MaterialProperties::double_sided_normals`] of the material.
+    pub double_sided_normals: bool,
+    /// The [`MaterialProperties::rasterizer_depth_bias`] of the material.
+    pub rasterizer_depth_bias: DepthBiasState,
+    /// The [`Material::view_key`] of the view the pipeline draws from.
+    pub view_key: u32,
 }
 
 impl<M: Material> Eq for MaterialPipelineKey<M> where M::Data: PartialEq {}
@@ -322,7 +679,17 @@ where
     M::Data: PartialEq,
 {
     fn eq(&self, other: &Self) -> bool {
-        self.mesh_key == other.mesh_key && self.bind_group_data == other.bind_group_data
+        self.mesh_key == other.mesh_key
+            && self.bind_group_data == other.bind_group_data
+            && self.pass_index == other.pass_index
+            && self.stencil == other.stencil
+            && self.blend_state == other.blend_state
+            && self.lod == other.lod
+            && self.shader_defs == other.shader_defs
+            && self.cull_mode == other.cull_mode
+            && self.double_sided_normals == other.double_sided_normals
+            && self.rasterizer_depth_bias == other.rasterizer_depth_bias
+            && self.view_key == other.view_key
     }
 }
 
@@ -334,6 +701,15 @@ where
         Self {
             mesh_key: self.mesh_key,
             bind_group_data: self.bind_group_data.clone(),
+            pass_index: self.pass_index,
+            stencil: self.stencil.clone(),
+            blend_state: self.blend_state,
+            lod: self.lod.clone(),
+            shader_defs: self.shader_defs.clone(),
+            cull_mode: self.cull_mode,
+            double_sided_normals: self.double_sided_normals,
+            rasterizer_depth_bias: self.rasterizer_depth_bias,
+            view_key: self.view_key,
         }
     }
 }
@@ -345,6 +721,59 @@ where
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         self.mesh_key.hash(state);
         self.bind_group_data.hash(state);
+        self.pass_index.hash(state);
+        self.stencil.hash(state);
+        self.blend_state.hash(state);
+        self.lod.hash(state);
+        self.shader_defs.hash(state);
+        self.cull_mode.hash(state);
+        self.double_sided_normals.hash(state);
+        self.rasterizer_depth_bias.hash(state);
+        self.view_key.hash(state);
+    }
+}
+
+impl<M: Material> PipelineKeyBits for MaterialPipelineKey<M>
+where
+    M::Data: Clone,
+{
+    fn bits(&self) -> u64 {
+        self.mesh_key.bits()
+    }
+
+    fn without_bits(&self) -> Self {
+        Self {
+            mesh_key: MeshPipelineKey::NONE,
+            ..self.clone()
+        }
+    }
+}
+
+/// The data of a view drawing a [`Material`], passed to [`Material::view_key`].
+pub struct MaterialView<'a> {
+    /// The view, with its HDR settings and projection matrix.
+    pub view: &'a ExtractedView,
+    /// The projection of the camera of the view. Views without a camera, such as shadow views,
+    /// have none.
+    pub projection: Option<&'a Projection>,
+    /// The [`ExtractedCamera::exposure`] of the view. Views without a camera have the default
+    /// [`Exposure`].
+    pub exposure: f32,
+}
+
+impl<'a> MaterialView<'a> {
+    /// Creates the [`MaterialView`] of a view, with its camera if it has one.
+    pub fn new(
+        view: &'a ExtractedView,
+        projection: Option<&'a Projection>,
+        camera: Option<&ExtractedCamera>,
+    ) -> Self {
+        Self {
+            view,
+            projection,
+            exposure: camera
+                .map_or_else(|| Exposure::default().exposure(), |camera| camera.exposure),
+        }
     }
 }
 
@@ -387,30 +816,135 @@ where
         }
 
         if let Some(fragment_shader) = &self.fragment_shader {
-            descriptor.fragment.as_mut().unwrap().shader = fragment_shader.clone();
+            let Some(fragment) = descriptor.fragment.as_mut() else {
+                return Err(SpecializedMeshPipelineError::MissingShader {
+                    pipeline_type: None,
+                    label: descriptor.label,
+                    stage: "fragment",
+                    key_bits: key.mesh_key.bits(),
+                });
+            };
+            fragment.shader = fragment_shader.clone();
+        }
+
+        let pass_index_def = ShaderDefVal::UInt("MATERIAL_PASS_INDEX".into(), key.pass_index);
+        descriptor.vertex.shader_defs.push(pass_index_def.clone());
+        descriptor
+            .vertex
+            .shader_defs
+            .extend_from_slice(&key.shader_defs);
+        if let Some(fragment) = descriptor.fragment.as_mut() {
+            fragment.shader_defs.push(pass_index_def);
+            fragment.shader_defs.extend_from_slice(&key.shader_defs);
+            if key.double_sided_normals {
+                fragment
+                    .shader_defs
+                    .push("MATERIAL_DOUBLE_SIDED_NORMALS".into());
+            }
+        }
+
+        descriptor.primitive.cull_mode = key.cull_mode;
+        if let Some(depth_stencil) = descriptor.depth_stencil.as_mut() {
+            depth_stencil.bias = rasterizer_depth_bias(&key.rasterizer_depth_bias, &key.mesh_key);
+        }
+
+        if let Some(lod) = &key.lod {
+            let mut lod_shader_defs = lod.shader_defs.to_vec();
+            if let Some(fade) = lod.fade {
+                // The margin is passed as bits and converted back in the shader.
+                lod_shader_defs.extend([
+                    "MATERIAL_LOD_FADE".into(),
+                    ShaderDefVal::UInt(
+                        "MATERIAL_LOD_FADE_START".into(),
+                        fade.margin_start.0.to_bits(),
+                    ),
+                    ShaderDefVal::UInt("MATERIAL_LOD_FADE_END".into(), fade.margin_end.0.to_bits()),
+                ]);
+                if fade.fade_in {
+                    lod_shader_defs.push("MATERIAL_LOD_FADE_IN".into());
+                }
+            }
+            descriptor
+                .vertex
+                .shader_defs
+                .extend_from_slice(&lod_shader_defs);
+            if let Some(fragment) = descriptor.fragment.as_mut() {
+                fragment.shader_defs.extend(lod_shader_defs);
+            }
+        }
+
+        if let (Some(stencil), Some(depth_stencil)) = (&key.stencil, &mut descriptor.depth_stencil)
+        {
+            depth_stencil.stencil = stencil.clone();
+        }
+
+        if let Some(blend_state) = key.blend_state {
+            // The opaque phases are drawn front to back, without sorting, so blending them with
+            // what's behind doesn't work.
+            let blend = key
+                .mesh_key
+                .intersection(MeshPipelineKey::BLEND_RESERVED_BITS);
+            if blend == MeshPipelineKey::BLEND_OPAQUE
+                || blend == MeshPipelineKey::BLEND_ALPHA_TO_COVERAGE
+            {
+                return Err(SpecializedMeshPipelineError::IncompatibleBlendPhase {
+                    pipeline_type: None,
+                    blend: "custom",
+                    phase: "opaque",
+                    key_bits: key.mesh_key.bits(),
+                });
+            }
+            if let Some(Some(target)) = descriptor
+                .fragment
+                .as_mut()
+                .and_then(|fragment| fragment.targets.first_mut())
+            {
+                target.blend = Some(blend_state);
+            }
         }
 
         descriptor.layout.insert(2, self.material_layout.clone());
 
+        let key_bits = key.mesh_key.bits();
         M::specialize(self, &mut descriptor, layout, key)?;
+        check_bind_group_limit(&descriptor, self.mesh_pipeline.max_bind_groups, key_bits)?;
         Ok(descriptor)
     }
 }
 
+/// Checks that the bind groups of a pipeline specialized with the mesh pipeline key `key_bits`
+/// fit in the `max_bind_groups` of the device.
+pub(crate) fn check_bind_group_limit(
+    descriptor: &RenderPipelineDescriptor,
+    max_bind_groups: u32,
+    key_bits: u64,
+) -> Result<(), SpecializedMeshPipelineError> {
+    if descriptor.layout.len() > max_bind_groups as usize {
+        return Err(SpecializedMeshPipelineError::BindGroupLimitExceeded {
+            pipeline_type: None,
+            label: descriptor.label.clone(),
+            bind_groups: descriptor.layout.len(),
+            max_bind_groups,
+            key_bits,
+        });
+    }
+    Ok(())
+}
+
 impl<M: Material> FromWorld for MaterialPipeline<M> {
     fn from_world(world: &mut World) -> Self {
+        let shaders = MaterialShaderReplacements::<M>::get(world);
         let asset_server = world.resource::<AssetServer>();
-        let render_device = world.resource::<RenderDevice>();
 
         MaterialPipeline {
             mesh_pipeline: world.resource::<MeshPipeline>().clone(),
-            material_layout: M::bind_group_layout(render_device),
-            vertex_shader: match M::vertex_shader() {
+            material_layout: material_bind_group_layout::<M>(world),
+            vertex_shader: match shaders.vertex.unwrap_or_else(M::vertex_shader) {
                 ShaderRef::Default => None,
                 ShaderRef::Handle(handle) => Some(handle),
                 ShaderRef::Path(path) => Some(asset_server.load(path)),
             },
-            fragment_shader: match M::fragment_shader() {
+            fragment_shader: match shaders.fragment.unwrap_or_else(M::fragment_shader) {
                 ShaderRef::Default => None,
                 ShaderRef::Handle(handle) => Some(handle),
                 ShaderRef::Path(path) => Some(asset_server.load(path)),
@@ -420,12 +954,14 @@ impl<M: Material> FromWorld for MaterialPipeline<M> {
     }
 }
 
-type DrawMaterial<M> = (
+pub(crate) type DrawMaterial<M> = (
     SetItemPipeline,
     SetMeshViewBindGroup<0>,
     SetMeshBindGroup<1>,
     SetMaterialBindGroup<M, 2>,
-    DrawMesh,
+    SetMaterialStencilReference<M>,
+    SetMeshVertexPullingBindGroup<3>,
+    TimeMaterialDraw<M, DrawMesh>,
 );
 
 /// Sets the bind group for a given [`Material`] at the configured `I` index.
@@ -460,8 +996,113 @@ impl<P: PhaseItem, M: Material, const I: usize> RenderCommand<P> for SetMaterial
     }
 }
 
+/// Times the draw of `C` with the [`DrawTimer`], if the mesh has [`TimedDraws`] or its
+/// [`Material`] has [`Material::timed_draws`].
+pub struct TimeMaterialDraw<M: Material, C>(PhantomData<(M, C)>);
+impl<P: PhaseItem, M: Material, C: RenderCommand<P>> RenderCommand<P> for TimeMaterialDraw<M, C> {
+    type Param = (
+        Option<SRes<DrawTimer>>,
+        SRes<RenderAssets<PreparedMaterial<M>>>,
+        SRes<RenderMaterialInstances<M>>,
+        C::Param,
+    );
+    type ViewQuery = C::ViewQuery;
+    type ItemQuery = (Has<TimedDraws>, C::ItemQuery);
+
+    #[inline]
+    fn render<'w>(
+        item: &P,
+        view: ROQueryItem<'w, Self::ViewQuery>,
+        item_query: Option<ROQueryItem<'w, Self::ItemQuery>>,
+        (draw_timer, materials, material_instances, param): SystemParamItem<'w, '_, Self::Param>,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        let (timed_entity, item_query) = match item_query {
+            Some((timed_entity, item_query)) => (timed_entity, Some(item_query)),
+            None => (false, None),
+        };
+        let Some(draw_timer) = draw_timer else {
+            return C::render(item, view, item_query, param, pass);
+        };
+
+        let timed = timed_entity
+            || material_instances
+                .into_inner()
+                .get(&item.entity())
+                .and_then(|material_asset_id| materials.into_inner().get(*material_asset_id))
+                .is_some_and(|material| material.properties.timed_draws);
+        if !timed {
+            return C::render(item, view, item_query, param, pass);
+        }
+
+        draw_timer.into_inner().time_draw(
+            pass,
+            get_short_name(std::any::type_name::<M>()),
+            |pass| C::render(item, view, item_query, param, pass),
+        )
+    }
+}
+
+/// Sets the stencil reference value of a [`Material`] from its [`Material::stencil`], or to 0 if
+/// it has none.
+pub struct SetMaterialStencilReference<M: Material>(PhantomData<M>);
+impl<P: PhaseItem, M: Material> RenderCommand<P> for SetMaterialStencilReference<M> {
+    type Param = (
+        SRes<RenderAssets<PreparedMaterial<M>>>,
+        SRes<RenderMaterialInstances<M>>,
+    );
+    type ViewQuery = ();
+    type ItemQuery = ();
+
+    #[inline]
+    fn render<'w>(
+        item: &P,
+        _view: (),
+        _item_query: Option<()>,
+        (materials, material_instances): SystemParamItem<'w, '_, Self::Param>,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        let Some(material_asset_id) = material_instances.into_inner().get(&item.entity()) else {
+            return RenderCommandResult::Failure;
+        };
+        let Some(material) = materials.into_inner().get(*material_asset_id) else {
+            return RenderCommandResult::Failure;
+        };
+        // Always set the reference, so that materials without a stencil don't inherit the one of
+        // the previous draw.
+        let reference = material
+            .properties
+            .stencil
+            .as_ref()
+            .map_or(0, |stencil| stencil.reference);
+        pass.set_stencil_reference(reference);
+        RenderCommandResult::Success
+    }
+}
+
 pub type RenderMaterialInstances<M> = ExtractedInstances<AssetId<M>>;
 
+/// Returns the [`Material::rasterizer_depth_bias`] of a pipeline, which is only supported for
+/// meshes with a triangle topology.
+pub(crate) fn rasterizer_depth_bias(
+    depth_bias: &DepthBiasState,
+    mesh_key: &MeshPipelineKey,
+) -> DepthBiasState {
+    match mesh_key.primitive_topology() {
+        PrimitiveTopology::TriangleList | PrimitiveTopology::TriangleStrip => *depth_bias,
+        _ => DepthBiasState::default(),
+    }
+}
+
+/// Marks the materials of the visible entities extracted this frame as [`VisibleRenderAssets`],
+/// so that they're prepared before the others.
+pub fn extract_visible_materials<M: Material>(
+    render_material_instances: Res<RenderMaterialInstances<M>>,
+    mut visible_materials: ResMut<VisibleRenderAssets<PreparedMaterial<M>>>,
+) {
+    visible_materials.extend(render_material_instances.values().copied());
+}
+
 pub const fn alpha_mode_pipeline_key(alpha_mode: AlphaMode, msaa: &Msaa) -> MeshPipelineKey {
     match alpha_mode {
         // Premultiplied and Add share the same pipeline key
@@ -470,6 +1111,7 @@ pub const fn alpha_mode_pipeline_key(alpha_mode: AlphaMode, msaa: &Msaa) -> Mesh
         AlphaMode::Blend => MeshPipelineKey::BLEND_ALPHA,
         AlphaMode::Multiply => MeshPipelineKey::BLEND_MULTIPLY,
         AlphaMode::Mask(_) => MeshPipelineKey::MAY_DISCARD,
+        AlphaMode::Hashed => MeshPipelineKey::MAY_DISCARD.union(MeshPipelineKey::ALPHA_HASHED),
         AlphaMode::AlphaToCoverage => match *msaa {
             Msaa::Off => MeshPipelineKey::MAY_DISCARD,
             _ => MeshPipelineKey::BLEND_ALPHA_TO_COVERAGE,
@@ -530,8 +1172,18 @@ pub fn queue_material_meshes<M: Material>(
     render_material_instances: Res<RenderMaterialInstances<M>>,
     render_lightmaps: Res<RenderLightmaps>,
     render_visibility_ranges: Res<RenderVisibilityRanges>,
+    (render_material_lods, mesh_view_extra_bindings, decal_draw_functions): (
+        Res<RenderMaterialLods>,
+        Res<MeshViewExtraBindings>,
+        Res<DrawFunctions<Decal3d>>,
+    ),
     mut views: Query<(
-        &ExtractedView,
+        (
+            Entity,
+            &ExtractedView,
+            Option<&ExtractedCamera>,
+            Option<&ExtraMeshPipelineKey>,
+        ),
         &VisibleEntities,
         Option<&Tonemapping>,
         Option<&DebandDither>,
@@ -549,7 +1201,10 @@ pub fn queue_material_meshes<M: Material>(
         &mut BinnedRenderPhase<Opaque3d>,
         &mut BinnedRenderPhase<AlphaMask3d>,
         &mut SortedRenderPhase<Transmissive3d>,
-        &mut SortedRenderPhase<Transparent3d>,
+        (
+            &mut SortedRenderPhase<Transparent3d>,
+            Option<&mut SortedRenderPhase<Decal3d>>,
+        ),
         (
             Has<RenderViewLightProbes<EnvironmentMapLight>>,
             Has<RenderViewLightProbes<IrradianceVolume>>,
@@ -559,7 +1214,7 @@ pub fn queue_material_meshes<M: Material>(
     M::Data: PartialEq + Eq + Hash + Clone,
 {
     for (
-        view,
+        (view_entity, view, camera, extra_key),
         visible_entities,
         tonemapping,
         dither,
@@ -572,7 +1227,7 @@ pub fn queue_material_meshes<M: Material>(
         mut opaque_phase,
         mut alpha_mask_phase,
         mut transmissive_phase,
-        mut transparent_phase,
+        (mut transparent_phase, mut decal_phase),
         (has_environment_maps, has_irradiance_volumes),
     ) in &mut views
     {
@@ -580,9 +1235,14 @@ pub fn queue_material_meshes<M: Material>(
         let draw_alpha_mask_pbr = alpha_mask_draw_functions.read().id::<DrawMaterial<M>>();
         let draw_transmissive_pbr = transmissive_draw_functions.read().id::<DrawMaterial<M>>();
         let draw_transparent_pbr = transparent_draw_functions.read().id::<DrawMaterial<M>>();
+        let draw_decal_pbr = decal_draw_functions.read().id::<DrawMaterial<M>>();
+        let material_view_key = M::view_key(&MaterialView::new(view, projection, camera));
 
         let mut view_key = MeshPipelineKey::from_msaa_samples(msaa.samples())
-            | MeshPipelineKey::from_hdr(view.hdr);
+            | MeshPipelineKey::from_hdr(view.hdr)
+            | MeshPipelineKey::from_hdr_format(view.hdr_format)
+            | mesh_view_extra_bindings.view_key(view_entity)
+            | extra_key.map_or(MeshPipelineKey::NONE, ExtraMeshPipelineKey::view_key);
 
         if normal_prepass {
             view_key |= MeshPipelineKey::NORMAL_PREPASS;
@@ -604,6 +1264,10 @@ pub fn queue_material_meshes<M: Material>(
             view_key |= MeshPipelineKey::TEMPORAL_JITTER;
         }
 
+        if camera_3d.is_some_and(|camera_3d| camera_3d.stencil) {
+            view_key |= MeshPipelineKey::DEPTH_STENCIL;
+        }
+
         if has_environment_maps {
             view_key |= MeshPipelineKey::ENVIRONMENT_MAP;
         }
@@ -665,108 +1329,265 @@ pub fn queue_material_meshes<M: Material>(
                 continue;
             };
 
-            let mut mesh_key = view_key
-                | MeshPipelineKey::from_bits_retain(mesh.key_bits.bits())
-                | material.properties.mesh_pipeline_key_bits;
+            let mut entity_key = view_key | MeshPipelineKey::from_bits_retain(mesh.key_bits.bits());
 
             let lightmap_image = render_lightmaps
                 .render_lightmaps
                 .get(visible_entity)
                 .map(|lightmap| lightmap.image);
             if lightmap_image.is_some() {
-                mesh_key |= MeshPipelineKey::LIGHTMAPPED;
+                entity_key |= MeshPipelineKey::LIGHTMAPPED;
+            }
+
+            // Custom vertex shaders read the vertex buffers.
+            if material_pipeline.vertex_shader.is_none() {
+                entity_key |=
+                    vertex_pulling_key(&material_pipeline.mesh_pipeline.mesh_layouts, mesh);
             }
 
             if render_visibility_ranges.entity_has_crossfading_visibility_ranges(*visible_entity) {
-                mesh_key |= MeshPipelineKey::VISIBILITY_RANGE_DITHER;
+                entity_key |= MeshPipelineKey::VISIBILITY_RANGE_DITHER;
             }
 
-            let pipeline_id = pipelines.specialize(
-                &pipeline_cache,
-                &material_pipeline,
-                MaterialPipelineKey {
-                    mesh_key,
-                    bind_group_data: material.key.clone(),
-                },
-                &mesh.layout,
-            );
-            let pipeline_id = match pipeline_id {
-                Ok(id) => id,
-                Err(err) => {
-                    error!("{}", err);
-                    continue;
-                }
-            };
+            if !material.properties.deband_dither {
+                entity_key.remove(MeshPipelineKey::DEBAND_DITHER);
+            }
 
             mesh_instance
                 .material_bind_group_id
                 .set(material.get_bind_group_id());
+            mesh_instance
+                .material_bindings_index
+                .set(material.get_bindings_index());
+
+            let stencil = material
+                .properties
+                .stencil
+                .filter(|_| view_key.contains(MeshPipelineKey::DEPTH_STENCIL))
+                .map(|stencil| stencil.state());
+
+            // Meshes with a `MaterialLod` are drawn with the level for their
+            // distance, and with two levels while they crossfade between them.
+            let lod_distance = view
+                .transform
+                .translation()
+                .distance(mesh_instance.translation);
+            for lod_draw in render_material_lods.draws(*visible_entity, lod_distance) {
+                let mut entity_key = entity_key.difference(lod_draw.removed_key_bits);
+                if lod_draw.key.as_ref().is_some_and(|lod| lod.fade.is_some()) {
+                    entity_key |= MeshPipelineKey::VISIBILITY_RANGE_DITHER;
+                }
 
-            match mesh_key
-                .intersection(MeshPipelineKey::BLEND_RESERVED_BITS | MeshPipelineKey::MAY_DISCARD)
-            {
-                MeshPipelineKey::BLEND_OPAQUE | MeshPipelineKey::BLEND_ALPHA_TO_COVERAGE => {
-                    if material.properties.reads_view_transmission_texture {
+                for (pass_index, pass) in material.properties.additional_passes.iter().enumerate() {
+                    let mesh_key = entity_key | pass.mesh_pipeline_key_bits;
+                    let pipeline_id = pipelines.specialize(
+                        &pipeline_cache,
+                        &material_pipeline,
+                        MaterialPipelineKey {
+                            mesh_key,
+                            bind_group_data: material.key.clone(),
+                            pass_index: pass_index as u32 + 1,
+                            stencil: stencil.clone(),
+                            blend_state: None,
+                            lod: lod_draw.key.clone(),
+                            shader_defs: material.properties.shader_defs.clone(),
+                            cull_mode: material.properties.cull_mode,
+                            double_sided_normals: material.properties.double_sided_normals,
+                            rasterizer_depth_bias: material.properties.rasterizer_depth_bias,
+                            view_key: material_view_key,
+                        },
+                        &mesh.layout,
+                    );
+                    let pipeline_id = match pipeline_id {
+                        Ok(id) => id,
+                        Err(err) => {
+                            error!("{}", err);
+                            continue;
+                        }
+                    };
+
+                    if mesh_key.contains(MeshPipelineKey::DECAL) {
+                        if let Some(decal_phase) = decal_phase.as_mut() {
+                            let distance = rangefinder
+                                .distance_translation(&mesh_instance.translation)
+                                + pass.depth_bias;
+                            decal_phase.add(Decal3d {
+                                entity: *visible_entity,
+                                draw_function: draw_decal_pbr,
+                                pipeline: pipeline_id,
+                                distance,
+                                batch_range: 0..1,
+                                extra_index: PhaseItemExtraIndex::NONE,
+                            });
+                        }
+                        continue;
+                    }
+
+                    match mesh_key.intersection(
+                        MeshPipelineKey::BLEND_RESERVED_BITS | MeshPipelineKey::MAY_DISCARD,
+                    ) {
+                        MeshPipelineKey::BLEND_OPAQUE
+                        | MeshPipelineKey::BLEND_ALPHA_TO_COVERAGE => {
+                            let bin_key = Opaque3dBinKey {
+                                draw_function: draw_opaque_pbr,
+                                pipeline: pipeline_id,
+                                asset_id: mesh_instance.mesh_asset_id,
+                                material_bind_group_id: material.get_bind_group_id().0,
+                                lightmap_image,
+                            };
+                            opaque_phase.add(
+                                bin_key,
+                                *visible_entity,
+                                mesh_instance.should_batch(),
+                            );
+                        }
+                        MeshPipelineKey::MAY_DISCARD => {
+                            let bin_key = OpaqueNoLightmap3dBinKey {
+                                draw_function: draw_alpha_mask_pbr,
+                                pipeline: pipeline_id,
+                                asset_id: mesh_instance.mesh_asset_id,
+                                material_bind_group_id: material.get_bind_group_id().0,
+                            };
+                            alpha_mask_phase.add(
+                                bin_key,
+                                *visible_entity,
+                                mesh_instance.should_batch(),
+                            );
+                        }
+                        _ => {
+                            let distance = rangefinder
+                                .distance_translation(&mesh_instance.translation)
+                                + pass.depth_bias;
+                            transparent_phase.add(Transparent3d {
+                                entity: *visible_entity,
+                                draw_function: draw_transparent_pbr,
+                                pipeline: pipeline_id,
+                                distance,
+                                batch_range: 0..1,
+                                extra_index: PhaseItemExtraIndex::NONE,
+                            });
+                        }
+                    }
+                }
+
+                let mesh_key = entity_key | material.properties.mesh_pipeline_key_bits;
+                let pipeline_id = pipelines.specialize(
+                    &pipeline_cache,
+                    &material_pipeline,
+                    MaterialPipelineKey {
+                        mesh_key,
+                        bind_group_data: material.key.clone(),
+                        pass_index: 0,
+                        stencil: stencil.clone(),
+                        blend_state: material.properties.blend_state,
+                        lod: lod_draw.key,
+                        shader_defs: material.properties.shader_defs.clone(),
+                        cull_mode: material.properties.cull_mode,
+                        double_sided_normals: material.properties.double_sided_normals,
+                        rasterizer_depth_bias: material.properties.rasterizer_depth_bias,
+                        view_key: material_view_key,
+                    },
+                    &mesh.layout,
+                );
+                let pipeline_id = match pipeline_id {
+                    Ok(id) => id,
+                    Err(err) => {
+                        error!("{}", err);
+                        continue;
+                    }
+                };
+
+                if mesh_key.contains(MeshPipelineKey::DECAL) {
+                    // Decals need the depth prepass, without which views have no decal phase.
+                    if let Some(decal_phase) = decal_phase.as_mut() {
                         let distance = rangefinder.distance_translation(&mesh_instance.translation)
                             + material.properties.depth_bias;
-                        transmissive_phase.add(Transmissive3d {
+                        decal_phase.add(Decal3d {
                             entity: *visible_entity,
-                            draw_function: draw_transmissive_pbr,
+                            draw_function: draw_decal_pbr,
                             pipeline: pipeline_id,
                             distance,
                             batch_range: 0..1,
                             extra_index: PhaseItemExtraIndex::NONE,
                         });
-                    } else if material.properties.render_method == OpaqueRendererMethod::Forward {
-                        let bin_key = Opaque3dBinKey {
-                            draw_function: draw_opaque_pbr,
-                            pipeline: pipeline_id,
-                            asset_id: mesh_instance.mesh_asset_id,
-                            material_bind_group_id: material.get_bind_group_id().0,
-                            lightmap_image,
-                        };
-                        opaque_phase.add(bin_key, *visible_entity, mesh_instance.should_batch());
                     }
+                    continue;
                 }
-                // Alpha mask
-                MeshPipelineKey::MAY_DISCARD => {
-                    if material.properties.reads_view_transmission_texture {
+
+                match mesh_key.intersection(
+                    MeshPipelineKey::BLEND_RESERVED_BITS | MeshPipelineKey::MAY_DISCARD,
+                ) {
+                    MeshPipelineKey::BLEND_OPAQUE | MeshPipelineKey::BLEND_ALPHA_TO_COVERAGE => {
+                        if material.properties.reads_view_transmission_texture {
+                            let distance = rangefinder
+                                .distance_translation(&mesh_instance.translation)
+                                + material.properties.depth_bias;
+                            transmissive_phase.add(Transmissive3d {
+                                entity: *visible_entity,
+                                draw_function: draw_transmissive_pbr,
+                                pipeline: pipeline_id,
+                                distance,
+                                batch_range: 0..1,
+                                extra_index: PhaseItemExtraIndex::NONE,
+                            });
+                        } else if material.properties.render_method == OpaqueRendererMethod::Forward
+                        {
+                            let bin_key = Opaque3dBinKey {
+                                draw_function: draw_opaque_pbr,
+                                pipeline: pipeline_id,
+                                asset_id: mesh_instance.mesh_asset_id,
+                                material_bind_group_id: material.get_bind_group_id().0,
+                                lightmap_image,
+                            };
+                            opaque_phase.add(
+                                bin_key,
+                                *visible_entity,
+                                mesh_instance.should_batch(),
+                            );
+                        }
+                    }
+                    // Alpha mask
+                    MeshPipelineKey::MAY_DISCARD => {
+                        if material.properties.reads_view_transmission_texture {
+                            let distance = rangefinder
+                                .distance_translation(&mesh_instance.translation)
+                                + material.properties.depth_bias;
+                            transmissive_phase.add(Transmissive3d {
+                                entity: *visible_entity,
+                                draw_function: draw_transmissive_pbr,
+                                pipeline: pipeline_id,
+                                distance,
+                                batch_range: 0..1,
+                                extra_index: PhaseItemExtraIndex::NONE,
+                            });
+                        } else if material.properties.render_method == OpaqueRendererMethod::Forward
+                        {
+                            let bin_key = OpaqueNoLightmap3dBinKey {
+                                draw_function: draw_alpha_mask_pbr,
+                                pipeline: pipeline_id,
+                                asset_id: mesh_instance.mesh_asset_id,
+                                material_bind_group_id: material.get_bind_group_id().0,
+                            };
+                            alpha_mask_phase.add(
+                                bin_key,
+                                *visible_entity,
+                                mesh_instance.should_batch(),
+                            );
+                        }
+                    }
+                    _ => {
                         let distance = rangefinder.distance_translation(&mesh_instance.translation)
                             + material.properties.depth_bias;
-                        transmissive_phase.add(Transmissive3d {
+                        transparent_phase.add(Transparent3d {
                             entity: *visible_entity,
-                            draw_function: draw_transmissive_pbr,
+                            draw_function: draw_transparent_pbr,
                             pipeline: pipeline_id,
                             distance,
                             batch_range: 0..1,
                             extra_index: PhaseItemExtraIndex::NONE,
                         });
-                    } else if material.properties.render_method == OpaqueRendererMethod::Forward {
-                        let bin_key = OpaqueNoLightmap3dBinKey {
-                            draw_function: draw_alpha_mask_pbr,
-                            pipeline: pipeline_id,
-                            asset_id: mesh_instance.mesh_asset_id,
-                            material_bind_group_id: material.get_bind_group_id().0,
-                        };
-                        alpha_mask_phase.add(
-                            bin_key,
-                            *visible_entity,
-                            mesh_instance.should_batch(),
-                        );
                     }
                 }
-                _ => {
-                    let distance = rangefinder.distance_translation(&mesh_instance.translation)
-                        + material.properties.depth_bias;
-                    transparent_phase.add(Transparent3d {
-                        entity: *visible_entity,
-                        draw_function: draw_transparent_pbr,
-                        pipeline: pipeline_id,
-                        distance,
-                        batch_range: 0..1,
-                        extra_index: PhaseItemExtraIndex::NONE,
-                    });
-                }
             }
         }
     }
@@ -820,6 +1641,23 @@ pub enum OpaqueRendererMethod {
     Auto,
 }
 
+/// How a [`Material`] uses the vertex colors of meshes, see [`Material::vertex_color_mode`].
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq, Hash, Reflect)]
+pub enum VertexColorMode {
+    /// The vertex colors aren't read.
+    Ignore,
+    /// The vertex color multiplies the base color of the material.
+    #[default]
+    Multiply,
+    /// The vertex color is used instead of the base color of the material. Textures still
+    /// multiply it.
+    Replace,
+    /// The red channel of the vertex color is ambient occlusion, multiplied into the diffuse
+    /// occlusion of the material instead of its base color, see
+    /// [`VertexOcclusionBaker`](crate::VertexOcclusionBaker).
+    Occlusion,
+}
+
 /// Common [`Material`] properties, calculated for a specific material instance.
 pub struct MaterialProperties {
     /// Is this material should be rendered by the deferred renderer when.
@@ -841,6 +1679,142 @@ pub struct MaterialProperties {
     /// This allows taking color output from the [`Opaque3d`] pass as an input, (for screen-space transmission) but requires
     /// rendering to take place in a separate [`Transmissive3d`] pass.
     pub reads_view_transmission_texture: bool,
+    /// The [`Material::additional_passes`] of this material.
+    pub additional_passes: Vec<MaterialPassProperties>,
+    /// The [`Material::stencil`] of this material.
+    pub stencil: Option<MaterialStencil>,
+    /// The [`Material::blend_state`] of this material, if it's supported.
+    pub blend_state: Option<BlendState>,
+    /// The [`Material::deband_dither`] of this material.
+    pub deband_dither: bool,
+    /// The [`Material::shader_defs`] of this material, which are added to the shaders of its
+    /// pipelines during specialization.
+    pub shader_defs: Arc<[ShaderDefVal]>,
+    /// The [`Material::cull_mode`] of this material, applied to the rasterizer state of its
+    /// pipelines before [`Material::specialize`].
+    pub cull_mode: Option<Face>,
+    /// The [`Material::double_sided_normals`] of this material.
+    pub double_sided_normals: bool,
+    /// The [`Material::rasterizer_depth_bias`] of this material, applied to the depth stencil
+    /// state of its pipelines, except the shadow ones, before [`Material::specialize`].
+    pub rasterizer_depth_bias: DepthBiasState,
+    /// The [`Material::vertex_color_mode`] of this material, which is also part of
+    /// [`MaterialProperties::mesh_pipeline_key_bits`].
+    pub vertex_color_mode: VertexColorMode,
+    /// The [`Material::deferred_unsupported_features`] that made this material fall back from the
+    /// deferred renderer to the forward renderer, or empty if it didn't.
+    pub deferred_fallback: Vec<&'static str>,
+    /// The [`Material::deferred_gbuffer_extension`] of this material.
+    pub deferred_gbuffer_extension: bool,
+    /// The [`Material::timed_draws`] of this material.
+    pub timed_draws: bool,
+    /// The [`Material::colored_shadow`] of this material.
+    pub colored_shadow: bool,
+}
+
+/// The stencil test and operations of a [`Material`], returned by [`Material::stencil`].
+///
+/// The same test and operations apply to front and back faces.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct MaterialStencil {
+    /// The value the stencil is compared with, and that [`StencilOperation::Replace`] writes.
+    pub reference: u32,
+    /// How the reference value is compared with the stencil, as `reference <compare> stencil`.
+    /// Fragments that fail the test are discarded.
+    pub compare: CompareFunction,
+    /// The operation applied to the stencil when the stencil test fails.
+    pub fail_op: StencilOperation,
+    /// The operation applied to the stencil when the stencil test passes but the depth test
+    /// fails.
+    pub depth_fail_op: StencilOperation,
+    /// The operation applied to the stencil when both the stencil and depth tests pass.
+    pub pass_op: StencilOperation,
+    /// The bits of the stencil and reference value that are compared.
+    pub read_mask: u32,
+    /// The bits of the stencil that are written.
+    pub write_mask: u32,
+}
+
+impl Default for MaterialStencil {
+    fn default() -> Self {
+        Self {
+            reference: 0,
+            compare: CompareFunction::Always,
+            fail_op: StencilOperation::Keep,
+            depth_fail_op: StencilOperation::Keep,
+            pass_op: StencilOperation::Keep,
+            read_mask: 0xff,
+            write_mask: 0xff,
+        }
+    }
+}
+
+impl MaterialStencil {
+    /// Draws only where the stencil equals `reference`, without writing to it.
+    pub fn test_equal(reference: u32) -> Self {
+        Self {
+            reference,
+            compare: CompareFunction::Equal,
+            write_mask: 0,
+            ..Default::default()
+        }
+    }
+
+    /// Writes `reference` to the stencil wherever the mesh is drawn.
+    pub fn write(reference: u32) -> Self {
+        Self {
+            reference,
+            pass_op: StencilOperation::Replace,
+            ..Default::default()
+        }
+    }
+
+    /// The [`StencilState`] of the render pipelines of the material.
+    pub fn state(&self) -> StencilState {
+        let face = StencilFaceState {
+            compare: self.compare,
+            fail_op: self.fail_op,
+            depth_fail_op: self.depth_fail_op,
+            pass_op: self.pass_op,
+        };
+        StencilState {
+            front: face,
+            back: face,
+            read_mask: self.read_mask,
+            write_mask: self.write_mask,
+        }
+    }
+}
+
+/// An additional pass a [`Material`] is drawn in, returned by [`Material::additional_passes`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MaterialPass {
+    /// The [`AlphaMode`] of the pass, which selects the phase it's drawn in and its blending.
+    pub alpha_mode: AlphaMode,
+    /// Add a bias to the view depth of the mesh in this pass, see [`Material::depth_bias`].
+    ///
+    /// Only used by transparent passes, which are sorted by depth.
+    pub depth_bias: f32,
+}
+
+impl MaterialPass {
+    pub fn new(alpha_mode: AlphaMode) -> Self {
+        Self {
+            alpha_mode,
+            depth_bias: 0.0,
+        }
+    }
+}
+
+/// Common properties of an additional [`MaterialPass`], calculated for a specific material
+/// instance.
+pub struct MaterialPassProperties {
+    /// The [`AlphaMode`] of this pass.
+    pub alpha_mode: AlphaMode,
+    /// The bits in the [`MeshPipelineKey`] for this pass.
+    pub mesh_pipeline_key_bits: MeshPipelineKey,
+    /// The depth bias of this pass, see [`MaterialPass::depth_bias`].
+    pub depth_bias: f32,
 }
 
 /// Data prepared for a [`Material`] instance.
@@ -849,6 +1823,8 @@ pub struct PreparedMaterial<T: Material> {
     pub bind_group: BindGroup,
     pub key: T::Data,
     pub properties: MaterialProperties,
+    /// The slot of this material in its [`MaterialTable`], if it has one.
+    pub table_slot: Option<MaterialTableSlot>,
 }
 
 impl<M: Material> RenderAsset for PreparedMaterial<M> {
@@ -861,23 +1837,62 @@ impl<M: Material> RenderAsset for PreparedMaterial<M> {
         SRes<MaterialPipeline<M>>,
         SRes<DefaultOpaqueRendererMethod>,
         SRes<Msaa>,
+        Option<SResMut<MaterialTable<M>>>,
     );
 
     fn prepare_asset(
         material: Self::SourceAsset,
-        (render_device, images, fallback_image, pipeline, default_opaque_render_method, msaa): &mut SystemParamItem<Self::Param>,
-    ) -> Result<Self, PrepareAssetError<Self::SourceAsset>> {
-        match material.as_bind_group(
-            &pipeline.material_layout,
+        (
             render_device,
             images,
             fallback_image,
-        ) {
-            Ok(prepared) => {
+            pipeline,
+            default_opaque_render_method,
+            msaa,
+            material_table,
+        ): &mut SystemParamItem<Self::Param>,
+    ) -> Result<Self, PrepareAssetError<Self::SourceAsset>> {
+        let prepared = match material_table {
+            Some(material_table) => material_table.as_bind_group(
+                &material,
+                &pipeline.material_layout,
+                render_device,
+                images,
+                fallback_image,
+            ),
+            None => material
+                .as_bind_group(
+                    &pipeline.material_layout,
+                    render_device,
+                    images,
+                    fallback_image,
+                )
+                .map(|prepared| (prepared, None)),
+        };
+        match prepared {
+            Ok((prepared, table_slot)) => {
+                let mut deferred_fallback = Vec::new();
                 let method = match material.opaque_render_method() {
                     OpaqueRendererMethod::Forward => OpaqueRendererMethod::Forward,
                     OpaqueRendererMethod::Deferred => OpaqueRendererMethod::Deferred,
-                    OpaqueRendererMethod::Auto => default_opaque_render_method.0,
+                    OpaqueRendererMethod::Auto => match default_opaque_render_method.0 {
+                        OpaqueRendererMethod::Deferred => {
+                            deferred_fallback = material.deferred_unsupported_features();
+                            if deferred_fallback.is_empty() {
+                                OpaqueRendererMethod::Deferred
+                            } else {
+                                info_once!(
+                                    "Materials with features the deferred renderer can't shade \
+                                    are drawn with the forward renderer, such as a `{}` with {}. \
+                                    See `MaterialProperties::deferred_fallback`.",
+                                    std::any::type_name::<M>(),
+                                    deferred_fallback.join(", ")
+                                );
+                                OpaqueRendererMethod::Forward
+                            }
+                        }
+                        method => method,
+                    },
                 };
                 let mut mesh_pipeline_key_bits = MeshPipelineKey::empty();
                 mesh_pipeline_key_bits.set(
@@ -885,6 +1900,51 @@ impl<M: Material> RenderAsset for PreparedMaterial<M> {
                     material.reads_view_transmission_texture(),
                 );
                 mesh_pipeline_key_bits.insert(alpha_mode_pipeline_key(material.alpha_mode(), msaa));
+                // Bits shared with the additional passes.
+                let vertex_color_mode = material.vertex_color_mode();
+                let mut shared_key_bits =
+                    MeshPipelineKey::from_vertex_color_mode(vertex_color_mode);
+                shared_key_bits.set(MeshPipelineKey::DECAL, material.decal());
+                mesh_pipeline_key_bits.insert(shared_key_bits);
+
+                let blend_state = material.blend_state().filter(|blend_state| {
+                    let dual_source =
+                        [blend_state.color, blend_state.alpha]
+                            .iter()
+                            .any(|component| {
+                                component.src_factor.ref_second_blend_source()
+                                    || component.dst_factor.ref_second_blend_source()
+                            });
+                    if dual_source
+                        && !render_device
+                            .features()
+                            .contains(WgpuFeatures::DUAL_SOURCE_BLENDING)
+                    {
+                        warn!(
+                            "The blend state of a material uses dual-source blending, which isn't \
+                            supported by this device. The blend state of its alpha mode is used \
+                            instead."
+                        );
+                        return false;
+                    }
+                    true
+                });
+
+                let additional_passes = material
+                    .additional_passes()
+                    .into_iter()
+                    .map(|pass| MaterialPassProperties {
+                        alpha_mode: pass.alpha_mode,
+                        mesh_pipeline_key_bits: alpha_mode_pipeline_key(pass.alpha_mode, msaa)
+                            | shared_key_bits,
+                        depth_bias: pass.depth_bias,
+                    })
+                    .collect();
+
+                let mut shader_defs = material.shader_defs();
+                if table_slot.is_some() {
+                    shader_defs.push("MATERIAL_TABLE".into());
+                }
 
                 Ok(PreparedMaterial {
                     bindings: prepared.bindings,
@@ -897,7 +1957,21 @@ impl<M: Material> RenderAsset for PreparedMaterial<M> {
                             .contains(MeshPipelineKey::READS_VIEW_TRANSMISSION_TEXTURE),
                         render_method: method,
                         mesh_pipeline_key_bits,
+                        additional_passes,
+                        stencil: material.stencil(),
+                        blend_state,
+                        deband_dither: material.deband_dither(),
+                        shader_defs: shader_defs.into(),
+                        cull_mode: material.cull_mode(),
+                        double_sided_normals: material.double_sided_normals(),
+                        rasterizer_depth_bias: material.rasterizer_depth_bias(),
+                        vertex_color_mode,
+                        deferred_fallback,
+                        deferred_gbuffer_extension: material.deferred_gbuffer_extension(),
+                        timed_draws: material.timed_draws(),
+                        colored_shadow: material.colored_shadow(),
                     },
+                    table_slot,
                 })
             }
             Err(AsBindGroupError::RetryNextUpdate) => {
@@ -950,8 +2024,85 @@ impl AtomicMaterialBindGroupId {
     }
 }
 
+/// The index of a material in the remapping table of its [`MaterialTable`].
+///
+/// Unlike the slot of the data of the material in the table buffer, the index
+/// stays the same for as long as the material asset exists, even when the
+/// material is prepared again. Its generation is incremented each time the
+/// index is reused by another material, so that shaders can detect stale
+/// indices.
+///
+/// It's written into the mesh uniform of each entity using the material,
+/// packed with [`MaterialBindingsIndex::packed`]. The index of materials
+/// without a table is 0.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct MaterialBindingsIndex {
+    /// The index of the entry of the material in the remapping table.
+    ///
+    /// Only the low [`MaterialBindingsIndex::INDEX_BITS`] bits are packed.
+    pub index: u32,
+    /// The generation of the entry, wrapping at
+    /// [`MaterialBindingsIndex::GENERATION_MASK`].
+    pub generation: u32,
+}
+
+impl MaterialBindingsIndex {
+    /// The number of low bits of the packed value holding the index; the high
+    /// bits hold the generation.
+    pub const INDEX_BITS: u32 = 24;
+
+    /// The mask of the generation, after it's unpacked.
+    pub const GENERATION_MASK: u32 = (1 << (32 - Self::INDEX_BITS)) - 1;
+
+    /// Packs the index and the generation into one `u32`, as read by
+    /// `bevy_pbr::mesh_functions::get_material_bindings_index`.
+    pub fn packed(self) -> u32 {
+        (self.index & ((1 << Self::INDEX_BITS) - 1))
+            | ((self.generation & Self::GENERATION_MASK) << Self::INDEX_BITS)
+    }
+
+    /// Unpacks a value returned by [`MaterialBindingsIndex::packed`].
+    pub fn from_packed(packed: u32) -> Self {
+        Self {
+            index: packed & ((1 << Self::INDEX_BITS) - 1),
+            generation: packed >> Self::INDEX_BITS,
+        }
+    }
+}
+
+/// An atomic version of [`MaterialBindingsIndex`] that can be read from and
+/// written to safely from multiple threads.
+#[derive(Default)]
+pub struct AtomicMaterialBindingsIndex(AtomicU32);
+
+impl AtomicMaterialBindingsIndex {
+    /// Stores a value atomically. Uses [`Ordering::Relaxed`] so there is zero guarantee of ordering
+    /// relative to other operations.
+    ///
+    /// See also:  [`AtomicU32::store`].
+    pub fn set(&self, index: MaterialBindingsIndex) {
+        self.0.store(index.packed(), Ordering::Relaxed);
+    }
+
+    /// Loads a value atomically. Uses [`Ordering::Relaxed`] so there is zero guarantee of ordering
+    /// relative to other operations.
+    ///
+    /// See also:  [`AtomicU32::load`].
+    pub fn get(&self) -> MaterialBindingsIndex {
+        MaterialBindingsIndex::from_packed(self.0.load(Ordering::Relaxed))
+    }
+}
+
 impl<T: Material> PreparedMaterial<T> {
     pub fn get_bind_group_id(&self) -> MaterialBindGroupId {
         MaterialBindGroupId(Some(self.bind_group.id()))
     }
+
+    /// Returns the [`MaterialBindingsIndex`] of this material.
+    pub fn get_bindings_index(&self) -> MaterialBindingsIndex {
+        self.table_slot.as_ref().map_or_else(
+            MaterialBindingsIndex::default,
+            MaterialTableSlot::bindings_index,
+        )
+    }
 }
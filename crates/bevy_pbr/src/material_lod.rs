@@ -0,0 +1,322 @@
+use std::{borrow::Cow, iter, ops::Range, sync::Arc};
+
+use bevy_derive::{Deref, DerefMut};
+use bevy_ecs::prelude::*;
+use bevy_math::FloatOrd;
+use bevy_reflect::{std_traits::ReflectDefault, Reflect};
+use bevy_render::{render_resource::ShaderDefVal, Extract};
+use bevy_utils::EntityHashMap;
+
+use crate::MeshPipelineKey;
+
+/// Simplifies the material of a mesh as the camera moves away from it.
+///
+/// Each [`MaterialLodLevel`] is used from the start of its
+/// [`MaterialLodLevel::start_margin`] until the next level takes over, and
+/// turns off expensive material features with shader defs and by removing bits
+/// from the [`MeshPipelineKey`] of the mesh. Closer than the first level, the
+/// mesh is drawn with its full material. Unlike a
+/// [`VisibilityRange`](bevy_render::view::VisibilityRange), which switches
+/// between entities, the mesh stays a single entity that's specialized
+/// differently.
+///
+/// Distances are measured like those of visibility ranges, from the camera to
+/// the origin of the mesh. Within the start margin of a level, the mesh is drawn
+/// with both the previous level and the new one, which crossfade with the
+/// `VISIBILITY_RANGE_DITHER` pattern so that the switch doesn't pop. If the mesh
+/// also fades through its own visibility range there, that fade wins.
+///
+/// Only the forward main pass uses material LODs. The prepass, deferred and
+/// shadow passes draw the full material.
+///
+/// ```
+/// # use bevy_pbr::{MaterialLod, MaterialLodLevel, MeshPipelineKey};
+/// // Beyond 30 units, skip parallax mapping and image based lighting.
+/// let material_lod = MaterialLod::new([MaterialLodLevel::new(30.0..32.0)
+///     .with_shader_def("STANDARD_MATERIAL_NO_PARALLAX_MAPPING")
+///     .without_key_bits(MeshPipelineKey::ENVIRONMENT_MAP | MeshPipelineKey::IRRADIANCE_VOLUME)]);
+/// ```
+#[derive(Component, Reflect, Clone, Debug, Default, PartialEq)]
+#[reflect(Component, Default, PartialEq)]
+pub struct MaterialLod {
+    /// The levels, sorted by the start of their [`MaterialLodLevel::start_margin`].
+    ///
+    /// [`MaterialLod::new`] sorts them.
+    pub levels: Vec<MaterialLodLevel>,
+}
+
+impl MaterialLod {
+    /// Creates a [`MaterialLod`] with the given levels, in any order.
+    pub fn new(levels: impl IntoIterator<Item = MaterialLodLevel>) -> Self {
+        let mut levels: Vec<_> = levels.into_iter().collect();
+        levels.sort_by_key(|level| FloatOrd(level.start_margin.start));
+        Self { levels }
+    }
+}
+
+/// A level of a [`MaterialLod`].
+#[derive(Reflect, Clone, Debug, PartialEq)]
+#[reflect(PartialEq)]
+pub struct MaterialLodLevel {
+    /// The range of distances, in world units, over which this level fades in
+    /// and the previous one fades out as the camera moves away.
+    ///
+    /// If the start and end of this range are identical, the switch is abrupt.
+    pub start_margin: Range<f32>,
+
+    /// The shader defs defined in the pipelines of this level, for the shaders
+    /// of the material to skip expensive features.
+    ///
+    /// [`StandardMaterial`](crate::StandardMaterial) skips parallax mapping
+    /// when `STANDARD_MATERIAL_NO_PARALLAX_MAPPING` is defined.
+    pub shader_defs: Vec<Cow<'static, str>>,
+
+    /// The bits removed from the [`MeshPipelineKey`] of the mesh, which turn off
+    /// features of the view for this level.
+    ///
+    /// Only the bits of [`MaterialLodLevel::REMOVABLE_KEY_BITS`] are removed.
+    /// Removing the shadow filter method falls back to
+    /// [`ShadowFilteringMethod::Hardware2x2`](crate::ShadowFilteringMethod::Hardware2x2),
+    /// and removing the screen space specular transmission quality falls back
+    /// to the lowest quality.
+    #[reflect(ignore)]
+    pub removed_key_bits: MeshPipelineKey,
+}
+
+impl MaterialLodLevel {
+    /// The bits of the [`MeshPipelineKey`] that a level can remove. They don't
+    /// change the layout of the view bind group.
+    pub const REMOVABLE_KEY_BITS: MeshPipelineKey = MeshPipelineKey::ENVIRONMENT_MAP
+        .union(MeshPipelineKey::IRRADIANCE_VOLUME)
+        .union(MeshPipelineKey::SCREEN_SPACE_AMBIENT_OCCLUSION)
+        .union(MeshPipelineKey::DEBAND_DITHER)
+        .union(MeshPipelineKey::SHADOW_FILTER_METHOD_RESERVED_BITS)
+        .union(MeshPipelineKey::SCREEN_SPACE_SPECULAR_TRANSMISSION_RESERVED_BITS);
+
+    /// Creates a level that fades in over `start_margin`, and doesn't simplify
+    /// anything yet.
+    pub fn new(start_margin: Range<f32>) -> Self {
+        Self {
+            start_margin,
+            shader_defs: Vec::new(),
+            removed_key_bits: MeshPipelineKey::NONE,
+        }
+    }
+
+    /// Creates a level that's used abruptly from `start` on, without a
+    /// crossfade.
+    pub fn abrupt(start: f32) -> Self {
+        Self::new(start..start)
+    }
+
+    /// Adds a shader def to [`Self::shader_defs`].
+    pub fn with_shader_def(mut self, shader_def: impl Into<Cow<'static, str>>) -> Self {
+        self.shader_defs.push(shader_def.into());
+        self
+    }
+
+    /// Adds `key_bits` to [`Self::removed_key_bits`].
+    pub fn without_key_bits(mut self, key_bits: MeshPipelineKey) -> Self {
+        self.removed_key_bits |= key_bits;
+        self
+    }
+}
+
+/// The [`MaterialLod`]s of the meshes, in the render world.
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct RenderMaterialLods(EntityHashMap<Entity, RenderMaterialLod>);
+
+/// A [`MaterialLod`], prepared for specializing pipelines.
+pub struct RenderMaterialLod {
+    levels: Vec<RenderMaterialLodLevel>,
+}
+
+struct RenderMaterialLodLevel {
+    start_margin: Range<f32>,
+    shader_defs: Arc<[ShaderDefVal]>,
+    removed_key_bits: MeshPipelineKey,
+}
+
+/// The part of a [`MaterialPipelineKey`](crate::MaterialPipelineKey) that
+/// selects a level of a [`MaterialLod`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct MaterialLodKey {
+    /// The [`MaterialLodLevel::shader_defs`] of the level.
+    pub shader_defs: Arc<[ShaderDefVal]>,
+    /// How the draw fades, while the mesh crossfades between two levels.
+    pub fade: Option<MaterialLodFade>,
+}
+
+/// How a draw of a mesh fades while it crossfades between two levels of its
+/// [`MaterialLod`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct MaterialLodFade {
+    /// The start of the [`MaterialLodLevel::start_margin`] of the level that
+    /// fades in.
+    pub margin_start: FloatOrd,
+    /// The end of the [`MaterialLodLevel::start_margin`] of the level that
+    /// fades in.
+    pub margin_end: FloatOrd,
+    /// Whether this draw is of the level that fades in, rather than of the one
+    /// that fades out.
+    pub fade_in: bool,
+}
+
+/// A draw of a mesh with a level of its [`MaterialLod`].
+#[derive(Clone, Debug, Default)]
+pub struct MaterialLodDraw {
+    /// The key of the level, or `None` for the full material without a fade.
+    pub key: Option<MaterialLodKey>,
+    /// The bits to remove from the [`MeshPipelineKey`] of the mesh, already
+    /// restricted to [`MaterialLodLevel::REMOVABLE_KEY_BITS`].
+    pub removed_key_bits: MeshPipelineKey,
+}
+
+impl RenderMaterialLods {
+    /// Returns the draws of `entity` at `distance` from the camera: one, or two
+    /// while it crossfades between two levels of its [`MaterialLod`].
+    pub fn draws(&self, entity: Entity, distance: f32) -> impl Iterator<Item = MaterialLodDraw> {
+        let Some(material_lod) = self.get(&entity) else {
+            return iter::once(MaterialLodDraw::default()).chain(None);
+        };
+
+        let current = material_lod
+            .levels
+            .partition_point(|level| level.start_margin.start <= distance)
+            .checked_sub(1);
+        let fading_level = current
+            .map(|index| &material_lod.levels[index])
+            .filter(|level| distance < level.start_margin.end);
+
+        match fading_level {
+            None => iter::once(material_lod.draw(current, None)).chain(None),
+            Some(level) => {
+                let fade = |fade_in| MaterialLodFade {
+                    margin_start: FloatOrd(level.start_margin.start),
+                    margin_end: FloatOrd(level.start_margin.end),
+                    fade_in,
+                };
+                let previous = current.and_then(|index| index.checked_sub(1));
+                iter::once(material_lod.draw(current, Some(fade(true))))
+                    .chain(Some(material_lod.draw(previous, Some(fade(false)))))
+            }
+        }
+    }
+}
+
+impl RenderMaterialLod {
+    fn draw(&self, level: Option<usize>, fade: Option<MaterialLodFade>) -> MaterialLodDraw {
+        let level = level.map(|index| &self.levels[index]);
+        if level.is_none() && fade.is_none() {
+            return MaterialLodDraw::default();
+        }
+
+        MaterialLodDraw {
+            key: Some(MaterialLodKey {
+                shader_defs: level.map_or_else(|| Arc::from([]), |level| level.shader_defs.clone()),
+                fade,
+            }),
+            removed_key_bits: level.map_or(MeshPipelineKey::NONE, |level| level.removed_key_bits),
+        }
+    }
+}
+
+impl From<&MaterialLod> for RenderMaterialLod {
+    fn from(material_lod: &MaterialLod) -> Self {
+        Self {
+            levels: material_lod
+                .levels
+                .iter()
+                .map(|level| RenderMaterialLodLevel {
+                    start_margin: level.start_margin.clone(),
+                    shader_defs: level
+                        .shader_defs
+                        .iter()
+                        .map(|shader_def| ShaderDefVal::from(&**shader_def))
+                        .collect(),
+                    removed_key_bits: level
+                        .removed_key_bits
+                        .intersection(MaterialLodLevel::REMOVABLE_KEY_BITS),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Extracts the [`MaterialLod`]s of the meshes into [`RenderMaterialLods`].
+pub fn extract_material_lods(
+    mut render_material_lods: ResMut<RenderMaterialLods>,
+    material_lods_query: Extract<Query<(), With<MaterialLod>>>,
+    changed_material_lods_query: Extract<Query<(Entity, &MaterialLod), Changed<MaterialLod>>>,
+) {
+    render_material_lods.retain(|entity, _| material_lods_query.contains(*entity));
+    for (entity, material_lod) in &changed_material_lods_query {
+        render_material_lods.insert(entity, material_lod.into());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs::entity::Entity;
+
+    use super::{MaterialLod, MaterialLodLevel, RenderMaterialLods};
+    use crate::MeshPipelineKey;
+
+    #[test]
+    fn material_lod_draws() {
+        let entity = Entity::from_raw(0);
+        let mut render_material_lods = RenderMaterialLods::default();
+        render_material_lods.insert(
+            entity,
+            (&MaterialLod::new([
+                MaterialLodLevel::abrupt(50.0).with_shader_def("FAR"),
+                MaterialLodLevel::new(10.0..20.0)
+                    .with_shader_def("MEDIUM")
+                    .without_key_bits(MeshPipelineKey::ENVIRONMENT_MAP | MeshPipelineKey::HDR),
+            ]))
+                .into(),
+        );
+
+        let draws = |distance| {
+            Vec::from_iter(render_material_lods.draws(entity, distance).map(|draw| {
+                let key = draw
+                    .key
+                    .unwrap_or_else(|| panic!("missing key at {distance}"));
+                (
+                    key.shader_defs.len(),
+                    key.fade.map(|fade| fade.fade_in),
+                    draw.removed_key_bits,
+                )
+            }))
+        };
+
+        // The full material, without a fade.
+        let draw = render_material_lods.draws(entity, 5.0).collect::<Vec<_>>();
+        assert_eq!(draw.len(), 1);
+        assert!(draw[0].key.is_none());
+
+        // Crossfading from the full material to the first level, whose removed
+        // key bits are restricted to the removable ones.
+        assert_eq!(
+            draws(15.0),
+            vec![
+                (1, Some(true), MeshPipelineKey::ENVIRONMENT_MAP),
+                (0, Some(false), MeshPipelineKey::NONE),
+            ]
+        );
+
+        // The levels alone, with an abrupt switch between them.
+        assert_eq!(
+            draws(20.0),
+            vec![(1, None, MeshPipelineKey::ENVIRONMENT_MAP)]
+        );
+        assert_eq!(draws(50.0), vec![(1, None, MeshPipelineKey::NONE)]);
+
+        // Entities without a material LOD draw the full material.
+        let draw = render_material_lods
+            .draws(Entity::from_raw(1), 15.0)
+            .collect::<Vec<_>>();
+        assert_eq!(draw.len(), 1);
+        assert!(draw[0].key.is_none());
+    }
+}
@@ -0,0 +1,285 @@
+use bevy_color::{Color, LinearRgba};
+use bevy_ecs::prelude::*;
+use bevy_math::{UVec2, Vec3};
+use bevy_reflect::{std_traits::ReflectDefault, Reflect};
+
+use crate::MeshFlags;
+
+/// Overrides a few properties of the [`StandardMaterial`](crate::StandardMaterial)
+/// of a mesh, for this entity only.
+///
+/// The overridden values are written into the per-instance mesh uniform of the
+/// entity, so tinting many instances that share a material doesn't require a
+/// material asset per instance. The instances keep sharing the material bind
+/// group and pipeline, and are still batched together.
+///
+/// Each value replaces the corresponding factor of the material. Textures of
+/// the material are still applied on top of it.
+#[derive(Component, Reflect, Clone, Copy, Debug, Default, PartialEq)]
+#[reflect(Component, Default, PartialEq)]
+pub struct MaterialOverrides {
+    /// Replaces [`StandardMaterial::base_color`](crate::StandardMaterial::base_color).
+    ///
+    /// It's stored in linear space as half floats in the mesh uniform, so HDR
+    /// values are kept.
+    pub base_color: Option<Color>,
+    /// Replaces [`StandardMaterial::emissive`](crate::StandardMaterial::emissive).
+    ///
+    /// The alpha channel is ignored.
+    pub emissive: Option<LinearRgba>,
+    /// Replaces [`StandardMaterial::perceptual_roughness`](crate::StandardMaterial::perceptual_roughness).
+    pub perceptual_roughness: Option<f32>,
+}
+
+impl MaterialOverrides {
+    /// Sets [`Self::base_color`].
+    pub fn with_base_color(mut self, base_color: impl Into<Color>) -> Self {
+        self.base_color = Some(base_color.into());
+        self
+    }
+
+    /// Sets [`Self::emissive`].
+    pub fn with_emissive(mut self, emissive: LinearRgba) -> Self {
+        self.emissive = Some(emissive);
+        self
+    }
+
+    /// Sets [`Self::perceptual_roughness`].
+    pub fn with_perceptual_roughness(mut self, perceptual_roughness: f32) -> Self {
+        self.perceptual_roughness = Some(perceptual_roughness);
+        self
+    }
+}
+
+/// [`MaterialOverrides`] packed into the mesh uniform of an entity.
+///
+/// The [`MeshFlags`] tell which of the values are set.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PackedMaterialOverrides {
+    pub flags: MeshFlags,
+    /// The linear base color, packed as four half floats like `pack2x16float`
+    /// does.
+    pub base_color: UVec2,
+    /// The linear emissive color, packed as `rgb9e5`.
+    pub emissive: u32,
+    pub perceptual_roughness: f32,
+}
+
+impl From<&MaterialOverrides> for PackedMaterialOverrides {
+    fn from(overrides: &MaterialOverrides) -> Self {
+        let mut packed = PackedMaterialOverrides::default();
+        if let Some(base_color) = overrides.base_color {
+            packed.flags |= MeshFlags::BASE_COLOR_OVERRIDE;
+            let base_color = LinearRgba::from(base_color);
+            let [red, green, blue, alpha] = [
+                base_color.red,
+                base_color.green,
+                base_color.blue,
+                base_color.alpha.clamp(0.0, 1.0),
+            ]
+            .map(|channel| f32_to_f16(channel.clamp(0.0, MAX_F16)) as u32);
+            packed.base_color = UVec2::new(red | (green << 16), blue | (alpha << 16));
+        }
+        if let Some(emissive) = overrides.emissive {
+            packed.flags |= MeshFlags::EMISSIVE_OVERRIDE;
+            packed.emissive =
+                vec3_to_rgb9e5(Vec3::new(emissive.red, emissive.green, emissive.blue));
+        }
+        if let Some(perceptual_roughness) = overrides.perceptual_roughness {
+            packed.flags |= MeshFlags::PERCEPTUAL_ROUGHNESS_OVERRIDE;
+            packed.perceptual_roughness = perceptual_roughness.clamp(0.0, 1.0);
+        }
+        packed
+    }
+}
+
+/// The largest finite half float.
+const MAX_F16: f32 = 65504.0;
+
+// Converts a f32 to the bits of the nearest half float, rounding ties to even,
+// which is what `pack2x16float` does on the GPU.
+fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xFF) as i32;
+    let mantissa = bits & 0x007F_FFFF;
+
+    // Infinity and NaN.
+    if exponent == 0xFF {
+        return sign | 0x7C00 | if mantissa != 0 { 0x0200 } else { 0 };
+    }
+
+    let half_exponent = exponent - 127 + 15;
+    if half_exponent >= 0x1F {
+        return sign | 0x7C00;
+    }
+
+    // Subnormal half floats, with the implicit leading bit made explicit.
+    let (half, mantissa, shift) = if half_exponent <= 0 {
+        if half_exponent < -10 {
+            return sign;
+        }
+        let mantissa = mantissa | 0x0080_0000;
+        let shift = (14 - half_exponent) as u32;
+        (mantissa >> shift, mantissa, shift)
+    } else {
+        (
+            ((half_exponent as u32) << 10) | (mantissa >> 13),
+            mantissa,
+            13,
+        )
+    };
+
+    // Round to nearest, ties to even. A carry into the exponent is correct,
+    // and rounds the largest values up to infinity.
+    let round_bit = 1 << (shift - 1);
+    let round_up = mantissa & round_bit != 0 && mantissa & (3 * round_bit - 1) != 0;
+    sign | (half + round_up as u32) as u16
+}
+
+// NOTE: This must match `vec3_to_rgb9e5_` in bevy_pbr/src/render/rgb9e5.wgsl!
+// https://www.khronos.org/registry/OpenGL/extensions/EXT/EXT_texture_shared_exponent.txt
+fn vec3_to_rgb9e5(rgb: Vec3) -> u32 {
+    const MANTISSA_BITS: i32 = 9;
+    const MANTISSA_VALUES: i32 = 1 << MANTISSA_BITS;
+    const EXP_BIAS: i32 = 15;
+    const MAX_RGB9E5: f32 = 65408.0;
+
+    let rgb = rgb.clamp(Vec3::ZERO, Vec3::splat(MAX_RGB9E5));
+    let max_rgb = rgb.max_element();
+    // `max_rgb.log2().floor()`, which is also well defined for zero.
+    let floor_log2 = ((max_rgb.to_bits() & 0x7F80_0000) >> 23) as i32 - 127;
+    let mut exp_shared = (-EXP_BIAS - 1).max(floor_log2) + 1 + EXP_BIAS;
+    let mut denom = 2.0f32.powi(exp_shared - EXP_BIAS - MANTISSA_BITS);
+
+    if (max_rgb / denom + 0.5).floor() as i32 == MANTISSA_VALUES {
+        denom *= 2.0;
+        exp_shared += 1;
+    }
+
+    let n = (rgb / denom + 0.5).floor().as_uvec3();
+    ((exp_shared as u32) << 27) | (n.z << 18) | (n.y << 9) | n.x
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_color::{Color, LinearRgba};
+    use bevy_math::{UVec2, Vec3, Vec4};
+
+    use super::{f32_to_f16, vec3_to_rgb9e5, MaterialOverrides, PackedMaterialOverrides};
+    use crate::MeshFlags;
+
+    fn rgb9e5_to_vec3(v: u32) -> Vec3 {
+        let scale = 2.0f32.powi((v >> 27) as i32 - 15 - 9);
+        Vec3::new(
+            (v & 511) as f32,
+            ((v >> 9) & 511) as f32,
+            ((v >> 18) & 511) as f32,
+        ) * scale
+    }
+
+    #[test]
+    fn rgb9e5_round_trip() {
+        for rgb in [
+            Vec3::ZERO,
+            Vec3::new(1.0, 0.5, 0.25),
+            Vec3::new(100.0, 3.0, 0.0),
+            Vec3::new(0.001, 0.002, 0.003),
+        ] {
+            let decoded = rgb9e5_to_vec3(vec3_to_rgb9e5(rgb));
+            let tolerance = rgb.max_element() / 256.0;
+            assert!(
+                (decoded - rgb).abs().max_element() <= tolerance,
+                "{rgb} was decoded as {decoded}"
+            );
+        }
+    }
+
+    fn f16_to_f32(half: u16) -> f32 {
+        let sign = if half & 0x8000 != 0 { -1.0 } else { 1.0 };
+        let exponent = ((half >> 10) & 0x1F) as i32;
+        let mantissa = (half & 0x03FF) as f32;
+        sign * match exponent {
+            0 => mantissa * 2.0f32.powi(-24),
+            0x1F => f32::INFINITY,
+            _ => (1.0 + mantissa / 1024.0) * 2.0f32.powi(exponent - 15),
+        }
+    }
+
+    // Like `unpack2x16float` on both halves.
+    fn unpack_base_color(packed: UVec2) -> Vec4 {
+        Vec4::new(
+            f16_to_f32(packed.x as u16),
+            f16_to_f32((packed.x >> 16) as u16),
+            f16_to_f32(packed.y as u16),
+            f16_to_f32((packed.y >> 16) as u16),
+        )
+    }
+
+    #[test]
+    fn f16_conversion() {
+        assert_eq!(f32_to_f16(0.0), 0x0000);
+        assert_eq!(f32_to_f16(-0.0), 0x8000);
+        assert_eq!(f32_to_f16(1.0), 0x3C00);
+        assert_eq!(f32_to_f16(-2.0), 0xC000);
+        assert_eq!(f32_to_f16(65504.0), 0x7BFF);
+        assert_eq!(f32_to_f16(65536.0), 0x7C00);
+        assert_eq!(f32_to_f16(f32::INFINITY), 0x7C00);
+        assert_eq!(f32_to_f16(f32::NAN) & 0x7E00, 0x7E00);
+        // The smallest subnormal half float, and values that round to it or to zero.
+        assert_eq!(f32_to_f16(2.0f32.powi(-24)), 0x0001);
+        assert_eq!(f32_to_f16(2.0f32.powi(-25) * 1.5), 0x0001);
+        assert_eq!(f32_to_f16(2.0f32.powi(-26)), 0x0000);
+        // Ties round to even.
+        assert_eq!(f32_to_f16(1.0 + 2.0f32.powi(-11)), 0x3C00);
+        assert_eq!(f32_to_f16(1.0 + 3.0 * 2.0f32.powi(-11)), 0x3C02);
+
+        for value in [0.5, 0.214, 1.0 / 3.0, 4.0, 1234.5, 0.0001] {
+            let decoded = f16_to_f32(f32_to_f16(value));
+            assert!(
+                (decoded - value).abs() <= value * 2.0f32.powi(-11),
+                "{value} was decoded as {decoded}"
+            );
+        }
+    }
+
+    #[test]
+    fn packing_sets_flags() {
+        assert_eq!(
+            PackedMaterialOverrides::from(&MaterialOverrides::default()).flags,
+            MeshFlags::empty()
+        );
+
+        let packed = PackedMaterialOverrides::from(
+            &MaterialOverrides::default()
+                .with_base_color(Color::srgba_u8(255, 128, 0, 255))
+                .with_emissive(LinearRgba::rgb(2.0, 0.0, 0.0))
+                .with_perceptual_roughness(1.5),
+        );
+        assert_eq!(
+            packed.flags,
+            MeshFlags::BASE_COLOR_OVERRIDE
+                | MeshFlags::EMISSIVE_OVERRIDE
+                | MeshFlags::PERCEPTUAL_ROUGHNESS_OVERRIDE
+        );
+        let base_color = LinearRgba::from(Color::srgba_u8(255, 128, 0, 255));
+        let decoded = unpack_base_color(packed.base_color);
+        assert_eq!(decoded.x, 1.0);
+        assert!((decoded.y - base_color.green).abs() <= base_color.green * 2.0f32.powi(-11));
+        assert_eq!(decoded.z, 0.0);
+        assert_eq!(decoded.w, 1.0);
+        assert_eq!(rgb9e5_to_vec3(packed.emissive), Vec3::new(2.0, 0.0, 0.0));
+        assert_eq!(packed.perceptual_roughness, 1.0);
+    }
+
+    #[test]
+    fn packing_keeps_hdr_base_colors() {
+        let packed = PackedMaterialOverrides::from(
+            &MaterialOverrides::default().with_base_color(LinearRgba::new(4.0, 0.5, 0.25, 2.0)),
+        );
+        assert_eq!(
+            unpack_base_color(packed.base_color),
+            Vec4::new(4.0, 0.5, 0.25, 1.0)
+        );
+    }
+}
@@ -0,0 +1,177 @@
+use std::{
+    any::{type_name, TypeId},
+    hash::Hash,
+};
+
+use bevy_app::{App, Plugin};
+use bevy_ecs::prelude::*;
+use bevy_render::{
+    render_resource::{
+        CachedPipelineState, PipelineCache, PipelineCacheError, SpecializedMeshPipelines,
+    },
+    RenderApp,
+};
+use bevy_utils::{HashMap, HashSet};
+
+use crate::{Material, MaterialPipeline, PrepassPipeline};
+
+/// Records a [`MaterialPipelineStats`] resource in the render world.
+///
+/// The statistics aren't recorded without this plugin, so that counting the pipelines doesn't
+/// cost anything unless they're looked at.
+#[derive(Default)]
+pub struct MaterialPipelineStatsPlugin;
+
+impl Plugin for MaterialPipelineStatsPlugin {
+    fn build(&self, app: &mut App) {
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app.init_resource::<MaterialPipelineStats>();
+        }
+    }
+}
+
+/// Statistics about the pipelines specialized for each [`Material`] type, to find the materials
+/// whose [`MaterialPipelineKey`](crate::MaterialPipelineKey)s vary more than expected, e.g.
+/// because their [`AsBindGroup::Data`](bevy_render::render_resource::AsBindGroup::Data) or
+/// shader defs differ between instances.
+///
+/// This is a resource of the render world, updated every frame by the
+/// [`MaterialPlugin`](crate::MaterialPlugin) of each material when the
+/// [`MaterialPipelineStatsPlugin`] is added.
+#[derive(Resource, Default, Debug)]
+pub struct MaterialPipelineStats {
+    materials: HashMap<TypeId, MaterialTypePipelineStats>,
+}
+
+impl MaterialPipelineStats {
+    /// Returns the statistics of the material `M`, if it has any pipelines.
+    pub fn get<M: Material>(&self) -> Option<&MaterialTypePipelineStats> {
+        self.materials.get(&TypeId::of::<M>())
+    }
+
+    /// Iterates over the statistics of each material type, by its [`TypeId`].
+    pub fn iter(&self) -> impl Iterator<Item = (TypeId, &MaterialTypePipelineStats)> {
+        self.materials
+            .iter()
+            .map(|(type_id, stats)| (*type_id, stats))
+    }
+}
+
+/// The statistics of the pipelines of a [`Material`] type, see [`MaterialPipelineStats`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MaterialTypePipelineStats {
+    /// The name of the material type.
+    pub type_name: &'static str,
+    /// The pipelines of the main passes, specialized by
+    /// [`queue_material_meshes`](crate::queue_material_meshes).
+    pub main: PipelineCounts,
+    /// The pipelines of the prepasses and of the shadow passes.
+    pub prepass: PipelineCounts,
+}
+
+/// The number of keys and pipelines of a [`SpecializedMeshPipelines`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PipelineCounts {
+    /// The number of distinct keys the pipelines were specialized with.
+    ///
+    /// A key is specialized into one pipeline per vertex buffer layout, so this may be lower than
+    /// the number of pipelines.
+    pub keys: usize,
+    /// The number of pipelines that are compiled and ready to draw with.
+    pub compiled: usize,
+    /// The number of pipelines that are queued or being compiled, including those waiting for
+    /// their shaders to load.
+    pub pending: usize,
+    /// The number of pipelines that failed to compile.
+    pub failed: usize,
+}
+
+impl PipelineCounts {
+    /// Counts the distinct keys of `pipelines`, and the pipelines by their state.
+    pub fn new<'a, K: Eq + Hash + 'a>(
+        pipelines: impl IntoIterator<Item = (&'a K, &'a CachedPipelineState)>,
+    ) -> Self {
+        let mut counts = Self::default();
+        let mut keys = HashSet::new();
+        for (key, state) in pipelines {
+            keys.insert(key);
+            match state {
+                CachedPipelineState::Ok(_) => counts.compiled += 1,
+                CachedPipelineState::Queued
+                | CachedPipelineState::Creating(_)
+                | CachedPipelineState::Err(
+                    PipelineCacheError::ShaderNotLoaded(_)
+                    | PipelineCacheError::ShaderImportNotYetAvailable,
+                ) => counts.pending += 1,
+                CachedPipelineState::Err(_) => counts.failed += 1,
+                // Evicted pipelines are removed from their `SpecializedMeshPipelines`.
+                CachedPipelineState::Evicted => {}
+            }
+        }
+        counts.keys = keys.len();
+        counts
+    }
+}
+
+/// Updates the [`MaterialTypePipelineStats`] of the material `M`, if the
+/// [`MaterialPipelineStatsPlugin`] is added.
+pub fn update_material_pipeline_stats<M: Material>(
+    stats: Option<ResMut<MaterialPipelineStats>>,
+    pipeline_cache: Res<PipelineCache>,
+    main_pipelines: Res<SpecializedMeshPipelines<MaterialPipeline<M>>>,
+    prepass_pipelines: Option<Res<SpecializedMeshPipelines<PrepassPipeline<M>>>>,
+) where
+    M::Data: PartialEq + Eq + Hash + Clone,
+{
+    let Some(mut stats) = stats else {
+        return;
+    };
+
+    let main = PipelineCounts::new(
+        main_pipelines
+            .iter()
+            .map(|(key, id)| (key, pipeline_cache.get_render_pipeline_state(id))),
+    );
+    let prepass = prepass_pipelines.map_or_else(PipelineCounts::default, |pipelines| {
+        PipelineCounts::new(
+            pipelines
+                .iter()
+                .map(|(key, id)| (key, pipeline_cache.get_render_pipeline_state(id))),
+        )
+    });
+    stats.materials.insert(
+        TypeId::of::<M>(),
+        MaterialTypePipelineStats {
+            type_name: type_name::<M>(),
+            main,
+            prepass,
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_asset::AssetId;
+    use bevy_render::render_resource::{CachedPipelineState, PipelineCacheError};
+
+    use super::PipelineCounts;
+
+    #[test]
+    fn pipelines_are_counted_by_state() {
+        let states = [
+            CachedPipelineState::Queued,
+            CachedPipelineState::Err(PipelineCacheError::ShaderNotLoaded(AssetId::default())),
+            CachedPipelineState::Err(PipelineCacheError::CreateShaderModule(String::new())),
+        ];
+        let counts = PipelineCounts::new([(&0, &states[0]), (&1, &states[1]), (&1, &states[2])]);
+        assert_eq!(
+            counts,
+            PipelineCounts {
+                keys: 2,
+                compiled: 0,
+                pending: 2,
+                failed: 1,
+            }
+        );
+    }
+}
@@ -0,0 +1,470 @@
+use std::fmt;
+
+use bevy_render::{
+    alpha::AlphaMode,
+    render_resource::{CompareFunction, Face, ShaderRef, StencilOperation},
+    view::Msaa,
+};
+
+use crate::{
+    alpha_mode_pipeline_key, DrawMaterial, DrawPrepass, Material, MaterialProperties,
+    MeshPipelineKey, OpaqueRendererMethod, PreparedMaterial,
+};
+
+/// The render phase a pass of a material is queued in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MaterialPhase {
+    /// The [`Opaque3d`](bevy_core_pipeline::core_3d::Opaque3d) phase.
+    Opaque3d,
+    /// The [`AlphaMask3d`](bevy_core_pipeline::core_3d::AlphaMask3d) phase.
+    AlphaMask3d,
+    /// The [`Transmissive3d`](bevy_core_pipeline::core_3d::Transmissive3d) phase.
+    Transmissive3d,
+    /// The [`Transparent3d`](bevy_core_pipeline::core_3d::Transparent3d) phase.
+    Transparent3d,
+    /// The [`Opaque3dDeferred`](bevy_core_pipeline::deferred::Opaque3dDeferred) phase.
+    Opaque3dDeferred,
+    /// The [`AlphaMask3dDeferred`](bevy_core_pipeline::deferred::AlphaMask3dDeferred) phase.
+    AlphaMask3dDeferred,
+    /// The [`Decal3d`](bevy_core_pipeline::core_3d::Decal3d) phase, see [`Material::decal`].
+    Decal3d,
+}
+
+impl MaterialPhase {
+    /// Returns the phase [`queue_material_meshes`](crate::queue_material_meshes) and the
+    /// prepass queue a pass with these properties in.
+    pub fn from_properties(
+        mesh_pipeline_key_bits: MeshPipelineKey,
+        reads_view_transmission_texture: bool,
+        render_method: OpaqueRendererMethod,
+    ) -> Self {
+        if mesh_pipeline_key_bits.contains(MeshPipelineKey::DECAL) {
+            return Self::Decal3d;
+        }
+
+        let deferred = render_method == OpaqueRendererMethod::Deferred;
+        match mesh_pipeline_key_bits
+            .intersection(MeshPipelineKey::BLEND_RESERVED_BITS | MeshPipelineKey::MAY_DISCARD)
+        {
+            MeshPipelineKey::BLEND_OPAQUE | MeshPipelineKey::BLEND_ALPHA_TO_COVERAGE => {
+                if reads_view_transmission_texture {
+                    Self::Transmissive3d
+                } else if deferred {
+                    Self::Opaque3dDeferred
+                } else {
+                    Self::Opaque3d
+                }
+            }
+            MeshPipelineKey::MAY_DISCARD => {
+                if reads_view_transmission_texture {
+                    Self::Transmissive3d
+                } else if deferred {
+                    Self::AlphaMask3dDeferred
+                } else {
+                    Self::AlphaMask3d
+                }
+            }
+            _ => Self::Transparent3d,
+        }
+    }
+
+    /// Whether the phase is sorted by distance, so that the depth bias of the pass applies.
+    pub fn is_sorted(self) -> bool {
+        matches!(
+            self,
+            Self::Decal3d | Self::Transmissive3d | Self::Transparent3d
+        )
+    }
+}
+
+/// An inconsistency in [`MaterialProperties`], found by [`MaterialProperties::validate`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MaterialPropertiesIssue {
+    /// [`MaterialProperties::reads_view_transmission_texture`] doesn't match the
+    /// [`MeshPipelineKey::READS_VIEW_TRANSMISSION_TEXTURE`] bit of
+    /// [`MaterialProperties::mesh_pipeline_key_bits`].
+    ///
+    /// The mesh is queued according to the former, but its pipeline is specialized according to
+    /// the latter.
+    TransmissionKeyMismatch,
+    /// [`MaterialProperties::reads_view_transmission_texture`] is set, but the alpha mode of the
+    /// material is transparent, so it's drawn in the [`MaterialPhase::Transparent3d`] phase
+    /// instead of the [`MaterialPhase::Transmissive3d`] phase.
+    TransmissionInTransparentPhase,
+    /// The blend bits of the [`MeshPipelineKey`] of a pass don't match the [`AlphaMode`] of the
+    /// pass, so it's drawn in a phase or with blending its alpha mode doesn't select.
+    AlphaModeKeyMismatch {
+        /// The index of the pass, `0` for the main pass.
+        pass_index: u32,
+        alpha_mode: AlphaMode,
+    },
+    /// [`MaterialProperties::blend_state`] is set, but the main pass is drawn in an unsorted
+    /// phase, so the blended mesh isn't drawn after the meshes behind it.
+    BlendStateInUnsortedPhase(MaterialPhase),
+    /// A pass has a depth bias, but is drawn in an unsorted phase, which ignores it.
+    DepthBiasInUnsortedPhase {
+        /// The index of the pass, `0` for the main pass.
+        pass_index: u32,
+        phase: MaterialPhase,
+    },
+    /// [`MaterialProperties::stencil`] is set, but always passes and never writes to the stencil.
+    StencilHasNoEffect,
+    /// [`MaterialProperties::double_sided_normals`] is set, but the back faces the normals are
+    /// flipped for are culled by [`MaterialProperties::cull_mode`].
+    DoubleSidedNormalsWithBackFacesCulled,
+}
+
+impl fmt::Display for MaterialPropertiesIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TransmissionKeyMismatch => write!(
+                f,
+                "`reads_view_transmission_texture` doesn't match the \
+                `READS_VIEW_TRANSMISSION_TEXTURE` bit of the mesh pipeline key"
+            ),
+            Self::TransmissionInTransparentPhase => write!(
+                f,
+                "`reads_view_transmission_texture` is set, but the transparent alpha mode draws \
+                the material in the `Transparent3d` phase"
+            ),
+            Self::AlphaModeKeyMismatch {
+                pass_index,
+                alpha_mode,
+            } => write!(
+                f,
+                "the mesh pipeline key of pass {pass_index} doesn't match its alpha mode \
+                {alpha_mode:?}"
+            ),
+            Self::BlendStateInUnsortedPhase(phase) => write!(
+                f,
+                "a blend state is set, but the main pass is drawn in the unsorted {phase:?} phase"
+            ),
+            Self::DepthBiasInUnsortedPhase { pass_index, phase } => write!(
+                f,
+                "pass {pass_index} has a depth bias, but is drawn in the unsorted {phase:?} phase"
+            ),
+            Self::StencilHasNoEffect => {
+                write!(f, "the stencil always passes and is never written to")
+            }
+            Self::DoubleSidedNormalsWithBackFacesCulled => write!(
+                f,
+                "`double_sided_normals` is set, but the back faces are culled"
+            ),
+        }
+    }
+}
+
+impl MaterialProperties {
+    /// The phase the main pass of the material is queued in.
+    pub fn phase(&self) -> MaterialPhase {
+        MaterialPhase::from_properties(
+            self.mesh_pipeline_key_bits,
+            self.reads_view_transmission_texture,
+            self.render_method,
+        )
+    }
+
+    /// Checks that the properties are consistent with each other, returning the issues found.
+    ///
+    /// The properties prepared from a [`Material`] are consistent unless the material returns
+    /// contradicting values, such as a [`Material::blend_state`] with an opaque
+    /// [`Material::alpha_mode`]. Tools and tests can use this to catch such mistakes, which
+    /// otherwise silently draw the mesh in an unexpected way.
+    pub fn validate(&self) -> Vec<MaterialPropertiesIssue> {
+        let mut issues = Vec::new();
+        let phase = self.phase();
+
+        if self.reads_view_transmission_texture
+            != self
+                .mesh_pipeline_key_bits
+                .contains(MeshPipelineKey::READS_VIEW_TRANSMISSION_TEXTURE)
+        {
+            issues.push(MaterialPropertiesIssue::TransmissionKeyMismatch);
+        }
+        if self.reads_view_transmission_texture && phase == MaterialPhase::Transparent3d {
+            issues.push(MaterialPropertiesIssue::TransmissionInTransparentPhase);
+        }
+
+        let passes = std::iter::once((
+            self.alpha_mode,
+            self.mesh_pipeline_key_bits,
+            self.depth_bias,
+            phase,
+        ))
+        .chain(self.additional_passes.iter().map(|pass| {
+            (
+                pass.alpha_mode,
+                pass.mesh_pipeline_key_bits,
+                pass.depth_bias,
+                MaterialPhase::from_properties(
+                    pass.mesh_pipeline_key_bits,
+                    false,
+                    OpaqueRendererMethod::Forward,
+                ),
+            )
+        }));
+        for (pass_index, (alpha_mode, mesh_pipeline_key_bits, depth_bias, phase)) in
+            passes.enumerate()
+        {
+            let pass_index = pass_index as u32;
+            if !alpha_mode_matches_key(alpha_mode, mesh_pipeline_key_bits) {
+                issues.push(MaterialPropertiesIssue::AlphaModeKeyMismatch {
+                    pass_index,
+                    alpha_mode,
+                });
+            }
+            if depth_bias != 0.0 && !phase.is_sorted() {
+                issues
+                    .push(MaterialPropertiesIssue::DepthBiasInUnsortedPhase { pass_index, phase });
+            }
+        }
+
+        if self.blend_state.is_some() && !phase.is_sorted() {
+            issues.push(MaterialPropertiesIssue::BlendStateInUnsortedPhase(phase));
+        }
+
+        if let Some(stencil) = self.stencil {
+            let writes = stencil.write_mask != 0
+                && [stencil.fail_op, stencil.depth_fail_op, stencil.pass_op]
+                    .iter()
+                    .any(|op| *op != StencilOperation::Keep);
+            if stencil.compare == CompareFunction::Always && !writes {
+                issues.push(MaterialPropertiesIssue::StencilHasNoEffect);
+            }
+        }
+
+        if self.double_sided_normals && self.cull_mode == Some(Face::Back) {
+            issues.push(MaterialPropertiesIssue::DoubleSidedNormalsWithBackFacesCulled);
+        }
+
+        issues
+    }
+}
+
+/// Whether the blend bits of a [`MeshPipelineKey`] are the ones [`alpha_mode_pipeline_key`]
+/// returns for `alpha_mode`, with or without MSAA.
+fn alpha_mode_matches_key(alpha_mode: AlphaMode, mesh_pipeline_key_bits: MeshPipelineKey) -> bool {
+    let blend_bits = MeshPipelineKey::BLEND_RESERVED_BITS | MeshPipelineKey::MAY_DISCARD;
+    let bits = mesh_pipeline_key_bits.intersection(blend_bits);
+    [Msaa::Off, Msaa::Sample4]
+        .iter()
+        .any(|msaa| alpha_mode_pipeline_key(alpha_mode, msaa).intersection(blend_bits) == bits)
+}
+
+/// A summary of how a [`PreparedMaterial`] is drawn, returned by [`PreparedMaterial::report`].
+#[derive(Debug)]
+pub struct MaterialReport {
+    /// The type name of the material.
+    pub material: &'static str,
+    /// The main pass of the material, followed by its additional passes.
+    pub passes: Vec<MaterialPassReport>,
+    /// The issues found by [`MaterialProperties::validate`].
+    pub issues: Vec<MaterialPropertiesIssue>,
+    /// The [`MaterialProperties::deferred_fallback`] of the material.
+    pub deferred_fallback: Vec<&'static str>,
+    /// The vertex shader the material is drawn with in the shadow maps of all light types: its
+    /// [`Material::shadow_vertex_shader`], or else its [`Material::prepass_vertex_shader`].
+    pub shadow_vertex_shader: ShaderRef,
+    /// The fragment shader the material is drawn with in the shadow maps of all light types: its
+    /// [`Material::shadow_fragment_shader`], or else its [`Material::prepass_fragment_shader`].
+    pub shadow_fragment_shader: ShaderRef,
+}
+
+/// How a pass of a material is drawn, see [`MaterialReport`].
+#[derive(Debug)]
+pub struct MaterialPassReport {
+    /// The index of the pass, `0` for the main pass.
+    pub pass_index: u32,
+    pub alpha_mode: AlphaMode,
+    /// The phase the pass is queued in.
+    pub phase: MaterialPhase,
+    /// The type name of the draw function the pass is drawn with.
+    pub draw_function: &'static str,
+    pub vertex_shader: ShaderRef,
+    pub fragment_shader: ShaderRef,
+    pub depth_bias: f32,
+}
+
+impl<M: Material> PreparedMaterial<M> {
+    /// Lists the passes of the material, with the phase, draw function and shaders of each, and
+    /// the issues found by [`MaterialProperties::validate`].
+    ///
+    /// This only covers the main view and the shadow shaders. The prepasses draw the main pass with
+    /// the prepass shaders of the material.
+    pub fn report(&self) -> MaterialReport {
+        let properties = &self.properties;
+        let main_phase = properties.phase();
+        let main_pass = match main_phase {
+            MaterialPhase::Opaque3dDeferred | MaterialPhase::AlphaMask3dDeferred => {
+                MaterialPassReport {
+                    pass_index: 0,
+                    alpha_mode: properties.alpha_mode,
+                    phase: main_phase,
+                    draw_function: std::any::type_name::<DrawPrepass<M>>(),
+                    vertex_shader: M::deferred_vertex_shader(),
+                    fragment_shader: M::deferred_fragment_shader(),
+                    depth_bias: properties.depth_bias,
+                }
+            }
+            _ => MaterialPassReport {
+                pass_index: 0,
+                alpha_mode: properties.alpha_mode,
+                phase: main_phase,
+                draw_function: std::any::type_name::<DrawMaterial<M>>(),
+                vertex_shader: M::vertex_shader(),
+                fragment_shader: M::fragment_shader(),
+                depth_bias: properties.depth_bias,
+            },
+        };
+
+        let additional_passes =
+            properties
+                .additional_passes
+                .iter()
+                .enumerate()
+                .map(|(index, pass)| MaterialPassReport {
+                    pass_index: index as u32 + 1,
+                    alpha_mode: pass.alpha_mode,
+                    phase: MaterialPhase::from_properties(
+                        pass.mesh_pipeline_key_bits,
+                        false,
+                        OpaqueRendererMethod::Forward,
+                    ),
+                    draw_function: std::any::type_name::<DrawMaterial<M>>(),
+                    vertex_shader: M::vertex_shader(),
+                    fragment_shader: M::fragment_shader(),
+                    depth_bias: pass.depth_bias,
+                });
+
+        MaterialReport {
+            material: std::any::type_name::<M>(),
+            passes: std::iter::once(main_pass)
+                .chain(additional_passes)
+                .collect(),
+            issues: properties.validate(),
+            deferred_fallback: properties.deferred_fallback.clone(),
+            shadow_vertex_shader: match M::shadow_vertex_shader() {
+                ShaderRef::Default => M::prepass_vertex_shader(),
+                shader => shader,
+            },
+            shadow_fragment_shader: match M::shadow_fragment_shader() {
+                ShaderRef::Default => M::prepass_fragment_shader(),
+                shader => shader,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use bevy_render::{
+        alpha::AlphaMode,
+        render_resource::{BlendState, DepthBiasState, Face},
+        view::Msaa,
+    };
+
+    use super::{MaterialPhase, MaterialPropertiesIssue};
+    use crate::{
+        alpha_mode_pipeline_key, MaterialPassProperties, MaterialProperties, MaterialStencil,
+        MeshPipelineKey, OpaqueRendererMethod, VertexColorMode,
+    };
+
+    fn properties(alpha_mode: AlphaMode) -> MaterialProperties {
+        MaterialProperties {
+            render_method: OpaqueRendererMethod::Forward,
+            alpha_mode,
+            mesh_pipeline_key_bits: alpha_mode_pipeline_key(alpha_mode, &Msaa::Sample4),
+            depth_bias: 0.0,
+            reads_view_transmission_texture: false,
+            additional_passes: Vec::new(),
+            stencil: None,
+            blend_state: None,
+            deband_dither: true,
+            shader_defs: Arc::from([]),
+            cull_mode: Some(Face::Back),
+            double_sided_normals: false,
+            rasterizer_depth_bias: DepthBiasState::default(),
+            vertex_color_mode: VertexColorMode::Multiply,
+            deferred_fallback: Vec::new(),
+            deferred_gbuffer_extension: false,
+            timed_draws: false,
+            colored_shadow: false,
+        }
+    }
+
+    #[test]
+    fn consistent_properties_have_no_issues() {
+        for alpha_mode in [
+            AlphaMode::Opaque,
+            AlphaMode::Mask(0.5),
+            AlphaMode::Blend,
+            AlphaMode::AlphaToCoverage,
+        ] {
+            assert_eq!(properties(alpha_mode).validate(), []);
+        }
+
+        let mut properties = properties(AlphaMode::Blend);
+        properties.depth_bias = 1.0;
+        properties.blend_state = Some(BlendState::ALPHA_BLENDING);
+        properties.stencil = Some(MaterialStencil::write(1));
+        properties.additional_passes.push(MaterialPassProperties {
+            alpha_mode: AlphaMode::Add,
+            mesh_pipeline_key_bits: alpha_mode_pipeline_key(AlphaMode::Add, &Msaa::Off),
+            depth_bias: 2.0,
+        });
+        assert_eq!(properties.validate(), []);
+    }
+
+    #[test]
+    fn decals_are_drawn_in_the_decal_phase() {
+        for alpha_mode in [AlphaMode::Opaque, AlphaMode::Mask(0.5), AlphaMode::Blend] {
+            let mut decal = properties(alpha_mode);
+            decal.mesh_pipeline_key_bits |= MeshPipelineKey::DECAL;
+            // The decal phase is sorted, so the depth bias and blend state apply.
+            decal.depth_bias = 1.0;
+            decal.blend_state = Some(BlendState::ALPHA_BLENDING);
+            assert_eq!(decal.phase(), MaterialPhase::Decal3d);
+            assert_eq!(decal.validate(), []);
+        }
+    }
+
+    #[test]
+    fn inconsistent_properties_are_reported() {
+        let mut opaque = properties(AlphaMode::Opaque);
+        opaque.reads_view_transmission_texture = true;
+        opaque.blend_state = Some(BlendState::ALPHA_BLENDING);
+        opaque.stencil = Some(MaterialStencil::default());
+        opaque.double_sided_normals = true;
+        opaque.additional_passes.push(MaterialPassProperties {
+            alpha_mode: AlphaMode::Blend,
+            mesh_pipeline_key_bits: MeshPipelineKey::NONE,
+            depth_bias: 1.0,
+        });
+        assert_eq!(
+            opaque.validate(),
+            [
+                MaterialPropertiesIssue::TransmissionKeyMismatch,
+                MaterialPropertiesIssue::AlphaModeKeyMismatch {
+                    pass_index: 1,
+                    alpha_mode: AlphaMode::Blend,
+                },
+                MaterialPropertiesIssue::DepthBiasInUnsortedPhase {
+                    pass_index: 1,
+                    phase: MaterialPhase::Opaque3d,
+                },
+                MaterialPropertiesIssue::StencilHasNoEffect,
+                MaterialPropertiesIssue::DoubleSidedNormalsWithBackFacesCulled,
+            ]
+        );
+
+        let mut transparent = properties(AlphaMode::Blend);
+        transparent.reads_view_transmission_texture = true;
+        transparent.mesh_pipeline_key_bits |= MeshPipelineKey::READS_VIEW_TRANSMISSION_TEXTURE;
+        assert_eq!(
+            transparent.validate(),
+            [MaterialPropertiesIssue::TransmissionInTransparentPhase]
+        );
+    }
+}
@@ -0,0 +1,996 @@
+use std::{marker::PhantomData, mem, num::NonZeroU64, ops::Range};
+
+#[cfg(feature = "bevy_animation")]
+use bevy_animation::AnimatedProperties;
+use bevy_app::{App, Plugin};
+#[cfg(feature = "bevy_animation")]
+use bevy_asset::Handle;
+use bevy_asset::{Asset, AssetEvent, AssetId};
+use bevy_ecs::prelude::*;
+use bevy_math::Vec4;
+use bevy_render::{
+    render_asset::{prepare_assets, RenderAssets},
+    render_resource::{encase, encase::internal::WriteInto, *},
+    renderer::{RenderDevice, RenderQueue},
+    texture::{FallbackImage, GpuImage},
+    Extract, ExtractSchedule, Render, RenderApp, RenderSet,
+};
+use bevy_utils::{warn_once, HashMap};
+use bytemuck::{Pod, Zeroable};
+use crossbeam_channel::{Receiver, Sender};
+
+use crate::{Material, MaterialBindingsIndex, MaterialPipeline, PreparedMaterial};
+
+/// The number of slots the buffer of a [`MaterialTable`] starts with.
+const MIN_MATERIAL_TABLE_CAPACITY: u32 = 64;
+
+/// A [`Material`] whose uniform data is stored in a [`MaterialTable`], see
+/// [`MaterialTablePlugin`].
+pub trait MaterialTableData: Material {
+    /// The data of one instance of the material.
+    type TableData: ShaderType + ShaderSize + WriteInto;
+
+    /// The binding of the table in the material bind group.
+    ///
+    /// The [`AsBindGroup`] implementation of the material must not use it.
+    const TABLE_BINDING: u32;
+
+    /// The binding of the remapping table of the table in the material bind
+    /// group, see [`MaterialBindingsIndex`].
+    ///
+    /// The [`AsBindGroup`] implementation of the material must not use it.
+    const TABLE_REMAP_BINDING: u32;
+
+    /// Returns the data of this instance of the material.
+    fn table_data(&self, images: &RenderAssets<GpuImage>) -> Self::TableData;
+
+    /// Returns the field of [`Self::TableData`] animated by the animation
+    /// property named `name`, if any.
+    ///
+    /// With the `bevy_animation` feature, the `AnimatedProperties` of the
+    /// entities using the material are written to these fields of its slot
+    /// every frame they change, so that clips can animate e.g. its base color,
+    /// emissive strength or UV offset without preparing the material again.
+    /// The slot is shared by all the entities using the material asset, so
+    /// entities animated separately need their own material.
+    fn animated_field(_name: &str) -> Option<MaterialTableField> {
+        None
+    }
+}
+
+/// A field of the [`MaterialTableData::TableData`] of a material, which can be
+/// written with [`MaterialTable::write_field`] without preparing the material
+/// again.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MaterialTableField {
+    /// The offset of the field in the table data, in bytes, in the layout of
+    /// storage buffers.
+    pub offset: u32,
+    /// The number of `f32` components of the field, from 1 to 4.
+    pub components: u32,
+}
+
+impl MaterialTableField {
+    /// Returns the bytes of the field in the table data of `slot`, or `None` if
+    /// the field doesn't fit in table data of `data_size` bytes.
+    fn byte_range(&self, slot: u32, data_size: usize) -> Option<Range<usize>> {
+        if !(1..=4).contains(&self.components) {
+            return None;
+        }
+        let start = self.offset as usize;
+        let end = start + self.components as usize * mem::size_of::<f32>();
+        let slot_offset = slot as usize * data_size;
+        (end <= data_size).then_some(slot_offset + start..slot_offset + end)
+    }
+}
+
+/// Stores the [`MaterialTableData::TableData`] of all the instances of the
+/// material `M` contiguously in a single storage buffer.
+///
+/// Each instance gets a slot in the buffer. The slot changes whenever the
+/// instance is prepared again, e.g. when one of its textures finishes loading,
+/// so the mesh uniforms of the entities using it hold its
+/// [`MaterialBindingsIndex`] instead, which stays the same for as long as the
+/// material asset exists. A second storage buffer, the remapping table, maps it
+/// to the current slot along with its generation, which shaders check so that
+/// a stale index never reads the data of another instance.
+///
+/// Instances whose other bindings are the same resources share a single bind
+/// group, so they're batched together like instances of one material, even
+/// when binding arrays aren't available. A material should therefore keep all
+/// of its uniform data in the table, and only textures and samplers in its
+/// [`AsBindGroup`] implementation.
+///
+/// Shaders read the data of an instance with the functions of
+/// `bevy_pbr::mesh_functions`:
+///
+/// ```wgsl
+/// #ifdef MATERIAL_TABLE
+/// @group(2) @binding(0) var<storage> material_table: array<CustomMaterial>;
+/// @group(2) @binding(1) var<storage> material_table_remap: array<vec2<u32>>;
+/// #else
+/// @group(2) @binding(0) var<uniform> material: CustomMaterial;
+/// #endif
+///
+/// @fragment
+/// fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+/// #ifdef MATERIAL_TABLE
+///     let index = get_material_bindings_index(in.instance_index);
+///     let slot = material_table_slot(index, material_table_remap[material_table_remap_index(index)]);
+///     if slot == MATERIAL_TABLE_INVALID_SLOT {
+///         discard;
+///     }
+///     let material = material_table[slot];
+/// #endif
+///     return material.color;
+/// }
+/// ```
+///
+/// The `MATERIAL_TABLE` shader def is only set when storage buffers are
+/// available. Otherwise each instance gets its own uniform buffer and bind
+/// group, like a material without a table.
+#[derive(Resource)]
+pub struct MaterialTable<M: Material> {
+    binding: u32,
+    remap_binding: u32,
+    data_size: NonZeroU64,
+    encode: fn(&M, &RenderAssets<GpuImage>, bool) -> Vec<u8>,
+    data: Vec<u8>,
+    /// The fields written to the slots of materials by animations this frame.
+    animated_fields: Vec<(AssetId<M>, MaterialTableField, Vec4)>,
+    /// The bytes of `data` that changed since they were last written to the
+    /// buffer, so that changing a few materials only uploads their slots.
+    dirty_range: Option<Range<usize>>,
+    buffer: Option<Buffer>,
+    capacity: u32,
+    slot_count: u32,
+    free_slots: Vec<u32>,
+    freed_sender: Sender<u32>,
+    freed_receiver: Receiver<u32>,
+    bind_groups: HashMap<MaterialTableBindGroupKey, MaterialTableBindGroup>,
+    slot_bind_group_keys: Vec<Option<MaterialTableBindGroupKey>>,
+    remap: MaterialTableRemap<M>,
+    remap_buffer: Option<Buffer>,
+    unused_materials: Vec<AssetId<M>>,
+    rebind: bool,
+    marker: PhantomData<M>,
+}
+
+/// The statistics of a [`MaterialTable`], see [`MaterialTable::stats`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MaterialTableStats {
+    /// The number of slots the buffers of the table have room for.
+    pub capacity: u32,
+    /// The number of slots holding the data of a material.
+    pub used_slots: u32,
+    /// The number of slots freed by removed materials, which are reused by the
+    /// next materials before the table grows.
+    ///
+    /// The table is compacted once its materials fit in a quarter of its
+    /// capacity.
+    pub free_slots: u32,
+    /// The number of entries of the remapping table, including those of
+    /// removed materials that are waiting to be reused.
+    pub remap_entries: u32,
+    /// The number of bind groups shared by the materials.
+    pub bind_groups: usize,
+}
+
+/// The resources bound by a material besides its table, by binding.
+type MaterialTableBindGroupKey = Vec<(u32, BindingResourceId)>;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum BindingResourceId {
+    Buffer(BufferId),
+    TextureView(TextureViewId),
+    Sampler(SamplerId),
+}
+
+impl From<&OwnedBindingResource> for BindingResourceId {
+    fn from(resource: &OwnedBindingResource) -> Self {
+        match resource {
+            OwnedBindingResource::Buffer(buffer) => BindingResourceId::Buffer(buffer.id()),
+            OwnedBindingResource::TextureView(view) => BindingResourceId::TextureView(view.id()),
+            OwnedBindingResource::Sampler(sampler) => BindingResourceId::Sampler(sampler.id()),
+        }
+    }
+}
+
+/// A bind group shared by the instances of a material that bind the same
+/// resources.
+struct MaterialTableBindGroup {
+    bindings: Vec<(u32, OwnedBindingResource)>,
+    bind_group: BindGroup,
+    users: u32,
+}
+
+/// The slot of an instance of a material in its [`MaterialTable`].
+///
+/// The slot is freed when this is dropped, along with the
+/// [`PreparedMaterial`] holding it.
+pub struct MaterialTableSlot {
+    index: u32,
+    bindings_index: MaterialBindingsIndex,
+    freed_sender: Sender<u32>,
+}
+
+impl MaterialTableSlot {
+    /// Returns the index of the slot in the table buffer.
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// Returns the stable index of the material in the remapping table.
+    ///
+    /// This is assigned in [`prepare_material_table`].
+    pub fn bindings_index(&self) -> MaterialBindingsIndex {
+        self.bindings_index
+    }
+}
+
+impl Drop for MaterialTableSlot {
+    fn drop(&mut self) {
+        // The table is gone if the channel is closed, so there's nothing to free.
+        let _ = self.freed_sender.send(self.index);
+    }
+}
+
+impl<M: Material> MaterialTable<M> {
+    /// Returns the layout entries of the table and of its remapping table in
+    /// the material bind group.
+    ///
+    /// The remapping table is only bound when storage buffers are available.
+    pub fn bind_group_layout_entries(
+        &self,
+        render_device: &RenderDevice,
+    ) -> Vec<BindGroupLayoutEntry> {
+        let entry = |binding, ty, min_binding_size| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::all(),
+            ty: BindingType::Buffer {
+                ty,
+                has_dynamic_offset: false,
+                min_binding_size: Some(min_binding_size),
+            },
+            count: None,
+        };
+        if !uses_storage_buffers(render_device) {
+            return vec![entry(
+                self.binding,
+                BufferBindingType::Uniform,
+                self.data_size,
+            )];
+        }
+        let storage = BufferBindingType::Storage { read_only: true };
+        vec![
+            entry(self.binding, storage, self.data_size),
+            entry(
+                self.remap_binding,
+                storage,
+                MaterialTableRemapEntry::SHADER_SIZE,
+            ),
+        ]
+    }
+
+    /// Creates the bind group of `material`, writing its data into a new slot
+    /// of the table.
+    ///
+    /// Returns no slot if storage buffers aren't available, in which case the
+    /// data is bound as a uniform buffer of the material instead.
+    pub fn as_bind_group(
+        &mut self,
+        material: &M,
+        layout: &BindGroupLayout,
+        render_device: &RenderDevice,
+        images: &RenderAssets<GpuImage>,
+        fallback_image: &FallbackImage,
+    ) -> Result<(PreparedBindGroup<M::Data>, Option<MaterialTableSlot>), AsBindGroupError> {
+        let UnpreparedBindGroup { mut bindings, data } =
+            material.unprepared_bind_group(layout, render_device, images, fallback_image)?;
+        let uses_storage = uses_storage_buffers(render_device);
+        let table_data = (self.encode)(material, images, uses_storage);
+
+        if !uses_storage {
+            let buffer = render_device.create_buffer_with_data(&BufferInitDescriptor {
+                label: Some("material_table_uniform_buffer"),
+                usage: BufferUsages::COPY_DST | BufferUsages::UNIFORM,
+                contents: &table_data,
+            });
+            bindings.push((self.binding, OwnedBindingResource::Buffer(buffer)));
+            let bind_group = create_bind_group::<M>(render_device, layout, &bindings, &[]);
+            return Ok((
+                PreparedBindGroup {
+                    bindings,
+                    bind_group,
+                    data,
+                },
+                None,
+            ));
+        }
+
+        let index = self.allocate_slot();
+        let data_size = self.data_size.get() as usize;
+        let offset = index as usize * data_size;
+        if self.data.len() < offset + data_size {
+            self.data.resize(offset + data_size, 0);
+        }
+        self.data[offset..offset + data_size].copy_from_slice(&table_data);
+        self.mark_dirty(offset..offset + data_size);
+
+        if index >= self.capacity {
+            self.reallocate(layout, render_device);
+        }
+        let (Some(buffer), Some(remap_buffer)) = (&self.buffer, &self.remap_buffer) else {
+            unreachable!("The table buffers are allocated along with the first slot");
+        };
+        let table_buffers = [(self.binding, buffer), (self.remap_binding, remap_buffer)];
+
+        let key: MaterialTableBindGroupKey = bindings
+            .iter()
+            .map(|(binding, resource)| (*binding, BindingResourceId::from(resource)))
+            .collect();
+        let shared = self.bind_groups.entry(key.clone()).or_insert_with(|| {
+            let bind_group =
+                create_bind_group::<M>(render_device, layout, &bindings, &table_buffers);
+            MaterialTableBindGroup {
+                bindings: bindings.iter().map(clone_binding).collect(),
+                bind_group,
+                users: 0,
+            }
+        });
+        shared.users += 1;
+        let bind_group = shared.bind_group.clone();
+        self.slot_bind_group_keys[index as usize] = Some(key);
+
+        bindings.extend(
+            table_buffers
+                .map(|(binding, buffer)| (binding, OwnedBindingResource::Buffer(buffer.clone()))),
+        );
+        Ok((
+            PreparedBindGroup {
+                bindings,
+                bind_group,
+                data,
+            },
+            Some(MaterialTableSlot {
+                index,
+                bindings_index: MaterialBindingsIndex::default(),
+                freed_sender: self.freed_sender.clone(),
+            }),
+        ))
+    }
+
+    /// Writes `value` to `field` in the data of the material in `slot`, without
+    /// preparing the material again, so that only the bytes of the field are
+    /// uploaded.
+    ///
+    /// The value is overwritten once the material is prepared again, e.g. when
+    /// its asset is modified.
+    pub fn write_field(
+        &mut self,
+        slot: &MaterialTableSlot,
+        field: MaterialTableField,
+        value: Vec4,
+    ) {
+        let Some(range) = field.byte_range(slot.index, self.data_size.get() as usize) else {
+            warn_once!(
+                "{:?} doesn't fit in the table data of {}",
+                field,
+                std::any::type_name::<M>()
+            );
+            return;
+        };
+        let components = &value.to_array()[..field.components as usize];
+        self.data[range.clone()].copy_from_slice(bytemuck::cast_slice(components));
+        self.mark_dirty(range);
+    }
+
+    fn mark_dirty(&mut self, range: Range<usize>) {
+        self.dirty_range = Some(merge_dirty_ranges(self.dirty_range.take(), range));
+    }
+
+    fn allocate_slot(&mut self) -> u32 {
+        if let Some(index) = self.free_slots.pop() {
+            return index;
+        }
+        let index = self.slot_count;
+        self.slot_count += 1;
+        self.slot_bind_group_keys.push(None);
+        index
+    }
+
+    /// Returns the statistics of the table.
+    pub fn stats(&self) -> MaterialTableStats {
+        MaterialTableStats {
+            capacity: self.capacity,
+            used_slots: self.slot_count - self.free_slots.len() as u32,
+            free_slots: self.free_slots.len() as u32,
+            remap_entries: self.remap.entries.len() as u32,
+            bind_groups: self.bind_groups.len(),
+        }
+    }
+
+    /// Returns the capacity of the buffers needed by the slots and the
+    /// remapping table entries in use.
+    fn required_capacity(&self, slot_count: u32) -> u32 {
+        slot_count
+            .max(self.remap.entries.len() as u32)
+            .next_power_of_two()
+            .max(MIN_MATERIAL_TABLE_CAPACITY)
+    }
+
+    /// Moves the materials in the slots past the used ones into the free
+    /// slots, and shrinks the buffers, if at most a quarter of their capacity
+    /// is needed after that.
+    ///
+    /// The moved materials keep their [`MaterialBindingsIndex`], only their
+    /// entry in the remapping table changes, so mesh uniforms don't need to be
+    /// written again.
+    fn compact(
+        &mut self,
+        materials: &mut RenderAssets<PreparedMaterial<M>>,
+        layout: &BindGroupLayout,
+        render_device: &RenderDevice,
+    ) {
+        let used_slots = self.slot_count - self.free_slots.len() as u32;
+        if self.required_capacity(used_slots) * 4 > self.capacity {
+            return;
+        }
+
+        // Each material past the used slots takes the lowest free slot.
+        let mut holes = self
+            .free_slots
+            .drain(..)
+            .filter(|slot| *slot < used_slots)
+            .collect::<Vec<_>>();
+        holes.sort_unstable_by(|a, b| b.cmp(a));
+        let data_size = self.data_size.get() as usize;
+        for (_, material) in materials.iter_mut() {
+            let Some(slot) = &mut material.table_slot else {
+                continue;
+            };
+            if slot.index < used_slots {
+                continue;
+            }
+            let Some(hole) = holes.pop() else {
+                unreachable!(
+                    "There are as many free slots below the used slots as used slots past them"
+                );
+            };
+            let (from, to) = (slot.index as usize, hole as usize);
+            self.data
+                .copy_within(from * data_size..(from + 1) * data_size, to * data_size);
+            self.slot_bind_group_keys[to] = self.slot_bind_group_keys[from].take();
+            self.remap.move_slot(slot.index, hole);
+            slot.index = hole;
+        }
+
+        self.slot_count = used_slots;
+        self.slot_bind_group_keys.truncate(used_slots as usize);
+        self.remap.slot_indices.truncate(used_slots as usize);
+        self.data.truncate(used_slots as usize * data_size);
+        self.reallocate(layout, render_device);
+    }
+
+    /// Creates buffers for the table and its remapping table sized for the
+    /// slots in use, and recreates the bind groups bound to the previous ones.
+    fn reallocate(&mut self, layout: &BindGroupLayout, render_device: &RenderDevice) {
+        self.capacity = self.required_capacity(self.slot_count);
+        let buffer = render_device.create_buffer(&BufferDescriptor {
+            label: Some("material_table_buffer"),
+            size: u64::from(self.capacity) * self.data_size.get(),
+            usage: BufferUsages::COPY_DST | BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+        let remap_buffer = render_device.create_buffer(&BufferDescriptor {
+            label: Some("material_table_remap_buffer"),
+            size: u64::from(self.capacity) * MaterialTableRemapEntry::SHADER_SIZE.get(),
+            usage: BufferUsages::COPY_DST | BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+
+        let table_buffers = [(self.binding, &buffer), (self.remap_binding, &remap_buffer)];
+        for shared in self.bind_groups.values_mut() {
+            shared.bind_group =
+                create_bind_group::<M>(render_device, layout, &shared.bindings, &table_buffers);
+        }
+        self.rebind = !self.bind_groups.is_empty();
+        self.buffer = Some(buffer);
+        self.remap_buffer = Some(remap_buffer);
+        // The new buffer is empty, so all of the data is written to it.
+        self.dirty_range = Some(0..self.data.len());
+        self.remap.dirty = true;
+    }
+}
+
+/// An entry of the remapping table of a [`MaterialTable`], read by shaders as
+/// a `vec2<u32>`.
+#[derive(ShaderType, Pod, Zeroable, Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(C)]
+struct MaterialTableRemapEntry {
+    /// The slot of the material in the table buffer, or
+    /// [`MaterialTableRemapEntry::INVALID_SLOT`] if it has none.
+    slot: u32,
+    /// The generation of the [`MaterialBindingsIndex`] of the entry.
+    generation: u32,
+}
+
+impl MaterialTableRemapEntry {
+    /// The slot of the entries whose material isn't prepared, which must match
+    /// `MATERIAL_TABLE_INVALID_SLOT` in `mesh_types.wgsl`.
+    const INVALID_SLOT: u32 = u32::MAX;
+}
+
+/// Maps the material assets of a [`MaterialTable`] to the stable indices of
+/// their [`MaterialBindingsIndex`], and those to their current slot.
+struct MaterialTableRemap<M: Asset> {
+    /// The index of the entry of each material asset.
+    indices: HashMap<AssetId<M>, u32>,
+    /// The entries, uploaded to the remapping table buffer.
+    entries: Vec<MaterialTableRemapEntry>,
+    /// The indices of the entries of removed material assets.
+    free_indices: Vec<u32>,
+    /// The index of the entry pointing to each slot of the table.
+    slot_indices: Vec<Option<u32>>,
+    /// Whether the entries changed since they were last uploaded.
+    dirty: bool,
+}
+
+impl<M: Asset> Default for MaterialTableRemap<M> {
+    fn default() -> Self {
+        Self {
+            indices: HashMap::default(),
+            entries: Vec::new(),
+            free_indices: Vec::new(),
+            slot_indices: Vec::new(),
+            dirty: false,
+        }
+    }
+}
+
+impl<M: Asset> MaterialTableRemap<M> {
+    /// Points the entry of the material asset `id` to `slot`, creating the
+    /// entry if the asset doesn't have one yet.
+    fn assign(&mut self, id: AssetId<M>, slot: u32) -> MaterialBindingsIndex {
+        let index = match self.indices.get(&id) {
+            Some(index) => *index,
+            None => {
+                let index = self.free_indices.pop().unwrap_or_else(|| {
+                    self.entries.push(MaterialTableRemapEntry {
+                        slot: MaterialTableRemapEntry::INVALID_SLOT,
+                        generation: 0,
+                    });
+                    self.entries.len() as u32 - 1
+                });
+                self.indices.insert(id, index);
+                index
+            }
+        };
+
+        let entry = &mut self.entries[index as usize];
+        entry.slot = slot;
+        if self.slot_indices.len() <= slot as usize {
+            self.slot_indices.resize(slot as usize + 1, None);
+        }
+        self.slot_indices[slot as usize] = Some(index);
+        self.dirty = true;
+        MaterialBindingsIndex {
+            index,
+            generation: entry.generation,
+        }
+    }
+
+    /// Returns true if an entry points to `slot`.
+    fn is_assigned(&self, slot: u32) -> bool {
+        self.slot_indices
+            .get(slot as usize)
+            .is_some_and(Option::is_some)
+    }
+
+    /// Points the entry pointing to `from` to `to` instead, when the material
+    /// is moved by [`MaterialTable::compact`].
+    fn move_slot(&mut self, from: u32, to: u32) {
+        let Some(index) = self
+            .slot_indices
+            .get_mut(from as usize)
+            .and_then(Option::take)
+        else {
+            return;
+        };
+        self.entries[index as usize].slot = to;
+        if self.slot_indices.len() <= to as usize {
+            self.slot_indices.resize(to as usize + 1, None);
+        }
+        self.slot_indices[to as usize] = Some(index);
+        self.dirty = true;
+    }
+
+    /// Invalidates the entry pointing to the freed `slot`, so that it can be
+    /// reused by another material.
+    fn free_slot(&mut self, slot: u32) {
+        let Some(index) = self
+            .slot_indices
+            .get_mut(slot as usize)
+            .and_then(Option::take)
+        else {
+            return;
+        };
+        let entry = &mut self.entries[index as usize];
+        if entry.slot == slot {
+            entry.slot = MaterialTableRemapEntry::INVALID_SLOT;
+            self.dirty = true;
+        }
+    }
+
+    /// Frees the entry of the removed material asset `id`, incrementing its
+    /// generation so that the indices of the asset held by mesh uniforms are
+    /// detected as stale once the entry is reused.
+    fn release(&mut self, id: AssetId<M>) {
+        let Some(index) = self.indices.remove(&id) else {
+            return;
+        };
+        let entry = &mut self.entries[index as usize];
+        entry.slot = MaterialTableRemapEntry::INVALID_SLOT;
+        entry.generation = (entry.generation + 1) & MaterialBindingsIndex::GENERATION_MASK;
+        self.free_indices.push(index);
+        self.dirty = true;
+    }
+}
+
+/// Adds a [`MaterialTable`] for the material `M`.
+///
+/// The [`MaterialPlugin`](crate::MaterialPlugin) of `M` must be added as well.
+pub struct MaterialTablePlugin<M: MaterialTableData>(PhantomData<M>);
+
+impl<M: MaterialTableData> Default for MaterialTablePlugin<M> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<M: MaterialTableData> Plugin for MaterialTablePlugin<M> {
+    fn build(&self, app: &mut App) {
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+
+        let (freed_sender, freed_receiver) = crossbeam_channel::unbounded();
+        render_app
+            .insert_resource(MaterialTable::<M> {
+                binding: M::TABLE_BINDING,
+                remap_binding: M::TABLE_REMAP_BINDING,
+                data_size: M::TableData::SHADER_SIZE,
+                encode: encode_table_data::<M>,
+                data: Vec::new(),
+                animated_fields: Vec::new(),
+                dirty_range: None,
+                buffer: None,
+                capacity: 0,
+                slot_count: 0,
+                free_slots: Vec::new(),
+                freed_sender,
+                freed_receiver,
+                bind_groups: HashMap::default(),
+                slot_bind_group_keys: Vec::new(),
+                remap: MaterialTableRemap::default(),
+                remap_buffer: None,
+                unused_materials: Vec::new(),
+                rebind: false,
+                marker: PhantomData,
+            })
+            .add_systems(ExtractSchedule, extract_unused_materials::<M>)
+            .add_systems(
+                Render,
+                prepare_material_table::<M>
+                    .in_set(RenderSet::PrepareAssets)
+                    .after(prepare_assets::<PreparedMaterial<M>>),
+            );
+
+        #[cfg(feature = "bevy_animation")]
+        render_app.add_systems(ExtractSchedule, extract_animated_material_fields::<M>);
+    }
+}
+
+/// Collects the material assets that are no longer used, whose entries in the
+/// remapping table of the [`MaterialTable`] are freed.
+pub fn extract_unused_materials<M: Material>(
+    mut material_table: ResMut<MaterialTable<M>>,
+    mut events: Extract<EventReader<AssetEvent<M>>>,
+) {
+    for event in events.read() {
+        if let AssetEvent::Unused { id } = event {
+            material_table.unused_materials.push(*id);
+        }
+    }
+}
+
+/// Collects the [`AnimatedProperties`] that changed on the entities using a
+/// material with a [`MaterialTable`], to write them to the fields of the slot
+/// of the material, see [`MaterialTableData::animated_field`].
+#[cfg(feature = "bevy_animation")]
+pub fn extract_animated_material_fields<M: MaterialTableData>(
+    mut material_table: ResMut<MaterialTable<M>>,
+    animated: Extract<Query<(&Handle<M>, &AnimatedProperties), Changed<AnimatedProperties>>>,
+) {
+    for (material, properties) in &animated {
+        for (property, value) in properties.iter() {
+            if let Some(field) = M::animated_field(property.name()) {
+                material_table
+                    .animated_fields
+                    .push((material.id(), field, value));
+            }
+        }
+    }
+}
+
+/// Frees the slots of the removed and replaced materials, assigns the new ones
+/// their entry in the remapping table, compacts the table once most of its
+/// slots are free, updates the bind groups of the materials if the table
+/// buffers were reallocated, writes the animated fields of the materials, and
+/// writes the tables to the GPU.
+pub fn prepare_material_table<M: Material>(
+    mut material_table: ResMut<MaterialTable<M>>,
+    mut materials: ResMut<RenderAssets<PreparedMaterial<M>>>,
+    material_pipeline: Res<MaterialPipeline<M>>,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+) {
+    let table = material_table.as_mut();
+
+    for id in table.unused_materials.drain(..) {
+        table.remap.release(id);
+    }
+
+    let mut freed = false;
+    while let Ok(index) = table.freed_receiver.try_recv() {
+        freed = true;
+        table.free_slots.push(index);
+        table.remap.free_slot(index);
+        let Some(key) = table.slot_bind_group_keys[index as usize].take() else {
+            continue;
+        };
+        if let Some(shared) = table.bind_groups.get_mut(&key) {
+            shared.users -= 1;
+            if shared.users == 0 {
+                table.bind_groups.remove(&key);
+            }
+        }
+    }
+
+    // The slots allocated since the last frame don't have an entry yet.
+    let mut assigned = false;
+    for (id, material) in materials.iter_mut() {
+        let Some(slot) = &mut material.table_slot else {
+            continue;
+        };
+        if !table.remap.is_assigned(slot.index) {
+            slot.bindings_index = table.remap.assign(id, slot.index);
+            assigned = true;
+        }
+    }
+    if assigned && table.remap.entries.len() as u32 > table.capacity {
+        table.reallocate(&material_pipeline.material_layout, &render_device);
+    } else if freed && table.capacity > MIN_MATERIAL_TABLE_CAPACITY {
+        table.compact(
+            &mut materials,
+            &material_pipeline.material_layout,
+            &render_device,
+        );
+    }
+
+    if table.rebind {
+        for (_, material) in materials.iter_mut() {
+            let Some(slot) = &material.table_slot else {
+                continue;
+            };
+            if let Some(shared) = table.slot_bind_group_keys[slot.index() as usize]
+                .as_ref()
+                .and_then(|key| table.bind_groups.get(key))
+            {
+                material.bind_group = shared.bind_group.clone();
+            }
+        }
+        table.rebind = false;
+    }
+
+    // Materials using uniform buffers instead of the table can't be animated.
+    for (id, field, value) in mem::take(&mut table.animated_fields) {
+        if let Some(slot) = materials
+            .get(id)
+            .and_then(|material| material.table_slot.as_ref())
+        {
+            table.write_field(slot, field, value);
+        }
+    }
+
+    if let Some(dirty_range) = table.dirty_range.take() {
+        if let Some(buffer) = &table.buffer {
+            render_queue.write_buffer(buffer, dirty_range.start as u64, &table.data[dirty_range]);
+        }
+    }
+
+    if table.remap.dirty {
+        if let Some(remap_buffer) = &table.remap_buffer {
+            render_queue.write_buffer(remap_buffer, 0, bytemuck::cast_slice(&table.remap.entries));
+        }
+        table.remap.dirty = false;
+    }
+}
+
+/// Creates the bind group layout of the material `M`, with the binding of its
+/// [`MaterialTable`] if it has one.
+pub(crate) fn material_bind_group_layout<M: Material>(world: &World) -> BindGroupLayout {
+    let render_device = world.resource::<RenderDevice>();
+    let Some(material_table) = world.get_resource::<MaterialTable<M>>() else {
+        return M::bind_group_layout(render_device);
+    };
+
+    let mut entries = M::bind_group_layout_entries(render_device);
+    entries.extend(material_table.bind_group_layout_entries(render_device));
+    render_device.create_bind_group_layout(M::label(), &entries)
+}
+
+/// Extends the `dirty` range of a [`MaterialTable`] to cover `range`, aligned to
+/// the [`COPY_BUFFER_ALIGNMENT`] of buffer writes.
+///
+/// A single range is kept, as writing the unchanged bytes between changed
+/// slots is cheaper than a write per slot when many materials change.
+fn merge_dirty_ranges(dirty: Option<Range<usize>>, range: Range<usize>) -> Range<usize> {
+    let alignment = COPY_BUFFER_ALIGNMENT as usize;
+    let range = range.start / alignment * alignment..range.end.div_ceil(alignment) * alignment;
+    match dirty {
+        Some(dirty) => dirty.start.min(range.start)..dirty.end.max(range.end),
+        None => range,
+    }
+}
+
+fn uses_storage_buffers(render_device: &RenderDevice) -> bool {
+    render_device.limits().max_storage_buffers_per_shader_stage > 0
+}
+
+fn encode_table_data<M: MaterialTableData>(
+    material: &M,
+    images: &RenderAssets<GpuImage>,
+    uses_storage: bool,
+) -> Vec<u8> {
+    let table_data = material.table_data(images);
+    if uses_storage {
+        let mut buffer = encase::StorageBuffer::new(Vec::new());
+        buffer.write(&table_data).unwrap();
+        buffer.into_inner()
+    } else {
+        let mut buffer = encase::UniformBuffer::new(Vec::new());
+        buffer.write(&table_data).unwrap();
+        buffer.into_inner()
+    }
+}
+
+fn create_bind_group<M: Material>(
+    render_device: &RenderDevice,
+    layout: &BindGroupLayout,
+    bindings: &[(u32, OwnedBindingResource)],
+    table_buffers: &[(u32, &Buffer)],
+) -> BindGroup {
+    let entries = bindings
+        .iter()
+        .map(|(binding, resource)| BindGroupEntry {
+            binding: *binding,
+            resource: resource.get_binding(),
+        })
+        .chain(
+            table_buffers
+                .iter()
+                .map(|(binding, buffer)| BindGroupEntry {
+                    binding: *binding,
+                    resource: buffer.as_entire_binding(),
+                }),
+        )
+        .collect::<Vec<_>>();
+    render_device.create_bind_group(M::label(), layout, &entries)
+}
+
+fn clone_binding((binding, resource): &(u32, OwnedBindingResource)) -> (u32, OwnedBindingResource) {
+    let resource = match resource {
+        OwnedBindingResource::Buffer(buffer) => OwnedBindingResource::Buffer(buffer.clone()),
+        OwnedBindingResource::TextureView(view) => OwnedBindingResource::TextureView(view.clone()),
+        OwnedBindingResource::Sampler(sampler) => OwnedBindingResource::Sampler(sampler.clone()),
+    };
+    (*binding, resource)
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_asset::AssetId;
+
+    use super::{
+        merge_dirty_ranges, MaterialTableField, MaterialTableRemap, MaterialTableRemapEntry,
+    };
+    use crate::{MaterialBindingsIndex, StandardMaterial};
+
+    #[test]
+    fn remapped_indices_survive_reallocated_slots() {
+        let mut remap = MaterialTableRemap::<StandardMaterial>::default();
+        let a = AssetId::default();
+        let b = AssetId::invalid();
+
+        let index_a = remap.assign(a, 0);
+        let index_b = remap.assign(b, 1);
+        assert_ne!(index_a.index, index_b.index);
+
+        // `a` is prepared again into a new slot before its old one is freed.
+        assert_eq!(remap.assign(a, 2), index_a);
+        remap.free_slot(0);
+        assert_eq!(remap.entries[index_a.index as usize].slot, 2);
+        assert!(!remap.is_assigned(0));
+
+        // `b` waits for its textures, so it has no slot until it's prepared.
+        remap.free_slot(1);
+        assert_eq!(
+            remap.entries[index_b.index as usize].slot,
+            MaterialTableRemapEntry::INVALID_SLOT
+        );
+        assert_eq!(remap.assign(b, 0), index_b);
+    }
+
+    #[test]
+    fn compacted_slots_keep_their_index() {
+        let mut remap = MaterialTableRemap::<StandardMaterial>::default();
+        let a = AssetId::default();
+
+        let index_a = remap.assign(a, 5);
+        remap.move_slot(5, 1);
+        assert_eq!(remap.entries[index_a.index as usize].slot, 1);
+        assert!(remap.is_assigned(1));
+        assert!(!remap.is_assigned(5));
+
+        // Freeing the old slot doesn't affect the moved material.
+        remap.free_slot(5);
+        assert_eq!(remap.entries[index_a.index as usize].slot, 1);
+    }
+
+    #[test]
+    fn released_indices_are_reused_with_a_new_generation() {
+        let mut remap = MaterialTableRemap::<StandardMaterial>::default();
+        let a = AssetId::default();
+        let b = AssetId::invalid();
+
+        let index_a = remap.assign(a, 0);
+        remap.release(a);
+        remap.free_slot(0);
+        let index_b = remap.assign(b, 0);
+        assert_eq!(index_b.index, index_a.index);
+        assert_ne!(index_b.generation, index_a.generation);
+
+        let packed = index_b.packed();
+        assert_eq!(MaterialBindingsIndex::from_packed(packed), index_b);
+        assert_ne!(packed, index_a.packed());
+    }
+
+    #[test]
+    fn dirty_ranges_cover_the_changed_slots() {
+        let dirty = merge_dirty_ranges(None, 64..96);
+        assert_eq!(dirty, 64..96);
+        assert_eq!(merge_dirty_ranges(Some(dirty.clone()), 16..32), 16..96);
+        assert_eq!(merge_dirty_ranges(Some(dirty), 80..88), 64..96);
+        // Writes to buffers must be aligned to 4 bytes.
+        assert_eq!(merge_dirty_ranges(None, 6..10), 4..12);
+    }
+
+    #[test]
+    fn fields_are_written_within_their_slot() {
+        let base_color = MaterialTableField {
+            offset: 16,
+            components: 4,
+        };
+        assert_eq!(base_color.byte_range(0, 48), Some(16..32));
+        assert_eq!(base_color.byte_range(2, 48), Some(112..128));
+
+        // Fields past the end of the data would write into the next slot.
+        assert_eq!(base_color.byte_range(0, 24), None);
+        let empty = MaterialTableField {
+            offset: 0,
+            components: 0,
+        };
+        assert_eq!(empty.byte_range(0, 48), None);
+    }
+}
@@ -12,7 +12,9 @@
     meshlets,
     draw_indirect_args,
     draw_triangle_buffer,
+    meshlet_instance_occlusion_visibility,
 }
+#import bevy_pbr::mesh_types::unpack_mesh_transform
 #import bevy_render::maths::affine3_to_square
 
 /// Culls individual clusters (1 per thread) in two passes (two pass occlusion culling), and outputs a bitmask of which clusters survived.
@@ -44,7 +46,7 @@ fn cull_meshlets(
     // Calculate world-space culling bounding sphere for the cluster
     let instance_uniform = meshlet_instance_uniforms[instance_id];
     let meshlet_id = meshlet_cluster_meshlet_ids[cluster_id];
-    let model = affine3_to_square(instance_uniform.model);
+    let model = affine3_to_square(unpack_mesh_transform(instance_uniform.model));
     let model_scale = max(length(model[0]), max(length(model[1]), length(model[2])));
     let bounding_spheres = meshlet_bounding_spheres[meshlet_id];
     var culling_bounding_sphere_center = model * vec4(bounding_spheres.self_culling.center, 1.0);
@@ -77,7 +79,7 @@ fn cull_meshlets(
 
     // Project the culling bounding sphere to view-space for occlusion culling
 #ifdef MESHLET_FIRST_CULLING_PASS
-    let previous_model = affine3_to_square(instance_uniform.previous_model);
+    let previous_model = affine3_to_square(unpack_mesh_transform(instance_uniform.previous_model));
     let previous_model_scale = max(length(previous_model[0]), max(length(previous_model[1]), length(previous_model[2])));
     culling_bounding_sphere_center = previous_model * vec4(bounding_spheres.self_culling.center, 1.0);
     culling_bounding_sphere_radius = previous_model_scale * bounding_spheres.self_culling.radius;
@@ -119,6 +121,9 @@ fn cull_meshlets(
 
     // Append a list of this cluster's triangles to draw if not culled
     if meshlet_visible {
+        // Mark the instance as visible, to be read back by `GpuVisibility`
+        atomicOr(&meshlet_instance_occlusion_visibility[instance_id / 32u], 1u << (instance_id % 32u));
+
         let meshlet_triangle_count = meshlets[meshlet_id].triangle_count;
         let buffer_start = atomicAdd(&draw_indirect_args.vertex_count, meshlet_triangle_count * 3u) / 3u;
         let cluster_id_packed = cluster_id << 6u;
@@ -1,11 +1,12 @@
 use super::{
     asset::{Meshlet, MeshletBoundingSpheres, MeshletMesh},
+    gpu_visibility::instance_visibility_size,
     persistent_buffer::PersistentGpuBuffer,
 };
 use crate::{
     Material, MeshFlags, MeshTransforms, MeshUniform, NotShadowCaster, NotShadowReceiver,
     PreviousGlobalTransform, PreviousViewData, PreviousViewUniforms, RenderMaterialInstances,
-    ShadowView,
+    ShadowView, UvTransform,
 };
 use bevy_asset::{AssetEvent, AssetId, AssetServer, Assets, Handle, UntypedAssetId};
 use bevy_core_pipeline::core_3d::Camera3d;
@@ -17,6 +18,7 @@ use bevy_ecs::{
     system::{Commands, Local, Query, Res, ResMut, Resource, SystemState},
     world::{FromWorld, World},
 };
+use bevy_math::Affine2;
 use bevy_render::{
     render_resource::{binding_types::*, *},
     renderer::{RenderDevice, RenderQueue},
@@ -52,6 +54,7 @@ pub fn extract_meshlet_meshes(
                     Option<&RenderLayers>,
                     Has<NotShadowReceiver>,
                     Has<NotShadowCaster>,
+                    Option<&UvTransform>,
                 )>,
                 Res<AssetServer>,
                 ResMut<Assets<MeshletMesh>>,
@@ -98,6 +101,7 @@ pub fn extract_meshlet_meshes(
         render_layers,
         not_shadow_receiver,
         not_shadow_caster,
+        uv_transform,
     ) in &instances_query
     {
         // Skip instances with an unloaded MeshletMesh asset
@@ -132,10 +136,11 @@ pub fn extract_meshlet_meshes(
             previous_transform: (&previous_transform).into(),
             flags: flags.bits(),
         };
-        gpu_scene
-            .instance_uniforms
-            .get_mut()
-            .push(MeshUniform::new(&transforms, None));
+        gpu_scene.instance_uniforms.get_mut().push(
+            MeshUniform::new(&transforms, None, instance).with_uv_transform(
+                &uv_transform.map_or(Affine2::IDENTITY, UvTransform::to_affine2),
+            ),
+        );
     }
 }
 
@@ -288,6 +293,21 @@ pub fn prepare_meshlet_per_frame_resources(
             }
         };
 
+    let needed_buffer_size = instance_visibility_size(gpu_scene.instances.len());
+    let instance_occlusion_visibility = match &mut gpu_scene.instance_occlusion_visibility_buffer {
+        Some(buffer) if buffer.size() >= needed_buffer_size => buffer.clone(),
+        slot => {
+            let buffer = render_device.create_buffer(&BufferDescriptor {
+                label: Some("meshlet_instance_occlusion_visibility"),
+                size: needed_buffer_size,
+                usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+            *slot = Some(buffer.clone());
+            buffer
+        }
+    };
+
     let needed_buffer_size =
         gpu_scene.scene_meshlet_count.div_ceil(u32::BITS) as u64 * size_of::<u32>() as u64;
     for (view_entity, view, render_layers, (_, shadow_view)) in &views {
@@ -452,18 +472,21 @@ pub fn prepare_meshlet_per_frame_resources(
             scene_meshlet_count: gpu_scene.scene_meshlet_count,
             second_pass_candidates_buffer,
             instance_visibility,
-            visibility_buffer: not_shadow_view
-                .then(|| texture_cache.get(&render_device, visibility_buffer)),
+            instance_occlusion_visibility: instance_occlusion_visibility.clone(),
+            visibility_buffer: not_shadow_view.then(|| {
+                texture_cache.get_for_view(&render_device, view_entity, visibility_buffer)
+            }),
             visibility_buffer_draw_indirect_args_first,
             visibility_buffer_draw_indirect_args_second,
             visibility_buffer_draw_triangle_buffer: visibility_buffer_draw_triangle_buffer.clone(),
             depth_pyramid_all_mips,
             depth_pyramid_mips,
             previous_depth_pyramid,
-            material_depth_color: not_shadow_view
-                .then(|| texture_cache.get(&render_device, material_depth_color)),
+            material_depth_color: not_shadow_view.then(|| {
+                texture_cache.get_for_view(&render_device, view_entity, material_depth_color)
+            }),
             material_depth: not_shadow_view
-                .then(|| texture_cache.get(&render_device, material_depth)),
+                .then(|| texture_cache.get_for_view(&render_device, view_entity, material_depth)),
         });
     }
 }
@@ -533,6 +556,9 @@ pub fn prepare_meshlet_view_bind_groups(
             &view_resources.previous_depth_pyramid,
             view_uniforms.clone(),
             previous_view_uniforms.clone(),
+            view_resources
+                .instance_occlusion_visibility
+                .as_entire_binding(),
         ));
         let culling_first = render_device.create_bind_group(
             "meshlet_culling_first_bind_group",
@@ -559,6 +585,9 @@ pub fn prepare_meshlet_view_bind_groups(
             &view_resources.depth_pyramid_all_mips,
             view_uniforms.clone(),
             previous_view_uniforms.clone(),
+            view_resources
+                .instance_occlusion_visibility
+                .as_entire_binding(),
         ));
         let culling_second = render_device.create_bind_group(
             "meshlet_culling_second_bind_group",
@@ -686,6 +715,8 @@ pub struct MeshletGpuScene {
     cluster_instance_ids: Option<Buffer>,
     cluster_meshlet_ids: Option<Buffer>,
     second_pass_candidates_buffer: Option<Buffer>,
+    /// Per-instance bit set by the culling passes when any meshlet of the instance is visible.
+    instance_occlusion_visibility_buffer: Option<Buffer>,
     previous_depth_pyramids: EntityHashMap<TextureView>,
     visibility_buffer_draw_triangle_buffer: Option<Buffer>,
 
@@ -743,6 +774,7 @@ impl FromWorld for MeshletGpuScene {
             cluster_instance_ids: None,
             cluster_meshlet_ids: None,
             second_pass_candidates_buffer: None,
+            instance_occlusion_visibility_buffer: None,
             previous_depth_pyramids: EntityHashMap::default(),
             visibility_buffer_draw_triangle_buffer: None,
 
@@ -776,6 +808,7 @@ impl FromWorld for MeshletGpuScene {
                         texture_2d(TextureSampleType::Float { filterable: false }),
                         uniform_buffer::<ViewUniform>(true),
                         uniform_buffer::<PreviousViewData>(true),
+                        storage_buffer_sized(false, None),
                     ),
                 ),
             ),
@@ -937,6 +970,11 @@ impl MeshletGpuScene {
             })
     }
 
+    /// The entity of each instance of the scene this frame, in the order of the instance ids.
+    pub(super) fn instance_entities(&self) -> impl ExactSizeIterator<Item = Entity> + '_ {
+        self.instances.iter().map(|(entity, _, _)| *entity)
+    }
+
     pub fn material_present_in_scene(&self, material_id: &u32) -> bool {
         self.material_ids_present_in_scene.contains(material_id)
     }
@@ -971,6 +1009,9 @@ pub struct MeshletViewResources {
     pub scene_meshlet_count: u32,
     pub second_pass_candidates_buffer: Buffer,
     instance_visibility: Buffer,
+    /// Per-instance bit set by the culling passes of the view when any meshlet of the instance
+    /// is visible, shared by all views.
+    pub instance_occlusion_visibility: Buffer,
     pub visibility_buffer: Option<CachedTexture>,
     pub visibility_buffer_draw_indirect_args_first: Buffer,
     pub visibility_buffer_draw_indirect_args_second: Buffer,
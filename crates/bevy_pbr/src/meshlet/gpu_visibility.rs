@@ -0,0 +1,318 @@
+use super::gpu_scene::{MeshletGpuScene, MeshletViewResources};
+use bevy_core::FrameCount;
+use bevy_ecs::{
+    entity::{Entity, EntityHashMap, EntityHashSet},
+    prelude::*,
+};
+use bevy_reflect::{std_traits::ReflectDefault, Reflect};
+use bevy_render::{
+    camera::ExtractedCamera,
+    render_resource::*,
+    renderer::{RenderContext, RenderDevice},
+    Extract,
+};
+use bevy_utils::tracing::error;
+use crossbeam_channel::{Receiver, Sender};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// The number of readback buffers of each camera, so that the GPU visibility of
+/// a frame can be copied while the ones of the previous frames are mapped.
+const GPU_VISIBILITY_READBACK_BUFFER_COUNT: usize = 3;
+
+/// Add this component to an entity with a [`MeshletMesh`](super::MeshletMesh) to
+/// read back whether the GPU culling of the cameras let any of its meshlets
+/// through.
+///
+/// Unlike [`ViewVisibility`](bevy_render::view::ViewVisibility), which only
+/// accounts for frustum culling on the CPU, this accounts for the frustum, LOD
+/// and occlusion culling of the meshlets, so an entity hidden behind a wall is
+/// not visible. This lets streaming and gameplay systems react to what was
+/// actually rendered.
+///
+/// The visibility is read back asynchronously, so it lags a few frames behind
+/// the frame being simulated. Shadow views don't count.
+#[derive(Component, Reflect, Clone, Copy, Default, Debug, PartialEq, Eq)]
+#[reflect(Component, Default)]
+pub struct GpuVisibility {
+    /// Whether any meshlet of the entity survived the culling of any camera in
+    /// [`GpuVisibility::frame`].
+    pub visible: bool,
+    /// The [`FrameCount`] of the frame the visibility was rendered in.
+    pub frame: u32,
+    /// The [`FrameCount`] of the last frame the entity was visible in, if any.
+    pub last_visible_frame: Option<u32>,
+}
+
+impl GpuVisibility {
+    /// Merges the visibility of the entity in a camera, ignoring the frames
+    /// older than the current one.
+    fn update(&mut self, frame: u32, visible: bool) {
+        // Buffers may finish mapping out of order, never go back in time.
+        if is_newer_frame(self.frame, frame) {
+            return;
+        }
+        if is_newer_frame(frame, self.frame) {
+            self.frame = frame;
+            self.visible = visible;
+        } else {
+            // Another camera rendered the same frame.
+            self.visible |= visible;
+        }
+        if visible {
+            self.last_visible_frame = Some(frame);
+        }
+    }
+}
+
+/// The entities that survived the culling of a camera in a frame, as read back
+/// by the render world.
+pub(super) struct GpuVisibilityResults {
+    frame: u32,
+    visible: EntityHashSet,
+}
+
+/// Receives the [`GpuVisibilityResults`] of the render world.
+#[derive(Resource)]
+pub(super) struct GpuVisibilityReceiver(pub Receiver<GpuVisibilityResults>);
+
+/// Sends the [`GpuVisibilityResults`] read back from the GPU to the main world.
+#[derive(Resource)]
+pub(super) struct GpuVisibilitySender(pub Sender<GpuVisibilityResults>);
+
+/// Updates the [`GpuVisibility`] of the entities from the results sent by the
+/// render world.
+pub(super) fn receive_gpu_visibility(
+    receiver: Res<GpuVisibilityReceiver>,
+    mut entities: Query<(Entity, &mut GpuVisibility)>,
+) {
+    for results in receiver.0.try_iter() {
+        for (entity, mut gpu_visibility) in &mut entities {
+            let mut updated = *gpu_visibility;
+            updated.update(results.frame, results.visible.contains(&entity));
+            gpu_visibility.set_if_neq(updated);
+        }
+    }
+}
+
+/// Whether any entity has a [`GpuVisibility`], without which nothing is read
+/// back.
+#[derive(Resource, Default)]
+pub(super) struct GpuVisibilityRequested(bool);
+
+pub(super) fn extract_gpu_visibility_requested(
+    mut requested: ResMut<GpuVisibilityRequested>,
+    entities: Extract<Query<(), With<GpuVisibility>>>,
+) {
+    requested.0 = !entities.is_empty();
+}
+
+/// A buffer that the per-instance visibility bits of a frame are copied into so
+/// that they can be read back.
+struct GpuVisibilityReadback {
+    buffer: ReadbackBuffer,
+    /// The number of bytes the buffer has room for.
+    capacity: u64,
+    /// The entity of each instance of the frame, in the order of the bits.
+    instances: Vec<Entity>,
+    frame: u32,
+    /// Whether the raster node copied into the buffer, which it doesn't when the meshlet
+    /// pipelines aren't ready.
+    copied: AtomicBool,
+}
+
+impl GpuVisibilityReadback {
+    fn new(render_device: &RenderDevice, capacity: u64) -> Self {
+        Self {
+            buffer: ReadbackBuffer::new(
+                render_device,
+                "meshlet_gpu_visibility_readback_buffer",
+                capacity,
+            ),
+            capacity,
+            instances: Vec::new(),
+            frame: 0,
+            copied: AtomicBool::new(false),
+        }
+    }
+
+    /// The number of bytes of visibility bits copied into the buffer.
+    fn len(&self) -> u64 {
+        instance_visibility_size(self.instances.len())
+    }
+
+    /// Collects the visible instances out of the mapped buffer, and unmaps it.
+    fn read(&mut self) -> GpuVisibilityResults {
+        let len = self.len() as usize;
+        let visible = self.buffer.read(|data| {
+            let bits = bytemuck::cast_slice::<u8, u32>(&data[..len]);
+            self.instances
+                .drain(..)
+                .enumerate()
+                .filter(|(index, _)| bits[index / 32] & (1 << (index % 32)) != 0)
+                .map(|(_, entity)| entity)
+                .collect()
+        });
+
+        GpuVisibilityResults {
+            frame: self.frame,
+            visible,
+        }
+    }
+}
+
+/// The size in bytes of the visibility bits of `instance_count` instances.
+pub(super) fn instance_visibility_size(instance_count: usize) -> u64 {
+    instance_count.div_ceil(32).max(1) as u64 * 4
+}
+
+/// The ring of readback buffers of a camera.
+#[derive(Default)]
+struct GpuVisibilityReadbacks {
+    readbacks: Vec<GpuVisibilityReadback>,
+    /// The index of the readback buffer the next frame copies into, if it is
+    /// free.
+    next: usize,
+    /// The readback buffer copied into this frame, if any.
+    current: Option<usize>,
+}
+
+/// The readback buffers of every camera drawing meshlets.
+#[derive(Resource, Default)]
+pub(super) struct MeshletGpuVisibilityReadbacks {
+    views: EntityHashMap<GpuVisibilityReadbacks>,
+}
+
+/// Claims a readback buffer for the cameras drawing meshlets, if any entity has
+/// a [`GpuVisibility`].
+pub(super) fn prepare_meshlet_gpu_visibility_readbacks(
+    requested: Res<GpuVisibilityRequested>,
+    gpu_scene: Res<MeshletGpuScene>,
+    render_device: Res<RenderDevice>,
+    frame_count: Res<FrameCount>,
+    mut readbacks: ResMut<MeshletGpuVisibilityReadbacks>,
+    views: Query<Entity, (With<ExtractedCamera>, With<MeshletViewResources>)>,
+) {
+    readbacks.views.retain(|view, _| views.contains(*view));
+    for view_readbacks in readbacks.views.values_mut() {
+        view_readbacks.current = None;
+    }
+    if !requested.0 {
+        return;
+    }
+
+    let size = instance_visibility_size(gpu_scene.instance_entities().len());
+    for view in &views {
+        let view_readbacks = readbacks.views.entry(view).or_default();
+        let index = view_readbacks.next;
+        match view_readbacks.readbacks.get(index) {
+            None => view_readbacks.readbacks.push(GpuVisibilityReadback::new(
+                &render_device,
+                size.next_power_of_two(),
+            )),
+            Some(readback) if !readback.buffer.is_free() => continue,
+            Some(readback) if readback.capacity < size => {
+                view_readbacks.readbacks[index] =
+                    GpuVisibilityReadback::new(&render_device, size.next_power_of_two());
+            }
+            Some(_) => {}
+        }
+        let readback = &mut view_readbacks.readbacks[index];
+        readback.buffer.start_copy();
+        *readback.copied.get_mut() = false;
+        readback.frame = frame_count.0;
+        readback.instances.clear();
+        readback.instances.extend(gpu_scene.instance_entities());
+        view_readbacks.current = Some(index);
+        view_readbacks.next = (index + 1) % GPU_VISIBILITY_READBACK_BUFFER_COUNT;
+    }
+}
+
+/// Copies the per-instance visibility bits of a camera into its readback
+/// buffer of this frame, if it has one.
+pub(super) fn copy_meshlet_gpu_visibility(
+    render_context: &mut RenderContext,
+    world: &World,
+    view: Entity,
+    view_resources: &MeshletViewResources,
+) {
+    let Some(view_readbacks) = world
+        .resource::<MeshletGpuVisibilityReadbacks>()
+        .views
+        .get(&view)
+    else {
+        return;
+    };
+    let Some(readback) = view_readbacks
+        .current
+        .and_then(|index| view_readbacks.readbacks.get(index))
+    else {
+        return;
+    };
+    render_context.command_encoder().copy_buffer_to_buffer(
+        &view_resources.instance_occlusion_visibility,
+        0,
+        &readback.buffer,
+        0,
+        readback.len(),
+    );
+    readback.copied.store(true, Ordering::Relaxed);
+}
+
+/// Starts mapping the readback buffers that were copied into this frame, and
+/// sends the contents of the buffers that finished mapping to the main world.
+pub(super) fn map_meshlet_gpu_visibility_readbacks(
+    render_device: Res<RenderDevice>,
+    mut readbacks: ResMut<MeshletGpuVisibilityReadbacks>,
+    sender: Res<GpuVisibilitySender>,
+) {
+    render_device.poll(Maintain::Poll);
+
+    for view_readbacks in readbacks.views.values_mut() {
+        for readback in &mut view_readbacks.readbacks {
+            if !*readback.copied.get_mut() {
+                readback.buffer.cancel_copy();
+            }
+            match readback.buffer.poll() {
+                None => {}
+                Some(Err(err)) => error!("Failed to map meshlet GPU visibility buffer: {err}"),
+                Some(Ok(())) => {
+                    // The main world may have been dropped during shutdown.
+                    let _ = sender.0.send(readback.read());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GpuVisibility;
+
+    #[test]
+    fn gpu_visibility_merges_cameras_and_ignores_older_frames() {
+        let mut gpu_visibility = GpuVisibility::default();
+        gpu_visibility.update(4, true);
+        gpu_visibility.update(5, false);
+        assert_eq!(
+            gpu_visibility,
+            GpuVisibility {
+                visible: false,
+                frame: 5,
+                last_visible_frame: Some(4),
+            }
+        );
+
+        // A second camera of the same frame sees it, a late buffer of an
+        // older frame doesn't count.
+        gpu_visibility.update(5, true);
+        gpu_visibility.update(3, false);
+        assert_eq!(
+            gpu_visibility,
+            GpuVisibility {
+                visible: true,
+                frame: 5,
+                last_visible_frame: Some(5),
+            }
+        );
+    }
+}
@@ -8,7 +8,7 @@ use bevy_core_pipeline::{
 };
 use bevy_derive::{Deref, DerefMut};
 use bevy_render::{
-    camera::TemporalJitter,
+    camera::{ExtractedCamera, Projection, TemporalJitter},
     mesh::{Mesh, MeshVertexBufferLayout, MeshVertexBufferLayoutRef, MeshVertexBufferLayouts},
     render_asset::RenderAssets,
     render_resource::*,
@@ -34,10 +34,12 @@ pub fn prepare_material_meshlet_meshes_main_opaque_pass<M: Material>(
     render_material_instances: Res<RenderMaterialInstances<M>>,
     asset_server: Res<AssetServer>,
     mut mesh_vertex_buffer_layouts: ResMut<MeshVertexBufferLayouts>,
+    mesh_view_extra_bindings: Res<MeshViewExtraBindings>,
     mut views: Query<
         (
+            Entity,
             &mut MeshletViewMaterialsMainOpaquePass,
-            &ExtractedView,
+            (&ExtractedView, Option<&ExtractedCamera>),
             Option<&Tonemapping>,
             Option<&DebandDither>,
             Option<&ShadowFilteringMethod>,
@@ -61,8 +63,9 @@ pub fn prepare_material_meshlet_meshes_main_opaque_pass<M: Material>(
     let fake_vertex_buffer_layout = &fake_vertex_buffer_layout(&mut mesh_vertex_buffer_layouts);
 
     for (
+        view_entity,
         mut materials,
-        view,
+        (view, camera),
         tonemapping,
         dither,
         shadow_filter_method,
@@ -74,8 +77,11 @@ pub fn prepare_material_meshlet_meshes_main_opaque_pass<M: Material>(
         has_irradiance_volumes,
     ) in &mut views
     {
-        let mut view_key =
-            MeshPipelineKey::from_msaa_samples(1) | MeshPipelineKey::from_hdr(view.hdr);
+        let material_view_key = M::view_key(&MaterialView::new(view, projection, camera));
+        let mut view_key = MeshPipelineKey::from_msaa_samples(1)
+            | MeshPipelineKey::from_hdr(view.hdr)
+            | MeshPipelineKey::from_hdr_format(view.hdr_format)
+            | mesh_view_extra_bindings.view_key(view_entity);
 
         if normal_prepass {
             view_key |= MeshPipelineKey::NORMAL_PREPASS;
@@ -144,14 +150,32 @@ pub fn prepare_material_meshlet_meshes_main_opaque_pass<M: Material>(
 
             if material.properties.alpha_mode != AlphaMode::Opaque
                 || material.properties.reads_view_transmission_texture
+                || material
+                    .properties
+                    .mesh_pipeline_key_bits
+                    .contains(MeshPipelineKey::DECAL)
             {
                 continue;
             }
 
+            let mut mesh_key = view_key;
+            if !material.properties.deband_dither {
+                mesh_key.remove(MeshPipelineKey::DEBAND_DITHER);
+            }
+
             let Ok(material_pipeline_descriptor) = material_pipeline.specialize(
                 MaterialPipelineKey {
-                    mesh_key: view_key,
+                    mesh_key,
                     bind_group_data: material.key.clone(),
+                    pass_index: 0,
+                    stencil: None,
+                    blend_state: None,
+                    lod: None,
+                    shader_defs: material.properties.shader_defs.clone(),
+                    cull_mode: material.properties.cull_mode,
+                    double_sided_normals: material.properties.double_sided_normals,
+                    rasterizer_depth_bias: material.properties.rasterizer_depth_bias,
+                    view_key: material_view_key,
                 },
                 fake_vertex_buffer_layout,
             ) else {
@@ -235,6 +259,7 @@ pub fn prepare_material_meshlet_meshes_prepass<M: Material>(
             &mut MeshletViewMaterialsDeferredGBufferPrepass,
             &ExtractedView,
             AnyOf<(&NormalPrepass, &MotionVectorPrepass, &DeferredPrepass)>,
+            (Option<&Projection>, Option<&ExtractedCamera>),
         ),
         With<Camera3d>,
     >,
@@ -248,10 +273,13 @@ pub fn prepare_material_meshlet_meshes_prepass<M: Material>(
         mut deferred_materials,
         view,
         (normal_prepass, motion_vector_prepass, deferred_prepass),
+        (projection, camera),
     ) in &mut views
     {
-        let mut view_key =
-            MeshPipelineKey::from_msaa_samples(1) | MeshPipelineKey::from_hdr(view.hdr);
+        let material_view_key = M::view_key(&MaterialView::new(view, projection, camera));
+        let mut view_key = MeshPipelineKey::from_msaa_samples(1)
+            | MeshPipelineKey::from_hdr(view.hdr)
+            | MeshPipelineKey::from_hdr_format(view.hdr_format);
 
         if normal_prepass.is_some() {
             view_key |= MeshPipelineKey::NORMAL_PREPASS;
@@ -269,6 +297,10 @@ pub fn prepare_material_meshlet_meshes_prepass<M: Material>(
 
             if material.properties.alpha_mode != AlphaMode::Opaque
                 || material.properties.reads_view_transmission_texture
+                || material
+                    .properties
+                    .mesh_pipeline_key_bits
+                    .contains(MeshPipelineKey::DECAL)
             {
                 continue;
             }
@@ -287,6 +319,15 @@ pub fn prepare_material_meshlet_meshes_prepass<M: Material>(
                 MaterialPipelineKey {
                     mesh_key: view_key,
                     bind_group_data: material.key.clone(),
+                    pass_index: 0,
+                    stencil: None,
+                    blend_state: None,
+                    lod: None,
+                    shader_defs: material.properties.shader_defs.clone(),
+                    cull_mode: material.properties.cull_mode,
+                    double_sided_normals: material.properties.double_sided_normals,
+                    rasterizer_depth_bias: material.properties.rasterizer_depth_bias,
+                    view_key: material_view_key,
                 },
                 fake_vertex_buffer_layout,
             ) else {
@@ -72,6 +72,7 @@ var<push_constant> cluster_count: u32;
 @group(0) @binding(9) var depth_pyramid: texture_2d<f32>; // From the end of the last frame for the first culling pass, and from the first raster pass for the second culling pass
 @group(0) @binding(10) var<uniform> view: View;
 @group(0) @binding(11) var<uniform> previous_view: PreviousViewUniforms;
+@group(0) @binding(12) var<storage, read_write> meshlet_instance_occlusion_visibility: array<atomic<u32>>; // 1 bit per entity instance, packed as a bitmask
 
 fn should_cull_instance(instance_id: u32) -> bool {
     let bit_offset = instance_id % 32u;
@@ -4,6 +4,7 @@ mod asset;
 #[cfg(feature = "meshlet_processor")]
 mod from_mesh;
 mod gpu_scene;
+mod gpu_visibility;
 mod material_draw_nodes;
 mod material_draw_prepare;
 mod persistent_buffer;
@@ -33,12 +34,18 @@ pub(crate) use self::{
 pub use self::asset::*;
 #[cfg(feature = "meshlet_processor")]
 pub use self::from_mesh::MeshToMeshletMeshConversionError;
+pub use self::gpu_visibility::GpuVisibility;
 
 use self::{
     gpu_scene::{
         extract_meshlet_meshes, perform_pending_meshlet_mesh_writes,
         prepare_meshlet_per_frame_resources, prepare_meshlet_view_bind_groups,
     },
+    gpu_visibility::{
+        extract_gpu_visibility_requested, map_meshlet_gpu_visibility_readbacks,
+        prepare_meshlet_gpu_visibility_readbacks, receive_gpu_visibility, GpuVisibilityReceiver,
+        GpuVisibilityRequested, GpuVisibilitySender, MeshletGpuVisibilityReadbacks,
+    },
     graph::NodeMeshlet,
     material_draw_nodes::{
         MeshletDeferredGBufferPrepassNode, MeshletMainOpaquePass3dNode, MeshletPrepassNode,
@@ -55,7 +62,7 @@ use self::{
     visibility_buffer_raster_node::MeshletVisibilityBufferRasterPassNode,
 };
 use crate::{graph::NodePbr, Material};
-use bevy_app::{App, Plugin, PostUpdate};
+use bevy_app::{App, Plugin, PostUpdate, PreUpdate};
 use bevy_asset::{load_internal_asset, AssetApp, Handle};
 use bevy_core_pipeline::{
     core_3d::{
@@ -164,13 +171,22 @@ impl Plugin for MeshletPlugin {
             Shader::from_wgsl
         );
 
+        let (sender, receiver) = crossbeam_channel::unbounded();
+
         app.init_asset::<MeshletMesh>()
             .register_asset_loader(MeshletMeshSaverLoad)
+            .register_type::<GpuVisibility>()
             .insert_resource(Msaa::Off)
+            .insert_resource(GpuVisibilityReceiver(receiver))
+            .add_systems(PreUpdate, receive_gpu_visibility)
             .add_systems(
                 PostUpdate,
                 check_visibility::<WithMeshletMesh>.in_set(VisibilitySystems::CheckVisibility),
             );
+
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app.insert_resource(GpuVisibilitySender(sender));
+        }
     }
 
     fn finish(&self, app: &mut App) {
@@ -224,7 +240,12 @@ impl Plugin for MeshletPlugin {
             )
             .init_resource::<MeshletGpuScene>()
             .init_resource::<MeshletPipelines>()
-            .add_systems(ExtractSchedule, extract_meshlet_meshes)
+            .init_resource::<GpuVisibilityRequested>()
+            .init_resource::<MeshletGpuVisibilityReadbacks>()
+            .add_systems(
+                ExtractSchedule,
+                (extract_meshlet_meshes, extract_gpu_visibility_requested),
+            )
             .add_systems(
                 Render,
                 (
@@ -234,6 +255,8 @@ impl Plugin for MeshletPlugin {
                         .in_set(RenderSet::ManageViews),
                     prepare_meshlet_per_frame_resources.in_set(RenderSet::PrepareResources),
                     prepare_meshlet_view_bind_groups.in_set(RenderSet::PrepareBindGroups),
+                    prepare_meshlet_gpu_visibility_readbacks.in_set(RenderSet::PrepareBindGroups),
+                    map_meshlet_gpu_visibility_readbacks.in_set(RenderSet::Cleanup),
                 ),
             );
     }
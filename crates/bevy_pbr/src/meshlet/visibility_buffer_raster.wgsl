@@ -13,6 +13,7 @@
         unpack_meshlet_vertex,
     },
     mesh_functions::mesh_position_local_to_world,
+    mesh_types::unpack_mesh_transform,
 }
 #import bevy_render::maths::affine3_to_square
 
@@ -50,7 +51,7 @@ fn vertex(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
     let instance_id = meshlet_cluster_instance_ids[cluster_id];
     let instance_uniform = meshlet_instance_uniforms[instance_id];
 
-    let model = affine3_to_square(instance_uniform.model);
+    let model = affine3_to_square(unpack_mesh_transform(instance_uniform.model));
     let world_position = mesh_position_local_to_world(model, vec4(vertex.position, 1.0));
     var clip_position = view.view_proj * vec4(world_position.xyz, 1.0);
 #ifdef DEPTH_CLAMP_ORTHO
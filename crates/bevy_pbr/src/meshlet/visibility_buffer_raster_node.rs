@@ -1,5 +1,6 @@
 use super::{
     gpu_scene::{MeshletViewBindGroups, MeshletViewResources},
+    gpu_visibility::copy_meshlet_gpu_visibility,
     pipelines::MeshletPipelines,
 };
 use crate::{LightEntity, PreviousViewUniformOffset, ShadowView, ViewLightEntities};
@@ -103,6 +104,11 @@ impl Node for MeshletVisibilityBufferRasterPassNode {
             0,
             None,
         );
+        render_context.command_encoder().clear_buffer(
+            &meshlet_view_resources.instance_occlusion_visibility,
+            0,
+            None,
+        );
         if first_node {
             fill_cluster_buffers_pass(
                 render_context,
@@ -147,6 +153,12 @@ impl Node for MeshletVisibilityBufferRasterPassNode {
             culling_second_pipeline,
             thread_per_cluster_workgroups,
         );
+        copy_meshlet_gpu_visibility(
+            render_context,
+            world,
+            graph.view_entity(),
+            meshlet_view_resources,
+        );
         raster_pass(
             false,
             render_context,
@@ -200,6 +212,11 @@ impl Node for MeshletVisibilityBufferRasterPassNode {
                 0,
                 None,
             );
+            render_context.command_encoder().clear_buffer(
+                &meshlet_view_resources.instance_occlusion_visibility,
+                0,
+                None,
+            );
             cull_pass(
                 "culling_first",
                 render_context,
@@ -14,10 +14,13 @@
     },
     mesh_view_bindings::view,
     mesh_functions::mesh_position_local_to_world,
-    mesh_types::MESH_FLAGS_SIGN_DETERMINANT_MODEL_3X3_BIT,
+    mesh_types::{
+        MESH_FLAGS_SIGN_DETERMINANT_MODEL_3X3_BIT, unpack_mesh_transform,
+        mesh_inverse_transpose_model,
+    },
     view_transformations::{position_world_to_clip, frag_coord_to_ndc},
 }
-#import bevy_render::maths::{affine3_to_square, mat2x4_f32_to_mat3x3_unpack}
+#import bevy_render::maths::affine3_to_square
 
 #ifdef PREPASS_FRAGMENT
 #ifdef MOTION_VECTOR_PREPASS
@@ -109,7 +112,7 @@ fn resolve_vertex_output(frag_coord: vec4<f32>) -> VertexOutput {
 
     let instance_id = meshlet_cluster_instance_ids[cluster_id];
     let instance_uniform = meshlet_instance_uniforms[instance_id];
-    let model = affine3_to_square(instance_uniform.model);
+    let model = affine3_to_square(unpack_mesh_transform(instance_uniform.model));
 
     let world_position_1 = mesh_position_local_to_world(model, vec4(vertex_1.position, 1.0));
     let world_position_2 = mesh_position_local_to_world(model, vec4(vertex_2.position, 1.0));
@@ -127,15 +130,13 @@ fn resolve_vertex_output(frag_coord: vec4<f32>) -> VertexOutput {
 
     let world_position = mat3x4(world_position_1, world_position_2, world_position_3) * partial_derivatives.barycentrics;
     let vertex_normal = mat3x3(vertex_1.normal, vertex_2.normal, vertex_3.normal) * partial_derivatives.barycentrics;
-    let world_normal = normalize(
-        mat2x4_f32_to_mat3x3_unpack(
-            instance_uniform.inverse_transpose_model_a,
-            instance_uniform.inverse_transpose_model_b,
-        ) * vertex_normal
-    );
-    let uv = mat3x2(vertex_1.uv, vertex_2.uv, vertex_3.uv) * partial_derivatives.barycentrics;
-    let ddx_uv = mat3x2(vertex_1.uv, vertex_2.uv, vertex_3.uv) * partial_derivatives.ddx;
-    let ddy_uv = mat3x2(vertex_1.uv, vertex_2.uv, vertex_3.uv) * partial_derivatives.ddy;
+    let world_normal = normalize(mesh_inverse_transpose_model(instance_uniform) * vertex_normal);
+    // The `UvTransform` of the instance is affine, so its matrix alone
+    // transforms the derivatives.
+    let uv_transform = mat2x2(instance_uniform.uv_transform_a.xy, instance_uniform.uv_transform_a.zw);
+    let uv = uv_transform * (mat3x2(vertex_1.uv, vertex_2.uv, vertex_3.uv) * partial_derivatives.barycentrics) + instance_uniform.uv_transform_b;
+    let ddx_uv = uv_transform * (mat3x2(vertex_1.uv, vertex_2.uv, vertex_3.uv) * partial_derivatives.ddx);
+    let ddy_uv = uv_transform * (mat3x2(vertex_1.uv, vertex_2.uv, vertex_3.uv) * partial_derivatives.ddy);
     let vertex_tangent = mat3x4(vertex_1.tangent, vertex_2.tangent, vertex_3.tangent) * partial_derivatives.barycentrics;
     let world_tangent = vec4(
         normalize(
@@ -150,7 +151,7 @@ fn resolve_vertex_output(frag_coord: vec4<f32>) -> VertexOutput {
 
 #ifdef PREPASS_FRAGMENT
 #ifdef MOTION_VECTOR_PREPASS
-    let previous_model = affine3_to_square(instance_uniform.previous_model);
+    let previous_model = affine3_to_square(unpack_mesh_transform(instance_uniform.previous_model));
     let previous_world_position_1 = mesh_position_local_to_world(previous_model, vec4(vertex_1.position, 1.0));
     let previous_world_position_2 = mesh_position_local_to_world(previous_model, vec4(vertex_2.position, 1.0));
     let previous_world_position_3 = mesh_position_local_to_world(previous_model, vec4(vertex_3.position, 1.0));
@@ -367,6 +367,11 @@ pub struct StandardMaterial {
     #[reflect(ignore)]
     pub cull_mode: Option<Face>,
 
+    /// How the vertex colors of meshes are applied to the base color of this material.
+    ///
+    /// Defaults to [`VertexColorMode::Multiply`].
+    pub vertex_color_mode: VertexColorMode,
+
     /// Whether to apply only the base color to this material.
     ///
     /// Normals, occlusion textures, roughness, metallic, reflectance, emissive,
@@ -582,6 +587,7 @@ impl Default for StandardMaterial {
             flip_normal_map_y: false,
             double_sided: false,
             cull_mode: Some(Face::Back),
+            vertex_color_mode: VertexColorMode::Multiply,
             unlit: false,
             fog_enabled: true,
             alpha_mode: AlphaMode::Opaque,
@@ -649,6 +655,7 @@ bitflags::bitflags! {
         const ALPHA_MODE_ADD             = 4 << Self::ALPHA_MODE_SHIFT_BITS;                          //   Right now only values 0–5 are used, which still gives
         const ALPHA_MODE_MULTIPLY        = 5 << Self::ALPHA_MODE_SHIFT_BITS;                          // ← us "room" for two more modes without adding more bits
         const ALPHA_MODE_ALPHA_TO_COVERAGE = 6 << Self::ALPHA_MODE_SHIFT_BITS;
+        const ALPHA_MODE_HASHED          = 7 << Self::ALPHA_MODE_SHIFT_BITS;
         const NONE                       = 0;
         const UNINITIALIZED              = 0xFFFF;
     }
@@ -787,6 +794,7 @@ impl AsBindGroupShaderType<StandardMaterialUniform> for StandardMaterial {
             AlphaMode::AlphaToCoverage => {
                 flags |= StandardMaterialFlags::ALPHA_MODE_ALPHA_TO_COVERAGE;
             }
+            AlphaMode::Hashed => flags |= StandardMaterialFlags::ALPHA_MODE_HASHED,
         };
 
         if self.attenuation_distance.is_finite() {
@@ -882,21 +890,27 @@ impl Material for StandardMaterial {
         self.alpha_mode
     }
 
+    #[inline]
+    fn vertex_color_mode(&self) -> VertexColorMode {
+        self.vertex_color_mode
+    }
+
     #[inline]
     fn opaque_render_method(&self) -> OpaqueRendererMethod {
-        match self.opaque_render_method {
-            // For now, diffuse transmission doesn't work under deferred rendering as we don't pack
-            // the required data into the GBuffer. If this material is set to `Auto`, we report it as
-            // `Forward` so that it's rendered correctly, even when the `DefaultOpaqueRendererMethod`
-            // is set to `Deferred`.
-            //
-            // If the developer explicitly sets the `OpaqueRendererMethod` to `Deferred`, we assume
-            // they know what they're doing and don't override it.
-            OpaqueRendererMethod::Auto if self.diffuse_transmission > 0.0 => {
-                OpaqueRendererMethod::Forward
-            }
-            other => other,
+        self.opaque_render_method
+    }
+
+    fn deferred_unsupported_features(&self) -> Vec<&'static str> {
+        // For now, transmission doesn't work under deferred rendering as we don't pack the
+        // required data into the GBuffer.
+        let mut features = Vec::new();
+        if self.diffuse_transmission > 0.0 {
+            features.push("diffuse transmission");
+        }
+        if self.specular_transmission > 0.0 {
+            features.push("specular transmission");
         }
+        features
     }
 
     #[inline]
@@ -909,6 +923,15 @@ impl Material for StandardMaterial {
         self.specular_transmission > 0.0
     }
 
+    fn colored_shadow(&self) -> bool {
+        self.specular_transmission > 0.0
+            || self.diffuse_transmission > 0.0
+            || matches!(
+                self.alpha_mode,
+                AlphaMode::Blend | AlphaMode::Premultiplied | AlphaMode::Add | AlphaMode::Multiply
+            )
+    }
+
     fn prepass_fragment_shader() -> ShaderRef {
         PBR_PREPASS_SHADER_HANDLE.into()
     }
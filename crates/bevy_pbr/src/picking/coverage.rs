@@ -0,0 +1,630 @@
+use bevy_app::{App, Plugin, PreUpdate};
+use bevy_asset::{load_internal_asset, Handle};
+use bevy_core::FrameCount;
+use bevy_core_pipeline::core_3d::{
+    graph::{Core3d, Node3d},
+    Camera3d,
+};
+use bevy_ecs::{
+    entity::{Entities, EntityHashMap},
+    prelude::*,
+    query::QueryItem,
+};
+use bevy_math::UVec2;
+use bevy_reflect::{std_traits::ReflectDefault, Reflect};
+use bevy_render::{
+    camera::Camera,
+    diagnostic::RecordDiagnostics,
+    render_graph::{NodeRunError, RenderGraphApp, RenderGraphContext, ViewNode, ViewNodeRunner},
+    render_resource::{
+        binding_types::{storage_buffer_sized, texture_2d},
+        *,
+    },
+    renderer::{RenderContext, RenderDevice},
+    view::{ExtractedView, VisibleEntities, WithMesh},
+    Extract, ExtractSchedule, Render, RenderApp, RenderSet,
+};
+#[cfg(feature = "trace")]
+use bevy_utils::tracing::info_span;
+use bevy_utils::{default, tracing::error, warn_once};
+use crossbeam_channel::{Receiver, Sender};
+
+use super::{ExtractedGpuPickingCamera, GpuPicking, GpuPickingEntities, VisibleMeshIdTextures};
+use crate::graph::NodePbr;
+
+pub const GPU_PICKING_COVERAGE_SHADER_HANDLE: Handle<Shader> =
+    Handle::weak_from_u128(11834952581836561643);
+
+/// Counts the pixels covered by each entity for every [`GpuPicking`] camera
+/// with the [`GpuPickingCoverage`] component, and reads the counts back to the
+/// [`GpuPickingCoverageFrames`] resource.
+///
+/// This plugin isn't added by default, and requires the
+/// [`GpuPickingPlugin`](super::GpuPickingPlugin). The pixels are counted by a
+/// compute shader, so coverage isn't available on WebGL2.
+pub struct GpuPickingCoveragePlugin;
+
+impl Plugin for GpuPickingCoveragePlugin {
+    fn build(&self, app: &mut App) {
+        load_internal_asset!(
+            app,
+            GPU_PICKING_COVERAGE_SHADER_HANDLE,
+            "gpu_picking_coverage.wgsl",
+            Shader::from_wgsl
+        );
+
+        let (sender, receiver) = crossbeam_channel::unbounded();
+
+        app.register_type::<GpuPickingCoverage>()
+            .insert_resource(GpuPickingCoverageFrames {
+                receiver,
+                frames: default(),
+            })
+            .add_systems(PreUpdate, receive_gpu_picking_coverage_frames);
+
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+
+        render_app
+            .insert_resource(GpuPickingCoverageSender(sender))
+            .init_resource::<GpuPickingCoverageReadbacks>()
+            .add_systems(ExtractSchedule, extract_gpu_picking_coverage_cameras)
+            .add_systems(
+                Render,
+                (
+                    prepare_gpu_picking_coverage_readbacks.in_set(RenderSet::PrepareResources),
+                    map_gpu_picking_coverage_readbacks.in_set(RenderSet::Cleanup),
+                ),
+            )
+            .add_render_graph_node::<ViewNodeRunner<GpuPickingCoverageNode>>(
+                Core3d,
+                NodePbr::GpuPickingCoverage,
+            )
+            .add_render_graph_edges(
+                Core3d,
+                (
+                    NodePbr::GpuPicking,
+                    NodePbr::GpuPickingCoverage,
+                    Node3d::EndMainPassPostProcessing,
+                ),
+            );
+    }
+
+    fn finish(&self, app: &mut App) {
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+
+        render_app.init_resource::<GpuPickingCoveragePipeline>();
+    }
+}
+
+/// Also counts the pixels covered by each entity for a [`GpuPicking`] camera,
+/// for gameplay that depends on how visible entities are, like target
+/// priorities, or for analytics and heatmaps. Read the counts from
+/// [`GpuPickingCoverageFrames`].
+///
+/// This requires the [`GpuPickingCoveragePlugin`]. The pixels are counted from
+/// the entity index texture of the camera, so an entity only covers the pixels
+/// where it would be picked: not where it's hidden behind other entities, and
+/// not where it's excluded by [`PickingVisibility`](super::PickingVisibility),
+/// [`PickingLayers`](super::PickingLayers) or the alpha tests of
+/// [`GpuPicking`].
+#[derive(Component, Reflect, Clone, Copy, Default, Debug)]
+#[reflect(Component, Default)]
+pub struct GpuPickingCoverage;
+
+/// The number of pixels covered by each entity in a single frame of a
+/// [`GpuPickingCoverage`] camera, read back from the GPU.
+#[derive(Clone, Debug)]
+pub struct GpuPickingCoverageFrame {
+    /// The camera that rendered this frame.
+    pub camera: Entity,
+    /// The [`FrameCount`] of the frame this was rendered in.
+    pub frame: u32,
+    /// The number of pixels in the viewport of the camera, at the resolution
+    /// of the picking texture. See [`GpuPicking::scale`].
+    pub viewport_pixels: u32,
+    /// The number of pixels covered by each entity that covers any, at the
+    /// resolution of the picking texture.
+    pub pixels: EntityHashMap<u32>,
+}
+
+impl GpuPickingCoverageFrame {
+    /// Returns the number of pixels covered by `entity`, which is zero if it
+    /// isn't visible at all.
+    pub fn pixels(&self, entity: Entity) -> u32 {
+        self.pixels.get(&entity).copied().unwrap_or_default()
+    }
+
+    /// Returns the fraction of the viewport covered by `entity`, between 0 and
+    /// 1.
+    pub fn fraction(&self, entity: Entity) -> f32 {
+        if self.viewport_pixels == 0 {
+            return 0.0;
+        }
+        self.pixels(entity) as f32 / self.viewport_pixels as f32
+    }
+
+    /// Iterates over the entities that cover any pixel, and their number of
+    /// pixels.
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, u32)> + '_ {
+        self.pixels
+            .iter()
+            .map(|(entity, pixels)| (*entity, *pixels))
+    }
+}
+
+/// The pixels covered by each entity index, as read back by the render world.
+struct GpuPickingCoverageCounts {
+    camera: Entity,
+    frame: u32,
+    viewport_pixels: u32,
+    /// The entity indices that cover any pixel, and their number of pixels.
+    pixels: Vec<(u32, u32)>,
+    /// The entities that were drawn in the frame.
+    entities: GpuPickingEntities,
+}
+
+impl GpuPickingCoverageCounts {
+    /// Resolves the entity indices to the entities that were drawn, leaving out
+    /// the entities that were despawned since the frame was rendered.
+    fn resolve(self, entities: &Entities) -> GpuPickingCoverageFrame {
+        GpuPickingCoverageFrame {
+            camera: self.camera,
+            frame: self.frame,
+            viewport_pixels: self.viewport_pixels,
+            pixels: self
+                .pixels
+                .into_iter()
+                .filter_map(|(entity_index, pixels)| {
+                    let entity = self
+                        .entities
+                        .get(entity_index)
+                        .filter(|entity| entities.contains(*entity))?;
+                    Some((entity, pixels))
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Holds the most recent [`GpuPickingCoverageFrame`] of every
+/// [`GpuPickingCoverage`] camera.
+///
+/// Like picking results, the coverage lags a few frames behind the frame being
+/// simulated. This is filled by [`receive_gpu_picking_coverage_frames`] at the
+/// start of every frame.
+#[derive(Resource)]
+pub struct GpuPickingCoverageFrames {
+    receiver: Receiver<GpuPickingCoverageCounts>,
+    frames: EntityHashMap<GpuPickingCoverageFrame>,
+}
+
+impl GpuPickingCoverageFrames {
+    /// Returns the most recent coverage read back for the given camera.
+    pub fn get(&self, camera: Entity) -> Option<&GpuPickingCoverageFrame> {
+        self.frames.get(&camera)
+    }
+
+    /// Iterates over the most recent coverage of every camera.
+    pub fn iter(&self) -> impl Iterator<Item = &GpuPickingCoverageFrame> {
+        self.frames.values()
+    }
+}
+
+/// Collects the coverage sent by the render world, keeping only the latest
+/// frame of each camera.
+pub fn receive_gpu_picking_coverage_frames(
+    mut coverage_frames: ResMut<GpuPickingCoverageFrames>,
+    entities: &Entities,
+    cameras: Query<(), (With<Camera>, With<GpuPickingCoverage>)>,
+) {
+    let GpuPickingCoverageFrames { receiver, frames } = &mut *coverage_frames;
+    for counts in receiver.try_iter() {
+        // Buffers may finish mapping out of order, never go back in time.
+        if let Some(latest) = frames.get(&counts.camera) {
+            if !is_newer_frame(counts.frame, latest.frame) {
+                continue;
+            }
+        }
+        frames.insert(counts.camera, counts.resolve(entities));
+    }
+    frames.retain(|camera, _| cameras.contains(*camera));
+}
+
+/// Marks a picking view whose coverage is counted.
+#[derive(Component, Clone, Copy)]
+pub struct ExtractedGpuPickingCoverage;
+
+fn extract_gpu_picking_coverage_cameras(
+    mut commands: Commands,
+    render_device: Res<RenderDevice>,
+    cameras: Extract<
+        Query<(Entity, &Camera), (With<Camera3d>, With<GpuPicking>, With<GpuPickingCoverage>)>,
+    >,
+) {
+    if cameras.is_empty() {
+        return;
+    }
+
+    if render_device.limits().max_storage_buffers_per_shader_stage == 0 {
+        warn_once!(
+            "GpuPickingCoverage requires storage buffers, which aren't supported by the current \
+            device. Coverage won't be counted."
+        );
+        return;
+    }
+
+    for (entity, camera) in &cameras {
+        if camera.is_active {
+            commands
+                .get_or_spawn(entity)
+                .insert(ExtractedGpuPickingCoverage);
+        }
+    }
+}
+
+/// Sends the coverage read back from the GPU to the main world.
+#[derive(Resource)]
+struct GpuPickingCoverageSender(Sender<GpuPickingCoverageCounts>);
+
+/// A buffer that the coverage of a frame is copied into so that it can be read
+/// back.
+struct GpuPickingCoverageReadback {
+    buffer: ReadbackBuffer,
+    /// The number of entity indices the buffer has room for.
+    capacity: u32,
+    /// The number of entity indices copied into the buffer.
+    len: u32,
+    frame: u32,
+    viewport_pixels: u32,
+    entities: GpuPickingEntities,
+}
+
+impl GpuPickingCoverageReadback {
+    fn new(render_device: &RenderDevice, capacity: u32) -> Self {
+        Self {
+            buffer: ReadbackBuffer::new(
+                render_device,
+                "gpu_picking_coverage_readback_buffer",
+                capacity as u64 * 4,
+            ),
+            capacity,
+            len: 0,
+            frame: 0,
+            viewport_pixels: 0,
+            entities: default(),
+        }
+    }
+
+    /// Copies the non-zero counts out of the mapped buffer, and unmaps it.
+    fn read(&self, camera: Entity) -> GpuPickingCoverageCounts {
+        let pixels = self.buffer.read(|data| {
+            bytemuck::cast_slice::<u8, u32>(&data[..self.len as usize * 4])
+                .iter()
+                .enumerate()
+                .filter(|(_, pixels)| **pixels != 0)
+                .map(|(entity_index, pixels)| (entity_index as u32, *pixels))
+                .collect()
+        });
+
+        GpuPickingCoverageCounts {
+            camera,
+            frame: self.frame,
+            viewport_pixels: self.viewport_pixels,
+            pixels,
+            entities: self.entities.clone(),
+        }
+    }
+}
+
+/// The buffers the coverage of a view is counted in and read back from.
+#[derive(Default)]
+struct GpuPickingCoverageBuffers {
+    /// The number of pixels covered by each entity index, counted on the GPU.
+    counts: Option<Buffer>,
+    /// The number of entity indices `counts` has room for.
+    counts_capacity: u32,
+    /// The ring of readback buffers, like the one of the picking data.
+    readbacks: Vec<GpuPickingCoverageReadback>,
+    /// The index of the readback buffer the next frame copies into.
+    next: usize,
+}
+
+impl GpuPickingCoverageBuffers {
+    /// Claims the next readback buffer of the ring, growing the buffers to fit
+    /// `len` entity indices if needed, or returns `None` if it is still waiting
+    /// on the GPU.
+    fn claim(&mut self, render_device: &RenderDevice, len: u32, count: usize) -> Option<usize> {
+        self.readbacks.truncate(count);
+        if self.next >= count {
+            self.next = 0;
+        }
+
+        // Grow to powers of two, so that spawning entities doesn't reallocate
+        // the buffers every frame.
+        let capacity = len.max(1).next_power_of_two();
+        let index = self.next;
+        match self.readbacks.get(index) {
+            None => self
+                .readbacks
+                .push(GpuPickingCoverageReadback::new(render_device, capacity)),
+            Some(readback) if !readback.buffer.is_free() => return None,
+            Some(readback) if readback.capacity < len => {
+                self.readbacks[index] = GpuPickingCoverageReadback::new(render_device, capacity);
+            }
+            Some(_) => {}
+        }
+        if self.counts.is_none() || self.counts_capacity < len {
+            self.counts = Some(render_device.create_buffer(&BufferDescriptor {
+                label: Some("gpu_picking_coverage_buffer"),
+                size: capacity as u64 * 4,
+                usage: BufferUsages::STORAGE | BufferUsages::COPY_SRC | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }));
+            self.counts_capacity = capacity;
+        }
+        self.next = (index + 1) % count;
+        Some(index)
+    }
+}
+
+/// The coverage buffers of every view with [`ExtractedGpuPickingCoverage`].
+#[derive(Resource, Default)]
+pub struct GpuPickingCoverageReadbacks {
+    views: EntityHashMap<GpuPickingCoverageBuffers>,
+}
+
+/// The index of the readback buffer, in the ring of the view in
+/// [`GpuPickingCoverageReadbacks`], that the [`GpuPickingCoverageNode`] copies
+/// into this frame.
+///
+/// Views without this component don't count their coverage this frame.
+#[derive(Component, Clone, Copy)]
+pub struct CurrentGpuPickingCoverageBufferIndex(pub usize);
+
+fn prepare_gpu_picking_coverage_readbacks(
+    mut commands: Commands,
+    render_device: Res<RenderDevice>,
+    frame_count: Res<FrameCount>,
+    pipeline_cache: Res<PipelineCache>,
+    coverage_pipeline: Res<GpuPickingCoveragePipeline>,
+    mut readbacks: ResMut<GpuPickingCoverageReadbacks>,
+    views: Query<
+        (
+            Entity,
+            &ExtractedView,
+            &ExtractedGpuPickingCamera,
+            &VisibleEntities,
+            Option<&GpuPickingEntities>,
+        ),
+        With<ExtractedGpuPickingCoverage>,
+    >,
+) {
+    readbacks.views.retain(|view, _| views.contains(*view));
+
+    // Don't read back buffers that nothing was counted into.
+    if pipeline_cache
+        .get_compute_pipeline(coverage_pipeline.pipeline_id)
+        .is_none()
+    {
+        return;
+    }
+
+    for (entity, view, gpu_picking_camera, visible_entities, drawn_entities) in &views {
+        // Pixels are counted by entity index, so the buffers need room for the
+        // highest index that can be drawn.
+        let len = visible_entities
+            .iter::<WithMesh>()
+            .map(|entity| entity.index() + 1)
+            .max()
+            .unwrap_or_default();
+
+        let buffers = readbacks.views.entry(entity).or_default();
+        let Some(index) = buffers.claim(
+            &render_device,
+            len,
+            gpu_picking_camera.readback_buffer_count,
+        ) else {
+            continue;
+        };
+
+        let viewport_size = (UVec2::new(view.viewport.z, view.viewport.w).as_vec2()
+            * gpu_picking_camera.scale)
+            .ceil()
+            .as_uvec2()
+            .max(UVec2::ONE);
+        let readback = &mut buffers.readbacks[index];
+        readback.buffer.start_copy();
+        readback.len = len;
+        readback.frame = frame_count.0;
+        readback.viewport_pixels = viewport_size.x * viewport_size.y;
+        readback.entities = drawn_entities.cloned().unwrap_or_default();
+        commands
+            .entity(entity)
+            .insert(CurrentGpuPickingCoverageBufferIndex(index));
+    }
+}
+
+/// Starts mapping the coverage buffers that were copied into this frame, and
+/// sends the contents of the buffers that finished mapping to the main world.
+fn map_gpu_picking_coverage_readbacks(
+    render_device: Res<RenderDevice>,
+    mut readbacks: ResMut<GpuPickingCoverageReadbacks>,
+    sender: Res<GpuPickingCoverageSender>,
+) {
+    render_device.poll(Maintain::Poll);
+
+    for (&camera, buffers) in &mut readbacks.views {
+        for readback in &mut buffers.readbacks {
+            match readback.buffer.poll() {
+                None => {}
+                Some(Err(err)) => error!("Failed to map GPU picking coverage buffer: {err}"),
+                Some(Ok(())) => {
+                    // The main world may have been dropped during shutdown.
+                    let _ = sender.0.send(readback.read(camera));
+                }
+            }
+        }
+    }
+}
+
+/// The compute pipeline that counts the pixels covered by each entity.
+#[derive(Resource)]
+pub struct GpuPickingCoveragePipeline {
+    pub layout: BindGroupLayout,
+    pub pipeline_id: CachedComputePipelineId,
+}
+
+impl FromWorld for GpuPickingCoveragePipeline {
+    fn from_world(world: &mut World) -> Self {
+        let layout = world.resource::<RenderDevice>().create_bind_group_layout(
+            "gpu_picking_coverage_layout",
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::COMPUTE,
+                (
+                    texture_2d(TextureSampleType::Uint),
+                    storage_buffer_sized(false, None),
+                ),
+            ),
+        );
+
+        let pipeline_id = world
+            .resource_mut::<PipelineCache>()
+            .queue_compute_pipeline(ComputePipelineDescriptor {
+                label: Some("gpu_picking_coverage_pipeline".into()),
+                layout: vec![layout.clone()],
+                push_constant_ranges: vec![],
+                shader: GPU_PICKING_COVERAGE_SHADER_HANDLE,
+                shader_defs: vec![],
+                entry_point: "count_coverage".into(),
+            });
+
+        GpuPickingCoveragePipeline {
+            layout,
+            pipeline_id,
+        }
+    }
+}
+
+/// Render node that counts the pixels covered by each entity in the entity
+/// index texture of a picking camera, and copies the counts into its current
+/// coverage readback buffer.
+#[derive(Default)]
+pub struct GpuPickingCoverageNode;
+
+impl ViewNode for GpuPickingCoverageNode {
+    type ViewQuery = (
+        &'static VisibleMeshIdTextures,
+        &'static CurrentGpuPickingCoverageBufferIndex,
+    );
+
+    fn run(
+        &self,
+        graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        (textures, buffer_index): QueryItem<Self::ViewQuery>,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let Some(buffers) = world
+            .resource::<GpuPickingCoverageReadbacks>()
+            .views
+            .get(&graph.view_entity())
+        else {
+            return Ok(());
+        };
+        let (Some(counts), Some(readback)) = (
+            buffers.counts.as_ref(),
+            buffers.readbacks.get(buffer_index.0),
+        ) else {
+            return Ok(());
+        };
+        // Nothing can cover any pixel, the readback has no counts to copy.
+        let Some(size) = BufferSize::new(readback.len as u64 * 4) else {
+            return Ok(());
+        };
+        let coverage_pipeline = world.resource::<GpuPickingCoveragePipeline>();
+        let Some(pipeline) = world
+            .resource::<PipelineCache>()
+            .get_compute_pipeline(coverage_pipeline.pipeline_id)
+        else {
+            return Ok(());
+        };
+
+        #[cfg(feature = "trace")]
+        let _gpu_picking_coverage_span = info_span!("gpu_picking_coverage").entered();
+
+        let diagnostics = render_context.diagnostic_recorder();
+
+        let bind_group = render_context.render_device().create_bind_group(
+            "gpu_picking_coverage_bind_group",
+            &coverage_pipeline.layout,
+            &BindGroupEntries::sequential((
+                &textures.mesh_id.default_view,
+                BufferBinding {
+                    buffer: counts,
+                    offset: 0,
+                    size: Some(size),
+                },
+            )),
+        );
+
+        let command_encoder = render_context.command_encoder();
+        command_encoder.clear_buffer(counts, 0, Some(size.get()));
+        {
+            let mut compute_pass = command_encoder.begin_compute_pass(&ComputePassDescriptor {
+                label: Some("gpu_picking_coverage"),
+                timestamp_writes: None,
+            });
+            let pass_span = diagnostics.pass_span(&mut compute_pass, "gpu_picking_coverage");
+
+            compute_pass.set_pipeline(pipeline);
+            compute_pass.set_bind_group(0, &bind_group, &[]);
+            compute_pass.dispatch_workgroups(
+                textures.size.x.div_ceil(8),
+                textures.size.y.div_ceil(8),
+                1,
+            );
+
+            pass_span.end(&mut compute_pass);
+        }
+        command_encoder.copy_buffer_to_buffer(counts, 0, &readback.buffer, 0, size.get());
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs::world::World;
+
+    use super::{GpuPickingCoverageCounts, GpuPickingEntities};
+
+    #[test]
+    fn coverage_of_despawned_entities_is_dropped() {
+        let mut world = World::new();
+        let camera = world.spawn_empty().id();
+        let visible = world.spawn_empty().id();
+        let despawned = world.spawn_empty().id();
+        let entities = GpuPickingEntities::new([visible, despawned]);
+        world.despawn(despawned);
+        // Reuses the index of the despawned entity.
+        let spawned = world.spawn_empty().id();
+
+        let frame = GpuPickingCoverageCounts {
+            camera,
+            frame: 0,
+            viewport_pixels: 100,
+            pixels: vec![(visible.index(), 25), (despawned.index(), 10)],
+            entities,
+        }
+        .resolve(world.entities());
+
+        assert_eq!(frame.iter().collect::<Vec<_>>(), [(visible, 25)]);
+        assert_eq!(frame.pixels(despawned), 0);
+        assert_eq!(frame.pixels(spawned), 0);
+        assert_eq!(frame.fraction(visible), 0.25);
+    }
+}
@@ -0,0 +1,146 @@
+#import bevy_pbr::{
+    mesh_bindings::mesh,
+    mesh_functions,
+    morph,
+    skinning,
+    view_transformations::position_world_to_clip,
+}
+
+#ifdef GPU_PICKING_ALPHA_TEST
+#import bevy_pbr::{
+    mesh_view_bindings::view,
+    pbr_bindings,
+    pbr_types,
+}
+#endif
+
+struct Vertex {
+    @builtin(instance_index) instance_index: u32,
+    @location(0) position: vec3<f32>,
+#ifdef VERTEX_UVS
+    @location(1) uv: vec2<f32>,
+#endif
+#ifdef SKINNED
+    @location(5) joint_indices: vec4<u32>,
+    @location(6) joint_weights: vec4<f32>,
+#endif
+#ifdef MORPH_TARGETS
+    @builtin(vertex_index) index: u32,
+#endif
+};
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    // The index of the entity this mesh belongs to, or of the mesh instance
+    // with `GPU_PICKING_INSTANCE_INDEX`, offset by one so that zero can mean
+    // "no entity".
+    @location(0) @interpolate(flat) entity_index: u32,
+#ifdef VERTEX_UVS
+    @location(1) uv: vec2<f32>,
+#endif
+};
+
+#ifdef MORPH_TARGETS
+fn morph_vertex(vertex_in: Vertex) -> Vertex {
+    var vertex = vertex_in;
+    let weight_count = morph::layer_count();
+    for (var i: u32 = 0u; i < weight_count; i ++) {
+        let weight = morph::weight_at(i);
+        if weight == 0.0 {
+            continue;
+        }
+        vertex.position += weight * morph::morph(vertex.index, morph::position_offset, i);
+    }
+    return vertex;
+}
+#endif
+
+@vertex
+fn vertex(vertex_no_morph: Vertex) -> VertexOutput {
+    var out: VertexOutput;
+
+#ifdef MORPH_TARGETS
+    var vertex = morph_vertex(vertex_no_morph);
+#else
+    var vertex = vertex_no_morph;
+#endif
+
+#ifdef SKINNED
+    let world_from_local = skinning::skin_model(vertex.joint_indices, vertex.joint_weights);
+#else
+    // Use vertex_no_morph.instance_index instead of vertex.instance_index to work around a wgpu dx12 bug.
+    // See https://github.com/gfx-rs/naga/issues/2416
+    let world_from_local = mesh_functions::get_model_matrix(vertex_no_morph.instance_index);
+#endif
+    let world_position = mesh_functions::mesh_position_local_to_world(
+        world_from_local,
+        vec4<f32>(vertex.position, 1.0)
+    );
+    out.position = position_world_to_clip(world_position.xyz);
+#ifdef GPU_PICKING_INSTANCE_INDEX
+    out.entity_index = vertex_no_morph.instance_index + 1u;
+#else
+    out.entity_index = mesh[vertex_no_morph.instance_index].entity_index + 1u;
+#endif
+#ifdef VERTEX_UVS
+    out.uv = mesh_functions::mesh_uv_transform(vertex_no_morph.instance_index, vertex.uv);
+#endif
+
+    return out;
+}
+
+struct FragmentOutput {
+#ifdef GPU_PICKING_TRIANGLES
+    // The entity index, and the index of the triangle within the mesh.
+    @location(0) mesh_id: vec2<u32>,
+#else
+    @location(0) mesh_id: u32,
+#endif
+#ifdef GPU_PICKING_DEPTH
+    // The normalized device coordinate depth, used to reconstruct the position
+    // of the hit on the CPU.
+    @location(1) depth: f32,
+#endif
+};
+
+@fragment
+fn fragment(
+    in: VertexOutput,
+#ifdef GPU_PICKING_TRIANGLES
+    @builtin(primitive_index) primitive_index: u32,
+#endif
+) -> FragmentOutput {
+    var out: FragmentOutput;
+
+#ifdef GPU_PICKING_ALPHA_TEST
+    // Discard the fragments that aren't drawn, or that are transparent enough
+    // to be picked through, like `prepass_alpha_discard` does.
+    var alpha = pbr_bindings::material.base_color.a;
+#ifdef VERTEX_UVS
+    if (pbr_bindings::material.flags & pbr_types::STANDARD_MATERIAL_FLAGS_BASE_COLOR_TEXTURE_BIT) != 0u {
+        let uv = (pbr_bindings::material.uv_transform * vec3(in.uv, 1.0)).xy;
+        alpha *= textureSampleBias(pbr_bindings::base_color_texture, pbr_bindings::base_color_sampler, uv, view.mip_bias).a;
+    }
+#endif // VERTEX_UVS
+#ifdef GPU_PICKING_ALPHA_MASK
+    if alpha < pbr_bindings::material.alpha_cutoff {
+        discard;
+    }
+#endif // GPU_PICKING_ALPHA_MASK
+#ifdef GPU_PICKING_ALPHA_THRESHOLD
+    if alpha < bitcast<f32>(u32(#{GPU_PICKING_ALPHA_THRESHOLD})) {
+        discard;
+    }
+#endif // GPU_PICKING_ALPHA_THRESHOLD
+#endif // GPU_PICKING_ALPHA_TEST
+
+#ifdef GPU_PICKING_TRIANGLES
+    out.mesh_id = vec2(in.entity_index, primitive_index);
+#else
+    out.mesh_id = in.entity_index;
+#endif
+#ifdef GPU_PICKING_DEPTH
+    out.depth = in.position.z;
+#endif
+    return out;
+}
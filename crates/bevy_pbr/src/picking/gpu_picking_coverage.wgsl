@@ -0,0 +1,23 @@
+// Counts the pixels of the GPU picking entity index texture that each entity
+// covers.
+
+// The entity index of each pixel plus one, or zero where no mesh was drawn.
+// The second channel, if any, holds triangle indices and is ignored.
+@group(0) @binding(0) var mesh_id: texture_2d<u32>;
+// The number of pixels covered by each entity, indexed by entity index.
+@group(0) @binding(1) var<storage, read_write> coverage: array<atomic<u32>>;
+
+@compute
+@workgroup_size(8, 8, 1)
+fn count_coverage(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    if any(global_id.xy >= textureDimensions(mesh_id)) {
+        return;
+    }
+
+    let entity_index = textureLoad(mesh_id, global_id.xy, 0).r;
+    // Entities spawned after the buffer was sized aren't counted.
+    if entity_index == 0u || entity_index > arrayLength(&coverage) {
+        return;
+    }
+    atomicAdd(&coverage[entity_index - 1u], 1u);
+}
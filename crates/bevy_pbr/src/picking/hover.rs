@@ -0,0 +1,403 @@
+use bevy_app::{App, Plugin, PreUpdate};
+use bevy_ecs::{entity::Entities, prelude::*};
+use bevy_input::{touch::Touches, InputSystem};
+use bevy_math::{UVec2, Vec2};
+use bevy_render::camera::{Camera, NormalizedRenderTarget};
+use bevy_utils::{HashMap, HashSet};
+use bevy_window::{PrimaryWindow, Window};
+
+use super::{receive_gpu_picking_frames, GpuPicking, GpuPickingFrames, GpuPickingResults};
+
+/// Keeps track of the entity under every pointer of every [`GpuPicking`]
+/// camera that renders to a window, and sends [`PickingHoverChanged`] events
+/// when it changes.
+///
+/// This plugin isn't added by default. The hovered entities can be read from
+/// the [`GpuPickingHovers`] resource.
+pub struct GpuPickingHoverPlugin;
+
+impl Plugin for GpuPickingHoverPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GpuPickingHovers>()
+            .add_event::<PickingHoverChanged>()
+            .add_systems(
+                PreUpdate,
+                update_gpu_picking_hovers
+                    .after(receive_gpu_picking_frames)
+                    .after(InputSystem),
+            );
+    }
+}
+
+/// A pointer that can hover entities.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PickingPointer {
+    /// The mouse cursor, in the window the camera renders to.
+    Mouse,
+    /// A finger on the primary window, identified by its
+    /// [`Touch::id`](bevy_input::touch::Touch::id).
+    Touch(u64),
+}
+
+/// Sent when the entity under a pointer of a [`GpuPicking`] camera changes.
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PickingHoverChanged {
+    /// The camera whose hovered entity changed.
+    pub camera: Entity,
+    /// The pointer whose hovered entity changed.
+    pub pointer: PickingPointer,
+    /// The entity that was hovered before, if any.
+    pub old: Option<Entity>,
+    /// The entity that is hovered now, if any.
+    pub new: Option<Entity>,
+}
+
+/// The entity under every pointer of every [`GpuPicking`] camera, updated by
+/// the [`GpuPickingHoverPlugin`].
+#[derive(Resource, Default)]
+pub struct GpuPickingHovers {
+    hovers: HashMap<(Entity, PickingPointer), GpuPickingHover>,
+}
+
+impl GpuPickingHovers {
+    /// Returns the entity under the given pointer of the given camera, if any.
+    pub fn get(&self, camera: Entity, pointer: PickingPointer) -> Option<Entity> {
+        self.hovers
+            .get(&(camera, pointer))
+            .and_then(|hover| hover.entity)
+    }
+
+    /// Iterates over the cameras, their pointers and the entity under them.
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, PickingPointer, Option<Entity>)> + '_ {
+        self.hovers
+            .iter()
+            .map(|((camera, pointer), hover)| (*camera, *pointer, hover.entity))
+    }
+}
+
+struct GpuPickingHover {
+    entity: Option<Entity>,
+    /// The [`GpuPickingFrame::frame`](super::GpuPickingFrame::frame) the entity
+    /// was picked from.
+    frame: u32,
+}
+
+/// Picks the entity under every pointer of every [`GpuPicking`] camera that
+/// renders to a window, and sends [`PickingHoverChanged`] events.
+///
+/// Picking results lag a few frames behind, so the hovered entity is only
+/// picked again once per frame read back from the GPU, rather than every frame.
+/// This keeps hover highlighting from flickering when the same readback is
+/// queried again with a pointer that moved since.
+///
+/// Hovers are cleared right away, without waiting for a readback, when their
+/// pointer leaves the window or is lifted, when their camera stops rendering,
+/// and when the hovered entity is despawned.
+#[allow(clippy::too_many_arguments)]
+pub fn update_gpu_picking_hovers(
+    mut hovers: ResMut<GpuPickingHovers>,
+    mut events: EventWriter<PickingHoverChanged>,
+    frames: Res<GpuPickingFrames>,
+    picking: GpuPickingResults,
+    entities: &Entities,
+    touches: Option<Res<Touches>>,
+    cameras: Query<(Entity, &Camera), With<GpuPicking>>,
+    primary_window: Query<Entity, With<PrimaryWindow>>,
+    windows: Query<&Window>,
+) {
+    let primary_window = primary_window.get_single().ok();
+    let mut pointers = Vec::new();
+    let mut live = HashSet::new();
+
+    for (camera_entity, camera) in &cameras {
+        let Some(frame) = frames.get(camera_entity) else {
+            continue;
+        };
+        if !camera.is_active {
+            continue;
+        }
+        let Some(NormalizedRenderTarget::Window(window_ref)) =
+            camera.target.normalize(primary_window)
+        else {
+            continue;
+        };
+        let Ok(window) = windows.get(window_ref.entity()) else {
+            continue;
+        };
+
+        pointers.clear();
+        if let Some(cursor) = window.physical_cursor_position() {
+            pointers.push((PickingPointer::Mouse, cursor));
+        }
+        if let Some(touches) = touches.as_deref() {
+            if Some(window_ref.entity()) == primary_window {
+                pointers.extend(touches.iter().map(|touch| {
+                    (
+                        PickingPointer::Touch(touch.id()),
+                        touch.position() * window.scale_factor(),
+                    )
+                }));
+            }
+        }
+
+        for &(pointer, position) in &pointers {
+            let key = (camera_entity, pointer);
+            live.insert(key);
+            let entity = match hovers.hovers.get(&key) {
+                Some(hover) if hover.frame == frame.frame => {
+                    hover.entity.filter(|entity| entities.contains(*entity))
+                }
+                _ => picking.pick_camera(camera_entity, pointer_pixel(position)),
+            };
+            let old = hovers
+                .hovers
+                .insert(
+                    key,
+                    GpuPickingHover {
+                        entity,
+                        frame: frame.frame,
+                    },
+                )
+                .and_then(|hover| hover.entity);
+            if old != entity {
+                events.send(PickingHoverChanged {
+                    camera: camera_entity,
+                    pointer,
+                    old,
+                    new: entity,
+                });
+            }
+        }
+    }
+
+    hovers.hovers.retain(|&(camera, pointer), hover| {
+        if live.contains(&(camera, pointer)) {
+            return true;
+        }
+        if hover.entity.is_some() {
+            events.send(PickingHoverChanged {
+                camera,
+                pointer,
+                old: hover.entity,
+                new: None,
+            });
+        }
+        false
+    });
+}
+
+fn pointer_pixel(position: Vec2) -> UVec2 {
+    position.max(Vec2::ZERO).as_uvec2()
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_asset::Assets;
+    use bevy_core::FrameCount;
+    use bevy_ecs::system::RunSystemOnce;
+    use bevy_input::touch::{touch_screen_input_system, TouchInput, TouchPhase};
+    use bevy_math::{DVec2, Mat4, URect};
+    use bevy_render::{camera::RenderTarget, mesh::Mesh};
+    use bevy_window::WindowRef;
+
+    use super::*;
+    use crate::picking::{GpuPickingEntities, GpuPickingFrame};
+
+    const SIZE: UVec2 = UVec2::new(4, 4);
+
+    struct Scene {
+        world: World,
+        window: Entity,
+        camera: Entity,
+        target: Entity,
+    }
+
+    impl Scene {
+        fn new() -> Self {
+            let mut world = World::new();
+            world.init_resource::<GpuPickingHovers>();
+            world.init_resource::<Events<PickingHoverChanged>>();
+            world.init_resource::<FrameCount>();
+            world.init_resource::<Assets<Mesh>>();
+            world.init_resource::<Touches>();
+            world.init_resource::<Events<TouchInput>>();
+            world.insert_resource(GpuPickingFrames {
+                receiver: crossbeam_channel::never(),
+                frames: Default::default(),
+            });
+            let window = world.spawn((Window::default(), PrimaryWindow)).id();
+            let camera = world
+                .spawn((
+                    Camera {
+                        target: RenderTarget::Window(WindowRef::Primary),
+                        ..Default::default()
+                    },
+                    GpuPicking::default(),
+                ))
+                .id();
+            let target = world.spawn_empty().id();
+            let mut scene = Self {
+                world,
+                window,
+                camera,
+                target,
+            };
+            scene.read_back(1);
+            scene
+        }
+
+        /// Receives a readback of `frame` where the target covers the top
+        /// left pixel.
+        fn read_back(&mut self, frame: u32) {
+            let mut entity_indices = vec![0; (SIZE.x * SIZE.y) as usize];
+            entity_indices[0] = self.target.index() + 1;
+            let frame = GpuPickingFrame {
+                camera: self.camera,
+                order: 0,
+                frame,
+                size: SIZE,
+                scale: 1.0,
+                entity_indices,
+                entities: GpuPickingEntities::new([self.target]),
+                depth: None,
+                triangle_indices: None,
+                viewport: URect::from_corners(UVec2::ZERO, SIZE),
+                target: Some(NormalizedRenderTarget::Window(
+                    WindowRef::Primary.normalize(Some(self.window)).unwrap(),
+                )),
+                clears: true,
+                world_from_view: Mat4::IDENTITY,
+                view_from_clip: Mat4::IDENTITY,
+            };
+            let mut frames = self.world.resource_mut::<GpuPickingFrames>();
+            frames.frames.insert(self.camera, frame);
+        }
+
+        fn move_cursor(&mut self, position: Option<DVec2>) {
+            let mut window = self.world.get_mut::<Window>(self.window).unwrap();
+            window.set_physical_cursor_position(position);
+        }
+
+        fn touch(&mut self, id: u64, phase: TouchPhase, position: Vec2) {
+            self.world.send_event(TouchInput {
+                phase,
+                position,
+                window: self.window,
+                force: None,
+                id,
+            });
+            self.world.run_system_once(touch_screen_input_system);
+        }
+
+        fn update(&mut self) -> Vec<PickingHoverChanged> {
+            self.world.run_system_once(update_gpu_picking_hovers);
+            self.world
+                .resource_mut::<Events<PickingHoverChanged>>()
+                .drain()
+                .collect()
+        }
+
+        fn changed(&self, pointer: PickingPointer, old: bool, new: bool) -> PickingHoverChanged {
+            PickingHoverChanged {
+                camera: self.camera,
+                pointer,
+                old: old.then_some(self.target),
+                new: new.then_some(self.target),
+            }
+        }
+    }
+
+    #[test]
+    fn hover_is_cleared_when_the_cursor_leaves_without_a_new_readback() {
+        let mut scene = Scene::new();
+        scene.move_cursor(Some(DVec2::new(0.5, 0.5)));
+        assert_eq!(
+            scene.update(),
+            [scene.changed(PickingPointer::Mouse, false, true)]
+        );
+
+        scene.move_cursor(None);
+        assert_eq!(
+            scene.update(),
+            [scene.changed(PickingPointer::Mouse, true, false)]
+        );
+        assert_eq!(
+            scene
+                .world
+                .resource::<GpuPickingHovers>()
+                .get(scene.camera, PickingPointer::Mouse),
+            None
+        );
+    }
+
+    #[test]
+    fn hover_is_cleared_when_the_camera_stops_rendering() {
+        let mut scene = Scene::new();
+        scene.move_cursor(Some(DVec2::new(0.5, 0.5)));
+        scene.update();
+
+        scene
+            .world
+            .get_mut::<Camera>(scene.camera)
+            .unwrap()
+            .is_active = false;
+        assert_eq!(
+            scene.update(),
+            [scene.changed(PickingPointer::Mouse, true, false)]
+        );
+        assert!(scene.update().is_empty());
+    }
+
+    #[test]
+    fn hover_is_cleared_when_the_hovered_entity_is_despawned() {
+        let mut scene = Scene::new();
+        scene.move_cursor(Some(DVec2::new(0.5, 0.5)));
+        scene.update();
+
+        scene.world.despawn(scene.target);
+        assert_eq!(
+            scene.update(),
+            [scene.changed(PickingPointer::Mouse, true, false)]
+        );
+    }
+
+    #[test]
+    fn hover_is_only_picked_again_from_new_readbacks() {
+        let mut scene = Scene::new();
+        scene.move_cursor(Some(DVec2::new(2.5, 2.5)));
+        assert!(scene.update().is_empty());
+
+        // The cursor moved over the target, but the readback is the same.
+        scene.move_cursor(Some(DVec2::new(0.5, 0.5)));
+        assert!(scene.update().is_empty());
+
+        scene.read_back(2);
+        assert_eq!(
+            scene.update(),
+            [scene.changed(PickingPointer::Mouse, false, true)]
+        );
+    }
+
+    #[test]
+    fn touches_hover_until_they_are_lifted() {
+        let mut scene = Scene::new();
+        scene.touch(7, TouchPhase::Started, Vec2::new(0.5, 0.5));
+        assert_eq!(
+            scene.update(),
+            [scene.changed(PickingPointer::Touch(7), false, true)]
+        );
+        assert_eq!(
+            scene
+                .world
+                .resource::<GpuPickingHovers>()
+                .get(scene.camera, PickingPointer::Mouse),
+            None
+        );
+
+        scene.touch(7, TouchPhase::Ended, Vec2::new(0.5, 0.5));
+        assert_eq!(
+            scene.update(),
+            [scene.changed(PickingPointer::Touch(7), true, false)]
+        );
+    }
+}
@@ -0,0 +1,1982 @@
+//! GPU picking: renders the index of the entity under each pixel into a
+//! texture, reads it back to the CPU and exposes the results to the main world.
+//!
+//! Add the [`GpuPicking`] component to a 3D camera, then use the
+//! [`GpuPickingResults`] system parameter to find out which entity is under a
+//! given pixel. Also add [`GpuPickingDepth`] to get the world space position
+//! of the picked point, and [`GpuPickingTriangles`] to get the triangle that
+//! was hit.
+//!
+//! Entities can be excluded from picking with [`PickingVisibility`], or only
+//! picked by some cameras with [`PickingLayers`].
+//!
+//! Meshes with a [`StandardMaterial`] are only picked where their alpha passes
+//! the same test as when they're drawn: alpha masked surfaces aren't picked
+//! where they're discarded, and blended surfaces can be picked through where
+//! they're mostly transparent. See [`GpuPicking::alpha_mask`] and
+//! [`GpuPicking::transparency_threshold`].
+//!
+//! For hover highlighting, add the [`GpuPickingHoverPlugin`], which tracks the
+//! entity under every pointer of every picking camera and sends
+//! [`PickingHoverChanged`] events. Effects that run on the GPU, like selection
+//! outlines, can read the entity indices of every pixel directly from the
+//! [`ViewMeshIdTexture`] of the view in custom render graph nodes.
+//!
+//! To find out how much of the screen each entity covers, for gameplay like
+//! target priorities or for analytics, add the [`GpuPickingCoveragePlugin`] and
+//! the [`GpuPickingCoverage`] component to the camera. The pixels of each
+//! entity are counted on the GPU and read back to [`GpuPickingCoverageFrames`].
+//!
+//! Reading data back from the GPU takes a few frames, so the results always
+//! describe a frame that was rendered slightly in the past. [`GpuPickingResults`]
+//! hides this latency: it answers queries from the most recent readback that
+//! has finished, and never blocks waiting on the GPU. Every camera cycles
+//! through a ring of readback buffers, one per frame in flight, and each
+//! [`GpuPickingFrame`] is labeled with the [`FrameCount`] it was rendered in,
+//! so that [`GpuPickingResults::latency`] can tell how old the results are.
+
+mod coverage;
+mod hover;
+mod node;
+
+pub use coverage::*;
+pub use hover::*;
+pub use node::*;
+
+use std::{ops::Range, sync::Arc};
+
+use bevy_app::{App, Plugin, PreUpdate};
+use bevy_asset::{load_internal_asset, AssetId, Assets, Handle};
+use bevy_core::FrameCount;
+use bevy_core_pipeline::core_3d::{
+    graph::{Core3d, Node3d},
+    Camera3d, CORE_3D_DEPTH_FORMAT,
+};
+use bevy_ecs::{
+    entity::{Entities, EntityHashMap},
+    prelude::*,
+    system::{
+        lifetimeless::{Read, SRes},
+        SystemParam, SystemParamItem,
+    },
+};
+use bevy_math::{FloatOrd, Mat4, URect, UVec2, Vec3, Vec4};
+use bevy_reflect::{std_traits::ReflectDefault, Reflect};
+use bevy_render::{
+    camera::{Camera, ClearColorConfig, ExtractedCamera, NormalizedRenderTarget},
+    mesh::{
+        morph::MeshMorphWeights, skinning::SkinnedMesh, GpuMesh, Indices, Mesh,
+        MeshVertexBufferLayoutRef,
+    },
+    render_asset::RenderAssets,
+    render_graph::{RenderGraphApp, ViewNodeRunner},
+    render_phase::{
+        AddRenderCommand, BinnedPhaseItem, BinnedRenderPhase, BinnedRenderPhasePlugin,
+        CachedRenderPipelinePhaseItem, DrawFunctionId, DrawFunctions, PhaseItem,
+        PhaseItemExtraIndex, RenderCommand, RenderCommandResult, SetItemPipeline,
+        TrackedRenderPass,
+    },
+    render_resource::{binding_types::uniform_buffer, *},
+    renderer::RenderDevice,
+    settings::WgpuFeatures,
+    texture::{CachedTexture, TextureCache},
+    view::{
+        ExtractedView, RenderLayers, ViewUniform, ViewUniformOffset, ViewUniforms, VisibleEntities,
+        WithMesh,
+    },
+    Extract, ExtractSchedule, Render, RenderApp, RenderSet,
+};
+use bevy_transform::components::GlobalTransform;
+use bevy_utils::{default, tracing::error, warn_once, HashMap};
+use crossbeam_channel::{Receiver, Sender};
+
+use crate::{
+    graph::NodePbr, setup_morph_and_skinning_defs, update_pipeline_permutations, AlphaMode,
+    DrawMesh, MeshLayouts, MeshPipeline, MeshPipelineKey, PreparedMaterial, RenderLightmaps,
+    RenderMaterialInstances, RenderMeshInstances, SetMaterialBindGroup, SetMeshBindGroup,
+    StandardMaterial,
+};
+
+pub const GPU_PICKING_SHADER_HANDLE: Handle<Shader> = Handle::weak_from_u128(293765148290571843);
+
+/// The format of the texture that GPU picking renders entity indices into.
+pub const GPU_PICKING_MESH_ID_FORMAT: TextureFormat = TextureFormat::R32Uint;
+
+/// The format of the texture that GPU picking renders entity indices into,
+/// when [`GpuPickingTriangles`] is used. The second channel holds the index of
+/// the triangle that was drawn.
+pub const GPU_PICKING_MESH_ID_TRIANGLE_FORMAT: TextureFormat = TextureFormat::Rg32Uint;
+
+/// The format of the texture that GPU picking copies depth into, when
+/// [`GpuPickingDepth`] is used.
+///
+/// Depth attachments can't be copied to buffers on every platform, so the
+/// fragment shader writes depth to a color target instead.
+pub const GPU_PICKING_DEPTH_COPY_FORMAT: TextureFormat = TextureFormat::R32Float;
+
+/// The default number of readback buffers in the ring of each picking camera.
+/// See [`GpuPicking::readback_buffer_count`].
+pub const GPU_PICKING_READBACK_BUFFER_COUNT: usize = 3;
+
+/// Adds GPU picking support for 3D cameras with the [`GpuPicking`] component.
+pub struct GpuPickingPlugin;
+
+impl Plugin for GpuPickingPlugin {
+    fn build(&self, app: &mut App) {
+        load_internal_asset!(
+            app,
+            GPU_PICKING_SHADER_HANDLE,
+            "gpu_picking.wgsl",
+            Shader::from_wgsl
+        );
+
+        let (sender, receiver) = crossbeam_channel::unbounded();
+
+        app.register_type::<GpuPicking>()
+            .register_type::<GpuPickingDepth>()
+            .register_type::<GpuPickingTriangles>()
+            .register_type::<PickingVisibility>()
+            .register_type::<PickingLayers>()
+            .insert_resource(GpuPickingFrames {
+                receiver,
+                frames: default(),
+            })
+            .add_systems(PreUpdate, receive_gpu_picking_frames)
+            .add_plugins(BinnedRenderPhasePlugin::<MeshId3d, MeshPipeline>::default());
+
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+
+        render_app
+            .insert_resource(GpuPickingSender(sender))
+            .init_resource::<DrawFunctions<MeshId3d>>()
+            .init_resource::<SpecializedMeshPipelines<GpuPickingPipeline>>()
+            .init_resource::<GpuPickingReadbacks>()
+            .init_resource::<GpuPickingViewBindGroup>()
+            .init_resource::<ExtractedPickingLayers>()
+            .add_render_command::<MeshId3d, DrawGpuPicking>()
+            .add_render_command::<MeshId3d, DrawGpuPickingAlphaTest>()
+            .add_systems(
+                ExtractSchedule,
+                (extract_gpu_picking_cameras, extract_picking_layers),
+            )
+            .add_systems(
+                Render,
+                (
+                    queue_gpu_picking_meshes.in_set(RenderSet::QueueMeshes),
+                    update_pipeline_permutations::<GpuPickingPipeline>
+                        .in_set(RenderSet::PrepareResources),
+                    (
+                        prepare_gpu_picking_textures,
+                        prepare_gpu_picking_readbacks.after(prepare_gpu_picking_textures),
+                    )
+                        .in_set(RenderSet::PrepareResources),
+                    prepare_gpu_picking_view_bind_group.in_set(RenderSet::PrepareBindGroups),
+                    map_gpu_picking_readbacks.in_set(RenderSet::Cleanup),
+                ),
+            )
+            .add_render_graph_node::<ViewNodeRunner<GpuPickingNode>>(Core3d, NodePbr::GpuPicking)
+            .add_render_graph_node::<ViewNodeRunner<EntityIndexBufferCopyNode>>(
+                Core3d,
+                NodePbr::EntityIndexBufferCopy,
+            )
+            .add_render_graph_edges(
+                Core3d,
+                (
+                    Node3d::EndMainPass,
+                    NodePbr::GpuPicking,
+                    NodePbr::EntityIndexBufferCopy,
+                    Node3d::EndMainPassPostProcessing,
+                ),
+            );
+    }
+
+    fn finish(&self, app: &mut App) {
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+
+        render_app.init_resource::<GpuPickingPipeline>();
+    }
+}
+
+/// Enables GPU picking for a 3D camera.
+///
+/// Every frame, the index of the entity visible under each pixel of the
+/// camera's render target is written to a texture and read back to the CPU.
+/// Query the results with [`GpuPickingResults`].
+///
+/// The alpha of [`StandardMaterial`]s is tested like when they're drawn, see
+/// [`GpuPicking::alpha_mask`] and [`GpuPicking::transparency_threshold`]. The
+/// alpha of other materials is ignored, so they're picked as if they were
+/// opaque.
+#[derive(Component, Reflect, Clone, Copy, Debug)]
+#[reflect(Component, Default)]
+pub struct GpuPicking {
+    /// The resolution of the picking textures, relative to the camera's render
+    /// target, between 0 and 1.
+    ///
+    /// Rendering and reading back entity indices at full resolution is
+    /// expensive on high resolution displays. A scale of 0.5 renders them at
+    /// half the resolution in each direction, at the cost of precision near
+    /// the edges of meshes. Cursor positions passed to [`GpuPickingResults`]
+    /// are still in physical pixels of the render target.
+    ///
+    /// Defaults to 1.0.
+    pub scale: f32,
+    /// The number of readback buffers the camera cycles through, which is the
+    /// number of frames whose picking data can be waiting on the GPU at once.
+    ///
+    /// Every frame copies its picking data into the next buffer of the ring.
+    /// If that buffer is still waiting to be read back because the GPU is
+    /// running that many frames ahead, the frame is skipped rather than
+    /// stalling. More buffers skip fewer frames, at the cost of memory.
+    ///
+    /// Defaults to [`GPU_PICKING_READBACK_BUFFER_COUNT`].
+    pub readback_buffer_count: usize,
+    /// Whether surfaces with an [`AlphaMode::Mask`] material are only picked
+    /// where they're drawn, like the leaves of alpha tested foliage.
+    ///
+    /// When disabled, the material is ignored and the whole mesh is picked,
+    /// which is cheaper as the material doesn't need to be sampled.
+    ///
+    /// Defaults to `true`.
+    pub alpha_mask: bool,
+    /// If set, surfaces with a blended material ([`AlphaMode::Blend`],
+    /// [`AlphaMode::Premultiplied`], [`AlphaMode::Add`] or
+    /// [`AlphaMode::Multiply`]) are picked through where their alpha is below
+    /// this threshold, between 0 and 1.
+    ///
+    /// When unset, blended surfaces are picked as if they were opaque.
+    ///
+    /// Defaults to `None`.
+    pub transparency_threshold: Option<f32>,
+}
+
+impl Default for GpuPicking {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            readback_buffer_count: GPU_PICKING_READBACK_BUFFER_COUNT,
+            alpha_mask: true,
+            transparency_threshold: None,
+        }
+    }
+}
+
+/// Also reads back depth for a [`GpuPicking`] camera, so that picks can report
+/// where the picked entity was hit. See [`GpuPickingResults::pick_hit`].
+#[derive(Component, Reflect, Clone, Copy, Default, Debug)]
+#[reflect(Component, Default)]
+pub struct GpuPickingDepth;
+
+/// Also reads back the index of the triangle under each pixel for a
+/// [`GpuPicking`] camera, for vertex and face selection tools. See
+/// [`GpuPickingResults::pick_triangle`].
+///
+/// This requires the [`WgpuFeatures::SHADER_PRIMITIVE_INDEX`] feature, which
+/// isn't available on WebGL2 and WebGPU. Without it, this component is ignored.
+///
+/// [`WgpuFeatures::SHADER_PRIMITIVE_INDEX`]: bevy_render::settings::WgpuFeatures::SHADER_PRIMITIVE_INDEX
+#[derive(Component, Reflect, Clone, Copy, Default, Debug)]
+#[reflect(Component, Default)]
+pub struct GpuPickingTriangles;
+
+/// Whether an entity is written to the GPU picking buffers.
+///
+/// This is independent of [`Visibility`](bevy_render::view::Visibility): hide
+/// gizmos or UI proxies from picking while still rendering them, or keep
+/// entities pickable that are only drawn by other cameras. Entities without this
+/// component are pickable.
+#[derive(Component, Reflect, Clone, Copy, Default, Debug, PartialEq, Eq)]
+#[reflect(Component, Default)]
+pub enum PickingVisibility {
+    /// The entity can be picked.
+    #[default]
+    Pickable,
+    /// The entity is never written to the picking buffers, so the entities
+    /// behind it are picked instead.
+    Hidden,
+}
+
+/// The layers an entity can be picked on, or the layers a [`GpuPicking`]
+/// camera picks, like [`RenderLayers`] does for rendering.
+///
+/// An entity is only picked by a camera if their picking layers intersect.
+/// Entities and cameras without this component are on the first layer only.
+/// Picking layers don't affect rendering: an entity still has to be visible to
+/// the camera, according to its [`RenderLayers`], to be picked.
+#[derive(Component, Reflect, Clone, Copy, Default, Debug, PartialEq, Eq)]
+#[reflect(Component, Default)]
+pub struct PickingLayers(pub RenderLayers);
+
+/// A single frame of picking data that was read back from the GPU.
+#[derive(Clone, Debug)]
+pub struct GpuPickingFrame {
+    /// The camera that rendered this frame.
+    pub camera: Entity,
+    /// The [`Camera::order`] of the camera at the time it was rendered.
+    pub order: isize,
+    /// The [`FrameCount`] of the frame this was rendered in.
+    pub frame: u32,
+    /// The size of the picking texture, in pixels. This is the size of the
+    /// camera's render target multiplied by [`GpuPickingFrame::scale`].
+    pub size: UVec2,
+    /// The resolution of the picking texture relative to the camera's render
+    /// target. See [`GpuPicking::scale`].
+    pub scale: f32,
+    /// One value per pixel, in row-major order.
+    ///
+    /// Zero means no mesh covers that pixel; any other value is the index of
+    /// the entity plus one. Use [`GpuPickingFrame::entity`] to get the entity.
+    pub entity_indices: Vec<u32>,
+    /// The entities that were drawn in this frame.
+    pub entities: GpuPickingEntities,
+    /// One normalized device coordinate depth value per pixel, in row-major
+    /// order, if the camera has the [`GpuPickingDepth`] component.
+    pub depth: Option<Vec<f32>>,
+    /// The index of the triangle under each pixel, within the mesh of the
+    /// entity under that pixel, in row-major order, if the camera has the
+    /// [`GpuPickingTriangles`] component.
+    pub triangle_indices: Option<Vec<u32>>,
+    /// The viewport of the camera, in physical pixels of the render target.
+    pub viewport: URect,
+    /// The render target of the camera.
+    pub target: Option<NormalizedRenderTarget>,
+    /// Whether the camera clears its render target, hiding the cameras that
+    /// rendered before it in its viewport.
+    ///
+    /// Cameras with [`ClearColorConfig::None`] are overlays: pixels where none
+    /// of their meshes were drawn are routed to the cameras below them. See
+    /// [`route_gpu_picking_frames`].
+    pub clears: bool,
+    /// The camera's view-to-world matrix at the time it was rendered.
+    pub world_from_view: Mat4,
+    /// The inverse of the camera's projection at the time it was rendered.
+    pub view_from_clip: Mat4,
+}
+
+/// Where a [`GpuPickingFrame`] was hit, as returned by
+/// [`GpuPickingResults::pick_hit`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GpuPickingHit {
+    /// The entity that was hit.
+    pub entity: Entity,
+    /// The camera that saw the hit.
+    pub camera: Entity,
+    /// The world space position of the hit.
+    pub world_position: Vec3,
+    /// The distance from the camera to the hit along the camera's forward
+    /// axis.
+    pub view_depth: f32,
+}
+
+/// The triangle of a mesh that was picked, as returned by
+/// [`GpuPickingResults::pick_triangle`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GpuPickingTriangle {
+    /// The entity that was hit.
+    pub entity: Entity,
+    /// The camera that saw the hit.
+    pub camera: Entity,
+    /// The index of the triangle that was hit. For triangle lists, its
+    /// vertices are the indices `3 * triangle_index..3 * triangle_index + 3` of
+    /// the mesh.
+    pub triangle_index: u32,
+    /// The barycentric coordinates of the hit within the triangle, one weight
+    /// per vertex.
+    ///
+    /// These are only available if the camera also has the [`GpuPickingDepth`]
+    /// component, and the mesh is a triangle list whose positions are still
+    /// available in the main world and aren't deformed by skinning or morph
+    /// targets.
+    pub barycentrics: Option<Vec3>,
+}
+
+impl GpuPickingFrame {
+    /// Returns whether the given pixel is in the viewport of the camera.
+    ///
+    /// `position` is in physical pixels of the camera's render target.
+    pub fn contains(&self, position: UVec2) -> bool {
+        position.cmpge(self.viewport.min).all() && position.cmplt(self.viewport.max).all()
+    }
+
+    /// Returns the index of the entity under the given pixel, if any.
+    ///
+    /// `position` is in physical pixels of the camera's render target.
+    pub fn entity_index(&self, position: UVec2) -> Option<u32> {
+        self.entity_indices[self.pixel_index(position)?].checked_sub(1)
+    }
+
+    /// Returns the entity that was drawn under the given pixel, if any.
+    ///
+    /// The entity may have been despawned since the frame was rendered.
+    /// `position` is in physical pixels of the camera's render target.
+    pub fn entity(&self, position: UVec2) -> Option<Entity> {
+        self.entities.get(self.entity_index(position)?)
+    }
+
+    /// Returns the index of the triangle under the given pixel, if triangle
+    /// indices were read back and a mesh covers that pixel.
+    pub fn triangle_index(&self, position: UVec2) -> Option<u32> {
+        self.entity_index(position)?;
+        Some(self.triangle_indices.as_ref()?[self.pixel_index(position)?])
+    }
+
+    /// Returns the view space position of the closest surface at the given
+    /// pixel, if depth was read back and a mesh covers that pixel.
+    pub fn view_position(&self, position: UVec2) -> Option<Vec3> {
+        self.entity_index(position)?;
+        let depth = self.depth.as_ref()?[self.pixel_index(position)?];
+
+        // Reconstruct the position from the center of the pixel.
+        let viewport_size = self.viewport.size().as_vec2();
+        let uv = (position.as_vec2() - self.viewport.min.as_vec2() + 0.5) / viewport_size;
+        let ndc = Vec4::new(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, depth, 1.0);
+        let view_position = self.view_from_clip * ndc;
+        Some(view_position.truncate() / view_position.w)
+    }
+
+    /// Returns the index of the value for the given render target pixel in the
+    /// per-pixel vectors, accounting for the scale of the picking texture.
+    fn pixel_index(&self, position: UVec2) -> Option<usize> {
+        let pixel = (position.as_vec2() * self.scale).as_uvec2();
+        if pixel.x >= self.size.x || pixel.y >= self.size.y {
+            return None;
+        }
+        Some((pixel.y * self.size.x + pixel.x) as usize)
+    }
+}
+
+/// The entities drawn by a GPU picking view in a frame, by [`Entity::index`].
+///
+/// Picking textures only store the index of the entities. The index of an
+/// entity despawned since the frame was rendered can be reused by a new entity,
+/// so this maps the indices back to the entities that were actually drawn,
+/// generation included.
+#[derive(Component, Clone, Debug, Default)]
+pub struct GpuPickingEntities(Arc<HashMap<u32, Entity>>);
+
+impl GpuPickingEntities {
+    /// Creates the table of the given drawn entities.
+    pub fn new(entities: impl IntoIterator<Item = Entity>) -> Self {
+        Self(Arc::new(
+            entities
+                .into_iter()
+                .map(|entity| (entity.index(), entity))
+                .collect(),
+        ))
+    }
+
+    /// Returns the drawn entity with the given index, if any.
+    pub fn get(&self, entity_index: u32) -> Option<Entity> {
+        self.0.get(&entity_index).copied()
+    }
+}
+
+/// Returns the frames that can see the pixel at `cursor`, in the order they
+/// should be queried.
+///
+/// Only the frames of cameras that render to `target`, if given, and whose
+/// viewport contains `cursor` are returned, from the highest [`Camera::order`]
+/// to the lowest. The frames of cameras under a camera that
+/// [clears](GpuPickingFrame::clears) are hidden by it, so they're left out: the
+/// cursor is routed to the camera on top, even if it has no mesh under that
+/// pixel.
+pub fn route_gpu_picking_frames<'a>(
+    frames: impl IntoIterator<Item = &'a GpuPickingFrame>,
+    target: Option<&NormalizedRenderTarget>,
+    cursor: UVec2,
+) -> Vec<&'a GpuPickingFrame> {
+    let mut frames: Vec<_> = frames
+        .into_iter()
+        .filter(|frame| target.is_none() || frame.target.as_ref() == target)
+        .filter(|frame| frame.contains(cursor))
+        .collect();
+    frames.sort_by_key(|frame| std::cmp::Reverse(frame.order));
+    if let Some(top) = frames.iter().position(|frame| frame.clears) {
+        frames.truncate(top + 1);
+    }
+    frames
+}
+
+/// Holds the most recent [`GpuPickingFrame`] of every picking camera.
+///
+/// This is filled by [`receive_gpu_picking_frames`] at the start of every
+/// frame. Most users should use [`GpuPickingResults`] instead.
+#[derive(Resource)]
+pub struct GpuPickingFrames {
+    receiver: Receiver<GpuPickingFrame>,
+    frames: EntityHashMap<GpuPickingFrame>,
+}
+
+impl GpuPickingFrames {
+    /// Returns the most recent frame read back for the given camera.
+    pub fn get(&self, camera: Entity) -> Option<&GpuPickingFrame> {
+        self.frames.get(&camera)
+    }
+
+    /// Iterates over the most recent frame of every picking camera.
+    pub fn iter(&self) -> impl Iterator<Item = &GpuPickingFrame> {
+        self.frames.values()
+    }
+}
+
+/// Collects the picking frames sent by the render world, keeping only the
+/// latest one for each camera.
+pub fn receive_gpu_picking_frames(
+    mut gpu_picking_frames: ResMut<GpuPickingFrames>,
+    cameras: Query<(), (With<Camera>, With<GpuPicking>)>,
+) {
+    let GpuPickingFrames { receiver, frames } = &mut *gpu_picking_frames;
+    for frame in receiver.try_iter() {
+        // Buffers may finish mapping out of order, never go back in time.
+        if let Some(latest) = frames.get(&frame.camera) {
+            if !is_newer_frame(frame.frame, latest.frame) {
+                continue;
+            }
+        }
+        frames.insert(frame.camera, frame);
+    }
+    frames.retain(|camera, _| cameras.contains(*camera));
+}
+
+/// A [`SystemParam`] used to find which entity is under a given pixel.
+///
+/// The answers come from the latest readback that finished, which usually
+/// lags a couple of frames behind the frame being simulated. Entities that were
+/// despawned in the meantime are never returned.
+#[derive(SystemParam)]
+pub struct GpuPickingResults<'w, 's> {
+    frames: Res<'w, GpuPickingFrames>,
+    frame_count: Res<'w, FrameCount>,
+    entities: &'w Entities,
+    meshes: Res<'w, Assets<Mesh>>,
+    mesh_instances: Query<
+        'w,
+        's,
+        (&'static Handle<Mesh>, &'static GlobalTransform),
+        (Without<SkinnedMesh>, Without<MeshMorphWeights>),
+    >,
+}
+
+impl<'w, 's> GpuPickingResults<'w, 's> {
+    /// Returns the entity under `cursor` for the picking camera with the
+    /// highest [`Camera::order`] whose viewport contains `cursor`.
+    ///
+    /// If that camera doesn't clear its render target and has no mesh under
+    /// that pixel, the cameras below it are tried. See
+    /// [`route_gpu_picking_frames`].
+    ///
+    /// `cursor` is in physical pixels of the render target, as returned by
+    /// `Window::physical_cursor_position`. When cameras render to several
+    /// render targets, use [`GpuPickingResults::pick_target`] instead.
+    pub fn pick(&self, cursor: UVec2) -> Option<Entity> {
+        route_gpu_picking_frames(self.frames.iter(), None, cursor)
+            .into_iter()
+            .find_map(|frame| self.resolve(frame, cursor))
+    }
+
+    /// Like [`GpuPickingResults::pick`], but only considers the cameras that
+    /// render to `target`, such as the window the cursor is in.
+    pub fn pick_target(&self, target: &NormalizedRenderTarget, cursor: UVec2) -> Option<Entity> {
+        route_gpu_picking_frames(self.frames.iter(), Some(target), cursor)
+            .into_iter()
+            .find_map(|frame| self.resolve(frame, cursor))
+    }
+
+    /// Returns the picking camera with the highest [`Camera::order`] that
+    /// renders to `target` and whose viewport contains `cursor`, which is the
+    /// camera a pick at `cursor` is routed to first.
+    pub fn camera_at(&self, target: &NormalizedRenderTarget, cursor: UVec2) -> Option<Entity> {
+        route_gpu_picking_frames(self.frames.iter(), Some(target), cursor)
+            .first()
+            .map(|frame| frame.camera)
+    }
+
+    /// Returns the entity under `cursor` as seen by the given camera.
+    ///
+    /// `cursor` is in physical pixels of the camera's render target.
+    pub fn pick_camera(&self, camera: Entity, cursor: UVec2) -> Option<Entity> {
+        self.resolve(self.frames.get(camera)?, cursor)
+    }
+
+    /// Like [`GpuPickingResults::pick`], but also returns where the entity was
+    /// hit.
+    ///
+    /// Cameras without the [`GpuPickingDepth`] component never return a hit.
+    pub fn pick_hit(&self, cursor: UVec2) -> Option<GpuPickingHit> {
+        route_gpu_picking_frames(self.frames.iter(), None, cursor)
+            .into_iter()
+            .find_map(|frame| self.hit(frame, cursor))
+    }
+
+    /// Like [`GpuPickingResults::pick_camera`], but also returns where the
+    /// entity was hit.
+    ///
+    /// Returns `None` if the camera doesn't have the [`GpuPickingDepth`]
+    /// component.
+    pub fn pick_camera_hit(&self, camera: Entity, cursor: UVec2) -> Option<GpuPickingHit> {
+        self.hit(self.frames.get(camera)?, cursor)
+    }
+
+    /// Like [`GpuPickingResults::pick`], but returns the triangle under
+    /// `cursor`.
+    ///
+    /// Cameras without the [`GpuPickingTriangles`] component never return a
+    /// triangle.
+    pub fn pick_triangle(&self, cursor: UVec2) -> Option<GpuPickingTriangle> {
+        route_gpu_picking_frames(self.frames.iter(), None, cursor)
+            .into_iter()
+            .find_map(|frame| self.triangle(frame, cursor))
+    }
+
+    /// Returns the triangle under `cursor` as seen by the given camera.
+    ///
+    /// Returns `None` if the camera doesn't have the [`GpuPickingTriangles`]
+    /// component.
+    pub fn pick_camera_triangle(
+        &self,
+        camera: Entity,
+        cursor: UVec2,
+    ) -> Option<GpuPickingTriangle> {
+        self.triangle(self.frames.get(camera)?, cursor)
+    }
+
+    /// Returns true if at least one readback has been received for the given
+    /// camera, meaning [`GpuPickingResults::pick_camera`] can produce results.
+    pub fn is_ready(&self, camera: Entity) -> bool {
+        self.frames.get(camera).is_some()
+    }
+
+    /// Returns how many frames ago the picking data of the given camera was
+    /// rendered, or `None` if no readback has been received for it yet.
+    pub fn latency(&self, camera: Entity) -> Option<u32> {
+        let frame = self.frames.get(camera)?;
+        Some(self.frame_count.0.wrapping_sub(frame.frame))
+    }
+
+    fn hit(&self, frame: &GpuPickingFrame, cursor: UVec2) -> Option<GpuPickingHit> {
+        let entity = self.resolve(frame, cursor)?;
+        let view_position = frame.view_position(cursor)?;
+        Some(GpuPickingHit {
+            entity,
+            camera: frame.camera,
+            world_position: frame.world_from_view.transform_point3(view_position),
+            view_depth: -view_position.z,
+        })
+    }
+
+    fn triangle(&self, frame: &GpuPickingFrame, cursor: UVec2) -> Option<GpuPickingTriangle> {
+        let entity = self.resolve(frame, cursor)?;
+        let triangle_index = frame.triangle_index(cursor)?;
+        let barycentrics = self
+            .hit(frame, cursor)
+            .and_then(|hit| self.barycentrics(entity, triangle_index, hit.world_position));
+        Some(GpuPickingTriangle {
+            entity,
+            camera: frame.camera,
+            triangle_index,
+            barycentrics,
+        })
+    }
+
+    /// Computes the barycentric coordinates of `world_position` in a triangle
+    /// of the mesh of `entity`, using its current transform.
+    fn barycentrics(
+        &self,
+        entity: Entity,
+        triangle_index: u32,
+        world_position: Vec3,
+    ) -> Option<Vec3> {
+        let (mesh, transform) = self.mesh_instances.get(entity).ok()?;
+        let mesh = self.meshes.get(mesh)?;
+        if mesh.primitive_topology() != PrimitiveTopology::TriangleList {
+            return None;
+        }
+        let positions = mesh.attribute(Mesh::ATTRIBUTE_POSITION)?.as_float3()?;
+
+        let first = triangle_index as usize * 3;
+        let vertex = |i: usize| -> Option<Vec3> {
+            let index = match mesh.indices() {
+                Some(Indices::U16(indices)) => *indices.get(first + i)? as usize,
+                Some(Indices::U32(indices)) => *indices.get(first + i)? as usize,
+                None => first + i,
+            };
+            Some(transform.transform_point(Vec3::from(*positions.get(index)?)))
+        };
+        let (a, b, c) = (vertex(0)?, vertex(1)?, vertex(2)?);
+
+        let (ab, ac, ap) = (b - a, c - a, world_position - a);
+        let (d00, d01, d11) = (ab.dot(ab), ab.dot(ac), ac.dot(ac));
+        let (d20, d21) = (ap.dot(ab), ap.dot(ac));
+        let denominator = d00 * d11 - d01 * d01;
+        if denominator == 0.0 {
+            return None;
+        }
+        let v = (d11 * d20 - d01 * d21) / denominator;
+        let w = (d00 * d21 - d01 * d20) / denominator;
+        Some(Vec3::new(1.0 - v - w, v, w))
+    }
+
+    /// Returns the entity drawn under `cursor` in `frame`, unless it was
+    /// despawned since.
+    fn resolve(&self, frame: &GpuPickingFrame, cursor: UVec2) -> Option<Entity> {
+        frame
+            .entity(cursor)
+            .filter(|entity| self.entities.contains(*entity))
+    }
+}
+
+/// Marks a view that GPU picking renders for.
+#[derive(Component, Clone, Copy)]
+pub struct ExtractedGpuPickingCamera {
+    /// Whether depth is read back too. See [`GpuPickingDepth`].
+    pub depth: bool,
+    /// Whether triangle indices are read back too. See [`GpuPickingTriangles`].
+    pub triangles: bool,
+    /// The resolution of the picking textures relative to the render target.
+    /// See [`GpuPicking::scale`].
+    pub scale: f32,
+    /// The number of readback buffers in the ring of this camera. See
+    /// [`GpuPicking::readback_buffer_count`].
+    pub readback_buffer_count: usize,
+    /// Whether alpha masked materials are tested. See [`GpuPicking::alpha_mask`].
+    pub alpha_mask: bool,
+    /// The alpha below which blended materials are picked through. See
+    /// [`GpuPicking::transparency_threshold`].
+    pub transparency_threshold: Option<f32>,
+    /// The layers this camera picks. See [`PickingLayers`].
+    pub layers: RenderLayers,
+}
+
+/// The picking layers of every entity that has [`PickingVisibility`] or
+/// [`PickingLayers`], extracted from the main world.
+///
+/// Entities that aren't in the map are pickable on the default layer. Hidden
+/// entities map to `None`.
+#[derive(Resource, Default)]
+pub struct ExtractedPickingLayers(pub EntityHashMap<Option<RenderLayers>>);
+
+impl ExtractedPickingLayers {
+    /// Returns whether `entity` can be picked by a camera on `camera_layers`.
+    pub fn is_pickable(&self, entity: Entity, camera_layers: &RenderLayers) -> bool {
+        match self.0.get(&entity) {
+            Some(Some(layers)) => layers.intersects(camera_layers),
+            Some(None) => false,
+            None => RenderLayers::default().intersects(camera_layers),
+        }
+    }
+}
+
+/// The textures a picking camera renders into.
+#[derive(Component)]
+pub struct VisibleMeshIdTextures {
+    /// The entity index of the closest mesh at each pixel, plus one. See
+    /// [`GPU_PICKING_MESH_ID_FORMAT`], or [`GPU_PICKING_MESH_ID_TRIANGLE_FORMAT`]
+    /// if triangle indices are written too.
+    pub mesh_id: CachedTexture,
+    /// The depth buffer used while rendering `mesh_id`.
+    pub depth: CachedTexture,
+    /// A copy of the depth of each pixel, if the camera has the
+    /// [`GpuPickingDepth`] component. See [`GPU_PICKING_DEPTH_COPY_FORMAT`].
+    pub depth_copy: Option<CachedTexture>,
+    /// Whether `mesh_id` also holds triangle indices.
+    pub triangles: bool,
+    /// The size of all textures.
+    pub size: UVec2,
+}
+
+/// The entity index texture of a [`GpuPicking`] view, for custom render graph
+/// nodes.
+///
+/// Each pixel holds the index of the entity drawn there plus one, or zero if
+/// no mesh covers it, as an unsigned integer in the first channel. If the
+/// camera has [`GpuPickingTriangles`], the second channel holds the index of
+/// the triangle. Bind [`ViewMeshIdTexture::view`] as a
+/// `texture_2d<u32>` with [`TextureSampleType::Uint`], and read it with
+/// `textureLoad`, to draw selection outlines or other per entity effects
+/// without rendering the meshes again.
+///
+/// The texture is written by the [`NodePbr::GpuPicking`] node of the
+/// [`Core3d`] graph, so nodes reading it must run after it. It covers the whole
+/// render target, scaled by [`ViewMeshIdTexture::scale`]: multiply positions in
+/// the render target by the scale to get the pixel to load.
+#[derive(Component, Clone)]
+pub struct ViewMeshIdTexture {
+    /// The texture the entity indices are written to.
+    pub texture: Texture,
+    /// A view of the whole texture.
+    pub view: TextureView,
+    /// Either [`GPU_PICKING_MESH_ID_FORMAT`], or
+    /// [`GPU_PICKING_MESH_ID_TRIANGLE_FORMAT`] if triangle indices are written
+    /// too.
+    pub format: TextureFormat,
+    /// The size of the texture, in pixels.
+    pub size: UVec2,
+    /// The resolution of the texture relative to the render target. See
+    /// [`GpuPicking::scale`].
+    pub scale: f32,
+}
+
+/// The index of the readback buffer, in the ring of the view in
+/// [`GpuPickingReadbacks`], that the [`EntityIndexBufferCopyNode`] copies into
+/// this frame.
+///
+/// Views without this component aren't read back this frame.
+#[derive(Component, Clone, Copy)]
+pub struct CurrentGpuPickingBufferIndex(pub usize);
+
+fn extract_gpu_picking_cameras(
+    mut commands: Commands,
+    render_device: Res<RenderDevice>,
+    cameras: Extract<
+        Query<
+            (
+                Entity,
+                &Camera,
+                &GpuPicking,
+                Has<GpuPickingDepth>,
+                Has<GpuPickingTriangles>,
+                Option<&PickingLayers>,
+            ),
+            With<Camera3d>,
+        >,
+    >,
+) {
+    let supports_triangles = render_device
+        .features()
+        .contains(WgpuFeatures::SHADER_PRIMITIVE_INDEX);
+
+    for (entity, camera, gpu_picking, depth, triangles, layers) in &cameras {
+        if triangles && !supports_triangles {
+            warn_once!(
+                "GpuPickingTriangles requires the SHADER_PRIMITIVE_INDEX feature, which isn't \
+                supported by the current device. Triangle indices won't be available."
+            );
+        }
+        if camera.is_active {
+            commands.get_or_spawn(entity).insert((
+                ExtractedGpuPickingCamera {
+                    depth,
+                    triangles: triangles && supports_triangles,
+                    scale: gpu_picking.scale.clamp(f32::EPSILON, 1.0),
+                    readback_buffer_count: gpu_picking.readback_buffer_count.max(1),
+                    alpha_mask: gpu_picking.alpha_mask,
+                    transparency_threshold: gpu_picking
+                        .transparency_threshold
+                        .map(|threshold| threshold.clamp(0.0, 1.0)),
+                    layers: layers.map(|layers| layers.0).unwrap_or_default(),
+                },
+                BinnedRenderPhase::<MeshId3d>::default(),
+            ));
+        }
+    }
+}
+
+fn extract_picking_layers(
+    mut extracted_picking_layers: ResMut<ExtractedPickingLayers>,
+    entities: Extract<
+        Query<
+            (Entity, Option<&PickingVisibility>, Option<&PickingLayers>),
+            (
+                Or<(With<PickingVisibility>, With<PickingLayers>)>,
+                Without<Camera>,
+            ),
+        >,
+    >,
+) {
+    extracted_picking_layers.0.clear();
+    for (entity, visibility, layers) in &entities {
+        let layers = match visibility {
+            Some(PickingVisibility::Hidden) => None,
+            _ => Some(layers.map(|layers| layers.0).unwrap_or_default()),
+        };
+        extracted_picking_layers.0.insert(entity, layers);
+    }
+}
+
+fn prepare_gpu_picking_textures(
+    mut commands: Commands,
+    mut texture_cache: ResMut<TextureCache>,
+    render_device: Res<RenderDevice>,
+    views: Query<(Entity, &ExtractedCamera, &ExtractedGpuPickingCamera)>,
+) {
+    for (entity, camera, gpu_picking_camera) in &views {
+        let Some(target_size) = camera.physical_target_size else {
+            continue;
+        };
+        let size = (target_size.as_vec2() * gpu_picking_camera.scale)
+            .ceil()
+            .as_uvec2()
+            .max(UVec2::ONE);
+        let extent = Extent3d {
+            width: size.x,
+            height: size.y,
+            depth_or_array_layers: 1,
+        };
+
+        let mesh_id_format = if gpu_picking_camera.triangles {
+            GPU_PICKING_MESH_ID_TRIANGLE_FORMAT
+        } else {
+            GPU_PICKING_MESH_ID_FORMAT
+        };
+        let mesh_id = texture_cache.get_for_view(
+            &render_device,
+            entity,
+            TextureDescriptor {
+                label: Some("gpu_picking_mesh_id_texture"),
+                size: extent,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: mesh_id_format,
+                // Also bound by custom nodes through `ViewMeshIdTexture`.
+                usage: TextureUsages::RENDER_ATTACHMENT
+                    | TextureUsages::COPY_SRC
+                    | TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            },
+        );
+        let depth = texture_cache.get_for_view(
+            &render_device,
+            entity,
+            TextureDescriptor {
+                label: Some("gpu_picking_depth_texture"),
+                size: extent,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: CORE_3D_DEPTH_FORMAT,
+                usage: TextureUsages::RENDER_ATTACHMENT,
+                view_formats: &[],
+            },
+        );
+        let depth_copy = gpu_picking_camera.depth.then(|| {
+            texture_cache.get_for_view(
+                &render_device,
+                entity,
+                TextureDescriptor {
+                    label: Some("gpu_picking_depth_copy_texture"),
+                    size: extent,
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: TextureDimension::D2,
+                    format: GPU_PICKING_DEPTH_COPY_FORMAT,
+                    usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC,
+                    view_formats: &[],
+                },
+            )
+        });
+
+        commands.entity(entity).insert((
+            ViewMeshIdTexture {
+                texture: mesh_id.texture.clone(),
+                view: mesh_id.default_view.clone(),
+                format: mesh_id_format,
+                size,
+                scale: gpu_picking_camera.scale,
+            },
+            VisibleMeshIdTextures {
+                mesh_id,
+                depth,
+                depth_copy,
+                triangles: gpu_picking_camera.triangles,
+                size,
+            },
+        ));
+    }
+}
+
+/// Sends finished [`GpuPickingFrame`]s from the render world to the main world.
+#[derive(Resource)]
+struct GpuPickingSender(Sender<GpuPickingFrame>);
+
+/// What a [`GpuPickingReadbackBuffer`] has room for.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct GpuPickingReadbackLayout {
+    /// The size of the textures copied into the buffer, in pixels.
+    pub size: UVec2,
+    /// Whether the buffer has room for a depth copy.
+    pub depth: bool,
+    /// Whether the entity indices are interleaved with triangle indices.
+    pub triangles: bool,
+}
+
+/// A buffer that picking data is copied into so that it can be read back.
+///
+/// The entity indices (interleaved with triangle indices, if any) are stored
+/// first, followed by the depth copy if there is one.
+pub struct GpuPickingReadbackBuffer {
+    /// The buffer itself.
+    pub buffer: ReadbackBuffer,
+    /// What the buffer has room for.
+    pub layout: GpuPickingReadbackLayout,
+    /// The number of bytes per row of entity indices, including padding.
+    pub mesh_id_padded_bytes_per_row: u32,
+    /// The number of bytes per row of the depth copy, including padding.
+    pub depth_padded_bytes_per_row: u32,
+    order: isize,
+    frame: u32,
+    scale: f32,
+    viewport: URect,
+    target: Option<NormalizedRenderTarget>,
+    clears: bool,
+    world_from_view: Mat4,
+    view_from_clip: Mat4,
+    entities: GpuPickingEntities,
+}
+
+impl GpuPickingReadbackBuffer {
+    fn new(render_device: &RenderDevice, layout: GpuPickingReadbackLayout) -> Self {
+        let mesh_id_bytes_per_pixel = if layout.triangles { 8 } else { 4 };
+        let mesh_id_padded_bytes_per_row = RenderDevice::align_copy_bytes_per_row(
+            layout.size.x as usize * mesh_id_bytes_per_pixel,
+        ) as u32;
+        let depth_padded_bytes_per_row =
+            RenderDevice::align_copy_bytes_per_row(layout.size.x as usize * 4) as u32;
+        let mut size = mesh_id_padded_bytes_per_row as u64 * layout.size.y as u64;
+        if layout.depth {
+            size += depth_padded_bytes_per_row as u64 * layout.size.y as u64;
+        }
+        let buffer = ReadbackBuffer::new(render_device, "gpu_picking_readback_buffer", size);
+        Self {
+            buffer,
+            layout,
+            mesh_id_padded_bytes_per_row,
+            depth_padded_bytes_per_row,
+            order: 0,
+            frame: 0,
+            scale: 1.0,
+            viewport: URect::default(),
+            target: None,
+            clears: false,
+            world_from_view: Mat4::IDENTITY,
+            view_from_clip: Mat4::IDENTITY,
+            entities: default(),
+        }
+    }
+
+    /// The offset of the depth copy in the buffer, in bytes.
+    pub fn depth_offset(&self) -> u64 {
+        self.mesh_id_padded_bytes_per_row as u64 * self.layout.size.y as u64
+    }
+
+    /// Copies the mapped contents of the buffer to a [`GpuPickingFrame`],
+    /// stripping the row padding, and unmaps the buffer.
+    fn read(&self, camera: Entity) -> GpuPickingFrame {
+        let (mesh_ids, depth) = self.buffer.read(|data| {
+            let (mesh_id_data, depth_data) = data.split_at(self.depth_offset() as usize);
+            let values_per_pixel = if self.layout.triangles { 2 } else { 1 };
+            (
+                self.read_rows::<u32>(
+                    mesh_id_data,
+                    self.mesh_id_padded_bytes_per_row,
+                    values_per_pixel,
+                ),
+                self.layout
+                    .depth
+                    .then(|| self.read_rows(depth_data, self.depth_padded_bytes_per_row, 1)),
+            )
+        });
+
+        let (entity_indices, triangle_indices) = if self.layout.triangles {
+            let (entity_indices, triangle_indices) = mesh_ids
+                .chunks_exact(2)
+                .map(|pixel| (pixel[0], pixel[1]))
+                .unzip();
+            (entity_indices, Some(triangle_indices))
+        } else {
+            (mesh_ids, None)
+        };
+
+        GpuPickingFrame {
+            camera,
+            order: self.order,
+            frame: self.frame,
+            size: self.layout.size,
+            scale: self.scale,
+            entity_indices,
+            entities: self.entities.clone(),
+            depth,
+            triangle_indices,
+            viewport: self.viewport,
+            target: self.target.clone(),
+            clears: self.clears,
+            world_from_view: self.world_from_view,
+            view_from_clip: self.view_from_clip,
+        }
+    }
+
+    fn read_rows<T: bytemuck::Pod>(
+        &self,
+        data: &[u8],
+        padded_bytes_per_row: u32,
+        values_per_pixel: usize,
+    ) -> Vec<T> {
+        let row_values = self.layout.size.x as usize * values_per_pixel;
+        let mut values = Vec::with_capacity(row_values * self.layout.size.y as usize);
+        for row in data
+            .chunks(padded_bytes_per_row as usize)
+            .take(self.layout.size.y as usize)
+        {
+            values.extend_from_slice(bytemuck::cast_slice(
+                &row[..row_values * std::mem::size_of::<T>()],
+            ));
+        }
+        values
+    }
+}
+
+/// The ring of readback buffers of a picking view.
+///
+/// Frames copy into the buffers in order, so that they are read back in the
+/// order they were rendered.
+#[derive(Default)]
+pub struct GpuPickingReadbackRing {
+    buffers: Vec<GpuPickingReadbackBuffer>,
+    /// The index of the buffer the next frame copies into.
+    next: usize,
+}
+
+impl GpuPickingReadbackRing {
+    /// Returns the buffer with the given index.
+    pub fn get(&self, index: usize) -> Option<&GpuPickingReadbackBuffer> {
+        self.buffers.get(index)
+    }
+
+    /// Returns the number of buffers that are waiting on the GPU.
+    pub fn frames_in_flight(&self) -> usize {
+        self.buffers
+            .iter()
+            .filter(|readback| !readback.buffer.is_free())
+            .count()
+    }
+
+    /// Claims the next buffer of the ring, allocating it if needed, or returns
+    /// `None` if it is still waiting on the GPU.
+    fn claim(
+        &mut self,
+        render_device: &RenderDevice,
+        layout: GpuPickingReadbackLayout,
+        count: usize,
+    ) -> Option<usize> {
+        if self.buffers.len() > count {
+            self.buffers.truncate(count);
+        }
+        if self.next >= count {
+            self.next = 0;
+        }
+
+        let index = self.next;
+        match self.buffers.get(index) {
+            None => self
+                .buffers
+                .push(GpuPickingReadbackBuffer::new(render_device, layout)),
+            Some(readback) if !readback.buffer.is_free() => return None,
+            Some(buffer) if buffer.layout != layout => {
+                self.buffers[index] = GpuPickingReadbackBuffer::new(render_device, layout);
+            }
+            Some(_) => {}
+        }
+        self.next = (index + 1) % count;
+        Some(index)
+    }
+}
+
+/// The readback buffers of every picking view.
+#[derive(Resource, Default)]
+pub struct GpuPickingReadbacks {
+    views: EntityHashMap<GpuPickingReadbackRing>,
+}
+
+impl GpuPickingReadbacks {
+    /// Returns the ring of readback buffers of a view.
+    pub fn ring(&self, view: Entity) -> Option<&GpuPickingReadbackRing> {
+        self.views.get(&view)
+    }
+
+    /// Returns the readback buffer with the given index for a view.
+    pub fn get(&self, view: Entity, index: usize) -> Option<&GpuPickingReadbackBuffer> {
+        self.ring(view)?.get(index)
+    }
+}
+
+fn prepare_gpu_picking_readbacks(
+    mut commands: Commands,
+    render_device: Res<RenderDevice>,
+    frame_count: Res<FrameCount>,
+    mut readbacks: ResMut<GpuPickingReadbacks>,
+    views: Query<(
+        Entity,
+        &ExtractedCamera,
+        &ExtractedView,
+        &ExtractedGpuPickingCamera,
+        &VisibleMeshIdTextures,
+        Option<&GpuPickingEntities>,
+    )>,
+) {
+    readbacks.views.retain(|view, _| views.contains(*view));
+
+    for (entity, camera, view, gpu_picking_camera, textures, drawn_entities) in &views {
+        let ring = readbacks.views.entry(entity).or_default();
+        let layout = GpuPickingReadbackLayout {
+            size: textures.size,
+            depth: textures.depth_copy.is_some(),
+            triangles: textures.triangles,
+        };
+
+        // The GPU is running as many frames ahead as there are buffers, skip
+        // this frame instead of waiting on it.
+        let Some(index) = ring.claim(
+            &render_device,
+            layout,
+            gpu_picking_camera.readback_buffer_count,
+        ) else {
+            continue;
+        };
+
+        let readback = &mut ring.buffers[index];
+        readback.buffer.start_copy();
+        readback.order = camera.order;
+        readback.frame = frame_count.0;
+        readback.scale = gpu_picking_camera.scale;
+        let origin = UVec2::new(view.viewport.x, view.viewport.y);
+        let size = UVec2::new(view.viewport.z, view.viewport.w);
+        readback.viewport = URect::from_corners(origin, origin + size);
+        readback.target.clone_from(&camera.target);
+        readback.clears = !matches!(camera.clear_color, ClearColorConfig::None);
+        readback.world_from_view = view.transform.compute_matrix();
+        readback.view_from_clip = view.projection.inverse();
+        readback.entities = drawn_entities.cloned().unwrap_or_default();
+        commands
+            .entity(entity)
+            .insert(CurrentGpuPickingBufferIndex(index));
+    }
+}
+
+/// Starts mapping the buffers that were copied into this frame, and sends the
+/// contents of the buffers that finished mapping to the main world.
+fn map_gpu_picking_readbacks(
+    render_device: Res<RenderDevice>,
+    mut readbacks: ResMut<GpuPickingReadbacks>,
+    sender: Res<GpuPickingSender>,
+) {
+    render_device.poll(Maintain::Poll);
+
+    for (&camera, ring) in &mut readbacks.views {
+        for readback in &mut ring.buffers {
+            match readback.buffer.poll() {
+                None => {}
+                Some(Err(err)) => error!("Failed to map GPU picking buffer: {err}"),
+                Some(Ok(())) => {
+                    // The main world may have been dropped during shutdown.
+                    let _ = sender.0.send(readback.read(camera));
+                }
+            }
+        }
+    }
+}
+
+/// Render phase for the GPU picking pass.
+pub struct MeshId3d {
+    /// Information that separates items into bins.
+    pub key: MeshId3dBinKey,
+
+    /// An entity from which Bevy fetches data common to all instances in this
+    /// batch, such as the mesh.
+    pub representative_entity: Entity,
+
+    pub batch_range: Range<u32>,
+    pub extra_index: PhaseItemExtraIndex,
+}
+
+/// The data used to bin each mesh in the GPU picking pass.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MeshId3dBinKey {
+    /// The ID of the GPU pipeline.
+    pub pipeline: CachedRenderPipelineId,
+
+    /// The function used to draw the mesh.
+    pub draw_function: DrawFunctionId,
+
+    /// The ID of the mesh.
+    pub asset_id: AssetId<Mesh>,
+
+    /// The ID of the material bind group, if the alpha of the material is
+    /// tested.
+    pub material_bind_group_id: Option<BindGroupId>,
+}
+
+impl PhaseItem for MeshId3d {
+    #[inline]
+    fn entity(&self) -> Entity {
+        self.representative_entity
+    }
+
+    #[inline]
+    fn draw_function(&self) -> DrawFunctionId {
+        self.key.draw_function
+    }
+
+    #[inline]
+    fn batch_range(&self) -> &Range<u32> {
+        &self.batch_range
+    }
+
+    #[inline]
+    fn batch_range_mut(&mut self) -> &mut Range<u32> {
+        &mut self.batch_range
+    }
+
+    #[inline]
+    fn extra_index(&self) -> PhaseItemExtraIndex {
+        self.extra_index
+    }
+
+    #[inline]
+    fn batch_range_and_extra_index_mut(&mut self) -> (&mut Range<u32>, &mut PhaseItemExtraIndex) {
+        (&mut self.batch_range, &mut self.extra_index)
+    }
+}
+
+impl BinnedPhaseItem for MeshId3d {
+    type BinKey = MeshId3dBinKey;
+
+    #[inline]
+    fn new(
+        key: Self::BinKey,
+        representative_entity: Entity,
+        batch_range: Range<u32>,
+        extra_index: PhaseItemExtraIndex,
+    ) -> Self {
+        MeshId3d {
+            key,
+            representative_entity,
+            batch_range,
+            extra_index,
+        }
+    }
+}
+
+impl CachedRenderPipelinePhaseItem for MeshId3d {
+    #[inline]
+    fn cached_pipeline(&self) -> CachedRenderPipelineId {
+        self.key.pipeline
+    }
+}
+
+/// The pipeline used to render entity indices for GPU picking.
+#[derive(Resource, Clone)]
+pub struct GpuPickingPipeline {
+    pub view_layout: BindGroupLayout,
+    pub mesh_layouts: MeshLayouts,
+    /// The layout of the [`StandardMaterial`] bind group, used by pipelines
+    /// that test the alpha of the material.
+    pub material_layout: BindGroupLayout,
+}
+
+impl FromWorld for GpuPickingPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+
+        let view_layout = render_device.create_bind_group_layout(
+            "gpu_picking_view_layout",
+            &BindGroupLayoutEntries::single(
+                ShaderStages::VERTEX_FRAGMENT,
+                uniform_buffer::<ViewUniform>(true),
+            ),
+        );
+
+        GpuPickingPipeline {
+            view_layout,
+            mesh_layouts: world.resource::<MeshPipeline>().mesh_layouts.clone(),
+            material_layout: StandardMaterial::bind_group_layout(render_device),
+        }
+    }
+}
+
+/// The key used to specialize the [`GpuPickingPipeline`].
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct GpuPickingPipelineKey {
+    pub mesh_key: MeshPipelineKey,
+    /// Whether depth is written to a [`GPU_PICKING_DEPTH_COPY_FORMAT`] target.
+    pub depth: bool,
+    /// Whether triangle indices are written next to entity indices, in a
+    /// [`GPU_PICKING_MESH_ID_TRIANGLE_FORMAT`] target.
+    pub triangles: bool,
+    /// Whether the index of the mesh instance, in the mesh uniform buffer, is
+    /// written instead of the entity index. Used by the experimental
+    /// visibility buffer.
+    pub instance_indices: bool,
+    /// How the alpha of the [`StandardMaterial`] of the mesh is tested.
+    pub alpha_test: GpuPickingAlphaTest,
+}
+
+impl PipelineKeyBits for GpuPickingPipelineKey {
+    fn bits(&self) -> u64 {
+        self.mesh_key.bits()
+    }
+
+    fn without_bits(&self) -> Self {
+        Self {
+            mesh_key: MeshPipelineKey::NONE,
+            ..*self
+        }
+    }
+}
+
+/// How the GPU picking pass tests the alpha of the [`StandardMaterial`] of a
+/// mesh, discarding the fragments that shouldn't be picked.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum GpuPickingAlphaTest {
+    /// The material is ignored, and the whole mesh is picked.
+    None,
+    /// Fragments with an alpha below the alpha cutoff of the material are
+    /// discarded, like when an [`AlphaMode::Mask`] material is drawn.
+    Mask,
+    /// Fragments with an alpha below the given threshold are discarded. See
+    /// [`GpuPicking::transparency_threshold`].
+    Threshold(FloatOrd),
+}
+
+impl SpecializedMeshPipeline for GpuPickingPipeline {
+    type Key = GpuPickingPipelineKey;
+
+    fn specialize(
+        &self,
+        key: Self::Key,
+        layout: &MeshVertexBufferLayoutRef,
+    ) -> Result<RenderPipelineDescriptor, SpecializedMeshPipelineError> {
+        let mut shader_defs = Vec::new();
+        let mut vertex_attributes = vec![Mesh::ATTRIBUTE_POSITION.at_shader_location(0)];
+
+        let mesh_layout = setup_morph_and_skinning_defs(
+            &self.mesh_layouts,
+            layout,
+            5,
+            &key.mesh_key,
+            &mut shader_defs,
+            &mut vertex_attributes,
+        );
+        let mut bind_group_layout = vec![self.view_layout.clone(), mesh_layout];
+
+        if key.alpha_test != GpuPickingAlphaTest::None {
+            shader_defs.push("GPU_PICKING_ALPHA_TEST".into());
+            bind_group_layout.push(self.material_layout.clone());
+
+            if layout.0.contains(Mesh::ATTRIBUTE_UV_0) {
+                shader_defs.push("VERTEX_UVS".into());
+                vertex_attributes.push(Mesh::ATTRIBUTE_UV_0.at_shader_location(1));
+            }
+        }
+        match key.alpha_test {
+            GpuPickingAlphaTest::None => {}
+            GpuPickingAlphaTest::Mask => shader_defs.push("GPU_PICKING_ALPHA_MASK".into()),
+            // Shader defs can't hold floats, so the threshold is passed as its
+            // bits and converted back in the shader.
+            GpuPickingAlphaTest::Threshold(threshold) => shader_defs.push(ShaderDefVal::UInt(
+                "GPU_PICKING_ALPHA_THRESHOLD".into(),
+                threshold.0.to_bits(),
+            )),
+        }
+
+        if key.instance_indices {
+            shader_defs.push("GPU_PICKING_INSTANCE_INDEX".into());
+        }
+
+        let mesh_id_format = if key.triangles {
+            shader_defs.push("GPU_PICKING_TRIANGLES".into());
+            GPU_PICKING_MESH_ID_TRIANGLE_FORMAT
+        } else {
+            GPU_PICKING_MESH_ID_FORMAT
+        };
+        let mut targets = vec![Some(ColorTargetState {
+            format: mesh_id_format,
+            blend: None,
+            write_mask: ColorWrites::ALL,
+        })];
+        if key.depth {
+            shader_defs.push("GPU_PICKING_DEPTH".into());
+            targets.push(Some(ColorTargetState {
+                format: GPU_PICKING_DEPTH_COPY_FORMAT,
+                blend: None,
+                write_mask: ColorWrites::ALL,
+            }));
+        }
+
+        let vertex_buffer_layout = layout.0.get_layout(&vertex_attributes)?;
+
+        Ok(RenderPipelineDescriptor {
+            label: Some("gpu_picking_pipeline".into()),
+            layout: bind_group_layout,
+            push_constant_ranges: vec![],
+            vertex: VertexState {
+                shader: GPU_PICKING_SHADER_HANDLE,
+                shader_defs: shader_defs.clone(),
+                entry_point: "vertex".into(),
+                buffers: vec![vertex_buffer_layout],
+            },
+            fragment: Some(FragmentState {
+                shader: GPU_PICKING_SHADER_HANDLE,
+                shader_defs,
+                entry_point: "fragment".into(),
+                targets,
+            }),
+            primitive: PrimitiveState {
+                topology: key.mesh_key.primitive_topology(),
+                // The material isn't known here, so don't cull anything to
+                // keep double sided meshes pickable from both sides.
+                cull_mode: None,
+                ..default()
+            },
+            depth_stencil: Some(DepthStencilState {
+                format: CORE_3D_DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: CompareFunction::GreaterEqual,
+                stencil: default(),
+                bias: default(),
+            }),
+            multisample: MultisampleState::default(),
+        })
+    }
+}
+
+/// The view bind group of the GPU picking pass.
+#[derive(Resource, Default)]
+pub struct GpuPickingViewBindGroup(pub Option<BindGroup>);
+
+fn prepare_gpu_picking_view_bind_group(
+    render_device: Res<RenderDevice>,
+    gpu_picking_pipeline: Res<GpuPickingPipeline>,
+    view_uniforms: Res<ViewUniforms>,
+    mut gpu_picking_view_bind_group: ResMut<GpuPickingViewBindGroup>,
+) {
+    if let Some(view_binding) = view_uniforms.uniforms.binding() {
+        gpu_picking_view_bind_group.0 = Some(render_device.create_bind_group(
+            "gpu_picking_view_bind_group",
+            &gpu_picking_pipeline.view_layout,
+            &BindGroupEntries::single(view_binding),
+        ));
+    }
+}
+
+pub struct SetGpuPickingViewBindGroup<const I: usize>;
+impl<P: PhaseItem, const I: usize> RenderCommand<P> for SetGpuPickingViewBindGroup<I> {
+    type Param = SRes<GpuPickingViewBindGroup>;
+    type ViewQuery = Read<ViewUniformOffset>;
+    type ItemQuery = ();
+
+    #[inline]
+    fn render<'w>(
+        _item: &P,
+        view_uniform_offset: &'_ ViewUniformOffset,
+        _entity: Option<()>,
+        gpu_picking_view_bind_group: SystemParamItem<'w, '_, Self::Param>,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        let Some(bind_group) = gpu_picking_view_bind_group.into_inner().0.as_ref() else {
+            return RenderCommandResult::Failure;
+        };
+        pass.set_bind_group(I, bind_group, &[view_uniform_offset.offset]);
+
+        RenderCommandResult::Success
+    }
+}
+
+pub type DrawGpuPicking = (
+    SetItemPipeline,
+    SetGpuPickingViewBindGroup<0>,
+    SetMeshBindGroup<1>,
+    DrawMesh,
+);
+
+/// Draws a mesh for GPU picking, testing the alpha of its [`StandardMaterial`].
+pub type DrawGpuPickingAlphaTest = (
+    SetItemPipeline,
+    SetGpuPickingViewBindGroup<0>,
+    SetMeshBindGroup<1>,
+    SetMaterialBindGroup<StandardMaterial, 2>,
+    DrawMesh,
+);
+
+#[allow(clippy::too_many_arguments)]
+pub fn queue_gpu_picking_meshes(
+    mut commands: Commands,
+    draw_functions: Res<DrawFunctions<MeshId3d>>,
+    gpu_picking_pipeline: Res<GpuPickingPipeline>,
+    mut pipelines: ResMut<SpecializedMeshPipelines<GpuPickingPipeline>>,
+    pipeline_cache: Res<PipelineCache>,
+    render_meshes: Res<RenderAssets<GpuMesh>>,
+    render_mesh_instances: Res<RenderMeshInstances>,
+    render_lightmaps: Res<RenderLightmaps>,
+    render_materials: Res<RenderAssets<PreparedMaterial<StandardMaterial>>>,
+    render_material_instances: Res<RenderMaterialInstances<StandardMaterial>>,
+    picking_layers: Res<ExtractedPickingLayers>,
+    mut views: Query<(
+        Entity,
+        &ExtractedGpuPickingCamera,
+        &VisibleEntities,
+        &mut BinnedRenderPhase<MeshId3d>,
+    )>,
+) {
+    let draw_gpu_picking = draw_functions.read().id::<DrawGpuPicking>();
+    let draw_gpu_picking_alpha_test = draw_functions.read().id::<DrawGpuPickingAlphaTest>();
+
+    for (view_entity, gpu_picking_camera, visible_entities, mut phase) in &mut views {
+        let mut drawn_entities = Vec::new();
+        for visible_entity in visible_entities.iter::<WithMesh>() {
+            if !picking_layers.is_pickable(*visible_entity, &gpu_picking_camera.layers) {
+                continue;
+            }
+
+            let Some(mesh_instance) = render_mesh_instances.render_mesh_queue_data(*visible_entity)
+            else {
+                continue;
+            };
+            let Some(mesh) = render_meshes.get(mesh_instance.mesh_asset_id) else {
+                continue;
+            };
+
+            let mut mesh_key = MeshPipelineKey::from_bits_retain(mesh.key_bits.bits());
+
+            // The lightmap isn't used, but `SetMeshBindGroup` binds the
+            // lightmapped mesh bind group, so the layout has to match.
+            if render_lightmaps
+                .render_lightmaps
+                .contains_key(visible_entity)
+            {
+                mesh_key |= MeshPipelineKey::LIGHTMAPPED;
+            }
+
+            let material = render_material_instances
+                .get(visible_entity)
+                .and_then(|material_asset_id| render_materials.get(*material_asset_id));
+            let alpha_test = match (
+                material.map(|material| material.properties.alpha_mode),
+                gpu_picking_camera.transparency_threshold,
+            ) {
+                (Some(AlphaMode::Mask(_) | AlphaMode::Hashed), _)
+                    if gpu_picking_camera.alpha_mask =>
+                {
+                    GpuPickingAlphaTest::Mask
+                }
+                (
+                    Some(
+                        AlphaMode::Blend
+                        | AlphaMode::Premultiplied
+                        | AlphaMode::Add
+                        | AlphaMode::Multiply,
+                    ),
+                    Some(threshold),
+                ) => GpuPickingAlphaTest::Threshold(FloatOrd(threshold)),
+                _ => GpuPickingAlphaTest::None,
+            };
+            let (draw_function, material_bind_group_id) = match (alpha_test, material) {
+                (GpuPickingAlphaTest::None, _) | (_, None) => (draw_gpu_picking, None),
+                (_, Some(material)) => {
+                    (draw_gpu_picking_alpha_test, material.get_bind_group_id().0)
+                }
+            };
+
+            let pipeline_id = match pipelines.specialize(
+                &pipeline_cache,
+                &gpu_picking_pipeline,
+                GpuPickingPipelineKey {
+                    mesh_key,
+                    depth: gpu_picking_camera.depth,
+                    triangles: gpu_picking_camera.triangles,
+                    instance_indices: false,
+                    alpha_test,
+                },
+                &mesh.layout,
+            ) {
+                Ok(id) => id,
+                Err(err) => {
+                    error!("{}", err);
+                    continue;
+                }
+            };
+
+            phase.add(
+                MeshId3dBinKey {
+                    pipeline: pipeline_id,
+                    draw_function,
+                    asset_id: mesh_instance.mesh_asset_id,
+                    material_bind_group_id,
+                },
+                *visible_entity,
+                mesh_instance.should_batch(),
+            );
+            drawn_entities.push(*visible_entity);
+        }
+
+        commands
+            .entity(view_entity)
+            .insert(GpuPickingEntities::new(drawn_entities));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs::{entity::Entity, world::World};
+    use bevy_math::{Mat4, URect, UVec2};
+    use bevy_render::camera::{ManualTextureViewHandle, NormalizedRenderTarget};
+    use bevy_utils::default;
+
+    use super::{route_gpu_picking_frames, GpuPickingEntities, GpuPickingFrame};
+
+    const TARGET_SIZE: UVec2 = UVec2::new(8, 8);
+
+    /// The inputs of a [`GpuPickingFrame`] the routing depends on.
+    struct TestFrame {
+        camera: u32,
+        order: isize,
+        viewport: URect,
+        /// Whether the camera clears its viewport, hiding the cameras below it.
+        clears: bool,
+        /// The manual texture view the camera renders to.
+        target: u32,
+        /// The entity index drawn over the whole viewport, if any.
+        entity_index: Option<u32>,
+    }
+
+    impl Default for TestFrame {
+        fn default() -> Self {
+            Self {
+                camera: 0,
+                order: 0,
+                viewport: URect::from_corners(UVec2::ZERO, TARGET_SIZE),
+                clears: true,
+                target: 0,
+                entity_index: None,
+            }
+        }
+    }
+
+    impl TestFrame {
+        fn build(self) -> GpuPickingFrame {
+            let pixel_count = (TARGET_SIZE.x * TARGET_SIZE.y) as usize;
+            let mut entity_indices = vec![0; pixel_count];
+            if let Some(entity_index) = self.entity_index {
+                for y in self.viewport.min.y..self.viewport.max.y {
+                    for x in self.viewport.min.x..self.viewport.max.x {
+                        entity_indices[(y * TARGET_SIZE.x + x) as usize] = entity_index + 1;
+                    }
+                }
+            }
+            GpuPickingFrame {
+                camera: Entity::from_raw(self.camera),
+                order: self.order,
+                frame: 0,
+                size: TARGET_SIZE,
+                scale: 1.0,
+                entity_indices,
+                entities: GpuPickingEntities::new(self.entity_index.map(Entity::from_raw)),
+                depth: None,
+                triangle_indices: None,
+                viewport: self.viewport,
+                target: Some(NormalizedRenderTarget::TextureView(
+                    ManualTextureViewHandle(self.target),
+                )),
+                clears: self.clears,
+                world_from_view: Mat4::IDENTITY,
+                view_from_clip: Mat4::IDENTITY,
+            }
+        }
+    }
+
+    fn route(frames: &[GpuPickingFrame], target: Option<u32>, cursor: UVec2) -> Vec<u32> {
+        let target = target
+            .map(|target| NormalizedRenderTarget::TextureView(ManualTextureViewHandle(target)));
+        route_gpu_picking_frames(frames, target.as_ref(), cursor)
+            .into_iter()
+            .map(|frame| frame.camera.index())
+            .collect()
+    }
+
+    fn pick(frames: &[GpuPickingFrame], cursor: UVec2) -> Option<u32> {
+        route_gpu_picking_frames(frames, None, cursor)
+            .into_iter()
+            .find_map(|frame| frame.entity_index(cursor))
+    }
+
+    #[test]
+    fn split_screen_routes_to_viewport() {
+        let frames = [
+            TestFrame {
+                viewport: URect::new(0, 0, 4, 8),
+                entity_index: Some(10),
+                ..default()
+            }
+            .build(),
+            TestFrame {
+                camera: 1,
+                order: 1,
+                viewport: URect::new(4, 0, 8, 8),
+                entity_index: Some(11),
+                ..default()
+            }
+            .build(),
+        ];
+
+        assert_eq!(route(&frames, None, UVec2::new(1, 1)), [0]);
+        assert_eq!(route(&frames, None, UVec2::new(5, 1)), [1]);
+        assert_eq!(pick(&frames, UVec2::new(1, 1)), Some(10));
+        assert_eq!(pick(&frames, UVec2::new(5, 1)), Some(11));
+    }
+
+    #[test]
+    fn viewport_max_is_exclusive() {
+        let frames = [
+            TestFrame {
+                viewport: URect::new(0, 0, 4, 8),
+                entity_index: Some(10),
+                ..default()
+            }
+            .build(),
+            TestFrame {
+                camera: 1,
+                order: 1,
+                viewport: URect::new(4, 0, 8, 8),
+                entity_index: Some(11),
+                ..default()
+            }
+            .build(),
+        ];
+
+        assert_eq!(route(&frames, None, UVec2::new(3, 7)), [0]);
+        assert_eq!(route(&frames, None, UVec2::new(4, 7)), [1]);
+        assert!(route(&frames, None, UVec2::new(8, 0)).is_empty());
+    }
+
+    #[test]
+    fn overlapping_viewport_hides_lower_cameras() {
+        // A minimap in the corner of a full screen camera, with nothing under
+        // the cursor in the minimap.
+        let frames = [
+            TestFrame {
+                entity_index: Some(10),
+                ..default()
+            }
+            .build(),
+            TestFrame {
+                camera: 1,
+                order: 1,
+                viewport: URect::new(4, 4, 8, 8),
+                ..default()
+            }
+            .build(),
+        ];
+
+        assert_eq!(route(&frames, None, UVec2::new(5, 5)), [1]);
+        assert_eq!(pick(&frames, UVec2::new(5, 5)), None);
+        assert_eq!(route(&frames, None, UVec2::new(1, 1)), [0]);
+        assert_eq!(pick(&frames, UVec2::new(1, 1)), Some(10));
+    }
+
+    #[test]
+    fn overlapping_overlay_falls_through() {
+        // An overlay that doesn't clear only hides the cameras below it where
+        // it drew something.
+        let frames = [
+            TestFrame {
+                entity_index: Some(10),
+                ..default()
+            }
+            .build(),
+            TestFrame {
+                camera: 1,
+                order: 2,
+                viewport: URect::new(4, 4, 8, 8),
+                clears: false,
+                ..default()
+            }
+            .build(),
+            TestFrame {
+                camera: 2,
+                order: 1,
+                clears: false,
+                ..default()
+            }
+            .build(),
+        ];
+
+        assert_eq!(route(&frames, None, UVec2::new(5, 5)), [1, 2, 0]);
+        assert_eq!(pick(&frames, UVec2::new(5, 5)), Some(10));
+
+        let frames = [
+            TestFrame {
+                entity_index: Some(10),
+                ..default()
+            }
+            .build(),
+            TestFrame {
+                camera: 1,
+                order: 1,
+                viewport: URect::new(4, 4, 8, 8),
+                clears: false,
+                entity_index: Some(11),
+                ..default()
+            }
+            .build(),
+        ];
+        assert_eq!(pick(&frames, UVec2::new(5, 5)), Some(11));
+    }
+
+    #[test]
+    fn other_render_targets_are_ignored() {
+        let frames = [
+            TestFrame {
+                entity_index: Some(10),
+                ..default()
+            }
+            .build(),
+            TestFrame {
+                camera: 1,
+                order: 1,
+                target: 1,
+                entity_index: Some(11),
+                ..default()
+            }
+            .build(),
+        ];
+
+        assert_eq!(route(&frames, Some(0), UVec2::new(1, 1)), [0]);
+        assert_eq!(route(&frames, Some(1), UVec2::new(1, 1)), [1]);
+        assert!(route(&frames, Some(2), UVec2::new(1, 1)).is_empty());
+    }
+
+    #[test]
+    fn reused_entity_indices_resolve_to_the_drawn_entity() {
+        let mut world = World::new();
+        let drawn = world.spawn_empty().id();
+        let frame = GpuPickingFrame {
+            entities: GpuPickingEntities::new([drawn]),
+            ..TestFrame {
+                entity_index: Some(drawn.index()),
+                ..default()
+            }
+            .build()
+        };
+        assert_eq!(frame.entity(UVec2::ZERO), Some(drawn));
+
+        // A new entity reuses the index of the despawned one, but the frame
+        // still refers to the despawned entity, which no longer exists.
+        world.despawn(drawn);
+        let spawned = world.spawn_empty().id();
+        assert_eq!(spawned.index(), drawn.index());
+        assert_ne!(frame.entity(UVec2::ZERO), Some(spawned));
+        assert!(!world
+            .entities()
+            .contains(frame.entity(UVec2::ZERO).unwrap()));
+
+        // Indices that weren't drawn by the picking pass don't resolve.
+        let frame = GpuPickingFrame {
+            entities: GpuPickingEntities::default(),
+            ..frame
+        };
+        assert_eq!(frame.entity(UVec2::ZERO), None);
+    }
+}
@@ -0,0 +1,163 @@
+use bevy_ecs::{prelude::*, query::QueryItem};
+use bevy_math::UVec2;
+use bevy_render::{
+    camera::{ExtractedCamera, Viewport},
+    diagnostic::RecordDiagnostics,
+    render_graph::{NodeRunError, RenderGraphContext, ViewNode},
+    render_phase::BinnedRenderPhase,
+    render_resource::{
+        Extent3d, ImageCopyBuffer, ImageDataLayout, LoadOp, Operations, RenderPassColorAttachment,
+        RenderPassDepthStencilAttachment, RenderPassDescriptor, StoreOp,
+    },
+    renderer::RenderContext,
+};
+#[cfg(feature = "trace")]
+use bevy_utils::tracing::info_span;
+
+use super::{
+    CurrentGpuPickingBufferIndex, ExtractedGpuPickingCamera, GpuPickingReadbacks, MeshId3d,
+    VisibleMeshIdTextures,
+};
+
+/// Render node that draws the entity index of every visible mesh into the
+/// [`VisibleMeshIdTextures`] of a picking camera.
+#[derive(Default)]
+pub struct GpuPickingNode;
+
+impl ViewNode for GpuPickingNode {
+    type ViewQuery = (
+        &'static ExtractedCamera,
+        &'static ExtractedGpuPickingCamera,
+        &'static BinnedRenderPhase<MeshId3d>,
+        &'static VisibleMeshIdTextures,
+    );
+
+    fn run(
+        &self,
+        graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        (camera, gpu_picking_camera, mesh_id_phase, textures): QueryItem<Self::ViewQuery>,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        #[cfg(feature = "trace")]
+        let _gpu_picking_span = info_span!("gpu_picking").entered();
+
+        let diagnostics = render_context.diagnostic_recorder();
+
+        let color_attachments = [Some(&textures.mesh_id), textures.depth_copy.as_ref()]
+            .into_iter()
+            .flatten()
+            .map(|texture| {
+                Some(RenderPassColorAttachment {
+                    view: &texture.default_view,
+                    resolve_target: None,
+                    ops: Operations {
+                        // Zero means no entity, and the far plane for depth.
+                        load: LoadOp::Clear(Default::default()),
+                        store: StoreOp::Store,
+                    },
+                })
+            })
+            .collect::<Vec<_>>();
+
+        // The pass always runs, even with nothing to draw, so that the textures
+        // are cleared.
+        let mut render_pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
+            label: Some("gpu_picking"),
+            color_attachments: &color_attachments,
+            depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+                view: &textures.depth.default_view,
+                depth_ops: Some(Operations {
+                    load: LoadOp::Clear(0.0),
+                    store: StoreOp::Discard,
+                }),
+                stencil_ops: None,
+            }),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        let pass_span = diagnostics.pass_span(&mut render_pass, "gpu_picking");
+
+        if let Some(viewport) = camera.viewport.as_ref() {
+            // The picking textures may be smaller than the render target.
+            let scale = gpu_picking_camera.scale;
+            let position = (viewport.physical_position.as_vec2() * scale).as_uvec2();
+            let size = (viewport.physical_size.as_vec2() * scale)
+                .ceil()
+                .as_uvec2()
+                .max(UVec2::ONE)
+                .min(textures.size.saturating_sub(position).max(UVec2::ONE));
+            render_pass.set_camera_viewport(&Viewport {
+                physical_position: position,
+                physical_size: size,
+                depth: viewport.depth.clone(),
+            });
+        }
+
+        if !mesh_id_phase.is_empty() {
+            mesh_id_phase.render(&mut render_pass, world, graph.view_entity());
+        }
+
+        pass_span.end(&mut render_pass);
+
+        Ok(())
+    }
+}
+
+/// Render node that copies the entity index texture of a picking camera into
+/// its current readback buffer.
+#[derive(Default)]
+pub struct EntityIndexBufferCopyNode;
+
+impl ViewNode for EntityIndexBufferCopyNode {
+    type ViewQuery = (
+        &'static VisibleMeshIdTextures,
+        &'static CurrentGpuPickingBufferIndex,
+    );
+
+    fn run(
+        &self,
+        graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        (textures, buffer_index): QueryItem<Self::ViewQuery>,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let Some(readback) = world
+            .resource::<GpuPickingReadbacks>()
+            .get(graph.view_entity(), buffer_index.0)
+        else {
+            return Ok(());
+        };
+
+        let copies = [
+            Some((&textures.mesh_id, 0, readback.mesh_id_padded_bytes_per_row)),
+            textures.depth_copy.as_ref().map(|depth_copy| {
+                (
+                    depth_copy,
+                    readback.depth_offset(),
+                    readback.depth_padded_bytes_per_row,
+                )
+            }),
+        ];
+        for (texture, offset, padded_bytes_per_row) in copies.into_iter().flatten() {
+            render_context.command_encoder().copy_texture_to_buffer(
+                texture.texture.as_image_copy(),
+                ImageCopyBuffer {
+                    buffer: &readback.buffer,
+                    layout: ImageDataLayout {
+                        offset,
+                        bytes_per_row: Some(padded_bytes_per_row),
+                        rows_per_image: None,
+                    },
+                },
+                Extent3d {
+                    width: readback.layout.size.x,
+                    height: readback.layout.size.y,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+
+        Ok(())
+    }
+}
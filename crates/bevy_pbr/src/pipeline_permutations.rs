@@ -0,0 +1,179 @@
+use std::{any::type_name, fmt};
+
+use bevy_app::{App, Plugin};
+use bevy_ecs::prelude::*;
+use bevy_render::{
+    render_resource::{
+        PipelineKeyBits, PipelinePermutations, SpecializedMeshPipeline, SpecializedMeshPipelines,
+    },
+    RenderApp,
+};
+use bevy_utils::{tracing::warn, HashMap, HashSet};
+
+/// Records a [`PipelinePermutationReport`] resource in the render world, and warns about the
+/// mesh pipelines whose [`PipelinePermutations::theoretical`] count exceeds the `budget`.
+///
+/// The report isn't recorded without this plugin, so that walking the pipelines doesn't cost
+/// anything unless they're looked at.
+#[derive(Default)]
+pub struct PipelinePermutationsPlugin {
+    /// The number of pipeline permutations a mesh pipeline can reach before a warning is logged,
+    /// once per pipeline. No warning is logged if `None`.
+    pub budget: Option<u64>,
+}
+
+impl Plugin for PipelinePermutationsPlugin {
+    fn build(&self, app: &mut App) {
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app.insert_resource(PipelinePermutationReport {
+                budget: self.budget,
+                ..Default::default()
+            });
+        }
+    }
+}
+
+/// The [`PipelinePermutations`] of each [`SpecializedMeshPipelines`], by the type name of its
+/// [`SpecializedMeshPipeline`], to find the pipelines whose keys explode combinatorially before
+/// every combination shows up at runtime.
+///
+/// This is a resource of the render world, updated every frame by
+/// [`update_pipeline_permutations`] when the [`PipelinePermutationsPlugin`] is added. The
+/// [`MaterialPlugin`](crate::MaterialPlugin) of each material adds it for the main and prepass
+/// pipelines of the material, and other plugins can add it for their own pipelines.
+///
+/// Its [`Display`](fmt::Display) implementation lists the pipelines from the most permutations
+/// to the least, flagging those over the budget.
+#[derive(Resource, Default, Debug)]
+pub struct PipelinePermutationReport {
+    /// The number of pipeline permutations a pipeline can reach before it is flagged, if any.
+    pub budget: Option<u64>,
+    pipelines: HashMap<&'static str, PipelinePermutations>,
+    warned: HashSet<&'static str>,
+}
+
+impl PipelinePermutationReport {
+    /// Returns the permutations of the pipelines specialized from `S`, if they were recorded.
+    pub fn get<S: SpecializedMeshPipeline>(&self) -> Option<&PipelinePermutations> {
+        self.pipelines.get(type_name::<S>())
+    }
+
+    /// Iterates over the permutations of each pipeline, by the type name of its
+    /// [`SpecializedMeshPipeline`].
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, &PipelinePermutations)> {
+        self.pipelines
+            .iter()
+            .map(|(type_name, permutations)| (*type_name, permutations))
+    }
+
+    /// Iterates over the pipelines whose [`PipelinePermutations::theoretical`] count exceeds the
+    /// [`PipelinePermutationReport::budget`].
+    pub fn over_budget(&self) -> impl Iterator<Item = (&'static str, &PipelinePermutations)> {
+        self.iter()
+            .filter(|(_, permutations)| self.is_over_budget(permutations))
+    }
+
+    /// Records the permutations of the pipelines specialized from `S`, and warns once if they
+    /// exceed the budget.
+    pub fn record<S: SpecializedMeshPipeline>(&mut self, permutations: PipelinePermutations) {
+        let type_name = type_name::<S>();
+        if self.is_over_budget(&permutations) && self.warned.insert(type_name) {
+            warn!(
+                "{type_name} can reach {} pipeline permutations, over the budget of {}: {} varying \
+                key bits ({:#x}), {} other key variants and {} vertex buffer layouts, for {} \
+                pipelines specialized so far",
+                permutations.theoretical(),
+                self.budget.unwrap_or_default(),
+                permutations.varying_bits.count_ones(),
+                permutations.varying_bits,
+                permutations.other_variants,
+                permutations.vertex_layouts,
+                permutations.specialized,
+            );
+        }
+        self.pipelines.insert(type_name, permutations);
+    }
+
+    fn is_over_budget(&self, permutations: &PipelinePermutations) -> bool {
+        self.budget
+            .is_some_and(|budget| permutations.theoretical() > budget)
+    }
+}
+
+impl fmt::Display for PipelinePermutationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut pipelines: Vec<_> = self.iter().collect();
+        pipelines.sort_by(|(name_a, a), (name_b, b)| {
+            b.theoretical()
+                .cmp(&a.theoretical())
+                .then_with(|| name_a.cmp(name_b))
+        });
+        for (type_name, permutations) in pipelines {
+            writeln!(
+                f,
+                "{}{type_name}: {} permutations ({} varying bits x {} other variants x {} \
+                vertex layouts), {} specialized",
+                if self.is_over_budget(permutations) {
+                    "[over budget] "
+                } else {
+                    ""
+                },
+                permutations.theoretical(),
+                permutations.varying_bits.count_ones(),
+                permutations.other_variants,
+                permutations.vertex_layouts,
+                permutations.specialized,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Records the [`PipelinePermutations`] of the pipelines specialized from `S` in the
+/// [`PipelinePermutationReport`], if the [`PipelinePermutationsPlugin`] is added.
+pub fn update_pipeline_permutations<S: SpecializedMeshPipeline>(
+    report: Option<ResMut<PipelinePermutationReport>>,
+    pipelines: Option<Res<SpecializedMeshPipelines<S>>>,
+) where
+    S::Key: PipelineKeyBits,
+    SpecializedMeshPipelines<S>: Resource,
+{
+    let (Some(mut report), Some(pipelines)) = (report, pipelines) else {
+        return;
+    };
+    if pipelines.is_changed() || report.get::<S>().is_none() {
+        report.record::<S>(pipelines.permutations());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_render::render_resource::PipelinePermutations;
+
+    use super::PipelinePermutationReport;
+    use crate::MeshPipeline;
+
+    #[test]
+    fn permutations_over_budget_are_flagged() {
+        let permutations = PipelinePermutations {
+            specialized: 6,
+            keys: 3,
+            varying_bits: 0b1011,
+            other_variants: 3,
+            vertex_layouts: 2,
+        };
+        assert_eq!(permutations.theoretical(), 48);
+        assert_eq!(PipelinePermutations::default().theoretical(), 0);
+
+        let mut report = PipelinePermutationReport {
+            budget: Some(32),
+            ..Default::default()
+        };
+        report.record::<MeshPipeline>(permutations);
+        assert_eq!(report.over_budget().count(), 1);
+        assert!(report.to_string().starts_with("[over budget] "));
+
+        report.budget = Some(48);
+        assert_eq!(report.over_budget().count(), 0);
+    }
+}
@@ -0,0 +1,427 @@
+use std::hash::Hash;
+
+use bevy_app::{App, Plugin, PreUpdate};
+use bevy_asset::{AssetId, UntypedAssetId};
+use bevy_ecs::prelude::*;
+use bevy_render::{
+    mesh::{GpuMesh, Mesh},
+    render_asset::RenderAssets,
+    render_resource::{
+        CachedPipelineState, CachedRenderPipelineId, PipelineCache, PipelineCacheError,
+        SpecializedMeshPipelines,
+    },
+    Extract, ExtractSchedule, Render, RenderApp, RenderSet,
+};
+use bevy_utils::tracing::error;
+use crossbeam_channel::{Receiver, Sender};
+
+use crate::{
+    vertex_pulling_key, Material, MaterialPipeline, MaterialPipelineKey, MeshPipelineKey,
+    PreparedMaterial,
+};
+
+/// Compiles the pipelines of materials ahead of time, e.g. behind a loading screen, so that
+/// meshes don't pop in or stall while their pipelines compile the first time they're drawn.
+///
+/// Submit the pipelines to compile to the [`PipelinePrewarm`] resource, and read its
+/// [`progress`](PipelinePrewarm::progress) to show a loading bar. This plugin isn't added by
+/// default. The requests of a material are compiled by its [`MaterialPlugin`](crate::MaterialPlugin).
+pub struct PipelinePrewarmPlugin;
+
+impl Plugin for PipelinePrewarmPlugin {
+    fn build(&self, app: &mut App) {
+        let (sender, receiver) = crossbeam_channel::unbounded();
+
+        app.insert_resource(PipelinePrewarm {
+            requests: Vec::new(),
+            statuses: Vec::new(),
+            receiver,
+        })
+        .add_systems(PreUpdate, receive_pipeline_prewarm_statuses);
+
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+
+        render_app
+            .insert_resource(RenderPipelinePrewarm {
+                requests: Vec::new(),
+                sender,
+            })
+            .add_systems(ExtractSchedule, extract_pipeline_prewarm_requests)
+            .add_systems(
+                Render,
+                send_pipeline_prewarm_statuses.in_set(RenderSet::Cleanup),
+            );
+    }
+}
+
+/// A main pass pipeline to compile ahead of time, see [`PipelinePrewarm`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PipelinePrewarmRequest {
+    /// The material to draw with, which selects the [`Material`] type and its
+    /// [`AsBindGroup::Data`](bevy_render::render_resource::AsBindGroup::Data).
+    pub material: UntypedAssetId,
+    /// The mesh to draw, whose vertex buffer layout and primitive topology the pipeline is
+    /// specialized for. Any mesh with the same layout and topology shares the pipeline.
+    pub mesh: AssetId<Mesh>,
+    /// The bits of the [`MeshPipelineKey`] that depend on the view, such as its MSAA sample
+    /// count, HDR, tonemapping and prepasses. These must match the camera the mesh will be drawn
+    /// from, or a different pipeline will be compiled.
+    pub view_key: MeshPipelineKey,
+    /// The [`Material::view_key`] of the camera the mesh will be drawn from.
+    pub material_view_key: u32,
+}
+
+impl PipelinePrewarmRequest {
+    /// Creates a request for the pipeline drawing `mesh` with `material` from a view with the
+    /// given `view_key`.
+    pub fn new<M: Material>(
+        material: impl Into<AssetId<M>>,
+        mesh: impl Into<AssetId<Mesh>>,
+        view_key: MeshPipelineKey,
+    ) -> Self {
+        Self {
+            material: material.into().untyped(),
+            mesh: mesh.into(),
+            view_key,
+            material_view_key: 0,
+        }
+    }
+
+    /// Sets [`Self::material_view_key`].
+    pub fn with_material_view_key(mut self, material_view_key: u32) -> Self {
+        self.material_view_key = material_view_key;
+        self
+    }
+}
+
+/// The status of a [`PipelinePrewarmRequest`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PipelinePrewarmStatus {
+    /// Waiting for the material and mesh to be prepared in the render world.
+    #[default]
+    Waiting,
+    /// The pipelines are queued or being compiled, including while their shaders load.
+    Compiling,
+    /// All the pipelines are compiled and ready to draw with.
+    Compiled,
+    /// A pipeline failed to specialize or compile. The error is logged.
+    Failed,
+}
+
+impl PipelinePrewarmStatus {
+    /// Returns whether the request won't progress any further.
+    pub fn is_finished(&self) -> bool {
+        matches!(self, Self::Compiled | Self::Failed)
+    }
+}
+
+/// The number of [`PipelinePrewarmRequest`]s in each state, see [`PipelinePrewarm::progress`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PipelinePrewarmProgress {
+    /// The number of requests submitted.
+    pub requested: usize,
+    /// The number of requests whose pipelines are all compiled.
+    pub compiled: usize,
+    /// The number of requests with a pipeline that failed.
+    pub failed: usize,
+}
+
+impl PipelinePrewarmProgress {
+    /// Returns the proportion of requests that finished, compiled or failed, from `0.0` to `1.0`.
+    ///
+    /// This is `1.0` when there are no requests.
+    pub fn fraction(&self) -> f32 {
+        if self.requested == 0 {
+            return 1.0;
+        }
+        (self.compiled + self.failed) as f32 / self.requested as f32
+    }
+
+    /// Returns whether all the requests finished, compiled or failed.
+    pub fn is_done(&self) -> bool {
+        self.compiled + self.failed == self.requested
+    }
+}
+
+/// The queue of pipelines to compile ahead of time, added by the [`PipelinePrewarmPlugin`].
+///
+/// Requests are compiled once their material and mesh assets are loaded, in the same order as
+/// the pipelines of visible meshes, and their statuses are updated at the start of each frame.
+#[derive(Resource)]
+pub struct PipelinePrewarm {
+    requests: Vec<PipelinePrewarmRequest>,
+    statuses: Vec<PipelinePrewarmStatus>,
+    receiver: Receiver<(usize, PipelinePrewarmStatus)>,
+}
+
+impl PipelinePrewarm {
+    /// Submits a request, and returns its index for [`Self::status`].
+    pub fn prewarm(&mut self, request: PipelinePrewarmRequest) -> usize {
+        self.requests.push(request);
+        self.statuses.push(PipelinePrewarmStatus::Waiting);
+        self.requests.len() - 1
+    }
+
+    /// Submits the pipeline drawing each mesh with each material, from a view with `view_key`.
+    pub fn prewarm_all<M: Material>(
+        &mut self,
+        materials: impl IntoIterator<Item = AssetId<M>>,
+        meshes: impl IntoIterator<Item = AssetId<Mesh>> + Clone,
+        view_key: MeshPipelineKey,
+    ) {
+        for material in materials {
+            for mesh in meshes.clone() {
+                self.prewarm(PipelinePrewarmRequest::new(material, mesh, view_key));
+            }
+        }
+    }
+
+    /// Returns the submitted requests, in order.
+    pub fn requests(&self) -> &[PipelinePrewarmRequest] {
+        &self.requests
+    }
+
+    /// Returns the status of the request with the given index.
+    pub fn status(&self, index: usize) -> Option<PipelinePrewarmStatus> {
+        self.statuses.get(index).copied()
+    }
+
+    /// Counts the requests by status.
+    pub fn progress(&self) -> PipelinePrewarmProgress {
+        let mut progress = PipelinePrewarmProgress {
+            requested: self.statuses.len(),
+            ..Default::default()
+        };
+        for status in &self.statuses {
+            match status {
+                PipelinePrewarmStatus::Compiled => progress.compiled += 1,
+                PipelinePrewarmStatus::Failed => progress.failed += 1,
+                PipelinePrewarmStatus::Waiting | PipelinePrewarmStatus::Compiling => {}
+            }
+        }
+        progress
+    }
+}
+
+/// Updates the statuses of the [`PipelinePrewarm`] from the render world.
+pub fn receive_pipeline_prewarm_statuses(mut prewarm: ResMut<PipelinePrewarm>) {
+    let PipelinePrewarm {
+        statuses, receiver, ..
+    } = &mut *prewarm;
+    for (index, status) in receiver.try_iter() {
+        if let Some(current) = statuses.get_mut(index) {
+            *current = status;
+        }
+    }
+}
+
+/// The [`PipelinePrewarm`] requests in the render world.
+#[derive(Resource)]
+pub struct RenderPipelinePrewarm {
+    requests: Vec<RenderPipelinePrewarmRequest>,
+    sender: Sender<(usize, PipelinePrewarmStatus)>,
+}
+
+struct RenderPipelinePrewarmRequest {
+    request: PipelinePrewarmRequest,
+    /// The pipelines of the main pass and of the additional passes of the material, once
+    /// specialized.
+    pipelines: Option<Vec<CachedRenderPipelineId>>,
+    /// Whether a pipeline failed to specialize.
+    failed: bool,
+    /// The last status sent to the main world.
+    status: PipelinePrewarmStatus,
+}
+
+/// Copies the requests submitted since the last frame to the render world.
+pub fn extract_pipeline_prewarm_requests(
+    prewarm: Extract<Res<PipelinePrewarm>>,
+    mut render_prewarm: ResMut<RenderPipelinePrewarm>,
+) {
+    let extracted = render_prewarm.requests.len();
+    render_prewarm.requests.extend(
+        prewarm.requests[extracted.min(prewarm.requests.len())..]
+            .iter()
+            .map(|request| RenderPipelinePrewarmRequest {
+                request: *request,
+                pipelines: None,
+                failed: false,
+                status: PipelinePrewarmStatus::Waiting,
+            }),
+    );
+}
+
+/// Specializes the pipelines of the [`PipelinePrewarm`] requests of the material `M`, once their
+/// material and mesh are prepared.
+///
+/// The keys match those of [`queue_material_meshes`](crate::queue_material_meshes) for a mesh
+/// without a lightmap, [`MaterialLod`](crate::MaterialLod) or visibility range.
+pub fn specialize_prewarm_pipelines<M: Material>(
+    prewarm: Option<ResMut<RenderPipelinePrewarm>>,
+    material_pipeline: Res<MaterialPipeline<M>>,
+    mut pipelines: ResMut<SpecializedMeshPipelines<MaterialPipeline<M>>>,
+    pipeline_cache: Res<PipelineCache>,
+    render_meshes: Res<RenderAssets<GpuMesh>>,
+    render_materials: Res<RenderAssets<PreparedMaterial<M>>>,
+) where
+    M::Data: PartialEq + Eq + Hash + Clone,
+{
+    let Some(mut prewarm) = prewarm else {
+        return;
+    };
+
+    for prewarm_request in &mut prewarm.requests {
+        if prewarm_request.pipelines.is_some() {
+            continue;
+        }
+        let request = &prewarm_request.request;
+        let Ok(material_id) = request.material.try_typed::<M>() else {
+            continue;
+        };
+        let (Some(material), Some(mesh)) = (
+            render_materials.get(material_id),
+            render_meshes.get(request.mesh),
+        ) else {
+            continue;
+        };
+
+        let mut entity_key =
+            request.view_key | MeshPipelineKey::from_bits_retain(mesh.key_bits.bits());
+        if material_pipeline.vertex_shader.is_none() {
+            entity_key |= vertex_pulling_key(&material_pipeline.mesh_pipeline.mesh_layouts, mesh);
+        }
+        if !material.properties.deband_dither {
+            entity_key.remove(MeshPipelineKey::DEBAND_DITHER);
+        }
+        let stencil = material
+            .properties
+            .stencil
+            .filter(|_| entity_key.contains(MeshPipelineKey::DEPTH_STENCIL))
+            .map(|stencil| stencil.state());
+
+        let passes = material
+            .properties
+            .additional_passes
+            .iter()
+            .enumerate()
+            .map(|(pass_index, pass)| (pass_index as u32 + 1, pass.mesh_pipeline_key_bits, None))
+            .chain([(
+                0,
+                material.properties.mesh_pipeline_key_bits,
+                material.properties.blend_state,
+            )]);
+
+        let mut ids = Vec::new();
+        for (pass_index, mesh_pipeline_key_bits, blend_state) in passes {
+            let pipeline_id = pipelines.specialize(
+                &pipeline_cache,
+                &material_pipeline,
+                MaterialPipelineKey {
+                    mesh_key: entity_key | mesh_pipeline_key_bits,
+                    bind_group_data: material.key.clone(),
+                    pass_index,
+                    stencil: stencil.clone(),
+                    blend_state,
+                    lod: None,
+                    shader_defs: material.properties.shader_defs.clone(),
+                    cull_mode: material.properties.cull_mode,
+                    double_sided_normals: material.properties.double_sided_normals,
+                    rasterizer_depth_bias: material.properties.rasterizer_depth_bias,
+                    view_key: request.material_view_key,
+                },
+                &mesh.layout,
+            );
+            match pipeline_id {
+                Ok(id) => ids.push(id),
+                Err(err) => {
+                    error!("{}", err);
+                    prewarm_request.failed = true;
+                }
+            }
+        }
+        prewarm_request.pipelines = Some(ids);
+    }
+}
+
+/// Sends the statuses of the [`PipelinePrewarm`] requests that changed back to the main world.
+pub fn send_pipeline_prewarm_statuses(
+    mut prewarm: ResMut<RenderPipelinePrewarm>,
+    pipeline_cache: Res<PipelineCache>,
+) {
+    let RenderPipelinePrewarm { requests, sender } = &mut *prewarm;
+    for (index, prewarm_request) in requests.iter_mut().enumerate() {
+        if prewarm_request.status.is_finished() {
+            continue;
+        }
+        let Some(pipelines) = &prewarm_request.pipelines else {
+            continue;
+        };
+        let status = if prewarm_request.failed {
+            PipelinePrewarmStatus::Failed
+        } else {
+            pipelines_status(
+                pipelines
+                    .iter()
+                    .map(|id| pipeline_cache.get_render_pipeline_state(*id)),
+            )
+        };
+        if status != prewarm_request.status {
+            prewarm_request.status = status;
+            let _ = sender.send((index, status));
+        }
+    }
+}
+
+/// Returns the status of a request from the states of its pipelines.
+fn pipelines_status<'a>(
+    states: impl IntoIterator<Item = &'a CachedPipelineState>,
+) -> PipelinePrewarmStatus {
+    let mut status = PipelinePrewarmStatus::Compiled;
+    for state in states {
+        match state {
+            // Evicted pipelines were prewarmed but went unused for a while.
+            CachedPipelineState::Ok(_) | CachedPipelineState::Evicted => {}
+            CachedPipelineState::Queued
+            | CachedPipelineState::Creating(_)
+            | CachedPipelineState::Err(
+                PipelineCacheError::ShaderNotLoaded(_)
+                | PipelineCacheError::ShaderImportNotYetAvailable,
+            ) => status = PipelinePrewarmStatus::Compiling,
+            CachedPipelineState::Err(_) => return PipelinePrewarmStatus::Failed,
+        }
+    }
+    status
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_asset::AssetId;
+    use bevy_render::render_resource::{CachedPipelineState, PipelineCacheError};
+
+    use super::{pipelines_status, PipelinePrewarmProgress, PipelinePrewarmStatus};
+
+    #[test]
+    fn request_status_follows_its_pipelines() {
+        assert_eq!(pipelines_status([]), PipelinePrewarmStatus::Compiled);
+        let states = [
+            CachedPipelineState::Queued,
+            CachedPipelineState::Err(PipelineCacheError::ShaderNotLoaded(AssetId::default())),
+            CachedPipelineState::Err(PipelineCacheError::CreateShaderModule(String::new())),
+        ];
+        assert_eq!(
+            pipelines_status(&states[..2]),
+            PipelinePrewarmStatus::Compiling
+        );
+        assert_eq!(pipelines_status(&states), PipelinePrewarmStatus::Failed);
+
+        let progress = PipelinePrewarmProgress {
+            requested: 4,
+            compiled: 2,
+            failed: 1,
+        };
+        assert_eq!(progress.fraction(), 0.75);
+        assert!(!progress.is_done());
+    }
+}
@@ -1,12 +1,12 @@
 mod prepass_bindings;
 
 use bevy_render::mesh::{GpuMesh, MeshVertexBufferLayoutRef};
-use bevy_render::render_resource::binding_types::uniform_buffer;
+use bevy_render::render_resource::binding_types::{texture_2d, uniform_buffer};
 use bevy_render::view::WithMesh;
 pub use prepass_bindings::*;
 
 use bevy_asset::{load_internal_asset, AssetServer};
-use bevy_core_pipeline::{core_3d::CORE_3D_DEPTH_FORMAT, prelude::Camera3d};
+use bevy_core_pipeline::prelude::Camera3d;
 use bevy_core_pipeline::{deferred::*, prepass::*};
 use bevy_ecs::{
     prelude::*,
@@ -17,12 +17,14 @@ use bevy_ecs::{
 };
 use bevy_math::{Affine3A, Mat4};
 use bevy_render::{
+    camera::{ExtractedCamera, Projection},
     globals::{GlobalsBuffer, GlobalsUniform},
     prelude::{Camera, Mesh},
     render_asset::RenderAssets,
     render_phase::*,
     render_resource::*,
     renderer::{RenderDevice, RenderQueue},
+    texture::BlueNoise,
     view::{ExtractedView, Msaa, ViewUniform, ViewUniformOffset, ViewUniforms, VisibleEntities},
     Extract,
 };
@@ -34,7 +36,7 @@ use crate::meshlet::{
     prepare_material_meshlet_meshes_prepass, queue_material_meshlet_meshes, MeshletGpuScene,
     MeshletMesh,
 };
-use crate::*;
+use crate::{material::MaterialShaderReplacements, *};
 
 use std::{hash::Hash, marker::PhantomData};
 
@@ -98,7 +100,12 @@ where
         render_app
             .add_systems(
                 Render,
-                prepare_prepass_view_bind_group::<M>.in_set(RenderSet::PrepareBindGroups),
+                (
+                    prepare_prepass_view_bind_group::<M>.in_set(RenderSet::PrepareBindGroups),
+                    update_pipeline_permutations::<PrepassPipeline<M>>
+                        .in_set(RenderSet::PrepareResources),
+                    evict_unused_mesh_pipelines::<PrepassPipeline<M>>.in_set(RenderSet::Cleanup),
+                ),
             )
             .init_resource::<PrepassViewBindGroup>()
             .init_resource::<SpecializedMeshPipelines<PrepassPipeline<M>>>()
@@ -151,6 +158,15 @@ where
                     BinnedRenderPhasePlugin::<Opaque3dPrepass, MeshPipeline>::default(),
                     BinnedRenderPhasePlugin::<AlphaMask3dPrepass, MeshPipeline>::default(),
                 ));
+            #[cfg(feature = "frame_capture")]
+            app.add_plugins((
+                bevy_render::frame_capture::FrameCapturePhasePlugin::<
+                    BinnedRenderPhase<Opaque3dPrepass>,
+                >::default(),
+                bevy_render::frame_capture::FrameCapturePhasePlugin::<
+                    BinnedRenderPhase<AlphaMask3dPrepass>,
+                >::default(),
+            ));
         }
 
         let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
@@ -250,6 +266,8 @@ pub struct PrepassPipeline<M: Material> {
     pub material_layout: BindGroupLayout,
     pub prepass_material_vertex_shader: Option<Handle<Shader>>,
     pub prepass_material_fragment_shader: Option<Handle<Shader>>,
+    pub shadow_material_vertex_shader: Option<Handle<Shader>>,
+    pub shadow_material_fragment_shader: Option<Handle<Shader>>,
     pub deferred_material_vertex_shader: Option<Handle<Shader>>,
     pub deferred_material_fragment_shader: Option<Handle<Shader>>,
     pub material_pipeline: MaterialPipeline<M>,
@@ -258,33 +276,44 @@ pub struct PrepassPipeline<M: Material> {
 
 impl<M: Material> FromWorld for PrepassPipeline<M> {
     fn from_world(world: &mut World) -> Self {
+        let shaders = MaterialShaderReplacements::<M>::get(world);
         let render_device = world.resource::<RenderDevice>();
         let asset_server = world.resource::<AssetServer>();
 
         let view_layout_motion_vectors = render_device.create_bind_group_layout(
             "prepass_view_layout_motion_vectors",
-            &BindGroupLayoutEntries::sequential(
+            &BindGroupLayoutEntries::with_indices(
                 ShaderStages::VERTEX_FRAGMENT,
                 (
                     // View
-                    uniform_buffer::<ViewUniform>(true),
+                    (0, uniform_buffer::<ViewUniform>(true)),
                     // Globals
-                    uniform_buffer::<GlobalsUniform>(false),
+                    (1, uniform_buffer::<GlobalsUniform>(false)),
                     // PreviousViewUniforms
-                    uniform_buffer::<PreviousViewData>(true),
+                    (2, uniform_buffer::<PreviousViewData>(true)),
+                    // Blue noise
+                    (
+                        3,
+                        texture_2d(TextureSampleType::Float { filterable: false }),
+                    ),
                 ),
             ),
         );
 
         let view_layout_no_motion_vectors = render_device.create_bind_group_layout(
             "prepass_view_layout_no_motion_vectors",
-            &BindGroupLayoutEntries::sequential(
+            &BindGroupLayoutEntries::with_indices(
                 ShaderStages::VERTEX_FRAGMENT,
                 (
                     // View
-                    uniform_buffer::<ViewUniform>(true),
+                    (0, uniform_buffer::<ViewUniform>(true)),
                     // Globals
-                    uniform_buffer::<GlobalsUniform>(false),
+                    (1, uniform_buffer::<GlobalsUniform>(false)),
+                    // Blue noise
+                    (
+                        3,
+                        texture_2d(TextureSampleType::Float { filterable: false }),
+                    ),
                 ),
             ),
         );
@@ -295,33 +324,77 @@ impl<M: Material> FromWorld for PrepassPipeline<M> {
             view_layout_motion_vectors,
             view_layout_no_motion_vectors,
             mesh_layouts: mesh_pipeline.mesh_layouts.clone(),
-            prepass_material_vertex_shader: match M::prepass_vertex_shader() {
+            prepass_material_vertex_shader: match shaders
+                .prepass_vertex
+                .unwrap_or_else(M::prepass_vertex_shader)
+            {
                 ShaderRef::Default => None,
                 ShaderRef::Handle(handle) => Some(handle),
                 ShaderRef::Path(path) => Some(asset_server.load(path)),
             },
-            prepass_material_fragment_shader: match M::prepass_fragment_shader() {
+            prepass_material_fragment_shader: match shaders
+                .prepass_fragment
+                .unwrap_or_else(M::prepass_fragment_shader)
+            {
                 ShaderRef::Default => None,
                 ShaderRef::Handle(handle) => Some(handle),
                 ShaderRef::Path(path) => Some(asset_server.load(path)),
             },
-            deferred_material_vertex_shader: match M::deferred_vertex_shader() {
+            shadow_material_vertex_shader: match shaders
+                .shadow_vertex
+                .unwrap_or_else(M::shadow_vertex_shader)
+            {
                 ShaderRef::Default => None,
                 ShaderRef::Handle(handle) => Some(handle),
                 ShaderRef::Path(path) => Some(asset_server.load(path)),
             },
-            deferred_material_fragment_shader: match M::deferred_fragment_shader() {
+            shadow_material_fragment_shader: match shaders
+                .shadow_fragment
+                .unwrap_or_else(M::shadow_fragment_shader)
+            {
                 ShaderRef::Default => None,
                 ShaderRef::Handle(handle) => Some(handle),
                 ShaderRef::Path(path) => Some(asset_server.load(path)),
             },
-            material_layout: M::bind_group_layout(render_device),
+            deferred_material_vertex_shader: match shaders
+                .deferred_vertex
+                .unwrap_or_else(M::deferred_vertex_shader)
+            {
+                ShaderRef::Default => None,
+                ShaderRef::Handle(handle) => Some(handle),
+                ShaderRef::Path(path) => Some(asset_server.load(path)),
+            },
+            deferred_material_fragment_shader: match shaders
+                .deferred_fragment
+                .unwrap_or_else(M::deferred_fragment_shader)
+            {
+                ShaderRef::Default => None,
+                ShaderRef::Handle(handle) => Some(handle),
+                ShaderRef::Path(path) => Some(asset_server.load(path)),
+            },
+            material_layout: material_bind_group_layout::<M>(world),
             material_pipeline: world.resource::<MaterialPipeline<M>>().clone(),
             _marker: PhantomData,
         }
     }
 }
 
+impl<M: Material> PrepassPipeline<M> {
+    /// The vertex shader of the material used in the shadow views, if it has a custom one.
+    pub fn shadow_vertex_shader(&self) -> Option<&Handle<Shader>> {
+        self.shadow_material_vertex_shader
+            .as_ref()
+            .or(self.prepass_material_vertex_shader.as_ref())
+    }
+
+    /// The fragment shader of the material used in the shadow views, if it has a custom one.
+    pub fn shadow_fragment_shader(&self) -> Option<&Handle<Shader>> {
+        self.shadow_material_fragment_shader
+            .as_ref()
+            .or(self.prepass_material_fragment_shader.as_ref())
+    }
+}
+
 impl<M: Material> SpecializedMeshPipeline for PrepassPipeline<M>
 where
     M::Data: PartialEq + Eq + Hash + Clone,
@@ -366,6 +439,10 @@ where
             shader_defs.push("MAY_DISCARD".into());
         }
 
+        if key.mesh_key.contains(MeshPipelineKey::ALPHA_HASHED) {
+            shader_defs.push("ALPHA_HASHED".into());
+        }
+
         let blend_key = key
             .mesh_key
             .intersection(MeshPipelineKey::BLEND_RESERVED_BITS);
@@ -429,8 +506,15 @@ where
             shader_defs.push("DEFERRED_PREPASS".into());
         }
 
-        if layout.0.contains(Mesh::ATTRIBUTE_COLOR) {
+        let vertex_color_mode = key.mesh_key.vertex_color_mode();
+        if layout.0.contains(Mesh::ATTRIBUTE_COLOR) && vertex_color_mode != VertexColorMode::Ignore
+        {
             shader_defs.push("VERTEX_COLORS".into());
+            if vertex_color_mode == VertexColorMode::Replace {
+                shader_defs.push("VERTEX_COLORS_REPLACE".into());
+            } else if vertex_color_mode == VertexColorMode::Occlusion {
+                shader_defs.push("VERTEX_COLORS_OCCLUSION".into());
+            }
             vertex_attributes.push(Mesh::ATTRIBUTE_COLOR.at_shader_location(7));
         }
 
@@ -441,14 +525,45 @@ where
             shader_defs.push("MOTION_VECTOR_PREPASS".into());
         }
 
+        if key.mesh_key.contains(MeshPipelineKey::OBJECT_ID_PREPASS) {
+            shader_defs.push("OBJECT_ID_PREPASS".into());
+        }
+
+        if key.mesh_key.contains(
+            MeshPipelineKey::DEFERRED_PREPASS | MeshPipelineKey::WRITES_DEFERRED_GBUFFER_EXTENSION,
+        ) {
+            shader_defs.push("DEFERRED_GBUFFER_EXTENSION".into());
+        }
+
+        if key.mesh_key.contains(MeshPipelineKey::TRANSLUCENT_SHADOW) {
+            // The colored shadow maps are blended, which the G-buffer can't be.
+            if key.mesh_key.contains(MeshPipelineKey::DEFERRED_PREPASS) {
+                return Err(SpecializedMeshPipelineError::IncompatibleBlendPhase {
+                    pipeline_type: None,
+                    blend: "translucent shadow",
+                    phase: "deferred prepass",
+                    key_bits: key.mesh_key.bits(),
+                });
+            }
+            shader_defs.push("TRANSLUCENT_SHADOW".into());
+        }
+
         if key.mesh_key.intersects(
             MeshPipelineKey::NORMAL_PREPASS
                 | MeshPipelineKey::MOTION_VECTOR_PREPASS
-                | MeshPipelineKey::DEFERRED_PREPASS,
+                | MeshPipelineKey::OBJECT_ID_PREPASS
+                | MeshPipelineKey::DEFERRED_PREPASS
+                | MeshPipelineKey::TRANSLUCENT_SHADOW,
         ) {
             shader_defs.push("PREPASS_FRAGMENT".into());
         }
 
+        shader_defs.extend_from_slice(&key.shader_defs);
+
+        if key.double_sided_normals {
+            shader_defs.push("MATERIAL_DOUBLE_SIDED_NORMALS".into());
+        }
+
         let bind_group = setup_morph_and_skinning_defs(
             &self.mesh_layouts,
             layout,
@@ -459,9 +574,41 @@ where
         );
         bind_group_layouts.insert(1, bind_group);
 
-        let vertex_buffer_layout = layout.0.get_layout(&vertex_attributes)?;
+        // Use the shaders from the material if present
+        let (material_vertex_shader, material_fragment_shader) =
+            if key.mesh_key.contains(MeshPipelineKey::DEFERRED_PREPASS) {
+                (
+                    self.deferred_material_vertex_shader.as_ref(),
+                    self.deferred_material_fragment_shader.as_ref(),
+                )
+            } else if key.mesh_key.contains(MeshPipelineKey::SHADOW_PASS) {
+                (self.shadow_vertex_shader(), self.shadow_fragment_shader())
+            } else {
+                (
+                    self.prepass_material_vertex_shader.as_ref(),
+                    self.prepass_material_fragment_shader.as_ref(),
+                )
+            };
+        let vert_shader_handle = material_vertex_shader
+            .cloned()
+            .unwrap_or(PREPASS_SHADER_HANDLE);
+
+        let mut vertex_buffers = vec![layout.0.get_layout(&vertex_attributes)?];
+        if key.mesh_key.contains(MeshPipelineKey::VERTEX_PULLING)
+            && vert_shader_handle == PREPASS_SHADER_HANDLE
+        {
+            setup_vertex_pulling(
+                &self.mesh_layouts,
+                layout,
+                &mut shader_defs,
+                &mut bind_group_layouts,
+                &mut vertex_buffers,
+            );
+        }
 
-        // Setup prepass fragment targets - normals in slot 0 (or None if not needed), motion vectors in slot 1
+        // Setup prepass fragment targets - normals in slot 0 (or None if not needed), motion vectors in slot 1,
+        // the deferred gbuffer and lighting pass id in slots 2 and 3, object ids in slot 4 and the deferred
+        // gbuffer extension in slot 5
         let mut targets = vec![
             key.mesh_key
                 .contains(MeshPipelineKey::NORMAL_PREPASS)
@@ -494,8 +641,54 @@ where
                     blend: None,
                     write_mask: ColorWrites::ALL,
                 }),
+            key.mesh_key
+                .contains(MeshPipelineKey::OBJECT_ID_PREPASS)
+                .then_some(ColorTargetState {
+                    format: OBJECT_ID_PREPASS_FORMAT,
+                    blend: None,
+                    write_mask: ColorWrites::ALL,
+                }),
+            key.mesh_key
+                .contains(
+                    MeshPipelineKey::DEFERRED_PREPASS | MeshPipelineKey::DEFERRED_GBUFFER_EXTENSION,
+                )
+                .then_some(ColorTargetState {
+                    format: DEFERRED_GBUFFER_EXTENSION_FORMAT,
+                    blend: None,
+                    // Materials that don't write to the extension have no output for it, which
+                    // requires an empty write mask
+                    write_mask: if key
+                        .mesh_key
+                        .contains(MeshPipelineKey::WRITES_DEFERRED_GBUFFER_EXTENSION)
+                    {
+                        ColorWrites::ALL
+                    } else {
+                        ColorWrites::empty()
+                    },
+                }),
         ];
 
+        // Translucent shadow casters multiply the transmittance of the colored shadow map by
+        // theirs, and keep the depth of the caster closest to the light in alpha.
+        if key.mesh_key.contains(MeshPipelineKey::TRANSLUCENT_SHADOW) {
+            targets = vec![Some(ColorTargetState {
+                format: COLORED_SHADOW_MAP_FORMAT,
+                blend: Some(BlendState {
+                    color: BlendComponent {
+                        src_factor: BlendFactor::Dst,
+                        dst_factor: BlendFactor::Zero,
+                        operation: BlendOperation::Add,
+                    },
+                    alpha: BlendComponent {
+                        src_factor: BlendFactor::One,
+                        dst_factor: BlendFactor::One,
+                        operation: BlendOperation::Max,
+                    },
+                }),
+                write_mask: ColorWrites::ALL,
+            })];
+        }
+
         if targets.iter().all(Option::is_none) {
             // if no targets are required then clear the list, so that no fragment shader is required
             // (though one may still be used for discarding depth buffer writes)
@@ -508,49 +701,23 @@ where
         let fragment_required = !targets.is_empty()
             || key.mesh_key.contains(MeshPipelineKey::DEPTH_CLAMP_ORTHO)
             || (key.mesh_key.contains(MeshPipelineKey::MAY_DISCARD)
-                && self.prepass_material_fragment_shader.is_some());
-
-        let fragment = fragment_required.then(|| {
-            // Use the fragment shader from the material
-            let frag_shader_handle = if key.mesh_key.contains(MeshPipelineKey::DEFERRED_PREPASS) {
-                match self.deferred_material_fragment_shader.clone() {
-                    Some(frag_shader_handle) => frag_shader_handle,
-                    _ => PREPASS_SHADER_HANDLE,
-                }
-            } else {
-                match self.prepass_material_fragment_shader.clone() {
-                    Some(frag_shader_handle) => frag_shader_handle,
-                    _ => PREPASS_SHADER_HANDLE,
-                }
-            };
-
-            FragmentState {
-                shader: frag_shader_handle,
-                entry_point: "fragment".into(),
-                shader_defs: shader_defs.clone(),
-                targets,
-            }
+                && material_fragment_shader.is_some());
+
+        let fragment = fragment_required.then(|| FragmentState {
+            shader: material_fragment_shader
+                .cloned()
+                .unwrap_or(PREPASS_SHADER_HANDLE),
+            entry_point: "fragment".into(),
+            shader_defs: shader_defs.clone(),
+            targets,
         });
 
-        // Use the vertex shader from the material if present
-        let vert_shader_handle = if key.mesh_key.contains(MeshPipelineKey::DEFERRED_PREPASS) {
-            if let Some(handle) = &self.deferred_material_vertex_shader {
-                handle.clone()
-            } else {
-                PREPASS_SHADER_HANDLE
-            }
-        } else if let Some(handle) = &self.prepass_material_vertex_shader {
-            handle.clone()
-        } else {
-            PREPASS_SHADER_HANDLE
-        };
-
         let mut descriptor = RenderPipelineDescriptor {
             vertex: VertexState {
                 shader: vert_shader_handle,
                 entry_point: "vertex".into(),
                 shader_defs,
-                buffers: vec![vertex_buffer_layout],
+                buffers: vertex_buffers,
             },
             fragment,
             layout: bind_group_layouts,
@@ -558,14 +725,16 @@ where
                 topology: key.mesh_key.primitive_topology(),
                 strip_index_format: None,
                 front_face: FrontFace::Ccw,
-                cull_mode: None,
+                cull_mode: key.cull_mode,
                 unclipped_depth: false,
                 polygon_mode: PolygonMode::Fill,
                 conservative: false,
             },
             depth_stencil: Some(DepthStencilState {
-                format: CORE_3D_DEPTH_FORMAT,
-                depth_write_enabled: true,
+                format: key.mesh_key.depth_format(),
+                // Translucent shadow casters are tested against the depth of the opaque ones, but
+                // don't occlude each other.
+                depth_write_enabled: !key.mesh_key.contains(MeshPipelineKey::TRANSLUCENT_SHADOW),
                 depth_compare: CompareFunction::GreaterEqual,
                 stencil: StencilState {
                     front: StencilFaceState::IGNORE,
@@ -573,11 +742,7 @@ where
                     read_mask: 0,
                     write_mask: 0,
                 },
-                bias: DepthBiasState {
-                    constant: 0,
-                    slope_scale: 0.0,
-                    clamp: 0.0,
-                },
+                bias: rasterizer_depth_bias(&key.rasterizer_depth_bias, &key.mesh_key),
             }),
             multisample: MultisampleState {
                 count: key.mesh_key.msaa_samples(),
@@ -591,7 +756,13 @@ where
         // This is a bit risky because it's possible to change something that would
         // break the prepass but be fine in the main pass.
         // Since this api is pretty low-level it doesn't matter that much, but it is a potential issue.
+        let key_bits = key.mesh_key.bits();
         M::specialize(&self.material_pipeline, &mut descriptor, layout, key)?;
+        check_bind_group_limit(
+            &descriptor,
+            self.material_pipeline.mesh_pipeline.max_bind_groups,
+            key_bits,
+        )?;
 
         Ok(descriptor)
     }
@@ -670,6 +841,7 @@ pub fn prepare_prepass_view_bind_group<M: Material>(
     view_uniforms: Res<ViewUniforms>,
     globals_buffer: Res<GlobalsBuffer>,
     previous_view_uniforms: Res<PreviousViewUniforms>,
+    blue_noise: Res<BlueNoise>,
     mut prepass_view_bind_group: ResMut<PrepassViewBindGroup>,
 ) {
     if let (Some(view_binding), Some(globals_binding)) = (
@@ -679,17 +851,22 @@ pub fn prepare_prepass_view_bind_group<M: Material>(
         prepass_view_bind_group.no_motion_vectors = Some(render_device.create_bind_group(
             "prepass_view_no_motion_vectors_bind_group",
             &prepass_pipeline.view_layout_no_motion_vectors,
-            &BindGroupEntries::sequential((view_binding.clone(), globals_binding.clone())),
+            &BindGroupEntries::with_indices((
+                (0, view_binding.clone()),
+                (1, globals_binding.clone()),
+                (3, &blue_noise.texture_view),
+            )),
         ));
 
         if let Some(previous_view_uniforms_binding) = previous_view_uniforms.uniforms.binding() {
             prepass_view_bind_group.motion_vectors = Some(render_device.create_bind_group(
                 "prepass_view_motion_vectors_bind_group",
                 &prepass_pipeline.view_layout_motion_vectors,
-                &BindGroupEntries::sequential((
-                    view_binding,
-                    globals_binding,
-                    previous_view_uniforms_binding,
+                &BindGroupEntries::with_indices((
+                    (0, view_binding),
+                    (1, globals_binding),
+                    (2, previous_view_uniforms_binding),
+                    (3, &blue_noise.texture_view),
                 )),
             ));
         }
@@ -722,7 +899,15 @@ pub fn queue_prepass_material_meshes<M: Material>(
             Option<&DepthPrepass>,
             Option<&NormalPrepass>,
             Option<&MotionVectorPrepass>,
+            Has<ObjectIdPrepass>,
             Option<&DeferredPrepass>,
+            Has<DeferredGBufferExtensionPrepass>,
+            Option<&Camera3d>,
+            (
+                Option<&Projection>,
+                Option<&ExtractedCamera>,
+                Option<&ExtraMeshPipelineKey>,
+            ),
         ),
         Or<(
             With<BinnedRenderPhase<Opaque3dPrepass>>,
@@ -751,7 +936,7 @@ pub fn queue_prepass_material_meshes<M: Material>(
         .get_id::<DrawPrepass<M>>()
         .unwrap();
     for (
-        _view,
+        view,
         visible_entities,
         mut opaque_phase,
         mut alpha_mask_phase,
@@ -760,10 +945,19 @@ pub fn queue_prepass_material_meshes<M: Material>(
         depth_prepass,
         normal_prepass,
         motion_vector_prepass,
+        object_id_prepass,
         deferred_prepass,
+        deferred_gbuffer_extension_prepass,
+        camera_3d,
+        (projection, camera, extra_key),
     ) in &mut views
     {
-        let mut view_key = MeshPipelineKey::from_msaa_samples(msaa.samples());
+        let material_view_key = M::view_key(&MaterialView::new(view, projection, camera));
+        let mut view_key = MeshPipelineKey::from_msaa_samples(msaa.samples())
+            | extra_key.map_or(MeshPipelineKey::NONE, ExtraMeshPipelineKey::view_key);
+        if camera_3d.is_some_and(|camera_3d| camera_3d.stencil) {
+            view_key |= MeshPipelineKey::DEPTH_STENCIL;
+        }
         if depth_prepass.is_some() {
             view_key |= MeshPipelineKey::DEPTH_PREPASS;
         }
@@ -773,6 +967,9 @@ pub fn queue_prepass_material_meshes<M: Material>(
         if motion_vector_prepass.is_some() {
             view_key |= MeshPipelineKey::MOTION_VECTOR_PREPASS;
         }
+        if object_id_prepass {
+            view_key |= MeshPipelineKey::OBJECT_ID_PREPASS;
+        }
 
         for visible_entity in visible_entities.iter::<WithMesh>() {
             let Some(material_asset_id) = render_material_instances.get(visible_entity) else {
@@ -793,7 +990,10 @@ pub fn queue_prepass_material_meshes<M: Material>(
 
             let alpha_mode = material.properties.alpha_mode;
             match alpha_mode {
-                AlphaMode::Opaque | AlphaMode::AlphaToCoverage | AlphaMode::Mask(_) => {
+                AlphaMode::Opaque
+                | AlphaMode::AlphaToCoverage
+                | AlphaMode::Mask(_)
+                | AlphaMode::Hashed => {
                     mesh_key |= alpha_mode_pipeline_key(alpha_mode, &msaa);
                 }
                 AlphaMode::Blend
@@ -802,6 +1002,18 @@ pub fn queue_prepass_material_meshes<M: Material>(
                 | AlphaMode::Multiply => continue,
             }
 
+            mesh_key |=
+                MeshPipelineKey::from_vertex_color_mode(material.properties.vertex_color_mode);
+
+            if material
+                .properties
+                .mesh_pipeline_key_bits
+                .contains(MeshPipelineKey::DECAL)
+            {
+                // Decals are drawn over the depth of the prepass, which they don't write to.
+                continue;
+            }
+
             if material.properties.reads_view_transmission_texture {
                 // No-op: Materials reading from `ViewTransmissionTexture` are not rendered in the `Opaque3d`
                 // phase, and are therefore also excluded from the prepass much like alpha-blended materials.
@@ -818,6 +1030,13 @@ pub fn queue_prepass_material_meshes<M: Material>(
 
             if deferred {
                 mesh_key |= MeshPipelineKey::DEFERRED_PREPASS;
+                if deferred_gbuffer_extension_prepass {
+                    mesh_key |= MeshPipelineKey::DEFERRED_GBUFFER_EXTENSION;
+                    mesh_key.set(
+                        MeshPipelineKey::WRITES_DEFERRED_GBUFFER_EXTENSION,
+                        material.properties.deferred_gbuffer_extension,
+                    );
+                }
             }
 
             // Even though we don't use the lightmap in the prepass, the
@@ -832,12 +1051,31 @@ pub fn queue_prepass_material_meshes<M: Material>(
                 mesh_key |= MeshPipelineKey::LIGHTMAPPED;
             }
 
+            // Custom vertex shaders read the vertex buffers.
+            let material_vertex_shader = if mesh_key.contains(MeshPipelineKey::DEFERRED_PREPASS) {
+                &prepass_pipeline.deferred_material_vertex_shader
+            } else {
+                &prepass_pipeline.prepass_material_vertex_shader
+            };
+            if material_vertex_shader.is_none() {
+                mesh_key |= vertex_pulling_key(&prepass_pipeline.mesh_layouts, mesh);
+            }
+
             let pipeline_id = pipelines.specialize(
                 &pipeline_cache,
                 &prepass_pipeline,
                 MaterialPipelineKey {
                     mesh_key,
                     bind_group_data: material.key.clone(),
+                    pass_index: 0,
+                    stencil: None,
+                    blend_state: None,
+                    lod: None,
+                    shader_defs: material.properties.shader_defs.clone(),
+                    cull_mode: material.properties.cull_mode,
+                    double_sided_normals: material.properties.double_sided_normals,
+                    rasterizer_depth_bias: material.properties.rasterizer_depth_bias,
+                    view_key: material_view_key,
                 },
                 &mesh.layout,
             );
@@ -964,5 +1202,6 @@ pub type DrawPrepass<M> = (
     SetPrepassViewBindGroup<0>,
     SetMeshBindGroup<1>,
     SetMaterialBindGroup<M, 2>,
+    SetMeshVertexPullingBindGroup<3>,
     DrawMesh,
 );
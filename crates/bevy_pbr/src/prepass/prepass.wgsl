@@ -4,8 +4,11 @@
     prepass_io::{Vertex, VertexOutput, FragmentOutput},
     skinning,
     morph,
+    vertex_pulling,
     mesh_view_bindings::view,
+    mesh_bindings::mesh,
 }
+#import bevy_render::view::VIEW_FLAGS_CAMERA_CUT_BIT
 
 #ifdef DEFERRED_PREPASS
 #import bevy_pbr::rgb9e5
@@ -32,8 +35,41 @@ fn morph_vertex(vertex_in: Vertex) -> Vertex {
 }
 #endif
 
+#ifdef VERTEX_PULLING
+// Builds the vertex from the vertex buffer, instead of the vertex attributes.
+fn pull_vertex(instance_index: u32, vertex_index: u32) -> Vertex {
+    var vertex: Vertex;
+    vertex.instance_index = instance_index;
+    vertex.position = vertex_pulling::position(vertex_index);
+#ifdef VERTEX_UVS
+    vertex.uv = vertex_pulling::uv(vertex_index);
+#endif
+#ifdef VERTEX_UVS_B
+    vertex.uv_b = vertex_pulling::uv_b(vertex_index);
+#endif
+#ifdef NORMAL_PREPASS_OR_DEFERRED_PREPASS
+    vertex.normal = vertex_pulling::normal(vertex_index);
+#ifdef VERTEX_TANGENTS
+    vertex.tangent = vertex_pulling::tangent(vertex_index);
+#endif
+#endif
+#ifdef VERTEX_COLORS
+    vertex.color = vertex_pulling::color(vertex_index);
+#endif
+    return vertex;
+}
+#endif
+
 @vertex
+#ifdef VERTEX_PULLING
+fn vertex(
+    @builtin(instance_index) instance_index: u32,
+    @builtin(vertex_index) vertex_index: u32,
+) -> VertexOutput {
+    let vertex_no_morph = pull_vertex(instance_index, vertex_index);
+#else
 fn vertex(vertex_no_morph: Vertex) -> VertexOutput {
+#endif
     var out: VertexOutput;
 
 #ifdef MORPH_TARGETS
@@ -57,7 +93,7 @@ fn vertex(vertex_no_morph: Vertex) -> VertexOutput {
 #endif // DEPTH_CLAMP_ORTHO
 
 #ifdef VERTEX_UVS
-    out.uv = vertex.uv;
+    out.uv = mesh_functions::mesh_uv_transform(vertex_no_morph.instance_index, vertex.uv);
 #endif // VERTEX_UVS
 
 #ifdef VERTEX_UVS_B
@@ -136,6 +172,11 @@ fn fragment(in: VertexOutput) -> FragmentOutput {
     // range -2,2, so this needs to be scaled by 0.5. And the V direction goes
     // down where clip space y goes up, so y needs to be flipped.
     out.motion_vector = (clip_position - previous_clip_position) * vec2(0.5, -0.5);
+    // The previous frame belongs to a different shot on camera cuts, so there's
+    // no motion to follow.
+    if (view.flags & VIEW_FLAGS_CAMERA_CUT_BIT) != 0u {
+        out.motion_vector = vec2(0.0);
+    }
 #endif // MOTION_VECTOR_PREPASS
 
 #ifdef DEFERRED_PREPASS
@@ -147,6 +188,16 @@ fn fragment(in: VertexOutput) -> FragmentOutput {
     out.deferred_lighting_pass_id = 1u;
 #endif
 
+#ifdef OBJECT_ID_PREPASS
+    // 0 is left for the background.
+    out.object_id = mesh[in.instance_index].entity_index + 1u;
+#endif
+
+#ifdef TRANSLUCENT_SHADOW
+    // There isn't any material info available here, so the caster blocks all light.
+    out.transmittance = vec4(vec3(0.0), in.position.z);
+#endif
+
     return out;
 }
 #endif // PREPASS_FRAGMENT
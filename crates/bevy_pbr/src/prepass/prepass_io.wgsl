@@ -87,6 +87,19 @@ struct FragmentOutput {
     @location(3) deferred_lighting_pass_id: u32,
 #endif
 
+#ifdef OBJECT_ID_PREPASS
+    @location(4) object_id: u32,
+#endif
+
+#ifdef DEFERRED_GBUFFER_EXTENSION
+    @location(5) deferred_gbuffer_extension: vec4<u32>,
+#endif
+
+#ifdef TRANSLUCENT_SHADOW
+    // Filtered light transmittance in rgb, caster depth in a.
+    @location(0) transmittance: vec4<f32>,
+#endif
+
 #ifdef DEPTH_CLAMP_ORTHO
     @builtin(frag_depth) frag_depth: f32,
 #endif // DEPTH_CLAMP_ORTHO
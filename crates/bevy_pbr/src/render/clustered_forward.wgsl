@@ -2,6 +2,7 @@
 
 #import bevy_pbr::{
     mesh_view_bindings as bindings,
+    shader_constants::CLUSTER_COUNT_SIZE,
     utils::rand_f,
 }
 
@@ -36,8 +37,6 @@ fn fragment_cluster_index(frag_coord: vec2<f32>, view_z: f32, is_orthographic: b
     );
 }
 
-// this must match CLUSTER_COUNT_SIZE in light.rs
-const CLUSTER_COUNT_SIZE = 9u;
 fn unpack_offset_and_counts(cluster_index: u32) -> vec3<u32> {
 #if AVAILABLE_STORAGE_BUFFER_BINDINGS >= 3
     return bindings::cluster_offsets_and_counts.data[cluster_index].xyz;
@@ -27,21 +27,23 @@ use bevy_render::{
     render_graph::{Node, NodeRunError, RenderGraphApp, RenderGraphContext},
     render_resource::{
         binding_types::{storage_buffer, storage_buffer_read_only, uniform_buffer},
-        BindGroup, BindGroupEntries, BindGroupLayout, BindingResource, BufferBinding,
-        CachedComputePipelineId, ComputePassDescriptor, ComputePipelineDescriptor,
-        DynamicBindGroupLayoutEntries, PipelineCache, Shader, ShaderStages, ShaderType,
-        SpecializedComputePipeline, SpecializedComputePipelines,
+        evict_unused_compute_pipelines, BindGroup, BindGroupEntries, BindGroupLayout,
+        BindingResource, BufferBinding, CachedComputePipelineId, ComputePassDescriptor,
+        ComputePipelineDescriptor, DynamicBindGroupLayoutEntries, PipelineCache, Shader,
+        ShaderStages, ShaderType, SpecializedComputePipeline, SpecializedComputePipelines,
     },
     renderer::{RenderContext, RenderDevice, RenderQueue},
     view::{GpuCulling, ViewUniform, ViewUniformOffset, ViewUniforms},
-    Render, RenderApp, RenderSet,
+    ExtractSchedule, Render, RenderApp, RenderSet,
 };
 use bevy_utils::tracing::warn;
 use bitflags::bitflags;
 use smallvec::{smallvec, SmallVec};
 
 use crate::{
-    graph::NodePbr, MeshCullingData, MeshCullingDataBuffer, MeshInputUniform, MeshUniform,
+    extract_gpu_sorting, graph::NodePbr, prepare_gpu_sort_bind_groups, GpuSortBuffers,
+    GpuSortPipelines, GpuSortUniforms, MeshCullingData, MeshCullingDataBuffer, MeshInputUniform,
+    MeshUniform, ViewGpuSort, GPU_SORT_SHADER_HANDLE,
 };
 
 /// The handle to the `mesh_preprocess.wgsl` compute shader.
@@ -70,6 +72,7 @@ pub struct GpuPreprocessNode {
         Read<PreprocessBindGroup>,
         Read<ViewUniformOffset>,
         Has<GpuCulling>,
+        Option<Read<ViewGpuSort>>,
     )>,
 }
 
@@ -119,6 +122,12 @@ impl Plugin for GpuMeshPreprocessPlugin {
             "mesh_preprocess.wgsl",
             Shader::from_wgsl
         );
+        load_internal_asset!(
+            app,
+            GPU_SORT_SHADER_HANDLE,
+            "gpu_sort.wgsl",
+            Shader::from_wgsl
+        );
     }
 
     fn finish(&self, app: &mut App) {
@@ -142,6 +151,10 @@ impl Plugin for GpuMeshPreprocessPlugin {
             .add_render_graph_edges(Core3d, (NodePbr::GpuPreprocess, NodePbr::ShadowPass))
             .init_resource::<PreprocessPipelines>()
             .init_resource::<SpecializedComputePipelines<PreprocessPipeline>>()
+            .init_resource::<GpuSortPipelines>()
+            .init_resource::<GpuSortUniforms>()
+            .init_resource::<GpuSortBuffers>()
+            .add_systems(ExtractSchedule, extract_gpu_sorting)
             .add_systems(
                 Render,
                 (
@@ -151,7 +164,14 @@ impl Plugin for GpuMeshPreprocessPlugin {
                             resource_exists::<BatchedInstanceBuffers<MeshUniform, MeshInputUniform>>,
                         )
                         .in_set(RenderSet::PrepareBindGroups),
+                    prepare_gpu_sort_bind_groups
+                        .run_if(
+                            resource_exists::<BatchedInstanceBuffers<MeshUniform, MeshInputUniform>>,
+                        )
+                        .in_set(RenderSet::PrepareBindGroups),
                     write_mesh_culling_data_buffer.in_set(RenderSet::PrepareResourcesFlush),
+                    evict_unused_compute_pipelines::<PreprocessPipeline>
+                        .in_set(RenderSet::Cleanup),
                 )
             );
     }
@@ -184,6 +204,7 @@ impl Node for GpuPreprocessNode {
 
         let pipeline_cache = world.resource::<PipelineCache>();
         let preprocess_pipelines = world.resource::<PreprocessPipelines>();
+        let gpu_sort_pipelines = world.resource::<GpuSortPipelines>();
 
         let mut compute_pass =
             render_context
@@ -194,7 +215,7 @@ impl Node for GpuPreprocessNode {
                 });
 
         // Run the compute passes.
-        for (view, bind_group, view_uniform_offset, gpu_culling) in
+        for (view, bind_group, view_uniform_offset, gpu_culling, view_gpu_sort) in
             self.view_query.iter_manual(world)
         {
             // Grab the index buffer for this view.
@@ -224,6 +245,18 @@ impl Node for GpuPreprocessNode {
                 return Ok(());
             };
 
+            // Sort the instances on the GPU first if necessary, so that the
+            // preprocessing writes them in sorted order.
+            if let Some(view_gpu_sort) = view_gpu_sort {
+                view_gpu_sort.dispatch(
+                    &mut compute_pass,
+                    pipeline_cache,
+                    gpu_sort_pipelines,
+                    &bind_group.0,
+                    view_uniform_offset.offset,
+                );
+            }
+
             compute_pass.set_pipeline(preprocess_pipeline);
 
             let mut dynamic_offsets: SmallVec<[u32; 1]> = smallvec![];
@@ -364,10 +397,8 @@ impl PreprocessPipeline {
         pipelines: &mut SpecializedComputePipelines<PreprocessPipeline>,
         key: PreprocessPipelineKey,
     ) {
-        if self.pipeline_id.is_some() {
-            return;
-        }
-
+        // Specialize every frame, so that the pipeline is marked as used and
+        // isn't evicted.
         let preprocess_pipeline_id = pipelines.specialize(pipeline_cache, self, key);
         self.pipeline_id = Some(preprocess_pipeline_id);
     }
@@ -0,0 +1,426 @@
+//! GPU sorting of mesh instances.
+//!
+//! In views with [`GpuSorting`], the transparent phase items are only grouped
+//! into batches on the CPU. The GPU then generates a sort key for each instance
+//! of these batches during preprocessing, from its batch and its depth, and
+//! radix sorts them, so that the mesh preprocessing shader writes the instances
+//! of each batch back to front.
+
+use bevy_asset::Handle;
+use bevy_derive::{Deref, DerefMut};
+use bevy_ecs::{
+    component::Component,
+    entity::Entity,
+    query::{With, Without},
+    system::{Commands, Query, Res, ResMut, Resource},
+    world::{FromWorld, World},
+};
+use bevy_render::{
+    batching::gpu_preprocessing::{BatchedInstanceBuffers, GpuSortItem, PreprocessWorkItem},
+    camera::Camera,
+    render_resource::{
+        binding_types::{storage_buffer, storage_buffer_read_only, uniform_buffer},
+        BindGroup, BindGroupEntries, BindGroupLayout, BindGroupLayoutEntries, BufferUsages,
+        CachedComputePipelineId, ComputePass, ComputePipelineDescriptor, DynamicUniformBuffer,
+        PipelineCache, Shader, ShaderStages, ShaderType, UninitBufferVec,
+    },
+    renderer::{RenderDevice, RenderQueue},
+    view::{GpuCulling, GpuSorting, ViewUniform, ViewUniforms},
+    Extract,
+};
+use bevy_utils::{warn_once, EntityHashMap};
+
+use crate::{MeshInputUniform, MeshUniform, PreprocessPipelines, MESH_PREPROCESS_SHADER_HANDLE};
+
+/// The handle to the `gpu_sort.wgsl` compute shader.
+pub const GPU_SORT_SHADER_HANDLE: Handle<Shader> = Handle::weak_from_u128(7401948285830126543);
+
+/// The number of instances in each block of the sort, which is the GPU
+/// workgroup size of the sorting shaders.
+const SORT_WORKGROUP_SIZE: u32 = 256;
+
+/// The number of bits of the keys each radix sort pass sorts on.
+const RADIX_BITS: u32 = 4;
+
+/// The number of distinct digits each radix sort pass sorts on.
+const RADIX_DIGITS: u32 = 1 << RADIX_BITS;
+
+/// The number of radix sort passes needed to sort the 32-bit keys.
+const RADIX_PASSES: usize = (u32::BITS / RADIX_BITS) as usize;
+
+/// The parameters of one pass of the GPU sort of the instances of a view.
+#[derive(Clone, Copy, ShaderType)]
+pub struct GpuSortUniform {
+    /// The number of instances to sort.
+    pub count: u32,
+    /// The number of blocks of instances, each sorted by one workgroup.
+    pub block_count: u32,
+    /// The number of high bits of the keys holding the batch index.
+    pub batch_bits: u32,
+    /// The first bit of the keys this pass sorts on.
+    pub shift: u32,
+}
+
+/// The [`GpuSortUniform`]s of all passes of all views.
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct GpuSortUniforms(pub DynamicUniformBuffer<GpuSortUniform>);
+
+/// The buffers the instances of each view are sorted in.
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct GpuSortBuffers(pub EntityHashMap<Entity, ViewGpuSortBuffers>);
+
+/// The buffers the instances of a single view are sorted in.
+pub struct ViewGpuSortBuffers {
+    /// The two buffers of keys that the radix sort passes ping-pong between.
+    pub keys: [UninitBufferVec<u32>; 2],
+    /// The two buffers of instance input indices sorted along with the keys.
+    pub values: [UninitBufferVec<u32>; 2],
+    /// The number of instances of each block with each digit.
+    pub block_histograms: UninitBufferVec<u32>,
+}
+
+/// The bind groups and uniform offsets needed to sort the instances of a view
+/// on the GPU.
+///
+/// This goes on the view.
+#[derive(Component)]
+pub struct ViewGpuSort {
+    /// The bind group of the key generation shader, which follows the
+    /// preprocessing bind group.
+    keys_bind_group: BindGroup,
+    /// The bind groups of the radix sort passes, sorting from the first buffers
+    /// to the second ones and back.
+    radix_bind_groups: [BindGroup; 2],
+    /// The offset of the [`GpuSortUniform`] of each pass.
+    uniform_offsets: [u32; RADIX_PASSES],
+    /// The number of blocks of instances.
+    block_count: u32,
+}
+
+/// The compute shader pipelines that sort the instances of views with
+/// [`GpuSorting`].
+#[derive(Resource)]
+pub struct GpuSortPipelines {
+    /// The layout of the bind group of the key generation shader.
+    pub keys_bind_group_layout: BindGroupLayout,
+    /// The layout of the bind groups of the radix sort passes.
+    pub radix_bind_group_layout: BindGroupLayout,
+    /// The pipeline that generates the sort keys.
+    pub generate_keys: CachedComputePipelineId,
+    /// The pipeline that counts the digits of each block.
+    pub build_histograms: CachedComputePipelineId,
+    /// The pipeline that turns the digit counts into offsets.
+    pub scan: CachedComputePipelineId,
+    /// The pipeline that moves the keys and values to their sorted place.
+    pub scatter: CachedComputePipelineId,
+    /// The pipeline that stores the sorted instances into the work items.
+    pub write_back: CachedComputePipelineId,
+}
+
+impl FromWorld for GpuSortPipelines {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+
+        let keys_bind_group_layout = render_device.create_bind_group_layout(
+            "GPU sort keys bind group layout",
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::COMPUTE,
+                (
+                    // `view`
+                    uniform_buffer::<ViewUniform>(/*has_dynamic_offset=*/ true),
+                    // `sort_uniform`
+                    uniform_buffer::<GpuSortUniform>(/*has_dynamic_offset=*/ true),
+                    // `sort_items`
+                    storage_buffer_read_only::<GpuSortItem>(false),
+                    // `sort_keys`
+                    storage_buffer::<u32>(false),
+                    // `sort_values`
+                    storage_buffer::<u32>(false),
+                ),
+            ),
+        );
+        let radix_bind_group_layout = render_device.create_bind_group_layout(
+            "GPU sort radix bind group layout",
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::COMPUTE,
+                (
+                    // `sort_uniform`
+                    uniform_buffer::<GpuSortUniform>(/*has_dynamic_offset=*/ true),
+                    // `keys_in`
+                    storage_buffer_read_only::<u32>(false),
+                    // `values_in`
+                    storage_buffer_read_only::<u32>(false),
+                    // `keys_out`
+                    storage_buffer::<u32>(false),
+                    // `values_out`
+                    storage_buffer::<u32>(false),
+                    // `block_histograms`
+                    storage_buffer::<u32>(false),
+                    // `sort_items`
+                    storage_buffer_read_only::<GpuSortItem>(false),
+                    // `work_items`
+                    storage_buffer::<PreprocessWorkItem>(false),
+                ),
+            ),
+        );
+
+        // The keys are generated by the preprocessing shader, reading the
+        // inputs and work items of its direct bind group.
+        let preprocess_bind_group_layout = world
+            .resource::<PreprocessPipelines>()
+            .direct
+            .bind_group_layout
+            .clone();
+
+        let pipeline_cache = world.resource::<PipelineCache>();
+        let generate_keys = pipeline_cache.queue_compute_pipeline(ComputePipelineDescriptor {
+            label: Some("GPU sort generate keys".into()),
+            layout: vec![preprocess_bind_group_layout, keys_bind_group_layout.clone()],
+            push_constant_ranges: vec![],
+            shader: MESH_PREPROCESS_SHADER_HANDLE,
+            shader_defs: vec!["GPU_SORTING".into()],
+            entry_point: "generate_sort_keys".into(),
+        });
+        let [build_histograms, scan, scatter, write_back] =
+            ["build_histograms", "scan", "scatter", "write_back"].map(|entry_point| {
+                pipeline_cache.queue_compute_pipeline(ComputePipelineDescriptor {
+                    label: Some(format!("GPU sort {entry_point}").into()),
+                    layout: vec![radix_bind_group_layout.clone()],
+                    push_constant_ranges: vec![],
+                    shader: GPU_SORT_SHADER_HANDLE,
+                    shader_defs: vec![],
+                    entry_point: entry_point.into(),
+                })
+            });
+
+        GpuSortPipelines {
+            keys_bind_group_layout,
+            radix_bind_group_layout,
+            generate_keys,
+            build_histograms,
+            scan,
+            scatter,
+            write_back,
+        }
+    }
+}
+
+impl ViewGpuSortBuffers {
+    fn new() -> Self {
+        ViewGpuSortBuffers {
+            keys: [
+                UninitBufferVec::new(BufferUsages::STORAGE),
+                UninitBufferVec::new(BufferUsages::STORAGE),
+            ],
+            values: [
+                UninitBufferVec::new(BufferUsages::STORAGE),
+                UninitBufferVec::new(BufferUsages::STORAGE),
+            ],
+            block_histograms: UninitBufferVec::new(BufferUsages::STORAGE),
+        }
+    }
+}
+
+impl ViewGpuSort {
+    /// Records the dispatches that sort the instances of this view, before the
+    /// preprocessing shader runs with `preprocess_bind_group`.
+    ///
+    /// The instances are left unsorted while the pipelines are being compiled.
+    pub fn dispatch<'a>(
+        &'a self,
+        compute_pass: &mut ComputePass<'a>,
+        pipeline_cache: &'a PipelineCache,
+        pipelines: &GpuSortPipelines,
+        preprocess_bind_group: &'a BindGroup,
+        view_uniform_offset: u32,
+    ) {
+        let (
+            Some(generate_keys),
+            Some(build_histograms),
+            Some(scan),
+            Some(scatter),
+            Some(write_back),
+        ) = (
+            pipeline_cache.get_compute_pipeline(pipelines.generate_keys),
+            pipeline_cache.get_compute_pipeline(pipelines.build_histograms),
+            pipeline_cache.get_compute_pipeline(pipelines.scan),
+            pipeline_cache.get_compute_pipeline(pipelines.scatter),
+            pipeline_cache.get_compute_pipeline(pipelines.write_back),
+        )
+        else {
+            return;
+        };
+
+        compute_pass.set_pipeline(generate_keys);
+        compute_pass.set_bind_group(0, preprocess_bind_group, &[]);
+        compute_pass.set_bind_group(
+            1,
+            &self.keys_bind_group,
+            &[view_uniform_offset, self.uniform_offsets[0]],
+        );
+        compute_pass.dispatch_workgroups(self.block_count, 1, 1);
+
+        for (pass, &uniform_offset) in self.uniform_offsets.iter().enumerate() {
+            compute_pass.set_bind_group(0, &self.radix_bind_groups[pass % 2], &[uniform_offset]);
+            compute_pass.set_pipeline(build_histograms);
+            compute_pass.dispatch_workgroups(self.block_count, 1, 1);
+            compute_pass.set_pipeline(scan);
+            compute_pass.dispatch_workgroups(1, 1, 1);
+            compute_pass.set_pipeline(scatter);
+            compute_pass.dispatch_workgroups(self.block_count, 1, 1);
+        }
+
+        // There's an even number of passes, so the sorted values are back in
+        // the first buffers.
+        compute_pass.set_pipeline(write_back);
+        compute_pass.set_bind_group(0, &self.radix_bind_groups[0], &[self.uniform_offsets[0]]);
+        compute_pass.dispatch_workgroups(self.block_count, 1, 1);
+    }
+}
+
+/// A system that marks the cameras with [`GpuSorting`] for sorting on the GPU.
+///
+/// Cameras with [`GpuCulling`] are skipped, as culling compacts the instances
+/// out of order.
+pub fn extract_gpu_sorting(
+    mut commands: Commands,
+    cameras: Extract<Query<(Entity, &Camera), (With<GpuSorting>, Without<GpuCulling>)>>,
+) {
+    for (entity, camera) in &cameras {
+        if camera.is_active {
+            commands.get_or_spawn(entity).insert(GpuSorting);
+        }
+    }
+}
+
+/// A system that sizes the sort buffers of the views with instances to sort on
+/// the GPU, and creates their bind groups.
+#[allow(clippy::too_many_arguments)]
+pub fn prepare_gpu_sort_bind_groups(
+    mut commands: Commands,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    batched_instance_buffers: Res<BatchedInstanceBuffers<MeshUniform, MeshInputUniform>>,
+    view_uniforms: Res<ViewUniforms>,
+    pipelines: Res<GpuSortPipelines>,
+    mut gpu_sort_buffers: ResMut<GpuSortBuffers>,
+    mut gpu_sort_uniforms: ResMut<GpuSortUniforms>,
+) {
+    let work_item_buffers = &batched_instance_buffers.work_item_buffers;
+
+    // Drop the buffers of the views that are gone.
+    gpu_sort_buffers.retain(|view, _| work_item_buffers.contains_key(view));
+
+    // Size the buffers and write the uniforms of every view first, as the bind
+    // groups need the final uniform buffer.
+    gpu_sort_uniforms.clear();
+    let max_block_count = render_device.limits().max_compute_workgroups_per_dimension;
+    let mut views = vec![];
+    for (&view, work_item_buffer) in work_item_buffers {
+        let count = work_item_buffer.sort_items.len() as u32;
+        if count == 0 {
+            continue;
+        }
+
+        let block_count = count.div_ceil(SORT_WORKGROUP_SIZE);
+        if block_count > max_block_count {
+            warn_once!("Too many instances to sort on the GPU in a view; they won't be sorted");
+            continue;
+        }
+
+        let view_buffers = gpu_sort_buffers
+            .entry(view)
+            .or_insert_with(ViewGpuSortBuffers::new);
+        for buffer in view_buffers.keys.iter_mut().chain(&mut view_buffers.values) {
+            buffer.reserve(count as usize, &render_device);
+        }
+        view_buffers
+            .block_histograms
+            .reserve((RADIX_DIGITS * block_count) as usize, &render_device);
+
+        // Only give the batch index as many bits as needed, leaving the rest to
+        // the depth.
+        let batch_bits = match work_item_buffer.sort_batch_count {
+            0 | 1 => 0,
+            batch_count => u32::BITS - (batch_count - 1).leading_zeros(),
+        };
+        let mut uniform_offsets = [0; RADIX_PASSES];
+        for (pass, uniform_offset) in uniform_offsets.iter_mut().enumerate() {
+            *uniform_offset = gpu_sort_uniforms.push(&GpuSortUniform {
+                count,
+                block_count,
+                batch_bits,
+                shift: pass as u32 * RADIX_BITS,
+            });
+        }
+        views.push((view, uniform_offsets, block_count));
+    }
+    gpu_sort_uniforms.write_buffer(&render_device, &render_queue);
+
+    let (Some(sort_uniforms_binding), Some(view_uniforms_binding)) = (
+        gpu_sort_uniforms.binding(),
+        view_uniforms.uniforms.binding(),
+    ) else {
+        return;
+    };
+
+    for (view, uniform_offsets, block_count) in views {
+        let work_item_buffer = &work_item_buffers[&view];
+        let view_buffers = &gpu_sort_buffers[&view];
+        let (
+            Some(sort_items),
+            Some(work_items),
+            [Some(keys_a), Some(keys_b)],
+            [Some(values_a), Some(values_b)],
+            Some(block_histograms),
+        ) = (
+            work_item_buffer.sort_items.buffer(),
+            work_item_buffer.buffer.buffer(),
+            view_buffers.keys.each_ref().map(UninitBufferVec::buffer),
+            view_buffers.values.each_ref().map(UninitBufferVec::buffer),
+            view_buffers.block_histograms.buffer(),
+        )
+        else {
+            continue;
+        };
+
+        let keys_bind_group = render_device.create_bind_group(
+            "GPU sort keys bind group",
+            &pipelines.keys_bind_group_layout,
+            &BindGroupEntries::sequential((
+                view_uniforms_binding.clone(),
+                sort_uniforms_binding.clone(),
+                sort_items.as_entire_binding(),
+                keys_a.as_entire_binding(),
+                values_a.as_entire_binding(),
+            )),
+        );
+        let radix_bind_groups = [
+            (keys_a, values_a, keys_b, values_b),
+            (keys_b, values_b, keys_a, values_a),
+        ]
+        .map(|(keys_in, values_in, keys_out, values_out)| {
+            render_device.create_bind_group(
+                "GPU sort radix bind group",
+                &pipelines.radix_bind_group_layout,
+                &BindGroupEntries::sequential((
+                    sort_uniforms_binding.clone(),
+                    keys_in.as_entire_binding(),
+                    values_in.as_entire_binding(),
+                    keys_out.as_entire_binding(),
+                    values_out.as_entire_binding(),
+                    block_histograms.as_entire_binding(),
+                    sort_items.as_entire_binding(),
+                    work_items.as_entire_binding(),
+                )),
+            )
+        });
+
+        commands.entity(view).insert(ViewGpuSort {
+            keys_bind_group,
+            radix_bind_groups,
+            uniform_offsets,
+            block_count,
+        });
+    }
+}
@@ -0,0 +1,184 @@
+// GPU sorting of mesh instances.
+//
+// This is a least significant digit radix sort of the keys generated by
+// `generate_sort_keys` in `mesh_preprocess.wgsl`, along with the input indices
+// of the instances. Each pass sorts on `RADIX_BITS` bits of the keys, in three
+// dispatches: `build_histograms` counts the digits of each block of instances,
+// `scan` turns those counts into the offsets each block scatters to, and
+// `scatter` moves the keys and values. The passes ping-pong between two pairs of
+// key and value buffers. Once all passes are done, `write_back` stores the
+// sorted input indices into the work items of the preprocessing shader, which
+// then writes the instances of each batch in sorted order.
+
+// The parameters of the GPU sort of the instances of a view. This must match
+// `GpuSortUniform` in `gpu_sort.rs`.
+struct GpuSortUniform {
+    // The number of instances to sort.
+    count: u32,
+    // The number of blocks of `SORT_WORKGROUP_SIZE` instances.
+    block_count: u32,
+    // The number of high bits of the keys holding the batch index.
+    batch_bits: u32,
+    // The first bit of the keys sorted on by the current pass.
+    shift: u32,
+}
+
+// One instance sorted on the GPU.
+struct GpuSortItem {
+    // The index of the `PreprocessWorkItem` of this instance in `work_items`.
+    work_item_index: u32,
+    // The index of the batch of this instance.
+    batch_index: u32,
+}
+
+// One invocation of the preprocessing shader. This must match
+// `PreprocessWorkItem` in `mesh_preprocess.wgsl`.
+struct PreprocessWorkItem {
+    input_index: u32,
+    output_index: u32,
+}
+
+// The number of bits of the keys each pass sorts on.
+const RADIX_BITS: u32 = 4u;
+// The number of distinct digits of `RADIX_BITS` bits.
+const RADIX_DIGITS: u32 = 16u;
+// The number of instances in each block, which is the workgroup size.
+const SORT_WORKGROUP_SIZE: u32 = 256u;
+
+@group(0) @binding(0) var<uniform> sort_uniform: GpuSortUniform;
+// The keys and values sorted by the previous pass.
+@group(0) @binding(1) var<storage> keys_in: array<u32>;
+@group(0) @binding(2) var<storage> values_in: array<u32>;
+// The keys and values sorted by this pass.
+@group(0) @binding(3) var<storage, read_write> keys_out: array<u32>;
+@group(0) @binding(4) var<storage, read_write> values_out: array<u32>;
+// The number of instances of each block with each digit, laid out digit by
+// digit, which `scan` turns into the offset of the first of them.
+@group(0) @binding(5) var<storage, read_write> block_histograms: array<u32>;
+// The instances to sort.
+@group(0) @binding(6) var<storage> sort_items: array<GpuSortItem>;
+// The work items of the preprocessing shader.
+@group(0) @binding(7) var<storage, read_write> work_items: array<PreprocessWorkItem>;
+
+var<workgroup> histogram: array<atomic<u32>, 16>;
+var<workgroup> scan_totals: array<u32, 256>;
+var<workgroup> digits: array<u32, 256>;
+
+// Returns the digit of a key sorted on by the current pass.
+fn key_digit(key: u32) -> u32 {
+    return (key >> sort_uniform.shift) & (RADIX_DIGITS - 1u);
+}
+
+// Counts the digits of the keys of each block.
+@compute
+@workgroup_size(256)
+fn build_histograms(
+    @builtin(global_invocation_id) global_invocation_id: vec3<u32>,
+    @builtin(local_invocation_index) local_invocation_index: u32,
+    @builtin(workgroup_id) workgroup_id: vec3<u32>,
+) {
+    if (local_invocation_index < RADIX_DIGITS) {
+        atomicStore(&histogram[local_invocation_index], 0u);
+    }
+    workgroupBarrier();
+
+    let sort_index = global_invocation_id.x;
+    if (sort_index < sort_uniform.count) {
+        atomicAdd(&histogram[key_digit(keys_in[sort_index])], 1u);
+    }
+    workgroupBarrier();
+
+    if (local_invocation_index < RADIX_DIGITS) {
+        block_histograms[local_invocation_index * sort_uniform.block_count + workgroup_id.x] =
+            atomicLoad(&histogram[local_invocation_index]);
+    }
+}
+
+// Replaces the block histograms by their exclusive prefix sum, in a single
+// workgroup. As they are laid out digit by digit, this yields the index the
+// first instance of each block with each digit is scattered to.
+@compute
+@workgroup_size(256)
+fn scan(@builtin(local_invocation_index) local_invocation_index: u32) {
+    // Sum a contiguous chunk of the histograms in each invocation.
+    let histogram_count = RADIX_DIGITS * sort_uniform.block_count;
+    let chunk_size = (histogram_count + SORT_WORKGROUP_SIZE - 1u) / SORT_WORKGROUP_SIZE;
+    let chunk_start = min(local_invocation_index * chunk_size, histogram_count);
+    let chunk_end = min(chunk_start + chunk_size, histogram_count);
+
+    var chunk_total = 0u;
+    for (var i = chunk_start; i < chunk_end; i += 1u) {
+        chunk_total += block_histograms[i];
+    }
+    scan_totals[local_invocation_index] = chunk_total;
+    workgroupBarrier();
+
+    // Scan the chunk totals across the workgroup.
+    for (var offset = 1u; offset < SORT_WORKGROUP_SIZE; offset <<= 1u) {
+        var total = scan_totals[local_invocation_index];
+        if (local_invocation_index >= offset) {
+            total += scan_totals[local_invocation_index - offset];
+        }
+        workgroupBarrier();
+        scan_totals[local_invocation_index] = total;
+        workgroupBarrier();
+    }
+
+    // Write the exclusive prefix sums of the chunk.
+    var prefix = scan_totals[local_invocation_index] - chunk_total;
+    for (var i = chunk_start; i < chunk_end; i += 1u) {
+        let count = block_histograms[i];
+        block_histograms[i] = prefix;
+        prefix += count;
+    }
+}
+
+// Moves the keys and values to their sorted place for this pass, keeping the
+// order of the instances with the same digit so that the sort is stable.
+@compute
+@workgroup_size(256)
+fn scatter(
+    @builtin(global_invocation_id) global_invocation_id: vec3<u32>,
+    @builtin(local_invocation_index) local_invocation_index: u32,
+    @builtin(workgroup_id) workgroup_id: vec3<u32>,
+) {
+    let sort_index = global_invocation_id.x;
+
+    // Share the digits of the block, marking the invocations past the end with
+    // a digit that matches no key.
+    var digit = RADIX_DIGITS;
+    if (sort_index < sort_uniform.count) {
+        digit = key_digit(keys_in[sort_index]);
+    }
+    digits[local_invocation_index] = digit;
+    workgroupBarrier();
+
+    if (sort_index >= sort_uniform.count) {
+        return;
+    }
+
+    // Rank this instance among the instances of the block with the same digit.
+    var rank = 0u;
+    for (var i = 0u; i < local_invocation_index; i += 1u) {
+        if (digits[i] == digit) {
+            rank += 1u;
+        }
+    }
+
+    let destination =
+        block_histograms[digit * sort_uniform.block_count + workgroup_id.x] + rank;
+    keys_out[destination] = keys_in[sort_index];
+    values_out[destination] = values_in[sort_index];
+}
+
+// Stores the sorted input indices into the work items of the sorted instances.
+@compute
+@workgroup_size(256)
+fn write_back(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+    let sort_index = global_invocation_id.x;
+    if (sort_index >= sort_uniform.count) {
+        return;
+    }
+
+    work_items[sort_items[sort_index].work_item_index].input_index = values_in[sort_index];
+}
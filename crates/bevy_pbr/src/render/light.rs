@@ -37,6 +37,9 @@ pub struct ExtractedPointLight {
     pub shadows_enabled: bool,
     pub shadow_depth_bias: f32,
     pub shadow_normal_bias: f32,
+    /// Whether the biases are in texels and scaled to the shadow map texel size at the fragment,
+    /// see [`ShadowBiasMode::Auto`].
+    pub auto_shadow_bias: bool,
     pub spot_light_angles: Option<(f32, f32)>,
 }
 
@@ -48,6 +51,9 @@ pub struct ExtractedDirectionalLight {
     pub shadows_enabled: bool,
     pub shadow_depth_bias: f32,
     pub shadow_normal_bias: f32,
+    /// Whether the depth bias is in texels and scaled to the texel size of each cascade, see
+    /// [`ShadowBiasMode::Auto`].
+    pub auto_shadow_bias: bool,
     pub cascade_shadow_config: CascadeShadowConfig,
     pub cascades: EntityHashMap<Vec<Cascade>>,
     pub frusta: EntityHashMap<Vec<Frustum>>,
@@ -150,6 +156,7 @@ bitflags::bitflags! {
     struct PointLightFlags: u32 {
         const SHADOWS_ENABLED            = 1 << 0;
         const SPOT_LIGHT_Y_NEGATIVE      = 1 << 1;
+        const AUTO_SHADOW_BIAS           = 1 << 2;
         const NONE                       = 0;
         const UNINITIALIZED              = 0xFFFF;
     }
@@ -174,6 +181,9 @@ pub struct GpuDirectionalLight {
     cascades_overlap_proportion: f32,
     depth_texture_base_index: u32,
     render_layers: u32,
+    cookie_strength: f32,
+    cookie_height: f32,
+    cookie_uv_transform: Vec4,
 }
 
 // NOTE: These must match the bit flags in bevy_pbr/src/render/mesh_view_types.wgsl!
@@ -181,6 +191,9 @@ bitflags::bitflags! {
     #[repr(transparent)]
     struct DirectionalLightFlags: u32 {
         const SHADOWS_ENABLED            = 1 << 0;
+        const AUTO_SHADOW_BIAS           = 1 << 1;
+        const CASCADE_BLEND_SMOOTH       = 1 << 2;
+        const CASCADE_BLEND_DITHERED     = 1 << 3;
         const NONE                       = 0;
         const UNINITIALIZED              = 0xFFFF;
     }
@@ -315,6 +328,7 @@ pub fn extract_lights(
     mut commands: Commands,
     point_light_shadow_map: Extract<Res<PointLightShadowMap>>,
     directional_light_shadow_map: Extract<Res<DirectionalLightShadowMap>>,
+    default_shadow_bias_mode: Extract<Res<DefaultShadowBiasMode>>,
     global_point_lights: Extract<Res<GlobalVisiblePointLights>>,
     point_lights: Extract<
         Query<(
@@ -323,6 +337,7 @@ pub fn extract_lights(
             &GlobalTransform,
             &ViewVisibility,
             &CubemapFrusta,
+            Option<&ShadowBiasMode>,
         )>,
     >,
     spot_lights: Extract<
@@ -332,6 +347,7 @@ pub fn extract_lights(
             &GlobalTransform,
             &ViewVisibility,
             &Frustum,
+            Option<&ShadowBiasMode>,
         )>,
     >,
     directional_lights: Extract<
@@ -346,6 +362,7 @@ pub fn extract_lights(
                 &GlobalTransform,
                 &ViewVisibility,
                 Option<&RenderLayers>,
+                Option<&ShadowBiasMode>,
             ),
             Without<SpotLight>,
         >,
@@ -372,8 +389,14 @@ pub fn extract_lights(
 
     let mut point_lights_values = Vec::with_capacity(*previous_point_lights_len);
     for entity in global_point_lights.iter().copied() {
-        let Ok((point_light, cubemap_visible_entities, transform, view_visibility, frusta)) =
-            point_lights.get(entity)
+        let Ok((
+            point_light,
+            cubemap_visible_entities,
+            transform,
+            view_visibility,
+            frusta,
+            shadow_bias_mode,
+        )) = point_lights.get(entity)
         else {
             continue;
         };
@@ -383,6 +406,14 @@ pub fn extract_lights(
         // TODO: This is very much not ideal. We should be able to re-use the vector memory.
         // However, since exclusive access to the main world in extract is ill-advised, we just clone here.
         let render_cubemap_visible_entities = cubemap_visible_entities.clone();
+        let shadow_bias_mode = shadow_bias_mode
+            .copied()
+            .unwrap_or(default_shadow_bias_mode.0);
+        let (shadow_depth_bias, shadow_normal_bias) = shadow_bias_mode.biases(
+            point_light.shadow_depth_bias,
+            point_light.shadow_normal_bias,
+        );
+        let auto_shadow_bias = shadow_bias_mode == ShadowBiasMode::Auto;
         let extracted_point_light = ExtractedPointLight {
             color: point_light.color.into(),
             // NOTE: Map from luminous power in lumens to luminous intensity in lumens per steradian
@@ -393,11 +424,18 @@ pub fn extract_lights(
             radius: point_light.radius,
             transform: *transform,
             shadows_enabled: point_light.shadows_enabled,
-            shadow_depth_bias: point_light.shadow_depth_bias,
+            // In texels, the depth bias is scaled to the texel size at 1 world unit from the light
+            // like the normal bias, and then to the distance of the fragment in the shader.
+            shadow_depth_bias: if auto_shadow_bias {
+                shadow_depth_bias * point_light_texel_size
+            } else {
+                shadow_depth_bias
+            },
             // The factor of SQRT_2 is for the worst-case diagonal offset
-            shadow_normal_bias: point_light.shadow_normal_bias
+            shadow_normal_bias: shadow_normal_bias
                 * point_light_texel_size
                 * std::f32::consts::SQRT_2,
+            auto_shadow_bias,
             spot_light_angles: None,
         };
         point_lights_values.push((
@@ -414,8 +452,14 @@ pub fn extract_lights(
 
     let mut spot_lights_values = Vec::with_capacity(*previous_spot_lights_len);
     for entity in global_point_lights.iter().copied() {
-        if let Ok((spot_light, visible_entities, transform, view_visibility, frustum)) =
-            spot_lights.get(entity)
+        if let Ok((
+            spot_light,
+            visible_entities,
+            transform,
+            view_visibility,
+            frustum,
+            shadow_bias_mode,
+        )) = spot_lights.get(entity)
         {
             if !view_visibility.get() {
                 continue;
@@ -425,6 +469,12 @@ pub fn extract_lights(
             let render_visible_entities = visible_entities.clone();
             let texel_size =
                 2.0 * spot_light.outer_angle.tan() / directional_light_shadow_map.size as f32;
+            let shadow_bias_mode = shadow_bias_mode
+                .copied()
+                .unwrap_or(default_shadow_bias_mode.0);
+            let (shadow_depth_bias, shadow_normal_bias) = shadow_bias_mode
+                .biases(spot_light.shadow_depth_bias, spot_light.shadow_normal_bias);
+            let auto_shadow_bias = shadow_bias_mode == ShadowBiasMode::Auto;
 
             spot_lights_values.push((
                 entity,
@@ -442,11 +492,17 @@ pub fn extract_lights(
                         radius: spot_light.radius,
                         transform: *transform,
                         shadows_enabled: spot_light.shadows_enabled,
-                        shadow_depth_bias: spot_light.shadow_depth_bias,
+                        // See the point light above
+                        shadow_depth_bias: if auto_shadow_bias {
+                            shadow_depth_bias * texel_size
+                        } else {
+                            shadow_depth_bias
+                        },
                         // The factor of SQRT_2 is for the worst-case diagonal offset
-                        shadow_normal_bias: spot_light.shadow_normal_bias
+                        shadow_normal_bias: shadow_normal_bias
                             * texel_size
                             * std::f32::consts::SQRT_2,
+                        auto_shadow_bias,
                         spot_light_angles: Some((spot_light.inner_angle, spot_light.outer_angle)),
                     },
                     render_visible_entities,
@@ -468,12 +524,21 @@ pub fn extract_lights(
         transform,
         view_visibility,
         maybe_layers,
+        shadow_bias_mode,
     ) in &directional_lights
     {
         if !view_visibility.get() {
             continue;
         }
 
+        let shadow_bias_mode = shadow_bias_mode
+            .copied()
+            .unwrap_or(default_shadow_bias_mode.0);
+        let (shadow_depth_bias, shadow_normal_bias) = shadow_bias_mode.biases(
+            directional_light.shadow_depth_bias,
+            directional_light.shadow_normal_bias,
+        );
+
         // TODO: As above
         let render_visible_entities = visible_entities.clone();
         commands.get_or_spawn(entity).insert((
@@ -482,9 +547,11 @@ pub fn extract_lights(
                 illuminance: directional_light.illuminance,
                 transform: *transform,
                 shadows_enabled: directional_light.shadows_enabled,
-                shadow_depth_bias: directional_light.shadow_depth_bias,
+                // In texels, the depth bias is scaled to the texel size of each cascade in the shader
+                shadow_depth_bias,
                 // The factor of SQRT_2 is for the worst-case diagonal offset
-                shadow_normal_bias: directional_light.shadow_normal_bias * std::f32::consts::SQRT_2,
+                shadow_normal_bias: shadow_normal_bias * std::f32::consts::SQRT_2,
+                auto_shadow_bias: shadow_bias_mode == ShadowBiasMode::Auto,
                 cascade_shadow_config: cascade_config.clone(),
                 cascades: cascades.cascades.clone(),
                 frusta: frusta.frusta.clone(),
@@ -554,9 +621,24 @@ fn face_index_to_name(face_index: usize) -> &'static str {
     }
 }
 
+/// Creates a view of a single layer of a colored shadow map, to draw the translucent casters of a
+/// light to.
+fn colored_shadow_layer_view(texture: &Texture, layer: u32) -> TextureView {
+    texture.create_view(&TextureViewDescriptor {
+        label: Some("colored_shadow_map_texture_view"),
+        dimension: Some(TextureViewDimension::D2),
+        base_array_layer: layer,
+        array_layer_count: Some(1u32),
+        ..Default::default()
+    })
+}
+
 #[derive(Component)]
 pub struct ShadowView {
     pub depth_attachment: DepthAttachment,
+    /// The layer of the colored shadow map the translucent casters are drawn to, if the view has
+    /// [`ColoredShadows`](crate::ColoredShadows).
+    pub colored_shadow_view: Option<TextureView>,
     pub pass_name: String,
 }
 
@@ -683,10 +765,16 @@ pub fn prepare_lights(
     render_queue: Res<RenderQueue>,
     mut global_light_meta: ResMut<GlobalLightMeta>,
     mut light_meta: ResMut<LightMeta>,
-    views: Query<
-        (Entity, &ExtractedView, &ExtractedClusterConfig),
+    mut views: Query<
+        (
+            Entity,
+            &ExtractedView,
+            &ExtractedClusterConfig,
+            Option<&mut ViewColoredShadowMaps>,
+        ),
         With<SortedRenderPhase<Transparent3d>>,
     >,
+    colored_shadow_map_sampler: Option<Res<ColoredShadowMapSampler>>,
     ambient_light: Res<AmbientLight>,
     point_light_shadow_map: Res<PointLightShadowMap>,
     directional_light_shadow_map: Res<DirectionalLightShadowMap>,
@@ -698,6 +786,7 @@ pub fn prepare_lights(
         AnyOf<(&CubemapFrusta, &Frustum)>,
     )>,
     directional_lights: Query<(Entity, &ExtractedDirectionalLight)>,
+    directional_light_cookies: Query<&ExtractedDirectionalLightCookie>,
 ) {
     let views_iter = views.iter();
     let views_count = views_iter.len();
@@ -834,6 +923,9 @@ pub fn prepare_lights(
         {
             flags |= PointLightFlags::SHADOWS_ENABLED;
         }
+        if light.auto_shadow_bias {
+            flags |= PointLightFlags::AUTO_SHADOW_BIAS;
+        }
 
         let (light_custom_data, spot_light_tan_angle) = match light.spot_light_angles {
             Some((inner, outer)) => {
@@ -886,7 +978,7 @@ pub fn prepare_lights(
 
     let mut gpu_directional_lights = [GpuDirectionalLight::default(); MAX_DIRECTIONAL_LIGHTS];
     let mut num_directional_cascades_enabled = 0usize;
-    for (index, (_light_entity, light)) in directional_lights
+    for (index, (light_entity, light)) in directional_lights
         .iter()
         .enumerate()
         .take(MAX_DIRECTIONAL_LIGHTS)
@@ -897,12 +989,26 @@ pub fn prepare_lights(
         if light.shadows_enabled && (index < directional_shadow_enabled_count) {
             flags |= DirectionalLightFlags::SHADOWS_ENABLED;
         }
+        if light.auto_shadow_bias {
+            flags |= DirectionalLightFlags::AUTO_SHADOW_BIAS;
+        }
 
         let num_cascades = light
             .cascade_shadow_config
             .bounds
             .len()
             .min(MAX_CASCADES_PER_LIGHT);
+        // Without an overlap there is nothing to blend, so the shader can skip it
+        if num_cascades > 1 && light.cascade_shadow_config.overlap_proportion > 0.0 {
+            match light.cascade_shadow_config.blend_mode {
+                CascadeBlendMode::None => {}
+                CascadeBlendMode::Smooth => flags |= DirectionalLightFlags::CASCADE_BLEND_SMOOTH,
+                CascadeBlendMode::Dithered => {
+                    flags |= DirectionalLightFlags::CASCADE_BLEND_DITHERED;
+                }
+            }
+        }
+        let cookie = directional_light_cookies.get(*light_entity).ok();
         gpu_directional_lights[index] = GpuDirectionalLight {
             // Filled in later.
             cascades: [GpuDirectionalCascade::default(); MAX_CASCADES_PER_LIGHT],
@@ -918,6 +1024,9 @@ pub fn prepare_lights(
             cascades_overlap_proportion: light.cascade_shadow_config.overlap_proportion,
             depth_texture_base_index: num_directional_cascades_enabled as u32,
             render_layers: light.render_layers.bits(),
+            cookie_strength: cookie.map_or(0.0, |cookie| cookie.strength),
+            cookie_height: cookie.map_or(0.0, |cookie| cookie.height),
+            cookie_uv_transform: cookie.map_or(Vec4::ZERO, |cookie| cookie.uv_transform),
         };
         if index < directional_shadow_enabled_count {
             num_directional_cascades_enabled += num_cascades;
@@ -929,46 +1038,73 @@ pub fn prepare_lights(
         .gpu_point_lights
         .write_buffer(&render_device, &render_queue);
 
+    let point_light_shadow_map_descriptor = TextureDescriptor {
+        size: Extent3d {
+            width: point_light_shadow_map.size as u32,
+            height: point_light_shadow_map.size as u32,
+            depth_or_array_layers: point_light_shadow_maps_count.max(1) as u32 * 6,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format: CORE_3D_DEPTH_FORMAT,
+        label: Some("point_light_shadow_map_texture"),
+        usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+    };
+    let directional_light_shadow_map_descriptor = TextureDescriptor {
+        size: Extent3d {
+            width: (directional_light_shadow_map.size as u32)
+                .min(render_device.limits().max_texture_dimension_2d),
+            height: (directional_light_shadow_map.size as u32)
+                .min(render_device.limits().max_texture_dimension_2d),
+            depth_or_array_layers: (num_directional_cascades_enabled + spot_light_shadow_maps_count)
+                .max(1) as u32,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format: CORE_3D_DEPTH_FORMAT,
+        label: Some("directional_light_shadow_map_texture"),
+        usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+    };
+
     // set up light data for each view
-    for (entity, extracted_view, clusters) in &views {
-        let point_light_depth_texture = texture_cache.get(
-            &render_device,
-            TextureDescriptor {
-                size: Extent3d {
-                    width: point_light_shadow_map.size as u32,
-                    height: point_light_shadow_map.size as u32,
-                    depth_or_array_layers: point_light_shadow_maps_count.max(1) as u32 * 6,
-                },
-                mip_level_count: 1,
-                sample_count: 1,
-                dimension: TextureDimension::D2,
-                format: CORE_3D_DEPTH_FORMAT,
-                label: Some("point_light_shadow_map_texture"),
-                usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
-                view_formats: &[],
-            },
-        );
+    for (entity, extracted_view, clusters, mut colored_shadow_maps) in &mut views {
+        let point_light_depth_texture =
+            texture_cache.get(&render_device, point_light_shadow_map_descriptor.clone());
         let directional_light_depth_texture = texture_cache.get(
             &render_device,
-            TextureDescriptor {
-                size: Extent3d {
-                    width: (directional_light_shadow_map.size as u32)
-                        .min(render_device.limits().max_texture_dimension_2d),
-                    height: (directional_light_shadow_map.size as u32)
-                        .min(render_device.limits().max_texture_dimension_2d),
-                    depth_or_array_layers: (num_directional_cascades_enabled
-                        + spot_light_shadow_maps_count)
-                        .max(1) as u32,
-                },
-                mip_level_count: 1,
-                sample_count: 1,
-                dimension: TextureDimension::D2,
-                format: CORE_3D_DEPTH_FORMAT,
-                label: Some("directional_light_shadow_map_texture"),
-                usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
-                view_formats: &[],
-            },
+            directional_light_shadow_map_descriptor.clone(),
         );
+
+        // The colored shadow maps have the same layers as the depth ones.
+        let colored_shadow_textures = colored_shadow_maps.is_some().then(|| {
+            (
+                texture_cache.get(
+                    &render_device,
+                    TextureDescriptor {
+                        format: COLORED_SHADOW_MAP_FORMAT,
+                        label: Some("point_light_colored_shadow_map_texture"),
+                        ..point_light_shadow_map_descriptor.clone()
+                    },
+                ),
+                texture_cache.get(
+                    &render_device,
+                    TextureDescriptor {
+                        format: COLORED_SHADOW_MAP_FORMAT,
+                        label: Some("directional_light_colored_shadow_map_texture"),
+                        ..directional_light_shadow_map_descriptor.clone()
+                    },
+                ),
+            )
+        });
+        let (point_light_colored_texture, directional_light_colored_texture) =
+            match &colored_shadow_textures {
+                Some((point, directional)) => (Some(&point.texture), Some(&directional.texture)),
+                None => (None, None),
+            };
         let mut view_lights = Vec::new();
 
         let is_orthographic = extracted_view.projection.w_axis.w == 1.0;
@@ -1035,38 +1171,45 @@ pub fn prepare_lights(
                             array_layer_count: Some(1u32),
                         });
 
-                let view_light_entity = commands
-                    .spawn((
-                        ShadowView {
-                            depth_attachment: DepthAttachment::new(depth_texture_view, Some(0.0)),
-                            pass_name: format!(
-                                "shadow pass point light {} {}",
-                                light_index,
-                                face_index_to_name(face_index)
-                            ),
-                        },
-                        ExtractedView {
-                            viewport: UVec4::new(
-                                0,
-                                0,
-                                point_light_shadow_map.size as u32,
-                                point_light_shadow_map.size as u32,
-                            ),
-                            transform: view_translation * *view_rotation,
-                            view_projection: None,
-                            projection: cube_face_projection,
-                            hdr: false,
-                            color_grading: Default::default(),
-                        },
-                        *frustum,
-                        BinnedRenderPhase::<Shadow>::default(),
-                        LightEntity::Point {
-                            light_entity,
-                            face_index,
-                        },
-                    ))
-                    .id();
-                view_lights.push(view_light_entity);
+                let colored_shadow_view = point_light_colored_texture.map(|texture| {
+                    colored_shadow_layer_view(texture, (light_index * 6 + face_index) as u32)
+                });
+
+                let mut view_light = commands.spawn((
+                    ShadowView {
+                        depth_attachment: DepthAttachment::new(depth_texture_view, Some(0.0)),
+                        colored_shadow_view,
+                        pass_name: format!(
+                            "shadow pass point light {} {}",
+                            light_index,
+                            face_index_to_name(face_index)
+                        ),
+                    },
+                    ExtractedView {
+                        viewport: UVec4::new(
+                            0,
+                            0,
+                            point_light_shadow_map.size as u32,
+                            point_light_shadow_map.size as u32,
+                        ),
+                        transform: view_translation * *view_rotation,
+                        view_projection: None,
+                        projection: cube_face_projection,
+                        hdr: false,
+                        hdr_format: Default::default(),
+                        color_grading: Default::default(),
+                    },
+                    *frustum,
+                    BinnedRenderPhase::<Shadow>::default(),
+                    LightEntity::Point {
+                        light_entity,
+                        face_index,
+                    },
+                ));
+                if point_light_colored_texture.is_some() {
+                    view_light.insert(BinnedRenderPhase::<TranslucentShadow>::default());
+                }
+                view_lights.push(view_light.id());
             }
         }
 
@@ -1098,32 +1241,42 @@ pub fn prepare_lights(
                         array_layer_count: Some(1u32),
                     });
 
-            let view_light_entity = commands
-                .spawn((
-                    ShadowView {
-                        depth_attachment: DepthAttachment::new(depth_texture_view, Some(0.0)),
-                        pass_name: format!("shadow pass spot light {light_index}"),
-                    },
-                    ExtractedView {
-                        viewport: UVec4::new(
-                            0,
-                            0,
-                            directional_light_shadow_map.size as u32,
-                            directional_light_shadow_map.size as u32,
-                        ),
-                        transform: spot_view_transform,
-                        projection: spot_projection,
-                        view_projection: None,
-                        hdr: false,
-                        color_grading: Default::default(),
-                    },
-                    *spot_light_frustum.unwrap(),
-                    BinnedRenderPhase::<Shadow>::default(),
-                    LightEntity::Spot { light_entity },
-                ))
-                .id();
+            let colored_shadow_view = directional_light_colored_texture.map(|texture| {
+                colored_shadow_layer_view(
+                    texture,
+                    (num_directional_cascades_enabled + light_index) as u32,
+                )
+            });
 
-            view_lights.push(view_light_entity);
+            let mut view_light = commands.spawn((
+                ShadowView {
+                    depth_attachment: DepthAttachment::new(depth_texture_view, Some(0.0)),
+                    colored_shadow_view,
+                    pass_name: format!("shadow pass spot light {light_index}"),
+                },
+                ExtractedView {
+                    viewport: UVec4::new(
+                        0,
+                        0,
+                        directional_light_shadow_map.size as u32,
+                        directional_light_shadow_map.size as u32,
+                    ),
+                    transform: spot_view_transform,
+                    projection: spot_projection,
+                    view_projection: None,
+                    hdr: false,
+                    hdr_format: Default::default(),
+                    color_grading: Default::default(),
+                },
+                *spot_light_frustum.unwrap(),
+                BinnedRenderPhase::<Shadow>::default(),
+                LightEntity::Spot { light_entity },
+            ));
+            if directional_light_colored_texture.is_some() {
+                view_light.insert(BinnedRenderPhase::<TranslucentShadow>::default());
+            }
+
+            view_lights.push(view_light.id());
         }
 
         // directional lights
@@ -1170,6 +1323,9 @@ pub fn prepare_lights(
                             base_array_layer: directional_depth_texture_array_index,
                             array_layer_count: Some(1u32),
                         });
+                let colored_shadow_view = directional_light_colored_texture.map(|texture| {
+                    colored_shadow_layer_view(texture, directional_depth_texture_array_index)
+                });
                 directional_depth_texture_array_index += 1;
 
                 let mut frustum = *frustum;
@@ -1177,35 +1333,39 @@ pub fn prepare_lights(
                 frustum.half_spaces[4] =
                     HalfSpace::new(frustum.half_spaces[4].normal().extend(f32::INFINITY));
 
-                let view_light_entity = commands
-                    .spawn((
-                        ShadowView {
-                            depth_attachment: DepthAttachment::new(depth_texture_view, Some(0.0)),
-                            pass_name: format!(
-                                "shadow pass directional light {light_index} cascade {cascade_index}"),
-                        },
-                        ExtractedView {
-                            viewport: UVec4::new(
-                                0,
-                                0,
-                                directional_light_shadow_map.size as u32,
-                                directional_light_shadow_map.size as u32,
-                            ),
-                            transform: GlobalTransform::from(cascade.view_transform),
-                            projection: cascade.projection,
-                            view_projection: Some(cascade.view_projection),
-                            hdr: false,
-                            color_grading: Default::default(),
-                        },
-                        frustum,
-                        BinnedRenderPhase::<Shadow>::default(),
-                        LightEntity::Directional {
-                            light_entity,
-                            cascade_index,
-                        },
-                    ))
-                    .id();
-                view_lights.push(view_light_entity);
+                let mut view_light = commands.spawn((
+                    ShadowView {
+                        depth_attachment: DepthAttachment::new(depth_texture_view, Some(0.0)),
+                        colored_shadow_view,
+                        pass_name: format!(
+                            "shadow pass directional light {light_index} cascade {cascade_index}"
+                        ),
+                    },
+                    ExtractedView {
+                        viewport: UVec4::new(
+                            0,
+                            0,
+                            directional_light_shadow_map.size as u32,
+                            directional_light_shadow_map.size as u32,
+                        ),
+                        transform: GlobalTransform::from(cascade.view_transform),
+                        projection: cascade.projection,
+                        view_projection: Some(cascade.view_projection),
+                        hdr: false,
+                        hdr_format: Default::default(),
+                        color_grading: Default::default(),
+                    },
+                    frustum,
+                    BinnedRenderPhase::<Shadow>::default(),
+                    LightEntity::Directional {
+                        light_entity,
+                        cascade_index,
+                    },
+                ));
+                if directional_light_colored_texture.is_some() {
+                    view_light.insert(BinnedRenderPhase::<TranslucentShadow>::default());
+                }
+                view_lights.push(view_light.id());
             }
         }
 
@@ -1257,6 +1417,63 @@ pub fn prepare_lights(
                 array_layer_count: None,
             });
 
+        if let (
+            Some(colored_shadow_maps),
+            Some((point_light_colored_texture, directional_light_colored_texture)),
+            Some(colored_shadow_map_sampler),
+        ) = (
+            colored_shadow_maps.as_deref_mut(),
+            &colored_shadow_textures,
+            &colored_shadow_map_sampler,
+        ) {
+            colored_shadow_maps.point_light_texture_view = Some(
+                point_light_colored_texture
+                    .texture
+                    .create_view(&TextureViewDescriptor {
+                        label: Some("point_light_colored_shadow_map_array_texture_view"),
+                        #[cfg(all(
+                            not(feature = "ios_simulator"),
+                            any(
+                                not(feature = "webgl"),
+                                not(target_arch = "wasm32"),
+                                feature = "webgpu"
+                            )
+                        ))]
+                        dimension: Some(TextureViewDimension::CubeArray),
+                        #[cfg(any(
+                            feature = "ios_simulator",
+                            all(
+                                feature = "webgl",
+                                target_arch = "wasm32",
+                                not(feature = "webgpu")
+                            )
+                        ))]
+                        dimension: Some(TextureViewDimension::Cube),
+                        ..Default::default()
+                    }),
+            );
+            colored_shadow_maps.directional_light_texture_view =
+                Some(directional_light_colored_texture.texture.create_view(
+                    &TextureViewDescriptor {
+                        label: Some("directional_light_colored_shadow_map_array_texture_view"),
+                        #[cfg(any(
+                            not(feature = "webgl"),
+                            not(target_arch = "wasm32"),
+                            feature = "webgpu"
+                        ))]
+                        dimension: Some(TextureViewDimension::D2Array),
+                        #[cfg(all(
+                            feature = "webgl",
+                            target_arch = "wasm32",
+                            not(feature = "webgpu")
+                        ))]
+                        dimension: Some(TextureViewDimension::D2),
+                        ..Default::default()
+                    },
+                ));
+            colored_shadow_maps.sampler = Some(colored_shadow_map_sampler.0.clone());
+        }
+
         commands.entity(entity).insert((
             ViewShadowBindings {
                 point_light_depth_texture: point_light_depth_texture.texture,
@@ -1274,9 +1491,9 @@ pub fn prepare_lights(
     }
 }
 
-// this must match CLUSTER_COUNT_SIZE in pbr.wgsl
+// this is passed to the shaders by `bevy_pbr::shader_constants`
 // and must be large enough to contain MAX_UNIFORM_BUFFER_POINT_LIGHTS
-const CLUSTER_COUNT_SIZE: u32 = 9;
+pub(crate) const CLUSTER_COUNT_SIZE: u32 = 9;
 
 const CLUSTER_OFFSET_MASK: u32 = (1 << (32 - (CLUSTER_COUNT_SIZE * 2))) - 1;
 const CLUSTER_COUNT_MASK: u32 = (1 << CLUSTER_COUNT_SIZE) - 1;
@@ -1601,6 +1818,7 @@ pub fn prepare_clusters(
 #[allow(clippy::too_many_arguments)]
 pub fn queue_shadows<M: Material>(
     shadow_draw_functions: Res<DrawFunctions<Shadow>>,
+    translucent_shadow_draw_functions: Res<DrawFunctions<TranslucentShadow>>,
     prepass_pipeline: Res<PrepassPipeline<M>>,
     render_meshes: Res<RenderAssets<GpuMesh>>,
     render_mesh_instances: Res<RenderMeshInstances>,
@@ -1610,7 +1828,12 @@ pub fn queue_shadows<M: Material>(
     pipeline_cache: Res<PipelineCache>,
     render_lightmaps: Res<RenderLightmaps>,
     view_lights: Query<(Entity, &ViewLightEntities)>,
-    mut view_light_shadow_phases: Query<(&LightEntity, &mut BinnedRenderPhase<Shadow>)>,
+    mut view_light_shadow_phases: Query<(
+        &LightEntity,
+        &ExtractedView,
+        &mut BinnedRenderPhase<Shadow>,
+        Option<&mut BinnedRenderPhase<TranslucentShadow>>,
+    )>,
     point_light_entities: Query<&CubemapVisibleEntities, With<ExtractedPointLight>>,
     directional_light_entities: Query<&CascadesVisibleEntities, With<ExtractedDirectionalLight>>,
     spot_light_entities: Query<&VisibleEntities, With<ExtractedPointLight>>,
@@ -1619,8 +1842,11 @@ pub fn queue_shadows<M: Material>(
 {
     for (entity, view_lights) in &view_lights {
         let draw_shadow_mesh = shadow_draw_functions.read().id::<DrawPrepass<M>>();
+        let draw_translucent_shadow_mesh = translucent_shadow_draw_functions
+            .read()
+            .id::<DrawPrepass<M>>();
         for view_light_entity in view_lights.lights.iter().copied() {
-            let (light_entity, mut shadow_phase) =
+            let (light_entity, light_view, mut shadow_phase, mut translucent_shadow_phase) =
                 view_light_shadow_phases.get_mut(view_light_entity).unwrap();
             let is_directional_light = matches!(light_entity, LightEntity::Directional { .. });
             let visible_entities = match light_entity {
@@ -1646,8 +1872,9 @@ pub fn queue_shadows<M: Material>(
                     .get(*light_entity)
                     .expect("Failed to get spot light visible entities"),
             };
-            let mut light_key = MeshPipelineKey::DEPTH_PREPASS;
+            let mut light_key = MeshPipelineKey::DEPTH_PREPASS | MeshPipelineKey::SHADOW_PASS;
             light_key.set(MeshPipelineKey::DEPTH_CLAMP_ORTHO, is_directional_light);
+            let material_view_key = M::view_key(&MaterialView::new(light_view, None, None));
 
             // NOTE: Lights with shadow mapping disabled will have no visible entities
             // so no meshes will be queued
@@ -1669,6 +1896,14 @@ pub fn queue_shadows<M: Material>(
                 let Some(material) = render_materials.get(*material_asset_id) else {
                     continue;
                 };
+                // Decals don't cast shadows.
+                if material
+                    .properties
+                    .mesh_pipeline_key_bits
+                    .contains(MeshPipelineKey::DECAL)
+                {
+                    continue;
+                }
                 let Some(mesh) = render_meshes.get(mesh_instance.mesh_asset_id) else {
                     continue;
                 };
@@ -1676,6 +1911,15 @@ pub fn queue_shadows<M: Material>(
                 let mut mesh_key =
                     light_key | MeshPipelineKey::from_bits_retain(mesh.key_bits.bits());
 
+                // In views with colored shadows, translucent casters tint the light instead of
+                // occluding it.
+                let translucent_shadow_phase = translucent_shadow_phase
+                    .as_deref_mut()
+                    .filter(|_| material.properties.colored_shadow);
+                if translucent_shadow_phase.is_some() {
+                    mesh_key |= MeshPipelineKey::TRANSLUCENT_SHADOW;
+                }
+
                 // Even though we don't use the lightmap in the shadow map, the
                 // `SetMeshBindGroup` render command will bind the data for it. So
                 // we need to include the appropriate flag in the mesh pipeline key
@@ -1685,12 +1929,23 @@ pub fn queue_shadows<M: Material>(
                     mesh_key |= MeshPipelineKey::LIGHTMAPPED;
                 }
 
+                mesh_key |=
+                    MeshPipelineKey::from_vertex_color_mode(material.properties.vertex_color_mode);
+
+                // Custom vertex shaders read the vertex buffers.
+                if prepass_pipeline.shadow_vertex_shader().is_none() {
+                    mesh_key |= vertex_pulling_key(&prepass_pipeline.mesh_layouts, mesh);
+                }
+
                 mesh_key |= match material.properties.alpha_mode {
                     AlphaMode::Mask(_)
                     | AlphaMode::Blend
                     | AlphaMode::Premultiplied
                     | AlphaMode::Add
                     | AlphaMode::AlphaToCoverage => MeshPipelineKey::MAY_DISCARD,
+                    AlphaMode::Hashed => {
+                        MeshPipelineKey::MAY_DISCARD | MeshPipelineKey::ALPHA_HASHED
+                    }
                     _ => MeshPipelineKey::NONE,
                 };
                 let pipeline_id = pipelines.specialize(
@@ -1699,6 +1954,16 @@ pub fn queue_shadows<M: Material>(
                     MaterialPipelineKey {
                         mesh_key,
                         bind_group_data: material.key.clone(),
+                        pass_index: 0,
+                        stencil: None,
+                        blend_state: None,
+                        lod: None,
+                        shader_defs: material.properties.shader_defs.clone(),
+                        cull_mode: material.properties.cull_mode,
+                        double_sided_normals: material.properties.double_sided_normals,
+                        // The depth bias of the shadows is set by the light instead.
+                        rasterizer_depth_bias: DepthBiasState::default(),
+                        view_key: material_view_key,
                     },
                     &mesh.layout,
                 );
@@ -1714,16 +1979,31 @@ pub fn queue_shadows<M: Material>(
                 mesh_instance
                     .material_bind_group_id
                     .set(material.get_bind_group_id());
-
-                shadow_phase.add(
-                    ShadowBinKey {
-                        draw_function: draw_shadow_mesh,
-                        pipeline: pipeline_id,
-                        asset_id: mesh_instance.mesh_asset_id,
-                    },
-                    entity,
-                    mesh_instance.should_batch(),
-                );
+                mesh_instance
+                    .material_bindings_index
+                    .set(material.get_bindings_index());
+
+                if let Some(translucent_shadow_phase) = translucent_shadow_phase {
+                    translucent_shadow_phase.add(
+                        ShadowBinKey {
+                            draw_function: draw_translucent_shadow_mesh,
+                            pipeline: pipeline_id,
+                            asset_id: mesh_instance.mesh_asset_id,
+                        },
+                        entity,
+                        mesh_instance.should_batch(),
+                    );
+                } else {
+                    shadow_phase.add(
+                        ShadowBinKey {
+                            draw_function: draw_shadow_mesh,
+                            pipeline: pipeline_id,
+                            asset_id: mesh_instance.mesh_asset_id,
+                        },
+                        entity,
+                        mesh_instance.should_batch(),
+                    );
+                }
             }
         }
     }
@@ -1806,9 +2086,80 @@ impl CachedRenderPipelinePhaseItem for Shadow {
     }
 }
 
+/// A translucent shadow caster, drawn to the colored shadow map of a light after the [`Shadow`]
+/// casters, see [`Material::colored_shadow`].
+pub struct TranslucentShadow {
+    pub key: ShadowBinKey,
+    pub representative_entity: Entity,
+    pub batch_range: Range<u32>,
+    pub extra_index: PhaseItemExtraIndex,
+}
+
+impl PhaseItem for TranslucentShadow {
+    #[inline]
+    fn entity(&self) -> Entity {
+        self.representative_entity
+    }
+
+    #[inline]
+    fn draw_function(&self) -> DrawFunctionId {
+        self.key.draw_function
+    }
+
+    #[inline]
+    fn batch_range(&self) -> &Range<u32> {
+        &self.batch_range
+    }
+
+    #[inline]
+    fn batch_range_mut(&mut self) -> &mut Range<u32> {
+        &mut self.batch_range
+    }
+
+    #[inline]
+    fn extra_index(&self) -> PhaseItemExtraIndex {
+        self.extra_index
+    }
+
+    #[inline]
+    fn batch_range_and_extra_index_mut(&mut self) -> (&mut Range<u32>, &mut PhaseItemExtraIndex) {
+        (&mut self.batch_range, &mut self.extra_index)
+    }
+}
+
+impl BinnedPhaseItem for TranslucentShadow {
+    type BinKey = ShadowBinKey;
+
+    #[inline]
+    fn new(
+        key: Self::BinKey,
+        representative_entity: Entity,
+        batch_range: Range<u32>,
+        extra_index: PhaseItemExtraIndex,
+    ) -> Self {
+        TranslucentShadow {
+            key,
+            representative_entity,
+            batch_range,
+            extra_index,
+        }
+    }
+}
+
+impl CachedRenderPipelinePhaseItem for TranslucentShadow {
+    #[inline]
+    fn cached_pipeline(&self) -> CachedRenderPipelineId {
+        self.key.pipeline
+    }
+}
+
 pub struct ShadowPassNode {
     main_view_query: QueryState<Read<ViewLightEntities>>,
-    view_light_query: QueryState<(Read<ShadowView>, Read<BinnedRenderPhase<Shadow>>)>,
+    view_light_query: QueryState<(
+        Read<ShadowView>,
+        Read<BinnedRenderPhase<Shadow>>,
+        Option<Read<BinnedRenderPhase<TranslucentShadow>>>,
+    )>,
 }
 
 impl ShadowPassNode {
@@ -1838,7 +2189,7 @@ impl Node for ShadowPassNode {
         let view_entity = graph.view_entity();
         if let Ok(view_lights) = self.main_view_query.get_manual(world, view_entity) {
             for view_light_entity in view_lights.lights.iter().copied() {
-                let (view_light, shadow_phase) = self
+                let (view_light, shadow_phase, translucent_shadow_phase) = self
                     .view_light_query
                     .get_manual(world, view_light_entity)
                     .unwrap();
@@ -1871,6 +2222,42 @@ impl Node for ShadowPassNode {
 
                     pass_span.end(&mut render_pass);
                     drop(render_pass);
+
+                    // The translucent casters are drawn to the colored shadow map, tested against
+                    // the depth of the opaque ones. It's cleared even without any, so that the
+                    // colored shadows of the previous frame don't remain.
+                    if let (Some(colored_shadow_view), Some(translucent_shadow_phase)) =
+                        (&view_light.colored_shadow_view, translucent_shadow_phase)
+                    {
+                        let pass_name = format!("{} translucent", view_light.pass_name);
+                        let render_pass =
+                            command_encoder.begin_render_pass(&RenderPassDescriptor {
+                                label: Some(&pass_name),
+                                color_attachments: &[Some(RenderPassColorAttachment {
+                                    view: colored_shadow_view,
+                                    resolve_target: None,
+                                    ops: Operations {
+                                        load: LoadOp::Clear(
+                                            LinearRgba::new(1.0, 1.0, 1.0, 0.0).into(),
+                                        ),
+                                        store: StoreOp::Store,
+                                    },
+                                })],
+                                depth_stencil_attachment: Some(
+                                    view_light.depth_attachment.get_attachment(StoreOp::Store),
+                                ),
+                                timestamp_writes: None,
+                                occlusion_query_set: None,
+                            });
+
+                        let mut render_pass = TrackedRenderPass::new(&render_device, render_pass);
+                        let pass_span = diagnostics.pass_span(&mut render_pass, pass_name);
+
+                        translucent_shadow_phase.render(&mut render_pass, world, view_light_entity);
+
+                        pass_span.end(&mut render_pass);
+                    }
+
                     command_encoder.finish()
                 });
             }
@@ -2,7 +2,10 @@ use std::mem;
 
 use bevy_asset::{load_internal_asset, AssetId};
 use bevy_core_pipeline::{
-    core_3d::{AlphaMask3d, Opaque3d, Transmissive3d, Transparent3d, CORE_3D_DEPTH_FORMAT},
+    core_3d::{
+        AlphaMask3d, Decal3d, Opaque3d, Transmissive3d, Transparent3d, CORE_3D_DEPTH_FORMAT,
+        CORE_3D_DEPTH_STENCIL_FORMAT,
+    },
     deferred::{AlphaMask3dDeferred, Opaque3dDeferred},
 };
 use bevy_derive::{Deref, DerefMut};
@@ -12,7 +15,7 @@ use bevy_ecs::{
     query::ROQueryItem,
     system::{lifetimeless::*, SystemParamItem, SystemState},
 };
-use bevy_math::{Affine3, Rect, UVec2, Vec3, Vec4};
+use bevy_math::{Affine2, Affine3, Affine3A, Rect, UVec2, Vec2, Vec3, Vec4};
 use bevy_render::{
     batching::{
         gpu_preprocessing::{
@@ -23,22 +26,22 @@ use bevy_render::{
     camera::Camera,
     mesh::*,
     primitives::Aabb,
-    render_asset::RenderAssets,
+    render_asset::{RenderAssets, VisibleRenderAssets},
     render_phase::{
         BinnedRenderPhasePlugin, PhaseItem, RenderCommand, RenderCommandResult,
         SortedRenderPhasePlugin, TrackedRenderPass,
     },
     render_resource::*,
     renderer::{RenderDevice, RenderQueue},
-    texture::{BevyDefault, DefaultImageSampler, ImageSampler, TextureFormatPixelInfo},
+    texture::{DefaultImageSampler, ImageSampler, TextureFormatPixelInfo},
     view::{
-        prepare_view_targets, GpuCulling, RenderVisibilityRanges, ViewTarget, ViewUniformOffset,
-        ViewVisibility, VisibilityRange, VISIBILITY_RANGES_STORAGE_BUFFER_COUNT,
+        prepare_view_targets, GpuCulling, HdrFormat, RenderVisibilityRanges, ViewTarget,
+        ViewUniformOffset, ViewVisibility, VisibilityRange, VISIBILITY_RANGES_STORAGE_BUFFER_COUNT,
     },
     Extract,
 };
 use bevy_transform::components::GlobalTransform;
-use bevy_utils::{tracing::error, tracing::warn, Entry, HashMap, Parallel};
+use bevy_utils::{tracing::error, tracing::info, tracing::warn, Entry, HashMap, Parallel};
 
 #[cfg(debug_assertions)]
 use bevy_utils::warn_once;
@@ -66,6 +69,11 @@ pub struct MeshRenderPlugin {
     /// This requires compute shader support and so will be forcibly disabled if
     /// the platform doesn't support those.
     pub use_gpu_instance_buffer_builder: bool,
+    /// Whether meshes are drawn with vertex pulling, see [`UseVertexPulling`].
+    ///
+    /// This requires storage buffer support and so will be forcibly disabled if
+    /// the platform doesn't support those.
+    pub use_vertex_pulling: bool,
 }
 
 pub const FORWARD_IO_HANDLE: Handle<Shader> = Handle::weak_from_u128(2645551199423808407);
@@ -114,7 +122,15 @@ impl Plugin for MeshRenderPlugin {
             "mesh_view_bindings.wgsl",
             Shader::from_wgsl
         );
-        load_internal_asset!(app, MESH_TYPES_HANDLE, "mesh_types.wgsl", Shader::from_wgsl);
+        // The layout of the `Mesh` struct depends on the `compressed_mesh_transforms` feature. The
+        // shader def of a module is also set in the shaders importing it.
+        load_internal_asset!(
+            app,
+            MESH_TYPES_HANDLE,
+            "mesh_types.wgsl",
+            Shader::from_wgsl_with_defs,
+            mesh_types_shader_defs()
+        );
         load_internal_asset!(
             app,
             MESH_FUNCTIONS_HANDLE,
@@ -124,6 +140,12 @@ impl Plugin for MeshRenderPlugin {
         load_internal_asset!(app, MESH_SHADER_HANDLE, "mesh.wgsl", Shader::from_wgsl);
         load_internal_asset!(app, SKINNING_HANDLE, "skinning.wgsl", Shader::from_wgsl);
         load_internal_asset!(app, MORPH_HANDLE, "morph.wgsl", Shader::from_wgsl);
+        load_internal_asset!(
+            app,
+            VERTEX_PULLING_HANDLE,
+            "vertex_pulling.wgsl",
+            Shader::from_wgsl
+        );
 
         app.add_systems(
             PostUpdate,
@@ -133,20 +155,55 @@ impl Plugin for MeshRenderPlugin {
             BinnedRenderPhasePlugin::<Opaque3d, MeshPipeline>::default(),
             BinnedRenderPhasePlugin::<AlphaMask3d, MeshPipeline>::default(),
             BinnedRenderPhasePlugin::<Shadow, MeshPipeline>::default(),
+            BinnedRenderPhasePlugin::<TranslucentShadow, MeshPipeline>::default(),
             BinnedRenderPhasePlugin::<Opaque3dDeferred, MeshPipeline>::default(),
             BinnedRenderPhasePlugin::<AlphaMask3dDeferred, MeshPipeline>::default(),
+            SortedRenderPhasePlugin::<Decal3d, MeshPipeline>::default(),
             SortedRenderPhasePlugin::<Transmissive3d, MeshPipeline>::default(),
             SortedRenderPhasePlugin::<Transparent3d, MeshPipeline>::default(),
-        ));
+        ))
+        .init_resource::<MeshPipelineKeyAllocator>();
+
+        #[cfg(feature = "frame_capture")]
+        {
+            use bevy_render::{
+                frame_capture::{
+                    FrameCapturePhasePlugin, FrameCaptureSystems, PendingFrameCapture,
+                },
+                render_phase::{BinnedRenderPhase, SortedRenderPhase},
+            };
+
+            app.add_plugins((
+                FrameCapturePhasePlugin::<BinnedRenderPhase<Opaque3d>>::default(),
+                FrameCapturePhasePlugin::<BinnedRenderPhase<AlphaMask3d>>::default(),
+                FrameCapturePhasePlugin::<BinnedRenderPhase<Shadow>>::default(),
+                FrameCapturePhasePlugin::<BinnedRenderPhase<TranslucentShadow>>::default(),
+                FrameCapturePhasePlugin::<BinnedRenderPhase<Opaque3dDeferred>>::default(),
+                FrameCapturePhasePlugin::<BinnedRenderPhase<AlphaMask3dDeferred>>::default(),
+                FrameCapturePhasePlugin::<SortedRenderPhase<Decal3d>>::default(),
+                FrameCapturePhasePlugin::<SortedRenderPhase<Transmissive3d>>::default(),
+                FrameCapturePhasePlugin::<SortedRenderPhase<Transparent3d>>::default(),
+            ));
+            if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+                render_app.add_systems(
+                    Render,
+                    capture_mesh_instances
+                        .in_set(FrameCaptureSystems)
+                        .run_if(resource_exists::<PendingFrameCapture>),
+                );
+            }
+        }
 
         if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
             render_app
                 .init_resource::<MeshBindGroups>()
+                .init_resource::<MeshViewExtraBindings>()
                 .init_resource::<SkinUniform>()
                 .init_resource::<SkinIndices>()
                 .init_resource::<MorphUniform>()
                 .init_resource::<MorphIndices>()
                 .init_resource::<MeshCullingDataBuffer>()
+                .init_resource::<VertexPullingBindGroups>()
                 .add_systems(
                     ExtractSchedule,
                     (
@@ -162,6 +219,7 @@ impl Plugin for MeshRenderPlugin {
                         prepare_skins.in_set(RenderSet::PrepareResources),
                         prepare_morphs.in_set(RenderSet::PrepareResources),
                         prepare_mesh_bind_group.in_set(RenderSet::PrepareBindGroups),
+                        prepare_vertex_pulling_bind_groups.in_set(RenderSet::PrepareBindGroups),
                         prepare_mesh_view_bind_groups.in_set(RenderSet::PrepareBindGroups),
                         no_gpu_preprocessing::clear_batched_cpu_instance_buffers::<MeshPipeline>
                             .in_set(RenderSet::Cleanup)
@@ -196,6 +254,8 @@ impl Plugin for MeshRenderPlugin {
                     .add_systems(
                         Render,
                         (
+                            write_mesh_input_material_bindings_indices
+                                .in_set(RenderSet::PrepareResources),
                             gpu_preprocessing::write_batched_instance_buffers::<MeshPipeline>
                                 .in_set(RenderSet::PrepareResourcesFlush),
                             gpu_preprocessing::delete_old_work_item_buffers::<MeshPipeline>
@@ -220,6 +280,11 @@ impl Plugin for MeshRenderPlugin {
                     );
             };
 
+            render_app.add_systems(
+                ExtractSchedule,
+                extract_visible_meshes.after(ExtractMeshesSet),
+            );
+
             let indirect_parameters_buffer = IndirectParametersBuffer::new();
 
             let render_device = render_app.world().resource::<RenderDevice>();
@@ -232,8 +297,12 @@ impl Plugin for MeshRenderPlugin {
                 ));
             }
 
+            let use_vertex_pulling = self.use_vertex_pulling
+                && render_device.limits().max_storage_buffers_per_shader_stage > 0;
+
             render_app
                 .insert_resource(indirect_parameters_buffer)
+                .insert_resource(UseVertexPulling(use_vertex_pulling))
                 .init_resource::<MeshPipeline>();
         }
 
@@ -256,16 +325,74 @@ pub struct MeshTransforms {
     pub flags: u32,
 }
 
+/// The shader defs of the `bevy_pbr::mesh_types` shader module.
+fn mesh_types_shader_defs() -> Vec<ShaderDefVal> {
+    if cfg!(feature = "compressed_mesh_transforms") {
+        vec!["COMPRESSED_MESH_TRANSFORMS".into()]
+    } else {
+        Vec::new()
+    }
+}
+
+/// An affine transform compressed into its translation, rotation and scale, which is half the
+/// size of the full transform and its inverse transpose.
+///
+/// Shears can't be represented, and the rotation is stored as a quaternion of four 16-bit signed
+/// normalized values. This is the transform of the [`MeshUniform`]s and [`MeshInputUniform`]s with
+/// the `compressed_mesh_transforms` feature.
+///
+/// This is a cargo feature rather than a pipeline key because it changes the layout of the
+/// [`MeshUniform`] and [`MeshInputUniform`] buffers, which are shared by every mesh pipeline.
+#[derive(ShaderType, Pod, Zeroable, Clone, Copy, Default, Debug, PartialEq)]
+#[repr(C)]
+pub struct CompressedTransform {
+    pub translation: Vec3,
+    /// The x and y components of the rotation quaternion, packed as two 16-bit signed normalized
+    /// values.
+    pub rotation_xy: u32,
+    pub scale: Vec3,
+    /// The z and w components of the rotation quaternion, packed as two 16-bit signed normalized
+    /// values.
+    pub rotation_zw: u32,
+}
+
+impl CompressedTransform {
+    /// Compresses the transform, dropping its shear if any.
+    pub fn new(transform: &Affine3) -> Self {
+        let (scale, rotation, translation) =
+            Affine3A::from(transform).to_scale_rotation_translation();
+        // Matches `pack2x16snorm` in WGSL.
+        let pack_snorm16x2 = |x: f32, y: f32| {
+            let snorm16 = |value: f32| (value.clamp(-1.0, 1.0) * 32767.0).round() as i16 as u16;
+            snorm16(x) as u32 | (snorm16(y) as u32) << 16
+        };
+        Self {
+            translation,
+            rotation_xy: pack_snorm16x2(rotation.x, rotation.y),
+            scale,
+            rotation_zw: pack_snorm16x2(rotation.z, rotation.w),
+        }
+    }
+}
+
 #[derive(ShaderType, Clone)]
 pub struct MeshUniform {
+    #[cfg(feature = "compressed_mesh_transforms")]
+    pub transform: CompressedTransform,
+    #[cfg(feature = "compressed_mesh_transforms")]
+    pub previous_transform: CompressedTransform,
     // Affine 4x3 matrices transposed to 3x4
+    #[cfg(not(feature = "compressed_mesh_transforms"))]
     pub transform: [Vec4; 3],
+    #[cfg(not(feature = "compressed_mesh_transforms"))]
     pub previous_transform: [Vec4; 3],
     // 3x3 matrix packed in mat2x4 and f32 as:
     //   [0].xyz, [1].x,
     //   [1].yz, [2].xy
     //   [2].z
+    #[cfg(not(feature = "compressed_mesh_transforms"))]
     pub inverse_transpose_model_a: [Vec4; 2],
+    #[cfg(not(feature = "compressed_mesh_transforms"))]
     pub inverse_transpose_model_b: f32,
     pub flags: u32,
     // Four 16-bit unsigned normalized UV values packed into a `UVec2`:
@@ -278,6 +405,21 @@ pub struct MeshUniform {
     //
     // (MSB: most significant bit; LSB: least significant bit.)
     pub lightmap_uv_rect: UVec2,
+    // The `UvTransform` of the entity: its 2x2 matrix packed column by column,
+    // and its translation.
+    pub uv_transform_a: Vec4,
+    pub uv_transform_b: Vec2,
+    // The index of the main world entity this mesh belongs to. Used by GPU
+    // picking to map rendered pixels back to entities.
+    pub entity_index: u32,
+    // The values of the `MaterialOverrides` of the entity, see
+    // `PackedMaterialOverrides`. Which ones are set is stored in `flags`.
+    pub material_override_base_color: UVec2,
+    pub material_override_emissive: u32,
+    pub material_override_perceptual_roughness: f32,
+    // The packed `MaterialBindingsIndex` of the material of the entity, see
+    // `MaterialBindingsIndex::packed`.
+    pub material_bindings_index: u32,
 }
 
 /// Information that has to be transferred from CPU to GPU in order to produce
@@ -288,7 +430,16 @@ pub struct MeshUniform {
 #[repr(C)]
 pub struct MeshInputUniform {
     /// Affine 4x3 matrix transposed to 3x4.
+    #[cfg(not(feature = "compressed_mesh_transforms"))]
     pub transform: [Vec4; 3],
+    /// The compressed transform.
+    #[cfg(feature = "compressed_mesh_transforms")]
+    pub transform: CompressedTransform,
+    /// The 2x2 matrix of the [`UvTransform`](crate::UvTransform) of the mesh,
+    /// packed column by column.
+    pub uv_transform_a: Vec4,
+    /// The translation of the [`UvTransform`](crate::UvTransform) of the mesh.
+    pub uv_transform_b: Vec2,
     /// Four 16-bit unsigned normalized UV values packed into a `UVec2`:
     ///
     /// ```text
@@ -301,6 +452,8 @@ pub struct MeshInputUniform {
     /// (MSB: most significant bit; LSB: least significant bit.)
     /// ```
     pub lightmap_uv_rect: UVec2,
+    /// [`PackedMaterialOverrides::base_color`].
+    pub material_override_base_color: UVec2,
     /// Various [`MeshFlags`].
     pub flags: u32,
     /// The index of this mesh's [`MeshInputUniform`] in the previous frame's
@@ -308,6 +461,19 @@ pub struct MeshInputUniform {
     ///
     /// This is used for TAA. If not present, this will be `u32::MAX`.
     pub previous_input_index: u32,
+    /// The index of the main world entity this mesh belongs to.
+    ///
+    /// This is copied into the [`MeshUniform`] for GPU picking.
+    pub entity_index: u32,
+    /// [`PackedMaterialOverrides::emissive`].
+    pub material_override_emissive: u32,
+    /// [`PackedMaterialOverrides::perceptual_roughness`].
+    pub material_override_perceptual_roughness: f32,
+    /// The [`MaterialBindingsIndex::packed`] of the material of this mesh.
+    ///
+    /// This is written after the materials are queued, see
+    /// [`write_mesh_input_material_bindings_indices`].
+    pub material_bindings_index: u32,
 }
 
 /// Information about each mesh instance needed to cull it on GPU.
@@ -334,18 +500,63 @@ pub struct MeshCullingData {
 pub struct MeshCullingDataBuffer(RawBufferVec<MeshCullingData>);
 
 impl MeshUniform {
-    pub fn new(mesh_transforms: &MeshTransforms, maybe_lightmap_uv_rect: Option<Rect>) -> Self {
+    pub fn new(
+        mesh_transforms: &MeshTransforms,
+        maybe_lightmap_uv_rect: Option<Rect>,
+        entity: Entity,
+    ) -> Self {
+        #[cfg(not(feature = "compressed_mesh_transforms"))]
         let (inverse_transpose_model_a, inverse_transpose_model_b) =
             mesh_transforms.transform.inverse_transpose_3x3();
         Self {
+            #[cfg(not(feature = "compressed_mesh_transforms"))]
             transform: mesh_transforms.transform.to_transpose(),
+            #[cfg(not(feature = "compressed_mesh_transforms"))]
             previous_transform: mesh_transforms.previous_transform.to_transpose(),
-            lightmap_uv_rect: lightmap::pack_lightmap_uv_rect(maybe_lightmap_uv_rect),
+            #[cfg(not(feature = "compressed_mesh_transforms"))]
             inverse_transpose_model_a,
+            #[cfg(not(feature = "compressed_mesh_transforms"))]
             inverse_transpose_model_b,
+            #[cfg(feature = "compressed_mesh_transforms")]
+            transform: CompressedTransform::new(&mesh_transforms.transform),
+            #[cfg(feature = "compressed_mesh_transforms")]
+            previous_transform: CompressedTransform::new(&mesh_transforms.previous_transform),
+            lightmap_uv_rect: lightmap::pack_lightmap_uv_rect(maybe_lightmap_uv_rect),
+            uv_transform_a: Vec4::from_array(Affine2::IDENTITY.matrix2.to_cols_array()),
+            uv_transform_b: Vec2::ZERO,
             flags: mesh_transforms.flags,
+            entity_index: entity.index(),
+            material_override_base_color: UVec2::ZERO,
+            material_override_emissive: 0,
+            material_override_perceptual_roughness: 0.0,
+            material_bindings_index: 0,
         }
     }
+
+    /// Sets the values of the [`MaterialOverrides`](crate::MaterialOverrides)
+    /// of the mesh.
+    ///
+    /// The [`MeshFlags`] telling which values are set must already be in the
+    /// flags of the [`MeshTransforms`] the uniform was created from.
+    pub fn with_material_overrides(mut self, overrides: &PackedMaterialOverrides) -> Self {
+        self.material_override_base_color = overrides.base_color;
+        self.material_override_emissive = overrides.emissive;
+        self.material_override_perceptual_roughness = overrides.perceptual_roughness;
+        self
+    }
+
+    /// Sets the [`UvTransform`](crate::UvTransform) of the mesh.
+    pub fn with_uv_transform(mut self, uv_transform: &Affine2) -> Self {
+        self.uv_transform_a = Vec4::from_array(uv_transform.matrix2.to_cols_array());
+        self.uv_transform_b = uv_transform.translation;
+        self
+    }
+
+    /// Sets the [`MaterialBindingsIndex`] of the material of the mesh.
+    pub fn with_material_bindings_index(mut self, index: MaterialBindingsIndex) -> Self {
+        self.material_bindings_index = index.packed();
+        self
+    }
 }
 
 // NOTE: These must match the bit flags in bevy_pbr/src/render/mesh_types.wgsl!
@@ -354,12 +565,19 @@ bitflags::bitflags! {
     ///
     /// Flags grow from the top bit down; other values grow from the bottom bit
     /// up.
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
     #[repr(transparent)]
     pub struct MeshFlags: u32 {
         /// Bitmask for the 16-bit index into the LOD array.
         ///
         /// This will be `u16::MAX` if this mesh has no LOD.
         const LOD_INDEX_MASK              = (1 << 16) - 1;
+        /// The mesh has a [`MaterialOverrides::perceptual_roughness`](crate::MaterialOverrides::perceptual_roughness).
+        const PERCEPTUAL_ROUGHNESS_OVERRIDE = 1 << 26;
+        /// The mesh has a [`MaterialOverrides::emissive`](crate::MaterialOverrides::emissive).
+        const EMISSIVE_OVERRIDE           = 1 << 27;
+        /// The mesh has a [`MaterialOverrides::base_color`](crate::MaterialOverrides::base_color).
+        const BASE_COLOR_OVERRIDE         = 1 << 28;
         const SHADOW_RECEIVER             = 1 << 29;
         const TRANSMITTED_SHADOW_RECEIVER = 1 << 30;
         // Indicates the sign of the determinant of the 3x3 model matrix. If the sign is positive,
@@ -456,8 +674,18 @@ pub struct RenderMeshInstanceShared {
     ///
     /// This is filled in during [`crate::material::queue_material_meshes`].
     pub material_bind_group_id: AtomicMaterialBindGroupId,
+    /// A slot for the [`MaterialBindingsIndex`] of the material.
+    ///
+    /// This is filled in during [`crate::material::queue_material_meshes`].
+    pub material_bindings_index: AtomicMaterialBindingsIndex,
     /// Various flags.
     pub flags: RenderMeshInstanceFlags,
+    /// The [`MaterialOverrides`](crate::MaterialOverrides) of the entity, to
+    /// be written into its [`MeshUniform`].
+    pub material_overrides: PackedMaterialOverrides,
+    /// The [`UvTransform`](crate::UvTransform) of the entity, to be written
+    /// into its [`MeshUniform`].
+    pub uv_transform: Affine2,
 }
 
 /// Information that is gathered during the parallel portion of mesh extraction
@@ -517,6 +745,8 @@ impl RenderMeshInstanceShared {
         handle: &Handle<Mesh>,
         not_shadow_caster: bool,
         no_automatic_batching: bool,
+        material_overrides: Option<&MaterialOverrides>,
+        uv_transform: Option<&UvTransform>,
     ) -> Self {
         let mut mesh_instance_flags = RenderMeshInstanceFlags::empty();
         mesh_instance_flags.set(RenderMeshInstanceFlags::SHADOW_CASTER, !not_shadow_caster);
@@ -534,6 +764,9 @@ impl RenderMeshInstanceShared {
 
             flags: mesh_instance_flags,
             material_bind_group_id: AtomicMaterialBindGroupId::default(),
+            material_bindings_index: AtomicMaterialBindingsIndex::default(),
+            material_overrides: material_overrides.map(Into::into).unwrap_or_default(),
+            uv_transform: uv_transform.map_or(Affine2::IDENTITY, UvTransform::to_affine2),
         }
     }
 
@@ -632,6 +865,65 @@ impl RenderMeshInstancesGpu {
     }
 }
 
+/// Marks the meshes of the visible entities extracted this frame as [`VisibleRenderAssets`], so
+/// that they're prepared before the others.
+pub fn extract_visible_meshes(
+    render_mesh_instances: Res<RenderMeshInstances>,
+    mut visible_meshes: ResMut<VisibleRenderAssets<GpuMesh>>,
+) {
+    match *render_mesh_instances {
+        RenderMeshInstances::CpuBuilding(ref instances) => visible_meshes.extend(
+            instances
+                .values()
+                .map(|render_mesh_instance| render_mesh_instance.mesh_asset_id),
+        ),
+        RenderMeshInstances::GpuBuilding(ref instances) => visible_meshes.extend(
+            instances
+                .values()
+                .map(|render_mesh_instance| render_mesh_instance.mesh_asset_id),
+        ),
+    }
+}
+
+/// Adds the [`RenderMeshInstances`] to the
+/// [`PendingFrameCapture`](bevy_render::frame_capture::PendingFrameCapture).
+#[cfg(feature = "frame_capture")]
+pub fn capture_mesh_instances(
+    render_mesh_instances: Res<RenderMeshInstances>,
+    mut pending_frame_capture: ResMut<bevy_render::frame_capture::PendingFrameCapture>,
+) {
+    use bevy_render::frame_capture::CapturedMeshInstance;
+
+    let mesh_instances = &mut pending_frame_capture.frame.mesh_instances;
+    match *render_mesh_instances {
+        RenderMeshInstances::CpuBuilding(ref instances) => {
+            mesh_instances.extend(instances.iter().map(|(entity, render_mesh_instance)| {
+                let transform = &render_mesh_instance.transforms.transform;
+                CapturedMeshInstance {
+                    entity: entity.to_bits(),
+                    mesh: format!("{:?}", render_mesh_instance.mesh_asset_id),
+                    translation: transform.translation.to_array(),
+                    transform: Some(bytemuck::cast(transform.to_transpose())),
+                    input_uniform_index: None,
+                    flags: render_mesh_instance.flags.bits().into(),
+                }
+            }));
+        }
+        RenderMeshInstances::GpuBuilding(ref instances) => {
+            mesh_instances.extend(instances.iter().map(|(entity, render_mesh_instance)| {
+                CapturedMeshInstance {
+                    entity: entity.to_bits(),
+                    mesh: format!("{:?}", render_mesh_instance.mesh_asset_id),
+                    translation: render_mesh_instance.translation.to_array(),
+                    transform: None,
+                    input_uniform_index: Some(render_mesh_instance.current_uniform_index.into()),
+                    flags: render_mesh_instance.flags.bits().into(),
+                }
+            }));
+        }
+    }
+}
+
 impl RenderMeshInstanceGpuQueue {
     /// Clears out a [`RenderMeshInstanceGpuQueue`], creating or recreating it
     /// as necessary.
@@ -689,13 +981,28 @@ impl RenderMeshInstanceGpuBuilder {
     ) -> usize {
         // Push the mesh input uniform.
         let current_uniform_index = current_input_buffer.push(MeshInputUniform {
+            #[cfg(not(feature = "compressed_mesh_transforms"))]
             transform: self.transform.to_transpose(),
+            #[cfg(feature = "compressed_mesh_transforms")]
+            transform: CompressedTransform::new(&self.transform),
+            uv_transform_a: Vec4::from_array(self.shared.uv_transform.matrix2.to_cols_array()),
+            uv_transform_b: self.shared.uv_transform.translation,
             lightmap_uv_rect: self.lightmap_uv_rect,
             flags: self.mesh_flags.bits(),
             previous_input_index: match self.previous_input_index {
                 Some(previous_input_index) => previous_input_index.into(),
                 None => u32::MAX,
             },
+            entity_index: entity.index(),
+            material_override_base_color: self.shared.material_overrides.base_color,
+            material_override_emissive: self.shared.material_overrides.emissive,
+            material_override_perceptual_roughness: self
+                .shared
+                .material_overrides
+                .perceptual_roughness,
+            // Written once the material is known, in
+            // `write_mesh_input_material_bindings_indices`.
+            material_bindings_index: 0,
         });
 
         // Record the [`RenderMeshInstance`].
@@ -783,6 +1090,8 @@ pub fn extract_meshes_for_cpu_building(
             Has<NotShadowCaster>,
             Has<NoAutomaticBatching>,
             Has<VisibilityRange>,
+            Option<&MaterialOverrides>,
+            Option<&UvTransform>,
         )>,
     >,
 ) {
@@ -800,6 +1109,8 @@ pub fn extract_meshes_for_cpu_building(
             not_shadow_caster,
             no_automatic_batching,
             visibility_range,
+            material_overrides,
+            uv_transform,
         )| {
             if !view_visibility.get() {
                 return;
@@ -810,20 +1121,22 @@ pub fn extract_meshes_for_cpu_building(
                 lod_index = render_visibility_ranges.lod_index_for_entity(entity);
             }
 
-            let mesh_flags = MeshFlags::from_components(
-                transform,
-                lod_index,
-                not_shadow_receiver,
-                transmitted_receiver,
-            );
-
             let shared = RenderMeshInstanceShared::from_components(
                 previous_transform,
                 handle,
                 not_shadow_caster,
                 no_automatic_batching,
+                material_overrides,
+                uv_transform,
             );
 
+            let mesh_flags = MeshFlags::from_components(
+                transform,
+                lod_index,
+                not_shadow_receiver,
+                transmitted_receiver,
+            ) | shared.material_overrides.flags;
+
             let transform = transform.affine();
             queue.push((
                 entity,
@@ -884,6 +1197,8 @@ pub fn extract_meshes_for_gpu_building(
             Has<NotShadowCaster>,
             Has<NoAutomaticBatching>,
             Has<VisibilityRange>,
+            Option<&MaterialOverrides>,
+            Option<&UvTransform>,
         )>,
     >,
     cameras_query: Extract<Query<(), (With<Camera>, With<GpuCulling>)>>,
@@ -918,6 +1233,8 @@ pub fn extract_meshes_for_gpu_building(
             not_shadow_caster,
             no_automatic_batching,
             visibility_range,
+            material_overrides,
+            uv_transform,
         )| {
             if !view_visibility.get() {
                 return;
@@ -928,20 +1245,22 @@ pub fn extract_meshes_for_gpu_building(
                 lod_index = render_visibility_ranges.lod_index_for_entity(entity);
             }
 
-            let mesh_flags = MeshFlags::from_components(
-                transform,
-                lod_index,
-                not_shadow_receiver,
-                transmitted_receiver,
-            );
-
             let shared = RenderMeshInstanceShared::from_components(
                 previous_transform,
                 handle,
                 not_shadow_caster,
                 no_automatic_batching,
+                material_overrides,
+                uv_transform,
             );
 
+            let mesh_flags = MeshFlags::from_components(
+                transform,
+                lod_index,
+                not_shadow_receiver,
+                transmitted_receiver,
+            ) | shared.material_overrides.flags;
+
             let lightmap_uv_rect =
                 lightmap::pack_lightmap_uv_rect(lightmap.map(|lightmap| lightmap.uv_rect));
 
@@ -1032,6 +1351,29 @@ fn collect_meshes_for_gpu_building(
     }
 }
 
+/// Writes the [`MaterialBindingsIndex`] of each mesh instance, which is only
+/// known once the materials are queued, into its [`MeshInputUniform`] when
+/// GPU mesh uniform building is in use.
+pub fn write_mesh_input_material_bindings_indices(
+    render_mesh_instances: Res<RenderMeshInstances>,
+    mut batched_instance_buffers: ResMut<
+        gpu_preprocessing::BatchedInstanceBuffers<MeshUniform, MeshInputUniform>,
+    >,
+) {
+    let RenderMeshInstances::GpuBuilding(ref render_mesh_instances) = *render_mesh_instances else {
+        return;
+    };
+
+    let input_uniforms = batched_instance_buffers.current_input_buffer.values_mut();
+    for render_mesh_instance in render_mesh_instances.values() {
+        let index = render_mesh_instance.current_uniform_index.get() as usize;
+        if let Some(input_uniform) = input_uniforms.get_mut(index) {
+            input_uniform.material_bindings_index =
+                render_mesh_instance.material_bindings_index.get().packed();
+        }
+    }
+}
+
 #[derive(Resource, Clone)]
 pub struct MeshPipeline {
     view_layouts: [MeshPipelineViewLayout; MeshPipelineViewLayoutKey::COUNT],
@@ -1057,6 +1399,12 @@ pub struct MeshPipeline {
     ///
     /// This affects whether reflection probes can be used.
     pub binding_arrays_are_usable: bool,
+
+    /// The maximum number of bind groups of a pipeline on the current render device.
+    pub max_bind_groups: u32,
+
+    /// The shader defs of the [`MeshViewExtraBindings`], by view layout.
+    view_layout_shader_defs: Vec<Vec<ShaderDefVal>>,
 }
 
 impl FromWorld for MeshPipeline {
@@ -1065,8 +1413,11 @@ impl FromWorld for MeshPipeline {
             Res<RenderDevice>,
             Res<DefaultImageSampler>,
             Res<RenderQueue>,
+            Res<MeshViewExtraBindings>,
+            Res<UseVertexPulling>,
         )> = SystemState::new(world);
-        let (render_device, default_sampler, render_queue) = system_state.get_mut(world);
+        let (render_device, default_sampler, render_queue, extra_bindings, use_vertex_pulling) =
+            system_state.get_mut(world);
         let clustered_forward_buffer_binding_type = render_device
             .get_supported_read_only_binding_type(CLUSTERED_FORWARD_STORAGE_BUFFER_COUNT);
         let visibility_ranges_buffer_binding_type = render_device
@@ -1076,8 +1427,42 @@ impl FromWorld for MeshPipeline {
             &render_device,
             clustered_forward_buffer_binding_type,
             visibility_ranges_buffer_binding_type,
+            &extra_bindings,
         );
 
+        let fitted_layout_count = view_layouts
+            .iter()
+            .enumerate()
+            .filter(|(i, layout)| layout.layout_key.bits() as usize != *i)
+            .count();
+        if fitted_layout_count > 0 {
+            info!(
+                "{fitted_layout_count} of the {} mesh view layouts have more sampled textures than \
+                the {} supported by this device, some of their features will be disabled when \
+                they're used",
+                MeshPipelineViewLayoutKey::COUNT,
+                render_device.limits().max_sampled_textures_per_shader_stage
+            );
+        }
+
+        #[cfg(debug_assertions)]
+        {
+            let extra_texture_count = extra_bindings.texture_count(&render_device);
+            let texture_count = view_layouts
+                [MeshPipelineViewLayoutKey::EXTENSION_RESERVED_BITS.bits() as usize]
+                .texture_count;
+            if extra_texture_count > 0
+                && texture_count > MESH_PIPELINE_VIEW_LAYOUT_SAFE_MAX_TEXTURES
+            {
+                warn!(
+                    "The extra mesh view bindings and layout extensions add {extra_texture_count} \
+                    textures to the mesh view layout, which then has {texture_count} textures. \
+                    Using more than {MESH_PIPELINE_VIEW_LAYOUT_SAFE_MAX_TEXTURES} might hit \
+                    `wgpu::Limits::max_sampled_textures_per_shader_stage` in some environments."
+                );
+            }
+        }
+
         // A 1x1x1 'all 1.0' texture to use as a dummy texture to use in place of optional StandardMaterial textures
         let dummy_white_gpu_image = {
             let image = Image::default();
@@ -1112,13 +1497,25 @@ impl FromWorld for MeshPipeline {
             }
         };
 
+        let mut mesh_layouts = MeshLayouts::new(&render_device);
+        if **use_vertex_pulling {
+            mesh_layouts.vertex_pulling = Some(MeshLayouts::vertex_pulling_layout(&render_device));
+        }
+
+        let view_layout_shader_defs = view_layouts
+            .iter()
+            .map(|layout| extra_bindings.shader_defs(layout.layout_key).collect())
+            .collect();
+
         MeshPipeline {
             view_layouts,
             clustered_forward_buffer_binding_type,
             dummy_white_gpu_image,
-            mesh_layouts: MeshLayouts::new(&render_device),
+            mesh_layouts,
             per_object_buffer_batch_size: GpuArrayBuffer::<MeshUniform>::batch_size(&render_device),
             binding_arrays_are_usable: binding_arrays_are_usable(&render_device),
+            max_bind_groups: render_device.limits().max_bind_groups,
+            view_layout_shader_defs,
         }
     }
 }
@@ -1141,8 +1538,7 @@ impl MeshPipeline {
     }
 
     pub fn get_view_layout(&self, layout_key: MeshPipelineViewLayoutKey) -> &BindGroupLayout {
-        let index = layout_key.bits() as usize;
-        let layout = &self.view_layouts[index];
+        let layout = self.view_layout(layout_key);
 
         #[cfg(debug_assertions)]
         if layout.texture_count > MESH_PIPELINE_VIEW_LAYOUT_SAFE_MAX_TEXTURES {
@@ -1152,6 +1548,55 @@ impl MeshPipeline {
 
         &layout.bind_group_layout
     }
+
+    /// The view layout used for `layout_key`, which lacks some of its features if they were
+    /// disabled to fit the texture budget of the device.
+    pub fn view_layout(&self, layout_key: MeshPipelineViewLayoutKey) -> &MeshPipelineViewLayout {
+        &self.view_layouts[layout_key.bits() as usize]
+    }
+
+    /// Removes from `key` the view features that were disabled from its view layout to fit the
+    /// texture budget of the device, see [`MeshViewTextureBudgetReport`].
+    ///
+    /// Pipelines using the view layouts should specialize with the returned key, so their
+    /// shaders don't declare the bindings of the disabled features.
+    pub fn fit_view_texture_budget(&self, mut key: MeshPipelineKey) -> MeshPipelineKey {
+        let requested_layout_key = MeshPipelineViewLayoutKey::from(key);
+        let disabled = requested_layout_key - self.view_layout(requested_layout_key).layout_key;
+        for (layout_feature, key_feature) in [
+            (
+                MeshPipelineViewLayoutKey::DEPTH_PREPASS,
+                MeshPipelineKey::DEPTH_PREPASS,
+            ),
+            (
+                MeshPipelineViewLayoutKey::NORMAL_PREPASS,
+                MeshPipelineKey::NORMAL_PREPASS,
+            ),
+            (
+                MeshPipelineViewLayoutKey::MOTION_VECTOR_PREPASS,
+                MeshPipelineKey::MOTION_VECTOR_PREPASS,
+            ),
+        ] {
+            if disabled.contains(layout_feature) {
+                key.remove(key_feature);
+            }
+        }
+        for index in 0..MAX_MESH_VIEW_LAYOUT_EXTENSIONS {
+            if disabled.contains(MeshPipelineViewLayoutKey::from_extension(index)) {
+                key.remove(MeshPipelineKey::from_view_layout_extension(index));
+            }
+        }
+        key
+    }
+
+    /// The shader defs of the [`MeshViewExtraBindings`] in the view layout of `layout_key`, which
+    /// pipelines using that layout should define.
+    pub fn get_view_layout_shader_defs(
+        &self,
+        layout_key: MeshPipelineViewLayoutKey,
+    ) -> &[ShaderDefVal] {
+        &self.view_layout_shader_defs[layout_key.bits() as usize]
+    }
 }
 
 impl GetBatchData for MeshPipeline {
@@ -1184,7 +1629,11 @@ impl GetBatchData for MeshPipeline {
             MeshUniform::new(
                 &mesh_instance.transforms,
                 maybe_lightmap.map(|lightmap| lightmap.uv_rect),
-            ),
+                entity,
+            )
+            .with_material_overrides(&mesh_instance.material_overrides)
+            .with_uv_transform(&mesh_instance.uv_transform)
+            .with_material_bindings_index(mesh_instance.material_bindings_index.get()),
             mesh_instance.should_batch().then_some((
                 mesh_instance.material_bind_group_id.get(),
                 mesh_instance.mesh_asset_id,
@@ -1236,10 +1685,16 @@ impl GetFullBatchData for MeshPipeline {
         let mesh_instance = mesh_instances.get(&entity)?;
         let maybe_lightmap = lightmaps.render_lightmaps.get(&entity);
 
-        Some(MeshUniform::new(
-            &mesh_instance.transforms,
-            maybe_lightmap.map(|lightmap| lightmap.uv_rect),
-        ))
+        Some(
+            MeshUniform::new(
+                &mesh_instance.transforms,
+                maybe_lightmap.map(|lightmap| lightmap.uv_rect),
+                entity,
+            )
+            .with_material_overrides(&mesh_instance.material_overrides)
+            .with_uv_transform(&mesh_instance.uv_transform)
+            .with_material_bindings_index(mesh_instance.material_bindings_index.get()),
+        )
     }
 
     fn get_binned_index(
@@ -1326,7 +1781,7 @@ fn get_batch_indirect_parameters_index(
 }
 
 bitflags::bitflags! {
-    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
     #[repr(transparent)]
     // NOTE: Apparently quadro drivers support up to 64x MSAA.
     /// MSAA uses the highest 3 bits for the MSAA log2(sample count) to support up to 128x MSAA.
@@ -1355,7 +1810,16 @@ bitflags::bitflags! {
         const LIGHTMAPPED                       = 1 << 13;
         const IRRADIANCE_VOLUME                 = 1 << 14;
         const VISIBILITY_RANGE_DITHER           = 1 << 15;
-        const LAST_FLAG                         = Self::VISIBILITY_RANGE_DITHER.bits();
+        const OBJECT_ID_PREPASS                 = 1 << 16;
+        const DEPTH_STENCIL                     = 1 << 17; // The view depth texture has a stencil, see `Camera3d::stencil`
+        const ALPHA_HASHED                      = 1 << 18; // Set along with `MAY_DISCARD` for `AlphaMode::Hashed`
+        const VERTEX_PULLING                    = 1 << 19; // Vertices are read from a storage buffer, see `UseVertexPulling`
+        const DECAL                             = 1 << 20; // Drawn in the `Decal3d` phase, see `Material::decal`
+        const SHADOW_PASS                       = 1 << 21; // Drawn in the shadow map of a light, see `Material::shadow_vertex_shader`
+        const DEFERRED_GBUFFER_EXTENSION        = 1 << 22; // The view has a G-buffer extension texture, see `DeferredGBufferExtensionPrepass`
+        const WRITES_DEFERRED_GBUFFER_EXTENSION = 1 << 23; // The material writes to it, see `Material::deferred_gbuffer_extension`
+        const TRANSLUCENT_SHADOW                = 1 << 24; // Drawn in the colored shadow map of a light, see `Material::colored_shadow`
+        const LAST_FLAG                         = Self::TRANSLUCENT_SHADOW.bits();
 
         // Bitfields
         const MSAA_RESERVED_BITS                = Self::MSAA_MASK_BITS << Self::MSAA_SHIFT_BITS;
@@ -1388,13 +1852,28 @@ bitflags::bitflags! {
         const SCREEN_SPACE_SPECULAR_TRANSMISSION_MEDIUM = 1 << Self::SCREEN_SPACE_SPECULAR_TRANSMISSION_SHIFT_BITS;
         const SCREEN_SPACE_SPECULAR_TRANSMISSION_HIGH = 2 << Self::SCREEN_SPACE_SPECULAR_TRANSMISSION_SHIFT_BITS;
         const SCREEN_SPACE_SPECULAR_TRANSMISSION_ULTRA = 3 << Self::SCREEN_SPACE_SPECULAR_TRANSMISSION_SHIFT_BITS;
+        const HDR_FORMAT_RESERVED_BITS          = Self::HDR_FORMAT_MASK_BITS << Self::HDR_FORMAT_SHIFT_BITS;
+        const HDR_FORMAT_RGBA16_FLOAT           = 0 << Self::HDR_FORMAT_SHIFT_BITS;
+        const HDR_FORMAT_RG11B10_FLOAT          = 1 << Self::HDR_FORMAT_SHIFT_BITS;
+        const HDR_FORMAT_RGB10A2_UNORM          = 2 << Self::HDR_FORMAT_SHIFT_BITS;
+        const VIEW_LAYOUT_EXTENSION_RESERVED_BITS = Self::VIEW_LAYOUT_EXTENSION_MASK_BITS << Self::VIEW_LAYOUT_EXTENSION_SHIFT_BITS; // ← One bit per `MeshViewLayoutExtension`
+        const VERTEX_COLOR_MODE_RESERVED_BITS   = Self::VERTEX_COLOR_MODE_MASK_BITS << Self::VERTEX_COLOR_MODE_SHIFT_BITS;
+        const VERTEX_COLOR_MODE_MULTIPLY        = 0 << Self::VERTEX_COLOR_MODE_SHIFT_BITS;
+        const VERTEX_COLOR_MODE_IGNORE          = 1 << Self::VERTEX_COLOR_MODE_SHIFT_BITS;
+        const VERTEX_COLOR_MODE_REPLACE         = 2 << Self::VERTEX_COLOR_MODE_SHIFT_BITS;
+        const VERTEX_COLOR_MODE_OCCLUSION       = 3 << Self::VERTEX_COLOR_MODE_SHIFT_BITS;
+        const USER_RESERVED_BITS                = Self::USER_MASK_BITS << Self::USER_SHIFT_BITS; // ← Allocated to plugins by `MeshPipelineKeyAllocator`
         const ALL_RESERVED_BITS =
             Self::BLEND_RESERVED_BITS.bits() |
             Self::MSAA_RESERVED_BITS.bits() |
             Self::TONEMAP_METHOD_RESERVED_BITS.bits() |
             Self::SHADOW_FILTER_METHOD_RESERVED_BITS.bits() |
             Self::VIEW_PROJECTION_RESERVED_BITS.bits() |
-            Self::SCREEN_SPACE_SPECULAR_TRANSMISSION_RESERVED_BITS.bits();
+            Self::SCREEN_SPACE_SPECULAR_TRANSMISSION_RESERVED_BITS.bits() |
+            Self::HDR_FORMAT_RESERVED_BITS.bits() |
+            Self::VIEW_LAYOUT_EXTENSION_RESERVED_BITS.bits() |
+            Self::VERTEX_COLOR_MODE_RESERVED_BITS.bits() |
+            Self::USER_RESERVED_BITS.bits();
     }
 }
 
@@ -1422,6 +1901,28 @@ impl MeshPipelineKey {
     const SCREEN_SPACE_SPECULAR_TRANSMISSION_SHIFT_BITS: u64 =
         Self::VIEW_PROJECTION_MASK_BITS.count_ones() as u64 + Self::VIEW_PROJECTION_SHIFT_BITS;
 
+    const HDR_FORMAT_MASK_BITS: u64 = 0b11;
+    const HDR_FORMAT_SHIFT_BITS: u64 = Self::SCREEN_SPACE_SPECULAR_TRANSMISSION_MASK_BITS
+        .count_ones() as u64
+        + Self::SCREEN_SPACE_SPECULAR_TRANSMISSION_SHIFT_BITS;
+
+    const VIEW_LAYOUT_EXTENSION_MASK_BITS: u64 = (1 << MAX_MESH_VIEW_LAYOUT_EXTENSIONS) - 1;
+    const VIEW_LAYOUT_EXTENSION_SHIFT_BITS: u64 =
+        Self::HDR_FORMAT_MASK_BITS.count_ones() as u64 + Self::HDR_FORMAT_SHIFT_BITS;
+
+    const VERTEX_COLOR_MODE_MASK_BITS: u64 = 0b11;
+    const VERTEX_COLOR_MODE_SHIFT_BITS: u64 = Self::VIEW_LAYOUT_EXTENSION_MASK_BITS.count_ones()
+        as u64
+        + Self::VIEW_LAYOUT_EXTENSION_SHIFT_BITS;
+
+    /// The number of bits reserved for plugins outside of Bevy, see [`MeshPipelineKeyAllocator`].
+    pub const USER_BIT_COUNT: u32 = 8;
+    const USER_MASK_BITS: u64 = (1 << Self::USER_BIT_COUNT) - 1;
+    // The user bits sit right below the base mesh pipeline key bits, so that the engine bits can
+    // keep growing upward.
+    const USER_SHIFT_BITS: u64 =
+        BaseMeshPipelineKey::PRIMITIVE_TOPOLOGY_SHIFT_BITS - Self::USER_BIT_COUNT as u64;
+
     pub fn from_msaa_samples(msaa_samples: u32) -> Self {
         let msaa_bits =
             (msaa_samples.trailing_zeros() as u64 & Self::MSAA_MASK_BITS) << Self::MSAA_SHIFT_BITS;
@@ -1436,6 +1937,53 @@ impl MeshPipelineKey {
         }
     }
 
+    /// The bit of the view layout extension registered at `index`, see
+    /// [`MeshViewLayoutExtensionPlugin`].
+    pub fn from_view_layout_extension(index: usize) -> Self {
+        debug_assert!(index < MAX_MESH_VIEW_LAYOUT_EXTENSIONS);
+        Self::from_bits_retain(1 << (Self::VIEW_LAYOUT_EXTENSION_SHIFT_BITS + index as u64))
+    }
+
+    pub fn from_hdr_format(hdr_format: HdrFormat) -> Self {
+        match hdr_format {
+            HdrFormat::Rgba16Float => MeshPipelineKey::HDR_FORMAT_RGBA16_FLOAT,
+            HdrFormat::Rg11b10Float => MeshPipelineKey::HDR_FORMAT_RG11B10_FLOAT,
+            HdrFormat::Rgb10a2Unorm => MeshPipelineKey::HDR_FORMAT_RGB10A2_UNORM,
+        }
+    }
+
+    pub fn from_vertex_color_mode(vertex_color_mode: VertexColorMode) -> Self {
+        match vertex_color_mode {
+            VertexColorMode::Ignore => MeshPipelineKey::VERTEX_COLOR_MODE_IGNORE,
+            VertexColorMode::Multiply => MeshPipelineKey::VERTEX_COLOR_MODE_MULTIPLY,
+            VertexColorMode::Replace => MeshPipelineKey::VERTEX_COLOR_MODE_REPLACE,
+            VertexColorMode::Occlusion => MeshPipelineKey::VERTEX_COLOR_MODE_OCCLUSION,
+        }
+    }
+
+    pub fn vertex_color_mode(&self) -> VertexColorMode {
+        match self.intersection(MeshPipelineKey::VERTEX_COLOR_MODE_RESERVED_BITS) {
+            MeshPipelineKey::VERTEX_COLOR_MODE_IGNORE => VertexColorMode::Ignore,
+            MeshPipelineKey::VERTEX_COLOR_MODE_REPLACE => VertexColorMode::Replace,
+            MeshPipelineKey::VERTEX_COLOR_MODE_OCCLUSION => VertexColorMode::Occlusion,
+            _ => VertexColorMode::Multiply,
+        }
+    }
+
+    pub fn hdr_format(&self) -> HdrFormat {
+        match self.intersection(MeshPipelineKey::HDR_FORMAT_RESERVED_BITS) {
+            MeshPipelineKey::HDR_FORMAT_RG11B10_FLOAT => HdrFormat::Rg11b10Float,
+            MeshPipelineKey::HDR_FORMAT_RGB10A2_UNORM => HdrFormat::Rgb10a2Unorm,
+            _ => HdrFormat::Rgba16Float,
+        }
+    }
+
+    /// The format of the main texture of the view, which the color target of the main passes
+    /// has.
+    pub fn view_target_format(&self) -> TextureFormat {
+        ViewTarget::main_texture_format_for(self.contains(MeshPipelineKey::HDR), self.hdr_format())
+    }
+
     pub fn msaa_samples(&self) -> u32 {
         1 << ((self.bits() >> Self::MSAA_SHIFT_BITS) & Self::MSAA_MASK_BITS)
     }
@@ -1447,6 +1995,15 @@ impl MeshPipelineKey {
         Self::from_bits_retain(primitive_topology_bits)
     }
 
+    /// The format of the depth texture of the main 3d passes of the view.
+    pub fn depth_format(&self) -> TextureFormat {
+        if self.contains(MeshPipelineKey::DEPTH_STENCIL) {
+            CORE_3D_DEPTH_STENCIL_FORMAT
+        } else {
+            CORE_3D_DEPTH_FORMAT
+        }
+    }
+
     pub fn primitive_topology(&self) -> PrimitiveTopology {
         let primitive_topology_bits = (self.bits()
             >> BaseMeshPipelineKey::PRIMITIVE_TOPOLOGY_SHIFT_BITS)
@@ -1462,6 +2019,16 @@ impl MeshPipelineKey {
     }
 }
 
+impl PipelineKeyBits for MeshPipelineKey {
+    fn bits(&self) -> u64 {
+        MeshPipelineKey::bits(self)
+    }
+
+    fn without_bits(&self) -> Self {
+        MeshPipelineKey::NONE
+    }
+}
+
 // Ensure that we didn't overflow the number of bits available in `MeshPipelineKey`.
 const_assert_eq!(
     (((MeshPipelineKey::LAST_FLAG.bits() << 1) - 1) | MeshPipelineKey::ALL_RESERVED_BITS.bits())
@@ -1469,6 +2036,15 @@ const_assert_eq!(
     0
 );
 
+// Ensure that the engine bits don't overlap with the bits reserved for plugins.
+const_assert_eq!(
+    (((MeshPipelineKey::LAST_FLAG.bits() << 1) - 1)
+        | (MeshPipelineKey::ALL_RESERVED_BITS.bits()
+            & !MeshPipelineKey::USER_RESERVED_BITS.bits()))
+        & MeshPipelineKey::USER_RESERVED_BITS.bits(),
+    0
+);
+
 // Ensure that the reserved bits don't overlap with the topology bits
 const_assert_eq!(
     (BaseMeshPipelineKey::PRIMITIVE_TOPOLOGY_MASK_BITS
@@ -1477,6 +2053,122 @@ const_assert_eq!(
     0
 );
 
+/// Hands out the [`MeshPipelineKey::USER_RESERVED_BITS`] to plugins outside of Bevy, so that
+/// they can add their own permutations of the mesh pipelines without colliding with the engine
+/// bits or with each other.
+///
+/// Allocate the bits when building the plugin. The allocator lives in the main world, and is
+/// inserted on first use so that the plugin can be added before or after the
+/// [`PbrPlugin`](crate::PbrPlugin):
+///
+/// ```
+/// # use bevy_app::App;
+/// # use bevy_pbr::MeshPipelineKeyAllocator;
+/// # let mut app = App::new();
+/// let outline_bits = app
+///     .world_mut()
+///     .get_resource_or_insert_with(MeshPipelineKeyAllocator::default)
+///     .allocate("outline_mode", 2);
+/// ```
+///
+/// The bits are then added to the views with an [`ExtraMeshPipelineKey`], and read back in
+/// [`Material::specialize`](crate::Material::specialize) with [`MeshPipelineKeyBits::get`].
+#[derive(Resource, Default)]
+pub struct MeshPipelineKeyAllocator {
+    allocations: Vec<MeshPipelineKeyBits>,
+    next_shift: u64,
+}
+
+/// A range of the [`MeshPipelineKey::USER_RESERVED_BITS`] allocated by a
+/// [`MeshPipelineKeyAllocator`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MeshPipelineKeyBits {
+    name: &'static str,
+    shift: u64,
+    mask: u64,
+}
+
+impl MeshPipelineKeyAllocator {
+    /// Allocates `bit_count` bits, which hold values from 0 to `2^bit_count - 1`, for the
+    /// permutation `name`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `name` was already allocated, or if there aren't enough bits left out of the
+    /// [`MeshPipelineKey::USER_BIT_COUNT`].
+    pub fn allocate(&mut self, name: &'static str, bit_count: u32) -> MeshPipelineKeyBits {
+        assert!(
+            self.allocations.iter().all(|bits| bits.name != name),
+            "The mesh pipeline key bits `{name}` are already allocated"
+        );
+        assert!(
+            bit_count > 0 && bit_count <= self.remaining(),
+            "Can't allocate {bit_count} mesh pipeline key bits for `{name}`, {} are left",
+            self.remaining()
+        );
+        let bits = MeshPipelineKeyBits {
+            name,
+            shift: MeshPipelineKey::USER_SHIFT_BITS + self.next_shift,
+            mask: (1 << bit_count) - 1,
+        };
+        self.next_shift += bit_count as u64;
+        self.allocations.push(bits);
+        bits
+    }
+
+    /// The number of bits left to allocate.
+    pub fn remaining(&self) -> u32 {
+        MeshPipelineKey::USER_BIT_COUNT - self.next_shift as u32
+    }
+
+    /// The bits allocated so far, in allocation order.
+    pub fn allocations(&self) -> &[MeshPipelineKeyBits] {
+        &self.allocations
+    }
+}
+
+impl MeshPipelineKeyBits {
+    /// The name the bits were allocated for.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// All the bits of the allocation, set.
+    pub fn reserved_bits(&self) -> MeshPipelineKey {
+        MeshPipelineKey::from_bits_retain(self.mask << self.shift)
+    }
+
+    /// The key with `value` in the allocated bits.
+    pub fn key(&self, value: u64) -> MeshPipelineKey {
+        debug_assert!(
+            value <= self.mask,
+            "{value} doesn't fit in the mesh pipeline key bits `{}`",
+            self.name
+        );
+        MeshPipelineKey::from_bits_retain((value & self.mask) << self.shift)
+    }
+
+    /// The value in the allocated bits of `key`.
+    pub fn get(&self, key: MeshPipelineKey) -> u64 {
+        (key.bits() >> self.shift) & self.mask
+    }
+}
+
+/// The [`MeshPipelineKey::USER_RESERVED_BITS`] of a view in the render world, added to the keys
+/// of the mesh pipelines of its main and prepass passes.
+///
+/// Build the key with the [`MeshPipelineKeyBits`] of a [`MeshPipelineKeyAllocator`]. The other
+/// bits are ignored.
+#[derive(Component, Clone, Copy, Default, Debug, Deref, DerefMut)]
+pub struct ExtraMeshPipelineKey(pub MeshPipelineKey);
+
+impl ExtraMeshPipelineKey {
+    /// The user bits of the key, to add to the key of a view.
+    pub fn view_key(&self) -> MeshPipelineKey {
+        self.0 & MeshPipelineKey::USER_RESERVED_BITS
+    }
+}
+
 fn is_skinned(layout: &MeshVertexBufferLayoutRef) -> bool {
     layout.0.contains(Mesh::ATTRIBUTE_JOINT_INDEX)
         && layout.0.contains(Mesh::ATTRIBUTE_JOINT_WEIGHT)
@@ -1523,12 +2215,16 @@ impl SpecializedMeshPipeline for MeshPipeline {
         key: Self::Key,
         layout: &MeshVertexBufferLayoutRef,
     ) -> Result<RenderPipelineDescriptor, SpecializedMeshPipelineError> {
+        let key = self.fit_view_texture_budget(key);
+
         let mut shader_defs = Vec::new();
         let mut vertex_attributes = Vec::new();
 
         // Let the shader code know that it's running in a mesh pipeline.
         shader_defs.push("MESH_PIPELINE".into());
 
+        shader_defs.extend_from_slice(self.get_view_layout_shader_defs(key.into()));
+
         shader_defs.push("VERTEX_OUTPUT_INSTANCE_INDEX".into());
 
         if layout.0.contains(Mesh::ATTRIBUTE_POSITION) {
@@ -1556,8 +2252,15 @@ impl SpecializedMeshPipeline for MeshPipeline {
             vertex_attributes.push(Mesh::ATTRIBUTE_TANGENT.at_shader_location(4));
         }
 
-        if layout.0.contains(Mesh::ATTRIBUTE_COLOR) {
+        let vertex_color_mode = key.vertex_color_mode();
+        if layout.0.contains(Mesh::ATTRIBUTE_COLOR) && vertex_color_mode != VertexColorMode::Ignore
+        {
             shader_defs.push("VERTEX_COLORS".into());
+            if vertex_color_mode == VertexColorMode::Replace {
+                shader_defs.push("VERTEX_COLORS_REPLACE".into());
+            } else if vertex_color_mode == VertexColorMode::Occlusion {
+                shader_defs.push("VERTEX_COLORS_OCCLUSION".into());
+            }
             vertex_attributes.push(Mesh::ATTRIBUTE_COLOR.at_shader_location(5));
         }
 
@@ -1584,7 +2287,16 @@ impl SpecializedMeshPipeline for MeshPipeline {
             shader_defs.push("SCREEN_SPACE_AMBIENT_OCCLUSION".into());
         }
 
-        let vertex_buffer_layout = layout.0.get_layout(&vertex_attributes)?;
+        let mut vertex_buffers = vec![layout.0.get_layout(&vertex_attributes)?];
+        if key.contains(MeshPipelineKey::VERTEX_PULLING) {
+            setup_vertex_pulling(
+                &self.mesh_layouts,
+                layout,
+                &mut shader_defs,
+                &mut bind_group_layout,
+                &mut vertex_buffers,
+            );
+        }
 
         let (label, blend, depth_write_enabled);
         let pass = key.intersection(MeshPipelineKey::BLEND_RESERVED_BITS);
@@ -1640,6 +2352,16 @@ impl SpecializedMeshPipeline for MeshPipeline {
             is_opaque = !key.contains(MeshPipelineKey::READS_VIEW_TRANSMISSION_TEXTURE);
         }
 
+        // Decals are drawn over the opaque meshes after the prepass, so they neither write depth
+        // nor have prepass normals of their own.
+        let depth_write_enabled = if key.contains(MeshPipelineKey::DECAL) {
+            shader_defs.push("DECAL".into());
+            is_opaque = false;
+            false
+        } else {
+            depth_write_enabled
+        };
+
         if key.contains(MeshPipelineKey::NORMAL_PREPASS) {
             shader_defs.push("NORMAL_PREPASS".into());
         }
@@ -1705,6 +2427,10 @@ impl SpecializedMeshPipeline for MeshPipeline {
             shader_defs.push("MAY_DISCARD".into());
         }
 
+        if key.contains(MeshPipelineKey::ALPHA_HASHED) {
+            shader_defs.push("ALPHA_HASHED".into());
+        }
+
         if key.contains(MeshPipelineKey::ENVIRONMENT_MAP) {
             shader_defs.push("ENVIRONMENT_MAP".into());
         }
@@ -1757,11 +2483,7 @@ impl SpecializedMeshPipeline for MeshPipeline {
             shader_defs.push("IRRADIANCE_VOLUMES_ARE_USABLE".into());
         }
 
-        let format = if key.contains(MeshPipelineKey::HDR) {
-            ViewTarget::TEXTURE_FORMAT_HDR
-        } else {
-            TextureFormat::bevy_default()
-        };
+        let format = key.view_target_format();
 
         // This is defined here so that custom shaders that use something other than
         // the mesh binding from bevy_pbr::mesh_bindings can easily make use of this
@@ -1778,7 +2500,7 @@ impl SpecializedMeshPipeline for MeshPipeline {
                 shader: MESH_SHADER_HANDLE,
                 entry_point: "vertex".into(),
                 shader_defs: shader_defs.clone(),
-                buffers: vec![vertex_buffer_layout],
+                buffers: vertex_buffers,
             },
             fragment: Some(FragmentState {
                 shader: MESH_SHADER_HANDLE,
@@ -1802,7 +2524,7 @@ impl SpecializedMeshPipeline for MeshPipeline {
                 strip_index_format: None,
             },
             depth_stencil: Some(DepthStencilState {
-                format: CORE_3D_DEPTH_FORMAT,
+                format: key.depth_format(),
                 depth_write_enabled,
                 depth_compare: CompareFunction::GreaterEqual,
                 stencil: StencilState {
@@ -2130,11 +2852,93 @@ impl<P: PhaseItem> RenderCommand<P> for DrawMesh {
 
 #[cfg(test)]
 mod tests {
-    use super::MeshPipelineKey;
+    use super::{MeshPipelineKey, MeshPipelineKeyAllocator};
+    use bevy_render::view::HdrFormat;
     #[test]
     fn mesh_key_msaa_samples() {
         for i in [1, 2, 4, 8, 16, 32, 64, 128] {
             assert_eq!(MeshPipelineKey::from_msaa_samples(i).msaa_samples(), i);
         }
     }
+
+    #[test]
+    fn mesh_key_hdr_format() {
+        for hdr_format in [
+            HdrFormat::Rgba16Float,
+            HdrFormat::Rg11b10Float,
+            HdrFormat::Rgb10a2Unorm,
+        ] {
+            let key = MeshPipelineKey::HDR | MeshPipelineKey::from_hdr_format(hdr_format);
+            assert_eq!(key.hdr_format(), hdr_format);
+            assert_eq!(key.view_target_format(), hdr_format.texture_format());
+        }
+    }
+
+    #[test]
+    fn mesh_key_user_bits() {
+        let mut allocator = MeshPipelineKeyAllocator::default();
+        let outline = allocator.allocate("outline", 2);
+        let toon = allocator.allocate("toon", 1);
+        assert_eq!(allocator.remaining(), MeshPipelineKey::USER_BIT_COUNT - 3);
+
+        let key = MeshPipelineKey::HDR | outline.key(3) | toon.key(1);
+        assert_eq!(outline.get(key), 3);
+        assert_eq!(toon.get(key), 1);
+        assert!((outline.reserved_bits() | toon.reserved_bits())
+            .difference(MeshPipelineKey::USER_RESERVED_BITS)
+            .is_empty());
+        assert!(outline
+            .reserved_bits()
+            .intersection(toon.reserved_bits())
+            .is_empty());
+    }
+
+    // The sizes of the structs with and without `compressed_mesh_transforms` must match the
+    // `Mesh` and `MeshInput` structs of `mesh_types.wgsl` and `mesh_preprocess.wgsl`.
+    #[test]
+    fn mesh_uniform_layout() {
+        use bevy_render::render_resource::ShaderType;
+
+        use super::{MeshInputUniform, MeshUniform};
+
+        let (mesh_input_size, mesh_size) = if cfg!(feature = "compressed_mesh_transforms") {
+            (96, 144)
+        } else {
+            (112, 208)
+        };
+        assert_eq!(MeshInputUniform::min_size().get(), mesh_input_size);
+        assert_eq!(
+            std::mem::size_of::<MeshInputUniform>() as u64,
+            mesh_input_size
+        );
+        assert_eq!(MeshUniform::min_size().get(), mesh_size);
+    }
+
+    #[test]
+    fn compressed_transform_keeps_rotation_and_scale() {
+        use bevy_math::{Affine3, Affine3A, Quat, Vec3};
+
+        use super::CompressedTransform;
+
+        let rotation = Quat::from_euler(bevy_math::EulerRot::XYZ, 0.3, -1.2, 2.5);
+        let affine = Affine3A::from_scale_rotation_translation(
+            Vec3::new(2.0, -0.5, 3.0),
+            rotation,
+            Vec3::new(10.0, 20.0, -30.0),
+        );
+        let compressed = CompressedTransform::new(&Affine3::from(&affine));
+
+        let unpack_snorm16x2 = |packed: u32| {
+            let snorm16 = |bits: u32| (bits as u16 as i16) as f32 / 32767.0;
+            (snorm16(packed), snorm16(packed >> 16))
+        };
+        let (x, y) = unpack_snorm16x2(compressed.rotation_xy);
+        let (z, w) = unpack_snorm16x2(compressed.rotation_zw);
+        let unpacked = Affine3A::from_scale_rotation_translation(
+            compressed.scale,
+            Quat::from_xyzw(x, y, z, w).normalize(),
+            compressed.translation,
+        );
+        assert!(unpacked.abs_diff_eq(affine, 1e-3));
+    }
 }
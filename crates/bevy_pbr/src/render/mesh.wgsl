@@ -2,6 +2,7 @@
     mesh_functions,
     skinning,
     morph::morph,
+    vertex_pulling,
     forward_io::{Vertex, VertexOutput},
     view_transformations::position_world_to_clip,
 }
@@ -27,8 +28,43 @@ fn morph_vertex(vertex_in: Vertex) -> Vertex {
 }
 #endif
 
+#ifdef VERTEX_PULLING
+// Builds the vertex from the vertex buffer, instead of the vertex attributes.
+fn pull_vertex(instance_index: u32, vertex_index: u32) -> Vertex {
+    var vertex: Vertex;
+    vertex.instance_index = instance_index;
+#ifdef VERTEX_POSITIONS
+    vertex.position = vertex_pulling::position(vertex_index);
+#endif
+#ifdef VERTEX_NORMALS
+    vertex.normal = vertex_pulling::normal(vertex_index);
+#endif
+#ifdef VERTEX_UVS
+    vertex.uv = vertex_pulling::uv(vertex_index);
+#endif
+#ifdef VERTEX_UVS_B
+    vertex.uv_b = vertex_pulling::uv_b(vertex_index);
+#endif
+#ifdef VERTEX_TANGENTS
+    vertex.tangent = vertex_pulling::tangent(vertex_index);
+#endif
+#ifdef VERTEX_COLORS
+    vertex.color = vertex_pulling::color(vertex_index);
+#endif
+    return vertex;
+}
+#endif
+
 @vertex
+#ifdef VERTEX_PULLING
+fn vertex(
+    @builtin(instance_index) instance_index: u32,
+    @builtin(vertex_index) vertex_index: u32,
+) -> VertexOutput {
+    let vertex_no_morph = pull_vertex(instance_index, vertex_index);
+#else
 fn vertex(vertex_no_morph: Vertex) -> VertexOutput {
+#endif
     var out: VertexOutput;
 
 #ifdef MORPH_TARGETS
@@ -64,7 +100,7 @@ fn vertex(vertex_no_morph: Vertex) -> VertexOutput {
 #endif
 
 #ifdef VERTEX_UVS
-    out.uv = vertex.uv;
+    out.uv = mesh_functions::mesh_uv_transform(vertex_no_morph.instance_index, vertex.uv);
 #endif
 
 #ifdef VERTEX_UVS_B
@@ -116,6 +116,14 @@ pub struct MeshLayouts {
     ///
     /// [`MorphAttributes`]: bevy_render::mesh::morph::MorphAttributes
     pub morphed_skinned: BindGroupLayout,
+
+    /// The vertex buffer of the mesh as a storage buffer, bound after the
+    /// material for vertex pulling.
+    ///
+    /// `None` unless vertex pulling is enabled, see [`UseVertexPulling`].
+    ///
+    /// [`UseVertexPulling`]: crate::UseVertexPulling
+    pub vertex_pulling: Option<BindGroupLayout>,
 }
 
 impl MeshLayouts {
@@ -129,9 +137,22 @@ impl MeshLayouts {
             skinned: Self::skinned_layout(render_device),
             morphed: Self::morphed_layout(render_device),
             morphed_skinned: Self::morphed_skinned_layout(render_device),
+            vertex_pulling: None,
         }
     }
 
+    /// Creates the layout of the vertex pulling bind group, which requires
+    /// storage buffers.
+    pub fn vertex_pulling_layout(render_device: &RenderDevice) -> BindGroupLayout {
+        render_device.create_bind_group_layout(
+            "vertex_pulling_layout",
+            &BindGroupLayoutEntries::single(
+                ShaderStages::VERTEX,
+                binding_types::storage_buffer_read_only_sized(false, None),
+            ),
+        )
+    }
+
     // ---------- create individual BindGroupLayouts ----------
 
     fn model_only_layout(render_device: &RenderDevice) -> BindGroupLayout {
@@ -3,18 +3,61 @@
 #import bevy_pbr::{
     mesh_view_bindings::{view, visibility_ranges},
     mesh_bindings::mesh,
-    mesh_types::MESH_FLAGS_SIGN_DETERMINANT_MODEL_3X3_BIT,
+    mesh_types::{
+        MESH_FLAGS_SIGN_DETERMINANT_MODEL_3X3_BIT, MESH_FLAGS_BASE_COLOR_OVERRIDE_BIT,
+        MATERIAL_TABLE_INVALID_SLOT,
+        unpack_mesh_transform, mesh_inverse_transpose_model,
+    },
+    shader_constants::{MATERIAL_BINDINGS_INDEX_BITS, MATERIAL_BINDINGS_GENERATION_SHIFT},
     view_transformations::position_world_to_clip,
 }
-#import bevy_render::maths::{affine3_to_square, mat2x4_f32_to_mat3x3_unpack}
+#import bevy_render::maths::affine3_to_square
 
 
 fn get_model_matrix(instance_index: u32) -> mat4x4<f32> {
-    return affine3_to_square(mesh[instance_index].model);
+    return affine3_to_square(unpack_mesh_transform(mesh[instance_index].model));
 }
 
 fn get_previous_model_matrix(instance_index: u32) -> mat4x4<f32> {
-    return affine3_to_square(mesh[instance_index].previous_model);
+    return affine3_to_square(unpack_mesh_transform(mesh[instance_index].previous_model));
+}
+
+// Applies the `UvTransform` of the mesh to a UV coordinate.
+fn mesh_uv_transform(instance_index: u32, uv: vec2<f32>) -> vec2<f32> {
+    let uv_transform = mesh[instance_index].uv_transform_a;
+    return mat2x2(uv_transform.xy, uv_transform.zw) * uv + mesh[instance_index].uv_transform_b;
+}
+
+// Returns the base color override of the mesh if it has one, and
+// `material_base_color` otherwise.
+fn mesh_base_color(instance_index: u32, material_base_color: vec4<f32>) -> vec4<f32> {
+    if ((mesh[instance_index].flags & MESH_FLAGS_BASE_COLOR_OVERRIDE_BIT) == 0u) {
+        return material_base_color;
+    }
+    let packed = mesh[instance_index].material_override_base_color;
+    return vec4(unpack2x16float(packed.x), unpack2x16float(packed.y));
+}
+
+// Returns the packed index of the material of the mesh in the remapping table
+// of its material table. Only meaningful with the `MATERIAL_TABLE` shader def.
+fn get_material_bindings_index(instance_index: u32) -> u32 {
+    return mesh[instance_index].material_bindings_index;
+}
+
+// Returns the index of the entry of a packed material bindings index in the
+// remapping table of its material table.
+fn material_table_remap_index(bindings_index: u32) -> u32 {
+    return bindings_index & MATERIAL_BINDINGS_INDEX_BITS;
+}
+
+// Returns the slot of the data of a material in its material table, given its
+// packed bindings index and its entry in the remapping table, or
+// `MATERIAL_TABLE_INVALID_SLOT` if the index is stale.
+fn material_table_slot(bindings_index: u32, remap_entry: vec2<u32>) -> u32 {
+    if remap_entry.y != bindings_index >> MATERIAL_BINDINGS_GENERATION_SHIFT {
+        return MATERIAL_TABLE_INVALID_SLOT;
+    }
+    return remap_entry.x;
 }
 
 fn mesh_position_local_to_world(model: mat4x4<f32>, vertex_position: vec4<f32>) -> vec4<f32> {
@@ -38,12 +81,7 @@ fn mesh_normal_local_to_world(vertex_normal: vec3<f32>, instance_index: u32) ->
     // Do not change this code unless you really know what you are doing.
     // http://www.mikktspace.com/
     if any(vertex_normal != vec3<f32>(0.0)) {
-        return normalize(
-            mat2x4_f32_to_mat3x3_unpack(
-                mesh[instance_index].inverse_transpose_model_a,
-                mesh[instance_index].inverse_transpose_model_b,
-            ) * vertex_normal
-        );
+        return normalize(mesh_inverse_transpose_model(mesh[instance_index]) * vertex_normal);
     } else {
         return vertex_normal;
     }
@@ -84,6 +122,24 @@ fn mesh_tangent_local_to_world(model: mat4x4<f32>, vertex_tangent: vec4<f32>, in
     }
 }
 
+// Returns the dither level of a mesh that crossfades between two levels of its
+// material LOD, with the same mapping as `get_visibility_range_dither_level`:
+// the new level counts up from -16 to 0 across the margin of the crossfade, and
+// the previous level from 0 to 16.
+#ifdef MATERIAL_LOD_FADE
+fn get_material_lod_dither_level(camera_distance: f32) -> i32 {
+    // The margin is passed as bits, see `MaterialPipeline::specialize`.
+    let margin_start = bitcast<f32>(u32(#{MATERIAL_LOD_FADE_START}));
+    let margin_end = bitcast<f32>(u32(#{MATERIAL_LOD_FADE_END}));
+    let level = clamp(i32(round((camera_distance - margin_start) / (margin_end - margin_start) * 16.0)), 0, 16);
+#ifdef MATERIAL_LOD_FADE_IN
+    return level - 16;
+#else
+    return level;
+#endif
+}
+#endif
+
 // Returns an appropriate dither level for the current mesh instance.
 //
 // This looks up the LOD range in the `visibility_ranges` table and compares the
@@ -91,12 +147,21 @@ fn mesh_tangent_local_to_world(model: mat4x4<f32>, vertex_tangent: vec4<f32>, in
 #ifdef VISIBILITY_RANGE_DITHER
 fn get_visibility_range_dither_level(instance_index: u32, world_position: vec4<f32>) -> i32 {
     let visibility_buffer_index = mesh[instance_index].flags & 0xffffu;
+    let camera_distance = length(view.world_position.xyz - world_position.xyz);
+
+#ifdef MATERIAL_LOD_FADE
+    let material_lod_level = get_material_lod_dither_level(camera_distance);
+    // Meshes without a visibility range only fade between material LOD levels.
+    if (visibility_buffer_index == 0xffffu) {
+        return material_lod_level;
+    }
+#endif
+
     if (visibility_buffer_index > arrayLength(&visibility_ranges)) {
         return -16;
     }
 
     let lod_range = visibility_ranges[visibility_buffer_index];
-    let camera_distance = length(view.world_position.xyz - world_position.xyz);
 
     // This encodes the following mapping:
     //
@@ -106,6 +171,14 @@ fn get_visibility_range_dither_level(instance_index: u32, world_position: vec4<f
     let offset = select(-16, 0, camera_distance >= lod_range.z);
     let bounds = select(lod_range.xy, lod_range.zw, camera_distance >= lod_range.z);
     let level = i32(round((camera_distance - bounds.x) / (bounds.y - bounds.x) * 16.0));
+
+#ifdef MATERIAL_LOD_FADE
+    // While the mesh fades through its visibility range, that fade wins over the
+    // crossfade between material LOD levels.
+    let range_level = offset + clamp(level, 0, 16);
+    return select(material_lod_level, range_level, range_level != 0);
+#else
     return offset + clamp(level, 0, 16);
+#endif
 }
 #endif
@@ -7,21 +7,40 @@
 // mesh's transform on the previous frame and writes it into the `MeshUniform`
 // so that TAA works.
 
-#import bevy_pbr::mesh_types::Mesh
+#import bevy_pbr::mesh_types::{Mesh, unpack_mesh_transform}
+#ifdef COMPRESSED_MESH_TRANSFORMS
+#import bevy_pbr::mesh_types::CompressedTransform
+#endif
 #import bevy_render::maths
 #import bevy_render::view::View
 
 // Per-frame data that the CPU supplies to the GPU.
 struct MeshInput {
     // The model transform.
+#ifdef COMPRESSED_MESH_TRANSFORMS
+    model: CompressedTransform,
+#else
     model: mat3x4<f32>,
+#endif
+    // The `UvTransform` of the entity.
+    uv_transform_a: vec4<f32>,
+    uv_transform_b: vec2<f32>,
     // The lightmap UV rect, packed into 64 bits.
     lightmap_uv_rect: vec2<u32>,
+    // The base color `MaterialOverrides` of the entity, packed as 4 f16.
+    material_override_base_color: vec2<u32>,
     // Various flags.
     flags: u32,
     // The index of this mesh's `MeshInput` in the `previous_input` array, if
     // applicable. If not present, this is `u32::MAX`.
     previous_input_index: u32,
+    // The index of the main world entity this mesh belongs to.
+    entity_index: u32,
+    // The other `MaterialOverrides` of the entity.
+    material_override_emissive: u32,
+    material_override_perceptual_roughness: f32,
+    // The packed index of the material in its material table.
+    material_bindings_index: u32,
 }
 
 // Information about each mesh instance needed to cull it on GPU.
@@ -135,7 +154,8 @@ fn main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
     // Unpack.
     let input_index = work_items[instance_index].input_index;
     let output_index = work_items[instance_index].output_index;
-    let model_affine_transpose = current_input[input_index].model;
+    let model_transform = current_input[input_index].model;
+    let model_affine_transpose = unpack_mesh_transform(model_transform);
     let model = maths::affine3_to_square(model_affine_transpose);
 
     // Cull if necessary.
@@ -150,7 +170,9 @@ fn main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
     }
 #endif
 
-    // Calculate inverse transpose.
+#ifndef COMPRESSED_MESH_TRANSFORMS
+    // Calculate inverse transpose. Compressed transforms unpack it from their
+    // rotation and scale instead.
     let inverse_transpose_model = transpose(maths::inverse_affine3(transpose(
         model_affine_transpose)));
 
@@ -159,13 +181,12 @@ fn main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
         vec4<f32>(inverse_transpose_model[0].xyz, inverse_transpose_model[1].x),
         vec4<f32>(inverse_transpose_model[1].yz, inverse_transpose_model[2].xy));
     let inverse_transpose_model_b = inverse_transpose_model[2].z;
+#endif
 
     // Look up the previous model matrix.
     let previous_input_index = current_input[input_index].previous_input_index;
-    var previous_model: mat3x4<f32>;
-    if (previous_input_index == 0xffffffff) {
-        previous_model = model_affine_transpose;
-    } else {
+    var previous_model = model_transform;
+    if (previous_input_index != 0xffffffff) {
         previous_model = previous_input[previous_input_index].model;
     }
 
@@ -180,10 +201,96 @@ fn main(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
 #endif
 
     // Write the output.
-    output[mesh_output_index].model = model_affine_transpose;
+    output[mesh_output_index].model = model_transform;
     output[mesh_output_index].previous_model = previous_model;
+#ifndef COMPRESSED_MESH_TRANSFORMS
     output[mesh_output_index].inverse_transpose_model_a = inverse_transpose_model_a;
     output[mesh_output_index].inverse_transpose_model_b = inverse_transpose_model_b;
+#endif
     output[mesh_output_index].flags = current_input[input_index].flags;
     output[mesh_output_index].lightmap_uv_rect = current_input[input_index].lightmap_uv_rect;
+    output[mesh_output_index].uv_transform_a = current_input[input_index].uv_transform_a;
+    output[mesh_output_index].uv_transform_b = current_input[input_index].uv_transform_b;
+    output[mesh_output_index].entity_index = current_input[input_index].entity_index;
+    output[mesh_output_index].material_override_base_color =
+        current_input[input_index].material_override_base_color;
+    output[mesh_output_index].material_override_emissive =
+        current_input[input_index].material_override_emissive;
+    output[mesh_output_index].material_override_perceptual_roughness =
+        current_input[input_index].material_override_perceptual_roughness;
+    output[mesh_output_index].material_bindings_index =
+        current_input[input_index].material_bindings_index;
+}
+
+#ifdef GPU_SORTING
+// The parameters of the GPU sort of the instances of a view. This must match
+// `GpuSortUniform` in `gpu_sort.rs`.
+struct GpuSortUniform {
+    // The number of instances to sort.
+    count: u32,
+    // The number of blocks of `SORT_WORKGROUP_SIZE` instances.
+    block_count: u32,
+    // The number of high bits of the keys holding the batch index.
+    batch_bits: u32,
+    // The first bit of the keys sorted on by the current radix sort pass.
+    shift: u32,
+}
+
+// One instance sorted on the GPU.
+struct GpuSortItem {
+    // The index of the `PreprocessWorkItem` of this instance in `work_items`.
+    work_item_index: u32,
+    // The index of the batch of this instance.
+    batch_index: u32,
 }
+
+// The view data, including the view matrix.
+@group(1) @binding(0) var<uniform> view: View;
+// The parameters of the sort.
+@group(1) @binding(1) var<uniform> sort_uniform: GpuSortUniform;
+// The instances to sort.
+@group(1) @binding(2) var<storage> sort_items: array<GpuSortItem>;
+// The keys to sort the instances by, one per instance.
+@group(1) @binding(3) var<storage, read_write> sort_keys: array<u32>;
+// The input indices of the instances, sorted along with the keys.
+@group(1) @binding(4) var<storage, read_write> sort_values: array<u32>;
+
+// Generates the sort key of each instance sorted on the GPU, before the radix
+// sort in `gpu_sort.wgsl` reorders the work items and `main` runs.
+@compute
+@workgroup_size(256)
+fn generate_sort_keys(@builtin(global_invocation_id) global_invocation_id: vec3<u32>) {
+    let sort_index = global_invocation_id.x;
+    if (sort_index >= sort_uniform.count) {
+        return;
+    }
+
+    let sort_item = sort_items[sort_index];
+    let input_index = work_items[sort_item.work_item_index].input_index;
+    let model = unpack_mesh_transform(current_input[input_index].model);
+    let translation = vec4(model[0].w, model[1].w, model[2].w, 1.0);
+
+    // The view space depth increases towards the camera, like the distance of
+    // `Transparent3d`, so sorting it in ascending order draws back to front.
+    let depth_bits = bitcast<u32>((view.inverse_view * translation).z);
+
+    // Flip the bits of the depth so that its unsigned integer ordering matches
+    // the floating point one.
+    let ordered_depth = select(
+        depth_bits | 0x80000000u,
+        ~depth_bits,
+        (depth_bits & 0x80000000u) != 0u,
+    );
+
+    // Keep the batches apart by putting the batch index in the high bits of the
+    // key, at the expense of the precision of the depth.
+    var key = ordered_depth;
+    if (sort_uniform.batch_bits != 0u) {
+        key = (sort_item.batch_index << (32u - sort_uniform.batch_bits)) |
+            (ordered_depth >> sort_uniform.batch_bits);
+    }
+
+    sort_keys[sort_index] = key;
+    sort_values[sort_index] = input_index;
+}
+#endif
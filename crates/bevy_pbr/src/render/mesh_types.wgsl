@@ -1,20 +1,57 @@
 #define_import_path bevy_pbr::mesh_types
 
+#import bevy_render::maths::mat2x4_f32_to_mat3x3_unpack
+
+#ifdef COMPRESSED_MESH_TRANSFORMS
+// An affine transform compressed into its translation, rotation and scale,
+// see `CompressedTransform`. Use `unpack_mesh_transform` to unpack.
+struct CompressedTransform {
+    translation: vec3<f32>,
+    // The x and y components of the rotation quaternion, packed as snorm16x2.
+    rotation_xy: u32,
+    scale: vec3<f32>,
+    // The z and w components of the rotation quaternion, packed as snorm16x2.
+    rotation_zw: u32,
+};
+#endif
+
 struct Mesh {
+#ifdef COMPRESSED_MESH_TRANSFORMS
+    model: CompressedTransform,
+    previous_model: CompressedTransform,
+#else
     // Affine 4x3 matrices transposed to 3x4
-    // Use bevy_render::maths::affine3_to_square to unpack
+    // Use unpack_mesh_transform and bevy_render::maths::affine3_to_square to unpack
     model: mat3x4<f32>,
     previous_model: mat3x4<f32>,
     // 3x3 matrix packed in mat2x4 and f32 as:
     // [0].xyz, [1].x,
     // [1].yz, [2].xy
     // [2].z
-    // Use bevy_pbr::mesh_functions::mat2x4_f32_to_mat3x3_unpack to unpack
+    // Use mesh_inverse_transpose_model to unpack
     inverse_transpose_model_a: mat2x4<f32>,
     inverse_transpose_model_b: f32,
+#endif
     // 'flags' is a bit field indicating various options. u32 is 32 bits so we have up to 32 options.
     flags: u32,
     lightmap_uv_rect: vec2<u32>,
+    // The `UvTransform` of the entity: its 2x2 matrix packed column by column,
+    // and its translation. Use mesh_functions::mesh_uv_transform to apply it.
+    uv_transform_a: vec4<f32>,
+    uv_transform_b: vec2<f32>,
+    // The index of the main world entity this mesh belongs to, used for GPU picking.
+    entity_index: u32,
+    // The `MaterialOverrides` of the entity, only valid if the matching
+    // `MESH_FLAGS_*_OVERRIDE_BIT` is set.
+    // Linear, packed as 4 f16 with pack2x16float.
+    material_override_base_color: vec2<u32>,
+    // Linear, packed as rgb9e5.
+    material_override_emissive: u32,
+    material_override_perceptual_roughness: f32,
+    // The index of the material in the remapping table of its material table
+    // in the low 24 bits, and its generation in the high 8 bits, see
+    // `MaterialBindingsIndex`.
+    material_bindings_index: u32,
 };
 
 #ifdef SKINNED
@@ -31,9 +68,90 @@ struct MorphWeights {
 
 // [2^0, 2^16)
 const MESH_FLAGS_VISIBILITY_RANGE_INDEX_BITS: u32 = 65535u;
+// 2^26
+const MESH_FLAGS_PERCEPTUAL_ROUGHNESS_OVERRIDE_BIT: u32 = 67108864u;
+// 2^27
+const MESH_FLAGS_EMISSIVE_OVERRIDE_BIT: u32 = 134217728u;
+// 2^28
+const MESH_FLAGS_BASE_COLOR_OVERRIDE_BIT: u32 = 268435456u;
 // 2^29
 const MESH_FLAGS_SHADOW_RECEIVER_BIT: u32 = 536870912u;
 // 2^30
 const MESH_FLAGS_TRANSMITTED_SHADOW_RECEIVER_BIT: u32 = 1073741824u;
 // 2^31 - if the flag is set, the sign is positive, else it is negative
 const MESH_FLAGS_SIGN_DETERMINANT_MODEL_3X3_BIT: u32 = 2147483648u;
+
+// The slot of a material whose entry in the remapping table of its material
+// table is stale or not prepared.
+const MATERIAL_TABLE_INVALID_SLOT: u32 = 4294967295u;
+
+#ifdef COMPRESSED_MESH_TRANSFORMS
+// Returns the rotation matrix of a compressed transform.
+fn compressed_transform_rotation(transform: CompressedTransform) -> mat3x3<f32> {
+    let q = normalize(vec4(
+        unpack2x16snorm(transform.rotation_xy),
+        unpack2x16snorm(transform.rotation_zw),
+    ));
+    let x2 = q.x + q.x;
+    let y2 = q.y + q.y;
+    let z2 = q.z + q.z;
+    let xx = q.x * x2;
+    let xy = q.x * y2;
+    let xz = q.x * z2;
+    let yy = q.y * y2;
+    let yz = q.y * z2;
+    let zz = q.z * z2;
+    let wx = q.w * x2;
+    let wy = q.w * y2;
+    let wz = q.w * z2;
+    return mat3x3(
+        vec3(1.0 - (yy + zz), xy + wz, xz - wy),
+        vec3(xy - wz, 1.0 - (xx + zz), yz + wx),
+        vec3(xz + wy, yz - wx, 1.0 - (xx + yy)),
+    );
+}
+
+// Unpacks the `model` or `previous_model` of a `Mesh` to an affine 4x3 matrix
+// transposed to 3x4. Use bevy_render::maths::affine3_to_square to unpack it
+// further.
+fn unpack_mesh_transform(transform: CompressedTransform) -> mat3x4<f32> {
+    let rotation = compressed_transform_rotation(transform);
+    let x = rotation[0] * transform.scale.x;
+    let y = rotation[1] * transform.scale.y;
+    let z = rotation[2] * transform.scale.z;
+    return mat3x4(
+        vec4(x.x, y.x, z.x, transform.translation.x),
+        vec4(x.y, y.y, z.y, transform.translation.y),
+        vec4(x.z, y.z, z.z, transform.translation.z),
+    );
+}
+
+// Returns the inverse transpose of the 3x3 model matrix of a `Mesh`, used to
+// transform its normals.
+fn mesh_inverse_transpose_model(mesh: Mesh) -> mat3x3<f32> {
+    // The inverse transpose of a rotation times a scale is the rotation times
+    // the inverse of the scale.
+    let rotation = compressed_transform_rotation(mesh.model);
+    return mat3x3(
+        rotation[0] / mesh.model.scale.x,
+        rotation[1] / mesh.model.scale.y,
+        rotation[2] / mesh.model.scale.z,
+    );
+}
+#else
+// Unpacks the `model` or `previous_model` of a `Mesh` to an affine 4x3 matrix
+// transposed to 3x4. Use bevy_render::maths::affine3_to_square to unpack it
+// further.
+fn unpack_mesh_transform(transform: mat3x4<f32>) -> mat3x4<f32> {
+    return transform;
+}
+
+// Returns the inverse transpose of the 3x3 model matrix of a `Mesh`, used to
+// transform its normals.
+fn mesh_inverse_transpose_model(mesh: Mesh) -> mat3x3<f32> {
+    return mat2x4_f32_to_mat3x3_unpack(
+        mesh.inverse_transpose_model_a,
+        mesh.inverse_transpose_model_b,
+    );
+}
+#endif
@@ -1,5 +1,6 @@
-use std::{array, num::NonZeroU64};
+use std::{array, fmt, marker::PhantomData, num::NonZeroU64};
 
+use bevy_app::{App, Plugin};
 use bevy_core_pipeline::{
     core_3d::ViewTransmissionTexture,
     prepass::ViewPrepassTextures,
@@ -9,8 +10,10 @@ use bevy_core_pipeline::{
 };
 use bevy_ecs::{
     component::Component,
-    entity::Entity,
-    system::{Commands, Query, Res},
+    entity::{Entity, EntityHashMap},
+    query::With,
+    schedule::IntoSystemConfigs,
+    system::{Commands, Local, Query, Res, ResMut, Resource},
 };
 use bevy_math::Vec4;
 use bevy_render::{
@@ -18,9 +21,13 @@ use bevy_render::{
     render_asset::RenderAssets,
     render_resource::{binding_types::*, *},
     renderer::RenderDevice,
-    texture::{BevyDefault, FallbackImage, FallbackImageMsaa, FallbackImageZero, GpuImage},
-    view::{Msaa, RenderVisibilityRanges, ViewUniform, ViewUniforms},
+    texture::{
+        BevyDefault, BlueNoise, FallbackImage, FallbackImageMsaa, FallbackImageZero, GpuImage,
+    },
+    view::{ExtractedView, Msaa, RenderVisibilityRanges, ViewUniform, ViewUniforms},
+    Render, RenderApp, RenderSet,
 };
+use bevy_utils::{tracing::warn, HashSet};
 
 #[cfg(all(feature = "webgl", target_arch = "wasm32", not(feature = "webgpu")))]
 use bevy_render::render_resource::binding_types::texture_cube;
@@ -41,7 +48,12 @@ use crate::{
 pub struct MeshPipelineViewLayout {
     pub bind_group_layout: BindGroupLayout,
 
-    #[cfg(debug_assertions)]
+    /// The key this layout was created with. It lacks some features of the requested key when
+    /// they were disabled to fit the texture budget of the device, see
+    /// [`MeshViewTextureBudgetReport`].
+    pub layout_key: MeshPipelineViewLayoutKey,
+
+    /// The number of sampled textures in the layout.
     pub texture_count: usize,
 }
 
@@ -59,6 +71,7 @@ bitflags::bitflags! {
         const NORMAL_PREPASS              = 1 << 2;
         const MOTION_VECTOR_PREPASS       = 1 << 3;
         const DEFERRED_PREPASS            = 1 << 4;
+        const EXTENSION_RESERVED_BITS     = ((1 << MAX_MESH_VIEW_LAYOUT_EXTENSIONS) - 1) << 5;
     }
 }
 
@@ -66,12 +79,20 @@ impl MeshPipelineViewLayoutKey {
     // The number of possible layouts
     pub const COUNT: usize = Self::all().bits() as usize + 1;
 
+    /// The bit of the view layout extension registered at `index`, see
+    /// [`MeshViewLayoutExtensionPlugin`].
+    pub fn from_extension(index: usize) -> Self {
+        debug_assert!(index < MAX_MESH_VIEW_LAYOUT_EXTENSIONS);
+        Self::from_bits_retain(1 << (5 + index))
+    }
+
     /// Builds a unique label for each layout based on the flags
     pub fn label(&self) -> String {
         use MeshPipelineViewLayoutKey as Key;
 
+        let extensions = self.intersection(Key::EXTENSION_RESERVED_BITS).bits() >> 5;
         format!(
-            "mesh_view_layout{}{}{}{}{}",
+            "mesh_view_layout{}{}{}{}{}{}",
             self.contains(Key::MULTISAMPLED)
                 .then_some("_multisampled")
                 .unwrap_or_default(),
@@ -87,6 +108,11 @@ impl MeshPipelineViewLayoutKey {
             self.contains(Key::DEFERRED_PREPASS)
                 .then_some("_deferred")
                 .unwrap_or_default(),
+            if extensions != 0 {
+                format!("_extensions{extensions}")
+            } else {
+                String::new()
+            },
         )
     }
 }
@@ -110,6 +136,11 @@ impl From<MeshPipelineKey> for MeshPipelineViewLayoutKey {
         if value.contains(MeshPipelineKey::DEFERRED_PREPASS) {
             result |= MeshPipelineViewLayoutKey::DEFERRED_PREPASS;
         }
+        for index in 0..MAX_MESH_VIEW_LAYOUT_EXTENSIONS {
+            if value.contains(MeshPipelineKey::from_view_layout_extension(index)) {
+                result |= MeshPipelineViewLayoutKey::from_extension(index);
+            }
+        }
 
         result
     }
@@ -173,6 +204,7 @@ fn layout_entries(
     visibility_ranges_buffer_binding_type: BufferBindingType,
     layout_key: MeshPipelineViewLayoutKey,
     render_device: &RenderDevice,
+    extra_bindings: &MeshViewExtraBindings,
 ) -> Vec<BindGroupLayoutEntry> {
     let mut entries = DynamicBindGroupLayoutEntries::new_with_indices(
         ShaderStages::FRAGMENT,
@@ -327,40 +359,212 @@ fn layout_entries(
         (26, sampler(SamplerBindingType::Filtering)),
     ));
 
+    // Blue noise
+    entries = entries.extend_with_indices(((
+        27,
+        texture_2d(TextureSampleType::Float { filterable: false }),
+    ),));
+
+    // Extra bindings and layout extensions
+    for (binding, layout_entry) in extra_bindings.layout_entries(layout_key, render_device) {
+        entries = entries.extend_with_indices(((binding, layout_entry),));
+    }
+
     entries.to_vec()
 }
 
 /// Generates all possible view layouts for the mesh pipeline, based on all combinations of
 /// [`MeshPipelineViewLayoutKey`] flags.
+///
+/// The layouts with more sampled textures than
+/// `wgpu::Limits::max_sampled_textures_per_shader_stage` are replaced by the layout without their
+/// lowest-priority features, see [`MeshViewTextureBudgetReport`].
 pub fn generate_view_layouts(
     render_device: &RenderDevice,
     clustered_forward_buffer_binding_type: BufferBindingType,
     visibility_ranges_buffer_binding_type: BufferBindingType,
+    extra_bindings: &MeshViewExtraBindings,
 ) -> [MeshPipelineViewLayout; MeshPipelineViewLayoutKey::COUNT] {
-    array::from_fn(|i| {
-        let key = MeshPipelineViewLayoutKey::from_bits_truncate(i as u32);
-        let entries = layout_entries(
-            clustered_forward_buffer_binding_type,
-            visibility_ranges_buffer_binding_type,
-            key,
-            render_device,
-        );
-
-        #[cfg(debug_assertions)]
-        let texture_count: usize = entries
+    let entries: [Vec<BindGroupLayoutEntry>; MeshPipelineViewLayoutKey::COUNT] =
+        array::from_fn(|i| {
+            layout_entries(
+                clustered_forward_buffer_binding_type,
+                visibility_ranges_buffer_binding_type,
+                MeshPipelineViewLayoutKey::from_bits_truncate(i as u32),
+                render_device,
+                extra_bindings,
+            )
+        });
+    let texture_counts = entries.each_ref().map(|entries| {
+        entries
             .iter()
             .filter(|entry| matches!(entry.ty, BindingType::Texture { .. }))
-            .count();
+            .map(|entry| entry.count.map_or(1, |count| count.get() as usize))
+            .sum::<usize>()
+    });
+    let max_textures = render_device.limits().max_sampled_textures_per_shader_stage as usize;
+
+    // Layouts over the budget aren't created, since that would fail validation.
+    let mut bind_group_layouts: [Option<BindGroupLayout>; MeshPipelineViewLayoutKey::COUNT] =
+        array::from_fn(|_| None);
+    array::from_fn(|i| {
+        let layout_key = fit_view_layout_texture_budget(
+            MeshPipelineViewLayoutKey::from_bits_truncate(i as u32),
+            |key| texture_counts[key.bits() as usize],
+            max_textures,
+        );
+        let index = layout_key.bits() as usize;
+        let bind_group_layout = bind_group_layouts[index]
+            .get_or_insert_with(|| {
+                render_device.create_bind_group_layout(layout_key.label().as_str(), &entries[index])
+            })
+            .clone();
 
         MeshPipelineViewLayout {
-            bind_group_layout: render_device
-                .create_bind_group_layout(key.label().as_str(), &entries),
-            #[cfg(debug_assertions)]
-            texture_count,
+            bind_group_layout,
+            layout_key,
+            texture_count: texture_counts[index],
         }
     })
 }
 
+/// Removes the lowest-priority features from `layout_key` until its layout has at most
+/// `max_textures` textures, or no feature that would remove textures is left.
+///
+/// From the lowest priority to the highest, those are the [`MeshViewLayoutExtension`]s, from the
+/// last added to the first, the motion vector prepass, the normal prepass and the depth prepass,
+/// unless the view uses the deferred prepass, which needs it.
+fn fit_view_layout_texture_budget(
+    mut layout_key: MeshPipelineViewLayoutKey,
+    texture_count: impl Fn(MeshPipelineViewLayoutKey) -> usize,
+    max_textures: usize,
+) -> MeshPipelineViewLayoutKey {
+    let features = (0..MAX_MESH_VIEW_LAYOUT_EXTENSIONS)
+        .rev()
+        .map(MeshPipelineViewLayoutKey::from_extension)
+        .chain([
+            MeshPipelineViewLayoutKey::MOTION_VECTOR_PREPASS,
+            MeshPipelineViewLayoutKey::NORMAL_PREPASS,
+            MeshPipelineViewLayoutKey::DEPTH_PREPASS,
+        ]);
+    for feature in features {
+        if texture_count(layout_key) <= max_textures {
+            break;
+        }
+        if feature == MeshPipelineViewLayoutKey::DEPTH_PREPASS
+            && layout_key.contains(MeshPipelineViewLayoutKey::DEFERRED_PREPASS)
+        {
+            continue;
+        }
+        let reduced_key = layout_key - feature;
+        if texture_count(reduced_key) < texture_count(layout_key) {
+            layout_key = reduced_key;
+        }
+    }
+    layout_key
+}
+
+/// A feature that can be disabled from a mesh view layout to fit the texture budget of the
+/// device, see [`MeshViewTextureBudgetReport`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MeshViewLayoutFeature {
+    /// The depth prepass texture.
+    DepthPrepass,
+    /// The normal prepass texture.
+    NormalPrepass,
+    /// The motion vector prepass texture.
+    MotionVectorPrepass,
+    /// The [`MeshViewLayoutExtension`] with this [`MeshViewLayoutExtension::SHADER_DEF`].
+    Extension(&'static str),
+}
+
+/// The features disabled from the mesh view layout of a view because it had more sampled textures
+/// than `wgpu::Limits::max_sampled_textures_per_shader_stage`, which is as low as 16 on WebGL 2.
+///
+/// The disabled features are only left out of the mesh view bind group: the prepasses still run
+/// and effects that read their textures, like SSAO or TAA, still work, but the mesh shaders of the
+/// view can't read them. A warning with this report is logged the first time a view uses such a
+/// layout.
+#[derive(Clone, Debug)]
+pub struct MeshViewTextureBudgetReport {
+    /// The key of the layout the view requested.
+    pub requested_layout_key: MeshPipelineViewLayoutKey,
+    /// The key of the layout used instead.
+    pub layout_key: MeshPipelineViewLayoutKey,
+    /// The number of sampled textures of the layout used.
+    pub texture_count: usize,
+    /// The maximum number of sampled textures supported by the device.
+    pub max_textures: usize,
+    /// The features disabled from the requested layout, from the lowest priority to the highest.
+    pub disabled_features: Vec<MeshViewLayoutFeature>,
+}
+
+impl MeshViewTextureBudgetReport {
+    /// The report of the layout requested with `requested_layout_key`, or `None` if no feature
+    /// was disabled from it.
+    pub fn new(
+        requested_layout_key: MeshPipelineViewLayoutKey,
+        layout: &MeshPipelineViewLayout,
+        max_textures: usize,
+        extra_bindings: &MeshViewExtraBindings,
+    ) -> Option<Self> {
+        let disabled = requested_layout_key - layout.layout_key;
+        if disabled.is_empty() {
+            return None;
+        }
+
+        let extensions = extra_bindings
+            .extensions
+            .iter()
+            .enumerate()
+            .rev()
+            .filter(|(index, _)| {
+                disabled.contains(MeshPipelineViewLayoutKey::from_extension(*index))
+            })
+            .map(|(_, extension)| MeshViewLayoutFeature::Extension(extension.shader_def));
+        let prepasses = [
+            (
+                MeshPipelineViewLayoutKey::MOTION_VECTOR_PREPASS,
+                MeshViewLayoutFeature::MotionVectorPrepass,
+            ),
+            (
+                MeshPipelineViewLayoutKey::NORMAL_PREPASS,
+                MeshViewLayoutFeature::NormalPrepass,
+            ),
+            (
+                MeshPipelineViewLayoutKey::DEPTH_PREPASS,
+                MeshViewLayoutFeature::DepthPrepass,
+            ),
+        ]
+        .into_iter()
+        .filter(|(key, _)| disabled.contains(*key))
+        .map(|(_, feature)| feature);
+
+        Some(Self {
+            requested_layout_key,
+            layout_key: layout.layout_key,
+            texture_count: layout.texture_count,
+            max_textures,
+            disabled_features: extensions.chain(prepasses).collect(),
+        })
+    }
+}
+
+impl fmt::Display for MeshViewTextureBudgetReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "The mesh view layout `{}` has more sampled textures than the {} supported by this \
+            device, disabled {:?} from it to use `{}`, with {} textures",
+            self.requested_layout_key.label(),
+            self.max_textures,
+            self.disabled_features,
+            self.layout_key.label(),
+            self.texture_count,
+        )
+    }
+}
+
 #[derive(Component)]
 pub struct MeshViewBindGroup {
     pub value: BindGroup,
@@ -398,6 +602,11 @@ pub fn prepare_mesh_view_bind_groups(
     tonemapping_luts: Res<TonemappingLuts>,
     light_probes_buffer: Res<LightProbesBuffer>,
     visibility_ranges: Res<RenderVisibilityRanges>,
+    (blue_noise, extra_bindings, mut reported_layouts): (
+        Res<BlueNoise>,
+        Res<MeshViewExtraBindings>,
+        Local<HashSet<MeshPipelineViewLayoutKey>>,
+    ),
 ) {
     if let (
         Some(view_binding),
@@ -407,6 +616,7 @@ pub fn prepare_mesh_view_bind_groups(
         Some(fog_binding),
         Some(light_probes_binding),
         Some(visibility_ranges_buffer),
+        Some(extra_binding_resources),
     ) = (
         view_uniforms.uniforms.binding(),
         light_meta.view_gpu_lights.binding(),
@@ -415,6 +625,7 @@ pub fn prepare_mesh_view_bind_groups(
         fog_meta.gpu_fogs.binding(),
         light_probes_buffer.binding(),
         visibility_ranges.buffer().buffer(),
+        extra_bindings.bindings(),
     ) {
         for (
             entity,
@@ -428,6 +639,26 @@ pub fn prepare_mesh_view_bind_groups(
             render_view_irradiance_volumes,
         ) in &views
         {
+            let requested_layout_key = MeshPipelineViewLayoutKey::from(*msaa)
+                | MeshPipelineViewLayoutKey::from(prepass_textures)
+                | MeshPipelineViewLayoutKey::from(extra_bindings.view_key(entity));
+            let view_layout = mesh_pipeline.view_layout(requested_layout_key);
+            let layout_key = view_layout.layout_key;
+            if layout_key != requested_layout_key && reported_layouts.insert(requested_layout_key) {
+                if let Some(report) = MeshViewTextureBudgetReport::new(
+                    requested_layout_key,
+                    view_layout,
+                    render_device.limits().max_sampled_textures_per_shader_stage as usize,
+                    &extra_bindings,
+                ) {
+                    warn!("{report}");
+                }
+            }
+
+            let Some(extension_bindings) = extra_bindings.view_bindings(entity, layout_key) else {
+                continue;
+            };
+
             let fallback_ssao = fallback_images
                 .image_for_samplecount(1, TextureFormat::bevy_default())
                 .texture_view
@@ -436,11 +667,6 @@ pub fn prepare_mesh_view_bind_groups(
                 .map(|t| &t.screen_space_ambient_occlusion_texture.default_view)
                 .unwrap_or(&fallback_ssao);
 
-            let layout = &mesh_pipeline.get_view_layout(
-                MeshPipelineViewLayoutKey::from(*msaa)
-                    | MeshPipelineViewLayoutKey::from(prepass_textures),
-            );
-
             let mut entries = DynamicBindGroupEntries::new_with_indices((
                 (0, view_binding.clone()),
                 (1, light_binding.clone()),
@@ -531,7 +757,15 @@ pub fn prepare_mesh_view_bind_groups(
                     .iter()
                     .map(Option::as_ref)
                     .zip([21, 22, 23, 24])
-                    .flat_map(|(b, i)| b.map(|b| (b, i)))
+                    .zip([
+                        MeshPipelineViewLayoutKey::DEPTH_PREPASS,
+                        MeshPipelineViewLayoutKey::NORMAL_PREPASS,
+                        MeshPipelineViewLayoutKey::MOTION_VECTOR_PREPASS,
+                        MeshPipelineViewLayoutKey::DEFERRED_PREPASS,
+                    ])
+                    // Skip the textures disabled to fit the texture budget
+                    .filter(|(_, key)| layout_key.contains(*key))
+                    .flat_map(|((b, i), _)| b.map(|b| (b, i)))
                 {
                     entries = entries.extend_with_indices(((index, binding),));
                 }
@@ -548,9 +782,426 @@ pub fn prepare_mesh_view_bind_groups(
             entries =
                 entries.extend_with_indices(((25, transmission_view), (26, transmission_sampler)));
 
+            entries = entries.extend_with_indices(((27, &blue_noise.texture_view),));
+
+            for (binding, extra_binding) in
+                (MESH_VIEW_EXTRA_BINDINGS_START..).zip(extra_binding_resources.iter().cloned())
+            {
+                entries = entries.extend_with_indices(((binding, extra_binding),));
+            }
+            for (binding, extension_binding) in extension_bindings {
+                entries = entries.extend_with_indices(((binding, extension_binding),));
+            }
+
             commands.entity(entity).insert(MeshViewBindGroup {
-                value: render_device.create_bind_group("mesh_view_bind_group", layout, &entries),
+                value: render_device.create_bind_group(
+                    "mesh_view_bind_group",
+                    &view_layout.bind_group_layout,
+                    &entries,
+                ),
             });
         }
     }
 }
+
+/// The binding of the first of the [`MeshViewExtraBindings`] in the mesh view bind group. The
+/// following ones take the next bindings, in the order their [`MeshViewBindingPlugin`]s are
+/// added.
+pub const MESH_VIEW_EXTRA_BINDINGS_START: u32 = 28;
+
+/// Custom data bound to the mesh view bind group (`@group(0)`), added with a
+/// [`MeshViewBindingPlugin`].
+///
+/// This is meant for global data that any material or mesh shader may read, such as weather, wind
+/// or a custom time, without adding it to the bind group of every material.
+pub trait MeshViewBinding: Resource {
+    /// The shader def defined in the mesh pipelines, with the binding of this data as value.
+    ///
+    /// The shaders declare the binding in the view bind group with it:
+    ///
+    /// ```wgsl
+    /// ##ifdef WEATHER_BINDING
+    /// @group(0) @binding(#{WEATHER_BINDING}) var<uniform> weather: Weather;
+    /// ##endif
+    /// ```
+    const SHADER_DEF: &'static str;
+
+    /// The layout of the binding. Its visibility is [`ShaderStages::FRAGMENT`] unless the entry
+    /// sets another one.
+    ///
+    /// Textures count towards the textures of the mesh view bind group, which is limited by
+    /// `wgpu::Limits::max_sampled_textures_per_shader_stage`.
+    fn layout_entry(render_device: &RenderDevice) -> BindGroupLayoutEntryBuilder;
+
+    /// The resource bound this frame.
+    ///
+    /// While this returns `None`, or the resource doesn't exist in the render world, the mesh view
+    /// bind groups aren't created and meshes aren't drawn.
+    fn binding(&self) -> Option<OwnedBindingResource>;
+}
+
+/// Adds the render world resource `T` to the mesh view bind group, see [`MeshViewBinding`].
+///
+/// This must be added before the [`PbrPlugin`](crate::PbrPlugin) is finished, since the view
+/// layouts are created then.
+pub struct MeshViewBindingPlugin<T: MeshViewBinding>(PhantomData<T>);
+
+impl<T: MeshViewBinding> Default for MeshViewBindingPlugin<T> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<T: MeshViewBinding> Plugin for MeshViewBindingPlugin<T> {
+    fn build(&self, app: &mut App) {
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app
+            .init_resource::<MeshViewExtraBindings>()
+            .add_systems(
+                Render,
+                prepare_mesh_view_extra_binding::<T>
+                    .in_set(RenderSet::PrepareBindGroups)
+                    .before(prepare_mesh_view_bind_groups),
+            );
+
+        let mut extra_bindings = render_app
+            .world_mut()
+            .resource_mut::<MeshViewExtraBindings>();
+        assert!(
+            extra_bindings
+                .bindings
+                .iter()
+                .all(|binding| binding.shader_def != T::SHADER_DEF),
+            "The mesh view binding shader def `{}` is used by another binding",
+            T::SHADER_DEF
+        );
+        extra_bindings.bindings.push(MeshViewExtraBinding {
+            shader_def: T::SHADER_DEF,
+            layout_entry: T::layout_entry,
+            resource: None,
+        });
+    }
+}
+
+/// The maximum number of [`MeshViewLayoutExtension`]s, each of which takes one bit of the
+/// [`MeshPipelineViewLayoutKey`] and of the [`MeshPipelineKey`].
+pub const MAX_MESH_VIEW_LAYOUT_EXTENSIONS: usize = 2;
+
+/// A view feature that adds bindings to the mesh view bind group (`@group(0)`) of the views that
+/// have this component in the render world, added with a [`MeshViewLayoutExtensionPlugin`].
+///
+/// Unlike a [`MeshViewBinding`], which is bound for every view, the extension gets its own bit in
+/// the [`MeshPipelineViewLayoutKey`], so views without it keep the smaller layouts. This suits
+/// per-view data such as volumetric fog froxels or custom light clusters. The mesh pipelines of
+/// the views with the extension are specialized with [`MeshViewLayoutExtension::SHADER_DEF`].
+pub trait MeshViewLayoutExtension: Component {
+    /// The shader def defined in the mesh pipelines of the views with this extension.
+    const SHADER_DEF: &'static str;
+
+    /// The shader defs defined with the binding of each of the
+    /// [`MeshViewLayoutExtension::layout_entries`] as value, in the same order.
+    ///
+    /// ```wgsl
+    /// ##ifdef FROXELS
+    /// @group(0) @binding(#{FROXELS_TEXTURE_BINDING}) var froxels_texture: texture_3d<f32>;
+    /// @group(0) @binding(#{FROXELS_SAMPLER_BINDING}) var froxels_sampler: sampler;
+    /// ##endif
+    /// ```
+    const BINDING_SHADER_DEFS: &'static [&'static str];
+
+    /// The layout of the bindings. Their visibility is [`ShaderStages::FRAGMENT`] unless an entry
+    /// sets another one.
+    fn layout_entries(render_device: &RenderDevice) -> Vec<BindGroupLayoutEntryBuilder>;
+
+    /// The resources bound for this view this frame, in the order of the
+    /// [`MeshViewLayoutExtension::layout_entries`].
+    ///
+    /// While this returns `None`, the mesh view bind group of this view isn't created and its
+    /// meshes aren't drawn.
+    fn bindings(&self) -> Option<Vec<OwnedBindingResource>>;
+}
+
+/// Adds the [`MeshViewLayoutExtension`] `T` to the mesh view layouts.
+///
+/// This must be added before the [`PbrPlugin`](crate::PbrPlugin) is finished, since the view
+/// layouts are created then. At most [`MAX_MESH_VIEW_LAYOUT_EXTENSIONS`] extensions can be added.
+pub struct MeshViewLayoutExtensionPlugin<T: MeshViewLayoutExtension>(PhantomData<T>);
+
+impl<T: MeshViewLayoutExtension> Default for MeshViewLayoutExtensionPlugin<T> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<T: MeshViewLayoutExtension> Plugin for MeshViewLayoutExtensionPlugin<T> {
+    fn build(&self, app: &mut App) {
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app
+            .init_resource::<MeshViewExtraBindings>()
+            .add_systems(
+                Render,
+                (
+                    mark_mesh_view_layout_extension::<T>.in_set(RenderSet::ManageViews),
+                    prepare_mesh_view_layout_extension::<T>
+                        .in_set(RenderSet::PrepareBindGroups)
+                        .before(prepare_mesh_view_bind_groups),
+                ),
+            );
+
+        let mut extra_bindings = render_app
+            .world_mut()
+            .resource_mut::<MeshViewExtraBindings>();
+        assert!(
+            extra_bindings.extensions.len() < MAX_MESH_VIEW_LAYOUT_EXTENSIONS,
+            "Can't add the mesh view layout extension `{}`, the maximum is {}",
+            T::SHADER_DEF,
+            MAX_MESH_VIEW_LAYOUT_EXTENSIONS
+        );
+        assert!(
+            extra_bindings
+                .extensions
+                .iter()
+                .all(|extension| extension.shader_def != T::SHADER_DEF),
+            "The mesh view layout extension shader def `{}` is used by another extension",
+            T::SHADER_DEF
+        );
+        extra_bindings
+            .extensions
+            .push(MeshViewLayoutExtensionEntry {
+                shader_def: T::SHADER_DEF,
+                binding_shader_defs: T::BINDING_SHADER_DEFS,
+                layout_entries: T::layout_entries,
+                views: EntityHashMap::default(),
+            });
+    }
+}
+
+/// The [`MeshViewBinding`]s appended to the mesh view bind group, in the order they were added,
+/// followed by the [`MeshViewLayoutExtension`]s.
+#[derive(Resource, Default)]
+pub struct MeshViewExtraBindings {
+    bindings: Vec<MeshViewExtraBinding>,
+    extensions: Vec<MeshViewLayoutExtensionEntry>,
+}
+
+struct MeshViewExtraBinding {
+    shader_def: &'static str,
+    layout_entry: fn(&RenderDevice) -> BindGroupLayoutEntryBuilder,
+    resource: Option<OwnedBindingResource>,
+}
+
+struct MeshViewLayoutExtensionEntry {
+    shader_def: &'static str,
+    binding_shader_defs: &'static [&'static str],
+    layout_entries: fn(&RenderDevice) -> Vec<BindGroupLayoutEntryBuilder>,
+    /// The views with the extension this frame, and their resources once prepared.
+    views: EntityHashMap<Option<Vec<OwnedBindingResource>>>,
+}
+
+impl MeshViewExtraBindings {
+    /// The shader defs of the bindings and of the extensions in the view layout of `layout_key`.
+    /// The bindings are defined with their binding in the mesh view bind group as value.
+    pub fn shader_defs(
+        &self,
+        layout_key: MeshPipelineViewLayoutKey,
+    ) -> impl Iterator<Item = ShaderDefVal> + '_ {
+        let bindings = (MESH_VIEW_EXTRA_BINDINGS_START..).zip(&self.bindings).map(
+            |(binding, extra_binding)| ShaderDefVal::UInt(extra_binding.shader_def.into(), binding),
+        );
+        let extensions = self
+            .extension_bindings()
+            .filter(move |(index, _, _)| {
+                layout_key.contains(MeshPipelineViewLayoutKey::from_extension(*index))
+            })
+            .flat_map(|(_, first_binding, extension)| {
+                std::iter::once(ShaderDefVal::from(extension.shader_def)).chain(
+                    (first_binding..).zip(extension.binding_shader_defs).map(
+                        |(binding, shader_def)| ShaderDefVal::UInt((*shader_def).into(), binding),
+                    ),
+                )
+            });
+        bindings.chain(extensions)
+    }
+
+    /// The layout entries of the bindings and of the extensions in the view layout of
+    /// `layout_key`, with their binding in the mesh view bind group.
+    pub fn layout_entries(
+        &self,
+        layout_key: MeshPipelineViewLayoutKey,
+        render_device: &RenderDevice,
+    ) -> Vec<(u32, BindGroupLayoutEntryBuilder)> {
+        let mut entries: Vec<_> = (MESH_VIEW_EXTRA_BINDINGS_START..)
+            .zip(&self.bindings)
+            .map(|(binding, extra_binding)| (binding, (extra_binding.layout_entry)(render_device)))
+            .collect();
+        for (index, first_binding, extension) in self.extension_bindings() {
+            if layout_key.contains(MeshPipelineViewLayoutKey::from_extension(index)) {
+                entries.extend((first_binding..).zip((extension.layout_entries)(render_device)));
+            }
+        }
+        entries
+    }
+
+    /// The extensions with their index and the binding of their first entry.
+    fn extension_bindings(
+        &self,
+    ) -> impl Iterator<Item = (usize, u32, &MeshViewLayoutExtensionEntry)> + '_ {
+        let first_binding = MESH_VIEW_EXTRA_BINDINGS_START + self.bindings.len() as u32;
+        self.extensions
+            .iter()
+            .enumerate()
+            .scan(first_binding, |binding, (index, extension)| {
+                let first_binding = *binding;
+                *binding += extension.binding_shader_defs.len() as u32;
+                Some((index, first_binding, extension))
+            })
+    }
+
+    /// The number of textures the bindings add to the mesh view bind group.
+    #[cfg(debug_assertions)]
+    pub(crate) fn texture_count(&self, render_device: &RenderDevice) -> usize {
+        self.layout_entries(
+            MeshPipelineViewLayoutKey::EXTENSION_RESERVED_BITS,
+            render_device,
+        )
+        .into_iter()
+        .map(|(binding, entry)| entry.build(binding, ShaderStages::FRAGMENT))
+        .filter(|entry| matches!(entry.ty, BindingType::Texture { .. }))
+        .count()
+    }
+
+    /// The resources bound this frame, or `None` if one of them isn't ready.
+    pub fn bindings(&self) -> Option<Vec<BindingResource<'_>>> {
+        self.bindings
+            .iter()
+            .map(|extra_binding| {
+                extra_binding
+                    .resource
+                    .as_ref()
+                    .map(OwnedBindingResource::get_binding)
+            })
+            .collect()
+    }
+
+    /// The bits of the extensions of the view `entity`, to add to the keys of the mesh pipelines
+    /// it's rendered with.
+    pub fn view_key(&self, entity: Entity) -> MeshPipelineKey {
+        self.extensions
+            .iter()
+            .enumerate()
+            .filter(|(_, extension)| extension.views.contains_key(&entity))
+            .fold(MeshPipelineKey::NONE, |key, (index, _)| {
+                key | MeshPipelineKey::from_view_layout_extension(index)
+            })
+    }
+
+    /// The resources of the extensions of the view `entity` in the view layout of `layout_key`
+    /// bound this frame, with their binding, or `None` if one of them isn't ready.
+    pub fn view_bindings(
+        &self,
+        entity: Entity,
+        layout_key: MeshPipelineViewLayoutKey,
+    ) -> Option<Vec<(u32, BindingResource<'_>)>> {
+        let mut bindings = Vec::new();
+        for (index, first_binding, extension) in self.extension_bindings() {
+            if !layout_key.contains(MeshPipelineViewLayoutKey::from_extension(index)) {
+                continue;
+            }
+            let Some(resources) = extension.views.get(&entity) else {
+                continue;
+            };
+            let resources = resources.as_ref()?;
+            if resources.len() != extension.binding_shader_defs.len() {
+                return None;
+            }
+            bindings.extend(
+                (first_binding..).zip(resources.iter().map(OwnedBindingResource::get_binding)),
+            );
+        }
+        Some(bindings)
+    }
+
+    fn extension_mut(&mut self, shader_def: &str) -> Option<&mut MeshViewLayoutExtensionEntry> {
+        self.extensions
+            .iter_mut()
+            .find(|extension| extension.shader_def == shader_def)
+    }
+}
+
+fn prepare_mesh_view_extra_binding<T: MeshViewBinding>(
+    binding: Option<Res<T>>,
+    mut extra_bindings: ResMut<MeshViewExtraBindings>,
+) {
+    if let Some(extra_binding) = extra_bindings
+        .bindings
+        .iter_mut()
+        .find(|extra_binding| extra_binding.shader_def == T::SHADER_DEF)
+    {
+        extra_binding.resource = binding.and_then(|binding| binding.binding());
+    }
+}
+
+fn mark_mesh_view_layout_extension<T: MeshViewLayoutExtension>(
+    views: Query<Entity, (With<ExtractedView>, With<T>)>,
+    mut extra_bindings: ResMut<MeshViewExtraBindings>,
+) {
+    if let Some(extension) = extra_bindings.extension_mut(T::SHADER_DEF) {
+        extension.views.clear();
+        extension
+            .views
+            .extend(views.iter().map(|entity| (entity, None)));
+    }
+}
+
+fn prepare_mesh_view_layout_extension<T: MeshViewLayoutExtension>(
+    views: Query<(Entity, &T)>,
+    mut extra_bindings: ResMut<MeshViewExtraBindings>,
+) {
+    if let Some(extension) = extra_bindings.extension_mut(T::SHADER_DEF) {
+        for (entity, view_extension) in &views {
+            if let Some(resources) = extension.views.get_mut(&entity) {
+                *resources = view_extension.bindings();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fit_view_layout_texture_budget, MeshPipelineViewLayoutKey as Key};
+
+    #[test]
+    fn fit_view_layout_texture_budget_disables_lowest_priority_features() {
+        // One texture per prepass and per extension, on top of 10 base textures.
+        let texture_count = |key: Key| {
+            10 + (key
+                & (Key::DEPTH_PREPASS
+                    | Key::NORMAL_PREPASS
+                    | Key::MOTION_VECTOR_PREPASS
+                    | Key::DEFERRED_PREPASS
+                    | Key::EXTENSION_RESERVED_BITS))
+                .bits()
+                .count_ones() as usize
+        };
+        let all_prepasses = Key::DEPTH_PREPASS | Key::NORMAL_PREPASS | Key::MOTION_VECTOR_PREPASS;
+
+        let key = all_prepasses | Key::from_extension(0) | Key::from_extension(1);
+        assert_eq!(fit_view_layout_texture_budget(key, texture_count, 15), key);
+        assert_eq!(
+            fit_view_layout_texture_budget(key, texture_count, 14),
+            all_prepasses | Key::from_extension(0)
+        );
+        assert_eq!(
+            fit_view_layout_texture_budget(key, texture_count, 12),
+            Key::DEPTH_PREPASS | Key::NORMAL_PREPASS
+        );
+
+        // The deferred prepass needs the depth prepass.
+        let key = Key::DEPTH_PREPASS | Key::DEFERRED_PREPASS;
+        assert_eq!(fit_view_layout_texture_budget(key, texture_count, 10), key);
+    }
+}
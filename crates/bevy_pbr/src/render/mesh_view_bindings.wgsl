@@ -21,6 +21,25 @@
 #endif
 @group(0) @binding(5) var directional_shadow_textures_sampler: sampler_comparison;
 
+#ifdef COLORED_SHADOWS
+#ifdef NO_CUBE_ARRAY_TEXTURES_SUPPORT
+@group(0) @binding(#{COLORED_SHADOWS_POINT_TEXTURES_BINDING}) var colored_point_shadow_textures: texture_cube<f32>;
+#else
+@group(0) @binding(#{COLORED_SHADOWS_POINT_TEXTURES_BINDING}) var colored_point_shadow_textures: texture_cube_array<f32>;
+#endif
+#ifdef NO_ARRAY_TEXTURES_SUPPORT
+@group(0) @binding(#{COLORED_SHADOWS_DIRECTIONAL_TEXTURES_BINDING}) var colored_directional_shadow_textures: texture_2d<f32>;
+#else
+@group(0) @binding(#{COLORED_SHADOWS_DIRECTIONAL_TEXTURES_BINDING}) var colored_directional_shadow_textures: texture_2d_array<f32>;
+#endif
+@group(0) @binding(#{COLORED_SHADOWS_SAMPLER_BINDING}) var colored_shadow_textures_sampler: sampler;
+#endif
+
+#ifdef DIRECTIONAL_LIGHT_COOKIE
+@group(0) @binding(#{DIRECTIONAL_LIGHT_COOKIE_TEXTURE_BINDING}) var directional_light_cookie_texture: texture_2d<f32>;
+@group(0) @binding(#{DIRECTIONAL_LIGHT_COOKIE_SAMPLER_BINDING}) var directional_light_cookie_sampler: sampler;
+#endif
+
 #if AVAILABLE_STORAGE_BUFFER_BINDINGS >= 3
 @group(0) @binding(6) var<storage> point_lights: types::PointLights;
 @group(0) @binding(7) var<storage> cluster_light_index_lists: types::ClusterLightIndexLists;
@@ -96,3 +115,12 @@
 
 @group(0) @binding(25) var view_transmission_texture: texture_2d<f32>;
 @group(0) @binding(26) var view_transmission_sampler: sampler;
+
+@group(0) @binding(27) var blue_noise_texture: texture_2d<f32>;
+
+#ifdef WETNESS_BINDING
+@group(0) @binding(#{WETNESS_BINDING}) var<uniform> wetness: types::Wetness;
+@group(0) @binding(#{WETNESS_WORLD_MASK_BINDING}) var wetness_world_mask_texture: texture_2d<f32>;
+@group(0) @binding(#{WETNESS_SCREEN_MASK_BINDING}) var wetness_screen_mask_texture: texture_2d<f32>;
+@group(0) @binding(#{WETNESS_MASK_SAMPLER_BINDING}) var wetness_mask_sampler: sampler;
+#endif
@@ -15,6 +15,7 @@ struct PointLight {
 
 const POINT_LIGHT_FLAGS_SHADOWS_ENABLED_BIT: u32   = 1u;
 const POINT_LIGHT_FLAGS_SPOT_LIGHT_Y_NEGATIVE: u32 = 2u;
+const POINT_LIGHT_FLAGS_AUTO_SHADOW_BIAS: u32      = 4u;
 
 struct DirectionalCascade {
     view_projection: mat4x4<f32>,
@@ -34,9 +35,18 @@ struct DirectionalLight {
     cascades_overlap_proportion: f32,
     depth_texture_base_index: u32,
     render_layers: u32,
+    // 0.0 if the light has no cookie
+    cookie_strength: f32,
+    // The world y coordinate of the plane the cookie is projected from
+    cookie_height: f32,
+    // xy: scale from the plane coordinates to the cookie uvs, zw: scroll offset of the uvs
+    cookie_uv_transform: vec4<f32>,
 };
 
 const DIRECTIONAL_LIGHT_FLAGS_SHADOWS_ENABLED_BIT: u32 = 1u;
+const DIRECTIONAL_LIGHT_FLAGS_AUTO_SHADOW_BIAS: u32    = 2u;
+const DIRECTIONAL_LIGHT_FLAGS_CASCADE_BLEND_SMOOTH: u32   = 4u;
+const DIRECTIONAL_LIGHT_FLAGS_CASCADE_BLEND_DITHERED: u32 = 8u;
 
 struct Lights {
     // NOTE: this array size must be kept in sync with the constants defined in bevy_pbr/src/render/light.rs
@@ -61,6 +71,15 @@ struct Lights {
     environment_map_intensity: f32,
 };
 
+struct Wetness {
+    wetness: f32,
+    darkening: f32,
+    wet_roughness: f32,
+    puddles: f32,
+    // Maps the world x and z coordinates to the world mask uvs: xy is the scale, zw the offset.
+    world_mask_transform: vec4<f32>,
+};
+
 struct Fog {
     base_color: vec4<f32>,
     directional_light_color: vec4<f32>,
@@ -117,6 +136,13 @@ struct LightProbe {
     inverse_transpose_transform: mat3x4<f32>,
     cubemap_index: i32,
     intensity: f32,
+    // 0 if the reflections aren't parallax corrected, 1 for a box proxy and 2
+    // for a sphere proxy.
+    parallax_correction_proxy: u32,
+    // The fraction of the half extents over which the reflections fade out.
+    influence_falloff: f32,
+    // The center of the light probe in world space.
+    center: vec3<f32>,
 };
 
 struct LightProbes {
@@ -1,16 +1,20 @@
 mod fog;
 mod gpu_preprocess;
+mod gpu_sort;
 mod light;
 pub(crate) mod mesh;
 mod mesh_bindings;
 mod mesh_view_bindings;
 mod morph;
 mod skin;
+mod vertex_pulling;
 
 pub use fog::*;
 pub use gpu_preprocess::*;
+pub use gpu_sort::*;
 pub use light::*;
 pub use mesh::*;
 pub use mesh_bindings::MeshLayouts;
 pub use mesh_view_bindings::*;
 pub use skin::{extract_skins, prepare_skins, SkinIndex, SkinUniform, MAX_JOINTS};
+pub use vertex_pulling::*;
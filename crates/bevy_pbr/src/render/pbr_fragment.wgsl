@@ -7,11 +7,27 @@
     prepass_utils,
     lighting,
     mesh_bindings::mesh,
+    mesh_functions,
     mesh_view_bindings::view,
     parallax_mapping::parallaxed_uv,
     lightmap::lightmap,
+    mesh_types::{
+        MESH_FLAGS_EMISSIVE_OVERRIDE_BIT,
+        MESH_FLAGS_PERCEPTUAL_ROUGHNESS_OVERRIDE_BIT,
+        unpack_mesh_transform,
+    },
+    rgb9e5::rgb9e5_to_vec3_,
 }
 
+#ifdef ALPHA_HASHED
+#import bevy_render::blue_noise::blue_noise
+#ifdef PREPASS_PIPELINE
+#import bevy_pbr::prepass_bindings
+#else
+#import bevy_pbr::mesh_view_bindings as view_bindings
+#endif
+#endif
+
 #ifdef SCREEN_SPACE_AMBIENT_OCCLUSION
 #import bevy_pbr::mesh_view_bindings::screen_space_ambient_occlusion_texture
 #import bevy_pbr::gtao_utils::gtao_multibounce
@@ -45,7 +61,9 @@ fn pbr_input_from_vertex_output(
     pbr_input.world_position = in.world_position;
 
 #ifdef VERTEX_COLORS
+#ifndef VERTEX_COLORS_OCCLUSION
     pbr_input.material.base_color = in.color;
+#endif
 #endif
 
     pbr_input.world_normal = pbr_functions::prepare_world_normal(
@@ -73,7 +91,14 @@ fn pbr_input_from_standard_material(
 
     var pbr_input: pbr_types::PbrInput = pbr_input_from_vertex_output(in, is_front, double_sided);
     pbr_input.material.flags = pbr_bindings::material.flags;
-    pbr_input.material.base_color *= pbr_bindings::material.base_color;
+
+    // `MaterialOverrides` of the entity replace the factors of the material.
+    let mesh_flags = mesh[in.instance_index].flags;
+    let base_color = mesh_functions::mesh_base_color(in.instance_index, pbr_bindings::material.base_color);
+
+#ifndef VERTEX_COLORS_REPLACE
+    pbr_input.material.base_color *= base_color;
+#endif
     pbr_input.material.deferred_lighting_pass_id = pbr_bindings::material.deferred_lighting_pass_id;
 
     // Neubelt and Pettineo 2013, "Crafting a Next-gen Material Pipeline for The Order: 1886"
@@ -84,6 +109,7 @@ fn pbr_input_from_standard_material(
     var uv = (uv_transform * vec3(in.uv, 1.0)).xy;
 
 #ifdef VERTEX_TANGENTS
+#ifndef STANDARD_MATERIAL_NO_PARALLAX_MAPPING
     if ((pbr_bindings::material.flags & pbr_types::STANDARD_MATERIAL_FLAGS_DEPTH_MAP_BIT) != 0u) {
         let V = pbr_input.V;
         let N = in.world_normal;
@@ -102,6 +128,7 @@ fn pbr_input_from_standard_material(
             -Vt,
         );
     }
+#endif // STANDARD_MATERIAL_NO_PARALLAX_MAPPING
 #endif // VERTEX_TANGENTS
 
     if ((pbr_bindings::material.flags & pbr_types::STANDARD_MATERIAL_FLAGS_BASE_COLOR_TEXTURE_BIT) != 0u) {
@@ -127,6 +154,28 @@ fn pbr_input_from_standard_material(
     }
 #endif // VERTEX_UVS
 
+#ifdef ALPHA_HASHED
+    // Reduce the alpha to fully opaque or fully transparent with a per-pixel
+    // threshold, matching `prepass_alpha_discard`.
+    if (pbr_bindings::material.flags & pbr_types::STANDARD_MATERIAL_FLAGS_ALPHA_MODE_RESERVED_BITS) ==
+            pbr_types::STANDARD_MATERIAL_FLAGS_ALPHA_MODE_HASHED {
+#ifdef PREPASS_PIPELINE
+        let threshold = blue_noise(
+            prepass_bindings::blue_noise_texture,
+            vec2<u32>(in.position.xy),
+            prepass_bindings::globals.frame_count,
+        ).x;
+#else
+        let threshold = blue_noise(
+            view_bindings::blue_noise_texture,
+            vec2<u32>(in.position.xy),
+            view_bindings::globals.frame_count,
+        ).x;
+#endif
+        pbr_input.material.base_color.a = select(0.0, 1.0, pbr_input.material.base_color.a > threshold);
+    }
+#endif // ALPHA_HASHED
+
     pbr_input.material.flags = pbr_bindings::material.flags;
 
     // NOTE: Unlit bit not set means == 0 is true, so the true case is if lit
@@ -140,6 +189,9 @@ fn pbr_input_from_standard_material(
         // emissive
         // TODO use .a for exposure compensation in HDR
         var emissive: vec4<f32> = pbr_bindings::material.emissive;
+        if ((mesh_flags & MESH_FLAGS_EMISSIVE_OVERRIDE_BIT) != 0u) {
+            emissive = vec4(rgb9e5_to_vec3_(mesh[in.instance_index].material_override_emissive), 1.0);
+        }
 #ifdef VERTEX_UVS
         if ((pbr_bindings::material.flags & pbr_types::STANDARD_MATERIAL_FLAGS_EMISSIVE_TEXTURE_BIT) != 0u) {
 #ifdef MESHLET_MESH_MATERIAL_PASS
@@ -154,6 +206,9 @@ fn pbr_input_from_standard_material(
         // metallic and perceptual roughness
         var metallic: f32 = pbr_bindings::material.metallic;
         var perceptual_roughness: f32 = pbr_bindings::material.perceptual_roughness;
+        if ((mesh_flags & MESH_FLAGS_PERCEPTUAL_ROUGHNESS_OVERRIDE_BIT) != 0u) {
+            perceptual_roughness = mesh[in.instance_index].material_override_perceptual_roughness;
+        }
         let roughness = lighting::perceptualRoughnessToRoughness(perceptual_roughness);
 #ifdef VERTEX_UVS
         if ((pbr_bindings::material.flags & pbr_types::STANDARD_MATERIAL_FLAGS_METALLIC_ROUGHNESS_TEXTURE_BIT) != 0u) {
@@ -196,7 +251,7 @@ fn pbr_input_from_standard_material(
         // TODO: Meshlet support
 #ifndef MESHLET_MESH_MATERIAL_PASS
         thickness *= length(
-            (transpose(mesh[in.instance_index].model) * vec4(pbr_input.N, 0.0)).xyz
+            (transpose(unpack_mesh_transform(mesh[in.instance_index].model)) * vec4(pbr_input.N, 0.0)).xyz
         );
 #endif
         pbr_input.material.thickness = thickness;
@@ -224,6 +279,9 @@ fn pbr_input_from_standard_material(
 #endif
         }
 #endif
+#ifdef VERTEX_COLORS_OCCLUSION
+        diffuse_occlusion *= in.color.r;
+#endif
 #ifdef SCREEN_SPACE_AMBIENT_OCCLUSION
         let ssao = textureLoad(screen_space_ambient_occlusion_texture, vec2<i32>(in.position.xy), 0i).r;
         let ssao_multibounce = gtao_multibounce(ssao, pbr_input.material.base_color.rgb);
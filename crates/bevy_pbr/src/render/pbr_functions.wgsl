@@ -14,13 +14,16 @@
     mesh_types::{MESH_FLAGS_SHADOW_RECEIVER_BIT, MESH_FLAGS_TRANSMITTED_SHADOW_RECEIVER_BIT},
 }
 
-#import bevy_render::maths::E
+#import bevy_render::{
+    blue_noise::blue_noise_load,
+    maths::E,
+}
 
 #ifdef ENVIRONMENT_MAP
 #import bevy_pbr::environment_map
 #endif
 
-#import bevy_core_pipeline::tonemapping::{screen_space_dither, powsafe, tone_mapping}
+#import bevy_core_pipeline::tonemapping::{dither_srgb, tone_mapping}
 
 // This is the standard 4x4 ordered dithering pattern from [1].
 //
@@ -83,8 +86,12 @@ fn alpha_discard(material: pbr_types::StandardMaterial, output_color: vec4<f32>)
     // NOTE: `MAY_DISCARD` is only defined in the alpha to coverage case if MSAA
     // was off. This special situation causes alpha to coverage to fall back to
     // alpha mask.
+    //
+    // Hashed alpha has already been reduced to 0.0 or 1.0 in
+    // `pbr_input_from_standard_material`.
     else if alpha_mode == pbr_types::STANDARD_MATERIAL_FLAGS_ALPHA_MODE_MASK ||
-            alpha_mode == pbr_types::STANDARD_MATERIAL_FLAGS_ALPHA_MODE_ALPHA_TO_COVERAGE {
+            alpha_mode == pbr_types::STANDARD_MATERIAL_FLAGS_ALPHA_MODE_ALPHA_TO_COVERAGE ||
+            alpha_mode == pbr_types::STANDARD_MATERIAL_FLAGS_ALPHA_MODE_HASHED {
         if color.a >= material.alpha_cutoff {
             // NOTE: If rendering as masked alpha and >= the cutoff, render as fully opaque
             color.a = 1.0;
@@ -268,10 +275,13 @@ fn apply_pbr_lighting(
     // Point lights (direct)
     for (var i: u32 = offset_and_counts[0]; i < offset_and_counts[0] + offset_and_counts[1]; i = i + 1u) {
         let light_id = clustering::get_light_id(i);
-        var shadow: f32 = 1.0;
+        var shadow = vec3(1.0);
         if ((in.flags & MESH_FLAGS_SHADOW_RECEIVER_BIT) != 0u
                 && (view_bindings::point_lights.data[light_id].flags & mesh_view_types::POINT_LIGHT_FLAGS_SHADOWS_ENABLED_BIT) != 0u) {
-            shadow = shadows::fetch_point_shadow(light_id, in.world_position, in.world_normal);
+            shadow = vec3(shadows::fetch_point_shadow(light_id, in.world_position, in.world_normal));
+#ifdef COLORED_SHADOWS
+            shadow *= shadows::fetch_point_colored_shadow(light_id, in.world_position, in.world_normal);
+#endif
         }
         let light_contrib = lighting::point_light(in.world_position.xyz, light_id, roughness, NdotV, in.N, in.V, R, F0, f_ab, diffuse_color);
         direct_light += light_contrib * shadow;
@@ -300,10 +310,13 @@ fn apply_pbr_lighting(
     for (var i: u32 = offset_and_counts[0] + offset_and_counts[1]; i < offset_and_counts[0] + offset_and_counts[1] + offset_and_counts[2]; i = i + 1u) {
         let light_id = clustering::get_light_id(i);
 
-        var shadow: f32 = 1.0;
+        var shadow = vec3(1.0);
         if ((in.flags & MESH_FLAGS_SHADOW_RECEIVER_BIT) != 0u
                 && (view_bindings::point_lights.data[light_id].flags & mesh_view_types::POINT_LIGHT_FLAGS_SHADOWS_ENABLED_BIT) != 0u) {
-            shadow = shadows::fetch_spot_shadow(light_id, in.world_position, in.world_normal);
+            shadow = vec3(shadows::fetch_spot_shadow(light_id, in.world_position, in.world_normal));
+#ifdef COLORED_SHADOWS
+            shadow *= shadows::fetch_spot_colored_shadow(light_id, in.world_position, in.world_normal);
+#endif
         }
         let light_contrib = lighting::spot_light(in.world_position.xyz, light_id, roughness, NdotV, in.N, in.V, R, F0, f_ab, diffuse_color);
         direct_light += light_contrib * shadow;
@@ -338,11 +351,17 @@ fn apply_pbr_lighting(
             continue;
         }
 
-        var shadow: f32 = 1.0;
+        var shadow = vec3(1.0);
         if ((in.flags & MESH_FLAGS_SHADOW_RECEIVER_BIT) != 0u
                 && (view_bindings::lights.directional_lights[i].flags & mesh_view_types::DIRECTIONAL_LIGHT_FLAGS_SHADOWS_ENABLED_BIT) != 0u) {
-            shadow = shadows::fetch_directional_shadow(i, in.world_position, in.world_normal, view_z);
+            shadow = vec3(shadows::fetch_directional_shadow(i, in.world_position, in.world_normal, view_z, in.frag_coord.xy));
+#ifdef COLORED_SHADOWS
+            shadow *= shadows::fetch_directional_colored_shadow(i, in.world_position, in.world_normal, view_z);
+#endif
         }
+#ifdef DIRECTIONAL_LIGHT_COOKIE
+        shadow *= shadows::fetch_directional_light_cookie(i, in.world_position);
+#endif
         var light_contrib = lighting::directional_light(i, roughness, NdotV, in.N, in.V, R, F0, f_ab, diffuse_color);
 #ifdef DIRECTIONAL_LIGHT_SHADOW_MAP_DEBUG_CASCADES
         light_contrib = shadows::cascade_debug_visualization(light_contrib, i, view_z);
@@ -362,10 +381,15 @@ fn apply_pbr_lighting(
         var transmitted_shadow: f32 = 1.0;
         if ((in.flags & (MESH_FLAGS_SHADOW_RECEIVER_BIT | MESH_FLAGS_TRANSMITTED_SHADOW_RECEIVER_BIT)) == (MESH_FLAGS_SHADOW_RECEIVER_BIT | MESH_FLAGS_TRANSMITTED_SHADOW_RECEIVER_BIT)
                 && (view_bindings::lights.directional_lights[i].flags & mesh_view_types::DIRECTIONAL_LIGHT_FLAGS_SHADOWS_ENABLED_BIT) != 0u) {
-            transmitted_shadow = shadows::fetch_directional_shadow(i, diffuse_transmissive_lobe_world_position, -in.world_normal, view_z);
+            transmitted_shadow = shadows::fetch_directional_shadow(i, diffuse_transmissive_lobe_world_position, -in.world_normal, view_z, in.frag_coord.xy);
         }
         let transmitted_light_contrib = lighting::directional_light(i, 1.0, 1.0, -in.N, -in.V, vec3<f32>(0.0), vec3<f32>(0.0), vec2<f32>(0.1), diffuse_transmissive_color);
+#ifdef DIRECTIONAL_LIGHT_COOKIE
+        transmitted_light += transmitted_light_contrib * transmitted_shadow
+            * shadows::fetch_directional_light_cookie(i, diffuse_transmissive_lobe_world_position);
+#else
         transmitted_light += transmitted_light_contrib * transmitted_shadow;
+#endif
 #endif
     }
 
@@ -623,15 +647,18 @@ fn main_pass_post_lighting_processing(
 
 #ifdef TONEMAP_IN_SHADER
     output_color = tone_mapping(output_color, view_bindings::view.color_grading);
-#ifdef DEBAND_DITHER
-    var output_rgb = output_color.rgb;
-    output_rgb = powsafe(output_rgb, 1.0 / 2.2);
-    output_rgb += screen_space_dither(pbr_input.frag_coord.xy);
-    // This conversion back to linear space is required because our output texture format is
-    // SRGB; the GPU will assume our output is linear and will apply an SRGB conversion.
-    output_rgb = powsafe(output_rgb, 2.2);
-    output_color = vec4(output_rgb, output_color.a);
 #endif
+#ifdef DEBAND_DITHER
+    output_color = vec4(
+        dither_srgb(
+            output_color.rgb,
+            pbr_input.frag_coord.xy,
+            blue_noise_load(view_bindings::blue_noise_texture, vec2<u32>(pbr_input.frag_coord.xy)),
+            view_bindings::view.dither_pattern,
+            view_bindings::view.dither_strength,
+        ),
+        output_color.a
+    );
 #endif
 #ifdef PREMULTIPLY_ALPHA
     output_color = premultiply_alpha(pbr_input.material.flags, output_color);
@@ -5,6 +5,7 @@
     pbr_functions,
     prepass_io,
     mesh_view_bindings::view,
+    mesh_bindings::mesh,
 }
 
 #ifdef MESHLET_MESH_MATERIAL_PASS
@@ -30,6 +31,10 @@ fn fragment(
 
     var out: prepass_io::FragmentOutput;
 
+#ifdef TRANSLUCENT_SHADOW
+    out.transmittance = pbr_prepass_functions::translucent_shadow_transmittance(in);
+#endif
+
 #ifdef DEPTH_CLAMP_ORTHO
     out.frag_depth = in.clip_position_unclamped.z;
 #endif // DEPTH_CLAMP_ORTHO
@@ -77,6 +82,13 @@ fn fragment(
 #else
     out.motion_vector = pbr_prepass_functions::calculate_motion_vector(in.world_position, in.previous_world_position);
 #endif
+#endif
+
+#ifdef OBJECT_ID_PREPASS
+#ifndef MESHLET_MESH_MATERIAL_PASS
+    // 0 is left for the background.
+    out.object_id = mesh[in.instance_index].entity_index + 1u;
+#endif
 #endif
 
     return out;
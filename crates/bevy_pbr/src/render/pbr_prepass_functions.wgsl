@@ -2,11 +2,17 @@
 
 #import bevy_pbr::{
     prepass_io::VertexOutput,
+    prepass_bindings,
     prepass_bindings::previous_view_uniforms,
     mesh_view_bindings::view,
+    mesh_functions,
     pbr_bindings,
     pbr_types,
 }
+#import bevy_render::{
+    blue_noise::blue_noise,
+    view::VIEW_FLAGS_CAMERA_CUT_BIT,
+}
 
 // Cutoff used for the premultiplied alpha modes BLEND, ADD, and ALPHA_TO_COVERAGE.
 const PREMULTIPLIED_ALPHA_CUTOFF = 0.05;
@@ -15,7 +21,10 @@ const PREMULTIPLIED_ALPHA_CUTOFF = 0.05;
 fn prepass_alpha_discard(in: VertexOutput) {
 
 #ifdef MAY_DISCARD
-    var output_color: vec4<f32> = pbr_bindings::material.base_color;
+    var output_color: vec4<f32> = mesh_functions::mesh_base_color(
+        in.instance_index,
+        pbr_bindings::material.base_color,
+    );
 
 #ifdef VERTEX_UVS
     let uv_transform = pbr_bindings::material.uv_transform;
@@ -30,6 +39,16 @@ fn prepass_alpha_discard(in: VertexOutput) {
         if output_color.a < pbr_bindings::material.alpha_cutoff {
             discard;
         }
+    } else if alpha_mode == pbr_types::STANDARD_MATERIAL_FLAGS_ALPHA_MODE_HASHED {
+        // This must match the threshold of `ALPHA_HASHED` in `pbr_fragment.wgsl`.
+        let threshold = blue_noise(
+            prepass_bindings::blue_noise_texture,
+            vec2<u32>(in.position.xy),
+            prepass_bindings::globals.frame_count,
+        ).x;
+        if output_color.a <= threshold {
+            discard;
+        }
     } else if (alpha_mode == pbr_types::STANDARD_MATERIAL_FLAGS_ALPHA_MODE_BLEND ||
             alpha_mode == pbr_types::STANDARD_MATERIAL_FLAGS_ALPHA_MODE_ADD ||
             alpha_mode == pbr_types::STANDARD_MATERIAL_FLAGS_ALPHA_MODE_ALPHA_TO_COVERAGE) {
@@ -45,8 +64,37 @@ fn prepass_alpha_discard(in: VertexOutput) {
 #endif // MAY_DISCARD
 }
 
+#ifdef TRANSLUCENT_SHADOW
+// Fraction of light passing through this caster, tinted by its base color, with
+// the caster depth stored in alpha so receivers in front of it stay untinted.
+fn translucent_shadow_transmittance(in: VertexOutput) -> vec4<f32> {
+    var base_color: vec4<f32> = pbr_bindings::material.base_color;
+
+#ifdef VERTEX_UVS
+    let uv_transform = pbr_bindings::material.uv_transform;
+    let uv = (uv_transform * vec3(in.uv, 1.0)).xy;
+    if (pbr_bindings::material.flags & pbr_types::STANDARD_MATERIAL_FLAGS_BASE_COLOR_TEXTURE_BIT) != 0u {
+        base_color = base_color * textureSampleBias(pbr_bindings::base_color_texture, pbr_bindings::base_color_sampler, uv, view.mip_bias);
+    }
+#endif // VERTEX_UVS
+
+    let opacity = saturate(base_color.a);
+    let transmission = saturate(pbr_bindings::material.specular_transmission + pbr_bindings::material.diffuse_transmission);
+    // Light that misses the surface passes untouched, light that hits it is
+    // filtered by the base color in proportion to how transmissive it is.
+    let transmittance = vec3(1.0 - opacity) + opacity * transmission * base_color.rgb;
+    return vec4(saturate(transmittance), in.position.z);
+}
+#endif // TRANSLUCENT_SHADOW
+
 #ifdef MOTION_VECTOR_PREPASS
 fn calculate_motion_vector(world_position: vec4<f32>, previous_world_position: vec4<f32>) -> vec2<f32> {
+    // The previous frame belongs to a different shot on camera cuts, so there's
+    // no motion to follow.
+    if (view.flags & VIEW_FLAGS_CAMERA_CUT_BIT) != 0u {
+        return vec2(0.0);
+    }
+
     let clip_position_t = view.unjittered_view_proj * world_position;
     let clip_position = clip_position_t.xy / clip_position_t.w;
     let previous_clip_position_t = previous_view_uniforms.view_proj * previous_world_position;
@@ -3,12 +3,14 @@
 #import bevy_pbr::{
     lighting,
     prepass_utils,
-    utils::interleaved_gradient_noise,
     utils,
     mesh_view_bindings as view_bindings,
 };
 
-#import bevy_render::maths::PI
+#import bevy_render::{
+    blue_noise::blue_noise,
+    maths::PI,
+}
 
 #import bevy_core_pipeline::tonemapping::{
     approximate_inverse_tone_mapping
@@ -104,11 +106,11 @@ fn fetch_transmissive_background(offset_position: vec2<f32>, frag_coord: vec3<f3
 #endif
     let num_spirals = i32(ceil(f32(num_taps) / 8.0));
 #ifdef TEMPORAL_JITTER
-    let random_angle = interleaved_gradient_noise(frag_coord.xy, view_bindings::globals.frame_count);
+    let random_angle = blue_noise(view_bindings::blue_noise_texture, vec2<u32>(frag_coord.xy), view_bindings::globals.frame_count).x;
 #else
-    let random_angle = interleaved_gradient_noise(frag_coord.xy, 0u);
+    let random_angle = blue_noise(view_bindings::blue_noise_texture, vec2<u32>(frag_coord.xy), 0u).x;
 #endif
-    // Pixel checkerboard pattern (helps make the interleaved gradient noise pattern less visible)
+    // Pixel checkerboard pattern (helps make the noise pattern less visible)
     let pixel_checkboard = (
 #ifdef TEMPORAL_JITTER
         // 0 or 1 on even/odd pixels, alternates every frame
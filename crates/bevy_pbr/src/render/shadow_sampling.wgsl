@@ -2,10 +2,12 @@
 
 #import bevy_pbr::{
     mesh_view_bindings as view_bindings,
-    utils::interleaved_gradient_noise,
     utils,
 }
-#import bevy_render::maths::{orthonormalize, PI}
+#import bevy_render::{
+    blue_noise::blue_noise,
+    maths::{orthonormalize, PI},
+}
 
 // Do the lookup, using HW 2x2 PCF and comparison
 fn sample_shadow_map_hardware(light_local: vec2<f32>, depth: f32, array_index: i32) -> f32 {
@@ -110,12 +112,15 @@ fn map(min1: f32, max1: f32, min2: f32, max2: f32, value: f32) -> f32 {
     return min2 + (value - min1) * (max2 - min2) / (max1 - min1);
 }
 
-// Creates a random rotation matrix using interleaved gradient noise.
+// Creates a random rotation matrix using blue noise, animated over frames.
 //
 // See: https://www.iryoku.com/next-generation-post-processing-in-call-of-duty-advanced-warfare/
 fn random_rotation_matrix(scale: vec2<f32>) -> mat2x2<f32> {
-    let random_angle = 2.0 * PI * interleaved_gradient_noise(
-        scale, view_bindings::globals.frame_count);
+    let random_angle = 2.0 * PI * blue_noise(
+        view_bindings::blue_noise_texture,
+        vec2<u32>(scale),
+        view_bindings::globals.frame_count,
+    ).x;
     let m = vec2(sin(random_angle), cos(random_angle));
     return mat2x2(
         m.y, -m.x,
@@ -1,19 +1,37 @@
 #define_import_path bevy_pbr::shadows
 
 #import bevy_pbr::{
-    mesh_view_types::POINT_LIGHT_FLAGS_SPOT_LIGHT_Y_NEGATIVE,
+    mesh_view_types::{
+        POINT_LIGHT_FLAGS_SPOT_LIGHT_Y_NEGATIVE, POINT_LIGHT_FLAGS_AUTO_SHADOW_BIAS,
+        DIRECTIONAL_LIGHT_FLAGS_AUTO_SHADOW_BIAS, DIRECTIONAL_LIGHT_FLAGS_CASCADE_BLEND_SMOOTH,
+        DIRECTIONAL_LIGHT_FLAGS_CASCADE_BLEND_DITHERED,
+    },
     mesh_view_bindings as view_bindings,
+    shader_constants::POINT_LIGHT_NEAR_Z,
     shadow_sampling::{SPOT_SHADOW_TEXEL_SIZE, sample_shadow_cubemap, sample_shadow_map}
 }
 
 #import bevy_render::{
+    blue_noise::blue_noise,
     color_operations::hsv_to_rgb,
     maths::PI_2
 }
 
 const flip_z: vec3<f32> = vec3<f32>(1.0, 1.0, -1.0);
 
-fn fetch_point_shadow(light_id: u32, frag_position: vec4<f32>, surface_normal: vec3<f32>) -> f32 {
+// Returns the depth bias of a point or spot light at the given distance from the light.
+fn point_shadow_depth_bias(flags: u32, shadow_depth_bias: f32, distance_to_light: f32) -> f32 {
+    if ((flags & POINT_LIGHT_FLAGS_AUTO_SHADOW_BIAS) != 0u) {
+        // Like the normal bias, the depth bias is scaled to the texel size at 1 world unit from
+        // the light, so scale it to the texel size at the fragment distance.
+        return shadow_depth_bias * distance_to_light;
+    }
+    return shadow_depth_bias;
+}
+
+// Returns the biased position of the fragment in the cubemap of a point light, flipped to the
+// left-handed space of cubemaps, with its depth in the shadow map in w.
+fn point_shadow_position(light_id: u32, frag_position: vec4<f32>, surface_normal: vec3<f32>) -> vec4<f32> {
     let light = &view_bindings::point_lights.data[light_id];
 
     // because the shadow maps align with the axes and the frustum planes are at 45 degrees
@@ -26,7 +44,8 @@ fn fetch_point_shadow(light_id: u32, frag_position: vec4<f32>, surface_normal: v
     // The texel size increases proportionally with distance from the light so multiplying by
     // distance to light scales the normal bias to the texel size at the fragment distance.
     let normal_offset = (*light).shadow_normal_bias * distance_to_light * surface_normal.xyz;
-    let depth_offset = (*light).shadow_depth_bias * normalize(surface_to_light.xyz);
+    let depth_bias = point_shadow_depth_bias((*light).flags, (*light).shadow_depth_bias, distance_to_light);
+    let depth_offset = depth_bias * normalize(surface_to_light.xyz);
     let offset_position = frag_position.xyz + normal_offset + depth_offset;
 
     // similar largest-absolute-axis trick as above, but now with the offset fragment position
@@ -41,12 +60,22 @@ fn fetch_point_shadow(light_id: u32, frag_position: vec4<f32>, surface_normal: v
     let zw = -major_axis_magnitude * (*light).light_custom_data.xy + (*light).light_custom_data.zw;
     let depth = zw.x / zw.y;
 
-    // Do the lookup, using HW PCF and comparison. Cubemaps assume a left-handed coordinate space,
-    // so we have to flip the z-axis when sampling.
-    return sample_shadow_cubemap(frag_ls * flip_z, distance_to_light, depth, light_id);
+    // Cubemaps assume a left-handed coordinate space, so we have to flip the z-axis when sampling.
+    return vec4(frag_ls * flip_z, depth);
 }
 
-fn fetch_spot_shadow(light_id: u32, frag_position: vec4<f32>, surface_normal: vec3<f32>) -> f32 {
+fn fetch_point_shadow(light_id: u32, frag_position: vec4<f32>, surface_normal: vec3<f32>) -> f32 {
+    let surface_to_light_abs = abs(view_bindings::point_lights.data[light_id].position_radius.xyz - frag_position.xyz);
+    let distance_to_light = max(surface_to_light_abs.x, max(surface_to_light_abs.y, surface_to_light_abs.z));
+    let position = point_shadow_position(light_id, frag_position, surface_normal);
+
+    // Do the lookup, using HW PCF and comparison.
+    return sample_shadow_cubemap(position.xyz, distance_to_light, position.w, light_id);
+}
+
+// Returns the biased position of the fragment in the shadow map of a spot light, as texture
+// coordinates in xy and depth in z.
+fn spot_shadow_position(light_id: u32, frag_position: vec4<f32>, surface_normal: vec3<f32>) -> vec3<f32> {
     let light = &view_bindings::point_lights.data[light_id];
 
     let surface_to_light = (*light).position_radius.xyz - frag_position.xyz;
@@ -62,9 +91,10 @@ fn fetch_spot_shadow(light_id: u32, frag_position: vec4<f32>, surface_normal: ve
     // view matrix z_axis is the reverse of transform.forward()
     let fwd = -spot_dir;
     let distance_to_light = dot(fwd, surface_to_light);
+    let depth_bias = point_shadow_depth_bias((*light).flags, (*light).shadow_depth_bias, distance_to_light);
     let offset_position =
         -surface_to_light
-        + ((*light).shadow_depth_bias * normalize(surface_to_light))
+        + (depth_bias * normalize(surface_to_light))
         + (surface_normal.xyz * (*light).shadow_normal_bias) * distance_to_light;
 
     // the construction of the up and right vectors needs to precisely mirror the code
@@ -91,12 +121,16 @@ fn fetch_spot_shadow(light_id: u32, frag_position: vec4<f32>, surface_normal: ve
     // convert to uv coordinates
     let shadow_uv = shadow_xy_ndc * vec2<f32>(0.5, -0.5) + vec2<f32>(0.5, 0.5);
 
-    // 0.1 must match POINT_LIGHT_NEAR_Z
-    let depth = 0.1 / -projected_position.z;
+    let depth = POINT_LIGHT_NEAR_Z / -projected_position.z;
+
+    return vec3(shadow_uv, depth);
+}
 
+fn fetch_spot_shadow(light_id: u32, frag_position: vec4<f32>, surface_normal: vec3<f32>) -> f32 {
+    let position = spot_shadow_position(light_id, frag_position, surface_normal);
     return sample_shadow_map(
-        shadow_uv,
-        depth,
+        position.xy,
+        position.z,
         i32(light_id) + view_bindings::lights.spot_light_shadowmap_offset,
         SPOT_SHADOW_TEXEL_SIZE
     );
@@ -113,24 +147,31 @@ fn get_cascade_index(light_id: u32, view_z: f32) -> u32 {
     return (*light).num_cascades;
 }
 
-fn sample_directional_cascade(light_id: u32, cascade_index: u32, frag_position: vec4<f32>, surface_normal: vec3<f32>) -> f32 {
+// Returns the biased position of the fragment in a cascade of a directional light, as texture
+// coordinates in xy and depth in z, with w set to 0.0 if the fragment is outside of the cascade.
+fn directional_cascade_position(light_id: u32, cascade_index: u32, frag_position: vec4<f32>, surface_normal: vec3<f32>) -> vec4<f32> {
     let light = &view_bindings::lights.directional_lights[light_id];
     let cascade = &(*light).cascades[cascade_index];
 
     // The normal bias is scaled to the texel size.
     let normal_offset = (*light).shadow_normal_bias * (*cascade).texel_size * surface_normal.xyz;
-    let depth_offset = (*light).shadow_depth_bias * (*light).direction_to_light.xyz;
+    var depth_bias = (*light).shadow_depth_bias;
+    if (((*light).flags & DIRECTIONAL_LIGHT_FLAGS_AUTO_SHADOW_BIAS) != 0u) {
+        // The depth bias is in texels, scale it to the texel size of the cascade.
+        depth_bias *= (*cascade).texel_size;
+    }
+    let depth_offset = depth_bias * (*light).direction_to_light.xyz;
     let offset_position = vec4<f32>(frag_position.xyz + normal_offset + depth_offset, frag_position.w);
 
     let offset_position_clip = (*cascade).view_projection * offset_position;
     if (offset_position_clip.w <= 0.0) {
-        return 1.0;
+        return vec4(0.0);
     }
     let offset_position_ndc = offset_position_clip.xyz / offset_position_clip.w;
     // No shadow outside the orthographic projection volume
     if (any(offset_position_ndc.xy < vec2<f32>(-1.0)) || offset_position_ndc.z < 0.0
             || any(offset_position_ndc > vec3<f32>(1.0))) {
-        return 1.0;
+        return vec4(0.0);
     }
 
     // compute texture coordinates for shadow lookup, compensating for the Y-flip difference
@@ -138,13 +179,28 @@ fn sample_directional_cascade(light_id: u32, cascade_index: u32, frag_position:
     let flip_correction = vec2<f32>(0.5, -0.5);
     let light_local = offset_position_ndc.xy * flip_correction + vec2<f32>(0.5, 0.5);
 
-    let depth = offset_position_ndc.z;
+    return vec4(light_local, offset_position_ndc.z, 1.0);
+}
+
+fn sample_directional_cascade(light_id: u32, cascade_index: u32, frag_position: vec4<f32>, surface_normal: vec3<f32>) -> f32 {
+    let light = &view_bindings::lights.directional_lights[light_id];
+    let position = directional_cascade_position(light_id, cascade_index, frag_position, surface_normal);
+    if (position.w == 0.0) {
+        return 1.0;
+    }
 
     let array_index = i32((*light).depth_texture_base_index + cascade_index);
-    return sample_shadow_map(light_local, depth, array_index, (*cascade).texel_size);
+    return sample_shadow_map(position.xy, position.z, array_index, (*light).cascades[cascade_index].texel_size);
 }
 
-fn fetch_directional_shadow(light_id: u32, frag_position: vec4<f32>, surface_normal: vec3<f32>, view_z: f32) -> f32 {
+// `frag_coord` is the pixel coordinate of the fragment, used to dither between cascades.
+fn fetch_directional_shadow(
+    light_id: u32,
+    frag_position: vec4<f32>,
+    surface_normal: vec3<f32>,
+    view_z: f32,
+    frag_coord: vec2<f32>,
+) -> f32 {
     let light = &view_bindings::lights.directional_lights[light_id];
     let cascade_index = get_cascade_index(light_id, view_z);
 
@@ -152,20 +208,145 @@ fn fetch_directional_shadow(light_id: u32, frag_position: vec4<f32>, surface_nor
         return 1.0;
     }
 
-    var shadow = sample_directional_cascade(light_id, cascade_index, frag_position, surface_normal);
-
-    // Blend with the next cascade, if there is one.
+    // Blend with the next cascade, if there is one and blending is enabled.
+    let blend_flags = (*light).flags
+        & (DIRECTIONAL_LIGHT_FLAGS_CASCADE_BLEND_SMOOTH | DIRECTIONAL_LIGHT_FLAGS_CASCADE_BLEND_DITHERED);
     let next_cascade_index = cascade_index + 1u;
-    if (next_cascade_index < (*light).num_cascades) {
+    if (blend_flags != 0u && next_cascade_index < (*light).num_cascades) {
         let this_far_bound = (*light).cascades[cascade_index].far_bound;
         let next_near_bound = (1.0 - (*light).cascades_overlap_proportion) * this_far_bound;
         if (-view_z >= next_near_bound) {
+            let blend = (-view_z - next_near_bound) / (this_far_bound - next_near_bound);
+            if ((blend_flags & DIRECTIONAL_LIGHT_FLAGS_CASCADE_BLEND_DITHERED) != 0u) {
+                // Sample a single cascade, picking the next one more often closer to its bound.
+                let noise = blue_noise(
+                    view_bindings::blue_noise_texture,
+                    vec2<u32>(frag_coord),
+                    view_bindings::globals.frame_count,
+                ).x;
+                if (noise < blend) {
+                    return sample_directional_cascade(light_id, next_cascade_index, frag_position, surface_normal);
+                }
+                return sample_directional_cascade(light_id, cascade_index, frag_position, surface_normal);
+            }
+            let shadow = sample_directional_cascade(light_id, cascade_index, frag_position, surface_normal);
             let next_shadow = sample_directional_cascade(light_id, next_cascade_index, frag_position, surface_normal);
-            shadow = mix(shadow, next_shadow, (-view_z - next_near_bound) / (this_far_bound - next_near_bound));
+            return mix(shadow, next_shadow, blend);
         }
     }
-    return shadow;
+    return sample_directional_cascade(light_id, cascade_index, frag_position, surface_normal);
+}
+
+#ifdef COLORED_SHADOWS
+// Returns the tint of the light reaching a receiver at `depth` in the shadow map of a light, from
+// the `transmittance` of the translucent casters in the colored shadow map, which stores the depth
+// of the caster closest to the light in alpha. Receivers in front of it aren't tinted.
+fn colored_shadow_tint(transmittance: vec4<f32>, depth: f32) -> vec3<f32> {
+    // Depths are reversed, so receivers behind the caster have a smaller depth.
+    if (depth < transmittance.a) {
+        return transmittance.rgb;
+    }
+    return vec3(1.0);
+}
+
+fn fetch_point_colored_shadow(light_id: u32, frag_position: vec4<f32>, surface_normal: vec3<f32>) -> vec3<f32> {
+    let position = point_shadow_position(light_id, frag_position, surface_normal);
+#ifdef NO_CUBE_ARRAY_TEXTURES_SUPPORT
+    let transmittance = textureSampleLevel(
+        view_bindings::colored_point_shadow_textures,
+        view_bindings::colored_shadow_textures_sampler,
+        position.xyz,
+        0.0,
+    );
+#else
+    let transmittance = textureSampleLevel(
+        view_bindings::colored_point_shadow_textures,
+        view_bindings::colored_shadow_textures_sampler,
+        position.xyz,
+        i32(light_id),
+        0.0,
+    );
+#endif
+    return colored_shadow_tint(transmittance, position.w);
+}
+
+fn sample_directional_colored_shadow_map(uv: vec2<f32>, array_index: i32) -> vec4<f32> {
+#ifdef NO_ARRAY_TEXTURES_SUPPORT
+    return textureSampleLevel(
+        view_bindings::colored_directional_shadow_textures,
+        view_bindings::colored_shadow_textures_sampler,
+        uv,
+        0.0,
+    );
+#else
+    return textureSampleLevel(
+        view_bindings::colored_directional_shadow_textures,
+        view_bindings::colored_shadow_textures_sampler,
+        uv,
+        array_index,
+        0.0,
+    );
+#endif
+}
+
+fn fetch_spot_colored_shadow(light_id: u32, frag_position: vec4<f32>, surface_normal: vec3<f32>) -> vec3<f32> {
+    let position = spot_shadow_position(light_id, frag_position, surface_normal);
+    let transmittance = sample_directional_colored_shadow_map(
+        position.xy,
+        i32(light_id) + view_bindings::lights.spot_light_shadowmap_offset,
+    );
+    return colored_shadow_tint(transmittance, position.z);
+}
+
+// Unlike `fetch_directional_shadow`, this doesn't blend between cascades.
+fn fetch_directional_colored_shadow(
+    light_id: u32,
+    frag_position: vec4<f32>,
+    surface_normal: vec3<f32>,
+    view_z: f32,
+) -> vec3<f32> {
+    let light = &view_bindings::lights.directional_lights[light_id];
+    let cascade_index = get_cascade_index(light_id, view_z);
+    if (cascade_index >= (*light).num_cascades) {
+        return vec3(1.0);
+    }
+
+    let position = directional_cascade_position(light_id, cascade_index, frag_position, surface_normal);
+    if (position.w == 0.0) {
+        return vec3(1.0);
+    }
+    let transmittance = sample_directional_colored_shadow_map(
+        position.xy,
+        i32((*light).depth_texture_base_index + cascade_index),
+    );
+    return colored_shadow_tint(transmittance, position.z);
+}
+#endif // COLORED_SHADOWS
+
+#ifdef DIRECTIONAL_LIGHT_COOKIE
+// Projects the fragment along the light onto the cookie plane and samples the cookie there.
+fn fetch_directional_light_cookie(light_id: u32, frag_position: vec4<f32>) -> vec3<f32> {
+    let light = &view_bindings::lights.directional_lights[light_id];
+    let strength = (*light).cookie_strength;
+    let direction_to_light = (*light).direction_to_light;
+    // Lights below the horizon never reach the plane from above.
+    if (strength <= 0.0 || direction_to_light.y <= 0.0) {
+        return vec3(1.0);
+    }
+
+    let distance = ((*light).cookie_height - frag_position.y) / direction_to_light.y;
+    let plane_position = frag_position.xz + direction_to_light.xz * distance;
+    let uv = plane_position * (*light).cookie_uv_transform.xy - (*light).cookie_uv_transform.zw;
+    // The light loop isn't uniform control flow, so the implicit derivatives can't be used.
+    let cookie = textureSampleLevel(
+        view_bindings::directional_light_cookie_texture,
+        view_bindings::directional_light_cookie_sampler,
+        uv,
+        0.0,
+    ).rgb;
+    return mix(vec3(1.0), cookie, strength);
 }
+#endif // DIRECTIONAL_LIGHT_COOKIE
 
 fn cascade_debug_visualization(
     output_color: vec3<f32>,
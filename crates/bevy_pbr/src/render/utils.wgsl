@@ -1,6 +1,7 @@
 #define_import_path bevy_pbr::utils
 
 #import bevy_pbr::rgb9e5
+#import bevy_render::utils as render_utils
 
 // Generates a random u32 in range [0, u32::MAX].
 //
@@ -42,30 +43,23 @@ fn coords_to_viewport_uv(position: vec2<f32>, viewport: vec4<f32>) -> vec2<f32>
     return (position - viewport.xy) / viewport.zw;
 }
 
-// https://jcgt.org/published/0003/02/01/paper.pdf
-
 // For encoding normals or unit direction vectors as octahedral coordinates.
+//
+// See `bevy_render::utils::octahedral_encode`.
 fn octahedral_encode(v: vec3<f32>) -> vec2<f32> {
-    var n = v / (abs(v.x) + abs(v.y) + abs(v.z));
-    let octahedral_wrap = (1.0 - abs(n.yx)) * select(vec2(-1.0), vec2(1.0), n.xy > vec2f(0.0));
-    let n_xy = select(octahedral_wrap, n.xy, n.z >= 0.0);
-    return n_xy * 0.5 + 0.5;
+    return render_utils::octahedral_encode(v);
 }
 
 // For decoding normals or unit direction vectors from octahedral coordinates.
+//
+// See `bevy_render::utils::octahedral_decode`.
 fn octahedral_decode(v: vec2<f32>) -> vec3<f32> {
-    let f = v * 2.0 - 1.0;
-    var n = vec3(f.xy, 1.0 - abs(f.x) - abs(f.y));
-    let t = saturate(-n.z);
-    let w = select(vec2(t), vec2(-t), n.xy >= vec2(0.0));
-    n = vec3(n.xy + w, n.z);
-    return normalize(n);
+    return render_utils::octahedral_decode(v);
 }
 
-// https://blog.demofox.org/2022/01/01/interleaved-gradient-noise-a-different-kind-of-low-discrepancy-sequence
+// See `bevy_render::utils::interleaved_gradient_noise`.
 fn interleaved_gradient_noise(pixel_coordinates: vec2<f32>, frame: u32) -> f32 {
-    let xy = pixel_coordinates + 5.588238 * f32(frame % 64u);
-    return fract(52.9829189 * fract(0.06711056 * xy.x + 0.00583715 * xy.y));
+    return render_utils::interleaved_gradient_noise(pixel_coordinates, frame);
 }
 
 // https://www.iryoku.com/next-generation-post-processing-in-call-of-duty-advanced-warfare (slides 120-135)
@@ -0,0 +1,239 @@
+//! Vertex pulling: reading vertex attributes from storage buffers in the vertex
+//! shader, instead of through fixed-function vertex buffers.
+//!
+//! Enable it with [`PbrPlugin::use_vertex_pulling`](crate::PbrPlugin::use_vertex_pulling).
+//! Meshes drawn with the default vertex shaders are then specialized with
+//! [`MeshPipelineKey::VERTEX_PULLING`], and their vertex buffer is bound as a
+//! storage buffer after the material bind group, at index 3. The vertex shader
+//! fetches each attribute by vertex index, at offsets passed as shader defs.
+//!
+//! Meshes that can't be pulled keep using the classic vertex buffer path, as do
+//! all meshes on platforms without storage buffers, like WebGL 2.
+
+use bevy_asset::{AssetId, Handle};
+use bevy_derive::Deref;
+use bevy_ecs::{
+    prelude::*,
+    system::{lifetimeless::SRes, SystemParamItem},
+};
+use bevy_render::{
+    mesh::{GpuMesh, Mesh, MeshVertexAttribute, MeshVertexBufferLayout, MeshVertexBufferLayoutRef},
+    render_asset::RenderAssets,
+    render_phase::{PhaseItem, RenderCommand, RenderCommandResult, TrackedRenderPass},
+    render_resource::{
+        BindGroup, BindGroupEntries, BindGroupLayout, BufferId, Shader, ShaderDefVal,
+        VertexBufferLayout,
+    },
+    renderer::RenderDevice,
+};
+use bevy_utils::HashMap;
+
+use crate::{MeshLayouts, MeshPipeline, MeshPipelineKey, RenderMeshInstances};
+
+pub const VERTEX_PULLING_HANDLE: Handle<Shader> = Handle::weak_from_u128(4873195420968316742);
+
+/// Whether meshes are drawn with vertex pulling, see
+/// [`PbrPlugin::use_vertex_pulling`](crate::PbrPlugin::use_vertex_pulling).
+///
+/// Always `false` on platforms without storage buffers.
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Debug, Deref)]
+pub struct UseVertexPulling(pub bool);
+
+/// Where the attributes of a vertex are in the vertex buffer of a mesh, for
+/// shaders that pull vertices.
+///
+/// Offsets and the stride are in 4 byte words.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct VertexPullingLayout {
+    /// The distance between two vertices.
+    pub stride: u32,
+    /// The offset of [`Mesh::ATTRIBUTE_POSITION`].
+    pub position: u32,
+    /// The offset of [`Mesh::ATTRIBUTE_NORMAL`], if the mesh has normals.
+    pub normal: Option<u32>,
+    /// The offset of [`Mesh::ATTRIBUTE_UV_0`], if the mesh has UVs.
+    pub uv: Option<u32>,
+    /// The offset of [`Mesh::ATTRIBUTE_UV_1`], if the mesh has a second UV set.
+    pub uv_b: Option<u32>,
+    /// The offset of [`Mesh::ATTRIBUTE_TANGENT`], if the mesh has tangents.
+    pub tangent: Option<u32>,
+    /// The offset of [`Mesh::ATTRIBUTE_COLOR`], if the mesh has vertex colors.
+    pub color: Option<u32>,
+}
+
+impl VertexPullingLayout {
+    /// Returns the layout of vertices with the given layout, or `None` if they
+    /// can't be pulled.
+    ///
+    /// Vertices are pulled as 4 byte words, so the stride must be a multiple
+    /// of 4 bytes, and the standard attributes must have their default
+    /// formats. Skinned meshes aren't supported.
+    pub fn new(layout: &MeshVertexBufferLayout) -> Option<Self> {
+        let buffer_layout = layout.layout();
+        if buffer_layout.array_stride % 4 != 0 || layout.contains(Mesh::ATTRIBUTE_JOINT_INDEX) {
+            return None;
+        }
+
+        // `Some(None)` if the attribute is missing, `None` if it can't be
+        // pulled.
+        let offset = |attribute: &MeshVertexAttribute| -> Option<Option<u32>> {
+            let Some(index) = layout
+                .attribute_ids()
+                .iter()
+                .position(|id| *id == attribute.id)
+            else {
+                return Some(None);
+            };
+            let vertex_attribute = &buffer_layout.attributes[index];
+            (vertex_attribute.format == attribute.format && vertex_attribute.offset % 4 == 0)
+                .then_some(Some(vertex_attribute.offset as u32 / 4))
+        };
+
+        Some(VertexPullingLayout {
+            stride: buffer_layout.array_stride as u32 / 4,
+            position: offset(&Mesh::ATTRIBUTE_POSITION)??,
+            normal: offset(&Mesh::ATTRIBUTE_NORMAL)?,
+            uv: offset(&Mesh::ATTRIBUTE_UV_0)?,
+            uv_b: offset(&Mesh::ATTRIBUTE_UV_1)?,
+            tangent: offset(&Mesh::ATTRIBUTE_TANGENT)?,
+            color: offset(&Mesh::ATTRIBUTE_COLOR)?,
+        })
+    }
+
+    /// Returns the layout of the vertices of `mesh`, or `None` if they can't
+    /// be pulled. Morphed meshes aren't supported.
+    pub fn for_mesh(mesh: &GpuMesh) -> Option<Self> {
+        if mesh.morph_targets.is_some() {
+            return None;
+        }
+        Self::new(&mesh.layout.0)
+    }
+
+    /// The shader defs `bevy_pbr::vertex_pulling` reads the layout from.
+    pub fn shader_defs(&self) -> Vec<ShaderDefVal> {
+        let mut shader_defs = vec![
+            "VERTEX_PULLING".into(),
+            ShaderDefVal::UInt("VERTEX_PULLING_STRIDE".into(), self.stride),
+            ShaderDefVal::UInt("VERTEX_PULLING_POSITION_OFFSET".into(), self.position),
+        ];
+        for (name, offset) in [
+            ("VERTEX_PULLING_NORMAL_OFFSET", self.normal),
+            ("VERTEX_PULLING_UV_OFFSET", self.uv),
+            ("VERTEX_PULLING_UV_B_OFFSET", self.uv_b),
+            ("VERTEX_PULLING_TANGENT_OFFSET", self.tangent),
+            ("VERTEX_PULLING_COLOR_OFFSET", self.color),
+        ] {
+            if let Some(offset) = offset {
+                shader_defs.push(ShaderDefVal::UInt(name.into(), offset));
+            }
+        }
+        shader_defs
+    }
+}
+
+/// Returns [`MeshPipelineKey::VERTEX_PULLING`] if vertex pulling is enabled and
+/// `mesh` can be drawn with it, or an empty key otherwise.
+pub fn vertex_pulling_key(mesh_layouts: &MeshLayouts, mesh: &GpuMesh) -> MeshPipelineKey {
+    if mesh_layouts.vertex_pulling.is_some() && VertexPullingLayout::for_mesh(mesh).is_some() {
+        MeshPipelineKey::VERTEX_PULLING
+    } else {
+        MeshPipelineKey::NONE
+    }
+}
+
+/// The vertex pulling bind groups of every mesh that can be pulled, along with
+/// the vertex buffer they were created for.
+#[derive(Resource, Default)]
+pub struct VertexPullingBindGroups(HashMap<AssetId<Mesh>, (BufferId, BindGroup)>);
+
+impl VertexPullingBindGroups {
+    /// Returns the vertex pulling bind group of a mesh.
+    pub fn get(&self, asset_id: AssetId<Mesh>) -> Option<&BindGroup> {
+        self.0.get(&asset_id).map(|(_, bind_group)| bind_group)
+    }
+}
+
+/// Creates the vertex pulling bind groups of meshes that were added or
+/// re-uploaded, and drops those of meshes that were removed.
+pub fn prepare_vertex_pulling_bind_groups(
+    render_device: Res<RenderDevice>,
+    mesh_pipeline: Res<MeshPipeline>,
+    meshes: Res<RenderAssets<GpuMesh>>,
+    mut bind_groups: ResMut<VertexPullingBindGroups>,
+) {
+    let Some(layout) = mesh_pipeline.mesh_layouts.vertex_pulling.as_ref() else {
+        return;
+    };
+
+    bind_groups
+        .0
+        .retain(|asset_id, (buffer_id, _)| match meshes.get(*asset_id) {
+            Some(mesh) => mesh.vertex_buffer.id() == *buffer_id,
+            None => false,
+        });
+
+    for (asset_id, mesh) in meshes.iter() {
+        if bind_groups.0.contains_key(&asset_id) || VertexPullingLayout::for_mesh(mesh).is_none() {
+            continue;
+        }
+        let bind_group = render_device.create_bind_group(
+            "vertex_pulling_bind_group",
+            layout,
+            &BindGroupEntries::single(mesh.vertex_buffer.as_entire_binding()),
+        );
+        bind_groups
+            .0
+            .insert(asset_id, (mesh.vertex_buffer.id(), bind_group));
+    }
+}
+
+/// Binds the vertex buffer of the mesh for vertex pulling, if it can be pulled.
+///
+/// Pipelines that don't pull vertices ignore the bind group.
+pub struct SetMeshVertexPullingBindGroup<const I: usize>;
+impl<P: PhaseItem, const I: usize> RenderCommand<P> for SetMeshVertexPullingBindGroup<I> {
+    type Param = (SRes<RenderMeshInstances>, SRes<VertexPullingBindGroups>);
+    type ViewQuery = ();
+    type ItemQuery = ();
+
+    #[inline]
+    fn render<'w>(
+        item: &P,
+        _view: (),
+        _item_query: Option<()>,
+        (render_mesh_instances, bind_groups): SystemParamItem<'w, '_, Self::Param>,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        let Some(mesh_asset_id) = render_mesh_instances.mesh_asset_id(item.entity()) else {
+            return RenderCommandResult::Success;
+        };
+        if let Some(bind_group) = bind_groups.into_inner().get(mesh_asset_id) {
+            pass.set_bind_group(I, bind_group, &[]);
+        }
+
+        RenderCommandResult::Success
+    }
+}
+
+/// Specializes a pipeline drawing meshes with the given vertex layout to pull
+/// their vertices: adds the `bevy_pbr::vertex_pulling` shader defs and the
+/// vertex pulling bind group layout, and removes the vertex buffers.
+///
+/// Does nothing if vertex pulling is disabled or the vertices can't be pulled,
+/// in which case the pipeline keeps using the vertex buffers.
+pub fn setup_vertex_pulling(
+    mesh_layouts: &MeshLayouts,
+    layout: &MeshVertexBufferLayoutRef,
+    shader_defs: &mut Vec<ShaderDefVal>,
+    bind_group_layouts: &mut Vec<BindGroupLayout>,
+    vertex_buffers: &mut Vec<VertexBufferLayout>,
+) {
+    if let (Some(bind_group_layout), Some(vertex_pulling_layout)) = (
+        mesh_layouts.vertex_pulling.as_ref(),
+        VertexPullingLayout::new(&layout.0),
+    ) {
+        shader_defs.extend(vertex_pulling_layout.shader_defs());
+        bind_group_layouts.push(bind_group_layout.clone());
+        vertex_buffers.clear();
+    }
+}
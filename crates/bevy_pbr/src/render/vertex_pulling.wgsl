@@ -0,0 +1,65 @@
+#define_import_path bevy_pbr::vertex_pulling
+
+#ifdef VERTEX_PULLING
+
+// Reads vertex attributes from the vertex buffer of the mesh being drawn, bound
+// as a storage buffer. See `VertexPullingLayout`: the stride and offsets are in
+// 4 byte words.
+
+@group(3) @binding(0) var<storage> vertex_data: array<u32>;
+
+fn load_f32x2(vertex_index: u32, offset: u32) -> vec2<f32> {
+    let word = vertex_index * #{VERTEX_PULLING_STRIDE}u + offset;
+    return bitcast<vec2<f32>>(vec2(vertex_data[word], vertex_data[word + 1u]));
+}
+
+fn load_f32x3(vertex_index: u32, offset: u32) -> vec3<f32> {
+    let word = vertex_index * #{VERTEX_PULLING_STRIDE}u + offset;
+    return bitcast<vec3<f32>>(vec3(vertex_data[word], vertex_data[word + 1u], vertex_data[word + 2u]));
+}
+
+fn load_f32x4(vertex_index: u32, offset: u32) -> vec4<f32> {
+    let word = vertex_index * #{VERTEX_PULLING_STRIDE}u + offset;
+    return bitcast<vec4<f32>>(vec4(
+        vertex_data[word],
+        vertex_data[word + 1u],
+        vertex_data[word + 2u],
+        vertex_data[word + 3u],
+    ));
+}
+
+fn position(vertex_index: u32) -> vec3<f32> {
+    return load_f32x3(vertex_index, #{VERTEX_PULLING_POSITION_OFFSET}u);
+}
+
+#ifdef VERTEX_PULLING_NORMAL_OFFSET
+fn normal(vertex_index: u32) -> vec3<f32> {
+    return load_f32x3(vertex_index, #{VERTEX_PULLING_NORMAL_OFFSET}u);
+}
+#endif
+
+#ifdef VERTEX_PULLING_UV_OFFSET
+fn uv(vertex_index: u32) -> vec2<f32> {
+    return load_f32x2(vertex_index, #{VERTEX_PULLING_UV_OFFSET}u);
+}
+#endif
+
+#ifdef VERTEX_PULLING_UV_B_OFFSET
+fn uv_b(vertex_index: u32) -> vec2<f32> {
+    return load_f32x2(vertex_index, #{VERTEX_PULLING_UV_B_OFFSET}u);
+}
+#endif
+
+#ifdef VERTEX_PULLING_TANGENT_OFFSET
+fn tangent(vertex_index: u32) -> vec4<f32> {
+    return load_f32x4(vertex_index, #{VERTEX_PULLING_TANGENT_OFFSET}u);
+}
+#endif
+
+#ifdef VERTEX_PULLING_COLOR_OFFSET
+fn color(vertex_index: u32) -> vec4<f32> {
+    return load_f32x4(vertex_index, #{VERTEX_PULLING_COLOR_OFFSET}u);
+}
+#endif
+
+#endif // VERTEX_PULLING
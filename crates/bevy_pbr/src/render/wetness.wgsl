@@ -0,0 +1,67 @@
+#define_import_path bevy_pbr::wetness
+
+#import bevy_pbr::{
+    mesh_view_bindings as view_bindings,
+    pbr_types::PbrInput,
+}
+
+// The perceptual roughness and reflectance of the water of the puddles.
+const PUDDLE_ROUGHNESS: f32 = 0.05;
+const PUDDLE_REFLECTANCE: f32 = 0.35;
+
+// Darkens and smooths the surface as it gets wet, and covers it with a puddle where water pools.
+fn apply_wetness(in: PbrInput) -> PbrInput {
+    var pbr_input = in;
+#ifdef WETNESS_BINDING
+    let wetness = view_bindings::wetness;
+
+    // Sample the base level, as this may run in non-uniform control flow.
+    let world_mask_uv = pbr_input.world_position.xz * wetness.world_mask_transform.xy
+        + wetness.world_mask_transform.zw;
+    let world_mask = textureSampleLevel(
+        view_bindings::wetness_world_mask_texture,
+        view_bindings::wetness_mask_sampler,
+        world_mask_uv,
+        0.0,
+    ).rg;
+    let viewport = view_bindings::view.viewport;
+    let screen_mask_uv = (pbr_input.frag_coord.xy - viewport.xy) / viewport.zw;
+    let screen_mask = textureSampleLevel(
+        view_bindings::wetness_screen_mask_texture,
+        view_bindings::wetness_mask_sampler,
+        screen_mask_uv,
+        0.0,
+    ).r;
+
+    let amount = saturate(wetness.wetness * world_mask.r * screen_mask);
+    if (amount <= 0.0) {
+        return pbr_input;
+    }
+
+    // Water fills the pores of dielectrics, which darkens them, and smooths every surface.
+    let dielectric = 1.0 - pbr_input.material.metallic;
+    let darkening = 1.0 - wetness.darkening * amount * dielectric;
+    pbr_input.material.base_color = vec4(
+        pbr_input.material.base_color.rgb * darkening,
+        pbr_input.material.base_color.a,
+    );
+    let roughness = pbr_input.material.perceptual_roughness;
+    pbr_input.material.perceptual_roughness =
+        mix(roughness, min(roughness, wetness.wet_roughness), amount);
+
+    // Puddles form on the up-facing surfaces, first where the green channel of the mask is highest.
+    let puddle_threshold = 1.0 - wetness.puddles;
+    let puddle = amount
+        * smoothstep(0.85, 0.95, pbr_input.world_normal.y)
+        * smoothstep(puddle_threshold, puddle_threshold + 0.1, world_mask.g);
+    if (puddle > 0.0) {
+        pbr_input.material.perceptual_roughness =
+            mix(pbr_input.material.perceptual_roughness, PUDDLE_ROUGHNESS, puddle);
+        pbr_input.material.reflectance = mix(pbr_input.material.reflectance, PUDDLE_REFLECTANCE, puddle);
+        pbr_input.material.metallic = mix(pbr_input.material.metallic, 0.0, puddle);
+        // The water surface is flat, whatever the normal map below.
+        pbr_input.N = normalize(mix(pbr_input.N, vec3(0.0, 1.0, 0.0), puddle));
+    }
+#endif // WETNESS_BINDING
+    return pbr_input;
+}
@@ -8,6 +8,7 @@
 #import bevy_pbr::gtao_utils::fast_acos
 
 #import bevy_render::{
+    blue_noise::{blue_noise_load, blue_noise_animate},
     view::View,
     globals::Globals,
     maths::{PI, HALF_PI},
@@ -15,7 +16,7 @@
 
 @group(0) @binding(0) var preprocessed_depth: texture_2d<f32>;
 @group(0) @binding(1) var normals: texture_2d<f32>;
-@group(0) @binding(2) var hilbert_index_lut: texture_2d<u32>;
+@group(0) @binding(2) var blue_noise_texture: texture_2d<f32>;
 @group(0) @binding(3) var ambient_occlusion: texture_storage_2d<r16float, write>;
 @group(0) @binding(4) var depth_differences: texture_storage_2d<r32uint, write>;
 @group(0) @binding(5) var<uniform> globals: Globals;
@@ -23,14 +24,13 @@
 @group(1) @binding(1) var<uniform> view: View;
 
 fn load_noise(pixel_coordinates: vec2<i32>) -> vec2<f32> {
-    var index = textureLoad(hilbert_index_lut, pixel_coordinates % 64, 0).r;
+    let noise = blue_noise_load(blue_noise_texture, vec2<u32>(pixel_coordinates));
 
 #ifdef TEMPORAL_JITTER
-    index += 288u * (globals.frame_count % 64u);
+    return blue_noise_animate(noise, globals.frame_count);
+#else
+    return noise;
 #endif
-
-    // R2 sequence - http://extremelearning.com.au/unreasonable-effectiveness-of-quasirandom-sequences
-    return fract(0.5 + f32(index) * vec2<f32>(0.75487766624669276005, 0.5698402909980532659114));
 }
 
 // Calculate differences in depth between neighbor pixels (later used by the spatial denoiser pass to preserve object edges)
@@ -1,10 +1,13 @@
 use crate::NodePbr;
-use bevy_app::{App, Plugin};
+use bevy_app::{App, Plugin, PostUpdate};
 use bevy_asset::{load_internal_asset, Handle};
 use bevy_core_pipeline::{
     core_3d::graph::{Core3d, Node3d},
     prelude::Camera3d,
-    prepass::{DepthPrepass, NormalPrepass, ViewPrepassTextures},
+    prepass::{
+        outputs::{check_prepass_requirements, PrepassOutputs},
+        DepthPrepass, NormalPrepass, ViewPrepassTextures,
+    },
 };
 use bevy_ecs::{
     prelude::{Bundle, Component, Entity},
@@ -27,8 +30,8 @@ use bevy_render::{
         },
         *,
     },
-    renderer::{RenderAdapter, RenderContext, RenderDevice, RenderQueue},
-    texture::{CachedTexture, TextureCache},
+    renderer::{RenderAdapter, RenderContext, RenderDevice},
+    texture::{BlueNoise, CachedTexture, TextureCache},
     view::{Msaa, ViewUniform, ViewUniformOffset, ViewUniforms},
     Extract, ExtractSchedule, Render, RenderApp, RenderSet,
 };
@@ -36,7 +39,6 @@ use bevy_utils::{
     prelude::default,
     tracing::{error, warn},
 };
-use std::mem;
 
 const PREPROCESS_DEPTH_SHADER_HANDLE: Handle<Shader> = Handle::weak_from_u128(102258915420479);
 const GTAO_SHADER_HANDLE: Handle<Shader> = Handle::weak_from_u128(253938746510568);
@@ -68,7 +70,13 @@ impl Plugin for ScreenSpaceAmbientOcclusionPlugin {
             Shader::from_wgsl
         );
 
-        app.register_type::<ScreenSpaceAmbientOcclusionSettings>();
+        app.register_type::<ScreenSpaceAmbientOcclusionSettings>()
+            .add_systems(
+                PostUpdate,
+                check_prepass_requirements::<ScreenSpaceAmbientOcclusionSettings>(
+                    PrepassOutputs::DEPTH_NORMAL,
+                ),
+            );
     }
 
     fn finish(&self, app: &mut App) {
@@ -307,38 +315,14 @@ struct SsaoPipelines {
     gtao_bind_group_layout: BindGroupLayout,
     spatial_denoise_bind_group_layout: BindGroupLayout,
 
-    hilbert_index_lut: TextureView,
     point_clamp_sampler: Sampler,
 }
 
 impl FromWorld for SsaoPipelines {
     fn from_world(world: &mut World) -> Self {
         let render_device = world.resource::<RenderDevice>();
-        let render_queue = world.resource::<RenderQueue>();
         let pipeline_cache = world.resource::<PipelineCache>();
 
-        let hilbert_index_lut = render_device
-            .create_texture_with_data(
-                render_queue,
-                &(TextureDescriptor {
-                    label: Some("ssao_hilbert_index_lut"),
-                    size: Extent3d {
-                        width: HILBERT_WIDTH as u32,
-                        height: HILBERT_WIDTH as u32,
-                        depth_or_array_layers: 1,
-                    },
-                    mip_level_count: 1,
-                    sample_count: 1,
-                    dimension: TextureDimension::D2,
-                    format: TextureFormat::R16Uint,
-                    usage: TextureUsages::TEXTURE_BINDING,
-                    view_formats: &[],
-                }),
-                TextureDataOrder::default(),
-                bytemuck::cast_slice(&generate_hilbert_index_lut()),
-            )
-            .create_view(&TextureViewDescriptor::default());
-
         let point_clamp_sampler = render_device.create_sampler(&SamplerDescriptor {
             min_filter: FilterMode::Nearest,
             mag_filter: FilterMode::Nearest,
@@ -381,7 +365,7 @@ impl FromWorld for SsaoPipelines {
                 (
                     texture_2d(TextureSampleType::Float { filterable: false }),
                     texture_2d(TextureSampleType::Float { filterable: false }),
-                    texture_2d(TextureSampleType::Uint),
+                    texture_2d(TextureSampleType::Float { filterable: false }),
                     texture_storage_2d(TextureFormat::R16Float, StorageTextureAccess::WriteOnly),
                     texture_storage_2d(TextureFormat::R32Uint, StorageTextureAccess::WriteOnly),
                     uniform_buffer::<GlobalsUniform>(false),
@@ -436,7 +420,6 @@ impl FromWorld for SsaoPipelines {
             gtao_bind_group_layout,
             spatial_denoise_bind_group_layout,
 
-            hilbert_index_lut,
             point_clamp_sampler,
         }
     }
@@ -529,8 +512,9 @@ fn prepare_ssao_textures(
             depth_or_array_layers: 1,
         };
 
-        let preprocessed_depth_texture = texture_cache.get(
+        let preprocessed_depth_texture = texture_cache.get_for_view(
             &render_device,
+            entity,
             TextureDescriptor {
                 label: Some("ssao_preprocessed_depth_texture"),
                 size,
@@ -543,8 +527,9 @@ fn prepare_ssao_textures(
             },
         );
 
-        let ssao_noisy_texture = texture_cache.get(
+        let ssao_noisy_texture = texture_cache.get_for_view(
             &render_device,
+            entity,
             TextureDescriptor {
                 label: Some("ssao_noisy_texture"),
                 size,
@@ -557,8 +542,9 @@ fn prepare_ssao_textures(
             },
         );
 
-        let ssao_texture = texture_cache.get(
+        let ssao_texture = texture_cache.get_for_view(
             &render_device,
+            entity,
             TextureDescriptor {
                 label: Some("ssao_texture"),
                 size,
@@ -571,8 +557,9 @@ fn prepare_ssao_textures(
             },
         );
 
-        let depth_differences_texture = texture_cache.get(
+        let depth_differences_texture = texture_cache.get_for_view(
             &render_device,
+            entity,
             TextureDescriptor {
                 label: Some("ssao_depth_differences_texture"),
                 size,
@@ -638,6 +625,7 @@ fn prepare_ssao_bind_groups(
     pipelines: Res<SsaoPipelines>,
     view_uniforms: Res<ViewUniforms>,
     global_uniforms: Res<GlobalsBuffer>,
+    blue_noise: Res<BlueNoise>,
     views: Query<(
         Entity,
         &ScreenSpaceAmbientOcclusionTextures,
@@ -691,7 +679,7 @@ fn prepare_ssao_bind_groups(
             &BindGroupEntries::sequential((
                 &ssao_textures.preprocessed_depth_texture.default_view,
                 prepass_textures.normal_view().unwrap(),
-                &pipelines.hilbert_index_lut,
+                &blue_noise.texture_view,
                 &ssao_textures.ssao_noisy_texture.default_view,
                 &ssao_textures.depth_differences_texture.default_view,
                 globals_uniforms.clone(),
@@ -719,45 +707,6 @@ fn prepare_ssao_bind_groups(
     }
 }
 
-#[allow(clippy::needless_range_loop)]
-fn generate_hilbert_index_lut() -> [[u16; 64]; 64] {
-    let mut t = [[0; 64]; 64];
-
-    for x in 0..64 {
-        for y in 0..64 {
-            t[x][y] = hilbert_index(x as u16, y as u16);
-        }
-    }
-
-    t
-}
-
-// https://www.shadertoy.com/view/3tB3z3
-const HILBERT_WIDTH: u16 = 64;
-fn hilbert_index(mut x: u16, mut y: u16) -> u16 {
-    let mut index = 0;
-
-    let mut level: u16 = HILBERT_WIDTH / 2;
-    while level > 0 {
-        let region_x = (x & level > 0) as u16;
-        let region_y = (y & level > 0) as u16;
-        index += level * level * ((3 * region_x) ^ region_y);
-
-        if region_y == 0 {
-            if region_x == 1 {
-                x = HILBERT_WIDTH - 1 - x;
-                y = HILBERT_WIDTH - 1 - y;
-            }
-
-            mem::swap(&mut x, &mut y);
-        }
-
-        level /= 2;
-    }
-
-    index
-}
-
 /// Divide `numerator` by `denominator`, rounded up to the nearest multiple of `denominator`.
 fn div_ceil(numerator: u32, denominator: u32) -> u32 {
     (numerator + denominator - 1) / denominator
@@ -0,0 +1,372 @@
+use std::marker::PhantomData;
+
+use bevy_app::{App, Plugin, PostUpdate};
+use bevy_asset::{AssetId, Assets, Handle, UntypedAssetId};
+use bevy_ecs::prelude::*;
+use bevy_math::{IVec3, Rect, Vec2};
+use bevy_reflect::{std_traits::ReflectDefault, Reflect};
+use bevy_render::{
+    mesh::{
+        morph::MeshMorphWeights, skinning::SkinnedMesh, Indices, Mesh, MeshVertexAttributeId,
+        PrimitiveTopology, VertexAttributeValues,
+    },
+    prelude::SpatialBundle,
+    render_resource::VertexFormat,
+    texture::Image,
+    view::{RenderLayers, Visibility, VisibilitySystems},
+};
+use bevy_transform::{
+    components::{GlobalTransform, Transform},
+    TransformSystem,
+};
+use bevy_utils::HashMap;
+
+use crate::{Lightmap, Material, NotShadowCaster, NotShadowReceiver};
+
+/// Merges the meshes with [`StaticBatch`] that share a material of type `M` into combined meshes,
+/// one per cell of a world-space grid, see [`StaticBatchingSettings`].
+///
+/// This trades memory for fewer draw calls, on platforms where GPU-driven indirect draws aren't
+/// available to batch meshes that don't share a mesh asset.
+pub struct StaticBatchingPlugin<M: Material>(PhantomData<M>);
+
+impl<M: Material> Default for StaticBatchingPlugin<M> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<M: Material> Plugin for StaticBatchingPlugin<M> {
+    fn build(&self, app: &mut App) {
+        app.register_type::<StaticBatch>()
+            .init_resource::<StaticBatchingSettings>()
+            .add_systems(
+                PostUpdate,
+                bake_static_batches::<M>
+                    .after(TransformSystem::TransformPropagate)
+                    .before(VisibilitySystems::CalculateBounds),
+            );
+    }
+}
+
+/// Marks a mesh entity as static, so that the [`StaticBatchingPlugin`] merges it with the other
+/// static meshes of its cell that share its material.
+///
+/// The mesh is baked in world space once its [`Mesh`] asset is loaded, so later changes to its
+/// transform, mesh or material aren't reflected. Meshes loaded later than the others of their cell
+/// are baked in another batch. Skinned and morphed meshes, and meshes with a strip topology,
+/// aren't batched.
+#[derive(Component, Clone, Copy, Debug, Default, Reflect)]
+#[reflect(Component, Default)]
+pub struct StaticBatch;
+
+/// Added to the [`StaticBatch`] entities once they're baked, which are then hidden.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct StaticBatched {
+    /// The entity drawing the combined mesh.
+    pub batch: Entity,
+}
+
+/// The entity drawing a combined mesh baked by the [`StaticBatchingPlugin`].
+#[derive(Component, Clone, Debug)]
+pub struct BakedStaticBatch {
+    /// The cell of the batch, see [`StaticBatchingSettings::cell_size`].
+    pub cell: IVec3,
+    /// The [`StaticBatch`] entities merged into the batch.
+    pub members: Vec<Entity>,
+}
+
+/// How the [`StaticBatchingPlugin`] groups meshes.
+#[derive(Resource, Clone, Debug)]
+pub struct StaticBatchingSettings {
+    /// The size of the cells of the world-space grid meshes are batched in, so that batches can
+    /// still be frustum culled. Meshes are assigned to the cell of their translation.
+    pub cell_size: f32,
+    /// The maximum number of vertices of a combined mesh. Larger groups are split.
+    pub max_vertices: usize,
+}
+
+impl Default for StaticBatchingSettings {
+    fn default() -> Self {
+        Self {
+            cell_size: 32.0,
+            max_vertices: 1 << 20,
+        }
+    }
+}
+
+/// The meshes merged into the same batch must share their vertex layout and draw state.
+#[derive(PartialEq, Eq, Hash)]
+struct StaticBatchKey {
+    material: UntypedAssetId,
+    cell: IVec3,
+    topology: PrimitiveTopology,
+    attributes: Vec<(MeshVertexAttributeId, VertexFormat)>,
+    indexed: bool,
+    lightmap: Option<AssetId<Image>>,
+    render_layers: Option<Vec<u8>>,
+    not_shadow_caster: bool,
+    not_shadow_receiver: bool,
+}
+
+struct StaticBatchMember {
+    entity: Entity,
+    mesh: AssetId<Mesh>,
+    transform: Transform,
+    lightmap_uv_rect: Option<Rect>,
+}
+
+#[allow(clippy::type_complexity)]
+fn bake_static_batches<M: Material>(
+    mut commands: Commands,
+    settings: Res<StaticBatchingSettings>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    static_meshes: Query<
+        (
+            Entity,
+            &Handle<Mesh>,
+            &Handle<M>,
+            &GlobalTransform,
+            Option<&Lightmap>,
+            Option<&RenderLayers>,
+            Has<NotShadowCaster>,
+            Has<NotShadowReceiver>,
+        ),
+        (
+            With<StaticBatch>,
+            Without<StaticBatched>,
+            Without<SkinnedMesh>,
+            Without<MeshMorphWeights>,
+        ),
+    >,
+) {
+    let mut groups: HashMap<StaticBatchKey, (Handle<M>, Option<Handle<Image>>, Vec<_>)> =
+        HashMap::default();
+    for (
+        entity,
+        mesh_handle,
+        material,
+        transform,
+        lightmap,
+        render_layers,
+        not_shadow_caster,
+        not_shadow_receiver,
+    ) in &static_meshes
+    {
+        let Some(mesh) = meshes.get(mesh_handle) else {
+            continue;
+        };
+        if !can_batch(mesh, lightmap.is_some()) {
+            continue;
+        }
+        let key = StaticBatchKey {
+            material: material.id().untyped(),
+            cell: (transform.translation() / settings.cell_size)
+                .floor()
+                .as_ivec3(),
+            topology: mesh.primitive_topology(),
+            attributes: mesh
+                .attributes()
+                .map(|(id, values)| (id, VertexFormat::from(values)))
+                .collect(),
+            indexed: mesh.indices().is_some(),
+            lightmap: lightmap.map(|lightmap| lightmap.image.id()),
+            render_layers: render_layers.map(|render_layers| render_layers.iter().collect()),
+            not_shadow_caster,
+            not_shadow_receiver,
+        };
+        groups
+            .entry(key)
+            .or_insert_with(|| {
+                (
+                    material.clone(),
+                    lightmap.map(|lightmap| lightmap.image.clone()),
+                    Vec::new(),
+                )
+            })
+            .2
+            .push(StaticBatchMember {
+                entity,
+                mesh: mesh_handle.id(),
+                transform: transform.compute_transform(),
+                lightmap_uv_rect: lightmap.map(|lightmap| lightmap.uv_rect),
+            });
+    }
+
+    for (key, (material, lightmap, members)) in groups {
+        // Split the group into batches of at most `max_vertices` vertices.
+        let mut batches: Vec<(usize, Vec<StaticBatchMember>)> = vec![(0, Vec::new())];
+        for member in members {
+            let vertex_count = meshes.get(member.mesh).map_or(0, Mesh::count_vertices);
+            let (batch_vertex_count, batch_members) = batches.last_mut().unwrap();
+            if !batch_members.is_empty()
+                && *batch_vertex_count + vertex_count > settings.max_vertices
+            {
+                batches.push((vertex_count, vec![member]));
+            } else {
+                *batch_vertex_count += vertex_count;
+                batch_members.push(member);
+            }
+        }
+
+        for (_, batch_members) in batches {
+            // A single mesh wouldn't save any draw call.
+            if batch_members.len() < 2 {
+                continue;
+            }
+            let combined_mesh = combine_meshes(batch_members.iter().filter_map(|member| {
+                Some((
+                    meshes.get(member.mesh)?,
+                    member.transform,
+                    member.lightmap_uv_rect,
+                ))
+            }));
+
+            let mut batch = commands.spawn((
+                meshes.add(combined_mesh),
+                material.clone(),
+                SpatialBundle::INHERITED_IDENTITY,
+                BakedStaticBatch {
+                    cell: key.cell,
+                    members: batch_members.iter().map(|member| member.entity).collect(),
+                },
+            ));
+            if let Some(image) = &lightmap {
+                // The lightmap UVs of the members are already remapped to their rects.
+                batch.insert(Lightmap {
+                    image: image.clone(),
+                    uv_rect: Rect::new(0.0, 0.0, 1.0, 1.0),
+                });
+            }
+            if let Some(render_layers) = &key.render_layers {
+                batch.insert(RenderLayers::from_layers(render_layers));
+            }
+            if key.not_shadow_caster {
+                batch.insert(NotShadowCaster);
+            }
+            if key.not_shadow_receiver {
+                batch.insert(NotShadowReceiver);
+            }
+            let batch = batch.id();
+
+            for member in &batch_members {
+                commands
+                    .entity(member.entity)
+                    .insert((StaticBatched { batch }, Visibility::Hidden));
+            }
+        }
+    }
+}
+
+/// Whether `mesh` can be appended to other meshes, and its lightmap UVs remapped if it has a
+/// lightmap.
+fn can_batch(mesh: &Mesh, lightmapped: bool) -> bool {
+    let list_topology = matches!(
+        mesh.primitive_topology(),
+        PrimitiveTopology::TriangleList
+            | PrimitiveTopology::LineList
+            | PrimitiveTopology::PointList
+    );
+    let lightmap_uvs = !lightmapped
+        || matches!(
+            mesh.attribute(Mesh::ATTRIBUTE_UV_1),
+            Some(VertexAttributeValues::Float32x2(_))
+        );
+    list_topology && lightmap_uvs && !mesh.has_morph_targets()
+}
+
+/// Combines the meshes, which must share their topology and vertex attributes, in the space of
+/// their transforms.
+///
+/// The lightmap UVs of the meshes with a lightmap UV rect are remapped to it, so that the combined
+/// mesh can use the whole lightmap texture.
+fn combine_meshes<'a>(
+    meshes: impl IntoIterator<Item = (&'a Mesh, Transform, Option<Rect>)>,
+) -> Mesh {
+    let mut combined_mesh: Option<Mesh> = None;
+    for (mesh, transform, lightmap_uv_rect) in meshes {
+        let mut mesh = mesh.clone().transformed_by(transform);
+        if let (Some(uv_rect), Some(VertexAttributeValues::Float32x2(uvs))) =
+            (lightmap_uv_rect, mesh.attribute_mut(Mesh::ATTRIBUTE_UV_1))
+        {
+            for uv in uvs.iter_mut() {
+                *uv = (uv_rect.min + Vec2::from(*uv) * uv_rect.size()).to_array();
+            }
+        }
+        match &mut combined_mesh {
+            Some(combined_mesh) => combined_mesh.merge(mesh),
+            None => {
+                // Batches can exceed the range of 16-bit indices.
+                if let Some(Indices::U16(indices)) = mesh.indices() {
+                    let indices = indices.iter().map(|index| *index as u32).collect();
+                    mesh.insert_indices(Indices::U32(indices));
+                }
+                combined_mesh = Some(mesh);
+            }
+        }
+    }
+    combined_mesh.unwrap_or_else(|| Mesh::new(PrimitiveTopology::TriangleList, Default::default()))
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_math::{Rect, Vec3};
+    use bevy_render::{
+        mesh::{Indices, Mesh, PrimitiveTopology, VertexAttributeValues},
+        render_asset::RenderAssetUsages,
+    };
+    use bevy_transform::components::Transform;
+
+    use super::combine_meshes;
+
+    fn triangle() -> Mesh {
+        Mesh::new(
+            PrimitiveTopology::TriangleList,
+            RenderAssetUsages::default(),
+        )
+        .with_inserted_attribute(
+            Mesh::ATTRIBUTE_POSITION,
+            vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]],
+        )
+        .with_inserted_attribute(
+            Mesh::ATTRIBUTE_UV_1,
+            vec![[0.0, 0.0], [1.0, 0.0], [0.0, 1.0]],
+        )
+        .with_inserted_indices(Indices::U16(vec![0, 1, 2]))
+    }
+
+    #[test]
+    fn combine_meshes_transforms_and_remaps_lightmap_uvs() {
+        let triangle = triangle();
+        let combined_mesh = combine_meshes([
+            (&triangle, Transform::IDENTITY, None),
+            (
+                &triangle,
+                Transform::from_translation(Vec3::X * 10.0),
+                Some(Rect::new(0.5, 0.5, 1.0, 0.75)),
+            ),
+        ]);
+
+        let Some(VertexAttributeValues::Float32x3(positions)) =
+            combined_mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+        else {
+            panic!("missing positions");
+        };
+        assert_eq!(positions.len(), 6);
+        assert_eq!(positions[4], [11.0, 0.0, 0.0]);
+
+        let Some(VertexAttributeValues::Float32x2(uvs)) =
+            combined_mesh.attribute(Mesh::ATTRIBUTE_UV_1)
+        else {
+            panic!("missing lightmap UVs");
+        };
+        assert_eq!(uvs[1], [1.0, 0.0]);
+        assert_eq!(uvs[4], [1.0, 0.5]);
+        assert_eq!(uvs[5], [0.5, 0.75]);
+
+        let Some(Indices::U32(indices)) = combined_mesh.indices() else {
+            panic!("indices should be widened to 32 bits");
+        };
+        assert_eq!(indices, &vec![0, 1, 2, 3, 4, 5]);
+    }
+}
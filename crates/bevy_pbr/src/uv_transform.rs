@@ -0,0 +1,101 @@
+use bevy_ecs::prelude::*;
+use bevy_math::{Affine2, Vec2};
+use bevy_reflect::{std_traits::ReflectDefault, Reflect};
+
+/// Transforms the texture coordinates of a mesh, for this entity only.
+///
+/// The transform is written into the per-instance mesh uniform of the entity
+/// and applied to the first UV channel in the mesh vertex shaders, before any
+/// UV transform of the material. This lets instances that share a material
+/// scroll or tile its textures independently, without a material asset per
+/// instance, while still being batched together.
+///
+/// The UVs are scaled, then rotated, then offset.
+#[derive(Component, Reflect, Clone, Copy, Debug, PartialEq)]
+#[reflect(Component, Default, PartialEq)]
+pub struct UvTransform {
+    /// Added to the UVs, after scaling and rotating them.
+    pub offset: Vec2,
+    /// Multiplies the UVs.
+    pub scale: Vec2,
+    /// Rotates the UVs counterclockwise around the origin, in radians.
+    pub rotation: f32,
+}
+
+impl Default for UvTransform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl UvTransform {
+    /// Leaves the UVs unchanged.
+    pub const IDENTITY: Self = Self {
+        offset: Vec2::ZERO,
+        scale: Vec2::ONE,
+        rotation: 0.0,
+    };
+
+    /// Creates a transform that only offsets the UVs, e.g. to scroll a texture.
+    pub const fn from_offset(offset: Vec2) -> Self {
+        Self {
+            offset,
+            ..Self::IDENTITY
+        }
+    }
+
+    /// Creates a transform that only scales the UVs, e.g. to tile a texture.
+    pub const fn from_scale(scale: Vec2) -> Self {
+        Self {
+            scale,
+            ..Self::IDENTITY
+        }
+    }
+
+    /// Sets [`Self::offset`].
+    pub const fn with_offset(mut self, offset: Vec2) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    /// Sets [`Self::scale`].
+    pub const fn with_scale(mut self, scale: Vec2) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Sets [`Self::rotation`].
+    pub const fn with_rotation(mut self, rotation: f32) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// Returns the transform as an affine matrix applied to the UVs.
+    pub fn to_affine2(&self) -> Affine2 {
+        Affine2::from_scale_angle_translation(self.scale, self.rotation, self.offset)
+    }
+}
+
+impl From<UvTransform> for Affine2 {
+    fn from(transform: UvTransform) -> Self {
+        transform.to_affine2()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_math::{Affine2, Vec2};
+
+    use super::UvTransform;
+
+    #[test]
+    fn scales_then_rotates_then_offsets() {
+        assert_eq!(UvTransform::default().to_affine2(), Affine2::IDENTITY);
+
+        let transform = UvTransform::from_scale(Vec2::new(2.0, 1.0))
+            .with_rotation(std::f32::consts::FRAC_PI_2)
+            .with_offset(Vec2::new(0.5, 0.0));
+        let uv = transform.to_affine2().transform_point2(Vec2::new(1.0, 0.0));
+        assert!((uv - Vec2::new(0.5, 2.0)).length() < 1e-6, "{uv}");
+    }
+}
@@ -0,0 +1,441 @@
+use std::fmt;
+
+use bevy_math::{Affine3A, Vec3, Vec3A};
+use bevy_render::mesh::{Indices, Mesh, PrimitiveTopology, VertexAttributeValues};
+
+/// Bakes per-vertex ambient occlusion into the [`Mesh::ATTRIBUTE_COLOR`] of meshes, as a
+/// lightweight alternative to lightmaps for stylized projects.
+///
+/// Rays are cast from each vertex over the hemisphere around its normal, against the mesh
+/// itself and the occluders added with [`VertexOcclusionBaker::add_occluder`]. The fraction of
+/// rays that escape is written into the red, green and blue channels of the vertex colors, which
+/// [`VertexColorMode::Occlusion`](crate::VertexColorMode::Occlusion) applies as diffuse
+/// occlusion instead of base color.
+///
+/// Baking runs on the CPU and is meant to happen ahead of time or while loading, since its cost
+/// grows with the number of vertices times [`VertexOcclusionSettings::ray_count`].
+///
+/// ```
+/// # use bevy_math::Affine3A;
+/// # use bevy_pbr::{VertexOcclusionBaker, VertexOcclusionSettings};
+/// # use bevy_render::mesh::{Mesh, Meshable};
+/// # use bevy_math::primitives::Cuboid;
+/// let mut mesh = Cuboid::default().mesh();
+/// let floor = Cuboid::new(10.0, 1.0, 10.0).mesh();
+/// let mut baker = VertexOcclusionBaker::new(VertexOcclusionSettings::default());
+/// baker
+///     .add_occluder(&floor, Affine3A::from_translation([0.0, -1.0, 0.0].into()))
+///     .unwrap();
+/// baker.bake(&mut mesh, Affine3A::IDENTITY).unwrap();
+/// ```
+pub struct VertexOcclusionBaker {
+    settings: VertexOcclusionSettings,
+    occluders: Vec<Triangle>,
+}
+
+/// How a [`VertexOcclusionBaker`] casts its rays.
+#[derive(Clone, Copy, Debug)]
+pub struct VertexOcclusionSettings {
+    /// The number of rays cast from each vertex.
+    pub ray_count: u32,
+    /// The distance beyond which geometry doesn't occlude the vertices, in world units.
+    ///
+    /// Smaller distances keep the occlusion local, like contact shadows in creases.
+    pub max_distance: f32,
+    /// How far along their normal the rays start from the vertices, to avoid hitting the
+    /// triangles around them.
+    pub bias: f32,
+    /// The occlusion is raised to this power, values above 1 darken it.
+    pub contrast: f32,
+}
+
+impl Default for VertexOcclusionSettings {
+    fn default() -> Self {
+        Self {
+            ray_count: 64,
+            max_distance: 1.0,
+            bias: 1e-3,
+            contrast: 1.0,
+        }
+    }
+}
+
+/// An error produced by a [`VertexOcclusionBaker`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VertexOcclusionBakeError {
+    /// The mesh primitive topology isn't [`PrimitiveTopology::TriangleList`].
+    WrongMeshPrimitiveTopology,
+    /// The mesh doesn't have [`Mesh::ATTRIBUTE_POSITION`] in [`VertexAttributeValues::Float32x3`].
+    MissingPositions,
+    /// The baked mesh doesn't have [`Mesh::ATTRIBUTE_NORMAL`] in
+    /// [`VertexAttributeValues::Float32x3`].
+    MissingNormals,
+}
+
+impl fmt::Display for VertexOcclusionBakeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VertexOcclusionBakeError::WrongMeshPrimitiveTopology => {
+                write!(f, "Mesh primitive topology is not TriangleList")
+            }
+            VertexOcclusionBakeError::MissingPositions => {
+                write!(f, "Mesh has no Float32x3 positions")
+            }
+            VertexOcclusionBakeError::MissingNormals => write!(f, "Mesh has no Float32x3 normals"),
+        }
+    }
+}
+
+impl std::error::Error for VertexOcclusionBakeError {}
+
+impl VertexOcclusionBaker {
+    pub fn new(settings: VertexOcclusionSettings) -> Self {
+        Self {
+            settings,
+            occluders: Vec::new(),
+        }
+    }
+
+    /// Adds the triangles of `mesh`, placed in the world with `transform`, to the geometry
+    /// occluding the baked meshes.
+    pub fn add_occluder(
+        &mut self,
+        mesh: &Mesh,
+        transform: Affine3A,
+    ) -> Result<&mut Self, VertexOcclusionBakeError> {
+        self.occluders.extend(mesh_triangles(mesh, transform)?);
+        Ok(self)
+    }
+
+    /// Bakes the occlusion of `mesh`, placed in the world with `transform`, by itself and the
+    /// occluders, into its vertex colors.
+    ///
+    /// Existing vertex colors in [`VertexAttributeValues::Float32x4`] keep their alpha.
+    pub fn bake(
+        &self,
+        mesh: &mut Mesh,
+        transform: Affine3A,
+    ) -> Result<(), VertexOcclusionBakeError> {
+        let occlusion = self.compute(mesh, transform)?;
+
+        let colors = match mesh.attribute(Mesh::ATTRIBUTE_COLOR) {
+            Some(VertexAttributeValues::Float32x4(colors)) if colors.len() == occlusion.len() => {
+                occlusion
+                    .iter()
+                    .zip(colors)
+                    .map(|(&occlusion, color)| [occlusion, occlusion, occlusion, color[3]])
+                    .collect()
+            }
+            _ => occlusion
+                .iter()
+                .map(|&occlusion| [occlusion, occlusion, occlusion, 1.0])
+                .collect::<Vec<_>>(),
+        };
+        mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+        Ok(())
+    }
+
+    /// Computes the occlusion of each vertex of `mesh`, from 0 for fully occluded to 1 for
+    /// unoccluded, without modifying it.
+    pub fn compute(
+        &self,
+        mesh: &Mesh,
+        transform: Affine3A,
+    ) -> Result<Vec<f32>, VertexOcclusionBakeError> {
+        let Some(VertexAttributeValues::Float32x3(positions)) =
+            mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+        else {
+            return Err(VertexOcclusionBakeError::MissingPositions);
+        };
+        let Some(VertexAttributeValues::Float32x3(normals)) =
+            mesh.attribute(Mesh::ATTRIBUTE_NORMAL)
+        else {
+            return Err(VertexOcclusionBakeError::MissingNormals);
+        };
+
+        let mut triangles = mesh_triangles(mesh, transform)?;
+        triangles.extend_from_slice(&self.occluders);
+        let bvh = Bvh::new(triangles);
+
+        let directions = hemisphere_directions(self.settings.ray_count.max(1));
+        let normal_transform = transform.matrix3.inverse().transpose();
+
+        Ok(positions
+            .iter()
+            .zip(normals)
+            .map(|(&position, &normal)| {
+                let position = transform.transform_point3a(position.into());
+                let normal = (normal_transform * Vec3A::from(normal)).normalize_or_zero();
+                if normal == Vec3A::ZERO {
+                    return 1.0;
+                }
+                let (tangent, bitangent) = Vec3::from(normal).any_orthonormal_pair();
+                let (tangent, bitangent) = (Vec3A::from(tangent), Vec3A::from(bitangent));
+                let origin = position + normal * self.settings.bias;
+
+                let escaped = directions
+                    .iter()
+                    .filter(|direction| {
+                        let direction =
+                            tangent * direction.x + bitangent * direction.y + normal * direction.z;
+                        !bvh.hits(origin, direction, self.settings.max_distance)
+                    })
+                    .count();
+                (escaped as f32 / directions.len() as f32).powf(self.settings.contrast)
+            })
+            .collect())
+    }
+}
+
+/// Bakes the occlusion of `mesh` by itself into its vertex colors, see [`VertexOcclusionBaker`].
+pub fn bake_vertex_occlusion(
+    mesh: &mut Mesh,
+    settings: VertexOcclusionSettings,
+) -> Result<(), VertexOcclusionBakeError> {
+    VertexOcclusionBaker::new(settings).bake(mesh, Affine3A::IDENTITY)
+}
+
+#[derive(Clone, Copy)]
+struct Triangle([Vec3A; 3]);
+
+impl Triangle {
+    fn min(&self) -> Vec3A {
+        self.0[0].min(self.0[1]).min(self.0[2])
+    }
+
+    fn max(&self) -> Vec3A {
+        self.0[0].max(self.0[1]).max(self.0[2])
+    }
+
+    fn centroid(&self) -> Vec3A {
+        (self.0[0] + self.0[1] + self.0[2]) / 3.0
+    }
+
+    /// Möller–Trumbore ray-triangle intersection, hitting both faces.
+    fn hits(&self, origin: Vec3A, direction: Vec3A, max_distance: f32) -> bool {
+        let edge_1 = self.0[1] - self.0[0];
+        let edge_2 = self.0[2] - self.0[0];
+        let p = direction.cross(edge_2);
+        let determinant = edge_1.dot(p);
+        if determinant.abs() < f32::EPSILON {
+            return false;
+        }
+        let inverse_determinant = 1.0 / determinant;
+        let t = origin - self.0[0];
+        let u = t.dot(p) * inverse_determinant;
+        if !(0.0..=1.0).contains(&u) {
+            return false;
+        }
+        let q = t.cross(edge_1);
+        let v = direction.dot(q) * inverse_determinant;
+        if v < 0.0 || u + v > 1.0 {
+            return false;
+        }
+        let distance = edge_2.dot(q) * inverse_determinant;
+        distance > 0.0 && distance <= max_distance
+    }
+}
+
+fn mesh_triangles(
+    mesh: &Mesh,
+    transform: Affine3A,
+) -> Result<Vec<Triangle>, VertexOcclusionBakeError> {
+    if mesh.primitive_topology() != PrimitiveTopology::TriangleList {
+        return Err(VertexOcclusionBakeError::WrongMeshPrimitiveTopology);
+    }
+    let Some(VertexAttributeValues::Float32x3(positions)) =
+        mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+    else {
+        return Err(VertexOcclusionBakeError::MissingPositions);
+    };
+    let positions: Vec<Vec3A> = positions
+        .iter()
+        .map(|&position| transform.transform_point3a(position.into()))
+        .collect();
+
+    let indices: Vec<usize> = match mesh.indices() {
+        Some(Indices::U16(indices)) => indices.iter().map(|&index| index as usize).collect(),
+        Some(Indices::U32(indices)) => indices.iter().map(|&index| index as usize).collect(),
+        None => (0..positions.len()).collect(),
+    };
+    Ok(indices
+        .chunks_exact(3)
+        .map(|triangle| {
+            Triangle([
+                positions[triangle[0]],
+                positions[triangle[1]],
+                positions[triangle[2]],
+            ])
+        })
+        .collect())
+}
+
+/// Cosine-weighted directions over the hemisphere around +Z, from a Hammersley sequence so that
+/// every vertex gets the same well-distributed rays.
+fn hemisphere_directions(count: u32) -> Vec<Vec3A> {
+    (0..count)
+        .map(|index| {
+            let u = (index as f32 + 0.5) / count as f32;
+            let v = index.reverse_bits() as f32 / (u32::MAX as f32 + 1.0);
+            let radius = u.sqrt();
+            let angle = std::f32::consts::TAU * v;
+            Vec3A::new(
+                radius * angle.cos(),
+                radius * angle.sin(),
+                (1.0 - u).max(0.0).sqrt(),
+            )
+        })
+        .collect()
+}
+
+/// The number of triangles below which a [`Bvh`] node isn't split further.
+const BVH_LEAF_SIZE: usize = 4;
+
+/// A bounding volume hierarchy over triangles, so that the rays only test the triangles near
+/// them.
+struct Bvh {
+    nodes: Vec<BvhNode>,
+    triangles: Vec<Triangle>,
+}
+
+struct BvhNode {
+    min: Vec3A,
+    max: Vec3A,
+    /// The first triangle of a leaf, or the index of the second child of an inner node, whose
+    /// first child follows it.
+    start: usize,
+    /// The number of triangles of a leaf, 0 for an inner node.
+    count: usize,
+}
+
+impl Bvh {
+    fn new(mut triangles: Vec<Triangle>) -> Self {
+        let mut nodes = Vec::new();
+        if !triangles.is_empty() {
+            let len = triangles.len();
+            Self::build(&mut nodes, &mut triangles, 0, len);
+        }
+        Self { nodes, triangles }
+    }
+
+    fn build(nodes: &mut Vec<BvhNode>, triangles: &mut [Triangle], start: usize, end: usize) {
+        let (min, max) = triangles[start..end].iter().fold(
+            (Vec3A::splat(f32::MAX), Vec3A::splat(f32::MIN)),
+            |(min, max), triangle| (min.min(triangle.min()), max.max(triangle.max())),
+        );
+        let index = nodes.len();
+        nodes.push(BvhNode {
+            min,
+            max,
+            start,
+            count: end - start,
+        });
+        if end - start <= BVH_LEAF_SIZE {
+            return;
+        }
+
+        // Split at the median centroid along the longest axis.
+        let extent = max - min;
+        let axis = if extent.x >= extent.y && extent.x >= extent.z {
+            0
+        } else if extent.y >= extent.z {
+            1
+        } else {
+            2
+        };
+        let middle = (start + end) / 2;
+        triangles[start..end].select_nth_unstable_by(middle - start, |a, b| {
+            a.centroid()[axis].total_cmp(&b.centroid()[axis])
+        });
+
+        Self::build(nodes, triangles, start, middle);
+        let second_child = nodes.len();
+        Self::build(nodes, triangles, middle, end);
+        nodes[index].start = second_child;
+        nodes[index].count = 0;
+    }
+
+    /// Whether the ray hits a triangle closer than `max_distance`.
+    fn hits(&self, origin: Vec3A, direction: Vec3A, max_distance: f32) -> bool {
+        if self.nodes.is_empty() {
+            return false;
+        }
+        let inverse_direction = direction.recip();
+        let mut stack = vec![0];
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            // Slab test against the bounds of the node.
+            let t_1 = (node.min - origin) * inverse_direction;
+            let t_2 = (node.max - origin) * inverse_direction;
+            let t_near = t_1.min(t_2).max_element();
+            let t_far = t_1.max(t_2).min_element();
+            if t_near > t_far || t_far < 0.0 || t_near > max_distance {
+                continue;
+            }
+
+            if node.count > 0 {
+                if self.triangles[node.start..node.start + node.count]
+                    .iter()
+                    .any(|triangle| triangle.hits(origin, direction, max_distance))
+                {
+                    return true;
+                }
+            } else {
+                stack.push(index + 1);
+                stack.push(node.start);
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_math::{primitives::Plane3d, Affine3A, Vec2, Vec3};
+    use bevy_render::mesh::{Mesh, Meshable, VertexAttributeValues};
+
+    use super::{VertexOcclusionBaker, VertexOcclusionSettings};
+
+    #[test]
+    fn vertex_occlusion_darkens_vertices_under_occluders() {
+        let mut floor = Plane3d::new(Vec3::Y, Vec2::splat(1.0)).mesh().build();
+        let roof = Plane3d::new(Vec3::Y, Vec2::splat(0.5)).mesh().build();
+
+        let mut baker = VertexOcclusionBaker::new(VertexOcclusionSettings {
+            max_distance: 10.0,
+            ..VertexOcclusionSettings::default()
+        });
+        // An open floor isn't occluded by itself.
+        assert!(baker
+            .compute(&floor, Affine3A::IDENTITY)
+            .unwrap()
+            .iter()
+            .all(|&occlusion| occlusion == 1.0));
+
+        // A roof over one corner only occludes that corner.
+        baker
+            .add_occluder(
+                &roof,
+                Affine3A::from_translation(Vec3::new(-0.5, 0.1, -0.5)),
+            )
+            .unwrap();
+        baker.bake(&mut floor, Affine3A::IDENTITY).unwrap();
+        let Some(VertexAttributeValues::Float32x4(colors)) = floor.attribute(Mesh::ATTRIBUTE_COLOR)
+        else {
+            panic!("The occlusion wasn't baked into the vertex colors");
+        };
+        let Some(VertexAttributeValues::Float32x3(positions)) =
+            floor.attribute(Mesh::ATTRIBUTE_POSITION)
+        else {
+            unreachable!();
+        };
+        for (position, color) in positions.iter().zip(colors) {
+            if position[0] < 0.0 && position[2] < 0.0 {
+                assert!(color[0] < 0.85, "{position:?} isn't occluded: {color:?}");
+            } else {
+                assert!(color[0] > 0.95, "{position:?} is occluded: {color:?}");
+            }
+            assert_eq!(color[3], 1.0);
+        }
+    }
+}
@@ -0,0 +1,696 @@
+//! An experimental visibility buffer rendering path.
+//!
+//! Instead of shading every fragment while meshes are rasterized, the
+//! visibility buffer path first rasterizes the index of the mesh instance and
+//! triangle visible at each pixel, then reconstructs the surface of that
+//! triangle in a compute pass. Every pixel is resolved exactly once, however
+//! many triangles overlap it, which scales better to scenes with very high
+//! triangle counts.
+//!
+//! Add the [`VisibilityBufferPlugin`] to the app and the [`VisibilityBuffer`]
+//! component to a 3D camera. The resolved attributes of every pixel are
+//! written to the textures of the [`ViewVisibilityBuffer`] of the view, for
+//! custom render graph nodes to shade from.
+//!
+//! The rasterization pass reuses the [`GpuPickingPipeline`] that renders mesh
+//! IDs for GPU picking. The resolve pass fetches vertices straight from the
+//! vertex and index buffers of each mesh, dispatching once per batch of mesh
+//! instances.
+//!
+//! This is a prototype:
+//! - Materials aren't shaded yet, only their vertex attributes are resolved.
+//! - Skinned and morphed meshes, non triangle list meshes, and meshes whose
+//!   vertices aren't aligned to 4 bytes are skipped.
+//! - Every batch is resolved over the whole view, so the cost of the resolve
+//!   pass grows with the number of distinct meshes.
+//! - The device needs to support storage buffers and
+//!   [`WgpuFeatures::SHADER_PRIMITIVE_INDEX`].
+
+mod node;
+
+pub use node::*;
+
+use std::ops::Range;
+
+use bevy_app::{App, Plugin};
+use bevy_asset::{load_internal_asset, AssetId, Handle};
+use bevy_core_pipeline::core_3d::{
+    graph::{Core3d, Node3d},
+    Camera3d, CORE_3D_DEPTH_FORMAT,
+};
+use bevy_ecs::prelude::*;
+use bevy_math::UVec2;
+use bevy_reflect::{std_traits::ReflectDefault, Reflect};
+use bevy_render::{
+    batching::{gpu_preprocessing, no_gpu_preprocessing},
+    camera::{Camera, ExtractedCamera},
+    mesh::{GpuBufferInfo, GpuMesh, Mesh, MeshVertexAttribute, PrimitiveTopology},
+    render_asset::RenderAssets,
+    render_graph::{RenderGraphApp, ViewNodeRunner},
+    render_phase::{
+        AddRenderCommand, BinnedPhaseItem, BinnedRenderPhase, BinnedRenderPhasePlugin,
+        CachedRenderPipelinePhaseItem, DrawFunctionId, DrawFunctions, PhaseItem,
+        PhaseItemExtraIndex,
+    },
+    render_resource::{
+        binding_types::{
+            storage_buffer_read_only_sized, texture_2d, texture_storage_2d, uniform_buffer,
+        },
+        *,
+    },
+    renderer::{RenderDevice, RenderQueue},
+    settings::WgpuFeatures,
+    texture::{CachedTexture, TextureCache},
+    view::{ViewUniform, ViewUniforms, VisibleEntities, WithMesh},
+    Extract, ExtractSchedule, Render, RenderApp, RenderSet,
+};
+use bevy_utils::{tracing::error, warn_once, HashMap};
+
+use crate::{
+    graph::NodePbr,
+    picking::{
+        DrawGpuPicking, GpuPickingAlphaTest, GpuPickingPipeline, GpuPickingPipelineKey,
+        MeshId3dBinKey, GPU_PICKING_MESH_ID_TRIANGLE_FORMAT,
+    },
+    MeshInputUniform, MeshPipeline, MeshPipelineKey, MeshUniform, RenderLightmaps,
+    RenderMeshInstances,
+};
+
+pub const VISIBILITY_BUFFER_RESOLVE_SHADER_HANDLE: Handle<Shader> =
+    Handle::weak_from_u128(7318453925065287301);
+
+/// The format of the visibility buffer. The first channel holds the index of
+/// the mesh instance plus one, or zero where no mesh was drawn, and the second
+/// the index of the triangle.
+pub const VISIBILITY_BUFFER_FORMAT: TextureFormat = GPU_PICKING_MESH_ID_TRIANGLE_FORMAT;
+
+/// The format of the texture the world space normals of the visible surfaces
+/// are resolved into. The alpha channel is 1 where a mesh was drawn.
+pub const VISIBILITY_BUFFER_NORMAL_FORMAT: TextureFormat = TextureFormat::Rgba16Float;
+
+/// The format of the texture the UVs of the visible surfaces are resolved into.
+pub const VISIBILITY_BUFFER_UV_FORMAT: TextureFormat = TextureFormat::Rg32Float;
+
+/// The size of the workgroups of the resolve pass, in pixels along each axis.
+const RESOLVE_WORKGROUP_SIZE: u32 = 8;
+
+/// Adds the experimental visibility buffer rendering path for 3D cameras with
+/// the [`VisibilityBuffer`] component.
+///
+/// Requires the [`PbrPlugin`](crate::PbrPlugin), whose GPU picking pipeline is
+/// used to rasterize the visibility buffer.
+pub struct VisibilityBufferPlugin;
+
+impl Plugin for VisibilityBufferPlugin {
+    fn build(&self, app: &mut App) {
+        load_internal_asset!(
+            app,
+            VISIBILITY_BUFFER_RESOLVE_SHADER_HANDLE,
+            "visibility_buffer_resolve.wgsl",
+            Shader::from_wgsl
+        );
+
+        app.register_type::<VisibilityBuffer>()
+            .add_plugins(BinnedRenderPhasePlugin::<VisibilityBuffer3d, MeshPipeline>::default());
+
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+
+        render_app
+            .init_resource::<DrawFunctions<VisibilityBuffer3d>>()
+            .init_resource::<VisibilityBufferDrawUniforms>()
+            .add_render_command::<VisibilityBuffer3d, DrawGpuPicking>()
+            .add_systems(ExtractSchedule, extract_visibility_buffer_cameras)
+            .add_systems(
+                Render,
+                (
+                    queue_visibility_buffer_meshes.in_set(RenderSet::QueueMeshes),
+                    prepare_visibility_buffer_textures.in_set(RenderSet::PrepareResources),
+                    prepare_visibility_buffer_bind_groups.in_set(RenderSet::PrepareBindGroups),
+                ),
+            )
+            .add_render_graph_node::<ViewNodeRunner<VisibilityBufferNode>>(
+                Core3d,
+                NodePbr::VisibilityBuffer,
+            )
+            .add_render_graph_edges(
+                Core3d,
+                (
+                    Node3d::EndPrepasses,
+                    NodePbr::VisibilityBuffer,
+                    Node3d::StartMainPass,
+                ),
+            );
+    }
+
+    fn finish(&self, app: &mut App) {
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+
+        render_app.init_resource::<VisibilityBufferResolvePipeline>();
+    }
+}
+
+/// Renders a visibility buffer for a 3D camera, and resolves the vertex
+/// attributes of the visible surfaces into the [`ViewVisibilityBuffer`] of the
+/// view.
+///
+/// Requires the [`VisibilityBufferPlugin`]. Cameras on devices that don't
+/// support the visibility buffer are ignored, with a warning.
+#[derive(Component, Reflect, Clone, Copy, Default, Debug)]
+#[reflect(Component, Default)]
+pub struct VisibilityBuffer;
+
+/// The textures of a view rendered with a [`VisibilityBuffer`].
+///
+/// They're written by the [`NodePbr::VisibilityBuffer`] node of the [`Core3d`]
+/// graph, which runs before the main passes, and cover the whole render target.
+#[derive(Component)]
+pub struct ViewVisibilityBuffer {
+    /// The visibility buffer itself. See [`VISIBILITY_BUFFER_FORMAT`].
+    pub ids: CachedTexture,
+    /// The depth buffer used while rasterizing `ids`.
+    pub depth: CachedTexture,
+    /// The world space normal of the surface at each pixel. See
+    /// [`VISIBILITY_BUFFER_NORMAL_FORMAT`].
+    pub world_normal: CachedTexture,
+    /// The UV of the surface at each pixel, or zero if the mesh has no UVs.
+    /// See [`VISIBILITY_BUFFER_UV_FORMAT`].
+    pub uv: CachedTexture,
+    /// The size of all textures.
+    pub size: UVec2,
+}
+
+fn extract_visibility_buffer_cameras(
+    mut commands: Commands,
+    render_device: Res<RenderDevice>,
+    cameras: Extract<Query<(Entity, &Camera), (With<Camera3d>, With<VisibilityBuffer>)>>,
+) {
+    if cameras.is_empty() {
+        return;
+    }
+
+    let limits = render_device.limits();
+    if !render_device
+        .features()
+        .contains(WgpuFeatures::SHADER_PRIMITIVE_INDEX)
+        || limits.max_storage_buffers_per_shader_stage < 3
+        || limits.max_storage_textures_per_shader_stage < 2
+    {
+        warn_once!(
+            "VisibilityBuffer requires storage buffers, storage textures and the \
+            SHADER_PRIMITIVE_INDEX feature, which aren't supported by the current device. \
+            The visibility buffer won't be rendered."
+        );
+        return;
+    }
+
+    for (entity, camera) in &cameras {
+        if camera.is_active {
+            commands.get_or_spawn(entity).insert((
+                VisibilityBuffer,
+                BinnedRenderPhase::<VisibilityBuffer3d>::default(),
+            ));
+        }
+    }
+}
+
+fn prepare_visibility_buffer_textures(
+    mut commands: Commands,
+    mut texture_cache: ResMut<TextureCache>,
+    render_device: Res<RenderDevice>,
+    views: Query<(Entity, &ExtractedCamera), With<VisibilityBuffer>>,
+) {
+    for (entity, camera) in &views {
+        let Some(size) = camera.physical_target_size else {
+            continue;
+        };
+        let size = size.max(UVec2::ONE);
+
+        let mut texture = |label, format, usage| {
+            texture_cache.get_for_view(
+                &render_device,
+                entity,
+                TextureDescriptor {
+                    label: Some(label),
+                    size: Extent3d {
+                        width: size.x,
+                        height: size.y,
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: TextureDimension::D2,
+                    format,
+                    usage,
+                    view_formats: &[],
+                },
+            )
+        };
+
+        // The resolved textures are cleared by a render pass, as they aren't
+        // written where no mesh was drawn.
+        let resolved_usage = TextureUsages::STORAGE_BINDING
+            | TextureUsages::TEXTURE_BINDING
+            | TextureUsages::RENDER_ATTACHMENT;
+
+        commands.entity(entity).insert(ViewVisibilityBuffer {
+            ids: texture(
+                "visibility_buffer_texture",
+                VISIBILITY_BUFFER_FORMAT,
+                TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+            ),
+            depth: texture(
+                "visibility_buffer_depth_texture",
+                CORE_3D_DEPTH_FORMAT,
+                TextureUsages::RENDER_ATTACHMENT,
+            ),
+            world_normal: texture(
+                "visibility_buffer_world_normal_texture",
+                VISIBILITY_BUFFER_NORMAL_FORMAT,
+                resolved_usage,
+            ),
+            uv: texture(
+                "visibility_buffer_uv_texture",
+                VISIBILITY_BUFFER_UV_FORMAT,
+                resolved_usage,
+            ),
+            size,
+        });
+    }
+}
+
+/// Render phase for the visibility buffer rasterization pass.
+///
+/// Items are drawn with the [`GpuPickingPipeline`], writing mesh instance
+/// indices instead of entity indices.
+pub struct VisibilityBuffer3d {
+    /// Information that separates items into bins.
+    pub key: MeshId3dBinKey,
+
+    /// An entity from which Bevy fetches data common to all instances in this
+    /// batch, such as the mesh.
+    pub representative_entity: Entity,
+
+    pub batch_range: Range<u32>,
+    pub extra_index: PhaseItemExtraIndex,
+}
+
+impl PhaseItem for VisibilityBuffer3d {
+    #[inline]
+    fn entity(&self) -> Entity {
+        self.representative_entity
+    }
+
+    #[inline]
+    fn draw_function(&self) -> DrawFunctionId {
+        self.key.draw_function
+    }
+
+    #[inline]
+    fn batch_range(&self) -> &Range<u32> {
+        &self.batch_range
+    }
+
+    #[inline]
+    fn batch_range_mut(&mut self) -> &mut Range<u32> {
+        &mut self.batch_range
+    }
+
+    #[inline]
+    fn extra_index(&self) -> PhaseItemExtraIndex {
+        self.extra_index
+    }
+
+    #[inline]
+    fn batch_range_and_extra_index_mut(&mut self) -> (&mut Range<u32>, &mut PhaseItemExtraIndex) {
+        (&mut self.batch_range, &mut self.extra_index)
+    }
+}
+
+impl BinnedPhaseItem for VisibilityBuffer3d {
+    type BinKey = MeshId3dBinKey;
+
+    #[inline]
+    fn new(
+        key: Self::BinKey,
+        representative_entity: Entity,
+        batch_range: Range<u32>,
+        extra_index: PhaseItemExtraIndex,
+    ) -> Self {
+        VisibilityBuffer3d {
+            key,
+            representative_entity,
+            batch_range,
+            extra_index,
+        }
+    }
+}
+
+impl CachedRenderPipelinePhaseItem for VisibilityBuffer3d {
+    #[inline]
+    fn cached_pipeline(&self) -> CachedRenderPipelineId {
+        self.key.pipeline
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn queue_visibility_buffer_meshes(
+    draw_functions: Res<DrawFunctions<VisibilityBuffer3d>>,
+    gpu_picking_pipeline: Res<GpuPickingPipeline>,
+    mut pipelines: ResMut<SpecializedMeshPipelines<GpuPickingPipeline>>,
+    pipeline_cache: Res<PipelineCache>,
+    render_meshes: Res<RenderAssets<GpuMesh>>,
+    render_mesh_instances: Res<RenderMeshInstances>,
+    render_lightmaps: Res<RenderLightmaps>,
+    mut views: Query<(&VisibleEntities, &mut BinnedRenderPhase<VisibilityBuffer3d>)>,
+) {
+    let draw_gpu_picking = draw_functions.read().id::<DrawGpuPicking>();
+
+    for (visible_entities, mut phase) in &mut views {
+        for visible_entity in visible_entities.iter::<WithMesh>() {
+            let Some(mesh_instance) = render_mesh_instances.render_mesh_queue_data(*visible_entity)
+            else {
+                continue;
+            };
+            let Some(mesh) = render_meshes.get(mesh_instance.mesh_asset_id) else {
+                continue;
+            };
+
+            // The resolve pass can't skin or morph vertices.
+            if mesh.primitive_topology() != PrimitiveTopology::TriangleList
+                || mesh.morph_targets.is_some()
+                || mesh.layout.0.contains(Mesh::ATTRIBUTE_JOINT_INDEX)
+            {
+                continue;
+            }
+
+            let mut mesh_key = MeshPipelineKey::from_bits_retain(mesh.key_bits.bits());
+
+            // The lightmap isn't used, but `SetMeshBindGroup` binds the
+            // lightmapped mesh bind group, so the layout has to match.
+            if render_lightmaps
+                .render_lightmaps
+                .contains_key(visible_entity)
+            {
+                mesh_key |= MeshPipelineKey::LIGHTMAPPED;
+            }
+
+            let pipeline_id = match pipelines.specialize(
+                &pipeline_cache,
+                &gpu_picking_pipeline,
+                GpuPickingPipelineKey {
+                    mesh_key,
+                    depth: false,
+                    triangles: true,
+                    instance_indices: true,
+                    alpha_test: GpuPickingAlphaTest::None,
+                },
+                &mesh.layout,
+            ) {
+                Ok(id) => id,
+                Err(err) => {
+                    error!("{}", err);
+                    continue;
+                }
+            };
+
+            // Only batches are resolved, see `BinnedRenderPhase::batches`, so
+            // every mesh is batched regardless of `NoAutomaticBatching`.
+            phase.add(
+                MeshId3dBinKey {
+                    pipeline: pipeline_id,
+                    draw_function: draw_gpu_picking,
+                    asset_id: mesh_instance.mesh_asset_id,
+                    material_bind_group_id: None,
+                },
+                *visible_entity,
+                true,
+            );
+        }
+    }
+}
+
+/// The pipeline that resolves the visibility buffer.
+#[derive(Resource)]
+pub struct VisibilityBufferResolvePipeline {
+    /// The layout of the bind group shared by every dispatch of a view.
+    pub view_layout: BindGroupLayout,
+    /// The layout of the bind group of the mesh resolved by a dispatch.
+    pub draw_layout: BindGroupLayout,
+    pub pipeline_id: CachedComputePipelineId,
+}
+
+impl FromWorld for VisibilityBufferResolvePipeline {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+
+        let view_layout = render_device.create_bind_group_layout(
+            "visibility_buffer_resolve_view_layout",
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::COMPUTE,
+                (
+                    uniform_buffer::<ViewUniform>(true),
+                    texture_2d(TextureSampleType::Uint),
+                    texture_storage_2d(
+                        VISIBILITY_BUFFER_NORMAL_FORMAT,
+                        StorageTextureAccess::WriteOnly,
+                    ),
+                    texture_storage_2d(
+                        VISIBILITY_BUFFER_UV_FORMAT,
+                        StorageTextureAccess::WriteOnly,
+                    ),
+                    // The mesh uniforms.
+                    storage_buffer_read_only_sized(false, None),
+                ),
+            ),
+        );
+        let draw_layout = render_device.create_bind_group_layout(
+            "visibility_buffer_resolve_draw_layout",
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::COMPUTE,
+                (
+                    uniform_buffer::<VisibilityBufferDraw>(true),
+                    // The vertex and index buffers of the mesh.
+                    storage_buffer_read_only_sized(false, None),
+                    storage_buffer_read_only_sized(false, None),
+                ),
+            ),
+        );
+
+        let pipeline_id = world
+            .resource_mut::<PipelineCache>()
+            .queue_compute_pipeline(ComputePipelineDescriptor {
+                label: Some("visibility_buffer_resolve_pipeline".into()),
+                layout: vec![view_layout.clone(), draw_layout.clone()],
+                push_constant_ranges: vec![],
+                shader: VISIBILITY_BUFFER_RESOLVE_SHADER_HANDLE,
+                shader_defs: vec![],
+                entry_point: "resolve".into(),
+            });
+
+        VisibilityBufferResolvePipeline {
+            view_layout,
+            draw_layout,
+            pipeline_id,
+        }
+    }
+}
+
+/// Describes the batch of mesh instances resolved by one dispatch of the
+/// resolve pass, and how to read the vertices of their mesh.
+///
+/// Offsets and strides are in 4 byte words.
+#[derive(ShaderType, Clone, Copy, Debug)]
+pub struct VisibilityBufferDraw {
+    /// The first mesh instance of the batch.
+    pub instance_start: u32,
+    /// One past the last mesh instance of the batch.
+    pub instance_end: u32,
+    /// The distance between two vertices in the vertex buffer.
+    pub vertex_stride: u32,
+    /// The offset of the position in each vertex.
+    pub position_offset: u32,
+    /// The offset of the normal in each vertex, or [`u32::MAX`] if the mesh
+    /// has no normals.
+    pub normal_offset: u32,
+    /// The offset of the UV in each vertex, or [`u32::MAX`] if the mesh has no
+    /// UVs.
+    pub uv_offset: u32,
+    /// 0 for non indexed meshes, 1 for 16 bit indices and 2 for 32 bit
+    /// indices.
+    pub index_format: u32,
+}
+
+impl VisibilityBufferDraw {
+    /// Describes how to read the vertices of `mesh`, or returns `None` if the
+    /// resolve pass can't read them.
+    fn new(mesh: &GpuMesh, instance_range: &Range<u32>) -> Option<Self> {
+        let layout = mesh.layout.0.layout();
+        if layout.array_stride % 4 != 0 {
+            return None;
+        }
+
+        let attribute_offset = |attribute: &MeshVertexAttribute| {
+            mesh.layout
+                .0
+                .attribute_ids()
+                .iter()
+                .position(|id| *id == attribute.id)
+                .map(|index| &layout.attributes[index])
+                .filter(|vertex_attribute| vertex_attribute.format == attribute.format)
+                .map(|vertex_attribute| vertex_attribute.offset as u32 / 4)
+        };
+
+        let index_format = match &mesh.buffer_info {
+            GpuBufferInfo::NonIndexed => 0,
+            GpuBufferInfo::Indexed {
+                index_format: IndexFormat::Uint16,
+                ..
+            } => 1,
+            GpuBufferInfo::Indexed {
+                index_format: IndexFormat::Uint32,
+                ..
+            } => 2,
+        };
+
+        Some(VisibilityBufferDraw {
+            instance_start: instance_range.start,
+            instance_end: instance_range.end,
+            vertex_stride: layout.array_stride as u32 / 4,
+            position_offset: attribute_offset(&Mesh::ATTRIBUTE_POSITION)?,
+            normal_offset: attribute_offset(&Mesh::ATTRIBUTE_NORMAL).unwrap_or(u32::MAX),
+            uv_offset: attribute_offset(&Mesh::ATTRIBUTE_UV_0).unwrap_or(u32::MAX),
+            index_format,
+        })
+    }
+}
+
+/// The [`VisibilityBufferDraw`]s of every view, rewritten every frame.
+#[derive(Resource, Default)]
+pub struct VisibilityBufferDrawUniforms(pub DynamicUniformBuffer<VisibilityBufferDraw>);
+
+/// The bind groups of the resolve pass of a view.
+#[derive(Component)]
+pub struct VisibilityBufferResolveBindGroups {
+    pub view: BindGroup,
+    /// The bind group and [`VisibilityBufferDraw`] offset of every dispatch.
+    pub draws: Vec<(BindGroup, u32)>,
+}
+
+#[allow(clippy::too_many_arguments)]
+fn prepare_visibility_buffer_bind_groups(
+    mut commands: Commands,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    resolve_pipeline: Res<VisibilityBufferResolvePipeline>,
+    view_uniforms: Res<ViewUniforms>,
+    render_meshes: Res<RenderAssets<GpuMesh>>,
+    cpu_batched_instance_buffer: Option<
+        Res<no_gpu_preprocessing::BatchedInstanceBuffer<MeshUniform>>,
+    >,
+    gpu_batched_instance_buffers: Option<
+        Res<gpu_preprocessing::BatchedInstanceBuffers<MeshUniform, MeshInputUniform>>,
+    >,
+    mut draw_uniforms: ResMut<VisibilityBufferDrawUniforms>,
+    views: Query<(
+        Entity,
+        &ViewVisibilityBuffer,
+        &BinnedRenderPhase<VisibilityBuffer3d>,
+    )>,
+) {
+    let mesh_uniforms = if let Some(buffer) = cpu_batched_instance_buffer.as_deref() {
+        buffer.instance_data_binding()
+    } else if let Some(buffers) = gpu_batched_instance_buffers.as_deref() {
+        buffers.instance_data_binding()
+    } else {
+        return;
+    };
+    let (Some(mesh_uniforms), Some(view_binding)) =
+        (mesh_uniforms, view_uniforms.uniforms.binding())
+    else {
+        return;
+    };
+
+    // Write the draws of every view first, as the bind groups need the buffer.
+    draw_uniforms.0.clear();
+    let view_draws: Vec<(Entity, Vec<(AssetId<Mesh>, u32)>)> = views
+        .iter()
+        .map(|(entity, _, phase)| {
+            let draws = phase
+                .batches()
+                .filter_map(|(key, batch)| {
+                    let mesh = render_meshes.get(key.asset_id)?;
+                    let draw = VisibilityBufferDraw::new(mesh, &batch.instance_range)?;
+                    Some((key.asset_id, draw_uniforms.0.push(&draw)))
+                })
+                .collect();
+            (entity, draws)
+        })
+        .collect();
+    draw_uniforms.0.write_buffer(&render_device, &render_queue);
+    let Some(draw_binding) = draw_uniforms.0.binding() else {
+        return;
+    };
+
+    let mut mesh_bind_groups = HashMap::new();
+    for (entity, draws) in view_draws {
+        let Ok((_, visibility_buffer, _)) = views.get(entity) else {
+            continue;
+        };
+
+        let view = render_device.create_bind_group(
+            "visibility_buffer_resolve_view_bind_group",
+            &resolve_pipeline.view_layout,
+            &BindGroupEntries::sequential((
+                view_binding.clone(),
+                &visibility_buffer.ids.default_view,
+                &visibility_buffer.world_normal.default_view,
+                &visibility_buffer.uv.default_view,
+                mesh_uniforms.clone(),
+            )),
+        );
+
+        let draws = draws
+            .into_iter()
+            .filter_map(|(asset_id, offset)| {
+                let bind_group = mesh_bind_groups
+                    .entry(asset_id)
+                    .or_insert_with(|| {
+                        let mesh = render_meshes.get(asset_id)?;
+                        // Non indexed meshes don't read their index buffer, so
+                        // bind the vertex buffer in its place.
+                        let index_buffer = match &mesh.buffer_info {
+                            GpuBufferInfo::Indexed { buffer, .. } => buffer,
+                            GpuBufferInfo::NonIndexed => &mesh.vertex_buffer,
+                        };
+                        Some(render_device.create_bind_group(
+                            "visibility_buffer_resolve_draw_bind_group",
+                            &resolve_pipeline.draw_layout,
+                            &BindGroupEntries::sequential((
+                                draw_binding.clone(),
+                                mesh.vertex_buffer.as_entire_binding(),
+                                index_buffer.as_entire_binding(),
+                            )),
+                        ))
+                    })
+                    .clone()?;
+                Some((bind_group, offset))
+            })
+            .collect();
+
+        commands
+            .entity(entity)
+            .insert(VisibilityBufferResolveBindGroups { view, draws });
+    }
+}
+
+/// The number of workgroups the resolve pass dispatches to cover `size`.
+fn resolve_workgroup_count(size: UVec2) -> UVec2 {
+    UVec2::new(
+        size.x.div_ceil(RESOLVE_WORKGROUP_SIZE),
+        size.y.div_ceil(RESOLVE_WORKGROUP_SIZE),
+    )
+}
@@ -0,0 +1,139 @@
+use bevy_ecs::{prelude::*, query::QueryItem};
+use bevy_render::{
+    camera::ExtractedCamera,
+    diagnostic::RecordDiagnostics,
+    render_graph::{NodeRunError, RenderGraphContext, ViewNode},
+    render_phase::BinnedRenderPhase,
+    render_resource::{
+        ComputePassDescriptor, LoadOp, Operations, PipelineCache, RenderPassColorAttachment,
+        RenderPassDepthStencilAttachment, RenderPassDescriptor, StoreOp,
+    },
+    renderer::RenderContext,
+    view::ViewUniformOffset,
+};
+#[cfg(feature = "trace")]
+use bevy_utils::tracing::info_span;
+
+use super::{
+    resolve_workgroup_count, ViewVisibilityBuffer, VisibilityBuffer3d,
+    VisibilityBufferResolveBindGroups, VisibilityBufferResolvePipeline,
+};
+
+/// Render node that rasterizes the visibility buffer of a view, then resolves
+/// it into the attribute textures of its [`ViewVisibilityBuffer`].
+#[derive(Default)]
+pub struct VisibilityBufferNode;
+
+impl ViewNode for VisibilityBufferNode {
+    type ViewQuery = (
+        &'static ExtractedCamera,
+        &'static BinnedRenderPhase<VisibilityBuffer3d>,
+        &'static ViewVisibilityBuffer,
+        &'static VisibilityBufferResolveBindGroups,
+        &'static ViewUniformOffset,
+    );
+
+    fn run(
+        &self,
+        graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        (camera, phase, visibility_buffer, bind_groups, view_uniform_offset): QueryItem<
+            Self::ViewQuery,
+        >,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        #[cfg(feature = "trace")]
+        let _visibility_buffer_span = info_span!("visibility_buffer").entered();
+
+        let diagnostics = render_context.diagnostic_recorder();
+
+        {
+            let mut render_pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
+                label: Some("visibility_buffer_raster"),
+                color_attachments: &[Some(RenderPassColorAttachment {
+                    view: &visibility_buffer.ids.default_view,
+                    resolve_target: None,
+                    ops: Operations {
+                        // Zero means no mesh.
+                        load: LoadOp::Clear(Default::default()),
+                        store: StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: Some(RenderPassDepthStencilAttachment {
+                    view: &visibility_buffer.depth.default_view,
+                    depth_ops: Some(Operations {
+                        load: LoadOp::Clear(0.0),
+                        store: StoreOp::Discard,
+                    }),
+                    stencil_ops: None,
+                }),
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            let pass_span = diagnostics.pass_span(&mut render_pass, "visibility_buffer_raster");
+
+            if let Some(viewport) = camera.viewport.as_ref() {
+                render_pass.set_camera_viewport(viewport);
+            }
+            if !phase.is_empty() {
+                phase.render(&mut render_pass, world, graph.view_entity());
+            }
+
+            pass_span.end(&mut render_pass);
+        }
+
+        // The resolve pass only writes the pixels where a mesh was drawn, so
+        // clear the attribute textures with an empty render pass first.
+        let clear_attachments =
+            [&visibility_buffer.world_normal, &visibility_buffer.uv].map(|texture| {
+                Some(RenderPassColorAttachment {
+                    view: &texture.default_view,
+                    resolve_target: None,
+                    ops: Operations {
+                        load: LoadOp::Clear(Default::default()),
+                        store: StoreOp::Store,
+                    },
+                })
+            });
+        render_context.begin_tracked_render_pass(RenderPassDescriptor {
+            label: Some("visibility_buffer_clear"),
+            color_attachments: &clear_attachments,
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+
+        let Some(resolve_pipeline) = world.resource::<PipelineCache>().get_compute_pipeline(
+            world
+                .resource::<VisibilityBufferResolvePipeline>()
+                .pipeline_id,
+        ) else {
+            // This will happen while the pipeline is being compiled and is fine.
+            return Ok(());
+        };
+
+        let mut compute_pass =
+            render_context
+                .command_encoder()
+                .begin_compute_pass(&ComputePassDescriptor {
+                    label: Some("visibility_buffer_resolve"),
+                    timestamp_writes: None,
+                });
+        let pass_span = diagnostics.pass_span(&mut compute_pass, "visibility_buffer_resolve");
+
+        compute_pass.set_pipeline(resolve_pipeline);
+        compute_pass.set_bind_group(0, &bind_groups.view, &[view_uniform_offset.offset]);
+
+        // Every dispatch covers the whole view, and only resolves the pixels
+        // of its own batch of mesh instances.
+        let workgroup_count = resolve_workgroup_count(visibility_buffer.size);
+        for (bind_group, draw_offset) in &bind_groups.draws {
+            compute_pass.set_bind_group(1, bind_group, &[*draw_offset]);
+            compute_pass.dispatch_workgroups(workgroup_count.x, workgroup_count.y, 1);
+        }
+
+        pass_span.end(&mut compute_pass);
+
+        Ok(())
+    }
+}
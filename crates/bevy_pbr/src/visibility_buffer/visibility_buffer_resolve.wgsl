@@ -0,0 +1,141 @@
+// Resolves the visibility buffer into the attributes of the visible surfaces.
+//
+// Every dispatch covers the whole view, and resolves the pixels whose mesh
+// instance belongs to the batch described by `draw`, fetching the vertices of
+// their triangle from the vertex and index buffers of the mesh.
+
+#import bevy_pbr::mesh_types::{Mesh, unpack_mesh_transform, mesh_inverse_transpose_model}
+#import bevy_render::{
+    maths::affine3_to_square,
+    view::View,
+}
+
+// See `VisibilityBufferDraw`. Offsets and strides are in 4 byte words.
+struct VisibilityBufferDraw {
+    instance_start: u32,
+    instance_end: u32,
+    vertex_stride: u32,
+    position_offset: u32,
+    normal_offset: u32,
+    uv_offset: u32,
+    index_format: u32,
+}
+
+const ATTRIBUTE_MISSING: u32 = 0xffffffffu;
+const INDEX_FORMAT_UINT16: u32 = 1u;
+const INDEX_FORMAT_UINT32: u32 = 2u;
+
+@group(0) @binding(0) var<uniform> view: View;
+@group(0) @binding(1) var visibility_buffer: texture_2d<u32>;
+@group(0) @binding(2) var world_normal_texture: texture_storage_2d<rgba16float, write>;
+@group(0) @binding(3) var uv_texture: texture_storage_2d<rg32float, write>;
+@group(0) @binding(4) var<storage> meshes: array<Mesh>;
+
+@group(1) @binding(0) var<uniform> draw: VisibilityBufferDraw;
+@group(1) @binding(1) var<storage> vertex_data: array<u32>;
+@group(1) @binding(2) var<storage> index_data: array<u32>;
+
+fn load_vertex_index(index: u32) -> u32 {
+    switch draw.index_format {
+        case INDEX_FORMAT_UINT16: {
+            return extractBits(index_data[index / 2u], (index % 2u) * 16u, 16u);
+        }
+        case INDEX_FORMAT_UINT32: {
+            return index_data[index];
+        }
+        default: {
+            return index;
+        }
+    }
+}
+
+fn load_vec3(vertex_index: u32, offset: u32) -> vec3<f32> {
+    let word = vertex_index * draw.vertex_stride + offset;
+    return bitcast<vec3<f32>>(vec3(vertex_data[word], vertex_data[word + 1u], vertex_data[word + 2u]));
+}
+
+fn load_vec2(vertex_index: u32, offset: u32) -> vec2<f32> {
+    let word = vertex_index * draw.vertex_stride + offset;
+    return bitcast<vec2<f32>>(vec2(vertex_data[word], vertex_data[word + 1u]));
+}
+
+// The perspective correct barycentrics of the point at `ndc` in the triangle
+// with the given clip space vertices.
+// https://github.com/ConfettiFX/The-Forge/blob/2d453f376ef278f66f97cbaf36c0d12e4361e275/Examples_3/Visibility_Buffer/src/Shaders/FSL/visibilityBuffer_shade.frag.fsl#L83-L139
+fn compute_barycentrics(clip_0: vec4<f32>, clip_1: vec4<f32>, clip_2: vec4<f32>, ndc: vec2<f32>) -> vec3<f32> {
+    let inv_w = 1.0 / vec3(clip_0.w, clip_1.w, clip_2.w);
+    let ndc_0 = clip_0.xy * inv_w[0];
+    let ndc_1 = clip_1.xy * inv_w[1];
+    let ndc_2 = clip_2.xy * inv_w[2];
+
+    let inv_det = 1.0 / determinant(mat2x2(ndc_2 - ndc_1, ndc_0 - ndc_1));
+    let ddx = vec3(ndc_1.y - ndc_2.y, ndc_2.y - ndc_0.y, ndc_0.y - ndc_1.y) * inv_det * inv_w;
+    let ddy = vec3(ndc_2.x - ndc_1.x, ndc_0.x - ndc_2.x, ndc_1.x - ndc_0.x) * inv_det * inv_w;
+
+    let delta = ndc - ndc_0;
+    let interp_inv_w = inv_w.x + delta.x * dot(ddx, vec3(1.0)) + delta.y * dot(ddy, vec3(1.0));
+    return (vec3(inv_w.x, 0.0, 0.0) + delta.x * ddx + delta.y * ddy) / interp_inv_w;
+}
+
+@compute
+@workgroup_size(8, 8, 1)
+fn resolve(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    if any(global_id.xy >= textureDimensions(visibility_buffer)) {
+        return;
+    }
+
+    // The instance index is offset by one so that zero can mean "no mesh".
+    let ids = textureLoad(visibility_buffer, global_id.xy, 0).rg;
+    if ids.r == 0u {
+        return;
+    }
+    let instance_index = ids.r - 1u;
+    if instance_index < draw.instance_start || instance_index >= draw.instance_end {
+        return;
+    }
+
+    let index = ids.g * 3u;
+    let vertex_0 = load_vertex_index(index);
+    let vertex_1 = load_vertex_index(index + 1u);
+    let vertex_2 = load_vertex_index(index + 2u);
+
+    let mesh = meshes[instance_index];
+    let world_from_local = affine3_to_square(unpack_mesh_transform(mesh.model));
+    let world_0 = world_from_local * vec4(load_vec3(vertex_0, draw.position_offset), 1.0);
+    let world_1 = world_from_local * vec4(load_vec3(vertex_1, draw.position_offset), 1.0);
+    let world_2 = world_from_local * vec4(load_vec3(vertex_2, draw.position_offset), 1.0);
+
+    // The center of the pixel, in the same normalized device coordinates the
+    // triangle was rasterized in.
+    let frag_coord = vec2<f32>(global_id.xy) + 0.5;
+    let ndc = (frag_coord - view.viewport.xy) / view.viewport.zw * vec2(2.0, -2.0) + vec2(-1.0, 1.0);
+    let barycentrics = compute_barycentrics(
+        view.view_proj * world_0,
+        view.view_proj * world_1,
+        view.view_proj * world_2,
+        ndc,
+    );
+
+    var world_normal: vec3<f32>;
+    if draw.normal_offset != ATTRIBUTE_MISSING {
+        let local_normal = mat3x3(
+            load_vec3(vertex_0, draw.normal_offset),
+            load_vec3(vertex_1, draw.normal_offset),
+            load_vec3(vertex_2, draw.normal_offset),
+        ) * barycentrics;
+        world_normal = normalize(mesh_inverse_transpose_model(mesh) * local_normal);
+    } else {
+        // Use the face normal of meshes without normals.
+        world_normal = normalize(cross(world_1.xyz - world_0.xyz, world_2.xyz - world_0.xyz));
+    }
+    textureStore(world_normal_texture, global_id.xy, vec4(world_normal, 1.0));
+
+    if draw.uv_offset != ATTRIBUTE_MISSING {
+        let uv = mat3x2(
+            load_vec2(vertex_0, draw.uv_offset),
+            load_vec2(vertex_1, draw.uv_offset),
+            load_vec2(vertex_2, draw.uv_offset),
+        ) * barycentrics;
+        textureStore(uv_texture, global_id.xy, vec4(uv, 0.0, 0.0));
+    }
+}
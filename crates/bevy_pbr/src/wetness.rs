@@ -0,0 +1,249 @@
+use bevy_app::{App, Plugin};
+use bevy_asset::Handle;
+use bevy_ecs::prelude::*;
+use bevy_math::{Vec2, Vec4};
+use bevy_reflect::{std_traits::ReflectDefault, Reflect};
+use bevy_render::{
+    extract_resource::{ExtractResource, ExtractResourcePlugin},
+    render_asset::RenderAssets,
+    render_resource::{binding_types::*, *},
+    renderer::{RenderDevice, RenderQueue},
+    texture::{FallbackImage, GpuImage, Image},
+    Render, RenderApp, RenderSet,
+};
+
+use crate::{MeshViewBinding, MeshViewBindingPlugin};
+
+/// Makes all the [`StandardMaterial`](crate::StandardMaterial)s respond to the [`Wetness`]
+/// resource.
+///
+/// This adds [`MeshViewBinding`]s, so it must be added before the
+/// [`PbrPlugin`](crate::PbrPlugin) is finished.
+pub struct WetnessPlugin;
+
+impl Plugin for WetnessPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Wetness>()
+            .init_resource::<Wetness>()
+            .add_plugins((
+                ExtractResourcePlugin::<Wetness>::default(),
+                MeshViewBindingPlugin::<WetnessUniform>::default(),
+                MeshViewBindingPlugin::<WetnessWorldMaskTexture>::default(),
+                MeshViewBindingPlugin::<WetnessScreenMaskTexture>::default(),
+                MeshViewBindingPlugin::<WetnessMaskSampler>::default(),
+            ));
+
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app
+            .init_resource::<WetnessUniform>()
+            .init_resource::<WetnessWorldMaskTexture>()
+            .init_resource::<WetnessScreenMaskTexture>()
+            .add_systems(Render, prepare_wetness.in_set(RenderSet::PrepareResources));
+    }
+
+    fn finish(&self, app: &mut App) {
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app.init_resource::<WetnessMaskSampler>();
+    }
+}
+
+/// How wet the scene is, applied to every [`StandardMaterial`](crate::StandardMaterial) when the
+/// [`WetnessPlugin`] is added, such as during and after rain.
+///
+/// Wet surfaces get darker, as water fills their pores, and glossier. Metals only get glossier.
+/// Water also pools into puddles on the up-facing surfaces, which are flat and almost mirror-like
+/// whatever the material below.
+///
+/// The masks limit the wetness to some areas, e.g. to keep the ground under a roof dry.
+///
+/// ```
+/// # use bevy_ecs::prelude::*;
+/// # use bevy_pbr::Wetness;
+/// fn start_raining(mut wetness: ResMut<Wetness>) {
+///     wetness.wetness = 1.0;
+///     wetness.puddles = 0.3;
+/// }
+/// # bevy_ecs::system::assert_is_system(start_raining);
+/// ```
+#[derive(Resource, Clone, Debug, ExtractResource, Reflect)]
+#[reflect(Resource, Default)]
+pub struct Wetness {
+    /// How wet the surfaces are, from 0.0 (dry, the default) to 1.0 (soaked).
+    pub wetness: f32,
+    /// How much wet dielectric surfaces darken when fully wet, from 0.0 to 1.0.
+    ///
+    /// Defaults to 0.4.
+    pub darkening: f32,
+    /// The perceptual roughness that surfaces tend to when fully wet. Surfaces that are already
+    /// smoother keep their roughness.
+    ///
+    /// Defaults to 0.2.
+    pub wet_roughness: f32,
+    /// How much of the up-facing surfaces are covered by puddles, from 0.0 (none, the default) to
+    /// 1.0 (all of them).
+    ///
+    /// Puddles only form where surfaces are wet, and first where the green channel of the
+    /// [`Wetness::world_mask`] is highest.
+    pub puddles: f32,
+    /// A texture mapped from above onto the world, whose red channel scales the wetness and whose
+    /// green channel is where puddles form first, like the low areas of a terrain.
+    ///
+    /// Outside of its area, the texels on its edges are used.
+    pub world_mask: Option<WetnessWorldMask>,
+    /// A texture mapped onto the screen, whose red channel scales the wetness.
+    pub screen_mask: Option<Handle<Image>>,
+}
+
+impl Default for Wetness {
+    fn default() -> Self {
+        Self {
+            wetness: 0.0,
+            darkening: 0.4,
+            wet_roughness: 0.2,
+            puddles: 0.0,
+            world_mask: None,
+            screen_mask: None,
+        }
+    }
+}
+
+/// The [`Wetness::world_mask`] texture, and the area of the world it covers on the x and z axes.
+#[derive(Clone, Debug, Default, Reflect)]
+#[reflect(Default)]
+pub struct WetnessWorldMask {
+    pub image: Handle<Image>,
+    /// The world x and z coordinates of the center of the texture.
+    pub center: Vec2,
+    /// The size of the area covered by the texture along the world x and z axes.
+    pub size: Vec2,
+}
+
+/// The [`Wetness`] in the layout of the shaders.
+#[derive(Clone, Copy, Debug, Default, ShaderType)]
+pub struct GpuWetness {
+    wetness: f32,
+    darkening: f32,
+    wet_roughness: f32,
+    puddles: f32,
+    /// Maps the world x and z coordinates to the world mask uvs: xy is the scale, zw the offset.
+    world_mask_transform: Vec4,
+}
+
+/// The buffer of the [`GpuWetness`], bound to the mesh view bind group.
+#[derive(Resource, Default)]
+pub struct WetnessUniform(pub UniformBuffer<GpuWetness>);
+
+impl MeshViewBinding for WetnessUniform {
+    const SHADER_DEF: &'static str = "WETNESS_BINDING";
+
+    fn layout_entry(_render_device: &RenderDevice) -> BindGroupLayoutEntryBuilder {
+        uniform_buffer::<GpuWetness>(false)
+    }
+
+    fn binding(&self) -> Option<OwnedBindingResource> {
+        self.0.buffer().cloned().map(OwnedBindingResource::Buffer)
+    }
+}
+
+/// The [`Wetness::world_mask`] texture, or a white texture without one.
+#[derive(Resource, Default)]
+pub struct WetnessWorldMaskTexture(pub Option<TextureView>);
+
+impl MeshViewBinding for WetnessWorldMaskTexture {
+    const SHADER_DEF: &'static str = "WETNESS_WORLD_MASK_BINDING";
+
+    fn layout_entry(_render_device: &RenderDevice) -> BindGroupLayoutEntryBuilder {
+        texture_2d(TextureSampleType::Float { filterable: true })
+    }
+
+    fn binding(&self) -> Option<OwnedBindingResource> {
+        self.0.clone().map(OwnedBindingResource::TextureView)
+    }
+}
+
+/// The [`Wetness::screen_mask`] texture, or a white texture without one.
+#[derive(Resource, Default)]
+pub struct WetnessScreenMaskTexture(pub Option<TextureView>);
+
+impl MeshViewBinding for WetnessScreenMaskTexture {
+    const SHADER_DEF: &'static str = "WETNESS_SCREEN_MASK_BINDING";
+
+    fn layout_entry(_render_device: &RenderDevice) -> BindGroupLayoutEntryBuilder {
+        texture_2d(TextureSampleType::Float { filterable: true })
+    }
+
+    fn binding(&self) -> Option<OwnedBindingResource> {
+        self.0.clone().map(OwnedBindingResource::TextureView)
+    }
+}
+
+/// The sampler of the wetness masks, which clamps to their edges.
+#[derive(Resource)]
+pub struct WetnessMaskSampler(pub Sampler);
+
+impl FromWorld for WetnessMaskSampler {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+        Self(render_device.create_sampler(&SamplerDescriptor {
+            label: Some("wetness_mask_sampler"),
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            ..Default::default()
+        }))
+    }
+}
+
+impl MeshViewBinding for WetnessMaskSampler {
+    const SHADER_DEF: &'static str = "WETNESS_MASK_SAMPLER_BINDING";
+
+    fn layout_entry(_render_device: &RenderDevice) -> BindGroupLayoutEntryBuilder {
+        sampler(SamplerBindingType::Filtering)
+    }
+
+    fn binding(&self) -> Option<OwnedBindingResource> {
+        Some(OwnedBindingResource::Sampler(self.0.clone()))
+    }
+}
+
+/// Writes the [`GpuWetness`], and picks the mask textures, or white textures until they're
+/// uploaded.
+#[allow(clippy::too_many_arguments)]
+pub fn prepare_wetness(
+    wetness: Res<Wetness>,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    images: Res<RenderAssets<GpuImage>>,
+    fallback_image: Res<FallbackImage>,
+    mut uniform: ResMut<WetnessUniform>,
+    mut world_mask_texture: ResMut<WetnessWorldMaskTexture>,
+    mut screen_mask_texture: ResMut<WetnessScreenMaskTexture>,
+) {
+    let mask_texture = |image: Option<&Handle<Image>>| {
+        image
+            .and_then(|image| images.get(image))
+            .map_or(&fallback_image.d2.texture_view, |image| &image.texture_view)
+            .clone()
+    };
+    world_mask_texture.0 = Some(mask_texture(
+        wetness.world_mask.as_ref().map(|mask| &mask.image),
+    ));
+    screen_mask_texture.0 = Some(mask_texture(wetness.screen_mask.as_ref()));
+
+    let world_mask_transform = wetness.world_mask.as_ref().map_or(Vec4::ZERO, |mask| {
+        let scale = mask.size.max(Vec2::splat(f32::EPSILON)).recip();
+        let offset = Vec2::splat(0.5) - mask.center * scale;
+        scale.extend(offset.x).extend(offset.y)
+    });
+    uniform.0.set(GpuWetness {
+        wetness: wetness.wetness.clamp(0.0, 1.0),
+        darkening: wetness.darkening.clamp(0.0, 1.0),
+        wet_roughness: wetness.wet_roughness.clamp(0.0, 1.0),
+        puddles: wetness.puddles.clamp(0.0, 1.0),
+        world_mask_transform,
+    });
+    uniform.0.write_buffer(&render_device, &render_queue);
+}
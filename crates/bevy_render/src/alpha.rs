@@ -44,6 +44,17 @@ pub enum AlphaMode {
     ///
     /// [alpha to coverage]: https://en.wikipedia.org/wiki/Alpha_to_coverage
     AlphaToCoverage,
+    /// Keeps or discards each fragment by comparing its alpha value to a
+    /// screen-space blue noise threshold, so the fraction of the surface that's
+    /// drawn is proportional to the alpha value.
+    ///
+    /// Like [`AlphaMode::Mask`], this doesn't require sorting or
+    /// order-independent transparency, and works with the deferred renderer,
+    /// but the surface fades smoothly instead of being cut off at a threshold.
+    /// It's especially useful for fading out foliage and other complex
+    /// objects. The noise changes every frame, so that temporal anti-aliasing
+    /// converges to the blended result.
+    Hashed,
     /// Combines the color of the fragments with the colors behind them in an
     /// additive process, (i.e. like light) producing lighter results.
     ///
@@ -10,7 +10,7 @@ use bevy_ecs::{
     world::{FromWorld, World},
 };
 use bevy_encase_derive::ShaderType;
-use bevy_utils::EntityHashMap;
+use bevy_utils::{warn_once, EntityHashMap};
 use bytemuck::{Pod, Zeroable};
 use nonmax::NonMaxU32;
 use smallvec::smallvec;
@@ -119,6 +119,48 @@ pub struct PreprocessWorkItemBuffer {
     pub buffer: BufferVec<PreprocessWorkItem>,
     /// True if we're using GPU culling.
     pub gpu_culling: bool,
+    /// The instances whose order within their batch is sorted on the GPU before
+    /// preprocessing, in a view with [`GpuSorting`](crate::view::GpuSorting).
+    pub sort_items: BufferVec<GpuSortItem>,
+    /// The number of batches sorted on the GPU in this view.
+    pub sort_batch_count: u32,
+}
+
+/// The maximum number of batches whose instances can be sorted on the GPU in a
+/// single view.
+///
+/// The batch index takes up the high bits of the sort keys, and the remaining
+/// bits hold the depth of the instance, so more batches make for a coarser
+/// sort.
+pub const MAX_GPU_SORTING_BATCHES: u32 = 1 << 16;
+
+/// One instance sorted on the GPU in a view with [`GpuSorting`](crate::view::GpuSorting).
+///
+/// The instances are sorted by batch and then back to front, and the input
+/// index of the [`PreprocessWorkItem`] of each of these instances is replaced
+/// by the one of the instance sorted into its place. As each batch occupies a
+/// contiguous range of the output buffer, the preprocessing shader then writes
+/// the instances of each batch in sorted order.
+#[derive(Clone, Copy, Pod, Zeroable, ShaderType)]
+#[repr(C)]
+pub struct GpuSortItem {
+    /// The index of the [`PreprocessWorkItem`] of this instance in the work
+    /// item buffer of the view.
+    pub work_item_index: u32,
+    /// The index of the batch of this instance among the batches sorted on the
+    /// GPU in the view.
+    pub batch_index: u32,
+}
+
+impl PreprocessWorkItemBuffer {
+    fn new(gpu_culling: bool) -> Self {
+        PreprocessWorkItemBuffer {
+            buffer: BufferVec::new(BufferUsages::STORAGE),
+            gpu_culling,
+            sort_items: BufferVec::new(BufferUsages::STORAGE),
+            sort_batch_count: 0,
+        }
+    }
 }
 
 /// One invocation of the preprocessing shader: i.e. one mesh instance in a
@@ -268,6 +310,8 @@ where
         self.previous_input_buffer.clear();
         for work_item_buffer in self.work_item_buffers.values_mut() {
             work_item_buffer.buffer.clear();
+            work_item_buffer.sort_items.clear();
+            work_item_buffer.sort_batch_count = 0;
         }
     }
 }
@@ -301,6 +345,12 @@ where
     /// If CPU culling is being used, then this will be `None`.
     indirect_parameters_index: Option<NonMaxU32>,
 
+    /// The index of this batch among the batches sorted on the GPU in the view.
+    ///
+    /// If the instances of this batch aren't sorted on the GPU, then this will
+    /// be `None`.
+    gpu_sorting_batch_index: Option<u32>,
+
     /// Metadata that can be used to determine whether an instance can be placed
     /// into this batch.
     ///
@@ -369,6 +419,10 @@ pub fn delete_old_work_item_buffers<GFBD>(
 /// Batch the items in a sorted render phase, when GPU instance buffer building
 /// is in use. This means comparing metadata needed to draw each phase item and
 /// trying to combine the draws into a batch.
+///
+/// In views with [`GpuSorting`](crate::view::GpuSorting), the instances of
+/// the phases that were only grouped into batches are also recorded to be
+/// sorted on the GPU.
 pub fn batch_and_prepare_sorted_render_phase<I, GFBD>(
     gpu_array_buffer: ResMut<BatchedInstanceBuffers<GFBD::BufferData, GFBD::BufferInputData>>,
     mut indirect_parameters_buffer: ResMut<IndirectParametersBuffer>,
@@ -387,13 +441,13 @@ pub fn batch_and_prepare_sorted_render_phase<I, GFBD>(
 
     for (view, mut phase, gpu_culling) in &mut views {
         // Create the work item buffer if necessary.
-        let work_item_buffer =
-            work_item_buffers
-                .entry(view)
-                .or_insert_with(|| PreprocessWorkItemBuffer {
-                    buffer: BufferVec::new(BufferUsages::STORAGE),
-                    gpu_culling,
-                });
+        let work_item_buffer = work_item_buffers
+            .entry(view)
+            .or_insert_with(|| PreprocessWorkItemBuffer::new(gpu_culling));
+
+        // Sorting on the GPU doesn't work along with GPU culling, which
+        // compacts the instances out of order.
+        let gpu_sorting = phase.sorted_on_gpu && !gpu_culling;
 
         // Walk through the list of phase items, building up batches as we go.
         let mut batch: Option<SortedRenderBatch<GFBD>> = None;
@@ -447,10 +501,23 @@ pub fn batch_and_prepare_sorted_render_phase<I, GFBD>(
                 } else {
                     None
                 };
+                let gpu_sorting_batch_index = if !gpu_sorting {
+                    None
+                } else if work_item_buffer.sort_batch_count < MAX_GPU_SORTING_BATCHES {
+                    work_item_buffer.sort_batch_count += 1;
+                    Some(work_item_buffer.sort_batch_count - 1)
+                } else {
+                    warn_once!(
+                        "Too many batches to sort on the GPU in a view; the remaining ones \
+                        won't be sorted"
+                    );
+                    None
+                };
                 batch = Some(SortedRenderBatch {
                     phase_item_start_index: current_index as u32,
                     instance_start_index: output_index,
                     indirect_parameters_index,
+                    gpu_sorting_batch_index,
                     meta: current_meta,
                 });
             }
@@ -459,6 +526,14 @@ pub fn batch_and_prepare_sorted_render_phase<I, GFBD>(
             // shader will copy the per-instance data over.
             if let (Some(batch), Some(input_index)) = (batch.as_ref(), current_input_index.as_ref())
             {
+                // Record the instance to be sorted on the GPU if necessary.
+                if let Some(batch_index) = batch.gpu_sorting_batch_index {
+                    work_item_buffer.sort_items.push(GpuSortItem {
+                        work_item_index: work_item_buffer.buffer.len() as u32,
+                        batch_index,
+                    });
+                }
+
                 work_item_buffer.buffer.push(PreprocessWorkItem {
                     input_index: (*input_index).into(),
                     output_index: match batch.indirect_parameters_index {
@@ -499,13 +574,9 @@ pub fn batch_and_prepare_binned_render_phase<BPI, GFBD>(
 
         // Create the work item buffer if necessary; otherwise, just mark it as
         // used this frame.
-        let work_item_buffer =
-            work_item_buffers
-                .entry(view)
-                .or_insert_with(|| PreprocessWorkItemBuffer {
-                    buffer: BufferVec::new(BufferUsages::STORAGE),
-                    gpu_culling,
-                });
+        let work_item_buffer = work_item_buffers
+            .entry(view)
+            .or_insert_with(|| PreprocessWorkItemBuffer::new(gpu_culling));
 
         // Prepare batchables.
 
@@ -638,6 +709,9 @@ pub fn write_batched_instance_buffers<GFBD>(
         index_buffer
             .buffer
             .write_buffer(&render_device, &render_queue);
+        index_buffer
+            .sort_items
+            .write_buffer(&render_device, &render_queue);
     }
 }
 
@@ -6,9 +6,11 @@ use crate::{
     render_asset::RenderAssets,
     render_graph::{InternedRenderSubGraph, RenderSubGraph},
     render_resource::TextureView,
+    renderer::{RenderAdapter, RenderDevice},
     texture::GpuImage,
     view::{
-        ColorGrading, ExtractedView, ExtractedWindows, GpuCulling, RenderLayers, VisibleEntities,
+        ColorGrading, ExtractedView, ExtractedWindows, GpuCulling, HdrFormat, Msaa, RenderLayers,
+        VisibleEntities,
     },
     Extract,
 };
@@ -44,7 +46,7 @@ use super::{ClearColorConfig, Projection};
 /// The viewport defines the area on the render target to which the camera renders its image.
 /// You can overlay multiple cameras in a single window using viewports to create effects like
 /// split screen, minimaps, and character viewers.
-#[derive(Reflect, Debug, Clone)]
+#[derive(Reflect, Debug, Clone, PartialEq)]
 #[reflect(Default)]
 pub struct Viewport {
     /// The physical position to render this viewport to within the [`RenderTarget`] of this [`Camera`].
@@ -206,6 +208,8 @@ pub struct Camera {
     /// If this is set to `true`, the camera will use an intermediate "high dynamic range" render texture.
     /// This allows rendering with a wider range of lighting values.
     pub hdr: bool,
+    /// The format of the intermediate texture used when [`Camera::hdr`] is enabled, see [`HdrFormat`].
+    pub hdr_format: HdrFormat,
     // todo: reflect this when #6042 lands
     /// The [`CameraOutputMode`] for this camera.
     #[reflect(ignore)]
@@ -229,6 +233,7 @@ impl Default for Camera {
             target: Default::default(),
             output_mode: Default::default(),
             hdr: false,
+            hdr_format: HdrFormat::default(),
             msaa_writeback: true,
             clear_color: Default::default(),
         }
@@ -816,6 +821,7 @@ pub struct ExtractedCamera {
     pub exposure: f32,
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn extract_cameras(
     mut commands: Commands,
     query: Extract<
@@ -835,7 +841,10 @@ pub fn extract_cameras(
         )>,
     >,
     primary_window: Extract<Query<Entity, With<PrimaryWindow>>>,
+    msaa: Extract<Res<Msaa>>,
     gpu_preprocessing_support: Res<GpuPreprocessingSupport>,
+    render_device: Res<RenderDevice>,
+    render_adapter: Res<RenderAdapter>,
 ) {
     let primary_window = primary_window.iter().next();
     for (
@@ -899,6 +908,13 @@ pub fn extract_cameras(
                     transform: *transform,
                     view_projection: None,
                     hdr: camera.hdr,
+                    hdr_format: if camera.hdr {
+                        camera
+                            .hdr_format
+                            .or_supported(&render_device, &render_adapter, **msaa)
+                    } else {
+                        HdrFormat::default()
+                    },
                     viewport: UVec4::new(
                         viewport_origin.x,
                         viewport_origin.y,
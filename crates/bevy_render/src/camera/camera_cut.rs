@@ -0,0 +1,91 @@
+use bevy_ecs::prelude::*;
+use bevy_reflect::{std_traits::ReflectDefault, Reflect};
+use bevy_transform::components::GlobalTransform;
+
+use crate::{extract_component::ExtractComponent, view::ViewHistoryTextures, Extract};
+
+/// Marks a camera as cutting to a different shot this frame, so that temporal
+/// effects don't blend the previous shot into the new one.
+///
+/// On the frame of a cut:
+/// - the history of every temporal effect of the view is reset, as with a
+///   [`ResetViewHistory`](crate::view::ResetViewHistory) event,
+/// - the camera cut bit of the view uniform flags is set, which zeroes the
+///   motion vectors written by the prepass,
+/// - motion blur is skipped.
+///
+/// Cinematic systems can insert this component when they cut, and cameras with
+/// [`CameraCutDetection`] get it automatically when they jump. It's removed at
+/// the start of the next frame.
+#[derive(Component, ExtractComponent, Reflect, Clone, Copy, Debug, Default)]
+#[reflect(Component, Default)]
+pub struct CameraCut;
+
+/// Inserts [`CameraCut`] on the camera when its [`GlobalTransform`] moves or
+/// turns further than the thresholds between two frames.
+#[derive(Component, Reflect, Clone, Copy, Debug)]
+#[reflect(Component, Default)]
+pub struct CameraCutDetection {
+    /// The distance the camera must move in a frame for it to be a cut.
+    pub translation_threshold: f32,
+    /// The angle, in radians, the camera must turn in a frame for it to be a
+    /// cut.
+    pub rotation_threshold: f32,
+    #[reflect(ignore)]
+    previous_transform: Option<GlobalTransform>,
+}
+
+impl CameraCutDetection {
+    pub fn new(translation_threshold: f32, rotation_threshold: f32) -> Self {
+        Self {
+            translation_threshold,
+            rotation_threshold,
+            previous_transform: None,
+        }
+    }
+}
+
+impl Default for CameraCutDetection {
+    fn default() -> Self {
+        Self::new(10.0, std::f32::consts::FRAC_PI_4)
+    }
+}
+
+/// Removes the [`CameraCut`] of the previous frame.
+pub fn remove_camera_cuts(mut commands: Commands, cameras: Query<Entity, With<CameraCut>>) {
+    for entity in &cameras {
+        commands.entity(entity).remove::<CameraCut>();
+    }
+}
+
+/// Inserts [`CameraCut`] on the cameras with [`CameraCutDetection`] that jumped
+/// since the previous frame.
+pub fn detect_camera_cuts(
+    mut commands: Commands,
+    mut cameras: Query<(Entity, &GlobalTransform, &mut CameraCutDetection)>,
+) {
+    for (entity, transform, mut detection) in &mut cameras {
+        let Some(previous_transform) = detection.previous_transform.replace(*transform) else {
+            continue;
+        };
+
+        let (_, previous_rotation, previous_translation) =
+            previous_transform.to_scale_rotation_translation();
+        let (_, rotation, translation) = transform.to_scale_rotation_translation();
+        if translation.distance(previous_translation) > detection.translation_threshold
+            || rotation.angle_between(previous_rotation) > detection.rotation_threshold
+        {
+            commands.entity(entity).insert(CameraCut);
+        }
+    }
+}
+
+/// Resets the history of the views that cut this frame.
+pub fn extract_camera_cuts(
+    mut histories: ResMut<ViewHistoryTextures>,
+    cameras: Extract<Query<Entity, With<CameraCut>>>,
+) {
+    for entity in &cameras {
+        histories.reset_history(entity);
+    }
+}
@@ -41,11 +41,14 @@ impl Node for CameraDriverNode {
             let mut run_graph = true;
             if let Some(NormalizedRenderTarget::Window(window_ref)) = camera.target {
                 let window_entity = window_ref.entity();
-                if windows.windows.get(&window_entity).is_some() {
-                    camera_windows.insert(window_entity);
-                } else {
+                match windows.windows.get(&window_entity) {
+                    Some(window) => {
+                        camera_windows.insert(window_entity);
+                        // The window isn't scheduled to be rendered this frame
+                        run_graph = window.should_render;
+                    }
                     // The window doesn't exist anymore so we don't need to run the graph
-                    run_graph = false;
+                    None => run_graph = false,
                 }
             }
             if run_graph {
@@ -0,0 +1,133 @@
+use std::sync::Arc;
+
+use bevy_ecs::prelude::*;
+use bevy_utils::warn_once;
+use wgpu::{CommandEncoder, Extent3d};
+
+use crate::{
+    camera::ExtractedCamera, extract_component::ExtractComponent, render_resource::Texture,
+    renderer::RenderQueue, view::ViewTarget,
+};
+
+/// Copies the final image of a camera into a texture shared with another process, such as a
+/// capture tool or a compositor.
+///
+/// Bevy doesn't create the shared texture: `wgpu` has no portable way to allocate memory that can
+/// be exported. Instead, create it from a platform handle (a DXGI shared handle, an `IOSurface`
+/// or a dmabuf) with the `wgpu-hal` interop APIs, such as
+/// [`wgpu::Device::create_texture_from_hal`], and convert it into a [`Texture`].
+///
+/// After the camera has rendered, its main texture is copied into [`Self::texture`] in the same
+/// submission as the rest of the frame. The texture must be at least as large as the camera's
+/// render target, have the format of its main texture (the
+/// [`Camera::hdr_format`](crate::camera::Camera::hdr_format) for HDR cameras,
+/// [`TextureFormat::bevy_default`](crate::texture::BevyDefault) otherwise) and be created with
+/// [`TextureUsages::COPY_DST`](wgpu::TextureUsages::COPY_DST). The camera's
+/// [`CameraMainTextureUsages`](crate::camera::CameraMainTextureUsages) must include
+/// [`TextureUsages::COPY_SRC`](wgpu::TextureUsages::COPY_SRC), which is the default.
+///
+/// Cameras that share a render target also share their main texture, so the copy contains the
+/// output of every camera that rendered to the target before it.
+#[derive(Component, ExtractComponent, Clone)]
+pub struct ExternalTextureExport {
+    /// The shared texture the camera's output is copied into.
+    pub texture: Texture,
+    /// Called from the render thread once the GPU has finished writing a frame into
+    /// [`Self::texture`]. Use this to signal the consumer, for example by releasing a keyed
+    /// mutex or signaling a shared fence, so that it never reads a partially written frame.
+    pub on_frame_ready: Option<Arc<dyn Fn() + Send + Sync>>,
+}
+
+impl ExternalTextureExport {
+    pub fn new(texture: impl Into<Texture>) -> Self {
+        Self {
+            texture: texture.into(),
+            on_frame_ready: None,
+        }
+    }
+
+    /// Sets [`Self::on_frame_ready`].
+    pub fn with_on_frame_ready(
+        mut self,
+        on_frame_ready: impl Fn() + Send + Sync + 'static,
+    ) -> Self {
+        self.on_frame_ready = Some(Arc::new(on_frame_ready));
+        self
+    }
+}
+
+/// The views whose output is copied into an [`ExternalTextureExport`] this frame.
+#[derive(Resource, Default)]
+pub struct ExternalTextureExportViews(Vec<(Entity, Extent3d)>);
+
+/// Collects the views that have a valid [`ExternalTextureExport`] into [`ExternalTextureExportViews`].
+pub fn prepare_external_texture_exports(
+    mut export_views: ResMut<ExternalTextureExportViews>,
+    views: Query<(
+        Entity,
+        &ExtractedCamera,
+        &ViewTarget,
+        &ExternalTextureExport,
+    )>,
+) {
+    export_views.0.clear();
+    for (entity, camera, view_target, export) in &views {
+        let Some(target_size) = camera.physical_target_size else {
+            continue;
+        };
+        let export_size = export.texture.size();
+        if export.texture.format() != view_target.main_texture_format()
+            || export_size.width < target_size.x
+            || export_size.height < target_size.y
+        {
+            warn_once!(
+                "The texture of the ExternalTextureExport of camera {entity:?} doesn't match its \
+                main texture, expected at least {}x{} with format {:?}",
+                target_size.x,
+                target_size.y,
+                view_target.main_texture_format()
+            );
+            continue;
+        }
+        export_views.0.push((
+            entity,
+            Extent3d {
+                width: target_size.x,
+                height: target_size.y,
+                depth_or_array_layers: 1,
+            },
+        ));
+    }
+}
+
+/// Copies the main texture of every exported view into its [`ExternalTextureExport`], after the
+/// render graph has run.
+pub(crate) fn submit_external_texture_export_commands(world: &World, encoder: &mut CommandEncoder) {
+    for (entity, size) in &world.resource::<ExternalTextureExportViews>().0 {
+        let (Some(view_target), Some(export)) = (
+            world.get::<ViewTarget>(*entity),
+            world.get::<ExternalTextureExport>(*entity),
+        ) else {
+            continue;
+        };
+        encoder.copy_texture_to_texture(
+            view_target.main_texture().as_image_copy(),
+            export.texture.as_image_copy(),
+            *size,
+        );
+    }
+}
+
+/// Calls [`ExternalTextureExport::on_frame_ready`] once the frame that was just submitted has
+/// finished executing on the GPU.
+pub(crate) fn notify_external_texture_exports(world: &World) {
+    let render_queue = world.resource::<RenderQueue>();
+    for (entity, _) in &world.resource::<ExternalTextureExportViews>().0 {
+        if let Some(on_frame_ready) = world
+            .get::<ExternalTextureExport>(*entity)
+            .and_then(|export| export.on_frame_ready.clone())
+        {
+            render_queue.on_submitted_work_done(move || on_frame_ready());
+        }
+    }
+}
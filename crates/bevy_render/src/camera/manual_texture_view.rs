@@ -29,6 +29,23 @@ impl ManualTextureView {
             format: TextureFormat::bevy_default(),
         }
     }
+
+    /// Creates a [`ManualTextureView`] of the whole `texture`, using its size and format.
+    ///
+    /// This is how textures created outside of Bevy are imported as a render target. When
+    /// rendering to a surface owned by the host application, call this each frame with the
+    /// texture of the current [`wgpu::SurfaceTexture`], replace the previous view in
+    /// [`ManualTextureViews`], and present the surface texture once the app has updated. This
+    /// requires disabling [`PipelinedRenderingPlugin`](crate::pipelined_rendering::PipelinedRenderingPlugin),
+    /// otherwise the frame is still being rendered when the update returns.
+    pub fn from_texture(texture: &wgpu::Texture) -> Self {
+        let size = texture.size();
+        Self {
+            texture_view: texture.create_view(&Default::default()).into(),
+            size: UVec2::new(size.width, size.height),
+            format: texture.format(),
+        }
+    }
 }
 
 /// Stores manually managed [`ManualTextureView`]s for use as a [`crate::camera::RenderTarget`].
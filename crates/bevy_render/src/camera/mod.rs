@@ -1,22 +1,31 @@
 #[allow(clippy::module_inception)]
 mod camera;
+mod camera_cut;
 mod camera_driver_node;
 mod clear_color;
+mod external_texture_export;
 mod manual_texture_view;
 mod projection;
+mod split_screen;
+mod viewport_aspect;
 
 pub use camera::*;
+pub use camera_cut::*;
 pub use camera_driver_node::*;
 pub use clear_color::*;
+pub use external_texture_export::*;
 pub use manual_texture_view::*;
 pub use projection::*;
+pub use split_screen::*;
+pub use viewport_aspect::*;
 
 use crate::{
     extract_component::ExtractComponentPlugin, extract_resource::ExtractResourcePlugin,
     render_graph::RenderGraph, ExtractSchedule, Render, RenderApp, RenderSet,
 };
-use bevy_app::{App, Plugin};
+use bevy_app::{App, First, Plugin, PostUpdate};
 use bevy_ecs::schedule::IntoSystemConfigs;
+use bevy_transform::TransformSystem;
 
 #[derive(Default)]
 pub struct CameraPlugin;
@@ -30,6 +39,11 @@ impl Plugin for CameraPlugin {
             .register_type::<Exposure>()
             .register_type::<TemporalJitter>()
             .register_type::<MipBias>()
+            .register_type::<SplitScreenLayout>()
+            .register_type::<SplitScreenArrangement>()
+            .register_type::<ViewportAspect>()
+            .register_type::<CameraCut>()
+            .register_type::<CameraCutDetection>()
             .init_resource::<ManualTextureViews>()
             .init_resource::<ClearColor>()
             .add_plugins((
@@ -39,13 +53,34 @@ impl Plugin for CameraPlugin {
                 ExtractResourcePlugin::<ManualTextureViews>::default(),
                 ExtractResourcePlugin::<ClearColor>::default(),
                 ExtractComponentPlugin::<CameraMainTextureUsages>::default(),
-            ));
+                ExtractComponentPlugin::<ExternalTextureExport>::default(),
+                ExtractComponentPlugin::<CameraCut>::default(),
+            ))
+            .add_systems(First, remove_camera_cuts)
+            .add_systems(
+                PostUpdate,
+                (
+                    (
+                        update_split_screen_layouts,
+                        update_viewport_aspects.after(update_split_screen_layouts),
+                    )
+                        .before(CameraUpdateSystem),
+                    detect_camera_cuts.after(TransformSystem::TransformPropagate),
+                ),
+            );
 
         if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
             render_app
                 .init_resource::<SortedCameras>()
-                .add_systems(ExtractSchedule, extract_cameras)
-                .add_systems(Render, sort_cameras.in_set(RenderSet::ManageViews));
+                .init_resource::<ExternalTextureExportViews>()
+                .add_systems(ExtractSchedule, (extract_cameras, extract_camera_cuts))
+                .add_systems(
+                    Render,
+                    (
+                        sort_cameras.in_set(RenderSet::ManageViews),
+                        prepare_external_texture_exports.in_set(RenderSet::PrepareResources),
+                    ),
+                );
             let camera_driver_node = CameraDriverNode::new(render_app.world_mut());
             let mut render_graph = render_app.world_mut().resource_mut::<RenderGraph>();
             render_graph.add_node(crate::graph::CameraDriverLabel, camera_driver_node);
@@ -0,0 +1,231 @@
+use bevy_ecs::{entity::EntityHashSet, prelude::*};
+use bevy_math::UVec2;
+use bevy_reflect::{std_traits::ReflectDefault, Reflect};
+use bevy_window::{Window, WindowRef};
+
+use crate::camera::{Camera, ClearColorConfig, RenderTarget, Viewport};
+
+/// Splits a window between several cameras, one per player.
+///
+/// Add this component to a [`Window`] entity, and list the cameras in
+/// [`Self::cameras`]. Every frame, each camera is made to render to the window,
+/// in its own [`Viewport`] laid out according to [`Self::arrangement`], so that
+/// the viewports follow the size of the window.
+///
+/// The cameras are ordered by their index in [`Self::cameras`], which
+/// overrides their [`Camera::order`]. Since clearing a render target clears all
+/// of it, only the first camera keeps its [`Camera::clear_color`], and the
+/// others are set to [`ClearColorConfig::None`].
+///
+/// When the window is too small to fit the viewport of a camera, the camera is
+/// deactivated with [`Camera::is_active`] rather than rendering to the whole
+/// window, and activated again once its viewport fits.
+///
+/// Every camera is still its own view, so post-processing settings such as
+/// tonemapping or bloom only apply to the player whose camera they're added
+/// to. For the same reason, every camera culls the scene against its own
+/// frustum: the frusta of the players don't overlap in general, so there's
+/// no culling work to share between them.
+#[derive(Component, Reflect, Clone, Debug, Default)]
+#[reflect(Component, Default)]
+pub struct SplitScreenLayout {
+    /// How the viewports are arranged in the window.
+    pub arrangement: SplitScreenArrangement,
+    /// The camera of each player, in order. Entities that aren't cameras are
+    /// ignored.
+    pub cameras: Vec<Entity>,
+    /// The gap between the viewports, in physical pixels.
+    pub gap: u32,
+}
+
+/// How a [`SplitScreenLayout`] arranges its viewports.
+#[derive(Reflect, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[reflect(Default)]
+pub enum SplitScreenArrangement {
+    /// The viewports are laid out in a grid with as many columns as rows, or
+    /// one more column, filled row by row.
+    #[default]
+    Grid,
+    /// The viewports are laid out side by side, from left to right.
+    Horizontal,
+    /// The viewports are stacked, from top to bottom.
+    Vertical,
+}
+
+impl SplitScreenLayout {
+    pub fn new(arrangement: SplitScreenArrangement, cameras: Vec<Entity>) -> Self {
+        Self {
+            arrangement,
+            cameras,
+            gap: 0,
+        }
+    }
+
+    /// Sets [`Self::gap`].
+    pub fn with_gap(mut self, gap: u32) -> Self {
+        self.gap = gap;
+        self
+    }
+
+    /// Returns the number of columns and rows of viewports for `count`
+    /// cameras.
+    pub fn grid_size(&self, count: u32) -> UVec2 {
+        let count = count.max(1);
+        match self.arrangement {
+            SplitScreenArrangement::Grid => {
+                let columns = (count as f32).sqrt().ceil() as u32;
+                UVec2::new(columns, count.div_ceil(columns))
+            }
+            SplitScreenArrangement::Horizontal => UVec2::new(count, 1),
+            SplitScreenArrangement::Vertical => UVec2::new(1, count),
+        }
+    }
+
+    /// Returns the viewport of the camera at `index` out of `count` cameras,
+    /// in a window of the given physical size, or `None` if there isn't
+    /// enough room for it.
+    pub fn viewport(&self, index: u32, count: u32, window_size: UVec2) -> Option<Viewport> {
+        let grid_size = self.grid_size(count);
+        let gaps = (grid_size - UVec2::ONE) * self.gap;
+        let cell_size = window_size.saturating_sub(gaps) / grid_size;
+        if index >= count || cell_size.x == 0 || cell_size.y == 0 {
+            return None;
+        }
+
+        let cell = UVec2::new(index % grid_size.x, index / grid_size.x);
+        Some(Viewport {
+            physical_position: cell * (cell_size + UVec2::splat(self.gap)),
+            physical_size: cell_size,
+            ..Default::default()
+        })
+    }
+}
+
+/// Updates the render target, viewport, order, clear color and activity of
+/// the cameras of every [`SplitScreenLayout`].
+///
+/// Cameras are only modified if their settings differ, so that they aren't
+/// marked as changed every frame. Only the cameras deactivated by this system,
+/// which are tracked in `deactivated`, are activated again, so cameras
+/// deactivated by the app stay inactive.
+pub fn update_split_screen_layouts(
+    layouts: Query<(Entity, &Window, &SplitScreenLayout)>,
+    mut cameras: Query<&mut Camera>,
+    mut deactivated: Local<EntityHashSet>,
+) {
+    for (window_entity, window, layout) in &layouts {
+        let count = layout
+            .cameras
+            .iter()
+            .filter(|camera| cameras.contains(**camera))
+            .count() as u32;
+        let window_size = window.physical_size();
+
+        let mut index = 0;
+        for camera_entity in &layout.cameras {
+            let Ok(mut camera) = cameras.get_mut(*camera_entity) else {
+                continue;
+            };
+
+            if !matches!(
+                camera.target,
+                RenderTarget::Window(WindowRef::Entity(target)) if target == window_entity
+            ) {
+                camera.target = RenderTarget::Window(WindowRef::Entity(window_entity));
+            }
+            match layout.viewport(index, count, window_size) {
+                Some(viewport) => {
+                    if camera.viewport.as_ref() != Some(&viewport) {
+                        camera.viewport = Some(viewport);
+                    }
+                    if deactivated.remove(camera_entity) {
+                        camera.is_active = true;
+                    }
+                }
+                None => {
+                    if camera.is_active {
+                        camera.is_active = false;
+                        deactivated.insert(*camera_entity);
+                    }
+                }
+            }
+            let order = index as isize;
+            if camera.order != order {
+                camera.order = order;
+            }
+            if index > 0 && !matches!(camera.clear_color, ClearColorConfig::None) {
+                camera.clear_color = ClearColorConfig::None;
+            }
+
+            index += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs::entity::Entity;
+    use bevy_math::UVec2;
+
+    use super::{SplitScreenArrangement, SplitScreenLayout};
+
+    fn layout(arrangement: SplitScreenArrangement, gap: u32) -> SplitScreenLayout {
+        SplitScreenLayout::new(arrangement, Vec::<Entity>::new()).with_gap(gap)
+    }
+
+    #[test]
+    fn grid_size() {
+        let grid = layout(SplitScreenArrangement::Grid, 0);
+        assert_eq!(grid.grid_size(0), UVec2::new(1, 1));
+        assert_eq!(grid.grid_size(1), UVec2::new(1, 1));
+        assert_eq!(grid.grid_size(2), UVec2::new(2, 1));
+        assert_eq!(grid.grid_size(3), UVec2::new(2, 2));
+        assert_eq!(grid.grid_size(4), UVec2::new(2, 2));
+        assert_eq!(grid.grid_size(5), UVec2::new(3, 2));
+        assert_eq!(grid.grid_size(9), UVec2::new(3, 3));
+
+        let horizontal = layout(SplitScreenArrangement::Horizontal, 0);
+        assert_eq!(horizontal.grid_size(3), UVec2::new(3, 1));
+        let vertical = layout(SplitScreenArrangement::Vertical, 0);
+        assert_eq!(vertical.grid_size(3), UVec2::new(1, 3));
+    }
+
+    #[test]
+    fn grid_viewports_fill_rows_first() {
+        let grid = layout(SplitScreenArrangement::Grid, 0);
+        let window_size = UVec2::new(1920, 1080);
+        let viewports: Vec<_> = (0..3)
+            .map(|index| grid.viewport(index, 3, window_size).unwrap())
+            .map(|viewport| (viewport.physical_position, viewport.physical_size))
+            .collect();
+        assert_eq!(
+            viewports,
+            [
+                (UVec2::new(0, 0), UVec2::new(960, 540)),
+                (UVec2::new(960, 0), UVec2::new(960, 540)),
+                (UVec2::new(0, 540), UVec2::new(960, 540)),
+            ]
+        );
+    }
+
+    #[test]
+    fn gaps_are_left_between_viewports() {
+        let vertical = layout(SplitScreenArrangement::Vertical, 10);
+        let window_size = UVec2::new(800, 610);
+        let first = vertical.viewport(0, 2, window_size).unwrap();
+        let second = vertical.viewport(1, 2, window_size).unwrap();
+        assert_eq!(first.physical_position, UVec2::new(0, 0));
+        assert_eq!(first.physical_size, UVec2::new(800, 300));
+        assert_eq!(second.physical_position, UVec2::new(0, 310));
+        assert_eq!(second.physical_size, UVec2::new(800, 300));
+    }
+
+    #[test]
+    fn no_viewport_without_room_or_out_of_range() {
+        let horizontal = layout(SplitScreenArrangement::Horizontal, 10);
+        assert!(horizontal.viewport(0, 2, UVec2::new(10, 100)).is_none());
+        assert!(horizontal.viewport(0, 2, UVec2::new(100, 0)).is_none());
+        assert!(horizontal.viewport(2, 2, UVec2::new(100, 100)).is_none());
+        assert!(horizontal.viewport(1, 2, UVec2::new(100, 100)).is_some());
+    }
+}
@@ -0,0 +1,216 @@
+use bevy_asset::Assets;
+use bevy_ecs::prelude::*;
+use bevy_math::UVec2;
+use bevy_reflect::{std_traits::ReflectDefault, Reflect};
+use bevy_window::{PrimaryWindow, Window};
+
+use crate::{
+    camera::{Camera, ManualTextureViews, Viewport},
+    texture::Image,
+};
+
+/// Computes the [`Camera::viewport`] of a camera from the size of its render
+/// target, so that the image keeps its aspect ratio when the window is
+/// resized.
+///
+/// Every frame, the viewport of cameras with this component is replaced by the
+/// one computed by [`Self::viewport`]. The area of the render target outside of
+/// the viewport isn't rendered to, and only gets the clear color of the camera,
+/// which gives the letterbox or pillarbox bars.
+///
+/// Since only the viewport is set, everything that already follows it keeps
+/// working: [`Camera::viewport_to_world`] and GPU picking take the cursor
+/// position in render target coordinates and map it through the computed
+/// viewport, and on WebGL the viewport is reset after the main passes of
+/// cameras with a viewport, as with any other camera.
+///
+/// This overrides the viewports set by a
+/// [`SplitScreenLayout`](super::SplitScreenLayout), so it shouldn't be added to
+/// the cameras of one.
+#[derive(Component, Reflect, Clone, Copy, Debug, Default, PartialEq)]
+#[reflect(Component, Default, PartialEq)]
+pub enum ViewportAspect {
+    /// The camera renders to the whole render target, and has no viewport.
+    ///
+    /// What happens to the aspect ratio of the image is left to the
+    /// projection of the camera. A [`PerspectiveProjection`] keeps its vertical
+    /// field of view, and crops or extends the sides of the image. An
+    /// [`OrthographicProjection`] stretches the image with
+    /// [`ScalingMode::Fixed`], crops it with [`ScalingMode::AutoMax`], and
+    /// extends it with [`ScalingMode::AutoMin`].
+    ///
+    /// [`PerspectiveProjection`]: super::PerspectiveProjection
+    /// [`OrthographicProjection`]: super::OrthographicProjection
+    /// [`ScalingMode::Fixed`]: super::ScalingMode::Fixed
+    /// [`ScalingMode::AutoMax`]: super::ScalingMode::AutoMax
+    /// [`ScalingMode::AutoMin`]: super::ScalingMode::AutoMin
+    #[default]
+    Fill,
+    /// The largest viewport with the given aspect ratio, the width divided by
+    /// the height, that fits in the render target, centered in it.
+    ///
+    /// Bars are left on the top and bottom of render targets that are taller
+    /// than the aspect ratio, and on the sides of those that are wider.
+    Letterbox { aspect_ratio: f32 },
+    /// A viewport of the given resolution scaled by the largest integer factor
+    /// that fits in the render target, centered in it.
+    ///
+    /// This keeps every pixel of pixel art the same size. It's meant to be
+    /// used with an [`OrthographicProjection`](super::OrthographicProjection)
+    /// whose [`ScalingMode::Fixed`](super::ScalingMode::Fixed) matches the
+    /// resolution. If the render target is smaller than the resolution, the
+    /// viewport is shrunk to fit in it.
+    IntegerScale { resolution: UVec2 },
+}
+
+impl ViewportAspect {
+    /// Returns the viewport for a render target of the given physical size, or
+    /// `None` if the camera should render to the whole render target.
+    pub fn viewport(&self, target_size: UVec2) -> Option<Viewport> {
+        let physical_size = match *self {
+            ViewportAspect::Fill => return None,
+            ViewportAspect::Letterbox { aspect_ratio } => {
+                if !aspect_ratio.is_finite() || aspect_ratio <= 0.0 {
+                    return None;
+                }
+                let target = target_size.as_vec2();
+                if target.x > target.y * aspect_ratio {
+                    UVec2::new((target.y * aspect_ratio).round() as u32, target_size.y)
+                } else {
+                    UVec2::new(target_size.x, (target.x / aspect_ratio).round() as u32)
+                }
+            }
+            ViewportAspect::IntegerScale { resolution } => {
+                if resolution.x == 0 || resolution.y == 0 {
+                    return None;
+                }
+                let scale = (target_size / resolution).min_element().max(1);
+                resolution * scale
+            }
+        };
+        let physical_size = physical_size.clamp(UVec2::ONE, target_size.max(UVec2::ONE));
+
+        Some(Viewport {
+            physical_position: target_size.saturating_sub(physical_size) / 2,
+            physical_size,
+            ..Default::default()
+        })
+    }
+}
+
+/// Updates the viewport of every camera with a [`ViewportAspect`] from the
+/// current size of its render target.
+///
+/// The viewport is computed before [`CameraUpdateSystem`](super::CameraUpdateSystem),
+/// so that the projection of the camera follows it in the same frame. Cameras
+/// are only modified if their viewport differs, so that they aren't marked as
+/// changed every frame.
+pub fn update_viewport_aspects(
+    mut cameras: Query<(&mut Camera, &ViewportAspect)>,
+    primary_window: Query<Entity, With<PrimaryWindow>>,
+    windows: Query<(Entity, &Window)>,
+    images: Res<Assets<Image>>,
+    manual_texture_views: Res<ManualTextureViews>,
+) {
+    let primary_window = primary_window.get_single().ok();
+
+    for (mut camera, aspect) in &mut cameras {
+        let Some(target_info) = camera.target.normalize(primary_window).and_then(|target| {
+            target.get_render_target_info(&windows, &images, &manual_texture_views)
+        }) else {
+            continue;
+        };
+
+        let viewport = aspect.viewport(target_info.physical_size);
+        if camera.viewport != viewport {
+            camera.viewport = viewport;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ViewportAspect;
+    use bevy_math::UVec2;
+
+    fn position_and_size(aspect: ViewportAspect, target_size: UVec2) -> Option<(UVec2, UVec2)> {
+        aspect
+            .viewport(target_size)
+            .map(|viewport| (viewport.physical_position, viewport.physical_size))
+    }
+
+    #[test]
+    fn fill_has_no_viewport() {
+        assert_eq!(
+            position_and_size(ViewportAspect::Fill, UVec2::new(1920, 1080)),
+            None
+        );
+    }
+
+    #[test]
+    fn letterbox_wider_target_is_pillarboxed() {
+        let aspect = ViewportAspect::Letterbox {
+            aspect_ratio: 4.0 / 3.0,
+        };
+        assert_eq!(
+            position_and_size(aspect, UVec2::new(1920, 1080)),
+            Some((UVec2::new(240, 0), UVec2::new(1440, 1080)))
+        );
+    }
+
+    #[test]
+    fn letterbox_taller_target_is_letterboxed() {
+        let aspect = ViewportAspect::Letterbox { aspect_ratio: 2.0 };
+        assert_eq!(
+            position_and_size(aspect, UVec2::new(1000, 1000)),
+            Some((UVec2::new(0, 250), UVec2::new(1000, 500)))
+        );
+    }
+
+    #[test]
+    fn letterbox_equal_aspect_ratio_fills_target() {
+        let aspect = ViewportAspect::Letterbox {
+            aspect_ratio: 16.0 / 9.0,
+        };
+        assert_eq!(
+            position_and_size(aspect, UVec2::new(1600, 900)),
+            Some((UVec2::ZERO, UVec2::new(1600, 900)))
+        );
+    }
+
+    #[test]
+    fn letterbox_invalid_aspect_ratio_has_no_viewport() {
+        for aspect_ratio in [0.0, -1.0, f32::NAN, f32::INFINITY] {
+            let aspect = ViewportAspect::Letterbox { aspect_ratio };
+            assert_eq!(position_and_size(aspect, UVec2::new(800, 600)), None);
+        }
+    }
+
+    #[test]
+    fn integer_scale_uses_largest_fitting_factor() {
+        let aspect = ViewportAspect::IntegerScale {
+            resolution: UVec2::new(320, 180),
+        };
+        // 1920x1200 fits 6x horizontally and 6x vertically.
+        assert_eq!(
+            position_and_size(aspect, UVec2::new(1920, 1200)),
+            Some((UVec2::new(0, 60), UVec2::new(1920, 1080)))
+        );
+        // 1000x1000 fits 3x horizontally and 5x vertically.
+        assert_eq!(
+            position_and_size(aspect, UVec2::new(1000, 1000)),
+            Some((UVec2::new(20, 230), UVec2::new(960, 540)))
+        );
+    }
+
+    #[test]
+    fn integer_scale_shrinks_to_small_target() {
+        let aspect = ViewportAspect::IntegerScale {
+            resolution: UVec2::new(320, 180),
+        };
+        assert_eq!(
+            position_and_size(aspect, UVec2::new(200, 100)),
+            Some((UVec2::ZERO, UVec2::new(200, 100)))
+        );
+    }
+}
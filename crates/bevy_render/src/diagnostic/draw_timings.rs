@@ -0,0 +1,336 @@
+use std::{
+    borrow::Cow,
+    mem,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use bevy_app::{App, Plugin, PreUpdate};
+use bevy_diagnostic::{Diagnostic, DiagnosticMeasurement, DiagnosticPath, DiagnosticsStore};
+use bevy_ecs::prelude::*;
+use bevy_utils::{tracing::warn, warn_once, HashMap, Instant};
+use wgpu::{
+    Buffer, BufferDescriptor, BufferUsages, CommandEncoder, Features, QuerySet, QuerySetDescriptor,
+    QueryType,
+};
+
+use crate::{
+    extract_component::{ExtractComponent, ExtractComponentPlugin},
+    render_phase::TrackedRenderPass,
+    render_resource::ReadbackBuffer,
+    renderer::{RenderDevice, RenderQueue, WgpuWrapper},
+    RenderApp,
+};
+
+use super::internal::WriteTimestamp;
+
+/// The maximum number of draws timed in a frame. Frames with more timed draws aren't measured.
+pub const MAX_TIMED_DRAWS: u32 = 128;
+
+const TIMESTAMP_SIZE: u64 = 8;
+
+/// Times the draws of the entities with [`TimedDraws`] on the GPU, and records the elapsed GPU
+/// time of each label in the [`DiagnosticsStore`], as `render/timed_draws/<label>/elapsed_gpu`
+/// in milliseconds, along with the number of draws as `render/timed_draws/<label>/draws`.
+///
+/// This finds the meshes whose shaders are the most expensive to draw. Each timed draw writes
+/// two timestamps inside its render pass, so only tag a few entities: the frames with more than
+/// [`MAX_TIMED_DRAWS`] timed draws aren't measured. Draw functions decide which draws are timed
+/// and their label with [`DrawTimer::time_draw`], e.g. the materials of `bevy_pbr` label them with
+/// the name of the material type.
+///
+/// Timestamps inside render passes require [`Features::TIMESTAMP_QUERY`] and
+/// [`Features::TIMESTAMP_QUERY_INSIDE_PASSES`], which aren't supported on every platform, e.g. on
+/// WebGPU and WebGL2. Tile-based GPUs may attribute the cost of a draw to the following ones.
+#[derive(Default)]
+pub struct DrawTimingsPlugin;
+
+impl Plugin for DrawTimingsPlugin {
+    fn build(&self, app: &mut App) {
+        let draw_timings_mutex = DrawTimingsMutex::default();
+        app.insert_resource(draw_timings_mutex.clone())
+            .add_plugins(ExtractComponentPlugin::<TimedDraws>::default())
+            .add_systems(PreUpdate, sync_draw_timings);
+
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app.insert_resource(draw_timings_mutex);
+        }
+    }
+
+    fn finish(&self, app: &mut App) {
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+
+        let device = render_app.world().resource::<RenderDevice>();
+        let required_features = Features::TIMESTAMP_QUERY | Features::TIMESTAMP_QUERY_INSIDE_PASSES;
+        if !device.features().contains(required_features) {
+            warn!(
+                "DrawTimingsPlugin requires {required_features:?}, which aren't supported. Draws won't be timed."
+            );
+            return;
+        }
+
+        let queue = render_app.world().resource::<RenderQueue>();
+        let draw_timer = DrawTimer::new(device, queue);
+        render_app.insert_resource(draw_timer);
+    }
+}
+
+/// Marks an entity whose draws are timed on the GPU, see [`DrawTimingsPlugin`].
+#[derive(Component, Clone, Copy, Debug, Default, ExtractComponent)]
+pub struct TimedDraws;
+
+struct DrawTimerInternal {
+    timestamp_period_ns: f32,
+    query_set: QuerySet,
+    resolve_buffer: Buffer,
+    num_draws: AtomicU32,
+    overflowed: AtomicBool,
+    labels: Mutex<Vec<(u32, Cow<'static, str>)>>,
+    pending_frames: Mutex<Vec<DrawTimingFrame>>,
+    free_read_buffers: Mutex<Vec<ReadbackBuffer>>,
+}
+
+/// The timed draws of a frame, waiting for their timestamps to be read back.
+struct DrawTimingFrame {
+    read_buffer: ReadbackBuffer,
+    labels: Vec<(u32, Cow<'static, str>)>,
+}
+
+/// Writes the timestamps of the timed draws of the render world, see [`DrawTimingsPlugin`].
+///
+/// This resource only exists if the timestamps are supported.
+#[derive(Resource)]
+pub struct DrawTimer(WgpuWrapper<DrawTimerInternal>);
+
+impl DrawTimer {
+    fn new(device: &RenderDevice, queue: &RenderQueue) -> Self {
+        let wgpu_device = device.wgpu_device();
+        let query_set = wgpu_device.create_query_set(&QuerySetDescriptor {
+            label: Some("draw_timings_query_set"),
+            ty: QueryType::Timestamp,
+            count: MAX_TIMED_DRAWS * 2,
+        });
+        let resolve_buffer = wgpu_device.create_buffer(&BufferDescriptor {
+            label: Some("draw_timings_resolve_buffer"),
+            size: u64::from(MAX_TIMED_DRAWS * 2) * TIMESTAMP_SIZE,
+            usage: BufferUsages::QUERY_RESOLVE | BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+
+        DrawTimer(WgpuWrapper::new(DrawTimerInternal {
+            timestamp_period_ns: queue.get_timestamp_period(),
+            query_set,
+            resolve_buffer,
+            num_draws: AtomicU32::new(0),
+            overflowed: AtomicBool::new(false),
+            labels: Mutex::new(Vec::new()),
+            pending_frames: Mutex::new(Vec::new()),
+            free_read_buffers: Mutex::new(Vec::new()),
+        }))
+    }
+
+    /// Runs `draw`, timing the commands it records in `pass` under `label`.
+    ///
+    /// The draw isn't timed if the frame already has [`MAX_TIMED_DRAWS`] timed draws, in which
+    /// case none of the draws of the frame are measured.
+    pub fn time_draw<'w, R>(
+        &self,
+        pass: &mut TrackedRenderPass<'w>,
+        label: impl Into<Cow<'static, str>>,
+        draw: impl FnOnce(&mut TrackedRenderPass<'w>) -> R,
+    ) -> R {
+        let index = self.0.num_draws.fetch_add(1, Ordering::Relaxed);
+        if index >= MAX_TIMED_DRAWS {
+            self.0.overflowed.store(true, Ordering::Relaxed);
+            return draw(pass);
+        }
+
+        pass.write_timestamp(&self.0.query_set, index * 2);
+        let result = draw(pass);
+        pass.write_timestamp(&self.0.query_set, index * 2 + 1);
+
+        self.0
+            .labels
+            .lock()
+            .expect("lock poisoned")
+            .push((index, label.into()));
+        result
+    }
+
+    /// Copies the timestamps of the draws of the frame to a buffer that can be read back, after
+    /// the render graph has run.
+    fn resolve(&self, device: &RenderDevice, encoder: &mut CommandEncoder) {
+        let num_draws = self.0.num_draws.swap(0, Ordering::Relaxed);
+        let labels = mem::take(&mut *self.0.labels.lock().expect("lock poisoned"));
+        if self.0.overflowed.swap(false, Ordering::Relaxed) {
+            warn_once!(
+                "More than {MAX_TIMED_DRAWS} draws were timed in a frame, which isn't measured. Time fewer draws."
+            );
+            return;
+        }
+        if num_draws == 0 {
+            return;
+        }
+
+        let size = u64::from(num_draws * 2) * TIMESTAMP_SIZE;
+        encoder.resolve_query_set(
+            &self.0.query_set,
+            0..num_draws * 2,
+            &self.0.resolve_buffer,
+            0,
+        );
+
+        let mut read_buffer = self
+            .0
+            .free_read_buffers
+            .lock()
+            .expect("lock poisoned")
+            .pop()
+            .unwrap_or_else(|| {
+                ReadbackBuffer::new(
+                    device,
+                    "draw_timings_read_buffer",
+                    u64::from(MAX_TIMED_DRAWS * 2) * TIMESTAMP_SIZE,
+                )
+            });
+        read_buffer.start_copy();
+        encoder.copy_buffer_to_buffer(&self.0.resolve_buffer, 0, &read_buffer, 0, size);
+
+        self.0
+            .pending_frames
+            .lock()
+            .expect("lock poisoned")
+            .push(DrawTimingFrame {
+                read_buffer,
+                labels,
+            });
+    }
+
+    /// Sends the timings of the frames whose timestamps were read back to the main world, and
+    /// starts reading back the timestamps of the frame that was just submitted.
+    fn map(&self, draw_timings_mutex: &DrawTimingsMutex) {
+        let mut pending_frames = self.0.pending_frames.lock().expect("lock poisoned");
+        let mut index = 0;
+        while index < pending_frames.len() {
+            let frame = &mut pending_frames[index];
+            match frame.read_buffer.poll() {
+                None => {
+                    index += 1;
+                    continue;
+                }
+                Some(Err(err)) => warn!("Failed to read back the draw timings: {err}"),
+                Some(Ok(())) => {
+                    let timings = frame.timings(self.0.timestamp_period_ns);
+                    *draw_timings_mutex.0.lock().expect("lock poisoned") = Some(timings);
+                }
+            }
+
+            let frame = pending_frames.swap_remove(index);
+            self.0
+                .free_read_buffers
+                .lock()
+                .expect("lock poisoned")
+                .push(frame.read_buffer);
+        }
+    }
+}
+
+impl DrawTimingFrame {
+    /// Sums the elapsed GPU time of the draws of each label, from the mapped read buffer, and
+    /// unmaps it.
+    fn timings(&self, timestamp_period_ns: f32) -> DrawTimings {
+        let timestamps = self.read_buffer.read(|data| {
+            data.chunks_exact(TIMESTAMP_SIZE as usize)
+                .map(|bytes| u64::from_ne_bytes(bytes.try_into().unwrap()))
+                .collect::<Vec<u64>>()
+        });
+
+        let mut timings = DrawTimings::default();
+        for (index, label) in &self.labels {
+            let begin = timestamps[*index as usize * 2];
+            let end = timestamps[*index as usize * 2 + 1];
+            let elapsed_ms =
+                end.saturating_sub(begin) as f64 * f64::from(timestamp_period_ns) / 1e6;
+            timings.add(label.clone(), elapsed_ms);
+        }
+        timings
+    }
+}
+
+/// The elapsed GPU time and number of the timed draws of each label in a frame.
+#[derive(Debug, Default, Clone)]
+struct DrawTimings(HashMap<Cow<'static, str>, (f64, u32)>);
+
+impl DrawTimings {
+    fn add(&mut self, label: Cow<'static, str>, elapsed_ms: f64) {
+        let (total_ms, draws) = self.0.entry(label).or_default();
+        *total_ms += elapsed_ms;
+        *draws += 1;
+    }
+}
+
+/// Stores the draw timings of the last measured frame until they're synced with the main app.
+#[derive(Debug, Default, Clone, Resource)]
+struct DrawTimingsMutex(Arc<Mutex<Option<DrawTimings>>>);
+
+/// Resolves the timestamps of the draws timed by the [`DrawTimer`], after the render graph has
+/// run.
+pub(crate) fn resolve_draw_timings(world: &World, encoder: &mut CommandEncoder) {
+    if let Some(draw_timer) = world.get_resource::<DrawTimer>() {
+        draw_timer.resolve(world.resource::<RenderDevice>(), encoder);
+    }
+}
+
+/// Reads back the timestamps of the draws timed by the [`DrawTimer`], once the frame was
+/// submitted.
+pub(crate) fn map_draw_timings(world: &World) {
+    if let Some(draw_timer) = world.get_resource::<DrawTimer>() {
+        draw_timer.map(world.resource::<DrawTimingsMutex>());
+    }
+}
+
+/// Records the draw timings in the [`DiagnosticsStore`].
+fn sync_draw_timings(mutex: Res<DrawTimingsMutex>, mut store: ResMut<DiagnosticsStore>) {
+    let Some(timings) = mutex.0.lock().ok().and_then(|mut v| v.take()) else {
+        return;
+    };
+
+    let time = Instant::now();
+
+    for (label, (elapsed_ms, draws)) in &timings.0 {
+        for (field, suffix, value) in [
+            ("elapsed_gpu", "ms", *elapsed_ms),
+            ("draws", "", f64::from(*draws)),
+        ] {
+            let path = DiagnosticPath::from_components(["render", "timed_draws", label, field]);
+            if store.get(&path).is_none() {
+                store.add(Diagnostic::new(path.clone()).with_suffix(suffix));
+            }
+
+            store
+                .get_mut(&path)
+                .unwrap()
+                .add_measurement(DiagnosticMeasurement { time, value });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DrawTimings;
+
+    #[test]
+    fn draw_timings_are_summed_per_label() {
+        let mut timings = DrawTimings::default();
+        timings.add("StandardMaterial".into(), 0.5);
+        timings.add("WaterMaterial".into(), 2.0);
+        timings.add("StandardMaterial".into(), 0.25);
+
+        assert_eq!(timings.0["StandardMaterial"], (0.75, 2));
+        assert_eq!(timings.0["WaterMaterial"], (2.0, 1));
+    }
+}
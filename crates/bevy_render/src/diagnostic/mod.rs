@@ -2,6 +2,7 @@
 //!
 //! For more info, see [`RenderDiagnosticsPlugin`].
 
+mod draw_timings;
 pub(crate) mod internal;
 
 use std::{borrow::Cow, marker::PhantomData, sync::Arc};
@@ -10,6 +11,7 @@ use bevy_app::{App, Plugin, PreUpdate};
 
 use crate::RenderApp;
 
+pub use self::draw_timings::*;
 use self::internal::{
     sync_diagnostics, DiagnosticsRecorder, Pass, RenderDiagnosticsMutex, WriteTimestamp,
 };
@@ -39,6 +41,8 @@ use super::{RenderDevice, RenderQueue};
 ///     time_span.end(render_context.command_encoder());
 ///     ```
 ///
+/// To time the draws of specific entities, see [`DrawTimingsPlugin`].
+///
 /// # Supported platforms
 /// Timestamp queries and pipeline statistics are currently supported only on Vulkan and DX12.
 /// On other platforms (Metal, WebGPU, WebGL2) only CPU time will be recorded.
@@ -105,7 +105,8 @@ where
     }
 }
 
-fn extract_all<EI>(
+/// Extracts the instances of all the entities, see [`ExtractInstancesPlugin::new`].
+pub fn extract_all<EI>(
     mut extracted_instances: ResMut<ExtractedInstances<EI>>,
     query: Extract<Query<(Entity, EI::QueryData), EI::QueryFilter>>,
 ) where
@@ -119,7 +120,9 @@ fn extract_all<EI>(
     }
 }
 
-fn extract_visible<EI>(
+/// Extracts the instances of the visible entities, see
+/// [`ExtractInstancesPlugin::extract_visible`].
+pub fn extract_visible<EI>(
     mut extracted_instances: ResMut<ExtractedInstances<EI>>,
     query: Extract<Query<(Entity, &ViewVisibility, EI::QueryData), EI::QueryFilter>>,
 ) where
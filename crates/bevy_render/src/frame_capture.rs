@@ -0,0 +1,422 @@
+//! Captures the render data of a frame to disk, to compare it between frames, runs or
+//! versions when bisecting a rendering issue.
+//!
+//! This module is only available with the `frame_capture` feature. Add the
+//! [`FrameCapturePlugin`], then call [`FrameCapture::capture`] in the main world to capture the
+//! next rendered frame. The capture is a [`CapturedFrame`] written as RON, once the frame was
+//! queued and its phases were batched. It contains:
+//!
+//! - The [`ExtractedView`] of every view: its projection, transform and viewport.
+//! - The phase items of every phase captured with a [`FrameCapturePhasePlugin`], per view, in
+//!   draw order: the entity, pipeline, draw function, instance range and extra index of each
+//!   draw. `bevy_pbr` adds these plugins for the 3D phases it draws meshes in.
+//! - The mesh instances of the frame, when `bevy_pbr` is used.
+//!
+//! Captures can only be inspected and compared, they can't be replayed. A capture can be read
+//! back with [`CapturedFrame::read`], but nothing renders it again, and it couldn't be rendered
+//! without the app that captured it:
+//!
+//! - Meshes, images and materials only exist in the render world as GPU buffers, textures and
+//!   bind groups, so only their asset IDs are captured.
+//! - Pipeline and draw function IDs are indices into the [`PipelineCache`] and [`DrawFunctions`]
+//!   of the app, and are only meaningful to the app that captured them.
+//! - Render world data other than the views, phases and mesh instances isn't captured: lights,
+//!   clusters, and the contents of the instance and material buffers.
+//!
+//! [`PipelineCache`]: crate::render_resource::PipelineCache
+//! [`DrawFunctions`]: crate::render_phase::DrawFunctions
+
+use std::{
+    any::type_name,
+    fs, io,
+    marker::PhantomData,
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use bevy_app::{App, Plugin};
+use bevy_ecs::prelude::*;
+use bevy_tasks::IoTaskPool;
+use bevy_utils::tracing::{error, info};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    render_on_demand::should_render_frame,
+    render_phase::{
+        BinnedPhaseItem, BinnedRenderPhase, CachedRenderPipelinePhaseItem, SortedPhaseItem,
+        SortedRenderPhase,
+    },
+    view::ExtractedView,
+    ExtractSchedule, Render, RenderApp, RenderSet,
+};
+
+/// Sets up [`FrameCapture`].
+///
+/// This plugin isn't added by default. Phases are only captured once their
+/// [`FrameCapturePhasePlugin`] is added too.
+#[derive(Default)]
+pub struct FrameCapturePlugin;
+
+impl Plugin for FrameCapturePlugin {
+    fn build(&self, app: &mut App) {
+        let frame_capture = FrameCapture::default();
+        app.insert_resource(frame_capture.clone());
+
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+
+        render_app
+            .insert_resource(frame_capture)
+            .configure_sets(
+                Render,
+                FrameCaptureSystems
+                    .after(RenderSet::PrepareResources)
+                    .before(RenderSet::Render)
+                    .run_if(resource_exists::<PendingFrameCapture>.and_then(should_render_frame)),
+            )
+            .add_systems(ExtractSchedule, start_frame_capture)
+            .add_systems(
+                Render,
+                (
+                    capture_views.in_set(FrameCaptureSystems),
+                    write_frame_capture.in_set(RenderSet::Cleanup).run_if(
+                        resource_exists::<PendingFrameCapture>.and_then(should_render_frame),
+                    ),
+                ),
+            );
+    }
+}
+
+/// Captures the render phase component `P` of every view, e.g.
+/// `FrameCapturePhasePlugin::<BinnedRenderPhase<Opaque3d>>`.
+///
+/// This does nothing without the [`FrameCapturePlugin`].
+pub struct FrameCapturePhasePlugin<P: CaptureRenderPhase>(PhantomData<P>);
+
+impl<P: CaptureRenderPhase> Default for FrameCapturePhasePlugin<P> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<P: CaptureRenderPhase> Plugin for FrameCapturePhasePlugin<P> {
+    fn build(&self, app: &mut App) {
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+
+        render_app.add_systems(
+            Render,
+            capture_render_phase::<P>
+                .in_set(FrameCaptureSystems)
+                .run_if(resource_exists::<PendingFrameCapture>),
+        );
+    }
+}
+
+/// A render phase component whose items can be captured by a [`FrameCapturePhasePlugin`].
+pub trait CaptureRenderPhase: Component {
+    /// Returns the name of the phase in captures.
+    fn name() -> String;
+
+    /// Captures the items of the phase, in draw order.
+    fn capture_items(&self) -> Vec<CapturedPhaseItem>;
+}
+
+impl<BPI> CaptureRenderPhase for BinnedRenderPhase<BPI>
+where
+    BPI: BinnedPhaseItem + CachedRenderPipelinePhaseItem,
+{
+    fn name() -> String {
+        type_name::<BPI>().to_owned()
+    }
+
+    fn capture_items(&self) -> Vec<CapturedPhaseItem> {
+        self.items()
+            .map(|item| CapturedPhaseItem::new(&item))
+            .collect()
+    }
+}
+
+impl<SPI> CaptureRenderPhase for SortedRenderPhase<SPI>
+where
+    SPI: SortedPhaseItem + CachedRenderPipelinePhaseItem,
+{
+    fn name() -> String {
+        type_name::<SPI>().to_owned()
+    }
+
+    fn capture_items(&self) -> Vec<CapturedPhaseItem> {
+        self.items.iter().map(CapturedPhaseItem::new).collect()
+    }
+}
+
+/// The render systems that fill the [`PendingFrameCapture`], after the phases were batched.
+#[derive(SystemSet, Clone, PartialEq, Eq, Debug, Hash)]
+pub struct FrameCaptureSystems;
+
+/// Requests frame captures, see the [module documentation](self).
+///
+/// This resource is shared between the main world and the render world.
+#[derive(Resource, Clone, Default)]
+pub struct FrameCapture(Arc<Mutex<Option<PathBuf>>>);
+
+impl FrameCapture {
+    /// Captures the next rendered frame to the file at `path`.
+    ///
+    /// If a capture was already requested and hasn't started yet, it is replaced.
+    pub fn capture(&self, path: impl Into<PathBuf>) {
+        *self.0.lock().unwrap() = Some(path.into());
+    }
+}
+
+/// Render world resource holding the capture of the current frame while it is filled by the
+/// [`FrameCaptureSystems`].
+#[derive(Resource)]
+pub struct PendingFrameCapture {
+    /// The file the capture is written to.
+    pub path: PathBuf,
+    /// The capture.
+    pub frame: CapturedFrame,
+}
+
+/// The render data of a frame captured by [`FrameCapture`].
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CapturedFrame {
+    /// The views, in no particular order.
+    pub views: Vec<CapturedView>,
+    /// The mesh instances, in no particular order.
+    pub mesh_instances: Vec<CapturedMeshInstance>,
+}
+
+impl CapturedFrame {
+    /// Returns the captured view of the render world entity `view`, adding it if it wasn't
+    /// captured yet.
+    pub fn view_mut(&mut self, view: Entity) -> &mut CapturedView {
+        let entity = view.to_bits();
+        match self.views.iter().position(|view| view.entity == entity) {
+            Some(index) => &mut self.views[index],
+            None => {
+                self.views.push(CapturedView {
+                    entity,
+                    ..Default::default()
+                });
+                self.views.last_mut().unwrap()
+            }
+        }
+    }
+
+    /// Reads a capture written by [`FrameCapture`].
+    pub fn read(path: impl AsRef<Path>) -> Result<Self, FrameCaptureError> {
+        Ok(ron::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    /// Writes the capture to the file at `path`.
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<(), FrameCaptureError> {
+        let ron = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        fs::write(path, ron)?;
+        Ok(())
+    }
+}
+
+/// A view captured by [`FrameCapture`].
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CapturedView {
+    /// The [`Entity::to_bits`] of the render world entity of the view.
+    pub entity: u64,
+    /// The [`ExtractedView::projection`], column by column.
+    pub projection: [f32; 16],
+    /// The matrix of the [`ExtractedView::transform`], column by column.
+    pub transform: [f32; 16],
+    /// The [`ExtractedView::viewport`]: its origin, then its size.
+    pub viewport: [u32; 4],
+    /// Whether the view is HDR.
+    pub hdr: bool,
+    /// The phases of the view that have items.
+    pub phases: Vec<CapturedPhase>,
+}
+
+/// A render phase captured by [`FrameCapture`].
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CapturedPhase {
+    /// The type name of the phase items of the phase.
+    pub name: String,
+    /// The items, in draw order.
+    pub items: Vec<CapturedPhaseItem>,
+}
+
+/// A phase item captured by [`FrameCapture`].
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CapturedPhaseItem {
+    /// The [`Entity::to_bits`] of the render world entity drawn by the item.
+    pub entity: u64,
+    /// The [`CachedRenderPipelineId::id`](crate::render_resource::CachedRenderPipelineId::id) of
+    /// the pipeline of the item.
+    pub pipeline: usize,
+    /// The [`DrawFunctionId::id`](crate::render_phase::DrawFunctionId::id) of the draw function
+    /// of the item.
+    pub draw_function: u32,
+    /// The range of instances drawn by the item.
+    pub batch_range: Range<u32>,
+    /// The [`PhaseItemExtraIndex`](crate::render_phase::PhaseItemExtraIndex) of the item.
+    pub extra_index: u32,
+}
+
+impl CapturedPhaseItem {
+    /// Captures a phase item.
+    pub fn new<I: CachedRenderPipelinePhaseItem>(item: &I) -> Self {
+        Self {
+            entity: item.entity().to_bits(),
+            pipeline: item.cached_pipeline().id(),
+            draw_function: item.draw_function().id(),
+            batch_range: item.batch_range().clone(),
+            extra_index: item.extra_index().0,
+        }
+    }
+}
+
+/// A mesh instance captured by [`FrameCapture`].
+#[derive(Clone, Default, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CapturedMeshInstance {
+    /// The [`Entity::to_bits`] of the render world entity of the instance.
+    pub entity: u64,
+    /// The asset ID of the mesh.
+    pub mesh: String,
+    /// The translation of the instance.
+    pub translation: [f32; 3],
+    /// The affine transform of the instance, transposed to 3x4, when it is kept on the CPU.
+    pub transform: Option<[f32; 12]>,
+    /// The index of the mesh input uniform of the instance, when mesh uniforms are built on the
+    /// GPU.
+    pub input_uniform_index: Option<u32>,
+    /// The flags of the instance.
+    pub flags: u32,
+}
+
+/// An error reading or writing a [`CapturedFrame`].
+#[derive(Error, Debug)]
+pub enum FrameCaptureError {
+    #[error("failed to access the capture file: {0}")]
+    Io(#[from] io::Error),
+    #[error("failed to serialize the capture: {0}")]
+    Serialize(#[from] ron::Error),
+    #[error("failed to deserialize the capture: {0}")]
+    Deserialize(#[from] ron::error::SpannedError),
+}
+
+/// Starts a capture if one was requested.
+fn start_frame_capture(
+    mut commands: Commands,
+    frame_capture: Res<FrameCapture>,
+    pending_frame_capture: Option<Res<PendingFrameCapture>>,
+) {
+    if pending_frame_capture.is_some() {
+        return;
+    }
+    if let Some(path) = frame_capture.0.lock().unwrap().take() {
+        commands.insert_resource(PendingFrameCapture {
+            path,
+            frame: CapturedFrame::default(),
+        });
+    }
+}
+
+fn capture_views(
+    mut pending_frame_capture: ResMut<PendingFrameCapture>,
+    views: Query<(Entity, &ExtractedView)>,
+) {
+    for (entity, extracted_view) in &views {
+        let view = pending_frame_capture.frame.view_mut(entity);
+        view.projection = extracted_view.projection.to_cols_array();
+        view.transform = extracted_view.transform.compute_matrix().to_cols_array();
+        view.viewport = extracted_view.viewport.to_array();
+        view.hdr = extracted_view.hdr;
+    }
+}
+
+fn capture_render_phase<P: CaptureRenderPhase>(
+    mut pending_frame_capture: ResMut<PendingFrameCapture>,
+    phases: Query<(Entity, &P)>,
+) {
+    for (view, phase) in &phases {
+        let items = phase.capture_items();
+        if items.is_empty() {
+            continue;
+        }
+        pending_frame_capture
+            .frame
+            .view_mut(view)
+            .phases
+            .push(CapturedPhase {
+                name: P::name(),
+                items,
+            });
+    }
+}
+
+/// Writes the capture of the frame once it was filled, in a task of the [`IoTaskPool`].
+fn write_frame_capture(world: &mut World) {
+    let Some(PendingFrameCapture { path, mut frame }) =
+        world.remove_resource::<PendingFrameCapture>()
+    else {
+        return;
+    };
+    // The capture systems run in no particular order, sort what they captured so that captures of
+    // the same frame compare equal.
+    frame.views.sort_by_key(|view| view.entity);
+    for view in &mut frame.views {
+        view.phases.sort_by(|a, b| a.name.cmp(&b.name));
+    }
+    frame
+        .mesh_instances
+        .sort_by_key(|mesh_instance| mesh_instance.entity);
+    IoTaskPool::get()
+        .spawn(async move {
+            match frame.write(&path) {
+                Ok(()) => info!("Captured the frame to {}", path.display()),
+                Err(err) => error!("Failed to capture the frame to {}: {err}", path.display()),
+            }
+        })
+        .detach();
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs::entity::Entity;
+
+    use super::{CapturedFrame, CapturedMeshInstance, CapturedPhase, CapturedPhaseItem};
+
+    #[test]
+    fn captured_frame_round_trips_through_ron() {
+        let mut frame = CapturedFrame::default();
+        let view = frame.view_mut(Entity::from_raw(7));
+        view.viewport = [0, 0, 1280, 720];
+        view.phases.push(CapturedPhase {
+            name: "Opaque3d".to_owned(),
+            items: vec![CapturedPhaseItem {
+                entity: Entity::from_raw(3).to_bits(),
+                pipeline: 2,
+                draw_function: 1,
+                batch_range: 4..9,
+                extra_index: u32::MAX,
+            }],
+        });
+        frame.mesh_instances.push(CapturedMeshInstance {
+            entity: Entity::from_raw(3).to_bits(),
+            mesh: "mesh".to_owned(),
+            translation: [1.0, 2.0, 3.0],
+            transform: None,
+            input_uniform_index: Some(0),
+            flags: 1,
+        });
+        // Views are only added once.
+        frame.view_mut(Entity::from_raw(7)).hdr = true;
+        assert_eq!(frame.views.len(), 1);
+
+        let ron = ron::ser::to_string_pretty(&frame, ron::ser::PrettyConfig::default()).unwrap();
+        assert_eq!(ron::from_str::<CapturedFrame>(&ron).unwrap(), frame);
+    }
+}
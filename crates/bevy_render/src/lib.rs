@@ -20,6 +20,8 @@ pub mod extract_component;
 pub mod extract_instances;
 mod extract_param;
 pub mod extract_resource;
+#[cfg(feature = "frame_capture")]
+pub mod frame_capture;
 pub mod globals;
 pub mod gpu_component_array_buffer;
 pub mod mesh;
@@ -28,6 +30,7 @@ pub mod pipelined_rendering;
 pub mod primitives;
 pub mod render_asset;
 pub mod render_graph;
+pub mod render_on_demand;
 pub mod render_phase;
 pub mod render_resource;
 pub mod renderer;
@@ -61,7 +64,8 @@ use bevy_hierarchy::ValidParentCheckPlugin;
 use bevy_window::{PrimaryWindow, RawHandleWrapper};
 use extract_resource::ExtractResourcePlugin;
 use globals::GlobalsPlugin;
-use render_asset::RenderAssetBytesPerFrame;
+use render_asset::{RenderAssetBacklog, RenderAssetBytesPerFrame, RenderAssetPreparesPerFrame};
+use render_on_demand::RenderOnDemandPlugin;
 use renderer::{RenderAdapter, RenderAdapterInfo, RenderDevice, RenderQueue};
 
 use crate::mesh::GpuMesh;
@@ -70,16 +74,21 @@ use crate::{
     camera::CameraPlugin,
     mesh::{morph::MorphPlugin, MeshPlugin},
     render_asset::prepare_assets,
-    render_resource::{PipelineCache, Shader, ShaderLoader},
+    render_resource::{
+        receive_pipeline_compilation_events, send_pipeline_compilation_events, PipelineCache,
+        PipelineCompilationEvent, PipelineCompilationEventReceiver, PipelineCompilationEventSender,
+        Shader, ShaderLoader,
+    },
     renderer::{render_system, RenderInstance},
     settings::RenderCreation,
     view::{ViewPlugin, WindowRenderPlugin},
 };
-use bevy_app::{App, AppLabel, Plugin, SubApp};
+use bevy_app::{App, AppLabel, Plugin, PreUpdate, SubApp};
 use bevy_asset::{load_internal_asset, AssetApp, AssetServer, Handle};
 use bevy_ecs::{prelude::*, schedule::ScheduleLabel, system::SystemState};
 use bevy_utils::tracing::debug;
 use std::{
+    num::NonZeroUsize,
     ops::{Deref, DerefMut},
     sync::{Arc, Mutex},
 };
@@ -98,6 +107,10 @@ pub struct RenderPlugin {
     /// If `true`, disables asynchronous pipeline compilation.
     /// This has no effect on macOS, Wasm, iOS, or without the `multi-threaded` feature.
     pub synchronous_pipeline_compilation: bool,
+    /// The maximum number of pipelines compiled in the background at the same time.
+    ///
+    /// If `None`, [`PipelineCache::default_max_concurrent_compilations()`] is used.
+    pub max_concurrent_pipeline_compilations: Option<NonZeroUsize>,
 }
 
 /// The systems sets of the default [`App`] rendering schedule.
@@ -238,12 +251,14 @@ pub const INSTANCE_INDEX_SHADER_HANDLE: Handle<Shader> =
 pub const MATHS_SHADER_HANDLE: Handle<Shader> = Handle::weak_from_u128(10665356303104593376);
 pub const COLOR_OPERATIONS_SHADER_HANDLE: Handle<Shader> =
     Handle::weak_from_u128(1844674407370955161);
+pub const UTILS_SHADER_HANDLE: Handle<Shader> = Handle::weak_from_u128(6153402712986025817);
 
 impl Plugin for RenderPlugin {
     /// Initializes the renderer, sets up the [`RenderSet`] and creates the rendering sub-app.
     fn build(&self, app: &mut App) {
         app.init_asset::<Shader>()
-            .init_asset_loader::<ShaderLoader>();
+            .init_asset_loader::<ShaderLoader>()
+            .add_event::<PipelineCompilationEvent>();
 
         match &self.render_creation {
             RenderCreation::Manual(device, queue, adapter_info, adapter, instance) => {
@@ -338,10 +353,20 @@ impl Plugin for RenderPlugin {
             GlobalsPlugin,
             MorphPlugin,
             BatchingPlugin,
+            RenderOnDemandPlugin,
         ));
 
+        let render_asset_backlog = RenderAssetBacklog::default();
         app.init_resource::<RenderAssetBytesPerFrame>()
-            .add_plugins(ExtractResourcePlugin::<RenderAssetBytesPerFrame>::default());
+            .init_resource::<RenderAssetPreparesPerFrame>()
+            .insert_resource(render_asset_backlog.clone())
+            .add_plugins((
+                ExtractResourcePlugin::<RenderAssetBytesPerFrame>::default(),
+                ExtractResourcePlugin::<RenderAssetPreparesPerFrame>::default(),
+            ));
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app.insert_resource(render_asset_backlog);
+        }
 
         app.register_type::<alpha::AlphaMode>()
             // These types cannot be registered in bevy_color, as it does not depend on the rest of Bevy
@@ -367,36 +392,53 @@ impl Plugin for RenderPlugin {
             "color_operations.wgsl",
             Shader::from_wgsl
         );
+        load_internal_asset!(app, UTILS_SHADER_HANDLE, "utils.wgsl", Shader::from_wgsl);
         if let Some(future_renderer_resources) =
             app.world_mut().remove_resource::<FutureRendererResources>()
         {
             let (device, queue, adapter_info, render_adapter, instance) =
                 future_renderer_resources.0.lock().unwrap().take().unwrap();
 
+            let (compilation_event_sender, compilation_event_receiver) = async_channel::unbounded();
+
             app.insert_resource(device.clone())
                 .insert_resource(queue.clone())
                 .insert_resource(adapter_info.clone())
-                .insert_resource(render_adapter.clone());
+                .insert_resource(render_adapter.clone())
+                .insert_resource(PipelineCompilationEventReceiver(compilation_event_receiver))
+                .add_systems(PreUpdate, receive_pipeline_compilation_events);
 
             let render_app = app.sub_app_mut(RenderApp);
 
             render_app
                 .insert_resource(instance)
-                .insert_resource(PipelineCache::new(
-                    device.clone(),
-                    render_adapter.clone(),
-                    self.synchronous_pipeline_compilation,
-                ))
+                .insert_resource({
+                    let mut pipeline_cache = PipelineCache::new(
+                        device.clone(),
+                        render_adapter.clone(),
+                        self.synchronous_pipeline_compilation,
+                    );
+                    if let Some(max) = self.max_concurrent_pipeline_compilations {
+                        pipeline_cache.set_max_concurrent_compilations(max);
+                    }
+                    pipeline_cache
+                })
                 .insert_resource(device)
                 .insert_resource(queue)
                 .insert_resource(render_adapter)
                 .insert_resource(adapter_info)
+                .insert_resource(PipelineCompilationEventSender(compilation_event_sender))
                 .add_systems(
                     Render,
-                    (|mut bpf: ResMut<RenderAssetBytesPerFrame>| {
-                        bpf.reset();
-                    })
-                    .in_set(RenderSet::Cleanup),
+                    (
+                        (|mut bpf: ResMut<RenderAssetBytesPerFrame>,
+                          mut apf: ResMut<RenderAssetPreparesPerFrame>| {
+                            bpf.reset();
+                            apf.reset();
+                        })
+                        .in_set(RenderSet::Cleanup),
+                        send_pipeline_compilation_events.in_set(RenderSet::Cleanup),
+                    ),
                 );
         }
     }
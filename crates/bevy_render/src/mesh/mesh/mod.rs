@@ -1506,9 +1506,17 @@ impl RenderAsset for GpuMesh {
             None => None,
         };
 
+        // Where storage buffers are supported, compute shaders can also read
+        // mesh data, like the visibility buffer resolve pass does.
+        let storage_usage = if render_device.limits().max_storage_buffers_per_shader_stage > 0 {
+            BufferUsages::STORAGE
+        } else {
+            BufferUsages::empty()
+        };
+
         let vertex_buffer_data = mesh.get_vertex_buffer_data();
         let vertex_buffer = render_device.create_buffer_with_data(&BufferInitDescriptor {
-            usage: BufferUsages::VERTEX,
+            usage: BufferUsages::VERTEX | storage_usage,
             label: Some("Mesh Vertex Buffer"),
             contents: &vertex_buffer_data,
         });
@@ -1516,7 +1524,7 @@ impl RenderAsset for GpuMesh {
         let buffer_info = if let Some(data) = mesh.get_index_buffer_bytes() {
             GpuBufferInfo::Indexed {
                 buffer: render_device.create_buffer_with_data(&BufferInitDescriptor {
-                    usage: BufferUsages::INDEX,
+                    usage: BufferUsages::INDEX | storage_usage,
                     contents: data,
                     label: Some("Mesh Index Buffer"),
                 }),
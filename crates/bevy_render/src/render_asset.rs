@@ -1,8 +1,9 @@
 use crate::{ExtractSchedule, MainWorld, Render, RenderApp, RenderSet};
-use bevy_app::{App, Plugin, SubApp};
+use bevy_app::{App, Plugin, SubApp, Update};
 use bevy_asset::{Asset, AssetEvent, AssetId, Assets};
+use bevy_diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic};
 use bevy_ecs::{
-    prelude::{Commands, EventReader, IntoSystemConfigs, ResMut, Resource},
+    prelude::{Commands, EventReader, IntoSystemConfigs, Res, ResMut, Resource},
     schedule::SystemConfigs,
     system::{StaticSystemParam, SystemParam, SystemParamItem, SystemState},
     world::{FromWorld, Mut},
@@ -11,7 +12,11 @@ use bevy_reflect::{Reflect, ReflectDeserialize, ReflectSerialize};
 use bevy_render_macros::ExtractResource;
 use bevy_utils::{tracing::debug, HashMap, HashSet};
 use serde::{Deserialize, Serialize};
-use std::marker::PhantomData;
+use std::{
+    any::type_name,
+    marker::PhantomData,
+    sync::{Arc, Mutex},
+};
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -139,6 +144,7 @@ impl<A: RenderAsset, AFTER: RenderAssetDependency + 'static> Plugin
                 .init_resource::<ExtractedAssets<A>>()
                 .init_resource::<RenderAssets<A>>()
                 .init_resource::<PrepareNextFrameAssets<A>>()
+                .init_resource::<VisibleRenderAssets<A>>()
                 .add_systems(ExtractSchedule, extract_render_asset::<A>);
             AFTER::register_system(
                 render_app,
@@ -307,50 +313,66 @@ impl<A: RenderAsset> Default for PrepareNextFrameAssets<A> {
     }
 }
 
+/// The assets used by the entities visible this frame.
+///
+/// When the [`RenderAssetBytesPerFrame`] or [`RenderAssetPreparesPerFrame`] budget doesn't allow
+/// preparing all the pending assets of a frame, these are prepared before the others. This is
+/// filled during the [`ExtractSchedule`] by the systems extracting the entities using the assets,
+/// and cleared once the assets are prepared.
+#[derive(Resource)]
+pub struct VisibleRenderAssets<A: RenderAsset>(HashSet<AssetId<A::SourceAsset>>);
+
+impl<A: RenderAsset> Default for VisibleRenderAssets<A> {
+    fn default() -> Self {
+        Self(Default::default())
+    }
+}
+
+impl<A: RenderAsset> VisibleRenderAssets<A> {
+    /// Marks the asset as used by a visible entity.
+    pub fn insert(&mut self, id: impl Into<AssetId<A::SourceAsset>>) {
+        self.0.insert(id.into());
+    }
+
+    /// Returns whether the asset is used by a visible entity.
+    pub fn contains(&self, id: impl Into<AssetId<A::SourceAsset>>) -> bool {
+        self.0.contains(&id.into())
+    }
+}
+
+impl<A: RenderAsset> Extend<AssetId<A::SourceAsset>> for VisibleRenderAssets<A> {
+    fn extend<T: IntoIterator<Item = AssetId<A::SourceAsset>>>(&mut self, iter: T) {
+        self.0.extend(iter);
+    }
+}
+
 /// This system prepares all assets of the corresponding [`RenderAsset::SourceAsset`] type
 /// which where extracted this frame for the GPU.
+///
+/// The assets extracted this frame are batched with those left over from the previous frames,
+/// keeping only the latest version of each asset, and the [`VisibleRenderAssets`] are prepared
+/// first. The assets that don't fit in the [`RenderAssetBytesPerFrame`] and
+/// [`RenderAssetPreparesPerFrame`] budgets are left for the next frame, and counted in the
+/// [`RenderAssetBacklog`].
+#[allow(clippy::too_many_arguments)]
 pub fn prepare_assets<A: RenderAsset>(
     mut extracted_assets: ResMut<ExtractedAssets<A>>,
     mut render_assets: ResMut<RenderAssets<A>>,
     mut prepare_next_frame: ResMut<PrepareNextFrameAssets<A>>,
+    mut visible_assets: ResMut<VisibleRenderAssets<A>>,
     param: StaticSystemParam<<A as RenderAsset>::Param>,
     mut bpf: ResMut<RenderAssetBytesPerFrame>,
+    mut apf: ResMut<RenderAssetPreparesPerFrame>,
+    backlog: Res<RenderAssetBacklog>,
 ) {
     let mut wrote_asset_count = 0;
 
     let mut param = param.into_inner();
-    let queued_assets = std::mem::take(&mut prepare_next_frame.assets);
-    for (id, extracted_asset) in queued_assets {
-        if extracted_assets.removed.contains(&id) || extracted_assets.added.contains(&id) {
-            // skip previous frame's assets that have been removed or updated
-            continue;
-        }
-
-        let write_bytes = if let Some(size) = A::byte_len(&extracted_asset) {
-            // we could check if available bytes > byte_len here, but we want to make some
-            // forward progress even if the asset is larger than the max bytes per frame.
-            // this way we always write at least one (sized) asset per frame.
-            // in future we could also consider partial asset uploads.
-            if bpf.exhausted() {
-                prepare_next_frame.assets.push((id, extracted_asset));
-                continue;
-            }
-            size
-        } else {
-            0
-        };
-
-        match A::prepare_asset(extracted_asset, &mut param) {
-            Ok(prepared_asset) => {
-                render_assets.insert(id, prepared_asset);
-                bpf.write_bytes(write_bytes);
-                wrote_asset_count += 1;
-            }
-            Err(PrepareAssetError::RetryNextUpdate(extracted_asset)) => {
-                prepare_next_frame.assets.push((id, extracted_asset));
-            }
-        }
-    }
+    let mut pending_assets = std::mem::take(&mut prepare_next_frame.assets);
+    // skip previous frame's assets that have been removed or updated
+    pending_assets.retain(|(id, _)| {
+        !extracted_assets.removed.contains(id) && !extracted_assets.added.contains(id)
+    });
 
     for removed in extracted_assets.removed.drain() {
         render_assets.remove(removed);
@@ -361,21 +383,29 @@ pub fn prepare_assets<A: RenderAsset>(
         // any users will not see the old asset after a new asset is extracted,
         // even if the new asset is not yet ready or we are out of bytes to write.
         render_assets.remove(id);
+        pending_assets.push((id, extracted_asset));
+    }
 
-        let write_bytes = if let Some(size) = A::byte_len(&extracted_asset) {
-            if bpf.exhausted() {
-                prepare_next_frame.assets.push((id, extracted_asset));
-                continue;
-            }
-            size
-        } else {
-            0
-        };
+    // The sort is stable, so the assets otherwise keep the order they were extracted in.
+    pending_assets.sort_by_key(|(id, _)| !visible_assets.0.contains(id));
+    visible_assets.0.clear();
+
+    for (id, extracted_asset) in pending_assets {
+        // we could check if available bytes > byte_len here, but we want to make some
+        // forward progress even if the asset is larger than the max bytes per frame.
+        // this way we always write at least one (sized) asset per frame.
+        // in future we could also consider partial asset uploads.
+        let write_bytes = A::byte_len(&extracted_asset);
+        if apf.exhausted() || (write_bytes.is_some() && bpf.exhausted()) {
+            prepare_next_frame.assets.push((id, extracted_asset));
+            continue;
+        }
 
         match A::prepare_asset(extracted_asset, &mut param) {
             Ok(prepared_asset) => {
                 render_assets.insert(id, prepared_asset);
-                bpf.write_bytes(write_bytes);
+                bpf.write_bytes(write_bytes.unwrap_or(0));
+                apf.prepare_asset();
                 wrote_asset_count += 1;
             }
             Err(PrepareAssetError::RetryNextUpdate(extracted_asset)) => {
@@ -384,10 +414,12 @@ pub fn prepare_assets<A: RenderAsset>(
         }
     }
 
-    if bpf.exhausted() && !prepare_next_frame.assets.is_empty() {
+    backlog.set::<A>(prepare_next_frame.assets.len());
+
+    if (bpf.exhausted() || apf.exhausted()) && !prepare_next_frame.assets.is_empty() {
         debug!(
             "{} write budget exhausted with {} assets remaining (wrote {})",
-            std::any::type_name::<A>(),
+            type_name::<A>(),
             prepare_next_frame.assets.len(),
             wrote_asset_count
         );
@@ -445,3 +477,186 @@ impl RenderAssetBytesPerFrame {
         self.max_bytes.is_some() && self.available == 0
     }
 }
+
+/// A resource that limits the number of assets prepared each frame, across all the
+/// [`RenderAsset`] types, to spread the cost of preparing them (and of creating their bind groups)
+/// over several frames when many assets are added or modified at once.
+///
+/// The assets that aren't prepared are counted in the [`RenderAssetBacklog`].
+#[derive(Resource, Default, Debug, Clone, Copy, ExtractResource)]
+pub struct RenderAssetPreparesPerFrame {
+    pub max_assets: Option<usize>,
+    pub available: usize,
+}
+
+impl RenderAssetPreparesPerFrame {
+    /// `max_assets`: the number of assets to prepare per frame.
+    pub fn new(max_assets: usize) -> Self {
+        Self {
+            max_assets: Some(max_assets),
+            available: 0,
+        }
+    }
+
+    /// Reset the available assets. Called once per frame by the [`crate::RenderPlugin`].
+    pub fn reset(&mut self) {
+        self.available = self.max_assets.unwrap_or(usize::MAX);
+    }
+
+    /// decrease the available assets for the current frame
+    fn prepare_asset(&mut self) {
+        if self.max_assets.is_none() {
+            return;
+        }
+
+        self.available = self.available.saturating_sub(1);
+    }
+
+    // check if any assets remain available for preparing this frame
+    fn exhausted(&self) -> bool {
+        self.max_assets.is_some() && self.available == 0
+    }
+}
+
+/// The number of assets of each [`RenderAsset`] type waiting to be prepared in a later frame,
+/// because they didn't fit in the frame's budget or their dependencies weren't ready yet.
+///
+/// This resource is shared by the main world and the render world, and is updated by
+/// [`prepare_assets`]. Add the [`RenderAssetDiagnosticsPlugin`] to record the total as a
+/// diagnostic.
+#[derive(Resource, Clone, Default, Debug)]
+pub struct RenderAssetBacklog(Arc<Mutex<HashMap<&'static str, usize>>>);
+
+impl RenderAssetBacklog {
+    /// Returns the number of assets of type `A` waiting to be prepared.
+    pub fn get<A: RenderAsset>(&self) -> usize {
+        self.0
+            .lock()
+            .ok()
+            .and_then(|backlog| backlog.get(type_name::<A>()).copied())
+            .unwrap_or(0)
+    }
+
+    /// Returns the number of assets of all types waiting to be prepared.
+    pub fn total(&self) -> usize {
+        self.0
+            .lock()
+            .map(|backlog| backlog.values().sum())
+            .unwrap_or(0)
+    }
+
+    /// Returns the number of assets waiting to be prepared, by [`RenderAsset`] type name.
+    pub fn by_type(&self) -> HashMap<&'static str, usize> {
+        self.0
+            .lock()
+            .map(|backlog| backlog.clone())
+            .unwrap_or_default()
+    }
+
+    fn set<A: RenderAsset>(&self, len: usize) {
+        if let Ok(mut backlog) = self.0.lock() {
+            backlog.insert(type_name::<A>(), len);
+        }
+    }
+}
+
+/// Records the total size of the [`RenderAssetBacklog`] as the
+/// [`RenderAssetDiagnosticsPlugin::BACKLOG`] diagnostic, to tune the [`RenderAssetBytesPerFrame`]
+/// and [`RenderAssetPreparesPerFrame`] budgets.
+#[derive(Default)]
+pub struct RenderAssetDiagnosticsPlugin;
+
+impl RenderAssetDiagnosticsPlugin {
+    /// The number of assets waiting to be prepared.
+    pub const BACKLOG: DiagnosticPath = DiagnosticPath::const_new("render_asset/backlog");
+
+    fn diagnostic_system(mut diagnostics: Diagnostics, backlog: Res<RenderAssetBacklog>) {
+        diagnostics.add_measurement(&Self::BACKLOG, || backlog.total() as f64);
+    }
+}
+
+impl Plugin for RenderAssetDiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_diagnostic(Diagnostic::new(Self::BACKLOG))
+            .add_systems(Update, Self::diagnostic_system);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_asset::{Asset, AssetId, Handle};
+    use bevy_ecs::{prelude::*, system::SystemParamItem};
+    use bevy_reflect::TypePath;
+
+    use super::{
+        prepare_assets, ExtractedAssets, PrepareAssetError, PrepareNextFrameAssets, RenderAsset,
+        RenderAssetBacklog, RenderAssetBytesPerFrame, RenderAssetPreparesPerFrame, RenderAssets,
+        VisibleRenderAssets,
+    };
+
+    #[derive(Asset, TypePath, Clone)]
+    struct TestAsset;
+
+    struct GpuTestAsset;
+
+    impl RenderAsset for GpuTestAsset {
+        type SourceAsset = TestAsset;
+        type Param = ();
+
+        fn prepare_asset(
+            _source_asset: Self::SourceAsset,
+            _param: &mut SystemParamItem<Self::Param>,
+        ) -> Result<Self, PrepareAssetError<Self::SourceAsset>> {
+            Ok(GpuTestAsset)
+        }
+    }
+
+    #[test]
+    fn visible_assets_are_prepared_first_within_budget() {
+        let mut world = World::new();
+        world.init_resource::<RenderAssets<GpuTestAsset>>();
+        world.init_resource::<PrepareNextFrameAssets<GpuTestAsset>>();
+        world.init_resource::<VisibleRenderAssets<GpuTestAsset>>();
+        world.init_resource::<RenderAssetBytesPerFrame>();
+        world.init_resource::<RenderAssetBacklog>();
+        world.insert_resource(RenderAssetPreparesPerFrame::new(1));
+        world.resource_mut::<RenderAssetPreparesPerFrame>().reset();
+
+        let ids: Vec<AssetId<TestAsset>> = (0..3)
+            .map(|index| Handle::weak_from_u128(index).id())
+            .collect();
+        world.insert_resource(ExtractedAssets::<GpuTestAsset> {
+            extracted: ids.iter().map(|id| (*id, TestAsset)).collect(),
+            removed: Default::default(),
+            added: ids.iter().copied().collect(),
+        });
+        world
+            .resource_mut::<VisibleRenderAssets<GpuTestAsset>>()
+            .insert(ids[2]);
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(prepare_assets::<GpuTestAsset>);
+        schedule.run(&mut world);
+
+        let render_assets = world.resource::<RenderAssets<GpuTestAsset>>();
+        assert!(render_assets.get(ids[2]).is_some());
+        assert!(render_assets.get(ids[0]).is_none());
+        assert_eq!(
+            world.resource::<RenderAssetBacklog>().get::<GpuTestAsset>(),
+            2
+        );
+
+        world.resource_mut::<RenderAssetPreparesPerFrame>().reset();
+        world.insert_resource(ExtractedAssets::<GpuTestAsset>::default());
+        schedule.run(&mut world);
+
+        assert!(world
+            .resource::<RenderAssets<GpuTestAsset>>()
+            .get(ids[0])
+            .is_some());
+        assert_eq!(
+            world.resource::<RenderAssetBacklog>().get::<GpuTestAsset>(),
+            1
+        );
+    }
+}
@@ -0,0 +1,161 @@
+//! Renders frames only when something asked for it.
+//!
+//! By default, the render schedule runs every time the app updates. With
+//! [`RenderOn::Demand`], the app keeps updating but most of the render schedule
+//! is skipped unless a redraw was requested during the frame, either by a
+//! [`RequestRedraw`] event, by a change to a [`Window`], or by a change to a
+//! component watched with [`RedrawOnChangePlugin`].
+//!
+//! Extraction, [`RenderSet::ExtractCommands`], [`RenderSet::PrepareAssets`] and
+//! [`RenderSet::Cleanup`] still run on skipped frames, so that the render world
+//! stays consistent with the main world: extracted assets are prepared, removed
+//! entities are cleaned up and events aren't missed.
+
+use std::marker::PhantomData;
+
+use bevy_app::{App, First, Plugin, PostUpdate};
+use bevy_ecs::prelude::*;
+use bevy_reflect::{std_traits::ReflectDefault, Reflect};
+use bevy_window::{RequestRedraw, Window};
+
+use crate::{renderer::send_time, Extract, ExtractSchedule, Render, RenderApp, RenderSet};
+
+/// Controls when the render schedule runs.
+#[derive(Resource, Reflect, Clone, Copy, Default, Debug, PartialEq, Eq)]
+#[reflect(Resource, Default)]
+pub enum RenderOn {
+    /// A frame is rendered every time the app updates.
+    #[default]
+    EveryFrame,
+    /// A frame is only rendered when a redraw was requested since the last
+    /// frame. See the [module documentation](self) for what requests a redraw.
+    Demand,
+}
+
+/// Whether a redraw has been requested during the current frame.
+///
+/// This is reset at the start of every frame. Systems that know the screen must
+/// be updated can set it directly, but sending a [`RequestRedraw`] event is
+/// usually more convenient.
+#[derive(Resource, Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct RedrawRequested(pub bool);
+
+/// Render world resource telling whether the current frame is rendered.
+///
+/// Use [`should_render_frame`] as a run condition for render systems that
+/// aren't part of the gated [`RenderSet`]s.
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ShouldRenderFrame(pub bool);
+
+impl Default for ShouldRenderFrame {
+    fn default() -> Self {
+        Self(true)
+    }
+}
+
+/// A run condition that is true when the current frame is rendered.
+pub fn should_render_frame(should_render_frame: Res<ShouldRenderFrame>) -> bool {
+    should_render_frame.0
+}
+
+/// The systems that collect redraw requests in the main world.
+#[derive(SystemSet, Clone, PartialEq, Eq, Debug, Hash)]
+pub struct RedrawRequestSystems;
+
+/// Sets up [`RenderOn`]. This is added by [`RenderPlugin`](crate::RenderPlugin).
+pub struct RenderOnDemandPlugin;
+
+impl Plugin for RenderOnDemandPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<RenderOn>()
+            .init_resource::<RenderOn>()
+            .init_resource::<RedrawRequested>()
+            .add_systems(First, reset_redraw_requested)
+            .add_systems(
+                PostUpdate,
+                (request_redraw_on_event, request_redraw_on_change::<Window>)
+                    .in_set(RedrawRequestSystems),
+            );
+
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+
+        render_app
+            .init_resource::<ShouldRenderFrame>()
+            .add_systems(ExtractSchedule, extract_should_render_frame)
+            .add_systems(
+                Render,
+                send_time
+                    .in_set(RenderSet::Cleanup)
+                    .run_if(not(should_render_frame)),
+            )
+            .configure_sets(
+                Render,
+                (
+                    RenderSet::ManageViews,
+                    RenderSet::Queue,
+                    RenderSet::PhaseSort,
+                    RenderSet::Prepare,
+                    RenderSet::Render,
+                )
+                    .run_if(should_render_frame),
+            );
+    }
+}
+
+/// Requests a redraw whenever a component of type `C` is added or changed, when
+/// [`RenderOn::Demand`] is used.
+pub struct RedrawOnChangePlugin<C: Component>(PhantomData<C>);
+
+impl<C: Component> Default for RedrawOnChangePlugin<C> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<C: Component> Plugin for RedrawOnChangePlugin<C> {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            PostUpdate,
+            request_redraw_on_change::<C>.in_set(RedrawRequestSystems),
+        );
+    }
+}
+
+fn reset_redraw_requested(mut redraw_requested: ResMut<RedrawRequested>) {
+    redraw_requested.0 = false;
+}
+
+fn request_redraw_on_event(
+    mut events: EventReader<RequestRedraw>,
+    mut redraw_requested: ResMut<RedrawRequested>,
+) {
+    if events.read().count() > 0 {
+        redraw_requested.0 = true;
+    }
+}
+
+/// Requests a redraw if any component of type `C` was added or changed this
+/// frame.
+pub fn request_redraw_on_change<C: Component>(
+    changed: Query<(), Changed<C>>,
+    mut redraw_requested: ResMut<RedrawRequested>,
+) {
+    if !changed.is_empty() {
+        redraw_requested.0 = true;
+    }
+}
+
+fn extract_should_render_frame(
+    mut should_render_frame: ResMut<ShouldRenderFrame>,
+    render_on: Extract<Res<RenderOn>>,
+    redraw_requested: Extract<Res<RedrawRequested>>,
+    mut rendered_first_frame: Local<bool>,
+) {
+    should_render_frame.0 = match **render_on {
+        RenderOn::EveryFrame => true,
+        RenderOn::Demand => redraw_requested.0 || !*rendered_first_frame,
+    };
+    *rendered_first_frame = true;
+}
@@ -42,6 +42,14 @@ pub trait Draw<P: PhaseItem>: Send + Sync + 'static {
 #[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord, Hash)]
 pub struct DrawFunctionId(u32);
 
+impl DrawFunctionId {
+    /// Returns the index of the draw function in the [`DrawFunctions`].
+    #[inline]
+    pub fn id(&self) -> u32 {
+        self.0
+    }
+}
+
 /// Stores all [`Draw`] functions for the [`PhaseItem`] type.
 ///
 /// For retrieval, the [`Draw`] functions are mapped to their respective [`TypeId`]s.
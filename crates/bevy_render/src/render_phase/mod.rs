@@ -44,6 +44,7 @@ use crate::{
         GetFullBatchData,
     },
     render_resource::{CachedRenderPipelineId, GpuArrayBufferIndex, PipelineCache},
+    view::GpuSorting,
     Render, RenderApp, RenderSet,
 };
 use bevy_ecs::{
@@ -242,74 +243,87 @@ where
         let mut draw_functions = draw_functions.write();
         draw_functions.prepare(world);
 
-        // Encode draws for batchables.
         debug_assert_eq!(self.batchable_keys.len(), self.batch_sets.len());
-        for (key, batch_set) in self.batchable_keys.iter().zip(self.batch_sets.iter()) {
-            for batch in batch_set {
-                let binned_phase_item = BPI::new(
-                    key.clone(),
-                    batch.representative_entity,
-                    batch.instance_range.clone(),
-                    batch.extra_index,
-                );
-
-                // Fetch the draw function.
-                let Some(draw_function) = draw_functions.get_mut(binned_phase_item.draw_function())
-                else {
-                    continue;
-                };
-
-                draw_function.draw(world, render_pass, view, &binned_phase_item);
-            }
+        for binned_phase_item in self.items() {
+            // Fetch the draw function.
+            let Some(draw_function) = draw_functions.get_mut(binned_phase_item.draw_function())
+            else {
+                continue;
+            };
+
+            draw_function.draw(world, render_pass, view, &binned_phase_item);
         }
+    }
 
-        // Encode draws for unbatchables.
+    /// Returns the phase items drawn by this phase, in draw order: one item per
+    /// batch of batchable entities, then one item per unbatchable entity.
+    ///
+    /// Items are only available after `batch_and_prepare_binned_render_phase`
+    /// has run in [`RenderSet::PrepareResources`](crate::RenderSet::PrepareResources).
+    pub fn items(&self) -> impl Iterator<Item = BPI> + '_ {
+        let batchable_items = self.batches().map(|(key, batch)| {
+            BPI::new(
+                key.clone(),
+                batch.representative_entity,
+                batch.instance_range.clone(),
+                batch.extra_index,
+            )
+        });
 
-        for key in &self.unbatchable_keys {
+        let unbatchable_items = self.unbatchable_keys.iter().flat_map(move |key| {
             let unbatchable_entities = &self.unbatchable_values[key];
-            for (entity_index, &entity) in unbatchable_entities.entities.iter().enumerate() {
-                let unbatchable_dynamic_offset = match &unbatchable_entities.buffer_indices {
-                    UnbatchableBinnedEntityIndexSet::NoEntities => {
-                        // Shouldn't happen…
-                        continue;
-                    }
-                    UnbatchableBinnedEntityIndexSet::Sparse {
-                        instance_range,
-                        first_indirect_parameters_index,
-                    } => UnbatchableBinnedEntityIndices {
-                        instance_index: instance_range.start + entity_index as u32,
-                        extra_index: match first_indirect_parameters_index {
-                            None => PhaseItemExtraIndex::NONE,
-                            Some(first_indirect_parameters_index) => {
-                                PhaseItemExtraIndex::indirect_parameters_index(
-                                    u32::from(*first_indirect_parameters_index)
-                                        + entity_index as u32,
-                                )
-                            }
+            unbatchable_entities.entities.iter().enumerate().filter_map(
+                move |(entity_index, &entity)| {
+                    let unbatchable_dynamic_offset = match &unbatchable_entities.buffer_indices {
+                        UnbatchableBinnedEntityIndexSet::NoEntities => {
+                            // Shouldn't happen…
+                            return None;
+                        }
+                        UnbatchableBinnedEntityIndexSet::Sparse {
+                            instance_range,
+                            first_indirect_parameters_index,
+                        } => UnbatchableBinnedEntityIndices {
+                            instance_index: instance_range.start + entity_index as u32,
+                            extra_index: match first_indirect_parameters_index {
+                                None => PhaseItemExtraIndex::NONE,
+                                Some(first_indirect_parameters_index) => {
+                                    PhaseItemExtraIndex::indirect_parameters_index(
+                                        u32::from(*first_indirect_parameters_index)
+                                            + entity_index as u32,
+                                    )
+                                }
+                            },
                         },
-                    },
-                    UnbatchableBinnedEntityIndexSet::Dense(ref dynamic_offsets) => {
-                        dynamic_offsets[entity_index]
-                    }
-                };
-
-                let binned_phase_item = BPI::new(
-                    key.clone(),
-                    entity,
-                    unbatchable_dynamic_offset.instance_index
-                        ..(unbatchable_dynamic_offset.instance_index + 1),
-                    unbatchable_dynamic_offset.extra_index,
-                );
-
-                // Fetch the draw function.
-                let Some(draw_function) = draw_functions.get_mut(binned_phase_item.draw_function())
-                else {
-                    continue;
-                };
-
-                draw_function.draw(world, render_pass, view, &binned_phase_item);
-            }
-        }
+                        UnbatchableBinnedEntityIndexSet::Dense(ref dynamic_offsets) => {
+                            dynamic_offsets[entity_index]
+                        }
+                    };
+
+                    Some(BPI::new(
+                        key.clone(),
+                        entity,
+                        unbatchable_dynamic_offset.instance_index
+                            ..(unbatchable_dynamic_offset.instance_index + 1),
+                        unbatchable_dynamic_offset.extra_index,
+                    ))
+                },
+            )
+        });
+
+        batchable_items.chain(unbatchable_items)
+    }
+
+    /// Returns the batches of batchable entities prepared for this phase, with
+    /// the key of the bin each batch belongs to.
+    ///
+    /// Batches are only available after `batch_and_prepare_binned_render_phase`
+    /// has run in [`RenderSet::PrepareResources`](crate::RenderSet::PrepareResources).
+    /// Unbatchable entities aren't included.
+    pub fn batches(&self) -> impl Iterator<Item = (&BPI::BinKey, &BinnedRenderPhaseBatch)> {
+        self.batchable_keys
+            .iter()
+            .zip(self.batch_sets.iter())
+            .flat_map(|(key, batch_set)| batch_set.iter().map(move |batch| (key, batch)))
     }
 
     pub fn is_empty(&self) -> bool {
@@ -545,6 +559,9 @@ where
 {
     /// The items within this [`SortedRenderPhase`].
     pub items: Vec<I>,
+    /// Whether the items were only grouped into batches, whose instances are sorted on the
+    /// GPU, in a view with [`GpuSorting`].
+    pub sorted_on_gpu: bool,
 }
 
 impl<I> Default for SortedRenderPhase<I>
@@ -552,7 +569,10 @@ where
     I: SortedPhaseItem,
 {
     fn default() -> Self {
-        Self { items: Vec::new() }
+        Self {
+            items: Vec::new(),
+            sorted_on_gpu: false,
+        }
     }
 }
 
@@ -569,6 +589,16 @@ where
     /// Sorts all of its [`PhaseItem`]s.
     pub fn sort(&mut self) {
         I::sort(&mut self.items);
+        self.sorted_on_gpu = false;
+    }
+
+    /// Groups its [`PhaseItem`]s into batches to be sorted on the GPU if they support it, see
+    /// [`SortedPhaseItem::sort_for_gpu_sorting`], or sorts them otherwise.
+    pub fn sort_for_gpu_sorting(&mut self) {
+        self.sorted_on_gpu = I::sort_for_gpu_sorting(&mut self.items);
+        if !self.sorted_on_gpu {
+            I::sort(&mut self.items);
+        }
     }
 
     /// An [`Iterator`] through the associated [`Entity`] for each [`PhaseItem`] in order.
@@ -861,6 +891,18 @@ pub trait SortedPhaseItem: PhaseItem {
     fn sort(items: &mut [Self]) {
         items.sort_unstable_by_key(|item| item.sort_key());
     }
+
+    /// Groups a slice of phase items into batches in a view with [`GpuSorting`], whose instances
+    /// are then sorted on the GPU, and returns `true`.
+    ///
+    /// This should only keep the items that can be batched together next to each other, in a
+    /// stable way, which is much cheaper than sorting them. Returns `false` by default, for the
+    /// items that must be sorted on the CPU with [`SortedPhaseItem::sort`].
+    #[inline]
+    #[allow(unused_variables)]
+    fn sort_for_gpu_sorting(items: &mut [Self]) -> bool {
+        false
+    }
 }
 
 /// A [`PhaseItem`] item, that automatically sets the appropriate render pipeline,
@@ -901,12 +943,16 @@ impl<P: CachedRenderPipelinePhaseItem> RenderCommand<P> for SetItemPipeline {
 }
 
 /// This system sorts the [`PhaseItem`]s of all [`SortedRenderPhase`]s of this
-/// type.
-pub fn sort_phase_system<I>(mut render_phases: Query<&mut SortedRenderPhase<I>>)
+/// type, or groups them into batches in the views with [`GpuSorting`].
+pub fn sort_phase_system<I>(mut render_phases: Query<(&mut SortedRenderPhase<I>, Has<GpuSorting>)>)
 where
     I: SortedPhaseItem,
 {
-    for mut phase in &mut render_phases {
-        phase.sort();
+    for (mut phase, gpu_sorting) in &mut render_phases {
+        if gpu_sorting {
+            phase.sort_for_gpu_sorting();
+        } else {
+            phase.sort();
+        }
     }
 }
@@ -1,5 +1,9 @@
 use crate::{define_atomic_id, render_resource::resource_macros::*};
-use std::ops::Deref;
+use bevy_utils::FixedState;
+use std::{
+    hash::{BuildHasher, Hash, Hasher},
+    ops::Deref,
+};
 
 define_atomic_id!(BindGroupLayoutId);
 render_resource_wrapper!(ErasedBindGroupLayout, wgpu::BindGroupLayout);
@@ -8,6 +12,7 @@ render_resource_wrapper!(ErasedBindGroupLayout, wgpu::BindGroupLayout);
 pub struct BindGroupLayout {
     id: BindGroupLayoutId,
     value: ErasedBindGroupLayout,
+    content_hash: Option<u64>,
 }
 
 impl PartialEq for BindGroupLayout {
@@ -26,6 +31,30 @@ impl BindGroupLayout {
     pub fn value(&self) -> &wgpu::BindGroupLayout {
         &self.value
     }
+
+    /// A hash of the label and of the entries the layout was created with, which unlike its
+    /// [`id`](BindGroupLayout::id) is the same in every run of the app.
+    ///
+    /// This is only known for the layouts created with
+    /// [`RenderDevice::create_bind_group_layout`](crate::renderer::RenderDevice::create_bind_group_layout).
+    #[inline]
+    pub fn content_hash(&self) -> Option<u64> {
+        self.content_hash
+    }
+
+    pub(crate) fn with_content_hash(
+        value: wgpu::BindGroupLayout,
+        label: Option<&str>,
+        entries: &[wgpu::BindGroupLayoutEntry],
+    ) -> Self {
+        let mut hasher = FixedState.build_hasher();
+        label.hash(&mut hasher);
+        entries.hash(&mut hasher);
+        BindGroupLayout {
+            content_hash: Some(hasher.finish()),
+            ..value.into()
+        }
+    }
 }
 
 impl From<wgpu::BindGroupLayout> for BindGroupLayout {
@@ -33,6 +62,7 @@ impl From<wgpu::BindGroupLayout> for BindGroupLayout {
         BindGroupLayout {
             id: BindGroupLayoutId::new(),
             value: ErasedBindGroupLayout::new(value),
+            content_hash: None,
         }
     }
 }
@@ -9,6 +9,7 @@ mod gpu_array_buffer;
 mod pipeline;
 mod pipeline_cache;
 mod pipeline_specializer;
+mod readback_buffer;
 pub mod resource_macros;
 mod shader;
 mod storage_buffer;
@@ -25,6 +26,7 @@ pub use gpu_array_buffer::*;
 pub use pipeline::*;
 pub use pipeline_cache::*;
 pub use pipeline_specializer::*;
+pub use readback_buffer::*;
 pub use shader::*;
 pub use storage_buffer::*;
 pub use texture::*;
@@ -3,22 +3,27 @@ use crate::{
     renderer::{RenderAdapter, RenderDevice},
     Extract,
 };
-use bevy_asset::{AssetEvent, AssetId, Assets};
+use async_channel::{Receiver, Sender};
+use bevy_asset::{AssetEvent, AssetId, Assets, Handle};
 use bevy_ecs::system::{Res, ResMut};
-use bevy_ecs::{event::EventReader, system::Resource};
+use bevy_ecs::{
+    event::{Event, EventReader, EventWriter},
+    system::Resource,
+};
 use bevy_tasks::Task;
 use bevy_utils::hashbrown::hash_map::EntryRef;
 use bevy_utils::{
     default,
     tracing::{debug, error},
-    HashMap, HashSet,
+    AHasher, FixedState, HashMap, HashSet,
 };
 use naga::valid::Capabilities;
 use std::{
     borrow::Cow,
     future::Future,
-    hash::Hash,
+    hash::{BuildHasher, Hash, Hasher},
     mem,
+    num::NonZeroUsize,
     ops::Deref,
     sync::{Arc, Mutex, PoisonError},
 };
@@ -41,6 +46,16 @@ pub enum PipelineDescriptor {
     ComputePipelineDescriptor(Box<ComputePipelineDescriptor>),
 }
 
+impl PipelineDescriptor {
+    /// The debug label of the pipeline.
+    pub fn label(&self) -> &Option<Cow<'static, str>> {
+        match self {
+            PipelineDescriptor::RenderPipelineDescriptor(descriptor) => &descriptor.label,
+            PipelineDescriptor::ComputePipelineDescriptor(descriptor) => &descriptor.label,
+        }
+    }
+}
+
 /// A pipeline defining the data layout and shader logic for a specific GPU task.
 ///
 /// Used to store an heterogenous collection of render and compute pipelines together.
@@ -83,6 +98,95 @@ impl CachedComputePipelineId {
 pub struct CachedPipeline {
     pub descriptor: PipelineDescriptor,
     pub state: CachedPipelineState,
+    pub priority: PipelinePriority,
+    /// The hash of the descriptor and of the sources of its shaders, known once the creation of
+    /// the pipeline starts with all its shaders loaded. See [`PipelineContentHash`].
+    pub content_hash: Option<PipelineContentHash>,
+}
+
+/// A hash of a cached pipeline's descriptor and of the sources of its shaders, including the
+/// shaders they import.
+///
+/// Unlike [`CachedRenderPipelineId`] and [`CachedComputePipelineId`], which depend on the order
+/// pipelines are queued in, the same pipeline gets the same hash in every run of the same build
+/// of the app on the same machine, as long as its shaders don't change. This makes it possible to
+/// correlate pipelines across runs in GPU captures and traces: the hash is appended to the label
+/// of the GPU pipeline objects.
+///
+/// Bind group layouts are hashed by their [`BindGroupLayout::content_hash`], so pipelines using
+/// layouts that weren't created by the [`RenderDevice`] have no content hash.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PipelineContentHash(pub u64);
+
+impl std::fmt::Display for PipelineContentHash {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+/// Sent in the main world when the [`PipelineCache`] of the render world finished compiling a
+/// pipeline, successfully or not.
+///
+/// Pipeline compilation errors are otherwise only logged. This lets editors display them, and
+/// tests assert that all the pipelines they queued compiled. A pipeline that is recreated after a
+/// change of its shaders sends a new event once the recreation finishes.
+#[derive(Event, Clone, Debug)]
+pub struct PipelineCompilationEvent {
+    /// The index of the pipeline in the cache, as returned by [`CachedRenderPipelineId::id`] or
+    /// [`CachedComputePipelineId::id`].
+    pub id: CachedPipelineId,
+    /// The debug label of the pipeline descriptor.
+    pub label: Option<Cow<'static, str>>,
+    /// The error text of the shader processing or shader module creation, if the compilation
+    /// failed.
+    pub result: Result<(), String>,
+}
+
+/// Receives the [`PipelineCompilationEvent`]s of the render world, in the main world.
+#[derive(Resource)]
+pub(crate) struct PipelineCompilationEventReceiver(pub Receiver<PipelineCompilationEvent>);
+
+/// Sends the [`PipelineCompilationEvent`]s of the [`PipelineCache`] to the main world.
+#[derive(Resource)]
+pub(crate) struct PipelineCompilationEventSender(pub Sender<PipelineCompilationEvent>);
+
+/// Sends the [`PipelineCompilationEvent`]s of the pipelines that finished compiling this frame
+/// to the main world.
+pub(crate) fn send_pipeline_compilation_events(
+    mut cache: ResMut<PipelineCache>,
+    sender: Res<PipelineCompilationEventSender>,
+) {
+    for event in cache.compilation_events.drain(..) {
+        // The main world is gone if this fails, so nobody is listening anymore.
+        let _ = sender.0.try_send(event);
+    }
+}
+
+/// Forwards the [`PipelineCompilationEvent`]s received from the render world as events of the
+/// main world.
+pub(crate) fn receive_pipeline_compilation_events(
+    receiver: Res<PipelineCompilationEventReceiver>,
+    mut events: EventWriter<PipelineCompilationEvent>,
+) {
+    while let Ok(event) = receiver.0.try_recv() {
+        events.send(event);
+    }
+}
+
+/// The order in which a [`PipelineCache`] starts compiling queued pipelines when it can't start
+/// all of them at once.
+///
+/// Pipelines of the same priority are started in the order they were queued.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PipelinePriority {
+    /// Pipelines without which a view can't be presented at all, such as tonemapping or the
+    /// final blit to the window.
+    High,
+    /// Pipelines that are needed to draw something this frame.
+    #[default]
+    Normal,
+    /// Pipelines compiled ahead of time in case they are needed later.
+    Low,
 }
 
 /// State of a cached pipeline inserted into a [`PipelineCache`].
@@ -96,6 +200,9 @@ pub enum CachedPipelineState {
     Ok(Pipeline),
     /// An error occurred while trying to create the pipeline GPU object.
     Err(PipelineCacheError),
+    /// The pipeline GPU object was dropped with [`PipelineCache::evict_render_pipeline`] or
+    /// [`PipelineCache::evict_compute_pipeline`], and won't be created again.
+    Evicted,
 }
 
 impl CachedPipelineState {
@@ -108,7 +215,8 @@ impl CachedPipelineState {
     /// # Panics
     ///
     /// This method panics if the pipeline GPU object is not available, either because it is
-    /// pending creation or because an error occurred while attempting to create GPU object.
+    /// pending creation, because an error occurred while attempting to create GPU object, or
+    /// because it was evicted.
     pub fn unwrap(&self) -> &Pipeline {
         match self {
             CachedPipelineState::Ok(pipeline) => pipeline,
@@ -119,6 +227,9 @@ impl CachedPipelineState {
                 panic!("Pipeline has not been compiled yet. It is still in the 'Creating' state.")
             }
             CachedPipelineState::Err(err) => panic!("{}", err),
+            CachedPipelineState::Evicted => {
+                panic!("Pipeline has been evicted. It is in the 'Evicted' state.")
+            }
         }
     }
 }
@@ -401,14 +512,68 @@ impl ShaderCache {
         Ok(module.clone())
     }
 
+    /// The shader `id` and the shaders importing it, directly or not.
+    fn affected_shaders(&self, id: AssetId<Shader>) -> HashSet<AssetId<Shader>> {
+        let mut affected = HashSet::new();
+        let mut shaders_to_visit = vec![id];
+        while let Some(handle) = shaders_to_visit.pop() {
+            if !affected.insert(handle) {
+                continue;
+            }
+            if let Some(data) = self.data.get(&handle) {
+                shaders_to_visit.extend(data.dependents.iter().copied());
+            }
+        }
+        affected
+    }
+
+    /// The pipelines created with one of the `shaders`.
+    fn pipelines_using(&self, shaders: &HashSet<AssetId<Shader>>) -> HashSet<CachedPipelineId> {
+        shaders
+            .iter()
+            .filter_map(|handle| self.data.get(handle))
+            .flat_map(|data| data.pipelines.iter().copied())
+            .collect()
+    }
+
+    /// Feeds the source and shader defs of the shader `id` and of the shaders it imports,
+    /// recursively, to the `hasher`. Returns `None` if one of them isn't loaded yet.
+    fn hash_source(
+        &self,
+        id: AssetId<Shader>,
+        hasher: &mut impl Hasher,
+        visited: &mut HashSet<AssetId<Shader>>,
+    ) -> Option<()> {
+        if !visited.insert(id) {
+            return Some(());
+        }
+        let shader = self.shaders.get(&id)?;
+        shader.source.hash(hasher);
+        shader.shader_defs.hash(hasher);
+        for import in shader.imports() {
+            import.module_name().hash(hasher);
+            let import_id = *self.import_path_shaders.get(import)?;
+            self.hash_source(import_id, hasher, visited)?;
+        }
+        Some(())
+    }
+
+    /// Stops tracking the shaders of a pipeline that won't be created again.
+    fn forget_pipeline(&mut self, pipeline: CachedPipelineId) {
+        for data in self.data.values_mut() {
+            data.pipelines.remove(&pipeline);
+        }
+    }
+
     fn clear(&mut self, id: AssetId<Shader>) -> Vec<CachedPipelineId> {
-        let mut shaders_to_clear = vec![id];
-        let mut pipelines_to_queue = Vec::new();
-        while let Some(handle) = shaders_to_clear.pop() {
+        let affected_shaders = self.affected_shaders(id);
+        let pipelines_to_queue = self
+            .pipelines_using(&affected_shaders)
+            .into_iter()
+            .collect();
+        for handle in affected_shaders {
             if let Some(data) = self.data.get_mut(&handle) {
                 data.processed_shaders.clear();
-                pipelines_to_queue.extend(data.pipelines.iter().copied());
-                shaders_to_clear.extend(data.dependents.iter().copied());
 
                 if let Some(Shader { import_path, .. }) = self.shaders.get(&handle) {
                     self.composer
@@ -507,6 +672,11 @@ impl LayoutCache {
 /// Note that the cache does not perform automatic deduplication of identical pipelines. It is
 /// up to the user not to insert the same pipeline twice to avoid wasting GPU resources.
 ///
+/// When a shader is modified, such as when it's hot-reloaded, only the pipelines that use it or a
+/// shader importing it are recreated, see [`PipelineCache::pipelines_using_shader()`]. Until their replacement is created, they keep returning the
+/// pipeline created with the previous version of the shader, so that drawing doesn't stop while
+/// the new one compiles, or if it fails to.
+///
 /// [`RenderSet::Render`]: crate::RenderSet::Render
 #[derive(Resource)]
 pub struct PipelineCache {
@@ -515,10 +685,18 @@ pub struct PipelineCache {
     device: RenderDevice,
     pipelines: Vec<CachedPipeline>,
     waiting_pipelines: HashSet<CachedPipelineId>,
+    /// The pipelines being recreated after a change of their shaders, used until their
+    /// replacement is ready.
+    stale_pipelines: HashMap<CachedPipelineId, Pipeline>,
     new_pipelines: Mutex<Vec<CachedPipeline>>,
+    /// The pipelines that finished compiling since the last
+    /// [`send_pipeline_compilation_events`].
+    compilation_events: Vec<PipelineCompilationEvent>,
     /// If `true`, disables asynchronous pipeline compilation.
     /// This has no effect on MacOS, wasm, or without the `multi_threaded` feature.
     synchronous_pipeline_compilation: bool,
+    /// The maximum number of pipelines compiled in the background at the same time.
+    max_concurrent_compilations: NonZeroUsize,
 }
 
 impl PipelineCache {
@@ -532,7 +710,32 @@ impl PipelineCache {
         self.waiting_pipelines.iter().copied()
     }
 
+    /// Returns an iterator over the IDs of the pipelines being recreated after a change of their
+    /// shaders, which keep serving the pipeline created with the previous shaders meanwhile.
+    pub fn recreated_pipelines(&self) -> impl Iterator<Item = CachedPipelineId> + '_ {
+        self.stale_pipelines.keys().copied()
+    }
+
+    /// Returns the IDs of the pipelines that are recreated when the shader `id` changes: the
+    /// pipelines created with it, or with a shader importing it, directly or not.
+    ///
+    /// Pipelines are tracked once their shaders are processed, so the pipelines that are still
+    /// queued aren't returned. They use the latest version of the shader when they're created.
+    pub fn pipelines_using_shader(&self, id: AssetId<Shader>) -> Vec<CachedPipelineId> {
+        let shader_cache = self.shader_cache.lock().unwrap();
+        let mut pipelines: Vec<_> = shader_cache
+            .pipelines_using(&shader_cache.affected_shaders(id))
+            .into_iter()
+            .collect();
+        pipelines.sort_unstable();
+        pipelines
+    }
+
     /// Create a new pipeline cache associated with the given render device.
+    ///
+    /// The cache compiles at most [`PipelineCache::default_max_concurrent_compilations()`]
+    /// pipelines in the background at the same time, see
+    /// [`PipelineCache::set_max_concurrent_compilations()`].
     pub fn new(
         device: RenderDevice,
         render_adapter: RenderAdapter,
@@ -543,12 +746,36 @@ impl PipelineCache {
             device,
             layout_cache: default(),
             waiting_pipelines: default(),
+            stale_pipelines: default(),
             new_pipelines: default(),
+            compilation_events: default(),
             pipelines: default(),
             synchronous_pipeline_compilation,
+            max_concurrent_compilations: Self::default_max_concurrent_compilations(),
         }
     }
 
+    /// The number of pipelines compiled in the background at the same time by default: one less
+    /// than the number of logical cores, so that compilation never occupies all of them.
+    pub fn default_max_concurrent_compilations() -> NonZeroUsize {
+        NonZeroUsize::new(bevy_tasks::available_parallelism().saturating_sub(1))
+            .unwrap_or(NonZeroUsize::MIN)
+    }
+
+    /// Returns the maximum number of pipelines compiled in the background at the same time.
+    pub fn max_concurrent_compilations(&self) -> NonZeroUsize {
+        self.max_concurrent_compilations
+    }
+
+    /// Sets the maximum number of pipelines compiled in the background at the same time.
+    ///
+    /// Queued pipelines beyond this limit stay in the [`CachedPipelineState::Queued`] state
+    /// until a compilation finishes, and are started by [`PipelinePriority`].
+    /// This has no effect when pipelines are compiled synchronously.
+    pub fn set_max_concurrent_compilations(&mut self, max_concurrent_compilations: NonZeroUsize) {
+        self.max_concurrent_compilations = max_concurrent_compilations;
+    }
+
     /// Get the state of a cached render pipeline.
     ///
     /// See [`PipelineCache::queue_render_pipeline()`].
@@ -593,6 +820,75 @@ impl PipelineCache {
         }
     }
 
+    /// Returns the [`PipelineContentHash`] of a cached render pipeline, once its creation
+    /// started.
+    #[inline]
+    pub fn get_render_pipeline_content_hash(
+        &self,
+        id: CachedRenderPipelineId,
+    ) -> Option<PipelineContentHash> {
+        self.pipelines.get(id.0)?.content_hash
+    }
+
+    /// Returns the [`PipelineContentHash`] of a cached compute pipeline, once its creation
+    /// started.
+    #[inline]
+    pub fn get_compute_pipeline_content_hash(
+        &self,
+        id: CachedComputePipelineId,
+    ) -> Option<PipelineContentHash> {
+        self.pipelines.get(id.0)?.content_hash
+    }
+
+    /// Hashes the `descriptor` and the sources of its shaders, or returns `None` if one of them
+    /// isn't loaded yet or one of its bind group layouts has no
+    /// [`BindGroupLayout::content_hash`].
+    fn content_hash(&self, descriptor: &PipelineDescriptor) -> Option<PipelineContentHash> {
+        let shader_cache = self.shader_cache.lock().unwrap();
+        let mut hasher = FixedState.build_hasher();
+        let hash_layout = |layout: &[BindGroupLayout], hasher: &mut AHasher| {
+            for bind_group_layout in layout {
+                bind_group_layout.content_hash()?.hash(hasher);
+            }
+            Some(())
+        };
+        let hash_shader = |shader: &Handle<Shader>, hasher: &mut AHasher| {
+            shader_cache.hash_source(shader.id(), hasher, &mut HashSet::new())
+        };
+        match descriptor {
+            PipelineDescriptor::RenderPipelineDescriptor(descriptor) => {
+                0u8.hash(&mut hasher);
+                descriptor.label.hash(&mut hasher);
+                hash_layout(&descriptor.layout, &mut hasher)?;
+                descriptor.push_constant_ranges.hash(&mut hasher);
+                hash_shader(&descriptor.vertex.shader, &mut hasher)?;
+                descriptor.vertex.shader_defs.hash(&mut hasher);
+                descriptor.vertex.entry_point.hash(&mut hasher);
+                descriptor.vertex.buffers.hash(&mut hasher);
+                descriptor.primitive.hash(&mut hasher);
+                descriptor.depth_stencil.hash(&mut hasher);
+                descriptor.multisample.hash(&mut hasher);
+                descriptor.fragment.is_some().hash(&mut hasher);
+                if let Some(fragment) = &descriptor.fragment {
+                    hash_shader(&fragment.shader, &mut hasher)?;
+                    fragment.shader_defs.hash(&mut hasher);
+                    fragment.entry_point.hash(&mut hasher);
+                    fragment.targets.hash(&mut hasher);
+                }
+            }
+            PipelineDescriptor::ComputePipelineDescriptor(descriptor) => {
+                1u8.hash(&mut hasher);
+                descriptor.label.hash(&mut hasher);
+                hash_layout(&descriptor.layout, &mut hasher)?;
+                descriptor.push_constant_ranges.hash(&mut hasher);
+                hash_shader(&descriptor.shader, &mut hasher)?;
+                descriptor.shader_defs.hash(&mut hasher);
+                descriptor.entry_point.hash(&mut hasher);
+            }
+        }
+        Some(PipelineContentHash(hasher.finish()))
+    }
+
     /// Try to retrieve a render pipeline GPU object from a cached ID.
     ///
     /// # Returns
@@ -600,14 +896,14 @@ impl PipelineCache {
     /// This method returns a successfully created render pipeline if any, or `None` if the pipeline
     /// was not created yet or if there was an error during creation. You can check the actual creation
     /// state with [`PipelineCache::get_render_pipeline_state()`].
+    ///
+    /// While the pipeline is recreated after a change of its shaders, this returns the pipeline
+    /// created with the previous shaders.
     #[inline]
     pub fn get_render_pipeline(&self, id: CachedRenderPipelineId) -> Option<&RenderPipeline> {
-        if let CachedPipelineState::Ok(Pipeline::RenderPipeline(pipeline)) =
-            &self.pipelines[id.0].state
-        {
-            Some(pipeline)
-        } else {
-            None
+        match self.get_pipeline(id.0) {
+            Some(Pipeline::RenderPipeline(pipeline)) => Some(pipeline),
+            _ => None,
         }
     }
 
@@ -618,15 +914,50 @@ impl PipelineCache {
             self.process_queue();
         }
 
-        let state = &mut self.pipelines[id.0].state;
-        if let CachedPipelineState::Creating(task) = state {
-            *state = match bevy_tasks::block_on(task) {
-                Ok(p) => CachedPipelineState::Ok(p),
+        let cached_pipeline = &mut self.pipelines[id.0];
+        if let CachedPipelineState::Creating(task) = &mut cached_pipeline.state {
+            cached_pipeline.state = match bevy_tasks::block_on(task) {
+                Ok(p) => {
+                    self.stale_pipelines.remove(&id.0);
+                    self.compilation_events.push(PipelineCompilationEvent {
+                        id: id.0,
+                        label: cached_pipeline.descriptor.label().clone(),
+                        result: Ok(()),
+                    });
+                    CachedPipelineState::Ok(p)
+                }
                 Err(e) => CachedPipelineState::Err(e),
             };
         }
     }
 
+    /// Drop the GPU object of a cached render pipeline that isn't needed anymore.
+    ///
+    /// The id stays valid, but its state becomes [`CachedPipelineState::Evicted`]: the pipeline
+    /// isn't created again, even when its shaders change. Queue the descriptor again to get a
+    /// new pipeline.
+    pub fn evict_render_pipeline(&mut self, id: CachedRenderPipelineId) {
+        self.evict_pipeline(id.0);
+    }
+
+    /// Drop the GPU object of a cached compute pipeline that isn't needed anymore.
+    ///
+    /// See [`PipelineCache::evict_render_pipeline()`].
+    pub fn evict_compute_pipeline(&mut self, id: CachedComputePipelineId) {
+        self.evict_pipeline(id.0);
+    }
+
+    fn evict_pipeline(&mut self, id: CachedPipelineId) {
+        if self.pipelines.len() <= id {
+            self.process_queue();
+        }
+
+        self.pipelines[id].state = CachedPipelineState::Evicted;
+        self.waiting_pipelines.remove(&id);
+        self.stale_pipelines.remove(&id);
+        self.shader_cache.lock().unwrap().forget_pipeline(id);
+    }
+
     /// Try to retrieve a compute pipeline GPU object from a cached ID.
     ///
     /// # Returns
@@ -634,14 +965,23 @@ impl PipelineCache {
     /// This method returns a successfully created compute pipeline if any, or `None` if the pipeline
     /// was not created yet or if there was an error during creation. You can check the actual creation
     /// state with [`PipelineCache::get_compute_pipeline_state()`].
+    ///
+    /// While the pipeline is recreated after a change of its shaders, this returns the pipeline
+    /// created with the previous shaders.
     #[inline]
     pub fn get_compute_pipeline(&self, id: CachedComputePipelineId) -> Option<&ComputePipeline> {
-        if let CachedPipelineState::Ok(Pipeline::ComputePipeline(pipeline)) =
-            &self.pipelines[id.0].state
-        {
-            Some(pipeline)
-        } else {
-            None
+        match self.get_pipeline(id.0) {
+            Some(Pipeline::ComputePipeline(pipeline)) => Some(pipeline),
+            _ => None,
+        }
+    }
+
+    /// The created pipeline of `id`, or the stale one it replaces while it's recreated.
+    #[inline]
+    fn get_pipeline(&self, id: CachedPipelineId) -> Option<&Pipeline> {
+        match &self.pipelines[id].state {
+            CachedPipelineState::Ok(pipeline) => Some(pipeline),
+            _ => self.stale_pipelines.get(&id),
         }
     }
 
@@ -661,6 +1001,18 @@ impl PipelineCache {
     pub fn queue_render_pipeline(
         &self,
         descriptor: RenderPipelineDescriptor,
+    ) -> CachedRenderPipelineId {
+        self.queue_render_pipeline_with_priority(descriptor, PipelinePriority::Normal)
+    }
+
+    /// Insert a render pipeline into the cache, and queue its creation with the given
+    /// [`PipelinePriority`].
+    ///
+    /// See [`PipelineCache::queue_render_pipeline()`].
+    pub fn queue_render_pipeline_with_priority(
+        &self,
+        descriptor: RenderPipelineDescriptor,
+        priority: PipelinePriority,
     ) -> CachedRenderPipelineId {
         let mut new_pipelines = self
             .new_pipelines
@@ -670,6 +1022,8 @@ impl PipelineCache {
         new_pipelines.push(CachedPipeline {
             descriptor: PipelineDescriptor::RenderPipelineDescriptor(Box::new(descriptor)),
             state: CachedPipelineState::Queued,
+            priority,
+            content_hash: None,
         });
         id
     }
@@ -690,6 +1044,18 @@ impl PipelineCache {
     pub fn queue_compute_pipeline(
         &self,
         descriptor: ComputePipelineDescriptor,
+    ) -> CachedComputePipelineId {
+        self.queue_compute_pipeline_with_priority(descriptor, PipelinePriority::Normal)
+    }
+
+    /// Insert a compute pipeline into the cache, and queue its creation with the given
+    /// [`PipelinePriority`].
+    ///
+    /// See [`PipelineCache::queue_compute_pipeline()`].
+    pub fn queue_compute_pipeline_with_priority(
+        &self,
+        descriptor: ComputePipelineDescriptor,
+        priority: PipelinePriority,
     ) -> CachedComputePipelineId {
         let mut new_pipelines = self
             .new_pipelines
@@ -699,24 +1065,43 @@ impl PipelineCache {
         new_pipelines.push(CachedPipeline {
             descriptor: PipelineDescriptor::ComputePipelineDescriptor(Box::new(descriptor)),
             state: CachedPipelineState::Queued,
+            priority,
+            content_hash: None,
         });
         id
     }
 
     fn set_shader(&mut self, id: AssetId<Shader>, shader: &Shader) {
-        let mut shader_cache = self.shader_cache.lock().unwrap();
-        let pipelines_to_queue = shader_cache.set_shader(id, shader.clone());
-        for cached_pipeline in pipelines_to_queue {
-            self.pipelines[cached_pipeline].state = CachedPipelineState::Queued;
-            self.waiting_pipelines.insert(cached_pipeline);
-        }
+        let pipelines_to_queue = self
+            .shader_cache
+            .lock()
+            .unwrap()
+            .set_shader(id, shader.clone());
+        self.requeue_pipelines(pipelines_to_queue);
     }
 
     fn remove_shader(&mut self, shader: AssetId<Shader>) {
-        let mut shader_cache = self.shader_cache.lock().unwrap();
-        let pipelines_to_queue = shader_cache.remove(shader);
-        for cached_pipeline in pipelines_to_queue {
-            self.pipelines[cached_pipeline].state = CachedPipelineState::Queued;
+        let pipelines_to_queue = self.shader_cache.lock().unwrap().remove(shader);
+        self.requeue_pipelines(pipelines_to_queue);
+    }
+
+    /// Queues the recreation of pipelines whose shaders changed, keeping the created ones until
+    /// their replacement is ready.
+    fn requeue_pipelines(&mut self, pipelines: Vec<CachedPipelineId>) {
+        for cached_pipeline in pipelines {
+            if matches!(
+                self.pipelines[cached_pipeline].state,
+                CachedPipelineState::Evicted
+            ) {
+                continue;
+            }
+            let state = mem::replace(
+                &mut self.pipelines[cached_pipeline].state,
+                CachedPipelineState::Queued,
+            );
+            if let CachedPipelineState::Ok(pipeline) = state {
+                self.stale_pipelines.insert(cached_pipeline, pipeline);
+            }
             self.waiting_pipelines.insert(cached_pipeline);
         }
     }
@@ -873,6 +1258,9 @@ impl PipelineCache {
 
     /// Process the pipeline queue and create all pending pipelines if possible.
     ///
+    /// Pipelines being compiled are polled first, then queued pipelines are started by
+    /// [`PipelinePriority`] until [`PipelineCache::max_concurrent_compilations()`] are in flight.
+    ///
     /// This is generally called automatically during the [`RenderSet::Render`] step, but can
     /// be called manually to force creation at a different time.
     ///
@@ -893,8 +1281,28 @@ impl PipelineCache {
             }
         }
 
-        for id in waiting_pipelines {
+        let (mut queued, waiting_pipelines): (Vec<_>, Vec<_>) = waiting_pipelines
+            .into_iter()
+            .partition(|&id| matches!(pipelines[id].state, CachedPipelineState::Queued));
+
+        for &id in &waiting_pipelines {
+            self.process_pipeline(&mut pipelines[id], id);
+        }
+
+        let mut in_flight = waiting_pipelines
+            .iter()
+            .filter(|&&id| matches!(pipelines[id].state, CachedPipelineState::Creating(_)))
+            .count();
+        queued.sort_unstable_by_key(|&id| (pipelines[id].priority, id));
+        for id in queued {
+            if in_flight >= self.max_concurrent_compilations.get() {
+                self.waiting_pipelines.insert(id);
+                continue;
+            }
             self.process_pipeline(&mut pipelines[id], id);
+            if matches!(pipelines[id].state, CachedPipelineState::Creating(_)) {
+                in_flight += 1;
+            }
         }
 
         self.pipelines = pipelines;
@@ -903,20 +1311,43 @@ impl PipelineCache {
     fn process_pipeline(&mut self, cached_pipeline: &mut CachedPipeline, id: usize) {
         match &mut cached_pipeline.state {
             CachedPipelineState::Queued => {
+                // Recomputed every time, as the shaders may have changed since the last creation
+                let content_hash = self.content_hash(&cached_pipeline.descriptor);
+                cached_pipeline.content_hash = content_hash;
+                let label_with_hash = |label: &Option<Cow<'static, str>>| {
+                    let Some(content_hash) = content_hash else {
+                        return label.clone();
+                    };
+                    Some(match label {
+                        Some(label) => format!("{label} {content_hash}").into(),
+                        None => content_hash.to_string().into(),
+                    })
+                };
                 cached_pipeline.state = match &cached_pipeline.descriptor {
                     PipelineDescriptor::RenderPipelineDescriptor(descriptor) => {
-                        self.start_create_render_pipeline(id, *descriptor.clone())
+                        let mut descriptor = *descriptor.clone();
+                        descriptor.label = label_with_hash(&descriptor.label);
+                        self.start_create_render_pipeline(id, descriptor)
                     }
                     PipelineDescriptor::ComputePipelineDescriptor(descriptor) => {
-                        self.start_create_compute_pipeline(id, *descriptor.clone())
+                        let mut descriptor = *descriptor.clone();
+                        descriptor.label = label_with_hash(&descriptor.label);
+                        self.start_create_compute_pipeline(id, descriptor)
                     }
                 };
+
+                // Synchronous compilation finishes right away.
+                if matches!(cached_pipeline.state, CachedPipelineState::Ok(_)) {
+                    self.push_compilation_event(id, cached_pipeline, Ok(()));
+                }
             }
 
             CachedPipelineState::Creating(ref mut task) => {
                 match bevy_utils::futures::check_ready(task) {
                     Some(Ok(pipeline)) => {
                         cached_pipeline.state = CachedPipelineState::Ok(pipeline);
+                        self.stale_pipelines.remove(&id);
+                        self.push_compilation_event(id, cached_pipeline, Ok(()));
                         return;
                     }
                     Some(Err(err)) => cached_pipeline.state = CachedPipelineState::Err(err),
@@ -936,21 +1367,44 @@ impl PipelineCache {
                     let error_detail =
                         err.emit_to_string(&self.shader_cache.lock().unwrap().composer);
                     error!("failed to process shader:\n{}", error_detail);
+                    self.push_compilation_event(id, cached_pipeline, Err(error_detail));
                     return;
                 }
                 PipelineCacheError::CreateShaderModule(description) => {
                     error!("failed to create shader module: {}", description);
+                    let description = description.clone();
+                    self.push_compilation_event(id, cached_pipeline, Err(description));
                     return;
                 }
             },
 
-            CachedPipelineState::Ok(_) => return,
+            CachedPipelineState::Ok(_) => {
+                self.stale_pipelines.remove(&id);
+                return;
+            }
+
+            CachedPipelineState::Evicted => return,
         }
 
         // Retry
         self.waiting_pipelines.insert(id);
     }
 
+    /// Records that the compilation of a pipeline finished, to send a
+    /// [`PipelineCompilationEvent`] to the main world.
+    fn push_compilation_event(
+        &mut self,
+        id: CachedPipelineId,
+        cached_pipeline: &CachedPipeline,
+        result: Result<(), String>,
+    ) {
+        self.compilation_events.push(PipelineCompilationEvent {
+            id,
+            label: cached_pipeline.descriptor.label().clone(),
+            result,
+        });
+    }
+
     pub(crate) fn process_pipeline_queue_system(mut cache: ResMut<Self>) {
         cache.process_queue();
     }
@@ -3,19 +3,26 @@ use crate::render_resource::CachedComputePipelineId;
 use crate::{
     mesh::MissingVertexAttributeError,
     render_resource::{
-        CachedRenderPipelineId, ComputePipelineDescriptor, PipelineCache, RenderPipelineDescriptor,
-        VertexBufferLayout,
+        CachedRenderPipelineId, ComputePipelineDescriptor, PipelineCache, PipelinePriority,
+        RenderPipelineDescriptor, VertexBufferLayout,
     },
 };
-use bevy_ecs::system::Resource;
+use bevy_ecs::system::{ResMut, Resource};
 use bevy_utils::hashbrown::hash_map::VacantEntry;
-use bevy_utils::{default, hashbrown::hash_map::RawEntryMut, tracing::error, Entry, HashMap};
-use std::{fmt::Debug, hash::Hash};
+use bevy_utils::{
+    default, hashbrown::hash_map::RawEntryMut, tracing::error, Entry, HashMap, HashSet,
+};
+use std::{borrow::Cow, fmt::Debug, hash::Hash};
 use thiserror::Error;
 
 pub trait SpecializedRenderPipeline {
     type Key: Clone + Hash + PartialEq + Eq;
     fn specialize(&self, key: Self::Key) -> RenderPipelineDescriptor;
+
+    /// The [`PipelinePriority`] the pipeline specialized for `key` is compiled with.
+    fn priority(&self, _key: &Self::Key) -> PipelinePriority {
+        PipelinePriority::Normal
+    }
 }
 
 #[derive(Resource)]
@@ -37,8 +44,9 @@ impl<S: SpecializedRenderPipeline> SpecializedRenderPipelines<S> {
         key: S::Key,
     ) -> CachedRenderPipelineId {
         *self.cache.entry(key.clone()).or_insert_with(|| {
+            let priority = specialize_pipeline.priority(&key);
             let descriptor = specialize_pipeline.specialize(key);
-            cache.queue_render_pipeline(descriptor)
+            cache.queue_render_pipeline_with_priority(descriptor, priority)
         })
     }
 }
@@ -46,16 +54,35 @@ impl<S: SpecializedRenderPipeline> SpecializedRenderPipelines<S> {
 pub trait SpecializedComputePipeline {
     type Key: Clone + Hash + PartialEq + Eq;
     fn specialize(&self, key: Self::Key) -> ComputePipelineDescriptor;
+
+    /// The [`PipelinePriority`] the pipeline specialized for `key` is compiled with.
+    fn priority(&self, _key: &Self::Key) -> PipelinePriority {
+        PipelinePriority::Normal
+    }
 }
 
+/// The pipelines specialized from a [`SpecializedComputePipeline`], for each key.
+///
+/// Like [`SpecializedMeshPipelines`], the resource counts how often each key is reused, see
+/// [`SpecializedComputePipelines::stats`], and can evict the pipelines that aren't specialized
+/// for a while with [`SpecializedComputePipelines::set_max_unused_frames`]. This requires
+/// [`evict_unused_compute_pipelines::<S>`](evict_unused_compute_pipelines) to run every frame.
 #[derive(Resource)]
 pub struct SpecializedComputePipelines<S: SpecializedComputePipeline> {
-    cache: HashMap<S::Key, CachedComputePipelineId>,
+    cache: HashMap<S::Key, PipelineUsage<CachedComputePipelineId>>,
+    frame: u32,
+    max_unused_frames: Option<u32>,
+    evicted: u64,
 }
 
 impl<S: SpecializedComputePipeline> Default for SpecializedComputePipelines<S> {
     fn default() -> Self {
-        Self { cache: default() }
+        Self {
+            cache: default(),
+            frame: 0,
+            max_unused_frames: None,
+            evicted: 0,
+        }
     }
 }
 
@@ -66,13 +93,102 @@ impl<S: SpecializedComputePipeline> SpecializedComputePipelines<S> {
         specialize_pipeline: &S,
         key: S::Key,
     ) -> CachedComputePipelineId {
-        *self.cache.entry(key.clone()).or_insert_with(|| {
-            let descriptor = specialize_pipeline.specialize(key);
-            cache.queue_compute_pipeline(descriptor)
-        })
+        let frame = self.frame;
+        match self.cache.entry(key) {
+            Entry::Occupied(entry) => {
+                let usage = entry.into_mut();
+                usage.hit(frame);
+                usage.id
+            }
+            Entry::Vacant(entry) => {
+                let key = entry.key().clone();
+                let priority = specialize_pipeline.priority(&key);
+                let descriptor = specialize_pipeline.specialize(key);
+                let id = cache.queue_compute_pipeline_with_priority(descriptor, priority);
+                entry.insert(PipelineUsage::new(id, frame));
+                id
+            }
+        }
+    }
+
+    /// Iterates over the keys of the pipelines specialized so far, with their ids.
+    pub fn iter(&self) -> impl Iterator<Item = (&S::Key, CachedComputePipelineId)> {
+        self.cache.iter().map(|(key, usage)| (key, usage.id))
+    }
+
+    /// Returns how the pipeline of each key specialized so far was used.
+    pub fn stats(&self) -> HashMap<&S::Key, SpecializedPipelineStats> {
+        self.cache
+            .iter()
+            .map(|(key, usage)| {
+                let stats = SpecializedPipelineStats {
+                    hits: usage.hits,
+                    misses: 1,
+                    last_used_frame: usage.last_used_frame,
+                };
+                (key, stats)
+            })
+            .collect()
+    }
+
+    /// The current frame, advanced by [`SpecializedComputePipelines::evict_unused`].
+    pub fn frame(&self) -> u32 {
+        self.frame
+    }
+
+    /// The number of frames a pipeline can stay unused before it is evicted, if any.
+    pub fn max_unused_frames(&self) -> Option<u32> {
+        self.max_unused_frames
+    }
+
+    /// Evicts the pipelines that weren't specialized in the last `max_unused_frames` frames,
+    /// or never evicts them if `None`, which is the default.
+    pub fn set_max_unused_frames(&mut self, max_unused_frames: Option<u32>) {
+        self.max_unused_frames = max_unused_frames;
+    }
+
+    /// The number of pipelines evicted so far.
+    pub fn evicted(&self) -> u64 {
+        self.evicted
+    }
+
+    /// Ends the current frame, evicting the pipelines that weren't specialized in the last
+    /// [`max_unused_frames`](SpecializedComputePipelines::max_unused_frames) frames from this
+    /// resource and from the `cache`.
+    ///
+    /// Returns the number of evicted pipelines.
+    pub fn evict_unused(&mut self, cache: &mut PipelineCache) -> usize {
+        let frame = self.frame;
+        self.frame = self.frame.wrapping_add(1);
+        let Some(max_unused_frames) = self.max_unused_frames else {
+            return 0;
+        };
+
+        let mut evicted = 0;
+        self.cache.retain(|_, usage| {
+            if !usage.is_unused(frame, max_unused_frames) {
+                return true;
+            }
+            cache.evict_compute_pipeline(usage.id);
+            evicted += 1;
+            false
+        });
+        self.evicted += evicted as u64;
+        evicted
     }
 }
 
+/// Evicts the unused pipelines of a [`SpecializedComputePipelines`], see
+/// [`SpecializedComputePipelines::evict_unused`].
+pub fn evict_unused_compute_pipelines<S: SpecializedComputePipeline>(
+    mut pipelines: ResMut<SpecializedComputePipelines<S>>,
+    mut cache: ResMut<PipelineCache>,
+) where
+    SpecializedComputePipelines<S>: Resource,
+{
+    pipelines.evict_unused(&mut cache);
+}
+
 pub trait SpecializedMeshPipeline {
     type Key: Clone + Hash + PartialEq + Eq;
     fn specialize(
@@ -82,10 +198,127 @@ pub trait SpecializedMeshPipeline {
     ) -> Result<RenderPipelineDescriptor, SpecializedMeshPipelineError>;
 }
 
+/// The pipelines specialized from a [`SpecializedMeshPipeline`], for each key and mesh vertex
+/// buffer layout.
+///
+/// The resource counts how often each key is reused, see [`SpecializedMeshPipelines::stats`].
+/// With [`SpecializedMeshPipelines::set_max_unused_frames`], the pipelines that aren't
+/// specialized for a while are evicted from the [`PipelineCache`], so that long sessions don't
+/// keep every pipeline they ever needed alive. This requires
+/// [`evict_unused_mesh_pipelines::<S>`](evict_unused_mesh_pipelines) to run every frame.
 #[derive(Resource)]
 pub struct SpecializedMeshPipelines<S: SpecializedMeshPipeline> {
-    mesh_layout_cache: HashMap<(MeshVertexBufferLayoutRef, S::Key), CachedRenderPipelineId>,
+    mesh_layout_cache:
+        HashMap<(MeshVertexBufferLayoutRef, S::Key), PipelineUsage<CachedRenderPipelineId>>,
     vertex_layout_cache: VertexLayoutCache<S>,
+    frame: u32,
+    max_unused_frames: Option<u32>,
+    evicted: u64,
+}
+
+/// A specialized pipeline, and how it was used.
+#[derive(Clone, Copy)]
+struct PipelineUsage<Id> {
+    id: Id,
+    hits: u64,
+    last_used_frame: u32,
+}
+
+impl<Id> PipelineUsage<Id> {
+    fn new(id: Id, frame: u32) -> Self {
+        Self {
+            id,
+            hits: 0,
+            last_used_frame: frame,
+        }
+    }
+
+    fn hit(&mut self, frame: u32) {
+        self.hits += 1;
+        self.last_used_frame = frame;
+    }
+
+    fn is_unused(&self, frame: u32, max_unused_frames: u32) -> bool {
+        frame.wrapping_sub(self.last_used_frame) > max_unused_frames
+    }
+}
+
+/// How the pipelines of a key of a [`SpecializedMeshPipelines`] or a
+/// [`SpecializedComputePipelines`] were used.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SpecializedPipelineStats {
+    /// The number of times the key was specialized and its pipeline was already cached.
+    pub hits: u64,
+    /// The number of times the key was specialized and its pipeline wasn't cached yet.
+    ///
+    /// Mesh pipelines miss once per mesh vertex buffer layout a key is specialized for. This
+    /// doesn't always queue a new pipeline, as layouts producing the same vertex buffer layout
+    /// share their pipelines.
+    pub misses: u64,
+    /// The last frame the key was specialized in, see [`SpecializedMeshPipelines::frame`] and
+    /// [`SpecializedComputePipelines::frame`].
+    pub last_used_frame: u32,
+}
+
+impl SpecializedPipelineStats {
+    /// The fraction of the specializations of the key that were hits, between `0.0` and `1.0`.
+    pub fn hit_rate(&self) -> f32 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            0.0
+        } else {
+            self.hits as f32 / total as f32
+        }
+    }
+}
+
+/// A pipeline key made of bit flags, which can each be switched independently of the others,
+/// and of other state compared as a whole.
+///
+/// This is used to estimate how many pipelines could be specialized from the keys seen so far,
+/// see [`SpecializedMeshPipelines::permutations`].
+pub trait PipelineKeyBits {
+    /// The bit flags of the key.
+    fn bits(&self) -> u64;
+
+    /// The key with all of its bit flags cleared, to compare the rest of the key.
+    fn without_bits(&self) -> Self;
+}
+
+/// How many pipelines a [`SpecializedMeshPipelines`] specialized, and how many it could end up
+/// specializing if the keys it has seen were combined, see
+/// [`SpecializedMeshPipelines::permutations`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PipelinePermutations {
+    /// The number of pipelines specialized so far, once per key and vertex buffer layout.
+    pub specialized: usize,
+    /// The number of distinct keys specialized so far.
+    pub keys: usize,
+    /// The [`PipelineKeyBits::bits`] that differ between the keys.
+    pub varying_bits: u64,
+    /// The number of distinct keys once their bits are cleared.
+    pub other_variants: usize,
+    /// The number of distinct vertex buffer layouts the keys were specialized with.
+    pub vertex_layouts: usize,
+}
+
+impl PipelinePermutations {
+    /// The number of pipelines that could be specialized if every varying bit, other variant
+    /// and vertex buffer layout were combined, saturating at [`u64::MAX`].
+    ///
+    /// Bits that always change together, like the bits of a multi-bit field, are counted as
+    /// independent, so this is an upper bound. A count much higher than
+    /// [`PipelinePermutations::specialized`] means that the keys explode combinatorially as new
+    /// meshes, materials and views show up.
+    pub fn theoretical(&self) -> u64 {
+        if self.keys == 0 {
+            return 0;
+        }
+        1u64.checked_shl(self.varying_bits.count_ones())
+            .unwrap_or(u64::MAX)
+            .saturating_mul(self.other_variants as u64)
+            .saturating_mul(self.vertex_layouts as u64)
+    }
 }
 
 pub type VertexLayoutCache<S> = HashMap<
@@ -98,11 +331,122 @@ impl<S: SpecializedMeshPipeline> Default for SpecializedMeshPipelines<S> {
         Self {
             mesh_layout_cache: Default::default(),
             vertex_layout_cache: Default::default(),
+            frame: 0,
+            max_unused_frames: None,
+            evicted: 0,
         }
     }
 }
 
 impl<S: SpecializedMeshPipeline> SpecializedMeshPipelines<S> {
+    /// Iterates over the keys of the pipelines specialized so far, with their ids.
+    ///
+    /// A key is returned once for each vertex buffer layout it was specialized with.
+    pub fn iter(&self) -> impl Iterator<Item = (&S::Key, CachedRenderPipelineId)> {
+        self.vertex_layout_cache
+            .values()
+            .flat_map(|pipelines| pipelines.iter().map(|(key, id)| (key, *id)))
+    }
+
+    /// Returns how the pipelines of each key specialized so far were used.
+    pub fn stats(&self) -> HashMap<&S::Key, SpecializedPipelineStats> {
+        let mut stats = HashMap::<&S::Key, SpecializedPipelineStats>::default();
+        for ((_, key), usage) in &self.mesh_layout_cache {
+            let key_stats = stats.entry(key).or_default();
+            key_stats.hits += usage.hits;
+            key_stats.misses += 1;
+            key_stats.last_used_frame = key_stats.last_used_frame.max(usage.last_used_frame);
+        }
+        stats
+    }
+
+    /// Returns how many pipelines were specialized so far, and how many could be specialized from
+    /// the bits and the other state of their keys.
+    pub fn permutations(&self) -> PipelinePermutations
+    where
+        S::Key: PipelineKeyBits,
+    {
+        let mut permutations = PipelinePermutations {
+            vertex_layouts: self.vertex_layout_cache.len(),
+            ..default()
+        };
+        let mut keys = HashSet::new();
+        let mut other_variants = HashSet::new();
+        let mut first_bits = None;
+        for (key, _) in self.iter() {
+            permutations.specialized += 1;
+            if !keys.insert(key) {
+                continue;
+            }
+            let bits = key.bits();
+            permutations.varying_bits |= bits ^ *first_bits.get_or_insert(bits);
+            other_variants.insert(key.without_bits());
+        }
+        permutations.keys = keys.len();
+        permutations.other_variants = other_variants.len();
+        permutations
+    }
+
+    /// The current frame, advanced by [`SpecializedMeshPipelines::evict_unused`].
+    pub fn frame(&self) -> u32 {
+        self.frame
+    }
+
+    /// The number of frames a pipeline can stay unused before it is evicted, if any.
+    pub fn max_unused_frames(&self) -> Option<u32> {
+        self.max_unused_frames
+    }
+
+    /// Evicts the pipelines that weren't specialized in the last `max_unused_frames` frames,
+    /// or never evicts them if `None`, which is the default.
+    pub fn set_max_unused_frames(&mut self, max_unused_frames: Option<u32>) {
+        self.max_unused_frames = max_unused_frames;
+    }
+
+    /// The number of pipelines evicted so far.
+    pub fn evicted(&self) -> u64 {
+        self.evicted
+    }
+
+    /// Ends the current frame, evicting the pipelines that weren't specialized in the last
+    /// [`max_unused_frames`](SpecializedMeshPipelines::max_unused_frames) frames from this
+    /// resource and from the `cache`.
+    ///
+    /// Returns the number of evicted pipelines.
+    pub fn evict_unused(&mut self, cache: &mut PipelineCache) -> usize {
+        let frame = self.frame;
+        self.frame = self.frame.wrapping_add(1);
+        let Some(max_unused_frames) = self.max_unused_frames else {
+            return 0;
+        };
+
+        // Mesh layouts producing the same vertex buffer layout share their pipeline, which is
+        // only evicted when none of them used it recently.
+        let used: HashSet<CachedRenderPipelineId> = self
+            .mesh_layout_cache
+            .values()
+            .filter(|usage| !usage.is_unused(frame, max_unused_frames))
+            .map(|usage| usage.id)
+            .collect();
+        self.mesh_layout_cache
+            .retain(|_, usage| used.contains(&usage.id));
+
+        let mut evicted = 0;
+        self.vertex_layout_cache.retain(|_, pipelines| {
+            pipelines.retain(|_, id| {
+                if used.contains(id) {
+                    return true;
+                }
+                cache.evict_render_pipeline(*id);
+                evicted += 1;
+                false
+            });
+            !pipelines.is_empty()
+        });
+        self.evicted += evicted as u64;
+        evicted
+    }
+
     #[inline]
     pub fn specialize(
         &mut self,
@@ -111,8 +455,13 @@ impl<S: SpecializedMeshPipeline> SpecializedMeshPipelines<S> {
         key: S::Key,
         layout: &MeshVertexBufferLayoutRef,
     ) -> Result<CachedRenderPipelineId, SpecializedMeshPipelineError> {
+        let frame = self.frame;
         return match self.mesh_layout_cache.entry((layout.clone(), key.clone())) {
-            Entry::Occupied(entry) => Ok(*entry.into_mut()),
+            Entry::Occupied(entry) => {
+                let usage = entry.into_mut();
+                usage.hit(frame);
+                Ok(usage.id)
+            }
             Entry::Vacant(entry) => specialize_slow(
                 &mut self.vertex_layout_cache,
                 cache,
@@ -120,6 +469,7 @@ impl<S: SpecializedMeshPipeline> SpecializedMeshPipelines<S> {
                 key,
                 layout,
                 entry,
+                frame,
             ),
         };
 
@@ -130,7 +480,11 @@ impl<S: SpecializedMeshPipeline> SpecializedMeshPipelines<S> {
             specialize_pipeline: &S,
             key: S::Key,
             layout: &MeshVertexBufferLayoutRef,
-            entry: VacantEntry<(MeshVertexBufferLayoutRef, S::Key), CachedRenderPipelineId>,
+            entry: VacantEntry<
+                (MeshVertexBufferLayoutRef, S::Key),
+                PipelineUsage<CachedRenderPipelineId>,
+            >,
+            frame: u32,
         ) -> Result<CachedRenderPipelineId, SpecializedMeshPipelineError>
         where
             S: SpecializedMeshPipeline,
@@ -138,10 +492,7 @@ impl<S: SpecializedMeshPipeline> SpecializedMeshPipelines<S> {
             let descriptor = specialize_pipeline
                 .specialize(key.clone(), layout)
                 .map_err(|mut err| {
-                    {
-                        let SpecializedMeshPipelineError::MissingVertexAttribute(err) = &mut err;
-                        err.pipeline_type = Some(std::any::type_name::<S>());
-                    }
+                    err.set_pipeline_type(std::any::type_name::<S>());
                     err
                 })?;
             // Different MeshVertexBufferLayouts can produce the same final VertexBufferLayout
@@ -157,7 +508,7 @@ impl<S: SpecializedMeshPipeline> SpecializedMeshPipelines<S> {
                         .1
                 }
             };
-            Ok(*entry.insert(match layout_map.entry(key) {
+            let id = match layout_map.entry(key) {
                 Entry::Occupied(entry) => {
                     if cfg!(debug_assertions) {
                         let stored_descriptor = cache.get_render_pipeline_descriptor(*entry.get());
@@ -176,13 +527,79 @@ impl<S: SpecializedMeshPipeline> SpecializedMeshPipelines<S> {
                     *entry.into_mut()
                 }
                 Entry::Vacant(entry) => *entry.insert(cache.queue_render_pipeline(descriptor)),
-            }))
+            };
+            entry.insert(PipelineUsage::new(id, frame));
+            Ok(id)
         }
     }
 }
 
+/// Evicts the unused pipelines of a [`SpecializedMeshPipelines`], see
+/// [`SpecializedMeshPipelines::evict_unused`].
+pub fn evict_unused_mesh_pipelines<S: SpecializedMeshPipeline>(
+    mut pipelines: ResMut<SpecializedMeshPipelines<S>>,
+    mut cache: ResMut<PipelineCache>,
+) where
+    SpecializedMeshPipelines<S>: Resource,
+{
+    pipelines.evict_unused(&mut cache);
+}
+
+/// An error returned by [`SpecializedMeshPipeline::specialize`].
+///
+/// The `pipeline_type` of the errors is filled in with the type name of the
+/// [`SpecializedMeshPipeline`] by [`SpecializedMeshPipelines::specialize`], and the `key_bits`
+/// are the bits of the mesh pipeline key the pipeline was specialized for.
 #[derive(Error, Debug)]
 pub enum SpecializedMeshPipelineError {
     #[error(transparent)]
     MissingVertexAttribute(#[from] MissingVertexAttributeError),
+    /// The blend state of the pipeline can't be used in the phase the mesh is drawn in.
+    #[error(
+        "The {blend} blend state can't be used in the {phase} phase \
+        (pipeline type: {pipeline_type:?}, key bits: {key_bits:#x})"
+    )]
+    IncompatibleBlendPhase {
+        pipeline_type: Option<&'static str>,
+        blend: &'static str,
+        phase: &'static str,
+        key_bits: u64,
+    },
+    /// The pipeline has no shader for a stage that requires one.
+    #[error(
+        "Pipeline {label:?} has no {stage} shader \
+        (pipeline type: {pipeline_type:?}, key bits: {key_bits:#x})"
+    )]
+    MissingShader {
+        pipeline_type: Option<&'static str>,
+        label: Option<Cow<'static, str>>,
+        stage: &'static str,
+        key_bits: u64,
+    },
+    /// The pipeline uses more bind groups than the device supports.
+    #[error(
+        "Pipeline {label:?} uses {bind_groups} bind groups, but the device supports at most \
+        {max_bind_groups} (pipeline type: {pipeline_type:?}, key bits: {key_bits:#x})"
+    )]
+    BindGroupLimitExceeded {
+        pipeline_type: Option<&'static str>,
+        label: Option<Cow<'static, str>>,
+        bind_groups: usize,
+        max_bind_groups: u32,
+        key_bits: u64,
+    },
+}
+
+impl SpecializedMeshPipelineError {
+    fn set_pipeline_type(&mut self, type_name: &'static str) {
+        let pipeline_type = match self {
+            SpecializedMeshPipelineError::MissingVertexAttribute(err) => &mut err.pipeline_type,
+            SpecializedMeshPipelineError::IncompatibleBlendPhase { pipeline_type, .. }
+            | SpecializedMeshPipelineError::MissingShader { pipeline_type, .. }
+            | SpecializedMeshPipelineError::BindGroupLimitExceeded { pipeline_type, .. } => {
+                pipeline_type
+            }
+        };
+        *pipeline_type = Some(type_name);
+    }
 }
@@ -0,0 +1,156 @@
+use std::{
+    ops::Deref,
+    sync::{Arc, Mutex},
+};
+
+use wgpu::{BufferAsyncError, BufferDescriptor, BufferUsages, MapMode};
+
+use crate::{render_resource::Buffer, renderer::RenderDevice};
+
+/// Returns true if `frame` is more recent than `latest`, both being
+/// [`FrameCount`](bevy_core::FrameCount)s, which wrap around.
+///
+/// [`ReadbackBuffer`]s may finish mapping out of order, so use this to ignore
+/// results read back from frames older than the latest one received.
+#[inline]
+pub fn is_newer_frame(frame: u32, latest: u32) -> bool {
+    frame.wrapping_sub(latest) as i32 > 0
+}
+
+/// Where a [`ReadbackBuffer`] is in its cycle.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum ReadbackState {
+    /// The buffer can be copied into.
+    #[default]
+    Free,
+    /// A copy into the buffer was recorded this frame.
+    Copying,
+    /// The buffer is waiting to be mapped.
+    Mapping,
+}
+
+/// A buffer that GPU data is copied into so that it can be read back on the
+/// CPU without stalling the GPU.
+///
+/// Claim a free buffer with [`ReadbackBuffer::start_copy`] in the frame a copy
+/// into it is recorded, then call [`ReadbackBuffer::poll`] on every buffer
+/// once per frame, after the frame was submitted, e.g. in
+/// [`RenderSet::Cleanup`](crate::RenderSet::Cleanup). Mapping takes a few
+/// frames, so keep a few buffers per readback to copy into while the previous
+/// ones are being mapped.
+pub struct ReadbackBuffer {
+    buffer: Buffer,
+    state: ReadbackState,
+    map_result: Arc<Mutex<Option<Result<(), BufferAsyncError>>>>,
+}
+
+impl ReadbackBuffer {
+    /// Creates a free buffer of `size` bytes that can be copied into and mapped
+    /// for reading.
+    pub fn new(render_device: &RenderDevice, label: &'static str, size: u64) -> Self {
+        Self::from(render_device.create_buffer(&BufferDescriptor {
+            label: Some(label),
+            size,
+            usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        }))
+    }
+
+    /// Returns where the buffer is in its cycle.
+    #[inline]
+    pub fn state(&self) -> ReadbackState {
+        self.state
+    }
+
+    /// Returns true if the buffer can be copied into.
+    #[inline]
+    pub fn is_free(&self) -> bool {
+        self.state == ReadbackState::Free
+    }
+
+    /// Marks the buffer as copied into this frame, so that the next
+    /// [`ReadbackBuffer::poll`] starts mapping it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the buffer isn't free.
+    pub fn start_copy(&mut self) {
+        assert!(self.is_free(), "copied into a readback buffer in use");
+        self.state = ReadbackState::Copying;
+    }
+
+    /// Frees a buffer claimed with [`ReadbackBuffer::start_copy`] whose copy
+    /// ended up not being recorded, e.g. because a pipeline wasn't ready.
+    pub fn cancel_copy(&mut self) {
+        if self.state == ReadbackState::Copying {
+            self.state = ReadbackState::Free;
+        }
+    }
+
+    /// Starts mapping the buffer if it was copied into this frame, and returns
+    /// the result once the mapping finished, at which point the buffer is free
+    /// again.
+    ///
+    /// On success the buffer is mapped, and must be read with
+    /// [`ReadbackBuffer::read`] before it is copied into again.
+    pub fn poll(&mut self) -> Option<Result<(), BufferAsyncError>> {
+        match self.state {
+            ReadbackState::Free => None,
+            ReadbackState::Copying => {
+                let map_result = self.map_result.clone();
+                self.buffer
+                    .slice(..)
+                    .map_async(MapMode::Read, move |result| {
+                        *map_result.lock().unwrap() = Some(result);
+                    });
+                self.state = ReadbackState::Mapping;
+                None
+            }
+            ReadbackState::Mapping => {
+                let result = self.map_result.lock().unwrap().take()?;
+                self.state = ReadbackState::Free;
+                Some(result)
+            }
+        }
+    }
+
+    /// Calls `read` with the contents of the mapped buffer, then unmaps it.
+    pub fn read<R>(&self, read: impl FnOnce(&[u8]) -> R) -> R {
+        let result = read(&self.buffer.slice(..).get_mapped_range());
+        self.buffer.unmap();
+        result
+    }
+}
+
+impl From<Buffer> for ReadbackBuffer {
+    fn from(buffer: Buffer) -> Self {
+        Self {
+            buffer,
+            state: ReadbackState::Free,
+            map_result: Arc::default(),
+        }
+    }
+}
+
+impl Deref for ReadbackBuffer {
+    type Target = Buffer;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.buffer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_newer_frame;
+
+    #[test]
+    fn newer_frames_wrap_around() {
+        assert!(is_newer_frame(5, 4));
+        assert!(!is_newer_frame(4, 4));
+        assert!(!is_newer_frame(3, 4));
+        assert!(is_newer_frame(1, u32::MAX));
+        assert!(!is_newer_frame(u32::MAX, 1));
+    }
+}
@@ -190,7 +190,7 @@ impl<'a> From<&'a Shader> for naga_oil::compose::NagaModuleDescriptor<'a> {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Hash)]
 pub enum Source {
     Wgsl(Cow<'static, str>),
     Glsl(Cow<'static, str>, naga::ShaderStage),
@@ -315,6 +315,87 @@ impl ShaderImport {
 }
 
 /// A reference to a shader asset.
+/// Generates a WGSL module of constants from their values on the Rust side.
+///
+/// Shaders that hard-code a value also defined in Rust silently break when only one of them
+/// changes. Instead, build a module of the shared constants from the Rust values, add it to the
+/// [`Assets<Shader>`](bevy_asset::Assets) under a fixed handle, and import the constants from it in
+/// the shaders.
+///
+/// ```
+/// # use bevy_render::render_resource::ShaderConstants;
+/// const CLUSTER_COUNT_SIZE: u32 = 9;
+///
+/// let shader = ShaderConstants::new("my_crate::constants")
+///     .with_u32("CLUSTER_COUNT_SIZE", CLUSTER_COUNT_SIZE)
+///     .build();
+/// ```
+#[derive(Clone, Debug)]
+pub struct ShaderConstants {
+    import_path: String,
+    source: String,
+}
+
+impl ShaderConstants {
+    /// Starts a module of constants with the given `#define_import_path`.
+    pub fn new(import_path: impl Into<String>) -> Self {
+        let import_path = import_path.into();
+        let source = format!(
+            "#define_import_path {import_path}\n\n// Generated from the Rust constants, do not edit.\n\n"
+        );
+        Self {
+            import_path,
+            source,
+        }
+    }
+
+    /// Adds a `u32` constant.
+    pub fn with_u32(mut self, name: &str, value: u32) -> Self {
+        self.source += &format!("const {name}: u32 = {value}u;\n");
+        self
+    }
+
+    /// Adds an `i32` constant.
+    pub fn with_i32(mut self, name: &str, value: i32) -> Self {
+        if value == i32::MIN {
+            // `2147483648i` overflows before it's negated, so WGSL has no literal for it.
+            self.source += &format!("const {name}: i32 = i32(-2147483647i - 1i);\n");
+        } else {
+            self.source += &format!("const {name}: i32 = {value}i;\n");
+        }
+        self
+    }
+
+    /// Adds an `f32` constant.
+    ///
+    /// # Panics
+    ///
+    /// If `value` isn't finite, as WGSL has no literal for it.
+    pub fn with_f32(mut self, name: &str, value: f32) -> Self {
+        assert!(value.is_finite(), "WGSL constant {name} must be finite");
+        // The `Debug` formatting always has a fractional part or an exponent, and round-trips.
+        self.source += &format!("const {name}: f32 = {value:?};\n");
+        self
+    }
+
+    /// Adds a `bool` constant.
+    pub fn with_bool(mut self, name: &str, value: bool) -> Self {
+        self.source += &format!("const {name}: bool = {value};\n");
+        self
+    }
+
+    /// The generated WGSL source.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Builds the shader module, whose path is its import path.
+    pub fn build(self) -> Shader {
+        Shader::from_wgsl(self.source, self.import_path)
+    }
+}
+
+#[derive(Debug, Clone)]
 pub enum ShaderRef {
     /// Use the "default" shader for the current context.
     Default,
@@ -341,3 +422,86 @@ impl From<&'static str> for ShaderRef {
         Self::Path(AssetPath::from(path))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{ShaderConstants, ShaderImport, Source};
+
+    fn constants(source: &str) -> Vec<&str> {
+        source
+            .lines()
+            .filter(|line| line.starts_with("const "))
+            .collect()
+    }
+
+    #[test]
+    fn shader_constants_source() {
+        let constants_builder = ShaderConstants::new("test::constants")
+            .with_u32("A", 9)
+            .with_i32("B", -3)
+            .with_f32("C", 0.1)
+            .with_f32("D", 2.0)
+            .with_bool("E", true);
+
+        assert!(constants_builder
+            .source()
+            .starts_with("#define_import_path test::constants\n"));
+        assert_eq!(
+            constants(constants_builder.source()),
+            [
+                "const A: u32 = 9u;",
+                "const B: i32 = -3i;",
+                "const C: f32 = 0.1;",
+                "const D: f32 = 2.0;",
+                "const E: bool = true;",
+            ]
+        );
+    }
+
+    #[test]
+    fn shader_constants_i32_extremes() {
+        let constants_builder = ShaderConstants::new("test::constants")
+            .with_i32("MIN", i32::MIN)
+            .with_i32("MAX", i32::MAX);
+
+        assert_eq!(
+            constants(constants_builder.source()),
+            [
+                "const MIN: i32 = i32(-2147483647i - 1i);",
+                "const MAX: i32 = 2147483647i;",
+            ]
+        );
+    }
+
+    #[test]
+    fn shader_constants_f32_round_trips() {
+        let constants_builder = ShaderConstants::new("test::constants")
+            .with_f32("SMALL", 1e-7)
+            .with_f32("LARGE", 1e20);
+
+        for line in constants(constants_builder.source()) {
+            let literal = line.rsplit(' ').next().unwrap().trim_end_matches(';');
+            assert!(literal.parse::<f32>().is_ok(), "{literal}");
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn shader_constants_reject_non_finite_f32() {
+        let _ = ShaderConstants::new("test::constants").with_f32("NAN", f32::NAN);
+    }
+
+    #[test]
+    fn shader_constants_build() {
+        let shader = ShaderConstants::new("test::constants")
+            .with_u32("A", 1)
+            .build();
+
+        assert_eq!(
+            shader.import_path,
+            ShaderImport::Custom("test::constants".into())
+        );
+        assert!(matches!(shader.source, Source::Wgsl(_)));
+        assert!(shader.imports.is_empty());
+    }
+}
@@ -3,7 +3,7 @@ mod render_device;
 
 use bevy_derive::{Deref, DerefMut};
 use bevy_tasks::ComputeTaskPool;
-use bevy_utils::tracing::{error, info, info_span};
+use bevy_utils::tracing::{error, info, info_span, warn};
 pub use graph_runner::*;
 pub use render_device::*;
 
@@ -12,7 +12,7 @@ use crate::{
     render_graph::RenderGraph,
     render_phase::TrackedRenderPass,
     render_resource::RenderPassDescriptor,
-    settings::{WgpuSettings, WgpuSettingsPriority},
+    settings::{RenderBackendPreference, WgpuSettings, WgpuSettingsPriority},
     view::{ExtractedWindows, ViewTarget},
 };
 use bevy_ecs::{prelude::*, system::SystemState};
@@ -45,9 +45,14 @@ pub fn render_system(world: &mut World, state: &mut SystemState<Query<Entity, Wi
         world,
         |encoder| {
             crate::view::screenshot::submit_screenshot_commands(world, encoder);
+            crate::camera::submit_external_texture_export_commands(world, encoder);
+            crate::diagnostic::resolve_draw_timings(world, encoder);
         },
     );
 
+    crate::camera::notify_external_texture_exports(world);
+    crate::diagnostic::map_draw_timings(world);
+
     match res {
         Ok(Some(diagnostics_recorder)) => {
             world.insert_resource(diagnostics_recorder);
@@ -103,6 +108,14 @@ pub fn render_system(world: &mut World, state: &mut SystemState<Query<Entity, Wi
 
     crate::view::screenshot::collect_screenshots(world);
 
+    send_time(world);
+}
+
+/// Sends the current time to the app world.
+///
+/// This is done by [`render_system`] at the end of every rendered frame, and
+/// must be done on frames that skip rendering too.
+pub(crate) fn send_time(world: &mut World) {
     // update the time and send it to the app world
     let time_sender = world.resource::<TimeSender>();
     if let Err(error) = time_sender.0.try_send(Instant::now()) {
@@ -172,6 +185,33 @@ const GPU_NOT_FOUND_ERROR_MESSAGE: &str = if cfg!(target_os = "linux") {
     "Unable to find a GPU! Make sure you have installed required drivers!"
 };
 
+/// Requests an adapter, using a software rasterizer if allowed by the [`RenderBackendPreference`].
+async fn request_adapter(
+    instance: &Instance,
+    backend_preference: RenderBackendPreference,
+    request_adapter_options: &RequestAdapterOptions<'_, '_>,
+) -> Option<Adapter> {
+    let software_adapter_options = RequestAdapterOptions {
+        force_fallback_adapter: true,
+        ..request_adapter_options.clone()
+    };
+    match backend_preference {
+        RenderBackendPreference::Hardware => {
+            instance.request_adapter(request_adapter_options).await
+        }
+        RenderBackendPreference::SoftwareFallback => {
+            if let Some(adapter) = instance.request_adapter(request_adapter_options).await {
+                return Some(adapter);
+            }
+            warn!("Unable to find a GPU, falling back to a software rasterizer");
+            instance.request_adapter(&software_adapter_options).await
+        }
+        RenderBackendPreference::Software => {
+            instance.request_adapter(&software_adapter_options).await
+        }
+    }
+}
+
 /// Initializes the renderer by retrieving and preparing the GPU instance, device and queue
 /// for the specified backend.
 pub async fn initialize_renderer(
@@ -179,13 +219,19 @@ pub async fn initialize_renderer(
     options: &WgpuSettings,
     request_adapter_options: &RequestAdapterOptions<'_, '_>,
 ) -> (RenderDevice, RenderQueue, RenderAdapterInfo, RenderAdapter) {
-    let adapter = instance
-        .request_adapter(request_adapter_options)
-        .await
-        .expect(GPU_NOT_FOUND_ERROR_MESSAGE);
+    let adapter = request_adapter(
+        instance,
+        options.backend_preference,
+        request_adapter_options,
+    )
+    .await
+    .expect(GPU_NOT_FOUND_ERROR_MESSAGE);
 
     let adapter_info = adapter.get_info();
     info!("{:?}", adapter_info);
+    if adapter_info.device_type == wgpu::DeviceType::Cpu {
+        info!("Rendering with a software rasterizer, performance will be limited");
+    }
 
     #[cfg(feature = "wgpu_trace")]
     let trace_path = {
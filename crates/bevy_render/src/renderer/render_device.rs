@@ -107,12 +107,12 @@ impl RenderDevice {
         label: impl Into<wgpu::Label<'a>>,
         entries: &'a [BindGroupLayoutEntry],
     ) -> BindGroupLayout {
-        BindGroupLayout::from(
+        let label = label.into();
+        BindGroupLayout::with_content_hash(
             self.device
-                .create_bind_group_layout(&BindGroupLayoutDescriptor {
-                    label: label.into(),
-                    entries,
-                }),
+                .create_bind_group_layout(&BindGroupLayoutDescriptor { label, entries }),
+            label,
+            entries,
         )
     }
 
@@ -1,7 +1,7 @@
 use crate::renderer::{
-    RenderAdapter, RenderAdapterInfo, RenderDevice, RenderInstance, RenderQueue,
+    RenderAdapter, RenderAdapterInfo, RenderDevice, RenderInstance, RenderQueue, WgpuWrapper,
 };
-use std::borrow::Cow;
+use std::{borrow::Cow, sync::Arc};
 
 pub use wgpu::{
     Backends, Dx12Compiler, Features as WgpuFeatures, Gles3MinorVersion, InstanceFlags,
@@ -50,6 +50,8 @@ pub struct WgpuSettings {
     pub gles3_minor_version: Gles3MinorVersion,
     /// These are for controlling WGPU's debug information to eg. enable validation and shader debug info in release builds.
     pub instance_flags: InstanceFlags,
+    /// Whether a software rasterizer may be used instead of a GPU.
+    pub backend_preference: RenderBackendPreference,
 }
 
 impl Default for WgpuSettings {
@@ -101,6 +103,8 @@ impl Default for WgpuSettings {
 
         let instance_flags = InstanceFlags::default().with_env();
 
+        let backend_preference = render_backend_preference_from_env().unwrap_or_default();
+
         Self {
             device_label: Default::default(),
             backends,
@@ -113,10 +117,46 @@ impl Default for WgpuSettings {
             dx12_shader_compiler: dx12_compiler,
             gles3_minor_version,
             instance_flags,
+            backend_preference,
         }
     }
 }
 
+/// Whether the renderer may run on a software rasterizer, such as lavapipe/llvmpipe,
+/// `SwiftShader` or WARP, instead of a GPU.
+///
+/// Software rasterizers are much slower than GPUs, but they let the renderer run headlessly on CI
+/// machines and servers without one, for example to compare rendered frames against golden images.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RenderBackendPreference {
+    /// Use the adapter that best matches the [`PowerPreference`]. Renderer initialization fails if
+    /// no adapter is found.
+    #[default]
+    Hardware,
+    /// Prefer a GPU, but fall back to a software rasterizer if none is found.
+    SoftwareFallback,
+    /// Always use a software rasterizer, even if a GPU is available. This makes the output
+    /// independent of the GPU and driver of the machine running the app.
+    Software,
+}
+
+/// Get a [`RenderBackendPreference`] from the environment variable `BEVY_RENDER_BACKEND`, which can
+/// be `hardware`, `software_fallback` or `software`.
+pub fn render_backend_preference_from_env() -> Option<RenderBackendPreference> {
+    Some(
+        match std::env::var("BEVY_RENDER_BACKEND")
+            .as_deref()
+            .map(str::to_lowercase)
+            .as_deref()
+        {
+            Ok("hardware") => RenderBackendPreference::Hardware,
+            Ok("software_fallback") => RenderBackendPreference::SoftwareFallback,
+            Ok("software") => RenderBackendPreference::Software,
+            _ => return None,
+        },
+    )
+}
+
 /// An enum describing how the renderer will initialize resources. This is used when creating the [`RenderPlugin`](crate::RenderPlugin).
 pub enum RenderCreation {
     /// Allows renderer resource initialization to happen outside of the rendering plugin.
@@ -142,6 +182,29 @@ impl RenderCreation {
     ) -> Self {
         Self::Manual(device, queue, adapter_info, adapter, instance)
     }
+
+    /// Creates a [`RenderCreation::Manual`] variant from `wgpu` objects that were created
+    /// outside of Bevy, for example by an engine or editor that Bevy's rendering is embedded in.
+    ///
+    /// The device must have been requested with the features and limits Bevy's renderer needs,
+    /// see [`WgpuSettings`]. To render into textures owned by the host, such as the current
+    /// texture of a surface it manages, target them with a
+    /// [`ManualTextureView`](crate::camera::ManualTextureView).
+    pub fn from_wgpu(
+        instance: wgpu::Instance,
+        adapter: wgpu::Adapter,
+        device: wgpu::Device,
+        queue: wgpu::Queue,
+    ) -> Self {
+        let adapter_info = adapter.get_info();
+        Self::Manual(
+            RenderDevice::from(device),
+            RenderQueue(Arc::new(WgpuWrapper::new(queue))),
+            RenderAdapterInfo(WgpuWrapper::new(adapter_info)),
+            RenderAdapter(Arc::new(WgpuWrapper::new(adapter))),
+            RenderInstance(Arc::new(WgpuWrapper::new(instance))),
+        )
+    }
 }
 
 impl Default for RenderCreation {
@@ -0,0 +1,260 @@
+use bevy_asset::Handle;
+use bevy_ecs::{prelude::*, world::FromWorld};
+
+use crate::{
+    render_resource::*,
+    renderer::{RenderDevice, RenderQueue},
+};
+
+pub const BLUE_NOISE_SHADER_HANDLE: Handle<Shader> =
+    Handle::weak_from_u128(121177810900287981646646191878542324036);
+
+/// The width and height of the [`BlueNoise`] texture, in texels.
+///
+/// Shaders tile the texture across the screen, so this must match
+/// `BLUE_NOISE_SIZE` in `bevy_render::blue_noise`.
+pub const BLUE_NOISE_SIZE: u32 = 64;
+
+/// A [`RenderApp`](crate::RenderApp) resource that contains a tileable blue
+/// noise texture, for shaders that need a well distributed random value per
+/// pixel.
+///
+/// Each of the two channels of the [`TextureFormat::Rg8Unorm`] texture holds
+/// an independent blue noise pattern, where every value in `[0, 1)` appears
+/// equally often. Compared to white noise or interleaved gradient noise, the
+/// error of blue noise is pushed to high frequencies, so it's less visible and
+/// is removed more effectively by blurs and temporal anti-aliasing.
+///
+/// Use the helpers of the `bevy_render::blue_noise` shader module to load and
+/// animate the noise. They offset the values by the golden ratio every frame,
+/// which keeps each frame blue noise in space while each pixel follows a low
+/// discrepancy sequence over time.
+#[derive(Resource)]
+pub struct BlueNoise {
+    pub texture: Texture,
+    pub texture_view: TextureView,
+}
+
+impl FromWorld for BlueNoise {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+        let render_queue = world.resource::<RenderQueue>();
+
+        let texture = render_device.create_texture_with_data(
+            render_queue,
+            &TextureDescriptor {
+                label: Some("blue_noise_texture"),
+                size: Extent3d {
+                    width: BLUE_NOISE_SIZE,
+                    height: BLUE_NOISE_SIZE,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: TextureFormat::Rg8Unorm,
+                usage: TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            },
+            TextureDataOrder::default(),
+            BLUE_NOISE_DATA,
+        );
+        let texture_view = texture.create_view(&TextureViewDescriptor::default());
+
+        Self {
+            texture,
+            texture_view,
+        }
+    }
+}
+
+/// The texels of the [`BlueNoise`] texture, made by [`generate_blue_noise`]
+/// with [`BLUE_NOISE_SEEDS`] for each channel.
+const BLUE_NOISE_DATA: &[u8] = include_bytes!("blue_noise.bin");
+
+#[cfg_attr(not(test), allow(dead_code))]
+const BLUE_NOISE_SEEDS: [u32; 2] = [0x2545_f491, 0x9e37_79b9];
+
+/// Generates a `size` by `size` tileable blue noise pattern with the
+/// void-and-cluster method, returning one byte per texel in row-major order.
+///
+/// See: Robert Ulichney, "The void-and-cluster method for dither array generation", 1993.
+#[cfg_attr(not(test), allow(dead_code))]
+fn generate_blue_noise(size: usize, seed: u32) -> Vec<u8> {
+    let texel_count = size * size;
+    let mut energy = EnergyField::new(size);
+    let mut pattern = vec![false; texel_count];
+    let mut rng = seed;
+
+    // Start from random points covering a tenth of the texels.
+    let initial_count = texel_count / 10;
+    let mut count = 0;
+    while count < initial_count {
+        rng ^= rng << 13;
+        rng ^= rng >> 17;
+        rng ^= rng << 5;
+        let index = rng as usize % texel_count;
+        if !pattern[index] {
+            pattern[index] = true;
+            energy.splat(index, 1.0);
+            count += 1;
+        }
+    }
+
+    // Move the point of the tightest cluster into the largest void until they
+    // coincide, which spreads the initial points evenly.
+    for _ in 0..texel_count {
+        let cluster = energy.tightest_cluster(&pattern);
+        pattern[cluster] = false;
+        energy.splat(cluster, -1.0);
+        let void = energy.largest_void(&pattern);
+        pattern[void] = true;
+        energy.splat(void, 1.0);
+        if void == cluster {
+            break;
+        }
+    }
+
+    let mut ranks = vec![0; texel_count];
+
+    // Rank the initial points by removing the tightest clusters first.
+    let mut initial_pattern = pattern.clone();
+    let mut initial_energy = energy.clone();
+    for rank in (0..initial_count).rev() {
+        let cluster = initial_energy.tightest_cluster(&initial_pattern);
+        initial_pattern[cluster] = false;
+        initial_energy.splat(cluster, -1.0);
+        ranks[cluster] = rank;
+    }
+
+    // Rank the remaining texels by filling the largest voids first.
+    for rank in initial_count..texel_count {
+        let void = energy.largest_void(&pattern);
+        pattern[void] = true;
+        energy.splat(void, 1.0);
+        ranks[void] = rank;
+    }
+
+    ranks
+        .into_iter()
+        .map(|rank| (rank * 256 / texel_count) as u8)
+        .collect()
+}
+
+/// The sum of a gaussian centered on every point of a pattern, wrapping
+/// around the edges so that the resulting noise tiles.
+#[derive(Clone)]
+struct EnergyField {
+    size: usize,
+    energy: Vec<f32>,
+    kernel: Vec<f32>,
+}
+
+impl EnergyField {
+    const SIGMA: f32 = 1.5;
+    const RADIUS: isize = 6;
+    const WIDTH: usize = 2 * Self::RADIUS as usize + 1;
+
+    fn new(size: usize) -> Self {
+        let kernel = (-Self::RADIUS..=Self::RADIUS)
+            .flat_map(|y| (-Self::RADIUS..=Self::RADIUS).map(move |x| (x * x + y * y) as f32))
+            .map(|distance_squared| (-distance_squared / (2.0 * Self::SIGMA * Self::SIGMA)).exp())
+            .collect();
+        Self {
+            size,
+            energy: vec![0.0; size * size],
+            kernel,
+        }
+    }
+
+    /// Adds the gaussian of the point at `index`, scaled by `sign`.
+    fn splat(&mut self, index: usize, sign: f32) {
+        let size = self.size as isize;
+        let (x, y) = ((index % self.size) as isize, (index / self.size) as isize);
+        for (kernel_index, weight) in self.kernel.iter().enumerate() {
+            let dx = (kernel_index % Self::WIDTH) as isize - Self::RADIUS;
+            let dy = (kernel_index / Self::WIDTH) as isize - Self::RADIUS;
+            let target = (y + dy).rem_euclid(size) * size + (x + dx).rem_euclid(size);
+            self.energy[target as usize] += sign * weight;
+        }
+    }
+
+    /// Returns the point of the pattern with the most energy.
+    fn tightest_cluster(&self, pattern: &[bool]) -> usize {
+        self.extremum(pattern, true, |energy, best| energy > best)
+    }
+
+    /// Returns the empty texel of the pattern with the least energy.
+    fn largest_void(&self, pattern: &[bool]) -> usize {
+        self.extremum(pattern, false, |energy, best| energy < best)
+    }
+
+    fn extremum(
+        &self,
+        pattern: &[bool],
+        value: bool,
+        is_better: impl Fn(f32, f32) -> bool,
+    ) -> usize {
+        let mut best = None;
+        for (index, (&energy, &texel)) in self.energy.iter().zip(pattern).enumerate() {
+            if texel != value {
+                continue;
+            }
+            if let Some((_, best_energy)) = best {
+                if !is_better(energy, best_energy) {
+                    continue;
+                }
+            }
+            best = Some((index, energy));
+        }
+        best.expect("the pattern has no texel to pick").0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{generate_blue_noise, BLUE_NOISE_DATA, BLUE_NOISE_SEEDS, BLUE_NOISE_SIZE};
+
+    #[test]
+    fn blue_noise_is_uniform() {
+        let size = 32;
+        let noise = generate_blue_noise(size, 1);
+        assert_eq!(noise.len(), size * size);
+
+        // Every value appears the same number of times.
+        let mut histogram = [0; 256];
+        for value in &noise {
+            histogram[*value as usize] += 1;
+        }
+        assert!(histogram.iter().all(|&count| count == size * size / 256));
+
+        // Unlike white noise, neighboring texels are rarely close in value.
+        let close_neighbors = (0..size * size)
+            .filter(|&index| {
+                let right = (index / size) * size + (index + 1) % size;
+                noise[index].abs_diff(noise[right]) < 16
+            })
+            .count();
+        assert!(close_neighbors < size * size / 16);
+    }
+
+    #[test]
+    fn blue_noise_data_is_up_to_date() {
+        let channels =
+            BLUE_NOISE_SEEDS.map(|seed| generate_blue_noise(BLUE_NOISE_SIZE as usize, seed));
+        let data: Vec<u8> = (0..channels[0].len())
+            .flat_map(|index| [channels[0][index], channels[1][index]])
+            .collect();
+        if std::env::var_os("BEVY_UPDATE_BLUE_NOISE").is_some() {
+            std::fs::write(
+                concat!(env!("CARGO_MANIFEST_DIR"), "/src/texture/blue_noise.bin"),
+                &data,
+            )
+            .unwrap();
+        }
+        assert!(
+            data == BLUE_NOISE_DATA,
+            "run this test with `BEVY_UPDATE_BLUE_NOISE=1` to regenerate the blue noise texture"
+        );
+    }
+}
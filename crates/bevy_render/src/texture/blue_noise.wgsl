@@ -0,0 +1,25 @@
+#define_import_path bevy_render::blue_noise
+
+// The width and height of the blue noise texture, in texels.
+const BLUE_NOISE_SIZE: u32 = 64u;
+
+// 1 / golden ratio, the most irrational step for a low discrepancy sequence.
+const GOLDEN_RATIO_CONJUGATE: f32 = 0.6180339887498949;
+
+// Loads the two independent blue noise values of `pixel`, in [0, 1), tiling
+// the texture across the screen.
+fn blue_noise_load(blue_noise_texture: texture_2d<f32>, pixel: vec2<u32>) -> vec2<f32> {
+    return textureLoad(blue_noise_texture, pixel % BLUE_NOISE_SIZE, 0).rg;
+}
+
+// Offsets blue noise values by the golden ratio every frame, so that every
+// frame is blue noise in space and each pixel follows a low discrepancy
+// sequence over time, which converges quickly under temporal accumulation.
+fn blue_noise_animate(noise: vec2<f32>, frame: u32) -> vec2<f32> {
+    return fract(noise + f32(frame % 1024u) * GOLDEN_RATIO_CONJUGATE);
+}
+
+// Returns the blue noise values of `pixel` for `frame`, in [0, 1).
+fn blue_noise(blue_noise_texture: texture_2d<f32>, pixel: vec2<u32>, frame: u32) -> vec2<f32> {
+    return blue_noise_animate(blue_noise_load(blue_noise_texture, pixel), frame);
+}
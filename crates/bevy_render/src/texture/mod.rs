@@ -1,5 +1,6 @@
 #[cfg(feature = "basis-universal")]
 mod basis;
+mod blue_noise;
 #[cfg(feature = "basis-universal")]
 mod compressed_image_saver;
 #[cfg(feature = "dds")]
@@ -22,6 +23,7 @@ pub(crate) mod image_texture_conversion;
 pub use self::image::*;
 #[cfg(feature = "ktx2")]
 pub use self::ktx2::*;
+pub use blue_noise::*;
 #[cfg(feature = "dds")]
 pub use dds::*;
 #[cfg(feature = "exr")]
@@ -37,10 +39,11 @@ pub use texture_attachment::*;
 pub use texture_cache::*;
 
 use crate::{
-    render_asset::RenderAssetPlugin, renderer::RenderDevice, Render, RenderApp, RenderSet,
+    render_asset::RenderAssetPlugin, render_resource::Shader, renderer::RenderDevice, Render,
+    RenderApp, RenderSet,
 };
 use bevy_app::{App, Plugin};
-use bevy_asset::{AssetApp, Assets, Handle};
+use bevy_asset::{load_internal_asset, AssetApp, Assets, Handle};
 use bevy_ecs::prelude::*;
 
 // TODO: replace Texture names with Image names?
@@ -84,6 +87,13 @@ impl Plugin for ImagePlugin {
             app.init_asset_loader::<HdrTextureLoader>();
         }
 
+        load_internal_asset!(
+            app,
+            BLUE_NOISE_SHADER_HANDLE,
+            "blue_noise.wgsl",
+            Shader::from_wgsl
+        );
+
         app.add_plugins(RenderAssetPlugin::<GpuImage>::default())
             .register_type::<Image>()
             .init_asset::<Image>()
@@ -151,7 +161,8 @@ impl Plugin for ImagePlugin {
                 .init_resource::<FallbackImage>()
                 .init_resource::<FallbackImageZero>()
                 .init_resource::<FallbackImageCubemap>()
-                .init_resource::<FallbackImageFormatMsaaCache>();
+                .init_resource::<FallbackImageFormatMsaaCache>()
+                .init_resource::<BlueNoise>();
         }
     }
 }
@@ -81,10 +81,12 @@ impl ColorAttachment {
     }
 }
 
-/// A wrapper for a [`TextureView`] that is used as a depth-only [`RenderPassDepthStencilAttachment`].
+/// A wrapper for a [`TextureView`] that is used as a depth, and optionally stencil,
+/// [`RenderPassDepthStencilAttachment`].
 pub struct DepthAttachment {
     pub view: TextureView,
     clear_value: Option<f32>,
+    stencil: bool,
     is_first_call: Arc<AtomicBool>,
 }
 
@@ -93,10 +95,18 @@ impl DepthAttachment {
         Self {
             view,
             clear_value,
+            stencil: false,
             is_first_call: Arc::new(AtomicBool::new(clear_value.is_some())),
         }
     }
 
+    /// Also attaches the stencil aspect of the texture view, which must have a depth-stencil
+    /// format. The stencil is cleared to `0` whenever the depth is cleared, and loaded otherwise.
+    pub fn with_stencil(mut self, stencil: bool) -> Self {
+        self.stencil = stencil;
+        self
+    }
+
     /// Get this texture view as an attachment. The attachment will be cleared with a value of
     /// `clear_value` if this is the first time calling this function with `store` == [`StoreOp::Store`],
     /// and a clear value was provided, otherwise it will be loaded.
@@ -116,7 +126,14 @@ impl DepthAttachment {
                 },
                 store,
             }),
-            stencil_ops: None,
+            stencil_ops: self.stencil.then_some(Operations {
+                load: if first_call {
+                    LoadOp::Clear(0)
+                } else {
+                    LoadOp::Load
+                },
+                store,
+            }),
         }
     }
 }
@@ -2,7 +2,7 @@ use crate::{
     render_resource::{Texture, TextureView},
     renderer::RenderDevice,
 };
-use bevy_ecs::{prelude::ResMut, system::Resource};
+use bevy_ecs::{entity::Entity, prelude::ResMut, system::Resource};
 use bevy_utils::{Entry, HashMap};
 use wgpu::{TextureDescriptor, TextureViewDescriptor};
 
@@ -13,6 +13,9 @@ struct CachedTextureMeta {
     default_view: TextureView,
     taken: bool,
     frames_since_last_use: usize,
+    /// The view that took this texture last, if it was taken with
+    /// [`TextureCache::get_for_view`].
+    view: Option<Entity>,
 }
 
 /// A cached GPU [`Texture`] with corresponding [`TextureView`].
@@ -38,6 +41,29 @@ impl TextureCache {
         &mut self,
         render_device: &RenderDevice,
         descriptor: TextureDescriptor<'static>,
+    ) -> CachedTexture {
+        self.take(render_device, descriptor, None)
+    }
+
+    /// Like [`Self::get`], for a texture sized after the `view`, such as its
+    /// main textures or its depth texture.
+    ///
+    /// The texture is released early when the view is resized, see
+    /// [`Self::remove_unused`].
+    pub fn get_for_view(
+        &mut self,
+        render_device: &RenderDevice,
+        view: Entity,
+        descriptor: TextureDescriptor<'static>,
+    ) -> CachedTexture {
+        self.take(render_device, descriptor, Some(view))
+    }
+
+    fn take(
+        &mut self,
+        render_device: &RenderDevice,
+        descriptor: TextureDescriptor<'static>,
+        view: Option<Entity>,
     ) -> CachedTexture {
         match self.textures.entry(descriptor) {
             Entry::Occupied(mut entry) => {
@@ -45,6 +71,7 @@ impl TextureCache {
                     if !texture.taken {
                         texture.frames_since_last_use = 0;
                         texture.taken = true;
+                        texture.view = view;
                         return CachedTexture {
                             texture: texture.texture.clone(),
                             default_view: texture.default_view.clone(),
@@ -59,6 +86,7 @@ impl TextureCache {
                     default_view: default_view.clone(),
                     frames_since_last_use: 0,
                     taken: true,
+                    view,
                 });
                 CachedTexture {
                     texture,
@@ -73,6 +101,7 @@ impl TextureCache {
                     default_view: default_view.clone(),
                     taken: true,
                     frames_since_last_use: 0,
+                    view,
                 }]);
                 CachedTexture {
                     texture,
@@ -82,6 +111,25 @@ impl TextureCache {
         }
     }
 
+    /// Drops the textures that aren't taken this frame, were last taken with
+    /// [`Self::get_for_view`], and for which the `predicate` returns `true`
+    /// given their view and descriptor, instead of waiting for them to go
+    /// unused for a few frames.
+    ///
+    /// This is used to free the textures of views that were resized, see
+    /// [`ViewTargetsResized`](crate::view::ViewTargetsResized).
+    pub fn remove_unused(
+        &mut self,
+        mut predicate: impl FnMut(Entity, &TextureDescriptor<'static>) -> bool,
+    ) {
+        self.textures.retain(|descriptor, textures| {
+            textures.retain(|texture| {
+                texture.taken || !texture.view.is_some_and(|view| predicate(view, descriptor))
+            });
+            !textures.is_empty()
+        });
+    }
+
     /// Updates the cache and only retains recently used textures.
     pub fn update(&mut self) {
         for textures in self.textures.values_mut() {
@@ -0,0 +1,121 @@
+#define_import_path bevy_render::utils
+
+// -----------------
+// HASHING & NOISE -
+// -----------------
+
+// Hashes a u32 into a u32 with no visible correlation to its input.
+//
+// https://www.pcg-random.org
+// https://www.reedbeta.com/blog/hash-functions-for-gpu-rendering
+fn pcg_hash(input: u32) -> u32 {
+    let state = input * 747796405u + 2891336453u;
+    let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+// Hashes two u32s, e.g. integer pixel or cell coordinates, into a u32.
+fn pcg_hash_2d(input: vec2<u32>) -> u32 {
+    return pcg_hash(input.x ^ pcg_hash(input.y));
+}
+
+// Maps a u32 hash to a f32 in range [0, 1.0).
+fn hash_to_unit_f32(hash: u32) -> f32 {
+    // Keep the high 24 bits, which is all a f32 can represent exactly.
+    return f32(hash >> 8u) * (1.0 / 16777216.0);
+}
+
+// Smooth 2D value noise in range [0, 1.0], with one random value per integer
+// lattice cell of `position`, interpolated with a smoothstep.
+fn value_noise_2d(position: vec2<f32>) -> f32 {
+    let cell = floor(position);
+    let f = position - cell;
+    let c = bitcast<vec2<u32>>(vec2<i32>(cell));
+
+    let v00 = hash_to_unit_f32(pcg_hash_2d(c));
+    let v10 = hash_to_unit_f32(pcg_hash_2d(c + vec2(1u, 0u)));
+    let v01 = hash_to_unit_f32(pcg_hash_2d(c + vec2(0u, 1u)));
+    let v11 = hash_to_unit_f32(pcg_hash_2d(c + vec2(1u, 1u)));
+
+    let t = f * f * (3.0 - 2.0 * f);
+    return mix(mix(v00, v10, t.x), mix(v01, v11, t.x), t.y);
+}
+
+// https://blog.demofox.org/2022/01/01/interleaved-gradient-noise-a-different-kind-of-low-discrepancy-sequence
+fn interleaved_gradient_noise(pixel_coordinates: vec2<f32>, frame: u32) -> f32 {
+    let xy = pixel_coordinates + 5.588238 * f32(frame % 64u);
+    return fract(52.9829189 * fract(0.06711056 * xy.x + 0.00583715 * xy.y));
+}
+
+// -----------------
+// OCTAHEDRAL ------
+// -----------------
+
+// https://jcgt.org/published/0003/02/01/paper.pdf
+
+// For encoding normals or unit direction vectors as octahedral coordinates.
+fn octahedral_encode(v: vec3<f32>) -> vec2<f32> {
+    var n = v / (abs(v.x) + abs(v.y) + abs(v.z));
+    let octahedral_wrap = (1.0 - abs(n.yx)) * select(vec2(-1.0), vec2(1.0), n.xy > vec2f(0.0));
+    let n_xy = select(octahedral_wrap, n.xy, n.z >= 0.0);
+    return n_xy * 0.5 + 0.5;
+}
+
+// For decoding normals or unit direction vectors from octahedral coordinates.
+fn octahedral_decode(v: vec2<f32>) -> vec3<f32> {
+    let f = v * 2.0 - 1.0;
+    var n = vec3(f.xy, 1.0 - abs(f.x) - abs(f.y));
+    let t = saturate(-n.z);
+    let w = select(vec2(t), vec2(-t), n.xy >= vec2(0.0));
+    n = vec3(n.xy + w, n.z);
+    return normalize(n);
+}
+
+// -----------------
+// COLOR SPACES ----
+// -----------------
+
+// Converts a nonlinear sRGB color to linear sRGB, with the exact piecewise
+// transfer function rather than a 2.2 gamma.
+//
+// https://en.wikipedia.org/wiki/SRGB#From_sRGB_to_CIE_XYZ
+fn srgb_to_linear(color: vec3<f32>) -> vec3<f32> {
+    let low = color / 12.92;
+    let high = pow((color + 0.055) / 1.055, vec3(2.4));
+    return select(high, low, color <= vec3(0.04045));
+}
+
+// Converts a linear sRGB color to nonlinear sRGB, with the exact piecewise
+// transfer function rather than a 2.2 gamma.
+//
+// https://en.wikipedia.org/wiki/SRGB#From_CIE_XYZ_to_sRGB
+fn linear_to_srgb(color: vec3<f32>) -> vec3<f32> {
+    let low = color * 12.92;
+    let high = 1.055 * pow(color, vec3(1.0 / 2.4)) - 0.055;
+    return select(high, low, color <= vec3(0.0031308));
+}
+
+// -----------------
+// DEPTH -----------
+// -----------------
+
+// Converts the NDC depth of a reverse-Z infinite perspective projection, which
+// is what Bevy's perspective cameras use, to linear view z.
+// Note: Depth values in front of the camera will be negative as -z is forward.
+fn perspective_depth_ndc_to_view_z(ndc_depth: f32, near: f32) -> f32 {
+    return -near / ndc_depth;
+}
+
+// Converts NDC depth to linear view z for any projection, given its inverse.
+// Note: Depth values in front of the camera will be negative as -z is forward.
+fn depth_ndc_to_view_z(ndc_depth: f32, inverse_projection: mat4x4<f32>) -> f32 {
+    let view_pos = inverse_projection * vec4(0.0, 0.0, ndc_depth, 1.0);
+    return view_pos.z / view_pos.w;
+}
+
+// Converts linear view z to NDC depth for any projection.
+// Note: View z input should be negative for values in front of the camera as -z is forward.
+fn view_z_to_depth_ndc(view_z: f32, projection: mat4x4<f32>) -> f32 {
+    let ndc_pos = projection * vec4(0.0, 0.0, view_z, 1.0);
+    return ndc_pos.z / ndc_pos.w;
+}
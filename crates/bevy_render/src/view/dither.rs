@@ -0,0 +1,69 @@
+use bevy_ecs::prelude::*;
+use bevy_reflect::{std_traits::ReflectDefault, Reflect};
+
+use crate::{camera::Camera, extract_component::ExtractComponent};
+
+/// Configures the dithering that a camera applies to hide color banding, when
+/// dithering is enabled with its `DebandDither` component.
+///
+/// Dithering adds a small offset to each pixel before it's written to an 8-bit
+/// texture, so that smooth gradients quantize to a mix of the neighboring
+/// values instead of visible bands. It's applied by the tonemapping pass, and
+/// by the shaders that write to an 8-bit main texture directly, such as meshes
+/// and sprites drawn by a camera without HDR.
+///
+/// Cameras without this component use [`DitherSettings::default`].
+#[derive(Component, ExtractComponent, Reflect, Clone, Copy, Debug, PartialEq)]
+#[extract_component_filter(With<Camera>)]
+#[reflect(Component, Default, PartialEq)]
+pub struct DitherSettings {
+    /// The noise the dither offsets are taken from.
+    pub pattern: DitherPattern,
+    /// Scales the dither offsets.
+    ///
+    /// At `1.0`, the offsets span about one step of an 8-bit channel, which
+    /// is just enough to break up the bands. Raise it if banding remains
+    /// after later passes, such as when the image is displayed with a lower
+    /// bit depth. `0.0` disables dithering.
+    pub strength: f32,
+}
+
+impl Default for DitherSettings {
+    fn default() -> Self {
+        Self {
+            pattern: DitherPattern::default(),
+            strength: 1.0,
+        }
+    }
+}
+
+/// The noise pattern of [`DitherSettings`].
+#[derive(Reflect, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[reflect(Default, PartialEq, Hash)]
+pub enum DitherPattern {
+    /// Triangular noise made from the blue noise texture of the renderer.
+    ///
+    /// It has no visible structure, and the least noticeable grain.
+    #[default]
+    BlueNoise,
+    /// A 4x4 Bayer matrix.
+    ///
+    /// The grain is regular, which can suit pixel art or a retro look.
+    Ordered,
+    /// Noise from a hash of the screen coordinates.
+    ///
+    /// It's the cheapest pattern, but its grain forms faint diagonal lines.
+    ScreenSpaceHash,
+}
+
+impl DitherPattern {
+    /// The value of the pattern in the view uniform, matching the
+    /// `DITHER_PATTERN_*` constants of `bevy_render::view`.
+    pub fn shader_index(self) -> u32 {
+        match self {
+            DitherPattern::BlueNoise => 0,
+            DitherPattern::Ordered => 1,
+            DitherPattern::ScreenSpaceHash => 2,
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use bevy_reflect::{std_traits::ReflectDefault, Reflect};
+use bevy_utils::warn_once;
+use wgpu::{Features, TextureFormat, TextureFormatFeatureFlags, TextureUsages};
+
+use crate::{
+    renderer::{RenderAdapter, RenderDevice},
+    view::Msaa,
+};
+
+/// The format of the intermediate texture that a camera with [`Camera::hdr`] enabled renders
+/// to, set with [`Camera::hdr_format`].
+///
+/// Smaller formats save memory and bandwidth, which matters on mobile and other memory
+/// constrained platforms, at the cost of precision or range.
+///
+/// Formats that the device can't render to, blend or multisample with the current [`Msaa`]
+/// fall back to [`HdrFormat::Rgba16Float`], with a warning.
+///
+/// [`Camera::hdr`]: crate::camera::Camera::hdr
+/// [`Camera::hdr_format`]: crate::camera::Camera::hdr_format
+#[derive(Reflect, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[reflect(Default, PartialEq, Hash)]
+pub enum HdrFormat {
+    /// 16-bit float per channel, with alpha. 8 bytes per pixel.
+    #[default]
+    Rgba16Float,
+    /// 11-bit float for red and green and 10-bit float for blue, without alpha. 4 bytes per
+    /// pixel.
+    ///
+    /// It keeps the range of [`HdrFormat::Rgba16Float`] with less precision, but the alpha of the
+    /// rendered image is always `1.0`. Rendering to it requires
+    /// [`Features::RG11B10UFLOAT_RENDERABLE`].
+    Rg11b10Float,
+    /// 10-bit unsigned normalized red, green and blue, with 2-bit alpha. 4 bytes per pixel.
+    ///
+    /// Values are clamped to `[0, 1]` before tonemapping, so it only suits scenes whose exposure
+    /// keeps them in that range.
+    Rgb10a2Unorm,
+}
+
+impl HdrFormat {
+    /// The [`TextureFormat`] of the intermediate texture.
+    pub const fn texture_format(self) -> TextureFormat {
+        match self {
+            HdrFormat::Rgba16Float => TextureFormat::Rgba16Float,
+            HdrFormat::Rg11b10Float => TextureFormat::Rg11b10Float,
+            HdrFormat::Rgb10a2Unorm => TextureFormat::Rgb10a2Unorm,
+        }
+    }
+
+    /// The [`HdrFormat`] whose [`HdrFormat::texture_format`] is `format`, if any.
+    pub const fn from_texture_format(format: TextureFormat) -> Option<Self> {
+        match format {
+            TextureFormat::Rgba16Float => Some(HdrFormat::Rgba16Float),
+            TextureFormat::Rg11b10Float => Some(HdrFormat::Rg11b10Float),
+            TextureFormat::Rgb10a2Unorm => Some(HdrFormat::Rgb10a2Unorm),
+            _ => None,
+        }
+    }
+
+    /// Whether the device can render to this format, blend with it and sample it, with the
+    /// sample count of `msaa`.
+    pub fn is_supported(
+        self,
+        render_device: &RenderDevice,
+        render_adapter: &RenderAdapter,
+        msaa: Msaa,
+    ) -> bool {
+        if self == HdrFormat::Rg11b10Float
+            && !render_device
+                .features()
+                .contains(Features::RG11B10UFLOAT_RENDERABLE)
+        {
+            return false;
+        }
+
+        let features = render_adapter.get_texture_format_features(self.texture_format());
+        let mut required_flags =
+            TextureFormatFeatureFlags::BLENDABLE | TextureFormatFeatureFlags::FILTERABLE;
+        if msaa != Msaa::Off {
+            required_flags |= TextureFormatFeatureFlags::MULTISAMPLE_RESOLVE;
+        }
+        features
+            .allowed_usages
+            .contains(TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING)
+            && features.flags.contains(required_flags)
+            && features.flags.sample_count_supported(msaa.samples())
+    }
+
+    /// Returns this format if [`HdrFormat::is_supported`], or [`HdrFormat::Rgba16Float`]
+    /// otherwise.
+    pub fn or_supported(
+        self,
+        render_device: &RenderDevice,
+        render_adapter: &RenderAdapter,
+        msaa: Msaa,
+    ) -> Self {
+        if self == HdrFormat::Rgba16Float || self.is_supported(render_device, render_adapter, msaa)
+        {
+            self
+        } else {
+            warn_once!(
+                "The HDR format {self:?} isn't supported by this device with {msaa:?}, \
+                falling back to {:?}.",
+                HdrFormat::Rgba16Float
+            );
+            HdrFormat::Rgba16Float
+        }
+    }
+}
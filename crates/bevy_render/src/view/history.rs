@@ -0,0 +1,181 @@
+use std::any::TypeId;
+
+use bevy_app::{App, Plugin};
+use bevy_ecs::{entity::EntityHashSet, prelude::*};
+use bevy_utils::HashMap;
+use wgpu::{TextureDescriptor, TextureViewDescriptor};
+
+use crate::{
+    renderer::RenderDevice, texture::CachedTexture, Extract, ExtractSchedule, Render, RenderApp,
+    RenderSet,
+};
+
+/// Manages the [`ViewHistoryTextures`] of temporal effects, and resets them on
+/// [`ResetViewHistory`] events.
+pub struct ViewHistoryPlugin;
+
+impl Plugin for ViewHistoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ResetViewHistory>();
+
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app
+            .init_resource::<ViewHistoryTextures>()
+            .add_systems(ExtractSchedule, extract_view_history_resets)
+            .add_systems(
+                Render,
+                clean_up_view_history_textures.in_set(RenderSet::Cleanup),
+            );
+    }
+}
+
+/// Send this event to discard the history of every temporal effect of a view,
+/// such as temporal anti-aliasing, on the next frame.
+///
+/// This should be sent on camera cuts and when the camera teleports, where the
+/// previous frames are no longer representative of the current one and would
+/// otherwise ghost into it.
+#[derive(Event, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ResetViewHistory {
+    /// The camera whose history is discarded.
+    pub view: Entity,
+}
+
+/// The history textures of a temporal effect for the current frame, returned by
+/// [`ViewHistoryTextures::get`].
+#[derive(Clone)]
+pub struct ViewHistoryTexture {
+    /// The texture written last frame, to read from.
+    pub read: CachedTexture,
+    /// The texture to write this frame, which is read next frame.
+    pub write: CachedTexture,
+    /// Whether the content of [`Self::read`] must be ignored, because the
+    /// textures were just allocated, or the history was reset with
+    /// [`ViewHistoryTextures::reset_history`].
+    pub reset: bool,
+}
+
+struct ViewHistory {
+    descriptor: TextureDescriptor<'static>,
+    textures: [CachedTexture; 2],
+    write_index: usize,
+    reset: bool,
+    used: bool,
+}
+
+/// Render world resource holding the history textures that temporal effects
+/// keep for each view across frames.
+///
+/// Unlike textures from the [`TextureCache`](crate::texture::TextureCache),
+/// history textures are owned by a single view and effect, so they are never
+/// handed to another user while their content is still needed. They're
+/// reallocated when the size or format the effect asks for changes, which
+/// resets the history, and they're dropped once the view stops using them.
+#[derive(Resource, Default)]
+pub struct ViewHistoryTextures {
+    histories: HashMap<(Entity, TypeId), ViewHistory>,
+    resets: EntityHashSet,
+}
+
+impl ViewHistoryTextures {
+    /// Returns the history textures of `view` for the effect identified by the
+    /// type `E`, allocating them if needed.
+    ///
+    /// `E` is usually the settings component of the effect, such as
+    /// `TemporalAntiAliasSettings`. Effects that keep several histories use a
+    /// marker type for each of them.
+    ///
+    /// The read and write textures are swapped on the first call of each frame,
+    /// and further calls in the same frame return the same textures. If the
+    /// `descriptor` differs from the one the textures were allocated with, such
+    /// as when the view was resized or switched to HDR, they're reallocated and
+    /// the history is reset.
+    pub fn get<E: 'static>(
+        &mut self,
+        render_device: &RenderDevice,
+        view: Entity,
+        descriptor: TextureDescriptor<'static>,
+    ) -> ViewHistoryTexture {
+        let key = (view, TypeId::of::<E>());
+        let reset = self.resets.contains(&view);
+
+        let history = match self.histories.get_mut(&key) {
+            Some(history) if history.descriptor == descriptor => {
+                if !history.used {
+                    history.used = true;
+                    history.write_index = 1 - history.write_index;
+                    history.reset = reset;
+                }
+                history
+            }
+            _ => {
+                let create_texture = || {
+                    let texture = render_device.create_texture(&descriptor);
+                    let default_view = texture.create_view(&TextureViewDescriptor::default());
+                    CachedTexture {
+                        texture,
+                        default_view,
+                    }
+                };
+                let history = ViewHistory {
+                    textures: [create_texture(), create_texture()],
+                    descriptor,
+                    write_index: 0,
+                    reset: true,
+                    used: true,
+                };
+                self.histories.insert(key, history);
+                self.histories.get_mut(&key).unwrap()
+            }
+        };
+
+        ViewHistoryTexture {
+            read: history.textures[1 - history.write_index].clone(),
+            write: history.textures[history.write_index].clone(),
+            reset: history.reset,
+        }
+    }
+
+    /// Returns true if the history of `view` is discarded this frame.
+    ///
+    /// Temporal effects that keep their history in something other than
+    /// textures, such as the exposure of auto-exposure, use this to reset it.
+    pub fn is_reset(&self, view: Entity) -> bool {
+        self.resets.contains(&view)
+    }
+
+    /// Discards the history of every effect of `view` this frame.
+    ///
+    /// This must be called before the effects get their textures. From the main
+    /// world, send a [`ResetViewHistory`] event instead.
+    pub fn reset_history(&mut self, view: Entity) {
+        self.resets.insert(view);
+        for ((history_view, _), history) in &mut self.histories {
+            if *history_view == view && history.used {
+                history.reset = true;
+            }
+        }
+    }
+}
+
+/// Resets the history of the views of the [`ResetViewHistory`] events sent
+/// this frame.
+pub fn extract_view_history_resets(
+    mut histories: ResMut<ViewHistoryTextures>,
+    mut events: Extract<EventReader<ResetViewHistory>>,
+) {
+    for event in events.read() {
+        histories.reset_history(event.view);
+    }
+}
+
+/// Drops the history textures that weren't used this frame.
+pub fn clean_up_view_history_textures(mut histories: ResMut<ViewHistoryTextures>) {
+    histories.histories.retain(|_, history| history.used);
+    for history in histories.histories.values_mut() {
+        history.used = false;
+    }
+    histories.resets.clear();
+}
@@ -1,15 +1,22 @@
+mod dither;
+mod hdr_format;
+mod history;
 pub mod visibility;
 pub mod window;
 
 use bevy_asset::{load_internal_asset, Handle};
+pub use dither::*;
+pub use hdr_format::*;
+pub use history::*;
 pub use visibility::*;
 pub use window::*;
 
 use crate::{
     camera::{
-        CameraMainTextureUsages, ClearColor, ClearColorConfig, Exposure, ExtractedCamera,
-        ManualTextureViews, MipBias, TemporalJitter,
+        CameraCut, CameraMainTextureUsages, ClearColor, ClearColorConfig, Exposure,
+        ExtractedCamera, ManualTextureViews, MipBias, TemporalJitter,
     },
+    extract_component::ExtractComponentPlugin,
     extract_resource::{ExtractResource, ExtractResourcePlugin},
     prelude::Shader,
     primitives::Frustum,
@@ -23,8 +30,8 @@ use crate::{
     Render, RenderApp, RenderSet,
 };
 use bevy_app::{App, Plugin};
-use bevy_ecs::prelude::*;
-use bevy_math::{mat3, vec2, vec3, Mat3, Mat4, UVec4, Vec2, Vec3, Vec4, Vec4Swizzles};
+use bevy_ecs::{entity::EntityHashMap, event::Events, prelude::*};
+use bevy_math::{mat3, vec2, vec3, Mat3, Mat4, UVec2, UVec4, Vec2, Vec3, Vec4, Vec4Swizzles};
 use bevy_reflect::{std_traits::ReflectDefault, Reflect};
 use bevy_transform::components::GlobalTransform;
 use bevy_utils::HashMap;
@@ -37,7 +44,8 @@ use std::{
 };
 use wgpu::{
     BufferUsages, Extent3d, RenderPassColorAttachment, RenderPassDepthStencilAttachment, StoreOp,
-    TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
+    TextureAspect, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
+    TextureViewDescriptor,
 };
 
 pub const VIEW_TYPE_HANDLE: Handle<Shader> = Handle::weak_from_u128(15421373904451797197);
@@ -105,26 +113,37 @@ impl Plugin for ViewPlugin {
             .register_type::<Visibility>()
             .register_type::<VisibleEntities>()
             .register_type::<ColorGrading>()
+            .register_type::<DitherSettings>()
+            .register_type::<HdrFormat>()
             .init_resource::<Msaa>()
             // NOTE: windows.is_changed() handles cases where a window was resized
             .add_plugins((
                 ExtractResourcePlugin::<Msaa>::default(),
+                ExtractComponentPlugin::<DitherSettings>::default(),
                 VisibilityPlugin,
                 VisibilityRangePlugin,
+                ViewHistoryPlugin,
             ));
 
         if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
-            render_app.add_systems(
-                Render,
-                (
-                    prepare_view_targets
-                        .in_set(RenderSet::ManageViews)
-                        .after(prepare_windows)
-                        .after(crate::render_asset::prepare_assets::<GpuImage>)
-                        .ambiguous_with(crate::camera::sort_cameras), // doesn't use `sorted_camera_index_for_target`
-                    prepare_view_uniforms.in_set(RenderSet::PrepareResources),
-                ),
-            );
+            render_app
+                .init_resource::<Events<ViewTargetsResized>>()
+                .init_resource::<ViewTargetSizes>()
+                .add_systems(
+                    Render,
+                    (
+                        prepare_view_target_resizes
+                            .in_set(RenderSet::ManageViews)
+                            .before(prepare_view_targets),
+                        prepare_view_targets
+                            .in_set(RenderSet::ManageViews)
+                            .after(prepare_windows)
+                            .after(crate::render_asset::prepare_assets::<GpuImage>)
+                            .ambiguous_with(crate::camera::sort_cameras), // doesn't use `sorted_camera_index_for_target`
+                        prepare_view_uniforms.in_set(RenderSet::PrepareResources),
+                        update_view_targets_resized_events.in_set(RenderSet::Cleanup),
+                    ),
+                );
         }
     }
 
@@ -179,6 +198,9 @@ pub struct ExtractedView {
     // stability matters and there is a more direct way to derive the view-projection matrix.
     pub view_projection: Option<Mat4>,
     pub hdr: bool,
+    /// The format of the main texture when [`ExtractedView::hdr`] is set, after falling back to
+    /// [`HdrFormat::Rgba16Float`] if the format of the camera isn't supported.
+    pub hdr_format: HdrFormat,
     // uvec4(origin.x, origin.y, width, height)
     pub viewport: UVec4,
     pub color_grading: ColorGrading,
@@ -189,6 +211,11 @@ impl ExtractedView {
     pub fn rangefinder3d(&self) -> ViewRangefinder3d {
         ViewRangefinder3d::from_view_matrix(&self.transform.compute_matrix())
     }
+
+    /// The format of the main texture of the view.
+    pub fn main_texture_format(&self) -> TextureFormat {
+        ViewTarget::main_texture_format_for(self.hdr, self.hdr_format)
+    }
 }
 
 /// Configures filmic color grading parameters to adjust the image appearance.
@@ -419,6 +446,22 @@ pub struct ViewUniform {
     color_grading: ColorGradingUniform,
     mip_bias: f32,
     render_layers: u32,
+    // 'flags' is a bit field indicating various options, see [`ViewUniformFlags`].
+    flags: u32,
+    // See [`DitherPattern::shader_index`].
+    dither_pattern: u32,
+    dither_strength: f32,
+}
+
+bitflags::bitflags! {
+    /// The flags of the [`ViewUniform`] of a view, available in shaders as
+    /// `view.flags`.
+    #[repr(transparent)]
+    pub struct ViewUniformFlags: u32 {
+        /// The view cuts to a different shot this frame, see [`CameraCut`].
+        const CAMERA_CUT = 1 << 0;
+        const NONE       = 0;
+    }
 }
 
 #[derive(Resource)]
@@ -542,12 +585,37 @@ impl From<ColorGrading> for ColorGradingUniform {
 #[derive(Component)]
 pub struct GpuCulling;
 
+/// Add this component to a camera to sort its transparent meshes on the GPU rather than on the
+/// CPU, for scenes with so many transparent instances, like particles, that sorting them on the
+/// CPU is the bottleneck.
+///
+/// The phase items are only grouped into batches on the CPU, and the instances of each batch are
+/// then sorted back to front by a GPU radix sort before the mesh uniforms are built, so that the
+/// draws follow the sorted order. Batches aren't sorted against each other, so this suits many
+/// instances sharing a mesh and material.
+///
+/// This requires GPU preprocessing, and is ignored on cameras with [`GpuCulling`], whose culled
+/// instances are compacted out of order.
+#[derive(Component, Clone, Copy, Default)]
+pub struct GpuSorting;
+
 #[derive(Component)]
 pub struct NoCpuCulling;
 
 impl ViewTarget {
+    /// The default format of the main texture of HDR views, see [`HdrFormat`].
     pub const TEXTURE_FORMAT_HDR: TextureFormat = TextureFormat::Rgba16Float;
 
+    /// The format of the main texture of a view, given its [`ExtractedView::hdr`] and
+    /// [`ExtractedView::hdr_format`].
+    pub fn main_texture_format_for(hdr: bool, hdr_format: HdrFormat) -> TextureFormat {
+        if hdr {
+            hdr_format.texture_format()
+        } else {
+            TextureFormat::bevy_default()
+        }
+    }
+
     /// Retrieve this target's main texture's color attachment.
     pub fn get_color_attachment(&self) -> RenderPassColorAttachment {
         if self.main_texture.load(Ordering::SeqCst) == 0 {
@@ -635,10 +703,10 @@ impl ViewTarget {
         self.main_texture_format
     }
 
-    /// Returns `true` if and only if the main texture is [`Self::TEXTURE_FORMAT_HDR`]
+    /// Returns `true` if and only if the main texture has one of the [`HdrFormat`]s.
     #[inline]
     pub fn is_hdr(&self) -> bool {
-        self.main_texture_format == ViewTarget::TEXTURE_FORMAT_HDR
+        HdrFormat::from_texture_format(self.main_texture_format).is_some()
     }
 
     /// The final texture this view will render to.
@@ -683,13 +751,28 @@ impl ViewTarget {
 pub struct ViewDepthTexture {
     pub texture: Texture,
     attachment: DepthAttachment,
+    depth_view: TextureView,
 }
 
 impl ViewDepthTexture {
+    /// Creates the depth texture of a view. If the texture has a depth-stencil format, its stencil
+    /// is attached too, and cleared along with the depth.
     pub fn new(texture: CachedTexture, clear_value: Option<f32>) -> Self {
+        let stencil = texture.texture.format().has_stencil_aspect();
+        let depth_view = if stencil {
+            texture.texture.create_view(&TextureViewDescriptor {
+                label: Some("view_depth_texture_depth_view"),
+                aspect: TextureAspect::DepthOnly,
+                ..Default::default()
+            })
+        } else {
+            texture.default_view.clone()
+        };
         Self {
             texture: texture.texture,
-            attachment: DepthAttachment::new(texture.default_view, clear_value),
+            attachment: DepthAttachment::new(texture.default_view, clear_value)
+                .with_stencil(stencil),
+            depth_view,
         }
     }
 
@@ -700,6 +783,12 @@ impl ViewDepthTexture {
     pub fn view(&self) -> &TextureView {
         &self.attachment.view
     }
+
+    /// A view of the depth aspect of the texture only, which can be bound as a depth texture even
+    /// when the texture also has a stencil.
+    pub fn depth_view(&self) -> &TextureView {
+        &self.depth_view
+    }
 }
 
 pub fn prepare_view_uniforms(
@@ -715,6 +804,8 @@ pub fn prepare_view_uniforms(
         Option<&TemporalJitter>,
         Option<&MipBias>,
         Option<&RenderLayers>,
+        Has<CameraCut>,
+        Option<&DitherSettings>,
     )>,
 ) {
     let view_iter = views.iter();
@@ -734,6 +825,8 @@ pub fn prepare_view_uniforms(
         temporal_jitter,
         mip_bias,
         maybe_layers,
+        camera_cut,
+        dither_settings,
     ) in &views
     {
         let viewport = extracted_view.viewport.as_vec4();
@@ -761,6 +854,8 @@ pub fn prepare_view_uniforms(
             .map(|frustum| frustum.half_spaces.map(|h| h.normal_d()))
             .unwrap_or([Vec4::ZERO; 6]);
 
+        let dither_settings = dither_settings.copied().unwrap_or_default();
+
         let view_uniforms = ViewUniformOffset {
             offset: writer.write(&ViewUniform {
                 view_proj,
@@ -779,6 +874,13 @@ pub fn prepare_view_uniforms(
                 color_grading: extracted_view.color_grading.clone().into(),
                 mip_bias: mip_bias.unwrap_or(&MipBias(0.0)).0,
                 render_layers: maybe_layers.copied().unwrap_or_default().bits(),
+                flags: if camera_cut {
+                    ViewUniformFlags::CAMERA_CUT.bits()
+                } else {
+                    ViewUniformFlags::NONE.bits()
+                },
+                dither_pattern: dither_settings.pattern.shader_index(),
+                dither_strength: dither_settings.strength,
             }),
         };
 
@@ -795,6 +897,106 @@ struct MainTargetTextures {
     main_texture: Arc<AtomicUsize>,
 }
 
+/// Sent in the render world when the render target or viewport of views changed
+/// size since the previous frame.
+///
+/// All the views resized in a frame are sent in a single event, before their
+/// [`ViewTarget`]s are prepared. By then, the textures that a resized view took
+/// from the [`TextureCache`] with [`TextureCache::get_for_view`] at its old
+/// size have been dropped, see [`ViewTargetResize::is_stale`]. Every per-view
+/// texture, such as the main textures, depth, prepass and GPU picking
+/// textures, is then allocated once at the new size in the same frame, instead
+/// of keeping the old allocations alive for a few more frames alongside the
+/// new ones. Textures of other views, and textures that aren't per view, are
+/// left alone.
+///
+/// Systems that keep per-view textures of their own outside of the
+/// [`TextureCache`], or at sizes derived from the view size such as mip chains,
+/// can read this event to reallocate them in the same frame.
+#[derive(Event, Clone, Debug, Default)]
+pub struct ViewTargetsResized {
+    pub views: Vec<ViewTargetResize>,
+}
+
+/// The change in size of a view, see [`ViewTargetsResized`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ViewTargetResize {
+    pub view: Entity,
+    pub old_size: ViewTargetSize,
+    pub new_size: ViewTargetSize,
+}
+
+impl ViewTargetResize {
+    /// Whether a texture of the given `size`, last used by `view`, was sized
+    /// after the old size of this view, which it no longer has.
+    pub fn is_stale(&self, view: Entity, size: Extent3d) -> bool {
+        let size = UVec2::new(size.width, size.height);
+        view == self.view
+            && ((size == self.old_size.target && size != self.new_size.target)
+                || (size == self.old_size.viewport && size != self.new_size.viewport))
+    }
+}
+
+/// The physical size of the render target and viewport of a view.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ViewTargetSize {
+    pub target: UVec2,
+    pub viewport: UVec2,
+}
+
+/// The size of every view in the previous frame.
+#[derive(Resource, Default)]
+pub struct ViewTargetSizes(EntityHashMap<ViewTargetSize>);
+
+/// Detects views whose size changed, drops the cached textures they had at
+/// their old size, and sends a [`ViewTargetsResized`] event.
+pub fn prepare_view_target_resizes(
+    mut sizes: ResMut<ViewTargetSizes>,
+    mut events: EventWriter<ViewTargetsResized>,
+    mut texture_cache: ResMut<TextureCache>,
+    cameras: Query<(Entity, &ExtractedCamera)>,
+) {
+    let mut views = Vec::new();
+    let mut current_sizes = EntityHashMap::default();
+    for (entity, camera) in &cameras {
+        let (Some(target), Some(viewport)) =
+            (camera.physical_target_size, camera.physical_viewport_size)
+        else {
+            continue;
+        };
+        let new_size = ViewTargetSize { target, viewport };
+        if let Some(old_size) = sizes.0.get(&entity) {
+            if *old_size != new_size {
+                views.push(ViewTargetResize {
+                    view: entity,
+                    old_size: *old_size,
+                    new_size,
+                });
+            }
+        }
+        current_sizes.insert(entity, new_size);
+    }
+    sizes.0 = current_sizes;
+
+    if views.is_empty() {
+        return;
+    }
+
+    texture_cache.remove_unused(|view, descriptor| {
+        views
+            .iter()
+            .any(|resize| resize.is_stale(view, descriptor.size))
+    });
+
+    events.send(ViewTargetsResized { views });
+}
+
+/// Swaps the buffers of the [`ViewTargetsResized`] events, so that they're kept
+/// for one more frame.
+pub fn update_view_targets_resized_events(mut events: ResMut<Events<ViewTargetsResized>>) {
+    events.update();
+}
+
 #[allow(clippy::too_many_arguments)]
 pub fn prepare_view_targets(
     mut commands: Commands,
@@ -825,11 +1027,7 @@ pub fn prepare_view_targets(
                     depth_or_array_layers: 1,
                 };
 
-                let main_texture_format = if view.hdr {
-                    ViewTarget::TEXTURE_FORMAT_HDR
-                } else {
-                    TextureFormat::bevy_default()
-                };
+                let main_texture_format = view.main_texture_format();
 
                 let clear_color = match camera.clear_color {
                     ClearColorConfig::Custom(color) => Some(color),
@@ -838,7 +1036,7 @@ pub fn prepare_view_targets(
                 };
 
                 let (a, b, sampled, main_texture) = textures
-                    .entry((camera.target.clone(), view.hdr))
+                    .entry((camera.target.clone(), main_texture_format))
                     .or_insert_with(|| {
                         let descriptor = TextureDescriptor {
                             label: None,
@@ -854,23 +1052,26 @@ pub fn prepare_view_targets(
                                 _ => &[],
                             },
                         };
-                        let a = texture_cache.get(
+                        let a = texture_cache.get_for_view(
                             &render_device,
+                            entity,
                             TextureDescriptor {
                                 label: Some("main_texture_a"),
                                 ..descriptor
                             },
                         );
-                        let b = texture_cache.get(
+                        let b = texture_cache.get_for_view(
                             &render_device,
+                            entity,
                             TextureDescriptor {
                                 label: Some("main_texture_b"),
                                 ..descriptor
                             },
                         );
                         let sampled = if msaa.samples() > 1 {
-                            let sampled = texture_cache.get(
+                            let sampled = texture_cache.get_for_view(
                                 &render_device,
+                                entity,
                                 TextureDescriptor {
                                     label: Some("main_texture_sampled"),
                                     size,
@@ -909,3 +1110,46 @@ pub fn prepare_view_targets(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bevy_ecs::entity::Entity;
+    use bevy_math::UVec2;
+    use wgpu::Extent3d;
+
+    use super::{ViewTargetResize, ViewTargetSize};
+
+    fn extent(width: u32, height: u32) -> Extent3d {
+        Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        }
+    }
+
+    #[test]
+    fn only_textures_of_the_resized_view_at_its_old_size_are_stale() {
+        let view = Entity::from_raw(1);
+        let other_view = Entity::from_raw(2);
+        let resize = ViewTargetResize {
+            view,
+            old_size: ViewTargetSize {
+                target: UVec2::new(800, 600),
+                viewport: UVec2::new(400, 600),
+            },
+            new_size: ViewTargetSize {
+                target: UVec2::new(1024, 768),
+                viewport: UVec2::new(400, 600),
+            },
+        };
+
+        assert!(resize.is_stale(view, extent(800, 600)));
+        // Other views, even at the same size, keep their textures.
+        assert!(!resize.is_stale(other_view, extent(800, 600)));
+        // The viewport didn't change size, so textures sized after it are
+        // still used.
+        assert!(!resize.is_stale(view, extent(400, 600)));
+        // Textures that aren't sized after the view, such as mip chains.
+        assert!(!resize.is_stale(view, extent(512, 512)));
+    }
+}
@@ -29,4 +29,15 @@ struct View {
     color_grading: ColorGrading,
     mip_bias: f32,
     render_layers: u32,
+    // 'flags' is a bit field indicating various options. u32 is 32 bits so we have up to 32 options.
+    flags: u32,
+    dither_pattern: u32,
+    dither_strength: f32,
 };
+
+const VIEW_FLAGS_CAMERA_CUT_BIT: u32 = 1u;
+
+// The values of `View::dither_pattern`, see `DitherPattern` in `bevy_render::view`.
+const DITHER_PATTERN_BLUE_NOISE: u32 = 0u;
+const DITHER_PATTERN_ORDERED: u32 = 1u;
+const DITHER_PATTERN_SCREEN_SPACE_HASH: u32 = 2u;
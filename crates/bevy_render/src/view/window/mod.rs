@@ -8,11 +8,13 @@ use crate::{
 };
 use bevy_app::{App, Plugin};
 use bevy_ecs::{entity::EntityHashMap, prelude::*};
+use bevy_reflect::{std_traits::ReflectDefault, Reflect};
 #[cfg(target_os = "linux")]
 use bevy_utils::warn_once;
 use bevy_utils::{default, tracing::debug, HashSet};
 use bevy_window::{
-    CompositeAlphaMode, PresentMode, PrimaryWindow, RawHandleWrapper, Window, WindowClosed,
+    CompositeAlphaMode, PresentMode, PrimaryWindow, RawHandleWrapper, RequestRedraw, Window,
+    WindowClosed,
 };
 use std::{
     num::NonZeroU32,
@@ -24,6 +26,8 @@ use wgpu::{
     TextureViewDescriptor,
 };
 
+#[cfg(target_arch = "wasm32")]
+pub mod offscreen_canvas;
 pub mod screenshot;
 
 use screenshot::{
@@ -36,7 +40,11 @@ pub struct WindowRenderPlugin;
 
 impl Plugin for WindowRenderPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins(ScreenshotPlugin);
+        app.add_plugins(ScreenshotPlugin)
+            .register_type::<WindowRenderScheduling>()
+            .register_type::<RenderWhenDirty>()
+            .init_resource::<WindowRenderScheduling>()
+            .add_event::<RequestWindowRedraw>();
 
         if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
             render_app
@@ -60,6 +68,50 @@ impl Plugin for WindowRenderPlugin {
     }
 }
 
+/// Controls which windows are rendered on a given frame when the app has more
+/// than one.
+///
+/// Windows that aren't rendered on a frame keep showing their last presented
+/// image, and the cameras targeting them don't run their render graphs.
+#[derive(Resource, Reflect, Clone, Copy, Default, Debug, PartialEq, Eq)]
+#[reflect(Resource, Default)]
+pub enum WindowRenderScheduling {
+    /// Every window is rendered every frame.
+    ///
+    /// Windows with the [`RenderWhenDirty`] component are still only rendered
+    /// when they are dirty.
+    #[default]
+    EveryFrame,
+    /// A single window is rendered each frame, cycling through all windows.
+    ///
+    /// This divides the GPU cost of many windows at the price of a lower
+    /// framerate for each of them.
+    RoundRobin,
+    /// Every window behaves as if it had the [`RenderWhenDirty`] component.
+    OnDemand,
+}
+
+/// Only renders this window when it is dirty.
+///
+/// A window is dirty on the frame it is created, when its [`Window`] component
+/// changes (which includes resizing, but also cursor movement), when a
+/// [`RequestRedraw`] or a [`RequestWindowRedraw`] targeting it is sent, or
+/// when a screenshot of it is requested.
+///
+/// This is meant for tool-style applications that shouldn't keep the GPU busy
+/// when nothing changes on screen.
+#[derive(Component, Reflect, Clone, Copy, Default, Debug)]
+#[reflect(Component, Default)]
+pub struct RenderWhenDirty;
+
+/// Marks a single window as dirty, so that it is rendered this frame even if it
+/// would otherwise be skipped. See [`RenderWhenDirty`].
+#[derive(Event, Clone, Copy, Debug)]
+pub struct RequestWindowRedraw {
+    /// The window to redraw.
+    pub window: Entity,
+}
+
 pub struct ExtractedWindow {
     /// An entity that contains the components in [`Window`].
     pub entity: Entity,
@@ -79,6 +131,11 @@ pub struct ExtractedWindow {
     pub present_mode_changed: bool,
     pub alpha_mode: CompositeAlphaMode,
     pub screenshot_func: Option<screenshot::ScreenshotFn>,
+    /// Whether this window is rendered this frame, according to the
+    /// [`WindowRenderScheduling`] and [`RenderWhenDirty`] settings.
+    ///
+    /// Windows that aren't rendered don't acquire a swap chain texture.
+    pub should_render: bool,
 }
 
 impl ExtractedWindow {
@@ -114,15 +171,34 @@ impl DerefMut for ExtractedWindows {
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn extract_windows(
     mut extracted_windows: ResMut<ExtractedWindows>,
     screenshot_manager: Extract<Res<ScreenshotManager>>,
+    scheduling: Extract<Res<WindowRenderScheduling>>,
     mut closed: Extract<EventReader<WindowClosed>>,
-    windows: Extract<Query<(Entity, &Window, &RawHandleWrapper, Option<&PrimaryWindow>)>>,
+    mut redraw_requests: Extract<EventReader<RequestRedraw>>,
+    mut window_redraw_requests: Extract<EventReader<RequestWindowRedraw>>,
+    windows: Extract<
+        Query<(
+            Entity,
+            Ref<Window>,
+            &RawHandleWrapper,
+            Option<&PrimaryWindow>,
+            Has<RenderWhenDirty>,
+        )>,
+    >,
     mut removed: Extract<RemovedComponents<RawHandleWrapper>>,
     mut window_surfaces: ResMut<WindowSurfaces>,
+    mut round_robin_counter: Local<usize>,
 ) {
-    for (entity, window, handle, primary) in windows.iter() {
+    let redraw_all = redraw_requests.read().count() > 0;
+    let redraw_windows: HashSet<Entity> = window_redraw_requests
+        .read()
+        .map(|request| request.window)
+        .collect();
+
+    for (entity, window, handle, primary, render_when_dirty) in windows.iter() {
         if primary.is_some() {
             extracted_windows.primary = Some(entity);
         }
@@ -147,6 +223,7 @@ fn extract_windows(
             alpha_mode: window.composite_alpha_mode,
             screenshot_func: None,
             screenshot_memory: None,
+            should_render: true,
         });
 
         // NOTE: Drop the swap chain frame here
@@ -175,6 +252,43 @@ fn extract_windows(
             );
             extracted_window.present_mode = window.present_mode;
         }
+
+        extracted_window.should_render = match **scheduling {
+            WindowRenderScheduling::EveryFrame if !render_when_dirty => true,
+            // The window whose turn it is gets picked below, once all windows
+            // are known.
+            WindowRenderScheduling::RoundRobin if !render_when_dirty => {
+                redraw_windows.contains(&entity)
+            }
+            _ => {
+                redraw_all
+                    || redraw_windows.contains(&entity)
+                    || window.is_changed()
+                    || extracted_window.size_changed
+                    || extracted_window.present_mode_changed
+            }
+        };
+    }
+
+    if **scheduling == WindowRenderScheduling::RoundRobin {
+        let mut round_robin_windows: Vec<Entity> = windows
+            .iter()
+            .filter(|(.., render_when_dirty)| !render_when_dirty)
+            .map(|(entity, ..)| entity)
+            .collect();
+        round_robin_windows.sort();
+        if !round_robin_windows.is_empty() {
+            let entity = round_robin_windows[*round_robin_counter % round_robin_windows.len()];
+            *round_robin_counter = round_robin_counter.wrapping_add(1);
+            if let Some(window) = extracted_windows.get_mut(&entity) {
+                window.should_render = true;
+            }
+        }
+        // Resized windows must be redrawn right away rather than stretching a
+        // stale image until their turn comes.
+        for window in extracted_windows.values_mut() {
+            window.should_render |= window.size_changed || window.present_mode_changed;
+        }
     }
 
     for closed_window in closed.read() {
@@ -197,6 +311,7 @@ fn extract_windows(
     {
         if let Some(window) = extracted_windows.get_mut(&window) {
             window.screenshot_func = Some(screenshot_func);
+            window.should_render = true;
         }
     }
 }
@@ -258,6 +373,10 @@ pub fn prepare_windows(
     #[cfg(target_os = "linux")] render_instance: Res<RenderInstance>,
 ) {
     for window in windows.windows.values_mut() {
+        if !window.should_render {
+            continue;
+        }
+
         let window_surfaces = window_surfaces.deref_mut();
         let Some(surface_data) = window_surfaces.surfaces.get(&window.entity) else {
             continue;
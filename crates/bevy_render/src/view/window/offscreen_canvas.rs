@@ -0,0 +1,220 @@
+//! Rendering to an [`OffscreenCanvas`] from a web worker.
+//!
+//! Heavy simulations can starve rendering when they share the browser's main thread with it. To
+//! avoid that, the main thread can hand its canvas over to a worker with
+//! `canvas.transferControlToOffscreen()`, and run the whole app in that worker. There is no
+//! `winit` in a worker, so the primary window is backed directly by the [`OffscreenCanvas`]
+//! through [`OffscreenCanvasPlugin`].
+//!
+//! ```ignore
+//! App::new()
+//!     .add_plugins(
+//!         DefaultPlugins
+//!             .build()
+//!             .disable::<WinitPlugin>()
+//!             .add_after::<WindowPlugin, _>(OffscreenCanvasPlugin::new(canvas).with_port(port)),
+//!     )
+//!     .run();
+//! ```
+//!
+//! With a [`MessagePort`], the main thread drives the frames: every message it posts to the port
+//! updates the app once, and the worker posts `"frame"` back once the frame has been presented.
+//! Posting the next message from a `requestAnimationFrame` callback, only after the previous
+//! frame was acknowledged, keeps the worker from queuing up frames. A message may carry `width`
+//! and `height` properties to resize the canvas, in physical pixels.
+
+use std::{cell::RefCell, ffi::c_void, ops::Deref, ptr::NonNull, rc::Rc};
+
+use bevy_app::{App, AppExit, Plugin, PluginsState};
+use bevy_ecs::prelude::*;
+use bevy_window::{PrimaryWindow, RawHandleWrapper, Window, WindowWrapper};
+use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+use web_sys::{MessageEvent, MessagePort, OffscreenCanvas};
+use wgpu::rwh::{
+    DisplayHandle, HandleError, HasDisplayHandle, HasWindowHandle, RawDisplayHandle,
+    RawWindowHandle, WebDisplayHandle, WebOffscreenCanvasWindowHandle, WindowHandle,
+};
+
+/// Backs the primary window with an [`OffscreenCanvas`], and optionally drives the app from a
+/// [`MessagePort`]. See the [module documentation](self).
+///
+/// This must be added after [`WindowPlugin`](bevy_window::WindowPlugin), which spawns the primary
+/// window, and before [`RenderPlugin`](crate::RenderPlugin), which creates the renderer for it.
+pub struct OffscreenCanvasPlugin {
+    canvas: ThreadBound<OffscreenCanvas>,
+    port: Option<ThreadBound<MessagePort>>,
+}
+
+impl OffscreenCanvasPlugin {
+    pub fn new(canvas: OffscreenCanvas) -> Self {
+        Self {
+            canvas: ThreadBound::new(canvas),
+            port: None,
+        }
+    }
+
+    /// Updates the app whenever a message is received on `port`, instead of using the default
+    /// runner.
+    pub fn with_port(mut self, port: MessagePort) -> Self {
+        self.port = Some(ThreadBound::new(port));
+        self
+    }
+}
+
+impl Plugin for OffscreenCanvasPlugin {
+    fn build(&self, app: &mut App) {
+        let canvas = (*self.canvas).clone();
+        let world = app.world_mut();
+        let Ok(primary_window) = world
+            .query_filtered::<Entity, With<PrimaryWindow>>()
+            .get_single(world)
+        else {
+            panic!("OffscreenCanvasPlugin must be added after WindowPlugin, with a primary window");
+        };
+
+        let handle = RawHandleWrapper::new(&WindowWrapper::new(OffscreenCanvasHandle {
+            canvas: ThreadBound::new(canvas.clone().into()),
+        }))
+        .expect("Failed to create a window handle for the offscreen canvas");
+        let mut window = world.entity_mut(primary_window);
+        window.insert(handle);
+        if let Some(mut window) = window.get_mut::<Window>() {
+            window
+                .resolution
+                .set_physical_resolution(canvas.width(), canvas.height());
+        }
+
+        if let Some(port) = &self.port {
+            let port = (**port).clone();
+            app.set_runner(move |app| offscreen_canvas_runner(app, canvas, port));
+        }
+    }
+}
+
+/// Updates `app` whenever a message is received on `port`.
+fn offscreen_canvas_runner(app: App, canvas: OffscreenCanvas, port: MessagePort) -> AppExit {
+    let app = Rc::new(RefCell::new(app));
+    let on_message_port = port.clone();
+    let on_message = Closure::<dyn FnMut(MessageEvent)>::new(move |event: MessageEvent| {
+        let mut app = app.borrow_mut();
+        resize_from_message(&mut app, &canvas, &event.data());
+
+        // The renderer is created asynchronously on the web, so the first messages may arrive
+        // before the app is ready. They are still acknowledged, to keep the main thread sending.
+        if app.plugins_state() == PluginsState::Ready {
+            app.finish();
+            app.cleanup();
+        }
+        if app.plugins_state() == PluginsState::Cleaned {
+            app.update();
+            if app.should_exit().is_some() {
+                on_message_port.set_onmessage(None);
+                return;
+            }
+        }
+        // Posting can only fail if the message can't be cloned, which never happens for strings.
+        let _ = on_message_port.post_message(&JsValue::from_str("frame"));
+    });
+    port.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+    port.start();
+    // The closure lives as long as the worker, which keeps the app alive with it.
+    on_message.forget();
+
+    AppExit::Success
+}
+
+fn resize_from_message(app: &mut App, canvas: &OffscreenCanvas, data: &JsValue) {
+    let size = |name: &str| {
+        js_sys::Reflect::get(data, &JsValue::from_str(name))
+            .ok()?
+            .as_f64()
+    };
+    let (Some(width), Some(height)) = (size("width"), size("height")) else {
+        return;
+    };
+    let (width, height) = (width as u32, height as u32);
+    canvas.set_width(width);
+    canvas.set_height(height);
+
+    let world = app.world_mut();
+    let mut windows = world.query_filtered::<&mut Window, With<PrimaryWindow>>();
+    if let Ok(mut window) = windows.get_single_mut(world) {
+        window.resolution.set_physical_resolution(width, height);
+    }
+}
+
+/// The [`OffscreenCanvas`] the primary window renders to, as a window handle.
+struct OffscreenCanvasHandle {
+    // The window handle points to this value, so it must not move while the handle is in use.
+    // `WindowWrapper` keeps it behind an `Arc`.
+    canvas: ThreadBound<JsValue>,
+}
+
+impl HasWindowHandle for OffscreenCanvasHandle {
+    fn window_handle(&self) -> Result<WindowHandle<'_>, HandleError> {
+        let canvas: &JsValue = &self.canvas;
+        let raw = RawWindowHandle::WebOffscreenCanvas(WebOffscreenCanvasWindowHandle::new(
+            NonNull::from(canvas).cast::<c_void>(),
+        ));
+        // SAFETY: The handle points to a `JsValue` holding an `OffscreenCanvas`, which lives as
+        // long as `self`.
+        Ok(unsafe { WindowHandle::borrow_raw(raw) })
+    }
+}
+
+impl HasDisplayHandle for OffscreenCanvasHandle {
+    fn display_handle(&self) -> Result<DisplayHandle<'_>, HandleError> {
+        let raw = RawDisplayHandle::Web(WebDisplayHandle::new());
+        // SAFETY: Web display handles don't point to anything.
+        Ok(unsafe { DisplayHandle::borrow_raw(raw) })
+    }
+}
+
+/// Makes JavaScript objects `Send` and `Sync`, so they can be stored in plugins and components.
+///
+/// Without `atomics`, WebAssembly runs on a single thread. With `atomics`, the value can only be
+/// accessed from the thread that created it, or else a panic will occur.
+#[cfg(not(target_feature = "atomics"))]
+struct ThreadBound<T>(T);
+
+#[cfg(not(target_feature = "atomics"))]
+impl<T> ThreadBound<T> {
+    fn new(value: T) -> Self {
+        Self(value)
+    }
+}
+
+#[cfg(not(target_feature = "atomics"))]
+impl<T> Deref for ThreadBound<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+// SAFETY: There is only one thread without `atomics`.
+#[cfg(not(target_feature = "atomics"))]
+unsafe impl<T> Send for ThreadBound<T> {}
+// SAFETY: There is only one thread without `atomics`.
+#[cfg(not(target_feature = "atomics"))]
+unsafe impl<T> Sync for ThreadBound<T> {}
+
+#[cfg(target_feature = "atomics")]
+struct ThreadBound<T>(send_wrapper::SendWrapper<T>);
+
+#[cfg(target_feature = "atomics")]
+impl<T> ThreadBound<T> {
+    fn new(value: T) -> Self {
+        Self(send_wrapper::SendWrapper::new(value))
+    }
+}
+
+#[cfg(target_feature = "atomics")]
+impl<T> Deref for ThreadBound<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
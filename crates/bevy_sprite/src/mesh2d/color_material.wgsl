@@ -1,10 +1,13 @@
 #import bevy_sprite::{
     mesh2d_vertex_output::VertexOutput,
-    mesh2d_view_bindings::view,
+    mesh2d_view_bindings::{view, blue_noise_texture},
 }
+#import bevy_render::blue_noise::blue_noise_load
 
 #ifdef TONEMAP_IN_SHADER
 #import bevy_core_pipeline::tonemapping
+#else ifdef DEBAND_DITHER
+#import bevy_core_pipeline::tonemapping
 #endif
 
 struct ColorMaterial {
@@ -31,6 +34,18 @@ fn fragment(
     }
 #ifdef TONEMAP_IN_SHADER
     output_color = tonemapping::tone_mapping(output_color, view.color_grading);
+#endif
+#ifdef DEBAND_DITHER
+    output_color = vec4(
+        tonemapping::dither_srgb(
+            output_color.rgb,
+            mesh.position.xy,
+            blue_noise_load(blue_noise_texture, vec2<u32>(mesh.position.xy)),
+            view.dither_pattern,
+            view.dither_strength,
+        ),
+        output_color.a
+    );
 #endif
     return output_color;
 }
@@ -121,6 +121,16 @@ pub trait Material2d: AsBindGroup + Asset + Clone + Sized {
         0.0
     }
 
+    /// Whether meshes with this material are dithered by cameras with
+    /// [`DebandDither::Enabled`] that draw them to an 8-bit texture.
+    ///
+    /// Return `false` for UI-like surfaces, such as flat colors and text, where the grain of the
+    /// dithering is more noticeable than banding.
+    #[inline]
+    fn deband_dither(&self) -> bool {
+        true
+    }
+
     /// Customizes the default [`RenderPipelineDescriptor`].
     #[allow(unused_variables)]
     #[inline]
@@ -392,7 +402,8 @@ pub fn queue_material2d_meshes<M: Material2d>(
         let draw_transparent_pbr = transparent_draw_functions.read().id::<DrawMaterial2d<M>>();
 
         let mut view_key = Mesh2dPipelineKey::from_msaa_samples(msaa.samples())
-            | Mesh2dPipelineKey::from_hdr(view.hdr);
+            | Mesh2dPipelineKey::from_hdr(view.hdr)
+            | Mesh2dPipelineKey::from_hdr_format(view.hdr_format);
 
         if !view.hdr {
             if let Some(tonemapping) = tonemapping {
@@ -416,8 +427,11 @@ pub fn queue_material2d_meshes<M: Material2d>(
             let Some(mesh) = render_meshes.get(mesh_instance.mesh_asset_id) else {
                 continue;
             };
-            let mesh_key =
+            let mut mesh_key =
                 view_key | Mesh2dPipelineKey::from_primitive_topology(mesh.primitive_topology());
+            if !material2d.deband_dither {
+                mesh_key.remove(Mesh2dPipelineKey::DEBAND_DITHER);
+            }
 
             let pipeline_id = pipelines.specialize(
                 &pipeline_cache,
@@ -466,6 +480,7 @@ pub struct PreparedMaterial2d<T: Material2d> {
     pub bind_group: BindGroup,
     pub key: T::Data,
     pub depth_bias: f32,
+    pub deband_dither: bool,
 }
 
 impl<T: Material2d> PreparedMaterial2d<T> {
@@ -499,6 +514,7 @@ impl<M: Material2d> RenderAsset for PreparedMaterial2d<M> {
                 bind_group: prepared.bind_group,
                 key: prepared.data,
                 depth_bias: material.depth_bias(),
+                deband_dither: material.deband_dither(),
             }),
             Err(AsBindGroupError::RetryNextUpdate) => {
                 Err(PrepareAssetError::RetryNextUpdate(material))
@@ -22,13 +22,17 @@ use bevy_render::{
     mesh::{GpuBufferInfo, Mesh},
     render_asset::RenderAssets,
     render_phase::{PhaseItem, RenderCommand, RenderCommandResult, TrackedRenderPass},
-    render_resource::{binding_types::uniform_buffer, *},
+    render_resource::{
+        binding_types::{texture_2d, uniform_buffer},
+        *,
+    },
     renderer::{RenderDevice, RenderQueue},
     texture::{
-        BevyDefault, DefaultImageSampler, GpuImage, Image, ImageSampler, TextureFormatPixelInfo,
+        BlueNoise, DefaultImageSampler, GpuImage, Image, ImageSampler, TextureFormatPixelInfo,
     },
     view::{
-        ExtractedView, ViewTarget, ViewUniform, ViewUniformOffset, ViewUniforms, ViewVisibility,
+        ExtractedView, HdrFormat, ViewTarget, ViewUniform, ViewUniformOffset, ViewUniforms,
+        ViewVisibility,
     },
     Extract, ExtractSchedule, Render, RenderApp, RenderSet,
 };
@@ -271,6 +275,8 @@ impl FromWorld for Mesh2dPipeline {
                     // View
                     uniform_buffer::<ViewUniform>(true),
                     uniform_buffer::<GlobalsUniform>(false),
+                    // Blue noise
+                    texture_2d(TextureSampleType::Float { filterable: false }),
                 ),
             ),
         );
@@ -386,6 +392,10 @@ bitflags::bitflags! {
         const TONEMAP_METHOD_SOMEWHAT_BORING_DISPLAY_TRANSFORM = 5 << Self::TONEMAP_METHOD_SHIFT_BITS;
         const TONEMAP_METHOD_TONY_MC_MAPFACE    = 6 << Self::TONEMAP_METHOD_SHIFT_BITS;
         const TONEMAP_METHOD_BLENDER_FILMIC     = 7 << Self::TONEMAP_METHOD_SHIFT_BITS;
+        const HDR_FORMAT_RESERVED_BITS          = Self::HDR_FORMAT_MASK_BITS << Self::HDR_FORMAT_SHIFT_BITS;
+        const HDR_FORMAT_RGBA16_FLOAT           = 0 << Self::HDR_FORMAT_SHIFT_BITS;
+        const HDR_FORMAT_RG11B10_FLOAT          = 1 << Self::HDR_FORMAT_SHIFT_BITS;
+        const HDR_FORMAT_RGB10A2_UNORM          = 2 << Self::HDR_FORMAT_SHIFT_BITS;
     }
 }
 
@@ -397,6 +407,9 @@ impl Mesh2dPipelineKey {
     const TONEMAP_METHOD_MASK_BITS: u32 = 0b111;
     const TONEMAP_METHOD_SHIFT_BITS: u32 =
         Self::PRIMITIVE_TOPOLOGY_SHIFT_BITS - Self::TONEMAP_METHOD_MASK_BITS.count_ones();
+    const HDR_FORMAT_MASK_BITS: u32 = 0b11;
+    const HDR_FORMAT_SHIFT_BITS: u32 =
+        Self::TONEMAP_METHOD_SHIFT_BITS - Self::HDR_FORMAT_MASK_BITS.count_ones();
 
     pub fn from_msaa_samples(msaa_samples: u32) -> Self {
         let msaa_bits =
@@ -412,6 +425,30 @@ impl Mesh2dPipelineKey {
         }
     }
 
+    pub fn from_hdr_format(hdr_format: HdrFormat) -> Self {
+        match hdr_format {
+            HdrFormat::Rgba16Float => Mesh2dPipelineKey::HDR_FORMAT_RGBA16_FLOAT,
+            HdrFormat::Rg11b10Float => Mesh2dPipelineKey::HDR_FORMAT_RG11B10_FLOAT,
+            HdrFormat::Rgb10a2Unorm => Mesh2dPipelineKey::HDR_FORMAT_RGB10A2_UNORM,
+        }
+    }
+
+    pub fn hdr_format(&self) -> HdrFormat {
+        match self.intersection(Mesh2dPipelineKey::HDR_FORMAT_RESERVED_BITS) {
+            Mesh2dPipelineKey::HDR_FORMAT_RG11B10_FLOAT => HdrFormat::Rg11b10Float,
+            Mesh2dPipelineKey::HDR_FORMAT_RGB10A2_UNORM => HdrFormat::Rgb10a2Unorm,
+            _ => HdrFormat::Rgba16Float,
+        }
+    }
+
+    /// The format of the main texture of the view, which the color target of the pipelines has.
+    pub fn view_target_format(&self) -> TextureFormat {
+        ViewTarget::main_texture_format_for(
+            self.contains(Mesh2dPipelineKey::HDR),
+            self.hdr_format(),
+        )
+    }
+
     pub fn msaa_samples(&self) -> u32 {
         1 << ((self.bits() >> Self::MSAA_SHIFT_BITS) & Self::MSAA_MASK_BITS)
     }
@@ -513,10 +550,7 @@ impl SpecializedMeshPipeline for Mesh2dPipeline {
 
         let vertex_buffer_layout = layout.0.get_layout(&vertex_attributes)?;
 
-        let format = match key.contains(Mesh2dPipelineKey::HDR) {
-            true => ViewTarget::TEXTURE_FORMAT_HDR,
-            false => TextureFormat::bevy_default(),
-        };
+        let format = key.view_target_format();
 
         Ok(RenderPipelineDescriptor {
             vertex: VertexState {
@@ -591,6 +625,7 @@ pub fn prepare_mesh2d_view_bind_groups(
     view_uniforms: Res<ViewUniforms>,
     views: Query<Entity, With<ExtractedView>>,
     globals_buffer: Res<GlobalsBuffer>,
+    blue_noise: Res<BlueNoise>,
 ) {
     if let (Some(view_binding), Some(globals)) = (
         view_uniforms.uniforms.binding(),
@@ -600,7 +635,11 @@ pub fn prepare_mesh2d_view_bind_groups(
             let view_bind_group = render_device.create_bind_group(
                 "mesh2d_view_bind_group",
                 &mesh2d_pipeline.view_layout,
-                &BindGroupEntries::sequential((view_binding.clone(), globals.clone())),
+                &BindGroupEntries::sequential((
+                    view_binding.clone(),
+                    globals.clone(),
+                    &blue_noise.texture_view,
+                )),
             );
 
             commands.entity(entity).insert(Mesh2dViewBindGroup {
@@ -1,11 +1,14 @@
 #import bevy_sprite::{
     mesh2d_functions as mesh_functions,
     mesh2d_vertex_output::VertexOutput,
-    mesh2d_view_bindings::view,
+    mesh2d_view_bindings::{view, blue_noise_texture},
 }
+#import bevy_render::blue_noise::blue_noise_load
 
 #ifdef TONEMAP_IN_SHADER
 #import bevy_core_pipeline::tonemapping
+#else ifdef DEBAND_DITHER
+#import bevy_core_pipeline::tonemapping
 #endif
 
 struct Vertex {
@@ -68,6 +71,18 @@ fn fragment(
     var color = in.color;
 #ifdef TONEMAP_IN_SHADER
     color = tonemapping::tone_mapping(color, view.color_grading);
+#endif
+#ifdef DEBAND_DITHER
+    color = vec4(
+        tonemapping::dither_srgb(
+            color.rgb,
+            in.position.xy,
+            blue_noise_load(blue_noise_texture, vec2<u32>(in.position.xy)),
+            view.dither_pattern,
+            view.dither_strength,
+        ),
+        color.a
+    );
 #endif
     return color;
 #else
@@ -6,3 +6,5 @@
 @group(0) @binding(0) var<uniform> view: View;
 
 @group(0) @binding(1) var<uniform> globals: Globals;
+
+@group(0) @binding(2) var blue_noise_texture: texture_2d<f32>;
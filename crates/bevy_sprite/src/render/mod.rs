@@ -28,10 +28,10 @@ use bevy_render::{
     },
     renderer::{RenderDevice, RenderQueue},
     texture::{
-        BevyDefault, DefaultImageSampler, GpuImage, Image, ImageSampler, TextureFormatPixelInfo,
+        BlueNoise, DefaultImageSampler, GpuImage, Image, ImageSampler, TextureFormatPixelInfo,
     },
     view::{
-        ExtractedView, Msaa, ViewTarget, ViewUniform, ViewUniformOffset, ViewUniforms,
+        ExtractedView, HdrFormat, Msaa, ViewTarget, ViewUniform, ViewUniformOffset, ViewUniforms,
         ViewVisibility, VisibleEntities,
     },
     Extract,
@@ -59,9 +59,12 @@ impl FromWorld for SpritePipeline {
 
         let view_layout = render_device.create_bind_group_layout(
             "sprite_view_layout",
-            &BindGroupLayoutEntries::single(
+            &BindGroupLayoutEntries::sequential(
                 ShaderStages::VERTEX_FRAGMENT,
-                uniform_buffer::<ViewUniform>(true),
+                (
+                    uniform_buffer::<ViewUniform>(true),
+                    texture_2d(TextureSampleType::Float { filterable: false }),
+                ),
             ),
         );
 
@@ -135,6 +138,10 @@ bitflags::bitflags! {
         const TONEMAP_METHOD_SOMEWHAT_BORING_DISPLAY_TRANSFORM = 5 << Self::TONEMAP_METHOD_SHIFT_BITS;
         const TONEMAP_METHOD_TONY_MC_MAPFACE    = 6 << Self::TONEMAP_METHOD_SHIFT_BITS;
         const TONEMAP_METHOD_BLENDER_FILMIC     = 7 << Self::TONEMAP_METHOD_SHIFT_BITS;
+        const HDR_FORMAT_RESERVED_BITS          = Self::HDR_FORMAT_MASK_BITS << Self::HDR_FORMAT_SHIFT_BITS;
+        const HDR_FORMAT_RGBA16_FLOAT           = 0 << Self::HDR_FORMAT_SHIFT_BITS;
+        const HDR_FORMAT_RG11B10_FLOAT          = 1 << Self::HDR_FORMAT_SHIFT_BITS;
+        const HDR_FORMAT_RGB10A2_UNORM          = 2 << Self::HDR_FORMAT_SHIFT_BITS;
     }
 }
 
@@ -144,6 +151,9 @@ impl SpritePipelineKey {
     const TONEMAP_METHOD_MASK_BITS: u32 = 0b111;
     const TONEMAP_METHOD_SHIFT_BITS: u32 =
         Self::MSAA_SHIFT_BITS - Self::TONEMAP_METHOD_MASK_BITS.count_ones();
+    const HDR_FORMAT_MASK_BITS: u32 = 0b11;
+    const HDR_FORMAT_SHIFT_BITS: u32 =
+        Self::TONEMAP_METHOD_SHIFT_BITS - Self::HDR_FORMAT_MASK_BITS.count_ones();
 
     #[inline]
     pub const fn from_msaa_samples(msaa_samples: u32) -> Self {
@@ -165,6 +175,33 @@ impl SpritePipelineKey {
             SpritePipelineKey::NONE
         }
     }
+
+    #[inline]
+    pub const fn from_hdr_format(hdr_format: HdrFormat) -> Self {
+        match hdr_format {
+            HdrFormat::Rgba16Float => SpritePipelineKey::HDR_FORMAT_RGBA16_FLOAT,
+            HdrFormat::Rg11b10Float => SpritePipelineKey::HDR_FORMAT_RG11B10_FLOAT,
+            HdrFormat::Rgb10a2Unorm => SpritePipelineKey::HDR_FORMAT_RGB10A2_UNORM,
+        }
+    }
+
+    #[inline]
+    pub fn hdr_format(&self) -> HdrFormat {
+        match self.intersection(SpritePipelineKey::HDR_FORMAT_RESERVED_BITS) {
+            SpritePipelineKey::HDR_FORMAT_RG11B10_FLOAT => HdrFormat::Rg11b10Float,
+            SpritePipelineKey::HDR_FORMAT_RGB10A2_UNORM => HdrFormat::Rgb10a2Unorm,
+            _ => HdrFormat::Rgba16Float,
+        }
+    }
+
+    /// The format of the main texture of the view, which the color target of the pipeline has.
+    #[inline]
+    pub fn view_target_format(&self) -> TextureFormat {
+        ViewTarget::main_texture_format_for(
+            self.contains(SpritePipelineKey::HDR),
+            self.hdr_format(),
+        )
+    }
 }
 
 impl SpecializedRenderPipeline for SpritePipeline {
@@ -202,10 +239,7 @@ impl SpecializedRenderPipeline for SpritePipeline {
             }
         }
 
-        let format = match key.contains(SpritePipelineKey::HDR) {
-            true => ViewTarget::TEXTURE_FORMAT_HDR,
-            false => TextureFormat::bevy_default(),
-        };
+        let format = key.view_target_format();
 
         let instance_rate_vertex_buffer_layout = VertexBufferLayout {
             array_stride: 80,
@@ -460,7 +494,9 @@ pub fn queue_sprites(
     let draw_sprite_function = draw_functions.read().id::<DrawSprite>();
 
     for (mut transparent_phase, visible_entities, view, tonemapping, dither) in &mut views {
-        let mut view_key = SpritePipelineKey::from_hdr(view.hdr) | msaa_key;
+        let mut view_key = SpritePipelineKey::from_hdr(view.hdr)
+            | SpritePipelineKey::from_hdr_format(view.hdr_format)
+            | msaa_key;
 
         if !view.hdr {
             if let Some(tonemapping) = tonemapping {
@@ -536,6 +572,7 @@ pub fn prepare_sprites(
     extracted_sprites: Res<ExtractedSprites>,
     mut phases: Query<&mut SortedRenderPhase<Transparent2d>>,
     events: Res<SpriteAssetEvents>,
+    blue_noise: Res<BlueNoise>,
 ) {
     // If an image has changed, the GpuImage has (probably) changed
     for event in &events.images {
@@ -559,7 +596,7 @@ pub fn prepare_sprites(
         sprite_meta.view_bind_group = Some(render_device.create_bind_group(
             "sprite_view_bind_group",
             &sprite_pipeline.view_layout,
-            &BindGroupEntries::single(view_binding),
+            &BindGroupEntries::sequential((view_binding, &blue_noise.texture_view)),
         ));
 
         // Index buffer indices
@@ -1,13 +1,17 @@
 #ifdef TONEMAP_IN_SHADER
 #import bevy_core_pipeline::tonemapping
+#else ifdef DEBAND_DITHER
+#import bevy_core_pipeline::tonemapping
 #endif
 
 #import bevy_render::{
+    blue_noise::blue_noise_load,
     maths::affine3_to_square,
     view::View,
 }
 
 @group(0) @binding(0) var<uniform> view: View;
+@group(0) @binding(1) var blue_noise_texture: texture_2d<f32>;
 
 struct VertexInput {
     @builtin(vertex_index) index: u32,
@@ -58,6 +62,18 @@ fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
 #ifdef TONEMAP_IN_SHADER
     color = tonemapping::tone_mapping(color, view.color_grading);
 #endif
+#ifdef DEBAND_DITHER
+    color = vec4(
+        tonemapping::dither_srgb(
+            color.rgb,
+            in.clip_position.xy,
+            blue_noise_load(blue_noise_texture, vec2<u32>(in.clip_position.xy)),
+            view.dither_pattern,
+            view.dither_strength,
+        ),
+        color.a
+    );
+#endif
 
     return color;
 }
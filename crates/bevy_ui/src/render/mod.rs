@@ -36,9 +36,9 @@ use bevy_render::{
     render_graph::{RenderGraph, RunGraphOnViewNode},
     render_phase::{sort_phase_system, AddRenderCommand, DrawFunctions, SortedRenderPhase},
     render_resource::*,
-    renderer::{RenderDevice, RenderQueue},
+    renderer::{RenderAdapter, RenderDevice, RenderQueue},
     texture::Image,
-    view::{ExtractedView, ViewUniforms},
+    view::{ExtractedView, HdrFormat, Msaa, ViewUniforms},
     Extract, RenderApp, RenderSet,
 };
 use bevy_sprite::TextureAtlasLayout;
@@ -658,7 +658,10 @@ pub struct DefaultCameraView(pub Entity);
 pub fn extract_default_ui_camera_view<T: Component>(
     mut commands: Commands,
     ui_scale: Extract<Res<UiScale>>,
+    msaa: Extract<Res<Msaa>>,
     query: Extract<Query<(Entity, &Camera), With<T>>>,
+    render_device: Res<RenderDevice>,
+    render_adapter: Res<RenderAdapter>,
 ) {
     let scale = ui_scale.0.recip();
     for (entity, camera) in &query {
@@ -698,6 +701,13 @@ pub fn extract_default_ui_camera_view<T: Component>(
                     ),
                     view_projection: None,
                     hdr: camera.hdr,
+                    hdr_format: if camera.hdr {
+                        camera
+                            .hdr_format
+                            .or_supported(&render_device, &render_adapter, **msaa)
+                    } else {
+                        HdrFormat::default()
+                    },
                     viewport: UVec4::new(
                         physical_origin.x,
                         physical_origin.y,
@@ -893,7 +903,10 @@ pub fn queue_uinodes(
         let pipeline = pipelines.specialize(
             &pipeline_cache,
             &ui_pipeline,
-            UiPipelineKey { hdr: view.hdr },
+            UiPipelineKey {
+                hdr: view.hdr,
+                hdr_format: view.hdr_format,
+            },
         );
         transparent_phase.add(TransparentUi {
             draw_function,
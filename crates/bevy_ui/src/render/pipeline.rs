@@ -5,8 +5,7 @@ use bevy_render::{
         *,
     },
     renderer::RenderDevice,
-    texture::BevyDefault,
-    view::{ViewTarget, ViewUniform},
+    view::{HdrFormat, ViewTarget, ViewUniform},
 };
 
 #[derive(Resource)]
@@ -48,6 +47,7 @@ impl FromWorld for UiPipeline {
 #[derive(Clone, Copy, Hash, PartialEq, Eq)]
 pub struct UiPipelineKey {
     pub hdr: bool,
+    pub hdr_format: HdrFormat,
 }
 
 impl SpecializedRenderPipeline for UiPipeline {
@@ -87,11 +87,7 @@ impl SpecializedRenderPipeline for UiPipeline {
                 shader_defs,
                 entry_point: "fragment".into(),
                 targets: vec![Some(ColorTargetState {
-                    format: if key.hdr {
-                        ViewTarget::TEXTURE_FORMAT_HDR
-                    } else {
-                        TextureFormat::bevy_default()
-                    },
+                    format: ViewTarget::main_texture_format_for(key.hdr, key.hdr_format),
                     blend: Some(BlendState::ALPHA_BLENDING),
                     write_mask: ColorWrites::ALL,
                 })],
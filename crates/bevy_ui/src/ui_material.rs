@@ -1,7 +1,10 @@
 use std::hash::Hash;
 
 use bevy_asset::Asset;
-use bevy_render::render_resource::{AsBindGroup, RenderPipelineDescriptor, ShaderRef};
+use bevy_render::{
+    render_resource::{AsBindGroup, RenderPipelineDescriptor, ShaderRef},
+    view::HdrFormat,
+};
 
 /// Materials are used alongside [`UiMaterialPlugin`](crate::UiMaterialPlugin) and [`MaterialNodeBundle`](crate::prelude::MaterialNodeBundle)
 /// to spawn entities that are rendered with a specific [`UiMaterial`] type. They serve as an easy to use high level
@@ -110,6 +113,7 @@ pub trait UiMaterial: AsBindGroup + Asset + Clone + Sized {
 
 pub struct UiMaterialKey<M: UiMaterial> {
     pub hdr: bool,
+    pub hdr_format: HdrFormat,
     pub bind_group_data: M::Data,
 }
 
@@ -120,7 +124,9 @@ where
     M::Data: PartialEq,
 {
     fn eq(&self, other: &Self) -> bool {
-        self.hdr == other.hdr && self.bind_group_data == other.bind_group_data
+        self.hdr == other.hdr
+            && self.hdr_format == other.hdr_format
+            && self.bind_group_data == other.bind_group_data
     }
 }
 
@@ -131,6 +137,7 @@ where
     fn clone(&self) -> Self {
         Self {
             hdr: self.hdr,
+            hdr_format: self.hdr_format,
             bind_group_data: self.bind_group_data.clone(),
         }
     }
@@ -142,6 +149,7 @@ where
 {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         self.hdr.hash(state);
+        self.hdr_format.hash(state);
         self.bind_group_data.hash(state);
     }
 }
@@ -152,6 +152,7 @@ fn spawn_reflection_probe(commands: &mut Commands, cubemaps: &Cubemaps) {
             specular_map: cubemaps.specular_reflection_probe.clone(),
             intensity: 5000.0,
         },
+        parallax_correction: ParallaxCorrection::default(),
     });
 }
 
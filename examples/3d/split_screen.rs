@@ -1,22 +1,30 @@
-//! Renders two cameras to the same window to accomplish "split screen".
+//! Renders four cameras to the same window to accomplish "split screen".
+//!
+//! The viewports of the cameras are laid out by a [`SplitScreenLayout`] on the window, and
+//! follow its size. Press space to switch between a grid, horizontal and vertical arrangement.
 
 use std::f32::consts::PI;
 
 use bevy::{
-    pbr::CascadeShadowConfigBuilder, prelude::*, render::camera::Viewport, window::WindowResized,
+    core_pipeline::tonemapping::Tonemapping,
+    pbr::CascadeShadowConfigBuilder,
+    prelude::*,
+    render::camera::{SplitScreenArrangement, SplitScreenLayout},
+    window::PrimaryWindow,
 };
 
 fn main() {
     App::new()
         .add_plugins(DefaultPlugins)
         .add_systems(Startup, setup)
-        .add_systems(Update, (set_camera_viewports, button_system))
+        .add_systems(Update, (switch_arrangement, button_system))
         .run();
 }
 
 /// set up a simple 3D scene
 fn setup(
     mut commands: Commands,
+    window: Query<Entity, With<PrimaryWindow>>,
     asset_server: Res<AssetServer>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
@@ -51,38 +59,31 @@ fn setup(
     });
 
     // Cameras and their dedicated UI
-    for (index, (camera_name, camera_pos)) in [
-        ("Player 1", Vec3::new(0.0, 200.0, -150.0)),
-        ("Player 2", Vec3::new(150.0, 150., 50.0)),
-        ("Player 3", Vec3::new(100.0, 150., -150.0)),
-        ("Player 4", Vec3::new(-100.0, 80., 150.0)),
-    ]
-    .iter()
-    .enumerate()
-    {
+    let mut cameras = Vec::new();
+    for (camera_name, camera_pos, tonemapping) in [
+        (
+            "Player 1",
+            Vec3::new(0.0, 200.0, -150.0),
+            Tonemapping::TonyMcMapface,
+        ),
+        ("Player 2", Vec3::new(150.0, 150., 50.0), Tonemapping::AgX),
+        (
+            "Player 3",
+            Vec3::new(100.0, 150., -150.0),
+            Tonemapping::BlenderFilmic,
+        ),
+        ("Player 4", Vec3::new(-100.0, 80., 150.0), Tonemapping::None),
+    ] {
+        // The split screen layout takes care of the viewport, order and clear color of the
+        // cameras, while post-processing settings like tonemapping stay specific to each player.
         let camera = commands
-            .spawn((
-                Camera3dBundle {
-                    transform: Transform::from_translation(*camera_pos)
-                        .looking_at(Vec3::ZERO, Vec3::Y),
-                    camera: Camera {
-                        // Renders cameras with different priorities to prevent ambiguities
-                        order: index as isize,
-                        // Don't clear after the first camera because the first camera already cleared the entire window
-                        clear_color: if index > 0 {
-                            ClearColorConfig::None
-                        } else {
-                            ClearColorConfig::default()
-                        },
-                        ..default()
-                    },
-                    ..default()
-                },
-                CameraPosition {
-                    pos: UVec2::new((index % 2) as u32, (index / 2) as u32),
-                },
-            ))
+            .spawn(Camera3dBundle {
+                transform: Transform::from_translation(camera_pos).looking_at(Vec3::ZERO, Vec3::Y),
+                tonemapping,
+                ..default()
+            })
             .id();
+        cameras.push(camera);
 
         // Set up UI
         commands
@@ -100,7 +101,7 @@ fn setup(
             ))
             .with_children(|parent| {
                 parent.spawn(TextBundle::from_section(
-                    *camera_name,
+                    camera_name,
                     TextStyle {
                         font_size: 20.,
                         ..default()
@@ -110,6 +111,10 @@ fn setup(
             });
     }
 
+    commands
+        .entity(window.single())
+        .insert(SplitScreenLayout::new(SplitScreenArrangement::Grid, cameras).with_gap(4));
+
     fn buttons_panel(parent: &mut ChildBuilder) {
         parent
             .spawn(NodeBundle {
@@ -162,11 +167,6 @@ fn setup(
     }
 }
 
-#[derive(Component)]
-struct CameraPosition {
-    pos: UVec2,
-}
-
 #[derive(Component)]
 struct RotateCamera(Direction);
 
@@ -175,25 +175,19 @@ enum Direction {
     Right,
 }
 
-fn set_camera_viewports(
-    windows: Query<&Window>,
-    mut resize_events: EventReader<WindowResized>,
-    mut query: Query<(&CameraPosition, &mut Camera)>,
+fn switch_arrangement(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut layouts: Query<&mut SplitScreenLayout>,
 ) {
-    // We need to dynamically resize the camera's viewports whenever the window size changes
-    // so then each camera always takes up half the screen.
-    // A resize_event is sent when the window is first created, allowing us to reuse this system for initial setup.
-    for resize_event in resize_events.read() {
-        let window = windows.get(resize_event.window).unwrap();
-        let size = window.physical_size() / 2;
-
-        for (camera_position, mut camera) in &mut query {
-            camera.viewport = Some(Viewport {
-                physical_position: camera_position.pos * size,
-                physical_size: size,
-                ..default()
-            });
-        }
+    if !keyboard.just_pressed(KeyCode::Space) {
+        return;
+    }
+    for mut layout in &mut layouts {
+        layout.arrangement = match layout.arrangement {
+            SplitScreenArrangement::Grid => SplitScreenArrangement::Horizontal,
+            SplitScreenArrangement::Horizontal => SplitScreenArrangement::Vertical,
+            SplitScreenArrangement::Vertical => SplitScreenArrangement::Grid,
+        };
     }
 }
 
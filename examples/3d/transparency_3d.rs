@@ -79,6 +79,18 @@ fn setup(
         ..default()
     });
 
+    // Transparent cube, uses `alpha_mode: Hashed`
+    commands.spawn(PbrBundle {
+        mesh: meshes.add(Cuboid::default()),
+        material: materials.add(StandardMaterial {
+            base_color: Color::srgba(1.0, 0.5, 0.5, 0.0),
+            alpha_mode: AlphaMode::Hashed,
+            ..default()
+        }),
+        transform: Transform::from_xyz(1.5, 0.5, 0.0),
+        ..default()
+    });
+
     // Opaque sphere
     commands.spawn(PbrBundle {
         mesh: meshes.add(Sphere::new(0.5).mesh().ico(3).unwrap()),
@@ -115,6 +127,8 @@ fn setup(
 ///   samples in use. For example, assuming 8xMSAA, the object will be
 ///   completely opaque, then will be 7/8 opaque (1/8 transparent), then will be
 ///   6/8 opaque, then 5/8, etc.
+/// - [`Hashed`](AlphaMode::Hashed): Object fades in and out as a noise pattern,
+///   drawing a fraction of its pixels proportional to the alpha value.
 pub fn fade_transparency(time: Res<Time>, mut materials: ResMut<Assets<StandardMaterial>>) {
     let alpha = (time.elapsed_seconds().sin() / 2.0) + 0.5;
     for (_, material) in materials.iter_mut() {
@@ -0,0 +1,129 @@
+//! A test to confirm that GPU picking sees skinned and morphed meshes where they are drawn, not
+//! in their bind pose.
+//! This is run in CI to ensure that this doesn't regress again.
+use bevy::{
+    pbr::picking::GpuPickingResults,
+    prelude::*,
+    render::{
+        mesh::morph::{MeshMorphWeights, MorphAttributes, MorphTargetImage},
+        mesh::skinning::SkinnedMesh,
+        render_asset::RenderAssetUsages,
+    },
+};
+
+/// How far the skinned mesh and the morphed mesh are moved away from their bind pose.
+const OFFSET: f32 = 2.0;
+
+/// How many frames to wait for a successful pick before failing.
+const MAX_FRAMES: u32 = 300;
+
+fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins)
+        .add_systems(Startup, setup)
+        .add_systems(Update, (move_skinned_mesh, check_picks))
+        .run();
+}
+
+#[derive(Component)]
+struct Expected;
+
+fn setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    commands.spawn((
+        Camera3dBundle {
+            transform: Transform::from_xyz(0.0, 0.0, 10.0).looking_at(Vec3::ZERO, Vec3::Y),
+            ..default()
+        },
+        GpuPicking::default(),
+    ));
+
+    commands.spawn(SceneBundle {
+        scene: asset_server.load("models/SimpleSkin/SimpleSkin.gltf#Scene0"),
+        transform: Transform::from_xyz(-3.0, -1.0, 0.0),
+        ..default()
+    });
+
+    // A cube with a single morph target that moves it up.
+    let cube = Mesh::from(Cuboid::default());
+    let vertex_count = cube.count_vertices();
+    let morph_target =
+        (0..vertex_count).map(|_| MorphAttributes::new(Vec3::Y * OFFSET, Vec3::ZERO, Vec3::ZERO));
+    let morph_targets = MorphTargetImage::new(
+        std::iter::once(morph_target),
+        vertex_count,
+        RenderAssetUsages::RENDER_WORLD,
+    )
+    .unwrap();
+    commands.spawn((
+        PbrBundle {
+            mesh: meshes.add(cube.with_morph_targets(images.add(morph_targets.0))),
+            material: materials.add(Color::WHITE),
+            transform: Transform::from_xyz(3.0, -1.0, 0.0),
+            ..default()
+        },
+        MeshMorphWeights::new(vec![1.0]).unwrap(),
+        Expected,
+    ));
+}
+
+/// Moves the root joint of the skinned mesh, so that it's drawn away from its bind pose.
+fn move_skinned_mesh(
+    mut commands: Commands,
+    skinned_meshes: Query<(Entity, &SkinnedMesh), Without<Expected>>,
+    mut transforms: Query<&mut Transform>,
+) {
+    for (entity, skinned_mesh) in &skinned_meshes {
+        if let Ok(mut transform) = transforms.get_mut(skinned_mesh.joints[0]) {
+            transform.translation.y += OFFSET;
+            commands.entity(entity).insert(Expected);
+        }
+    }
+}
+
+fn check_picks(
+    camera: Query<(&Camera, &GlobalTransform), With<GpuPicking>>,
+    expected: Query<(Entity, &GlobalTransform, Option<&SkinnedMesh>), With<Expected>>,
+    joints: Query<&GlobalTransform>,
+    picking: GpuPickingResults,
+    mut frames: Local<u32>,
+    mut exit: EventWriter<AppExit>,
+) {
+    *frames += 1;
+    let (camera, camera_transform) = camera.single();
+
+    let mut picked = 0;
+    for (entity, transform, skinned_mesh) in &expected {
+        // The skinned mesh is a strip to the right of its moved root joint, and the morphed cube
+        // is drawn above its transform.
+        let drawn_position = match skinned_mesh {
+            Some(skinned_mesh) => joints
+                .get(skinned_mesh.joints[0])
+                .map(|joint| joint.translation() + Vec3::X * 0.5)
+                .unwrap_or_default(),
+            None => transform.translation() + Vec3::Y * OFFSET,
+        };
+        let (Some(position), Some(scale_factor)) = (
+            camera.world_to_viewport(camera_transform, drawn_position),
+            camera.target_scaling_factor(),
+        ) else {
+            continue;
+        };
+        let cursor = (position * scale_factor).as_uvec2();
+        if picking.pick(cursor) == Some(entity) {
+            picked += 1;
+        }
+    }
+
+    if picked == 2 {
+        info!("Skinned and morphed meshes were picked where they are drawn");
+        exit.send(AppExit::Success);
+    } else if *frames > MAX_FRAMES {
+        panic!("GPU picking didn't find the skinned and morphed meshes where they are drawn");
+    }
+}